@@ -16,11 +16,25 @@ from pathlib import Path
 from typing import Any, Literal
 from uuid import UUID, uuid4
 
-from fastapi import Depends, FastAPI, Header, HTTPException
+
+def report_phase(phase: str, pct: int, label: str | None = None) -> None:
+    """Startup progress for the desktop, which reads stdout until LITECLAW_READY."""
+    payload = {"phase": phase, "pct": pct, **({"label": label} if label else {})}
+    print(f"LITECLAW_PHASE {json.dumps(payload)}", flush=True)
+
+
+report_phase("importing", 10, "Loading libraries")
+
+from fastapi import Depends, FastAPI, Header, HTTPException, Request
 from fastapi.middleware.cors import CORSMiddleware
-from pydantic import BaseModel, Field
+from fastapi.routing import APIRoute
+from pydantic import BaseModel, Field, model_validator
 
 APP_VERSION = "0.1.0-mvp"
+# Newest and oldest desktop API versions served, reported by /v1/health so the
+# desktop can negotiate. 2: /v1/config/reload sends folders as objects.
+API_VERSION = 2
+MIN_API_VERSION = 1
 ROUTER_CONFIDENCE_THRESHOLD = 0.70
 SHELL_CONFIDENCE_THRESHOLD = 0.80
 DEFAULT_SHELL_TIMEOUT_MS = 10_000
@@ -74,6 +88,10 @@ class RouterPlanRequest(BaseModel):
     prompt: str = Field(min_length=1)
     allowed_folders: list[str] = Field(default_factory=list)
     dry_run: bool = True
+    conversation_id: str | None = None
+    # The conversation's bound folder; file steps are scoped to it when it is
+    # one of allowed_folders.
+    working_folder: str | None = None
 
 
 class IssueTokenRequest(BaseModel):
@@ -157,9 +175,53 @@ class ShellConfig(BaseModel):
 
 class AppConfig(BaseModel):
     allowed_folders: list[str] = Field(default_factory=list)
+    folder_aliases: dict[str, str] = Field(default_factory=dict)
+    # Gitignore-syntax patterns per folder path, hidden from every walk.
+    folder_ignore_patterns: dict[str, list[str]] = Field(default_factory=dict)
+    respect_gitignore: bool = False
     shell: ShellConfig = Field(default_factory=ShellConfig)
     history_enabled: bool = True
 
+    @model_validator(mode="before")
+    @classmethod
+    def flatten_folder_entries(cls, data: Any) -> Any:
+        # The desktop stores folders as objects ({"path", "alias", ...});
+        # older configs use plain strings. Keep paths flat for scope checks.
+        if not isinstance(data, dict):
+            return data
+        entries = data.get("allowed_folders") or []
+        paths: list[str] = []
+        aliases: dict[str, str] = dict(data.get("folder_aliases") or {})
+        ignores: dict[str, list[str]] = dict(data.get("folder_ignore_patterns") or {})
+        for entry in entries:
+            if isinstance(entry, dict):
+                path = entry.get("path")
+                if not path:
+                    continue
+                paths.append(path)
+                if entry.get("alias"):
+                    aliases[path] = entry["alias"]
+                if entry.get("ignore_patterns"):
+                    ignores[path] = list(entry["ignore_patterns"])
+            else:
+                paths.append(entry)
+        return {
+            **data,
+            "allowed_folders": paths,
+            "folder_aliases": aliases,
+            "folder_ignore_patterns": ignores,
+        }
+
+
+class ConfigReloadRequest(BaseModel):
+    config_generation: int | None = None
+    # Sent instead of reading config.json while the desktop keeps it encrypted.
+    config: dict[str, Any] | None = None
+    # App-owned dirs no allowed folder grants: every profile, not just ours.
+    excluded_paths: list[str] | None = None
+    # Always sent by desktops that know it; null clears the preference.
+    locale: str | None = None
+
 
 class ModelEntry(BaseModel):
     model_id: str
@@ -168,9 +230,17 @@ class ModelEntry(BaseModel):
     status: Literal["registered", "download_stubbed"] = "registered"
 
 
+class ModelSelection(BaseModel):
+    provider: str = Field(min_length=1)
+    model_id: str = Field(min_length=1)
+    params: dict[str, Any] = Field(default_factory=dict)
+
+
 class ModelsState(BaseModel):
     installed_models: list[ModelEntry] = Field(default_factory=list)
     default_model_id: str | None = None
+    # Set from the desktop's model settings; `None` until chosen there.
+    selection: ModelSelection | None = None
 
 
 class ModelDownloadRequest(BaseModel):
@@ -233,16 +303,55 @@ stored_plans: dict[UUID, Plan] = {}
 approval_lock = threading.Lock()
 config_lock = threading.Lock()
 current_config = AppConfig()
+# Generation of the desktop's config.json that was last applied; the desktop
+# compares it with its own counter to detect a stale backend.
+applied_config_generation = int(os.environ.get("LITECLAW_CONFIG_GENERATION", "0") or 0)
+# Last `excluded_paths` the desktop sent; see `get_excluded_dirs`.
+desktop_excluded_paths: list[str] = []
+# BCP-47 tag the user wants answers in, from the desktop's config; `None`
+# leaves the language to the model.
+current_locale: str | None = os.environ.get("LITECLAW_LOCALE") or None
 models_lock = threading.Lock()
 current_models = ModelsState()
+# Milliseconds from the desktop's spawn (LITECLAW_SPAWNED_AT_MS) to the end of
+# startup, interpreter launch and imports included; reported by /v1/health.
+init_ms: int | None = None
+# Requests being handled, health checks aside; the desktop waits for this to
+# reach zero before stopping a backend it has replaced.
+in_flight_lock = threading.Lock()
+in_flight_requests = 0
+# Task executions under way, also under `in_flight_lock`; the model is not
+# switched while one runs.
+running_tasks = 0
+
+
+def apply_thread_limit() -> None:
+    """Caps the worker threads sync endpoints run on (LITECLAW_MAX_THREADS)."""
+    raw = os.environ.get("LITECLAW_MAX_THREADS", "")
+    if not raw.isdigit() or int(raw) < 1:
+        return
+    import anyio.to_thread
+
+    anyio.to_thread.current_default_thread_limiter().total_tokens = int(raw)
 
 
 @asynccontextmanager
 async def lifespan(_: FastAPI):
+    apply_thread_limit()
+    report_phase("loading-config", 60, "Loading settings")
     reload_config()
+    report_phase("loading-models", 75, "Loading models")
     reload_models()
+    report_phase("preparing-tasks", 90, "Preparing task store")
     ensure_task_store()
     backend_log_path().parent.mkdir(parents=True, exist_ok=True)
+    global init_ms
+    spawned_at = os.environ.get("LITECLAW_SPAWNED_AT_MS", "")
+    if spawned_at.isdigit():
+        init_ms = max(0, int(time.time() * 1000) - int(spawned_at))
+    # The desktop shell watches stdout for this line before polling /v1/health.
+    port = int(os.environ.get("LITECLAW_PORT", "8765"))
+    print(f"LITECLAW_READY {json.dumps({'port': port})}", flush=True)
     yield
 
 
@@ -256,6 +365,23 @@ app.add_middleware(
 )
 
 
+@app.middleware("http")
+async def count_in_flight(request: Request, call_next):
+    global in_flight_requests, running_tasks
+    if request.url.path == "/v1/health":
+        return await call_next(request)
+    task = request.url.path == "/v1/tasks/execute"
+    with in_flight_lock:
+        in_flight_requests += 1
+        running_tasks += int(task)
+    try:
+        return await call_next(request)
+    finally:
+        with in_flight_lock:
+            in_flight_requests -= 1
+            running_tasks -= int(task)
+
+
 def require_bearer(authorization: str | None = Header(default=None)) -> None:
     if not authorization:
         raise HTTPException(status_code=401, detail="Missing authorization header")
@@ -264,8 +390,18 @@ def require_bearer(authorization: str | None = Header(default=None)) -> None:
         raise HTTPException(status_code=401, detail="Invalid bearer token")
 
 
+def config_dir() -> Path:
+    # Split out on Linux (XDG); older desktops only pass the data dir. Read on
+    # each call so it follows DATA_DIR when the environment does not set it.
+    return Path(os.environ.get("LITECLAW_CONFIG_DIR") or DATA_DIR)
+
+
+def cache_dir() -> Path:
+    return Path(os.environ.get("LITECLAW_CACHE_DIR") or DATA_DIR / "cache")
+
+
 def config_path() -> Path:
-    return DATA_DIR / "config.json"
+    return config_dir() / "config.json"
 
 
 def models_registry_path() -> Path:
@@ -281,11 +417,12 @@ def task_index_path() -> Path:
 
 
 def backend_log_path() -> Path:
-    return DATA_DIR / "logs" / "backend.log"
+    return cache_dir() / "logs" / "backend.log"
 
 
 def write_default_config_if_missing() -> None:
     DATA_DIR.mkdir(parents=True, exist_ok=True)
+    config_dir().mkdir(parents=True, exist_ok=True)
     path = config_path()
     if path.exists():
         return
@@ -419,6 +556,10 @@ def load_config_from_disk() -> AppConfig:
         raise HTTPException(
             status_code=500, detail=f"Invalid config JSON: {exc}"
         ) from exc
+    if isinstance(raw, dict) and raw.get("encrypted") is True:
+        # Encrypted at rest; the desktop pushes the config on reload. Until
+        # then nothing is allowed.
+        return AppConfig()
     return AppConfig(**raw)
 
 
@@ -580,7 +721,9 @@ def redact_line(line: str, redact_paths: bool) -> str:
     if not redact_paths:
         return line
     rewritten = line
+    rewritten = rewritten.replace(str(cache_dir().resolve()), "{{CACHE_DIR}}")
     rewritten = rewritten.replace(str(DATA_DIR.resolve()), "{{DATA_DIR}}")
+    rewritten = rewritten.replace(str(config_dir().resolve()), "{{CONFIG_DIR}}")
     for idx, folder in enumerate(get_config_snapshot().allowed_folders, start=1):
         marker = f"{{{{ALLOWED_FOLDER_{idx}}}}}"
         try:
@@ -691,9 +834,12 @@ def build_plan(request: RouterPlanRequest) -> Plan:
     lowered = prompt.lower()
     plan_id = uuid4()
     created_at = iso(now_utc())
-    base_folder = (
-        request.allowed_folders[0] if request.allowed_folders else str(Path.cwd())
-    )
+    if request.working_folder and request.working_folder in request.allowed_folders:
+        base_folder = request.working_folder
+    else:
+        base_folder = (
+            request.allowed_folders[0] if request.allowed_folders else str(Path.cwd())
+        )
 
     router_confidence, should_route_file_search = detect_file_search_confidence(prompt)
     shell_confidence, should_route_shell_exec = detect_shell_exec_confidence(prompt)
@@ -930,6 +1076,21 @@ def is_blocked_path(candidate: Path) -> bool:
     return any(within_path(resolved, blocked) for blocked in get_blocked_paths())
 
 
+def get_excluded_dirs() -> list[Path]:
+    """App-owned dirs that stay out of every allowed folder, even one above
+    them. Our own dirs are always known; the desktop adds the other profiles'
+    on each reload."""
+    with config_lock:
+        sent = list(desktop_excluded_paths)
+    dirs = [DATA_DIR, config_dir(), cache_dir(), *map(Path, sent)]
+    return [path.resolve() for path in dirs]
+
+
+def is_excluded_path(candidate: Path) -> bool:
+    resolved = candidate.resolve()
+    return any(within_path(resolved, excluded) for excluded in get_excluded_dirs())
+
+
 def get_allowed_read_roots(plan: Plan) -> list[Path]:
     roots: list[Path] = []
     for permission in plan.required_permissions:
@@ -938,9 +1099,17 @@ def get_allowed_read_roots(plan: Plan) -> list[Path]:
     return roots
 
 
+def attachments_dir() -> Path:
+    return DATA_DIR / "attachments"
+
+
 def get_config_allowed_roots() -> list[Path]:
     config = get_config_snapshot()
-    return [Path(folder).resolve() for folder in config.allowed_folders]
+    roots = [Path(folder).resolve() for folder in config.allowed_folders]
+    # Files staged by the desktop for chat attachments are always readable.
+    if attachments_dir().is_dir():
+        roots.append(attachments_dir().resolve())
+    return roots
 
 
 def ensure_file_read_scope(target_path: Path, allowed_roots: list[Path]) -> None:
@@ -958,6 +1127,11 @@ def ensure_file_read_scope(target_path: Path, allowed_roots: list[Path]) -> None
             status_code=403,
             detail=f"Path is outside configured allowed folders: {resolved}",
         )
+    staged = attachments_dir().is_dir() and within_path(resolved, attachments_dir())
+    if is_excluded_path(resolved) and not staged:
+        raise HTTPException(
+            status_code=403, detail=f"Path belongs to the app's own data: {resolved}"
+        )
     if not allowed_roots:
         raise HTTPException(
             status_code=403, detail="No allowed file read roots configured"
@@ -1007,6 +1181,101 @@ def make_snippet(text: str, query: str, max_snippet_chars: int) -> str:
     return snippet[:max_snippet_chars]
 
 
+IgnoreRule = tuple[re.Pattern[str], bool, bool]
+
+
+def compile_ignore_pattern(pattern: str) -> IgnoreRule | None:
+    """Gitignore line to (regex, negated, directory_only); the desktop
+    validates the same syntax with the Rust `ignore` crate."""
+    pattern = pattern.strip()
+    if not pattern or pattern.startswith("#"):
+        return None
+    negated = pattern.startswith("!")
+    if negated:
+        pattern = pattern[1:]
+    directory_only = pattern.endswith("/")
+    pattern = pattern.rstrip("/")
+    anchored = "/" in pattern
+    pattern = pattern.lstrip("/")
+    if not pattern:
+        return None
+    regex = ""
+    i = 0
+    while i < len(pattern):
+        if pattern.startswith("**/", i):
+            regex += "(?:.*/)?"
+            i += 3
+        elif pattern.startswith("**", i):
+            regex += ".*"
+            i += 2
+        elif pattern[i] == "*":
+            regex += "[^/]*"
+            i += 1
+        elif pattern[i] == "?":
+            regex += "[^/]"
+            i += 1
+        elif pattern[i] == "[" and "]" in pattern[i + 1 :]:
+            end = pattern.index("]", i + 1)
+            regex += pattern[i : end + 1].replace("[!", "[^", 1)
+            i = end + 1
+        else:
+            regex += re.escape(pattern[i])
+            i += 1
+    prefix = "" if anchored else "(?:.*/)?"
+    try:
+        return re.compile(f"^{prefix}{regex}$"), negated, directory_only
+    except re.error:
+        return None
+
+
+def folder_ignore_rules(target: Path) -> tuple[Path, list[IgnoreRule]] | None:
+    """The allowed folder containing `target` and its compiled ignore rules."""
+    config = get_config_snapshot()
+    resolved = target.resolve()
+    for folder in config.allowed_folders:
+        root = Path(folder).resolve()
+        if resolved != root and root not in resolved.parents:
+            continue
+        lines = list(config.folder_ignore_patterns.get(folder, []))
+        if config.respect_gitignore:
+            try:
+                lines = (root / ".gitignore").read_text(encoding="utf-8").splitlines() + lines
+            except (OSError, UnicodeDecodeError):
+                pass
+        rules = [rule for rule in map(compile_ignore_pattern, lines) if rule]
+        return root, rules
+    return None
+
+
+def is_ignored_path(path: Path, ignore: tuple[Path, list[IgnoreRule]] | None) -> bool:
+    """True when `path` or any directory above it (inside the folder) matches;
+    as in git, the last matching rule wins and an ignored directory hides
+    everything below it."""
+    if ignore is None:
+        return False
+    if is_excluded_path(path):
+        return True
+    root, rules = ignore
+    if not rules:
+        return False
+    try:
+        parts = path.resolve().relative_to(root).parts
+    except ValueError:
+        return False
+    for depth in range(1, len(parts) + 1):
+        candidate = "/".join(parts[:depth])
+        candidate_is_dir = depth < len(parts) or path.is_dir()
+        ignored = False
+        for regex, negated, directory_only in rules:
+            if directory_only and not candidate_is_dir:
+                continue
+            if regex.match(candidate):
+                ignored = not negated
+        if ignored:
+            return True
+    return False
+
+
 def matches_glob(relative_path: str, patterns: list[str]) -> bool:
     for pattern in patterns:
         if fnmatch(relative_path, pattern):
@@ -1041,8 +1310,9 @@ def file_search(
     warnings: list[str] = []
     results: list[dict[str, Any]] = []
 
+    ignore = folder_ignore_rules(root_path)
     for file_path in sorted(root_path.rglob("*"), key=lambda p: str(p).lower()):
-        if not file_path.is_file():
+        if not file_path.is_file() or is_ignored_path(file_path, ignore):
             continue
         relative = file_path.relative_to(root_path).as_posix()
         if patterns and not matches_glob(relative, patterns):
@@ -1256,7 +1526,10 @@ def execute_internal_shell(
         ensure_exec_scope(target, plan)
         if not target.exists() or not target.is_dir():
             return "", f"ls target not found: {target}\n", 1, False
-        entries = sorted(item.name for item in target.iterdir())
+        ignore = folder_ignore_rules(target)
+        entries = sorted(
+            item.name for item in target.iterdir() if not is_ignored_path(item, ignore)
+        )
         ensure_not_timed_out()
         return "\n".join(entries) + ("\n" if entries else ""), "", 0, False
 
@@ -1283,7 +1556,12 @@ def execute_internal_shell(
             files = [target]
         elif target.is_dir():
             files = sorted(target.rglob("*")) if recursive else sorted(target.glob("*"))
-            files = [item for item in files if item.is_file()]
+            ignore = folder_ignore_rules(target)
+            files = [
+                item
+                for item in files
+                if item.is_file() and not is_ignored_path(item, ignore)
+            ]
         else:
             return "", f"grep target not found: {target}\n", 1, False
         for file_path in files:
@@ -1305,9 +1583,10 @@ def execute_internal_shell(
         if not root.exists() or not root.is_dir():
             return "", f"find root not found: {root}\n", 1, False
         matches: list[str] = []
+        ignore = folder_ignore_rules(root)
         for item in sorted(root.rglob("*")):
             ensure_not_timed_out()
-            if fnmatch(item.name, pattern):
+            if fnmatch(item.name, pattern) and not is_ignored_path(item, ignore):
                 ensure_exec_scope(item, plan)
                 matches.append(str(item))
         return ("\n".join(matches) + ("\n" if matches else "")), "", 0, False
@@ -1390,7 +1669,30 @@ def execute_shell_step(step: Step, plan: Plan) -> dict[str, Any]:
 
 @app.get("/v1/health", dependencies=[Depends(require_bearer)])
 def get_health() -> dict[str, Any]:
-    return {"status": "ok", "time": iso(now_utc())}
+    with config_lock:
+        generation = applied_config_generation
+        locale = current_locale
+    with in_flight_lock:
+        in_flight = in_flight_requests
+    health: dict[str, Any] = {
+        "status": "ok",
+        "time": iso(now_utc()),
+        "config_generation": generation,
+        "api_version": API_VERSION,
+        "min_api_version": MIN_API_VERSION,
+        "in_flight": in_flight,
+    }
+    if init_ms is not None:
+        health["init_ms"] = init_ms
+    if locale is not None:
+        health["locale"] = locale
+    return health
+
+
+@app.get("/v1/auth/verify", dependencies=[Depends(require_bearer)])
+def get_auth_verify() -> dict[str, bool]:
+    # The desktop's proof after health that this is the backend it spawned.
+    return {"ok": True}
 
 
 @app.get("/v1/version", dependencies=[Depends(require_bearer)])
@@ -1398,6 +1700,16 @@ def get_version() -> dict[str, str]:
     return {"version": APP_VERSION}
 
 
+@app.get("/v1/debug/routes", dependencies=[Depends(require_bearer)])
+def get_debug_routes() -> dict[str, Any]:
+    routes = [
+        {"path": route.path, "methods": sorted(route.methods), "name": route.name}
+        for route in app.routes
+        if isinstance(route, APIRoute)
+    ]
+    return {"routes": sorted(routes, key=lambda route: route["path"])}
+
+
 @app.get(
     "/v1/models", dependencies=[Depends(require_bearer)], response_model=ModelsState
 )
@@ -1461,6 +1773,39 @@ def post_models_set_default(request: ModelSetDefaultRequest) -> ModelsState:
     return get_models_snapshot()
 
 
+@app.get(
+    "/v1/config/model",
+    dependencies=[Depends(require_bearer)],
+    response_model=ModelSelection | None,
+)
+def get_config_model() -> ModelSelection | None:
+    return get_models_snapshot().selection
+
+
+@app.post(
+    "/v1/config/model",
+    dependencies=[Depends(require_bearer)],
+    response_model=ModelSelection,
+)
+def post_config_model(selection: ModelSelection) -> ModelSelection:
+    state = get_models_snapshot()
+    installed = {item.model_id for item in state.installed_models}
+    if selection.provider == "local" and selection.model_id not in installed:
+        raise HTTPException(
+            status_code=404, detail=f"Model not installed: {selection.model_id}"
+        )
+    # Held across the write so no task starts halfway through the switch.
+    with in_flight_lock:
+        if running_tasks > 0:
+            raise HTTPException(status_code=409, detail="generation_in_progress")
+        state.selection = selection
+        with models_lock:
+            global current_models
+            current_models = state
+            write_models_state(current_models)
+    return selection
+
+
 @app.get("/v1/config", dependencies=[Depends(require_bearer)], response_model=AppConfig)
 def get_config() -> AppConfig:
     return get_config_snapshot()
@@ -1471,8 +1816,27 @@ def get_config() -> AppConfig:
     dependencies=[Depends(require_bearer)],
     response_model=AppConfig,
 )
-def post_config_reload() -> AppConfig:
-    return reload_config()
+def post_config_reload(request: ConfigReloadRequest | None = None) -> AppConfig:
+    if request is not None and request.config is not None:
+        config = AppConfig(**request.config)
+        with config_lock:
+            global current_config
+            current_config = config
+    else:
+        config = reload_config()
+    if request is not None and request.excluded_paths is not None:
+        with config_lock:
+            global desktop_excluded_paths
+            desktop_excluded_paths = request.excluded_paths
+    if request is not None and "locale" in request.model_fields_set:
+        with config_lock:
+            global current_locale
+            current_locale = request.locale
+    if request is not None and request.config_generation is not None:
+        with config_lock:
+            global applied_config_generation
+            applied_config_generation = request.config_generation
+    return config
 
 
 @app.get(
@@ -1868,4 +2232,5 @@ if __name__ == "__main__":
     reload_config()
     reload_models()
     port = int(os.environ.get("LITECLAW_PORT", "8765"))
-    uvicorn.run("main:app", host="127.0.0.1", port=port, reload=False)
+    host = os.environ.get("LITECLAW_HOST", "127.0.0.1")
+    uvicorn.run("main:app", host=host, port=port, reload=False)
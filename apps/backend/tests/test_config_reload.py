@@ -88,3 +88,86 @@ def test_config_reload_allows_search_after_folder_added(tmp_path) -> None:
         assert second.status_code == 200, second.text
     finally:
         main.DATA_DIR = previous_data_dir
+
+
+def test_config_reload_accepts_folder_objects_with_aliases(tmp_path) -> None:
+    main.API_TOKEN = TOKEN
+    previous_data_dir = main.DATA_DIR
+    try:
+        main.DATA_DIR = tmp_path
+        main.reload_config()
+        client = authed()
+
+        root = tmp_path / "src"
+        root.mkdir()
+        other = tmp_path / "notes"
+        other.mkdir()
+        cfg_path = tmp_path / "config.json"
+        cfg = json.loads(cfg_path.read_text(encoding="utf-8"))
+        cfg["allowed_folders"] = [
+            {"path": str(root.resolve()), "alias": "api-src", "note": None},
+            str(other.resolve()),
+        ]
+        cfg_path.write_text(json.dumps(cfg, indent=2), encoding="utf-8")
+
+        reload_response = client.post("/v1/config/reload")
+        assert reload_response.status_code == 200
+        body = reload_response.json()
+        assert body["allowed_folders"] == [str(root.resolve()), str(other.resolve())]
+        assert body["folder_aliases"] == {str(root.resolve()): "api-src"}
+    finally:
+        main.DATA_DIR = previous_data_dir
+
+
+def test_staged_attachments_dir_is_an_allowed_root(tmp_path) -> None:
+    previous_data_dir = main.DATA_DIR
+    try:
+        main.DATA_DIR = tmp_path
+        main.reload_config()
+        assert main.get_config_allowed_roots() == []
+
+        staged = tmp_path / "attachments" / "abc" / "notes.txt"
+        staged.parent.mkdir(parents=True)
+        staged.write_text("hello", encoding="utf-8")
+        roots = main.get_config_allowed_roots()
+        assert (tmp_path / "attachments").resolve() in roots
+    finally:
+        main.DATA_DIR = previous_data_dir
+
+
+def test_health_echoes_applied_config_generation(tmp_path) -> None:
+    main.API_TOKEN = TOKEN
+    previous_data_dir = main.DATA_DIR
+    previous_generation = main.applied_config_generation
+    try:
+        main.DATA_DIR = tmp_path
+        client = authed()
+        response = client.post("/v1/config/reload", json={"config_generation": 7})
+        assert response.status_code == 200
+        assert client.get("/v1/health").json()["config_generation"] == 7
+
+        response = client.post("/v1/config/reload")
+        assert response.status_code == 200
+        assert client.get("/v1/health").json()["config_generation"] == 7
+    finally:
+        main.DATA_DIR = previous_data_dir
+        main.applied_config_generation = previous_generation
+
+
+def test_config_and_cache_dirs_follow_data_dir_unless_overridden(tmp_path, monkeypatch) -> None:
+    monkeypatch.delenv("LITECLAW_CONFIG_DIR", raising=False)
+    monkeypatch.delenv("LITECLAW_CACHE_DIR", raising=False)
+    monkeypatch.setattr(main, "DATA_DIR", tmp_path)
+    assert main.config_path() == tmp_path / "config.json"
+    assert main.backend_log_path() == tmp_path / "cache" / "logs" / "backend.log"
+
+    config_dir = tmp_path / "xdg-config"
+    cache_dir = tmp_path / "xdg-cache"
+    monkeypatch.setenv("LITECLAW_CONFIG_DIR", str(config_dir))
+    monkeypatch.setenv("LITECLAW_CACHE_DIR", str(cache_dir))
+    main.write_default_config_if_missing()
+    assert (config_dir / "config.json").exists()
+    assert not (tmp_path / "config.json").exists()
+    assert main.backend_log_path() == cache_dir / "logs" / "backend.log"
+    assert config_dir.resolve() in main.get_excluded_dirs()
+    assert cache_dir.resolve() in main.get_excluded_dirs()
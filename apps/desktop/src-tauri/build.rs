@@ -1,3 +1,41 @@
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Embeds the SHA-256 of each bundled backend file so the shell can refuse
+/// to run a modified copy (see `src/integrity.rs`), plus the bundled
+/// backend's version for `src/backend_update.rs`.
+fn write_backend_manifest() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR"));
+    let backend_dir = manifest_dir.join("..").join("..").join("backend");
+    println!("cargo:rerun-if-changed={}", backend_dir.display());
+    let mut names: Vec<String> = fs::read_dir(&backend_dir)
+        .expect("failed reading backend dir")
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".py"))
+        .collect();
+    names.sort();
+    let mut manifest = String::from("pub const BACKEND_MANIFEST: &[(&str, &str)] = &[\n");
+    for name in names {
+        let bytes = fs::read(backend_dir.join(&name)).expect("failed reading backend file");
+        manifest.push_str(&format!("    ({name:?}, \"{:x}\"),\n", Sha256::digest(&bytes)));
+    }
+    manifest.push_str("];\n");
+    // `APP_VERSION = "x.y.z"` in main.py, compared against update manifests.
+    let main_py = fs::read_to_string(backend_dir.join("main.py")).expect("failed reading main.py");
+    let version = main_py
+        .lines()
+        .find_map(|line| line.strip_prefix("APP_VERSION = "))
+        .map(|value| value.trim().trim_matches('"'))
+        .expect("APP_VERSION missing from main.py");
+    manifest.push_str(&format!("pub const BUNDLED_BACKEND_VERSION: &str = {version:?};\n"));
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR"));
+    fs::write(out_dir.join("backend_manifest.rs"), manifest).expect("failed writing manifest");
+}
+
 fn main() {
+    write_backend_manifest();
     tauri_build::build()
 }
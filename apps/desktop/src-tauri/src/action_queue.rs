@@ -0,0 +1,187 @@
+//! Backend calls made while the backend is down, replayed once it is ready.
+//! Only the idempotent calls named in `QueuedAction` can be queued, so a
+//! replay never does something twice that should happen once; anything else
+//! (token rotation, task execution) still fails immediately. The backend
+//! has one such call today, the config reload. A second action of a kind
+//! replaces the first, which also bounds the queue to one entry per kind.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::api_version::ApiVersion;
+use crate::backend_transport::UreqTransport;
+use crate::error::CommandError;
+use crate::wsl::WslTarget;
+use crate::{post_reload, read_local_config, timestamps, AppState, BackendRuntime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuedAction {
+    ReloadConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingAction {
+    pub id: String,
+    pub action: QueuedAction,
+    pub queued_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ActionCompleted {
+    id: String,
+    action: QueuedAction,
+    ok: bool,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ActionQueue {
+    pending: Mutex<VecDeque<PendingAction>>,
+}
+
+impl ActionQueue {
+    /// Replaces a pending action of the same kind, keeping its place in line.
+    pub fn enqueue(&self, action: QueuedAction) -> Result<PendingAction, CommandError> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|_| "action queue poisoned".to_string())?;
+        let entry = PendingAction {
+            id: Uuid::new_v4().to_string(),
+            action,
+            queued_at: timestamps::now(),
+        };
+        if let Some(existing) = pending.iter_mut().find(|queued| queued.action == action) {
+            *existing = entry.clone();
+            return Ok(entry);
+        }
+        pending.push_back(entry.clone());
+        Ok(entry)
+    }
+
+    pub fn list(&self) -> Vec<PendingAction> {
+        self.pending
+            .lock()
+            .map(|pending| pending.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn cancel(&self, id: &str) -> bool {
+        let Ok(mut pending) = self.pending.lock() else {
+            return false;
+        };
+        let before = pending.len();
+        pending.retain(|queued| queued.id != id);
+        pending.len() != before
+    }
+
+    /// Drops everything, e.g. when the profile the actions targeted is left.
+    pub fn clear(&self) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.clear();
+        }
+    }
+
+    fn take_all(&self) -> Vec<PendingAction> {
+        self.pending
+            .lock()
+            .map(|mut pending| pending.drain(..).collect())
+            .unwrap_or_default()
+    }
+}
+
+struct Target {
+    base_url: String,
+    token: String,
+    data_dir: std::path::PathBuf,
+    wsl: Option<WslTarget>,
+    api: ApiVersion,
+}
+
+fn run(target: &Target, action: QueuedAction) -> Result<(), String> {
+    match action {
+        // Re-read so the reload carries the config as it is now.
+        QueuedAction::ReloadConfig => {
+            let config = read_local_config(&target.data_dir)?;
+            post_reload(
+                &UreqTransport,
+                &target.base_url,
+                &target.token,
+                &target.data_dir,
+                &config,
+                target.wsl.as_ref(),
+                target.api,
+            )
+            .map_err(String::from)
+        }
+    }
+}
+
+fn target(runtime: &BackendRuntime) -> Target {
+    Target {
+        base_url: runtime.base_url.clone(),
+        token: runtime.token.clone(),
+        data_dir: runtime.data_dir.clone(),
+        wsl: runtime.backend_wsl.clone(),
+        api: runtime.backend_api,
+    }
+}
+
+/// Replays queued actions in order on a background thread, emitting
+/// `pending-action-completed` for each. No-op until the backend is ready.
+pub fn flush(app: &AppHandle, runtime: &BackendRuntime) {
+    if !runtime.backend_ready {
+        return;
+    }
+    let actions = runtime.pending_actions.take_all();
+    if actions.is_empty() {
+        return;
+    }
+    let target = target(runtime);
+    let app = app.clone();
+    thread::spawn(move || {
+        for pending in actions {
+            let result = run(&target, pending.action);
+            let _ = app.emit(
+                "pending-action-completed",
+                ActionCompleted {
+                    id: pending.id,
+                    action: pending.action,
+                    ok: result.is_ok(),
+                    error: result.err(),
+                },
+            );
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_pending_actions(state: State<'_, AppState>) -> Result<Vec<PendingAction>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(runtime.pending_actions.list())
+}
+
+#[tauri::command]
+pub fn cancel_pending_action(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<PendingAction>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    if !runtime.pending_actions.cancel(&id) {
+        return Err(CommandError::not_found(format!(
+            "no pending action with id `{id}`"
+        )));
+    }
+    Ok(runtime.pending_actions.list())
+}
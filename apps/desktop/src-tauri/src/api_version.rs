@@ -0,0 +1,231 @@
+//! Which backend API version the desktop speaks to a running backend. Every
+//! request carries `X-LiteClaw-Desktop-Version` (see `backend_http`), and
+//! `/v1/health` reports the newest and oldest API versions the backend
+//! accepts; a backend without `api_version` in its health predates
+//! negotiation and speaks v1 only. Spawning settles on the newest version
+//! both sides accept and stores it in `BackendRuntime::backend_api`, and
+//! payloads whose shape differs between versions pass through the shims
+//! here. No common version, or a payload the backend still rejects with a
+//! 422, is reported as `backend_incompatible` instead of a bare status.
+//!
+//! - v1: `/v1/config/reload` takes `allowed_folders` as plain paths, with
+//!   aliases and ignore patterns in maps keyed by path.
+//! - v2: `allowed_folders` entries are objects carrying their own metadata.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::{CommandError, ErrorCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize)]
+#[serde(into = "u32")]
+pub enum ApiVersion {
+    V1,
+    #[default]
+    V2,
+}
+
+const OLDEST: ApiVersion = ApiVersion::V1;
+const NEWEST: ApiVersion = ApiVersion::V2;
+
+impl ApiVersion {
+    pub fn number(self) -> u32 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    fn from_number(number: u32) -> Option<Self> {
+        match number {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2),
+            _ => None,
+        }
+    }
+}
+
+impl From<ApiVersion> for u32 {
+    fn from(version: ApiVersion) -> Self {
+        version.number()
+    }
+}
+
+pub fn incompatible(message: impl Into<String>) -> CommandError {
+    CommandError::new(ErrorCode::BackendIncompatible, message)
+}
+
+/// Picks the newest version both sides accept from what `/v1/health`
+/// reported.
+pub fn negotiate(
+    api_version: Option<u32>,
+    min_api_version: Option<u32>,
+) -> Result<ApiVersion, CommandError> {
+    let backend_newest = api_version.unwrap_or(1);
+    let backend_oldest = min_api_version.unwrap_or(backend_newest);
+    let chosen = backend_newest.min(NEWEST.number());
+    if chosen < backend_oldest.max(OLDEST.number()) {
+        let advice = if backend_oldest > NEWEST.number() {
+            "update the desktop app"
+        } else {
+            "update the backend"
+        };
+        return Err(incompatible(format!(
+            "backend speaks API v{backend_oldest}-v{backend_newest} but this desktop speaks \
+             v{}-v{}; {advice}",
+            OLDEST.number(),
+            NEWEST.number()
+        )));
+    }
+    ApiVersion::from_number(chosen).ok_or_else(|| incompatible(format!("unknown API v{chosen}")))
+}
+
+/// v2 folder objects as v1 paths plus per-path alias and ignore maps.
+fn folders_to_v1(target: &mut Map<String, Value>) {
+    let Some(Value::Array(entries)) = target.get("allowed_folders") else {
+        return;
+    };
+    let mut paths = Vec::new();
+    let mut aliases = Map::new();
+    let mut ignores = Map::new();
+    for entry in entries {
+        let Some(path) = entry.get("path").and_then(Value::as_str) else {
+            if entry.is_string() {
+                paths.push(entry.clone());
+            }
+            continue;
+        };
+        paths.push(Value::from(path));
+        if let Some(alias) = entry.get("alias").filter(|alias| alias.is_string()) {
+            aliases.insert(path.to_string(), alias.clone());
+        }
+        if let Some(patterns) = entry.get("ignore_patterns").filter(|p| p.is_array()) {
+            ignores.insert(path.to_string(), patterns.clone());
+        }
+    }
+    target.insert("allowed_folders".to_string(), Value::Array(paths));
+    target.insert("folder_aliases".to_string(), Value::Object(aliases));
+    target.insert("folder_ignore_patterns".to_string(), Value::Object(ignores));
+}
+
+/// The `/v1/config/reload` body for `api`, from the v2 payload.
+pub fn reload_body(api: ApiVersion, mut payload: Value) -> Value {
+    if api == ApiVersion::V1 {
+        if let Some(body) = payload.as_object_mut() {
+            folders_to_v1(body);
+        }
+        if let Some(config) = payload.get_mut("config").and_then(Value::as_object_mut) {
+            folders_to_v1(config);
+        }
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heartbeat::HealthInfo;
+    use serde_json::json;
+
+    const HEALTH_LEGACY: &str = r#"{"status":"ok","time":"2026-01-01T00:00:00Z"}"#;
+    const HEALTH_V1: &str = r#"{"status":"ok","api_version":1,"min_api_version":1}"#;
+    const HEALTH_V2: &str = r#"{"status":"ok","api_version":2,"min_api_version":1}"#;
+    const HEALTH_V2_ONLY: &str = r#"{"status":"ok","api_version":2,"min_api_version":2}"#;
+    const HEALTH_V3: &str = r#"{"status":"ok","api_version":3,"min_api_version":1}"#;
+    const HEALTH_V3_ONLY: &str = r#"{"status":"ok","api_version":3,"min_api_version":3}"#;
+
+    fn negotiate_health(body: &str) -> Result<ApiVersion, CommandError> {
+        let health: HealthInfo = serde_json::from_str(body).unwrap();
+        negotiate(health.api_version, health.min_api_version)
+    }
+
+    fn v2_payload() -> Value {
+        json!({
+            "allowed_folders": [
+                {
+                    "path": "/home/a/docs",
+                    "resolved_path": "/home/a/docs",
+                    "alias": "Docs",
+                    "mode": "read_only",
+                    "ignore_patterns": ["*.tmp"],
+                },
+                {
+                    "path": "/home/a/src",
+                    "resolved_path": "/home/a/src",
+                    "alias": null,
+                    "mode": "read_write",
+                    "ignore_patterns": [],
+                },
+            ],
+            "respect_gitignore": true,
+            "config_generation": 7,
+        })
+    }
+
+    #[test]
+    fn negotiates_across_backend_versions() {
+        let matrix = [
+            (HEALTH_LEGACY, Some(ApiVersion::V1)),
+            (HEALTH_V1, Some(ApiVersion::V1)),
+            (HEALTH_V2, Some(ApiVersion::V2)),
+            (HEALTH_V2_ONLY, Some(ApiVersion::V2)),
+            (HEALTH_V3, Some(ApiVersion::V2)),
+            (HEALTH_V3_ONLY, None),
+        ];
+        for (health, expected) in matrix {
+            match (negotiate_health(health), expected) {
+                (Ok(version), Some(expected)) => assert_eq!(version, expected, "{health}"),
+                (Err(err), None) => assert_eq!(err.code, ErrorCode::BackendIncompatible),
+                (result, expected) => panic!("{health}: got {result:?}, expected {expected:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn v2_reload_body_is_unchanged() {
+        assert_eq!(reload_body(ApiVersion::V2, v2_payload()), v2_payload());
+    }
+
+    #[test]
+    fn v1_reload_body_sends_folders_as_paths() {
+        let body = reload_body(ApiVersion::V1, v2_payload());
+        assert_eq!(
+            body,
+            json!({
+                "allowed_folders": ["/home/a/docs", "/home/a/src"],
+                "folder_aliases": { "/home/a/docs": "Docs" },
+                "folder_ignore_patterns": {
+                    "/home/a/docs": ["*.tmp"],
+                    "/home/a/src": [],
+                },
+                "respect_gitignore": true,
+                "config_generation": 7,
+            })
+        );
+    }
+
+    #[test]
+    fn v1_reload_body_converts_pushed_config() {
+        let mut payload = v2_payload();
+        payload["config"] = json!({
+            "allowed_folders": payload["allowed_folders"].clone(),
+            "shell": { "enabled": false },
+        });
+        let body = reload_body(ApiVersion::V1, payload);
+        assert_eq!(
+            body["config"]["allowed_folders"],
+            json!(["/home/a/docs", "/home/a/src"])
+        );
+        assert_eq!(
+            body["config"]["folder_aliases"],
+            json!({ "/home/a/docs": "Docs" })
+        );
+        assert_eq!(body["config"]["shell"], json!({ "enabled": false }));
+    }
+
+    #[test]
+    fn v1_reload_body_keeps_plain_paths() {
+        let body = reload_body(ApiVersion::V1, json!({ "allowed_folders": ["/srv/x"] }));
+        assert_eq!(body["allowed_folders"], json!(["/srv/x"]));
+    }
+}
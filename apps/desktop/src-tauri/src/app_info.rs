@@ -0,0 +1,79 @@
+//! Static facts about this install for about boxes and bug reports.
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::desktop_log::desktop_log_path;
+use crate::error::CommandError;
+use crate::integrations::IntegrationStatus;
+use crate::{backend_cwd, backend_debug, layout, read_local_config, unread, AppState};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppInfo {
+    pub app_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub data_dir: String,
+    /// Holds `config.json`; the data dir itself except on Linux.
+    pub config_dir: String,
+    /// Logs and other regenerable files, safe to leave out of backups.
+    pub cache_dir: String,
+    pub backend_log_path: String,
+    pub desktop_log_path: String,
+    /// Working directory the backend is spawned in.
+    pub backend_cwd: String,
+    /// Working directory the desktop app itself was launched from.
+    pub launch_cwd: Option<String>,
+    pub safe_mode: bool,
+    /// Tray, native dialogs and notifications, and what stands in for each
+    /// one that is missing.
+    pub integrations: IntegrationStatus,
+    /// Whether `backend_debug` commands run; see `backend_debug`.
+    pub developer_mode: bool,
+    /// Assistant answers not yet seen; see `unread`.
+    pub unread_count: u32,
+    /// Language tag the assistant answers in; see `locale`.
+    pub locale: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_app_info(state: State<'_, AppState>) -> Result<AppInfo, CommandError> {
+    let integrations = state
+        .integrations
+        .lock()
+        .map_err(|_| "integrations lock poisoned".to_string())?
+        .clone();
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(AppInfo {
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        data_dir: runtime.data_dir.to_string_lossy().into_owned(),
+        config_dir: layout::config_dir(&runtime.data_dir)
+            .to_string_lossy()
+            .into_owned(),
+        cache_dir: layout::cache_dir(&runtime.data_dir)
+            .to_string_lossy()
+            .into_owned(),
+        backend_log_path: runtime.log_path.clone(),
+        desktop_log_path: desktop_log_path(&runtime.data_dir)
+            .to_string_lossy()
+            .into_owned(),
+        backend_cwd: backend_cwd(&runtime.data_dir)
+            .to_string_lossy()
+            .into_owned(),
+        launch_cwd: std::env::current_dir()
+            .ok()
+            .map(|cwd| cwd.to_string_lossy().into_owned()),
+        safe_mode: runtime.safe_mode,
+        integrations,
+        developer_mode: backend_debug::developer_mode(),
+        unread_count: unread::count(),
+        locale: read_local_config(&runtime.data_dir)
+            .ok()
+            .and_then(|config| config.locale),
+    })
+}
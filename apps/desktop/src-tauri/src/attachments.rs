@@ -0,0 +1,255 @@
+//! Staging area for chat attachments: copies of files the user drops into the
+//! chat, kept under `<data_dir>/attachments/<uuid>/<filename>`. The backend is
+//! always allowed to read this directory.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{commit_config, read_local_config, AppState, LocalConfig};
+
+pub const RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const MAX_SIZE_LIMIT_MB: u64 = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentsConfig {
+    pub max_size_mb: u64,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        Self { max_size_mb: 25 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StagedAttachment {
+    pub id: String,
+    pub file_name: String,
+    pub staged_path: String,
+    pub size: u64,
+    pub mime: &'static str,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub staged_at: u64,
+}
+
+pub fn attachments_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("attachments")
+}
+
+pub fn guess_mime(file_name: &str) -> &'static str {
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "html" | "htm" => "text/html",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "zip" => "application/zip",
+        "py" => "text/x-python",
+        "rs" => "text/x-rust",
+        "js" | "mjs" => "text/javascript",
+        "ts" => "text/x-typescript",
+        _ => "application/octet-stream",
+    }
+}
+
+fn modified_unix(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn describe(id: &str, file: &Path) -> Option<StagedAttachment> {
+    let metadata = fs::metadata(file).ok()?;
+    let file_name = file.file_name()?.to_string_lossy().to_string();
+    Some(StagedAttachment {
+        id: id.to_string(),
+        mime: guess_mime(&file_name),
+        staged_path: file.to_string_lossy().to_string(),
+        file_name,
+        size: metadata.len(),
+        staged_at: modified_unix(file),
+    })
+}
+
+fn new_slot(data_dir: &Path) -> Result<(String, PathBuf), String> {
+    let id = Uuid::new_v4().to_string();
+    let dir = attachments_dir(data_dir).join(&id);
+    fs::create_dir_all(&dir).map_err(|e| format!("failed creating attachment dir: {e}"))?;
+    Ok((id, dir))
+}
+
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("failed encoding image: {e}"))?;
+    writer
+        .write_image_data(rgba)
+        .map_err(|e| format!("failed encoding image: {e}"))?;
+    writer
+        .finish()
+        .map_err(|e| format!("failed encoding image: {e}"))?;
+    Ok(out)
+}
+
+/// Stages in-memory content (e.g. a clipboard image) as a new attachment.
+pub fn stage_bytes(
+    data_dir: &Path,
+    file_name: &str,
+    bytes: &[u8],
+) -> Result<StagedAttachment, String> {
+    let (id, dir) = new_slot(data_dir)?;
+    let dest = dir.join(file_name);
+    if let Err(err) = fs::write(&dest, bytes) {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(format!("failed writing attachment: {err}"));
+    }
+    describe(&id, &dest).ok_or_else(|| "failed reading staged attachment".to_string())
+}
+
+pub fn stage_file(
+    data_dir: &Path,
+    config: &LocalConfig,
+    src: &Path,
+) -> Result<StagedAttachment, CommandError> {
+    let metadata = fs::metadata(src)
+        .map_err(|e| CommandError::not_found(format!("cannot read {}: {e}", src.display())))?;
+    if !metadata.is_file() {
+        return Err(CommandError::invalid_input(format!(
+            "not a file: {}",
+            src.display()
+        )));
+    }
+    let limit = config.attachments.max_size_mb * 1024 * 1024;
+    if metadata.len() > limit {
+        return Err(CommandError::invalid_input(format!(
+            "file is larger than the {} MB attachment limit",
+            config.attachments.max_size_mb
+        )));
+    }
+    let file_name = src
+        .file_name()
+        .ok_or_else(|| CommandError::invalid_input("path has no file name"))?;
+    let (id, dir) = new_slot(data_dir)?;
+    let dest = dir.join(file_name);
+    if let Err(err) = fs::copy(src, &dest) {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(format!("failed copying attachment: {err}").into());
+    }
+    describe(&id, &dest).ok_or_else(|| "failed reading staged attachment".to_string().into())
+}
+
+pub fn list_staged(data_dir: &Path) -> Vec<StagedAttachment> {
+    let Ok(entries) = fs::read_dir(attachments_dir(data_dir)) else {
+        return Vec::new();
+    };
+    let mut staged: Vec<StagedAttachment> = entries
+        .flatten()
+        .filter_map(|slot| {
+            let id = slot.file_name().to_string_lossy().to_string();
+            Uuid::parse_str(&id).ok()?;
+            let file = fs::read_dir(slot.path())
+                .ok()?
+                .flatten()
+                .find(|entry| entry.path().is_file())?;
+            describe(&id, &file.path())
+        })
+        .collect();
+    staged.sort_by_key(|attachment| std::cmp::Reverse(attachment.staged_at));
+    staged
+}
+
+#[tauri::command]
+pub fn stage_attachment(
+    state: State<'_, AppState>,
+    src_path: String,
+) -> Result<StagedAttachment, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let config = read_local_config(&runtime.data_dir)?;
+    stage_file(&runtime.data_dir, &config, Path::new(&src_path))
+}
+
+#[tauri::command]
+pub fn list_staged_attachments(
+    state: State<'_, AppState>,
+) -> Result<Vec<StagedAttachment>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(list_staged(&runtime.data_dir))
+}
+
+#[tauri::command]
+pub fn remove_staged_attachment(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let id = Uuid::parse_str(&id)
+        .map_err(|_| CommandError::invalid_input(format!("invalid attachment id: {id}")))?
+        .to_string();
+    let dir = attachments_dir(&runtime.data_dir).join(&id);
+    if !dir.is_dir() {
+        return Err(CommandError::not_found(format!(
+            "no staged attachment {id}"
+        )));
+    }
+    fs::remove_dir_all(&dir).map_err(|e| format!("failed removing attachment: {e}"))?;
+    Ok(())
+}
+
+pub fn validate_size_limit(max_size_mb: u64) -> Result<(), CommandError> {
+    if max_size_mb == 0 || max_size_mb > MAX_SIZE_LIMIT_MB {
+        return Err(CommandError::invalid_input(format!(
+            "attachment limit must be between 1 and {MAX_SIZE_LIMIT_MB} MB"
+        )));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_attachment_size_limit(
+    state: State<'_, AppState>,
+    max_size_mb: u64,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    validate_size_limit(max_size_mb)?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.attachments.max_size_mb = max_size_mb;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
@@ -0,0 +1,124 @@
+//! Append-only record of user-visible side effects (file deletions, config
+//! changes). One JSON object per line in `logs/audit.log`.
+//!
+//! Entries carry `v`: version 2 writes `ts` as RFC 3339, version 1 (no `v`)
+//! wrote unix seconds. `read_recent` reads both.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{profiles, safe_write, storage, unix_now};
+
+const ENTRY_VERSION: u32 = 2;
+
+fn legacy_version() -> u32 {
+    1
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    v: u32,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    ts: u64,
+    /// Profile active when the entry was written.
+    profile: String,
+    action: &'a str,
+    details: serde_json::Value,
+}
+
+/// An entry as read back, from any version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    #[serde(default = "legacy_version")]
+    pub v: u32,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub ts: u64,
+    #[serde(default)]
+    pub profile: String,
+    pub action: String,
+    #[serde(default)]
+    pub details: serde_json::Value,
+}
+
+pub fn audit_log_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("logs").join("audit.log")
+}
+
+pub fn record(data_dir: &Path, action: &str, details: serde_json::Value) -> Result<(), String> {
+    storage::ensure_available()?;
+    let path = audit_log_path(data_dir);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed creating logs dir: {e}"))?;
+    }
+    let entry = AuditEntry {
+        v: ENTRY_VERSION,
+        ts: unix_now(),
+        profile: profiles::active(),
+        action,
+        details,
+    };
+    let mut line = serde_json::to_string(&entry)
+        .map_err(|e| format!("failed serializing audit entry: {e}"))?;
+    line.push('\n');
+    safe_write::append(&path, line.as_bytes()).map_err(|e| format!("failed writing audit log: {e}"))
+}
+
+/// The last `limit` entries, oldest first. Lines that do not parse are
+/// skipped.
+pub fn read_recent(data_dir: &Path, limit: usize) -> Vec<AuditRecord> {
+    let Ok(content) = fs::read_to_string(audit_log_path(data_dir)) else {
+        return Vec::new();
+    };
+    let mut records: Vec<AuditRecord> = content
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(limit)
+        .collect();
+    records.reverse();
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_and_new_entries_read_back_alike() {
+        let data_dir =
+            std::env::temp_dir().join(format!("liteclaw-audit-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(data_dir.join("logs")).unwrap();
+        let new = AuditEntry {
+            v: ENTRY_VERSION,
+            ts: 1_714_564_801,
+            profile: "default".to_string(),
+            action: "folder_removed",
+            details: serde_json::json!({}),
+        };
+        let content = format!(
+            "{}\n{}\n",
+            r#"{"ts":1714564800,"profile":"default","action":"config_changed","details":{}}"#,
+            serde_json::to_string(&new).unwrap()
+        );
+        fs::write(audit_log_path(&data_dir), content).unwrap();
+
+        let records = read_recent(&data_dir, 10);
+        let read: Vec<(u32, u64, &str)> = records
+            .iter()
+            .map(|record| (record.v, record.ts, record.action.as_str()))
+            .collect();
+        assert_eq!(
+            read,
+            [
+                (1, 1_714_564_800, "config_changed"),
+                (2, 1_714_564_801, "folder_removed")
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&records[0]).unwrap()["ts"],
+            "2024-05-01T12:00:00.000Z"
+        );
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
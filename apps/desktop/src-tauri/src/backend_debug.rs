@@ -0,0 +1,171 @@
+//! Raw backend access for a hidden debug panel: `backend_debug_request`
+//! sends any authenticated request, unlike the `/v1/`-only upload paths,
+//! and `get_backend_routes` lists what the backend serves. Both refuse to
+//! run unless developer mode is on, which debug builds always are and
+//! release builds only with `LITECLAW_DEVELOPER_MODE=1` in the environment;
+//! nothing in config or the frontend can turn it on. Each request is
+//! audited with its method, path and status, never its body.
+
+use serde::Serialize;
+use std::io::Read;
+use std::time::Instant;
+use tauri::State;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{audit, backend_http, AppState};
+
+const DEVELOPER_MODE_ENV: &str = "LITECLAW_DEVELOPER_MODE";
+const METHODS: &[&str] = &["GET", "POST", "PUT", "PATCH", "DELETE"];
+const MAX_REQUEST_BODY: usize = 1024 * 1024;
+const MAX_RESPONSE_BODY: u64 = 4 * 1024 * 1024;
+
+pub fn developer_mode() -> bool {
+    cfg!(debug_assertions) || std::env::var(DEVELOPER_MODE_ENV).is_ok_and(|value| value == "1")
+}
+
+pub fn require_developer_mode() -> Result<(), CommandError> {
+    if developer_mode() {
+        return Ok(());
+    }
+    Err(CommandError::new(
+        ErrorCode::NotAllowed,
+        format!("backend debugging needs {DEVELOPER_MODE_ENV}=1 in release builds"),
+    ))
+}
+
+fn validate_path(path: &str) -> Result<(), CommandError> {
+    let valid = path.starts_with('/')
+        && !path.starts_with("//")
+        && !path.contains("://")
+        && path
+            .chars()
+            .all(|c| c.is_ascii_graphic() && c != '\\' && c != '#');
+    if !valid {
+        return Err(CommandError::invalid_input(format!(
+            "path must be an absolute backend path: {path}"
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+    /// The body was cut at the response size limit.
+    pub truncated: bool,
+}
+
+fn read_response(response: ureq::Response) -> DebugResponse {
+    let status = response.status();
+    let content_type = response.content_type().to_string();
+    let mut bytes = Vec::new();
+    let _ = response
+        .into_reader()
+        .take(MAX_RESPONSE_BODY + 1)
+        .read_to_end(&mut bytes);
+    let truncated = bytes.len() as u64 > MAX_RESPONSE_BODY;
+    bytes.truncate(MAX_RESPONSE_BODY as usize);
+    DebugResponse {
+        status,
+        content_type,
+        body: String::from_utf8_lossy(&bytes).into_owned(),
+        truncated,
+    }
+}
+
+fn send(
+    state: &State<'_, AppState>,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+) -> Result<DebugResponse, CommandError> {
+    let (base_url, token, data_dir) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        if !runtime.backend_ready {
+            return Err(CommandError::new(
+                ErrorCode::BackendUnavailable,
+                "backend is not running",
+            ));
+        }
+        (
+            runtime.base_url.clone(),
+            runtime.token.clone(),
+            runtime.data_dir.clone(),
+        )
+    };
+    let request = backend_http::agent()
+        .request(method, &format!("{base_url}{path}"))
+        .set("Authorization", &format!("Bearer {token}"));
+    let started = Instant::now();
+    let result = match body {
+        Some(body) => request
+            .set("Content-Type", "application/json")
+            .send_string(body),
+        None => request.call(),
+    };
+    backend_http::record(path, started, &result);
+    let response = match result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => Ok(read_response(response)),
+        Err(ureq::Error::Transport(err)) => Err(err.to_string()),
+    };
+    let _ = audit::record(
+        &data_dir,
+        "backend_debug_request",
+        serde_json::json!({
+            "method": method,
+            "path": path,
+            "status": response.as_ref().ok().map(|response| response.status),
+            "error": response.as_ref().err(),
+        }),
+    );
+    response.map_err(|err| {
+        CommandError::new(
+            ErrorCode::BackendUnavailable,
+            format!("backend request failed: {err}"),
+        )
+    })
+}
+
+/// Non-2xx statuses are returned, not raised, so the panel can show them.
+#[tauri::command]
+pub fn backend_debug_request(
+    state: State<'_, AppState>,
+    path: String,
+    method: String,
+    body: Option<String>,
+) -> Result<DebugResponse, CommandError> {
+    require_developer_mode()?;
+    validate_path(&path)?;
+    let method = method.trim().to_ascii_uppercase();
+    if !METHODS.contains(&method.as_str()) {
+        return Err(CommandError::invalid_input(format!(
+            "method must be one of {}",
+            METHODS.join(", ")
+        )));
+    }
+    if body
+        .as_ref()
+        .is_some_and(|body| body.len() > MAX_REQUEST_BODY)
+    {
+        return Err(CommandError::invalid_input(format!(
+            "request body must be at most {MAX_REQUEST_BODY} bytes"
+        )));
+    }
+    send(&state, &method, &path, body.as_deref())
+}
+
+#[tauri::command]
+pub fn get_backend_routes(state: State<'_, AppState>) -> Result<serde_json::Value, CommandError> {
+    require_developer_mode()?;
+    let response = send(&state, "GET", "/v1/debug/routes", None)?;
+    if response.status != 200 {
+        return Err(format!("backend returned HTTP {} for its routes", response.status).into());
+    }
+    serde_json::from_str(&response.body)
+        .map_err(|e| format!("invalid route listing from backend: {e}").into())
+}
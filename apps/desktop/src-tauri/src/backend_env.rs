@@ -0,0 +1,188 @@
+//! The environment the backend is spawned with. It starts empty and gets only
+//! a built-in set of variables the interpreter and TLS stack need, plus the
+//! names the user lists in `env_passthrough` and the fixed values in
+//! `env_extra`, so secrets exported in the user's shell stay out of the child
+//! and its logs. Proxy and `LITECLAW_*` variables are set afterwards by
+//! `spawn_backend` and always win.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::process::Command;
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{commit_config, read_local_config, AppState, BackendRuntime, LocalConfig};
+
+/// Inherited from the desktop process whenever set.
+const BUILTIN_PASSTHROUGH: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "USERNAME",
+    "LOGNAME",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "LANG",
+    "LANGUAGE",
+    "TZ",
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "NO_PROXY",
+    "ALL_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "no_proxy",
+    "all_proxy",
+    // Windows: Python's socket and TLS setup fail without these.
+    "SYSTEMROOT",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+];
+/// Locale categories (`LC_ALL`, `LC_CTYPE`, ...).
+const BUILTIN_PREFIXES: &[&str] = &["LC_"];
+/// Set by `spawn_backend` itself; not overridable from config.
+const RESERVED_PREFIX: &str = "LITECLAW_";
+
+/// What a backend was spawned with, to tell whether a restart is needed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvSettings {
+    pub passthrough: Vec<String>,
+    pub extra: BTreeMap<String, String>,
+}
+
+impl EnvSettings {
+    pub fn from_config(config: &LocalConfig) -> Self {
+        Self {
+            passthrough: config.env_passthrough.clone(),
+            extra: config.env_extra.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvUpdate {
+    pub config: LocalConfig,
+    /// The running backend still has the previous environment.
+    pub restart_pending: bool,
+}
+
+pub fn validate_key(key: &str) -> Result<(), CommandError> {
+    let valid = !key.is_empty()
+        && key
+            .bytes()
+            .all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit() || byte == b'_');
+    if !valid {
+        return Err(CommandError::invalid_input(format!(
+            "invalid environment variable name `{key}`: use A-Z, 0-9 and _"
+        )));
+    }
+    if key.starts_with(RESERVED_PREFIX) {
+        return Err(CommandError::invalid_input(format!(
+            "`{key}` is reserved for LiteClaw"
+        )));
+    }
+    Ok(())
+}
+
+fn is_builtin(key: &str) -> bool {
+    BUILTIN_PASSTHROUGH.contains(&key)
+        || BUILTIN_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+/// Clears the inherited environment and applies the allowlist and extras.
+pub fn apply_to_command(command: &mut Command, settings: &EnvSettings) {
+    command.env_clear();
+    for (key, value) in std::env::vars_os() {
+        let Some(name) = key.to_str() else {
+            continue;
+        };
+        // Windows keys are case-insensitive (`Path`, `SystemRoot`).
+        let upper = if cfg!(windows) {
+            name.to_ascii_uppercase()
+        } else {
+            name.to_string()
+        };
+        if is_builtin(&upper) || settings.passthrough.contains(&upper) {
+            command.env(&key, value);
+        }
+    }
+    command.envs(&settings.extra);
+}
+
+fn update_env(
+    runtime: &BackendRuntime,
+    change: impl FnOnce(&mut LocalConfig),
+) -> Result<ConfigChange<EnvUpdate>, CommandError> {
+    let mut config = read_local_config(&runtime.data_dir)?;
+    change(&mut config);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    let restart_pending = runtime.backend_child.is_some()
+        && runtime.backend_env.as_ref() != Some(&EnvSettings::from_config(&config));
+    Ok(ConfigChange::new(
+        EnvUpdate {
+            config,
+            restart_pending,
+        },
+        diff,
+    ))
+}
+
+/// Trimmed, checked, sorted and deduplicated.
+pub fn validate_passthrough(keys: Vec<String>) -> Result<Vec<String>, CommandError> {
+    let mut keys: Vec<String> = keys.iter().map(|key| key.trim().to_string()).collect();
+    for key in &keys {
+        validate_key(key)?;
+    }
+    keys.sort();
+    keys.dedup();
+    Ok(keys)
+}
+
+pub fn validate_extra(vars: &BTreeMap<String, String>) -> Result<(), CommandError> {
+    for (key, value) in vars {
+        validate_key(key)?;
+        if value.contains('\0') {
+            return Err(CommandError::invalid_input(format!(
+                "value of `{key}` contains a NUL byte"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_env_passthrough(
+    state: State<'_, AppState>,
+    keys: Vec<String>,
+) -> Result<ConfigChange<EnvUpdate>, CommandError> {
+    let keys = validate_passthrough(keys)?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    update_env(&runtime, |config| config.env_passthrough = keys)
+}
+
+/// Replaces all extra variables. Values are stored in the config but never
+/// written to the audit log or returned in diffs.
+#[tauri::command]
+pub fn set_env_extra(
+    state: State<'_, AppState>,
+    vars: BTreeMap<String, String>,
+) -> Result<ConfigChange<EnvUpdate>, CommandError> {
+    validate_extra(&vars)?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    update_env(&runtime, |config| config.env_extra = vars)
+}
@@ -0,0 +1,156 @@
+//! Why the backend is not running. The raw detail of a failure can hold the
+//! full backend URL, data-dir paths or the spawn command line, so it stays
+//! in `BackendRuntime::last_error` for desktop.log, diagnostics and crash
+//! reports; `ApiConfig` gets only the kind and a summary built from fixed
+//! text and safe numbers, plus the `traceback_hint` for failures the
+//! backend printed a traceback for. `get_last_error_details` hands out the
+//! detail in developer mode.
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::traceback_hint::FailureHint;
+use crate::{backend_debug, AppState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendErrorKind {
+    /// Preparing or launching the process failed.
+    SpawnFailed,
+    /// A traceback before the backend became ready.
+    StartupCrashed,
+    /// The process exited before becoming ready.
+    StartupExited,
+    HealthTimeout,
+    PortMismatch,
+    /// Something else answered on the backend's port and rejected its token.
+    PortSquatted,
+    /// No API version in common; see `api_version`.
+    Incompatible,
+    IntegrityFailed,
+    CrashLoop,
+}
+
+impl BackendErrorKind {
+    fn summary(self) -> &'static str {
+        match self {
+            Self::SpawnFailed => "The backend could not be started.",
+            Self::StartupCrashed => "The backend crashed while starting.",
+            Self::StartupExited => "The backend exited while starting.",
+            Self::HealthTimeout => "The backend did not answer its health check in time.",
+            Self::PortMismatch => "The backend started on an unexpected port.",
+            Self::PortSquatted => "Another program is answering on the backend's port.",
+            Self::Incompatible => "The backend version is not compatible with this app.",
+            Self::IntegrityFailed => "The backend files failed their integrity check.",
+            Self::CrashLoop => "The backend keeps crashing; automatic restarts stopped.",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackendError {
+    pub kind: BackendErrorKind,
+    summary: String,
+    detail: String,
+    hint: Option<FailureHint>,
+}
+
+impl BackendError {
+    pub fn new(kind: BackendErrorKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            summary: kind.summary().to_string(),
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: Option<FailureHint>) -> Self {
+        self.hint = hint;
+        self
+    }
+
+    pub fn hint(&self) -> Option<&FailureHint> {
+        self.hint.as_ref()
+    }
+
+    /// Replaces the fixed summary. Only for text made of counts, exit codes
+    /// and version numbers, never paths or URLs.
+    pub fn with_summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = summary.into();
+        self
+    }
+
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
+
+impl From<String> for BackendError {
+    fn from(detail: String) -> Self {
+        Self::new(BackendErrorKind::SpawnFailed, detail)
+    }
+}
+
+impl From<CommandError> for BackendError {
+    fn from(err: CommandError) -> Self {
+        Self::new(BackendErrorKind::SpawnFailed, err.message)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LastErrorDetails {
+    pub kind: BackendErrorKind,
+    pub summary: String,
+    pub detail: String,
+    pub failure_hint: Option<FailureHint>,
+}
+
+#[tauri::command]
+pub fn get_last_error_details(
+    state: State<'_, AppState>,
+) -> Result<Option<LastErrorDetails>, CommandError> {
+    backend_debug::require_developer_mode()?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(runtime.last_error.as_ref().map(|err| LastErrorDetails {
+        kind: err.kind,
+        summary: err.summary.clone(),
+        detail: err.detail.clone(),
+        failure_hint: err.hint.clone(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api_config, BackendRuntime};
+    use std::path::PathBuf;
+
+    #[test]
+    fn spawn_error_detail_stays_out_of_api_config() {
+        let secret = "fake-token-5f1c2b7e";
+        let data_dir = "/home/someone/.local/share/liteclaw";
+        let mut runtime = BackendRuntime::new(PathBuf::from(data_dir), "default".to_string());
+        runtime.token = "session-token".to_string();
+        runtime.last_error = Some(BackendError::from(format!(
+            "failed to spawn backend: python3 {data_dir}/backend/main.py \
+             LITECLAW_AUTH_TOKEN={secret} LITECLAW_PORT=48123: \
+             http://127.0.0.1:48123 refused"
+        )));
+
+        let serialized = serde_json::to_string(&api_config(&runtime)).unwrap();
+        assert!(!serialized.contains(secret));
+        assert!(!serialized.contains("LITECLAW_AUTH_TOKEN"));
+        assert!(!serialized.contains("main.py"));
+        assert!(serialized.contains(BackendErrorKind::SpawnFailed.summary()));
+        assert!(serialized.contains("\"last_error_kind\":\"spawn_failed\""));
+    }
+}
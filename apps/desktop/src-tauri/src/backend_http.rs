@@ -0,0 +1,443 @@
+//! Shared plumbing for desktop → backend HTTP calls. Every call gets an idle
+//! deadline and its outcome lands in a rolling log, so a backend that keeps
+//! answering `/v1/health` but stalls on real work can be told apart from one
+//! that is merely slow.
+//!
+//! Idempotent calls (GETs, config reload, pause/resume) go through
+//! `send_idempotent`, which retries connection failures and 502/503 with
+//! jittered backoff. Every attempt carries the same `Idempotency-Key` so the
+//! backend can drop duplicates. Anything else is sent once. Calls made
+//! through a `BackendTransport` get the same handling from `retry_idempotent`.
+//!
+//! Every agent from `builder` waits for a slot in `request_limiter` first. A
+//! request it turns away is not retried; callers map it with `rejected`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::error::Error as _;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::fault_injection::{self, FailureKind};
+use crate::request_limiter::{self, Busy};
+use crate::unix_now;
+
+/// Attempts per idempotent call, the first one included.
+pub const MAX_ATTEMPTS: u32 = 3;
+/// Wait before the first retry; doubled for each retry after it.
+pub const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+/// Random extra wait per retry, so callers don't retry in lockstep.
+pub const RETRY_MAX_JITTER: Duration = Duration::from_millis(100);
+/// Statuses a restarting backend (or the proxy in front of it) answers with.
+pub const RETRY_STATUSES: &[u16] = &[502, 503];
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-Key";
+/// Sent on every backend request; see `api_version`.
+pub const DESKTOP_VERSION_HEADER: &str = "X-LiteClaw-Desktop-Version";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HangDetectionConfig {
+    /// Longest a single read or write on a backend connection may stall.
+    pub request_timeout_secs: u64,
+    /// Timed-out requests in a row (with health still passing) before the
+    /// backend is considered hung.
+    pub consecutive_timeouts: usize,
+    /// How many recent requests are remembered.
+    pub history_len: usize,
+    pub auto_restart_on_hang: bool,
+}
+
+impl Default for HangDetectionConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: 30,
+            consecutive_timeouts: 3,
+            history_len: 20,
+            auto_restart_on_hang: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok,
+    /// The backend answered, but with an error status.
+    Status(u16),
+    /// Connection refused, reset, etc.
+    Failed,
+    TimedOut,
+    /// Turned away by `request_limiter`; never reached the backend.
+    Busy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestRecord {
+    pub endpoint: String,
+    pub outcome: Outcome,
+    /// Across all attempts.
+    pub elapsed_ms: u64,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub at: u64,
+    pub attempts: u32,
+}
+
+struct Tracker {
+    records: Mutex<VecDeque<RequestRecord>>,
+    timeout_secs: AtomicU64,
+    history_len: AtomicU64,
+}
+
+static TRACKER: Tracker = Tracker {
+    records: Mutex::new(VecDeque::new()),
+    timeout_secs: AtomicU64::new(30),
+    history_len: AtomicU64::new(20),
+};
+
+/// Applies the deadline and history size from config to subsequent calls.
+pub fn configure(config: &HangDetectionConfig) {
+    TRACKER
+        .timeout_secs
+        .store(config.request_timeout_secs.max(1), Ordering::Relaxed);
+    TRACKER
+        .history_len
+        .store(config.history_len.max(1) as u64, Ordering::Relaxed);
+}
+
+/// Agent with the configured deadline. The deadline is per read/write rather
+/// than for the whole exchange so large uploads and downloads still work.
+pub fn agent() -> ureq::Agent {
+    let timeout = Duration::from_secs(TRACKER.timeout_secs.load(Ordering::Relaxed));
+    builder()
+        .timeout_read(timeout)
+        .timeout_write(timeout)
+        .build()
+}
+
+struct DesktopVersion;
+
+impl ureq::Middleware for DesktopVersion {
+    fn handle(
+        &self,
+        request: ureq::Request,
+        next: ureq::MiddlewareNext<'_>,
+    ) -> Result<ureq::Response, ureq::Error> {
+        next.handle(request.set(DESKTOP_VERSION_HEADER, env!("CARGO_PKG_VERSION")))
+    }
+}
+
+/// Where `backend_unreachable` and `slow_backend` take effect; see
+/// `fault_injection`.
+struct InjectedFailures;
+
+impl ureq::Middleware for InjectedFailures {
+    fn handle(
+        &self,
+        request: ureq::Request,
+        next: ureq::MiddlewareNext<'_>,
+    ) -> Result<ureq::Response, ureq::Error> {
+        fault_injection::before_request()?;
+        next.handle(request)
+    }
+}
+
+/// Holds a `request_limiter` slot until the response headers arrive.
+struct Limited;
+
+impl ureq::Middleware for Limited {
+    fn handle(
+        &self,
+        request: ureq::Request,
+        next: ureq::MiddlewareNext<'_>,
+    ) -> Result<ureq::Response, ureq::Error> {
+        let path = url::Url::parse(request.url())
+            .map(|url| url.path().to_string())
+            .unwrap_or_default();
+        let _permit = request_limiter::acquire(&path).map_err(io::Error::other)?;
+        next.handle(request)
+    }
+}
+
+/// Base for every agent that talks to the backend: waits for a limiter slot
+/// and stamps each request with the desktop version header.
+pub fn builder() -> ureq::AgentBuilder {
+    ureq::AgentBuilder::new()
+        .middleware(Limited)
+        .middleware(InjectedFailures)
+        .middleware(DesktopVersion)
+}
+
+fn busy_of(err: &ureq::Error) -> Option<&Busy> {
+    let ureq::Error::Transport(transport) = err else {
+        return None;
+    };
+    transport
+        .source()
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .and_then(|io_err| io_err.get_ref())
+        .and_then(|inner| inner.downcast_ref::<Busy>())
+}
+
+/// `backend_busy` when the limiter turned the request away; callers check
+/// this before mapping other failures their own way.
+pub fn rejected(result: &Result<ureq::Response, ureq::Error>) -> Result<(), CommandError> {
+    match result.as_ref().err().and_then(busy_of) {
+        Some(busy) => Err(CommandError::new(ErrorCode::BackendBusy, busy.to_string())),
+        None => Ok(()),
+    }
+}
+
+fn is_timeout(err: &ureq::Error) -> bool {
+    let ureq::Error::Transport(transport) = err else {
+        return false;
+    };
+    transport
+        .source()
+        .and_then(|source| source.downcast_ref::<io::Error>())
+        .is_some_and(|io_err| {
+            matches!(
+                io_err.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            )
+        })
+}
+
+fn outcome_of(result: &Result<ureq::Response, ureq::Error>) -> Outcome {
+    match result {
+        Ok(_) => Outcome::Ok,
+        Err(ureq::Error::Status(code, _)) => Outcome::Status(*code),
+        Err(err) if busy_of(err).is_some() => Outcome::Busy,
+        Err(err) if is_timeout(err) => Outcome::TimedOut,
+        Err(_) => Outcome::Failed,
+    }
+}
+
+/// Records how a call that began at `started` went. Health probes should
+/// not be recorded: they are the control signal the log is compared against.
+pub fn record(endpoint: &str, started: Instant, result: &Result<ureq::Response, ureq::Error>) {
+    record_attempts(endpoint, started, result, 1);
+}
+
+fn record_attempts(
+    endpoint: &str,
+    started: Instant,
+    result: &Result<ureq::Response, ureq::Error>,
+    attempts: u32,
+) {
+    let record = RequestRecord {
+        endpoint: endpoint.to_string(),
+        outcome: outcome_of(result),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        at: unix_now(),
+        attempts,
+    };
+    if let Ok(mut records) = TRACKER.records.lock() {
+        let limit = TRACKER.history_len.load(Ordering::Relaxed) as usize;
+        records.push_back(record);
+        while records.len() > limit {
+            records.pop_front();
+        }
+    }
+}
+
+/// Worth another attempt: the backend was unreachable or said it is not
+/// ready. Timeouts are not retried, since the deadline is already long.
+fn is_transient(result: &Result<ureq::Response, ureq::Error>) -> bool {
+    match result {
+        Ok(_) => false,
+        Err(ureq::Error::Status(code, _)) => RETRY_STATUSES.contains(code),
+        Err(err @ ureq::Error::Transport(transport)) => {
+            !is_timeout(err)
+                && busy_of(err).is_none()
+                && matches!(
+                    transport.kind(),
+                    ureq::ErrorKind::ConnectionFailed | ureq::ErrorKind::Io
+                )
+        }
+    }
+}
+
+/// Wait before retry number `retry` (1-based).
+fn retry_delay(retry: u32) -> Duration {
+    let jitter_range = RETRY_MAX_JITTER.as_millis() as u64 + 1;
+    let jitter = (Uuid::new_v4().as_u128() % u128::from(jitter_range)) as u64;
+    RETRY_BASE_DELAY * 2u32.pow(retry - 1) + Duration::from_millis(jitter)
+}
+
+/// Result of `send_idempotent`.
+pub struct Sent {
+    pub result: Result<ureq::Response, ureq::Error>,
+    pub attempts: u32,
+}
+
+impl Sent {
+    /// `" after N attempts"` when retries were used, for error messages.
+    pub fn attempts_note(&self) -> String {
+        if self.attempts > 1 {
+            format!(" after {} attempts", self.attempts)
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// Sends an idempotent `request`, with `body` if given, retrying transient
+/// failures up to `MAX_ATTEMPTS` times. Every attempt carries the same
+/// `IDEMPOTENCY_HEADER`. The call is recorded once, with its attempt count.
+#[allow(clippy::result_large_err)]
+pub fn send_idempotent(endpoint: &str, request: ureq::Request, body: Option<&str>) -> Sent {
+    retry_idempotent(endpoint, |key| {
+        let request = request.clone().set(IDEMPOTENCY_HEADER, key);
+        match body {
+            Some(body) => request.send_string(body),
+            None => request.call(),
+        }
+    })
+}
+
+/// `send_idempotent` for requests made some other way, e.g. through a
+/// `BackendTransport`: `attempt` sends the request with the key it is given.
+pub fn retry_idempotent(
+    endpoint: &str,
+    mut attempt: impl FnMut(&str) -> Result<ureq::Response, ureq::Error>,
+) -> Sent {
+    let key = Uuid::new_v4().to_string();
+    let started = Instant::now();
+    let mut attempts = 0;
+    let result = loop {
+        if attempts > 0 {
+            thread::sleep(retry_delay(attempts));
+        }
+        attempts += 1;
+        let result = attempt(&key);
+        if attempts >= MAX_ATTEMPTS || !is_transient(&result) {
+            break result;
+        }
+    };
+    record_attempts(endpoint, started, &result, attempts);
+    Sent { result, attempts }
+}
+
+/// The most recent requests, if the last `threshold` of them all timed out.
+pub fn hang_evidence(threshold: usize) -> Option<Vec<RequestRecord>> {
+    if fault_injection::is_active(FailureKind::HealthDegraded) {
+        return Some(vec![RequestRecord {
+            endpoint: "(injected)".to_string(),
+            outcome: Outcome::TimedOut,
+            elapsed_ms: 0,
+            at: unix_now(),
+            attempts: 1,
+        }]);
+    }
+    let records = TRACKER.records.lock().ok()?;
+    // Turned-away requests say nothing about how the backend is doing.
+    let run: Vec<RequestRecord> = records
+        .iter()
+        .rev()
+        .filter(|record| record.outcome != Outcome::Busy)
+        .take_while(|record| record.outcome == Outcome::TimedOut)
+        .cloned()
+        .collect();
+    (threshold > 0 && run.len() >= threshold).then_some(run)
+}
+
+/// Forgets history, e.g. once the backend it described has been replaced.
+pub fn reset() {
+    if let Ok(mut records) = TRACKER.records.lock() {
+        records.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serves one connection per status in `statuses`, answering with each in
+    /// turn, and hands back the idempotency key every request carried.
+    fn flaky_listener(statuses: &[u16]) -> (String, thread::JoinHandle<Vec<Option<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/thing", listener.local_addr().unwrap());
+        let statuses = statuses.to_vec();
+        let handle = thread::spawn(move || {
+            let mut keys = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut key = None;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case(IDEMPOTENCY_HEADER) {
+                            key = Some(value.trim().to_string());
+                        }
+                    }
+                    line.clear();
+                }
+                keys.push(key);
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+            keys
+        });
+        (url, handle)
+    }
+
+    fn get(url: &str) -> Sent {
+        send_idempotent("/v1/thing", agent().get(url), None)
+    }
+
+    #[test]
+    fn transient_statuses_are_retried_with_one_key() {
+        let (url, server) = flaky_listener(&[503, 502, 200]);
+        let sent = get(&url);
+        assert_eq!(sent.result.unwrap().status(), 200);
+        assert_eq!(sent.attempts, 3);
+
+        let keys = server.join().unwrap();
+        assert_eq!(keys.len(), 3);
+        assert!(keys[0].is_some());
+        assert!(keys.iter().all(|key| key == &keys[0]));
+    }
+
+    #[test]
+    fn retries_stop_after_max_attempts() {
+        let (url, server) = flaky_listener(&[503; MAX_ATTEMPTS as usize]);
+        let sent = get(&url);
+        assert!(matches!(sent.result, Err(ureq::Error::Status(503, _))));
+        assert_eq!(sent.attempts, MAX_ATTEMPTS);
+        assert_eq!(sent.attempts_note(), " after 3 attempts");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let (url, server) = flaky_listener(&[404]);
+        let sent = get(&url);
+        assert!(matches!(sent.result, Err(ureq::Error::Status(404, _))));
+        assert_eq!(sent.attempts, 1);
+        assert_eq!(sent.attempts_note(), "");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn refused_connections_are_retried() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let sent = get(&format!("http://127.0.0.1:{port}/v1/thing"));
+        assert!(matches!(sent.result, Err(ureq::Error::Transport(_))));
+        assert_eq!(sent.attempts, MAX_ATTEMPTS);
+    }
+}
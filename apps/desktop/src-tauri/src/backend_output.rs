@@ -0,0 +1,237 @@
+//! Tees the backend's stdout/stderr into `logs/backend.log` line by line,
+//! prefixing each with a timestamp and stream label, and
+//! watches startup output: the `LITECLAW_READY` sentinel and
+//! `LITECLAW_PHASE` progress lines (see `startup_phase`) on stdout and fatal
+//! Python tracebacks on stderr. Log writes pause while the data dir is
+//! unavailable.
+
+use serde::Deserialize;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::{startup_phase, storage, timestamps};
+
+pub const READY_SENTINEL: &str = "LITECLAW_READY";
+pub const TRACEBACK_HEADER: &str = "Traceback (most recent call last):";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StartupSignal {
+    Ready {
+        port: Option<u16>,
+    },
+    /// A phase line, already passed to `startup_phase::report`.
+    Progress,
+    Fatal(String),
+}
+
+#[derive(Deserialize)]
+struct ReadyPayload {
+    port: Option<u16>,
+}
+
+pub fn parse_ready_line(line: &str) -> Option<StartupSignal> {
+    let rest = line.trim().strip_prefix(READY_SENTINEL)?;
+    let port = serde_json::from_str::<ReadyPayload>(rest.trim())
+        .ok()
+        .and_then(|payload| payload.port);
+    Some(StartupSignal::Ready { port })
+}
+
+/// Collects a traceback until its final `SomeError: message` line, which is
+/// the part worth showing in `last_error`.
+#[derive(Default)]
+struct TracebackWatch {
+    in_traceback: bool,
+}
+
+impl TracebackWatch {
+    fn feed(&mut self, line: &str) -> Option<String> {
+        if line.starts_with(TRACEBACK_HEADER) {
+            self.in_traceback = true;
+            return None;
+        }
+        if self.in_traceback && !line.starts_with(char::is_whitespace) && !line.is_empty() {
+            self.in_traceback = false;
+            return Some(line.trim().to_string());
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn label(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineFormat {
+    /// `2024-05-01T12:00:00.123Z [stdout] <line>`; the backend's own text
+    /// (including JSON) follows the prefix untouched.
+    Prefixed,
+    /// Bytes exactly as the backend wrote them (debug console mode).
+    Raw,
+}
+
+/// Longest line kept in prefixed mode; the rest is dropped with a marker.
+pub const MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// Reads one line of at most `MAX_LINE_BYTES`, skipping the remainder of a
+/// longer line. Returns the kept bytes and how many were dropped, or `None`
+/// at end of stream.
+fn read_capped_line<R: BufRead>(reader: &mut R, buf: &mut Vec<u8>) -> Option<usize> {
+    buf.clear();
+    let read = reader
+        .by_ref()
+        .take(MAX_LINE_BYTES as u64)
+        .read_until(b'\n', buf)
+        .ok()?;
+    if read == 0 {
+        return None;
+    }
+    let mut dropped = 0;
+    if buf.last() != Some(&b'\n') && read == MAX_LINE_BYTES {
+        let mut rest = Vec::new();
+        dropped = reader.read_until(b'\n', &mut rest).unwrap_or(0);
+        if rest.last() == Some(&b'\n') {
+            dropped -= 1;
+        }
+    }
+    Some(dropped)
+}
+
+struct SinkState {
+    /// Dropped on an outage and reopened once storage is back, since a
+    /// handle onto a vanished network share stays broken.
+    file: Option<File>,
+    dropped_lines: u64,
+}
+
+/// `logs/backend.log`, shared by both tee threads.
+pub struct LogSink {
+    path: PathBuf,
+    state: Mutex<SinkState>,
+}
+
+impl LogSink {
+    pub fn new(path: PathBuf, file: File) -> Self {
+        Self {
+            path,
+            state: Mutex::new(SinkState {
+                file: Some(file),
+                dropped_lines: 0,
+            }),
+        }
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if storage::is_unavailable() {
+            state.file = None;
+            state.dropped_lines += 1;
+            return;
+        }
+        if state.file.is_none() {
+            state.file = OpenOptions::new().append(true).open(&self.path).ok();
+        }
+        let dropped = state.dropped_lines;
+        let Some(file) = state.file.as_mut() else {
+            state.dropped_lines += 1;
+            return;
+        };
+        let marker = if dropped > 0 {
+            writeln!(
+                file,
+                "{} [desktop] {dropped} lines dropped while the data dir was unavailable",
+                timestamps::now()
+            )
+        } else {
+            Ok(())
+        };
+        let result = marker
+            .and_then(|()| file.write_all(bytes))
+            .and_then(|()| file.flush());
+        match result {
+            Ok(()) => state.dropped_lines = 0,
+            Err(err) => {
+                if storage::is_outage_io(&err) {
+                    storage::enter_outage();
+                }
+                state.file = None;
+                state.dropped_lines += 1;
+            }
+        }
+    }
+}
+
+pub fn spawn_tee<R: Read + Send + 'static>(
+    reader: R,
+    stream: Stream,
+    format: LineFormat,
+    log: Arc<LogSink>,
+    signals: Sender<StartupSignal>,
+) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut traceback = TracebackWatch::default();
+        let mut buf = Vec::new();
+        loop {
+            let dropped = if format == LineFormat::Raw {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => 0,
+                }
+            } else {
+                match read_capped_line(&mut reader, &mut buf) {
+                    Some(dropped) => dropped,
+                    None => break,
+                }
+            };
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\r', '\n']);
+            let entry = match format {
+                LineFormat::Raw => buf.clone(),
+                LineFormat::Prefixed if dropped > 0 => format!(
+                    "{} [{}] {line} ...[truncated {dropped} bytes]\n",
+                    timestamps::now(),
+                    stream.label()
+                )
+                .into_bytes(),
+                LineFormat::Prefixed => {
+                    format!("{} [{}] {line}\n", timestamps::now(), stream.label()).into_bytes()
+                }
+            };
+            log.write(&entry);
+            // Send errors just mean startup is over and nobody is listening.
+            let signal = match stream {
+                Stream::Stdout => match startup_phase::parse_phase_line(line) {
+                    Some(phase) => {
+                        startup_phase::report(phase);
+                        Some(StartupSignal::Progress)
+                    }
+                    None => parse_ready_line(line),
+                },
+                Stream::Stderr => traceback.feed(line).map(StartupSignal::Fatal),
+            };
+            if let Some(signal) = signal {
+                let _ = signals.send(signal);
+            }
+        }
+    });
+}
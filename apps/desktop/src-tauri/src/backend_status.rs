@@ -0,0 +1,217 @@
+//! What the window knows about the backend, in two parts: `ConnectionInfo`
+//! (where and how to reach it, which changes only on a respawn or profile
+//! switch) and `BackendStatus` (whether it is usable, which flips often).
+//! `get_api_config` still returns both flattened together, as before the
+//! split; it is deprecated and goes away after one release.
+//!
+//! Code that changes status does it through `set_status`, or calls
+//! `publish` after code without an `AppHandle` (a spawn) has done it. Either
+//! one compares with what was last published and emits only what changed:
+//! `backend-status-changed` with the changed `BackendStatus` fields,
+//! `connection-info-changed` with the whole `ConnectionInfo`, and the
+//! deprecated `backend-state-changed` with everything. The heartbeat
+//! publishes every beat, so a change made without notifying still reaches
+//! the window within one.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::api_version::ApiVersion;
+use crate::backend_error::BackendErrorKind;
+use crate::error::CommandError;
+use crate::startup_phase::{self, StartupPhase};
+use crate::traceback_hint::FailureHint;
+use crate::{
+    api_config, backend_state, config_generation, config_in_sync, data_dir_lock, integrity,
+    storage, ApiConfig, AppState, BackendRuntime, BackendState,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConnectionInfo {
+    pub base_url: String,
+    pub token: String,
+    pub log_path: String,
+    pub profile: String,
+    /// Negotiated API version of the running (or last) backend.
+    pub api_version: ApiVersion,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackendStatus {
+    pub backend_ready: bool,
+    pub backend_state: BackendState,
+    pub stopped_by_user: bool,
+    pub safe_mode: bool,
+    /// Safe summary of `BackendRuntime::last_error`.
+    pub last_error: Option<String>,
+    pub last_error_kind: Option<BackendErrorKind>,
+    /// What the backend's last traceback points to, when it printed one.
+    pub failure_hint: Option<FailureHint>,
+    pub config_generation: u64,
+    pub backend_config_generation: Option<u64>,
+    pub config_in_sync: bool,
+    pub crash_loop: bool,
+    pub storage_unavailable: bool,
+    /// The last start was refused because the backend files were modified.
+    pub backend_integrity_failed: bool,
+    pub backend_incompatible: bool,
+    /// What a starting backend last reported; after a failed start, where it
+    /// stopped. See `startup_phase`.
+    pub startup_phase: Option<StartupPhase>,
+    /// Set while another run owns the data dir and this one is read-only.
+    pub data_dir_owner: Option<data_dir_lock::Owner>,
+}
+
+pub fn connection_info(runtime: &BackendRuntime) -> ConnectionInfo {
+    ConnectionInfo {
+        base_url: runtime.base_url.clone(),
+        token: runtime.token.clone(),
+        log_path: runtime.log_path.clone(),
+        profile: runtime.profile.clone(),
+        api_version: runtime.backend_api,
+    }
+}
+
+pub fn backend_status(runtime: &BackendRuntime) -> BackendStatus {
+    BackendStatus {
+        backend_ready: runtime.backend_ready,
+        backend_state: backend_state(runtime),
+        stopped_by_user: runtime.stopped_by_user,
+        safe_mode: runtime.safe_mode,
+        last_error: runtime
+            .last_error
+            .as_ref()
+            .map(|err| err.summary().to_string()),
+        last_error_kind: runtime.last_error.as_ref().map(|err| err.kind),
+        failure_hint: runtime
+            .last_error
+            .as_ref()
+            .and_then(|err| err.hint())
+            .cloned(),
+        config_generation: config_generation(),
+        backend_config_generation: runtime.backend_config_generation,
+        config_in_sync: config_in_sync(runtime),
+        crash_loop: runtime.crash_tracker.crash_loop,
+        storage_unavailable: storage::is_unavailable(),
+        backend_integrity_failed: integrity::has_failed(),
+        backend_incompatible: runtime.backend_incompatible,
+        startup_phase: startup_phase::latest(),
+        data_dir_owner: data_dir_lock::locked_by(),
+    }
+}
+
+#[derive(Default)]
+struct Published {
+    connection: Option<ConnectionInfo>,
+    status: Map<String, Value>,
+}
+
+static PUBLISHED: Mutex<Option<Published>> = Mutex::new(None);
+
+/// The fields of `current` that differ from `previous`; all of them the
+/// first time.
+fn delta(previous: &Map<String, Value>, current: &Map<String, Value>) -> Map<String, Value> {
+    current
+        .iter()
+        .filter(|(key, value)| previous.get(*key) != Some(*value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// Applies `update` to the runtime and tells the windows what it changed.
+pub fn set_status(
+    app: &AppHandle,
+    runtime: &mut BackendRuntime,
+    update: impl FnOnce(&mut BackendRuntime),
+) -> ApiConfig {
+    update(runtime);
+    publish(app, runtime)
+}
+
+/// Emits whatever changed since the last publish; see the module docs.
+pub fn publish(app: &AppHandle, runtime: &BackendRuntime) -> ApiConfig {
+    let api = api_config(runtime);
+    let status = match serde_json::to_value(&api.status) {
+        Ok(Value::Object(status)) => status,
+        _ => return api,
+    };
+    let Ok(mut published) = PUBLISHED.lock() else {
+        return api;
+    };
+    let published = published.get_or_insert_with(Published::default);
+    let changed = delta(&published.status, &status);
+    let connection_changed = published.connection.as_ref() != Some(&api.connection);
+    if connection_changed {
+        let _ = app.emit("connection-info-changed", &api.connection);
+        published.connection = Some(api.connection.clone());
+    }
+    if !changed.is_empty() {
+        let _ = app.emit("backend-status-changed", &changed);
+        published.status = status;
+    }
+    if connection_changed || !changed.is_empty() {
+        let _ = app.emit("backend-state-changed", &api);
+    }
+    api
+}
+
+/// `publish` for callers that do not hold the runtime lock.
+pub fn publish_current(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let Ok(runtime) = state.runtime.lock() else {
+        return;
+    };
+    publish(app, &runtime);
+}
+
+#[tauri::command]
+pub fn get_connection_info(state: State<'_, AppState>) -> Result<ConnectionInfo, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(connection_info(&runtime))
+}
+
+#[tauri::command]
+pub fn get_backend_status(state: State<'_, AppState>) -> Result<BackendStatus, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(backend_status(&runtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn status_map(runtime: &BackendRuntime) -> Map<String, Value> {
+        match serde_json::to_value(backend_status(runtime)).unwrap() {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn a_status_flip_carries_only_what_changed() {
+        let mut runtime = BackendRuntime::new(PathBuf::from("data"), "default".to_string());
+        let before = status_map(&runtime);
+        assert_eq!(delta(&Map::new(), &before), before);
+
+        runtime.backend_ready = true;
+        runtime.token = "rotated".to_string();
+        let changed = delta(&before, &status_map(&runtime));
+        assert_eq!(changed["backend_ready"], true);
+        assert_eq!(changed["backend_state"], "ready");
+        assert!(!changed.contains_key("stopped_by_user"));
+        assert!(!changed.contains_key("token"));
+
+        let api = serde_json::to_value(api_config(&runtime)).unwrap();
+        assert_eq!(api["token"], "rotated");
+        assert_eq!(api["backend_ready"], true);
+    }
+}
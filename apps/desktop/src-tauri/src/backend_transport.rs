@@ -0,0 +1,325 @@
+//! The calls the desktop makes to the backend, behind a trait so the code
+//! that drives them (the health wait in `spawn_backend`, config reload, the
+//! retry layer and the heartbeat) can run without a backend. `UreqTransport`
+//! is the real one and goes through `backend_http`'s agents; tests script a
+//! `fake::FakeTransport` instead.
+//!
+//! Replies keep ureq's shape, so callers match on them as before: a status
+//! of 400 or more is `ureq::Error::Status`.
+
+use std::time::Duration;
+
+use crate::backend_http::{self, IDEMPOTENCY_HEADER};
+
+pub const HEALTH: &str = "/v1/health";
+pub const RELOAD: &str = "/v1/config/reload";
+pub const AUTH_VERIFY: &str = "/v1/auth/verify";
+/// Asked instead of `AUTH_VERIFY` by backends predating it.
+pub const VERSION: &str = "/v1/version";
+
+pub type Exchange = Result<ureq::Response, ureq::Error>;
+
+/// One request to the backend.
+pub struct Call<'a> {
+    pub method: &'static str,
+    pub url: String,
+    pub token: &'a str,
+    /// Sent as JSON.
+    pub body: Option<&'a str>,
+    pub idempotency_key: Option<&'a str>,
+    /// Deadline for the whole exchange; without one, the idle deadline from
+    /// `backend_http::agent` applies.
+    pub timeout: Option<Duration>,
+}
+
+impl<'a> Call<'a> {
+    pub fn get(base_url: &str, endpoint: &str, token: &'a str) -> Self {
+        Self {
+            method: "GET",
+            url: format!("{base_url}{endpoint}"),
+            token,
+            body: None,
+            idempotency_key: None,
+            timeout: None,
+        }
+    }
+
+    pub fn post(base_url: &str, endpoint: &str, token: &'a str, body: &'a str) -> Self {
+        Self {
+            method: "POST",
+            body: Some(body),
+            ..Self::get(base_url, endpoint, token)
+        }
+    }
+}
+
+// `ureq::Error` is large, but it is what every caller already matches on.
+#[allow(clippy::result_large_err)]
+pub trait BackendTransport: Send + Sync {
+    fn request(&self, call: &Call<'_>) -> Exchange;
+
+    fn health(&self, base_url: &str, token: &str, timeout: Option<Duration>) -> Exchange {
+        let call = Call {
+            timeout,
+            ..Call::get(base_url, HEALTH, token)
+        };
+        self.request(&call)
+    }
+
+    /// Whether the backend accepts `token`, asked at `endpoint`
+    /// (`AUTH_VERIFY`, or `VERSION` for older backends).
+    fn verify_token(&self, base_url: &str, token: &str, endpoint: &str) -> Exchange {
+        self.request(&Call::get(base_url, endpoint, token))
+    }
+
+    fn reload(&self, base_url: &str, token: &str, payload: &str, key: &str) -> Exchange {
+        let call = Call {
+            idempotency_key: Some(key),
+            ..Call::post(base_url, RELOAD, token, payload)
+        };
+        self.request(&call)
+    }
+}
+
+pub struct UreqTransport;
+
+impl BackendTransport for UreqTransport {
+    fn request(&self, call: &Call<'_>) -> Exchange {
+        let agent = match call.timeout {
+            Some(timeout) => backend_http::builder().timeout(timeout).build(),
+            None => backend_http::agent(),
+        };
+        let mut request = agent
+            .request(call.method, &call.url)
+            .set("Authorization", &format!("Bearer {}", call.token));
+        if let Some(key) = call.idempotency_key {
+            request = request.set(IDEMPOTENCY_HEADER, key);
+        }
+        match call.body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(body),
+            None => request.call(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::result_large_err)]
+pub mod fake {
+    use super::*;
+    use std::collections::{HashMap, VecDeque};
+    use std::io;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone)]
+    pub enum Reply {
+        Status(u16, &'static str),
+        Refused,
+        TimedOut,
+    }
+
+    /// A request the fake answered.
+    #[derive(Debug, Clone)]
+    pub struct Seen {
+        pub method: &'static str,
+        pub body: Option<String>,
+        pub idempotency_key: Option<String>,
+    }
+
+    /// Answers each URL from its script. The last reply for a URL repeats
+    /// once the others are used up; unscripted URLs refuse the connection.
+    #[derive(Default)]
+    pub struct FakeTransport {
+        script: Mutex<HashMap<String, VecDeque<Reply>>>,
+        seen: Mutex<HashMap<String, Vec<Seen>>>,
+    }
+
+    impl FakeTransport {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn script(&self, url: &str, replies: impl IntoIterator<Item = Reply>) -> &Self {
+            self.script
+                .lock()
+                .unwrap()
+                .entry(url.to_string())
+                .or_default()
+                .extend(replies);
+            self
+        }
+
+        pub fn seen(&self, url: &str) -> Vec<Seen> {
+            let seen = self.seen.lock().unwrap();
+            seen.get(url).cloned().unwrap_or_default()
+        }
+    }
+
+    impl BackendTransport for FakeTransport {
+        fn request(&self, call: &Call<'_>) -> Exchange {
+            self.seen
+                .lock()
+                .unwrap()
+                .entry(call.url.clone())
+                .or_default()
+                .push(Seen {
+                    method: call.method,
+                    body: call.body.map(str::to_string),
+                    idempotency_key: call.idempotency_key.map(str::to_string),
+                });
+            let reply = {
+                let mut script = self.script.lock().unwrap();
+                match script.get_mut(&call.url) {
+                    Some(replies) if replies.len() > 1 => replies.pop_front(),
+                    Some(replies) => replies.front().cloned(),
+                    None => None,
+                }
+            };
+            exchange(reply.unwrap_or(Reply::Refused))
+        }
+    }
+
+    fn exchange(reply: Reply) -> Exchange {
+        match reply {
+            Reply::Status(code, body) => {
+                let response = ureq::Response::new(code, "Scripted", body)?;
+                if code >= 400 {
+                    Err(ureq::Error::Status(code, response))
+                } else {
+                    Ok(response)
+                }
+            }
+            Reply::Refused => Err(io::Error::from(io::ErrorKind::ConnectionRefused).into()),
+            Reply::TimedOut => Err(io::Error::from(io::ErrorKind::TimedOut).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::{FakeTransport, Reply};
+    use crate::backend_error::BackendErrorKind;
+    use crate::{
+        find_backend, launch_with_retries, reload_config_via, BackendRuntime, LocalConfig,
+    };
+    use std::fs;
+
+    const BASE_URL: &str = "http://127.0.0.1:4100";
+
+    fn ready_runtime() -> BackendRuntime {
+        let data_dir = std::env::temp_dir().join(format!("liteclaw-fake-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&data_dir).unwrap();
+        let mut runtime = BackendRuntime::new(data_dir, "default".to_string());
+        runtime.base_url = BASE_URL.to_string();
+        runtime.token = "token".to_string();
+        runtime.backend_ready = true;
+        runtime
+    }
+
+    fn leaked(fake: FakeTransport) -> &'static FakeTransport {
+        Box::leak(Box::new(fake))
+    }
+
+    fn reload_url() -> String {
+        format!("{BASE_URL}{}", super::RELOAD)
+    }
+
+    #[test]
+    fn a_reload_posts_the_config_once() {
+        let fake = leaked(FakeTransport::new());
+        fake.script(&reload_url(), [Reply::Status(200, "{}")]);
+        let runtime = ready_runtime();
+
+        reload_config_via(fake, &runtime, &LocalConfig::default()).unwrap();
+        let seen = fake.seen(&reload_url());
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].method, "POST");
+        let body: serde_json::Value =
+            serde_json::from_str(seen[0].body.as_deref().unwrap()).unwrap();
+        assert!(body.is_object());
+        assert!(seen[0].idempotency_key.is_some());
+        fs::remove_dir_all(&runtime.data_dir).unwrap();
+    }
+
+    #[test]
+    fn a_reload_retries_an_unready_backend_with_one_key() {
+        let fake = leaked(FakeTransport::new());
+        fake.script(
+            &reload_url(),
+            [Reply::Status(503, ""), Reply::Status(200, "{}")],
+        );
+        let runtime = ready_runtime();
+
+        reload_config_via(fake, &runtime, &LocalConfig::default()).unwrap();
+        let seen = fake.seen(&reload_url());
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0].idempotency_key, seen[1].idempotency_key);
+        fs::remove_dir_all(&runtime.data_dir).unwrap();
+    }
+
+    #[test]
+    fn a_reload_answered_500_after_a_retry_fails() {
+        let fake = leaked(FakeTransport::new());
+        fake.script(&reload_url(), [Reply::Refused, Reply::Status(500, "boom")]);
+        let runtime = ready_runtime();
+
+        let err = reload_config_via(fake, &runtime, &LocalConfig::default()).unwrap_err();
+        assert!(err.message.contains("after 2 attempts"), "{}", err.message);
+        assert!(err.message.contains("500"), "{}", err.message);
+        // A 500 is the backend's answer, not a restart in progress.
+        assert_eq!(fake.seen(&reload_url()).len(), 2);
+        fs::remove_dir_all(&runtime.data_dir).unwrap();
+    }
+
+    fn healthy(fake: &FakeTransport, base_url: &str, verify: Reply) {
+        fake.script(
+            &format!("{base_url}{}", super::HEALTH),
+            [Reply::Status(200, "{}")],
+        );
+        fake.script(&format!("{base_url}{}", super::AUTH_VERIFY), [verify]);
+    }
+
+    #[test]
+    fn a_backend_that_rejects_the_token_is_a_squatter() {
+        let fake = FakeTransport::new();
+        let older = "http://127.0.0.1:4101";
+        healthy(&fake, older, Reply::Status(404, ""));
+        fake.script(
+            &format!("{older}{}", super::VERSION),
+            [Reply::Status(401, "")],
+        );
+
+        let urls = ["http://127.0.0.1:4100".to_string(), older.to_string()];
+        let found = find_backend(&fake, &urls, "token", 4101).unwrap();
+        let err = found.unwrap_err();
+        assert_eq!(err.kind, BackendErrorKind::PortSquatted);
+        assert!(err.summary().contains("4101"));
+    }
+
+    #[test]
+    fn a_squatted_port_moves_the_backend_to_the_next() {
+        let fake = FakeTransport::new();
+        healthy(&fake, "http://127.0.0.1:4101", Reply::Status(403, ""));
+        healthy(&fake, "http://127.0.0.1:4102", Reply::Status(200, "{}"));
+        let mut runtime = ready_runtime();
+
+        let launched = launch_with_retries(&mut runtime, |_, squatted| {
+            let port = 4101 + squatted.len() as u16;
+            let urls = [format!("http://127.0.0.1:{port}")];
+            match find_backend(&fake, &urls, "token", port) {
+                Some(Err(err)) => {
+                    squatted.push(port);
+                    Err(err)
+                }
+                Some(Ok((base_url, _))) => Ok(base_url),
+                None => panic!("port {port} did not answer"),
+            }
+        });
+        assert_eq!(launched.unwrap(), "http://127.0.0.1:4102");
+        let error = runtime.last_error.as_ref().unwrap();
+        assert_eq!(error.kind, BackendErrorKind::PortSquatted);
+        assert!(error.summary().contains("4101"));
+        fs::remove_dir_all(&runtime.data_dir).unwrap();
+    }
+}
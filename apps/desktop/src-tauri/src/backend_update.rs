@@ -0,0 +1,556 @@
+//! Updating the Python backend without a desktop release. `backend_update_url`
+//! in config points at a release manifest:
+//!
+//! ```json
+//! { "version": "0.2.0", "url": "backend-0.2.0.zip",
+//!   "sha256": "<hex>", "signature": "<hex ed25519, see below>" }
+//! ```
+//!
+//! The signature is over `liteclaw-backend-update\n<version>\n<sha256>`, so
+//! it vouches for the version number as well as the archive: an old signed
+//! archive cannot be offered again as a newer version to roll the backend
+//! back. Archives are streamed to a file and refused past
+//! `MAX_ARCHIVE_BYTES`.
+//!
+//! Installed bundles are extracted to `backend/<version>/` under the data root
+//! together with the signed archive, which `integrity` re-verifies before
+//! every spawn. `backend-version.json` names the current and previous
+//! version; only those two are kept, and with neither the bundled backend
+//! runs. Builds made without `LITECLAW_BACKEND_UPDATE_KEY` cannot update.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State};
+use url::Url;
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::file_ops::{decode_hex, sha256_hex};
+use crate::integrity::BUNDLED_BACKEND_VERSION;
+use crate::{
+    action_queue, audit, backend_status, ensure_not_safe_mode, layout, proxy, read_local_config,
+    session_file, spawn_backend, spawn_guard, ApiConfig, AppState, LocalConfig,
+};
+
+/// Hex-encoded ed25519 public key the release pipeline signs bundles with.
+const UPDATE_PUBLIC_KEY: Option<&str> = option_env!("LITECLAW_BACKEND_UPDATE_KEY");
+const VERSIONS_FILE: &str = "backend-version.json";
+const VERSIONS_DIR: &str = "backend";
+const ARCHIVE_FILE: &str = "bundle.zip";
+const SIGNATURE_FILE: &str = "bundle.sig";
+/// Prefix of the signed message, so no other signature made with the key
+/// passes for an update's.
+const SIGNATURE_CONTEXT: &str = "liteclaw-backend-update";
+/// Archives beyond this are refused; the backend bundle is well under it.
+const MAX_ARCHIVE_BYTES: u64 = 256 * 1024 * 1024;
+const PROGRESS_STEP: u64 = 256 * 1024;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Deserialize)]
+struct UpdateManifest {
+    version: String,
+    /// Absolute, or relative to the manifest URL.
+    url: String,
+    sha256: String,
+    signature: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct InstalledVersions {
+    current: Option<String>,
+    previous: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub latest_version: String,
+    pub update_available: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UpdateInstalled {
+    pub version: String,
+    pub previous_version: String,
+    pub api: ApiConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UpdateProgress {
+    version: String,
+    bytes_received: u64,
+    total_bytes: Option<u64>,
+}
+
+fn integrity_error(message: impl Into<String>) -> CommandError {
+    CommandError::new(ErrorCode::IntegrityMismatch, message)
+}
+
+fn root() -> Result<PathBuf, String> {
+    layout::root().ok_or_else(|| "data root is not initialized".to_string())
+}
+
+fn versions_path(root: &Path) -> PathBuf {
+    root.join(VERSIONS_FILE)
+}
+
+fn version_dir(root: &Path, version: &str) -> PathBuf {
+    root.join(VERSIONS_DIR).join(version)
+}
+
+fn read_versions(root: &Path) -> InstalledVersions {
+    fs::read_to_string(versions_path(root))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_versions(root: &Path, versions: &InstalledVersions) -> Result<(), String> {
+    let path = versions_path(root);
+    let temp = path.with_extension("tmp");
+    let bytes = serde_json::to_vec_pretty(versions)
+        .map_err(|e| format!("failed serializing backend versions: {e}"))?;
+    fs::write(&temp, bytes).map_err(|e| format!("failed writing backend versions: {e}"))?;
+    fs::rename(&temp, &path).map_err(|e| format!("failed replacing backend versions: {e}"))
+}
+
+fn current_version(versions: &InstalledVersions) -> &str {
+    versions
+        .current
+        .as_deref()
+        .unwrap_or(BUNDLED_BACKEND_VERSION)
+}
+
+/// The installed update to run instead of the bundled backend, if any.
+pub fn active_dir() -> Option<PathBuf> {
+    let root = layout::root()?;
+    let dir = version_dir(&root, read_versions(&root).current.as_deref()?);
+    dir.join("main.py").is_file().then_some(dir)
+}
+
+/// Numeric components, ignoring a `-suffix`: `0.2.0-mvp` is `[0, 2, 0]`.
+fn version_key(version: &str) -> Vec<u64> {
+    let core = version.split('-').next().unwrap_or_default();
+    core.split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    version_key(candidate) > version_key(current)
+}
+
+fn verifying_key() -> Result<VerifyingKey, CommandError> {
+    let key = UPDATE_PUBLIC_KEY.ok_or_else(|| {
+        CommandError::new(
+            ErrorCode::Unsupported,
+            "this build has no backend update signing key",
+        )
+    })?;
+    let bytes: [u8; 32] = decode_hex(key)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "embedded backend update key is malformed".to_string())?;
+    Ok(VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid update key: {e}"))?)
+}
+
+fn signed_message(version: &str, sha256: &str) -> Vec<u8> {
+    format!("{SIGNATURE_CONTEXT}\n{version}\n{sha256}").into_bytes()
+}
+
+fn verify_with(
+    key: &VerifyingKey,
+    version: &str,
+    sha256: &str,
+    signature_hex: &str,
+) -> Result<(), CommandError> {
+    let signature = decode_hex(signature_hex)
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| integrity_error("backend update signature is malformed"))?;
+    key.verify_strict(&signed_message(version, sha256), &signature)
+        .map_err(|_| integrity_error("backend update signature does not verify"))
+}
+
+/// Checks that `signature_hex` vouches for `version` with an archive whose
+/// hash is `sha256`.
+fn verify_signature(version: &str, sha256: &str, signature_hex: &str) -> Result<(), CommandError> {
+    verify_with(&verifying_key()?, version, sha256, signature_hex)
+}
+
+fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// File hashes of an installed update, read from its archive after checking
+/// the archive's signature again, for the version its dir is named after.
+/// `None` for a dir that is not an installed update.
+pub fn installed_manifest(dir: &Path) -> Option<Result<Vec<(String, String)>, String>> {
+    let archive_path = dir.join(ARCHIVE_FILE);
+    let archive = File::open(&archive_path).ok()?;
+    Some((|| {
+        let signature = fs::read_to_string(dir.join(SIGNATURE_FILE))
+            .map_err(|e| format!("update signature unreadable: {e}"))?;
+        let version = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let sha256 =
+            file_sha256(&archive_path).map_err(|e| format!("update archive unreadable: {e}"))?;
+        verify_signature(&version, &sha256, &signature).map_err(String::from)?;
+        let mut zip =
+            zip::ZipArchive::new(archive).map_err(|e| format!("update archive unreadable: {e}"))?;
+        let mut manifest = Vec::new();
+        for index in 0..zip.len() {
+            let mut entry = zip
+                .by_index(index)
+                .map_err(|e| format!("update archive unreadable: {e}"))?;
+            let Some(name) = entry.enclosed_name().filter(|_| entry.is_file()) else {
+                continue;
+            };
+            let mut bytes = Vec::new();
+            entry
+                .read_to_end(&mut bytes)
+                .map_err(|e| format!("update archive unreadable: {e}"))?;
+            manifest.push((
+                name.to_string_lossy().replace('\\', "/"),
+                sha256_hex(&bytes),
+            ));
+        }
+        Ok(manifest)
+    })())
+}
+
+fn manifest_url(config: &LocalConfig) -> Result<Url, CommandError> {
+    let raw = config.backend_update_url.as_deref().ok_or_else(|| {
+        CommandError::new(
+            ErrorCode::Unsupported,
+            "no backend_update_url is configured",
+        )
+    })?;
+    Url::parse(raw)
+        .ok()
+        .filter(|url| url.scheme() == "https")
+        .ok_or_else(|| {
+            CommandError::invalid_input(format!(
+                "backend_update_url must be an https URL, got `{raw}`"
+            ))
+        })
+}
+
+fn fetch_manifest(config: &LocalConfig) -> Result<(Url, UpdateManifest), CommandError> {
+    let url = manifest_url(config)?;
+    let agent = proxy::outbound_agent(&proxy::resolve(&config.proxy), &url);
+    let manifest: UpdateManifest = agent
+        .get(url.as_str())
+        .call()
+        .map_err(|e| format!("failed fetching update manifest: {e}"))?
+        .into_string()
+        .map_err(|e| format!("failed reading update manifest: {e}"))
+        .and_then(|body| {
+            serde_json::from_str(&body).map_err(|e| format!("invalid update manifest: {e}"))
+        })?;
+    let valid_version = !manifest.version.is_empty()
+        && manifest
+            .version
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'-' | b'_'));
+    if !valid_version {
+        return Err(format!(
+            "update manifest has an invalid version `{}`",
+            manifest.version
+        )
+        .into());
+    }
+    Ok((url, manifest))
+}
+
+/// Copies `reader` into `out` up to `MAX_ARCHIVE_BYTES`, returning the
+/// bytes copied and their SHA-256.
+fn copy_capped(
+    reader: &mut impl Read,
+    out: &mut impl Write,
+    progress: &dyn Fn(u64),
+) -> Result<(u64, String), CommandError> {
+    let too_large = || {
+        integrity_error(format!(
+            "backend update is larger than {MAX_ARCHIVE_BYTES} bytes"
+        ))
+    };
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut received = 0u64;
+    let mut last_reported = 0u64;
+    loop {
+        let read = reader
+            .read(&mut buf)
+            .map_err(|e| format!("backend update download interrupted: {e}"))?;
+        if read == 0 {
+            break;
+        }
+        received += read as u64;
+        if received > MAX_ARCHIVE_BYTES {
+            return Err(too_large());
+        }
+        out.write_all(&buf[..read])
+            .map_err(|e| format!("failed saving backend update: {e}"))?;
+        hasher.update(&buf[..read]);
+        if received - last_reported >= PROGRESS_STEP {
+            last_reported = received;
+            progress(received);
+        }
+    }
+    progress(received);
+    Ok((received, format!("{:x}", hasher.finalize())))
+}
+
+/// Downloads the archive to `temp`, returning its SHA-256.
+fn download(
+    app: &AppHandle,
+    config: &LocalConfig,
+    manifest_url: &Url,
+    manifest: &UpdateManifest,
+    temp: &Path,
+) -> Result<String, CommandError> {
+    let url = manifest_url
+        .join(&manifest.url)
+        .map_err(|e| format!("invalid bundle url: {e}"))?;
+    let agent = proxy::outbound_agent(&proxy::resolve(&config.proxy), &url);
+    let response = agent
+        .get(url.as_str())
+        .call()
+        .map_err(|e| format!("failed downloading backend update: {e}"))?;
+    let total_bytes = response
+        .header("Content-Length")
+        .and_then(|value| value.parse::<u64>().ok());
+    if total_bytes.is_some_and(|total| total > MAX_ARCHIVE_BYTES) {
+        return Err(integrity_error(format!(
+            "backend update is larger than {MAX_ARCHIVE_BYTES} bytes"
+        )));
+    }
+    let progress = |received: u64| {
+        let _ = app.emit(
+            "backend-update-progress",
+            UpdateProgress {
+                version: manifest.version.clone(),
+                bytes_received: received,
+                total_bytes,
+            },
+        );
+    };
+    let mut out = File::create(temp).map_err(|e| format!("failed saving backend update: {e}"))?;
+    let (_, sha256) = copy_capped(&mut response.into_reader(), &mut out, &progress)?;
+    out.sync_all()
+        .map_err(|e| format!("failed saving backend update: {e}"))?;
+    Ok(sha256)
+}
+
+/// Extracts the verified archive at `archive` into `dir` and moves it there
+/// with its signature.
+fn unpack(dir: &Path, archive: &Path, signature: &str) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("failed creating {}: {e}", dir.display()))?;
+    let file = File::open(archive).map_err(|e| format!("failed reading update archive: {e}"))?;
+    let mut zip = zip::ZipArchive::new(file)
+        .map_err(|e| format!("backend update is not a zip archive: {e}"))?;
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| format!("failed reading update archive: {e}"))?;
+        let Some(relative) = entry.enclosed_name() else {
+            return Err(format!(
+                "update archive entry `{}` escapes its folder",
+                entry.name()
+            ));
+        };
+        let target = dir.join(relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&target).map_err(|e| format!("failed extracting update: {e}"))?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("failed extracting update: {e}"))?;
+        }
+        let mut out =
+            File::create(&target).map_err(|e| format!("failed extracting update: {e}"))?;
+        io::copy(&mut entry, &mut out).map_err(|e| format!("failed extracting update: {e}"))?;
+    }
+    if !dir.join("main.py").is_file() {
+        return Err("backend update has no main.py at its root".to_string());
+    }
+    fs::rename(archive, dir.join(ARCHIVE_FILE))
+        .map_err(|e| format!("failed saving archive: {e}"))?;
+    fs::write(dir.join(SIGNATURE_FILE), signature.trim())
+        .map_err(|e| format!("failed saving signature: {e}"))
+}
+
+/// Removes version dirs other than the current and previous one, and any
+/// download an interrupted install left.
+fn prune(root: &Path, versions: &InstalledVersions) {
+    let Ok(entries) = fs::read_dir(root.join(VERSIONS_DIR)) else {
+        return;
+    };
+    let keep = [versions.current.as_deref(), versions.previous.as_deref()];
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if keep.contains(&Some(name.as_str())) {
+            continue;
+        }
+        if entry.path().is_dir() {
+            let _ = fs::remove_dir_all(entry.path());
+        } else {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[tauri::command]
+pub fn check_backend_update(state: State<'_, AppState>) -> Result<UpdateCheck, CommandError> {
+    let config = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        read_local_config(&runtime.data_dir)?
+    };
+    let versions = read_versions(&root()?);
+    let (_, manifest) = fetch_manifest(&config)?;
+    let current = current_version(&versions).to_string();
+    Ok(UpdateCheck {
+        update_available: is_newer(&manifest.version, &current),
+        current_version: current,
+        latest_version: manifest.version,
+    })
+}
+
+/// Downloads, verifies and switches to the newer backend, then restarts it.
+/// If the new backend fails its health check the previous version is
+/// restored and started again.
+// Async because the download blocks until it finishes.
+#[tauri::command]
+pub async fn install_backend_update(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<UpdateInstalled, CommandError> {
+    let config = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        ensure_not_safe_mode(&runtime)?;
+        read_local_config(&runtime.data_dir)?
+    };
+    let root = root()?;
+    let before = read_versions(&root);
+    let previous_version = current_version(&before).to_string();
+    let (manifest_url, manifest) = fetch_manifest(&config)?;
+    if !is_newer(&manifest.version, &previous_version) {
+        return Err(CommandError::conflict(format!(
+            "backend {previous_version} is already up to date"
+        )));
+    }
+
+    let downloads = root.join(VERSIONS_DIR);
+    fs::create_dir_all(&downloads).map_err(|e| format!("failed creating {VERSIONS_DIR}: {e}"))?;
+    let temp = downloads.join(format!(".download-{}.zip", Uuid::new_v4()));
+    let dir = version_dir(&root, &manifest.version);
+    let verified = download(&app, &config, &manifest_url, &manifest, &temp).and_then(|sha256| {
+        if sha256 != manifest.sha256.trim().to_ascii_lowercase() {
+            return Err(integrity_error("backend update checksum does not match"));
+        }
+        verify_signature(&manifest.version, &sha256, &manifest.signature)
+    });
+    if let Err(err) = verified {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    let _ = fs::remove_dir_all(&dir);
+    if let Err(err) = unpack(&dir, &temp, &manifest.signature) {
+        let _ = fs::remove_file(&temp);
+        let _ = fs::remove_dir_all(&dir);
+        return Err(err.into());
+    }
+    let after = InstalledVersions {
+        current: Some(manifest.version.clone()),
+        previous: before.current.clone(),
+    };
+    write_versions(&root, &after)?;
+
+    let starting = spawn_guard::begin()?;
+    let mut runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    runtime.stopped_by_user = false;
+    let started = spawn_backend(&mut runtime, &starting);
+    let details = serde_json::json!({ "from": previous_version, "to": manifest.version });
+    if let Err(err) = started {
+        write_versions(&root, &before)?;
+        let _ = fs::remove_dir_all(&dir);
+        let _ = audit::record(&runtime.data_dir, "backend_update_rolled_back", details);
+        let restored = spawn_backend(&mut runtime, &starting);
+        backend_status::publish(&app, &runtime);
+        let outcome = match restored {
+            Ok(()) => format!("rolled back to {previous_version}"),
+            Err(restore_err) => format!("{previous_version} also failed to start: {restore_err}"),
+        };
+        return Err(CommandError::new(
+            ErrorCode::BackendUnavailable,
+            format!(
+                "backend {} failed to start ({err}); {outcome}",
+                manifest.version
+            ),
+        ));
+    }
+    prune(&root, &after);
+    let _ = audit::record(&runtime.data_dir, "backend_updated", details);
+    session_file::deliver_pending(&app, &mut runtime);
+    action_queue::flush(&app, &runtime);
+    let api = backend_status::publish(&app, &runtime);
+    Ok(UpdateInstalled {
+        version: manifest.version,
+        previous_version,
+        api,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn a_signature_vouches_for_one_version_of_one_archive() {
+        let signing = SigningKey::from_bytes(&[7u8; 32]);
+        let key = signing.verifying_key();
+        let sha256 = sha256_hex(b"archive");
+        let signature = signing.sign(&signed_message("0.2.0", &sha256));
+        let signature = crate::file_ops::encode_hex(&signature.to_bytes());
+
+        assert!(verify_with(&key, "0.2.0", &sha256, &signature).is_ok());
+        // The same archive replayed under a higher version number.
+        let replayed = verify_with(&key, "9.0.0", &sha256, &signature).unwrap_err();
+        assert_eq!(replayed.code, ErrorCode::IntegrityMismatch);
+        let other = sha256_hex(b"other archive");
+        assert!(verify_with(&key, "0.2.0", &other, &signature).is_err());
+        assert!(verify_with(&key, "0.2.0", &sha256, "zz").is_err());
+    }
+
+    #[test]
+    fn downloads_are_hashed_and_capped() {
+        let mut out = Vec::new();
+        let (bytes, sha256) = copy_capped(&mut &b"archive"[..], &mut out, &|_| {}).unwrap();
+        assert_eq!(
+            (bytes, sha256.as_str(), out.as_slice()),
+            (7, sha256_hex(b"archive").as_str(), &b"archive"[..])
+        );
+
+        let mut endless = io::repeat(0).take(MAX_ARCHIVE_BYTES + 1);
+        let err = copy_capped(&mut endless, &mut io::sink(), &|_| {}).unwrap_err();
+        assert_eq!(err.code, ErrorCode::IntegrityMismatch);
+    }
+}
@@ -0,0 +1,449 @@
+//! Zip snapshots of config.json and the history database. `create_backup`
+//! makes one on demand; with `backups.enabled` a scheduler thread makes one
+//! whenever `interval_days` have passed since the time in the `last-backup`
+//! marker, checking at startup and daily after that, and keeps the newest
+//! `keep_count` per profile. A custom `destination` that is not there (an
+//! unplugged drive) makes a scheduled run skip quietly until the next check.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::history_search::history_db_path;
+use crate::tasks::{TaskKind, TaskToken};
+use crate::{
+    commit_config, config_path, desktop_log, paths, read_local_config, safe_write, shutdown,
+    unix_now, AppState, LocalConfig,
+};
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+const CHECK_INTERVAL: Duration = Duration::from_secs(DAY_SECS);
+const MARKER_FILE: &str = "last-backup";
+const FILE_PREFIX: &str = "liteclaw-backup-";
+const MAX_INTERVAL_DAYS: u32 = 365;
+const MAX_KEEP_COUNT: u32 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupsConfig {
+    pub enabled: bool,
+    pub interval_days: u32,
+    pub keep_count: u32,
+    /// Folder backups are written to; `None` uses `<data_dir>/backups`.
+    pub destination: Option<String>,
+}
+
+impl Default for BackupsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_days: 7,
+            keep_count: 8,
+            destination: None,
+        }
+    }
+}
+
+/// Held while a backup runs; a restore, once there is one, must take it
+/// too. The layout migration finishes in `setup`, before the scheduler and
+/// any command can start a backup.
+static EXCLUSIVE: Mutex<()> = Mutex::new(());
+
+fn try_exclusive() -> Option<MutexGuard<'static, ()>> {
+    match EXCLUSIVE.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+fn marker_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(MARKER_FILE)
+}
+
+fn last_backup_at(data_dir: &Path) -> Option<u64> {
+    fs::read_to_string(marker_path(data_dir))
+        .ok()
+        .and_then(|content| content.trim().parse().ok())
+}
+
+fn is_due(data_dir: &Path, config: &BackupsConfig, now: u64) -> bool {
+    let interval = u64::from(config.interval_days.max(1)) * DAY_SECS;
+    last_backup_at(data_dir).is_none_or(|then| now.saturating_sub(then) >= interval)
+}
+
+enum Destination {
+    Ready(PathBuf),
+    /// A configured folder that is missing right now.
+    Unavailable(PathBuf),
+}
+
+fn destination(data_dir: &Path, config: &BackupsConfig) -> Destination {
+    match &config.destination {
+        Some(dir) => {
+            let dir = paths::for_io(Path::new(dir));
+            // Never created: on an unplugged drive that would write to the
+            // bare mount point instead.
+            if dir.is_dir() {
+                Destination::Ready(dir)
+            } else {
+                Destination::Unavailable(dir)
+            }
+        }
+        None => Destination::Ready(data_dir.join("backups")),
+    }
+}
+
+fn profile_prefix(profile: &str) -> String {
+    format!("{FILE_PREFIX}{profile}-")
+}
+
+/// Backup time of a file this profile wrote, from its name.
+fn backup_time(name: &str, prefix: &str) -> Option<u64> {
+    name.strip_prefix(prefix)?
+        .strip_suffix(".zip")?
+        .parse()
+        .ok()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupCompleted {
+    pub path: String,
+    pub size: u64,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub created_at: u64,
+    /// Older backups deleted to stay within `keep_count`.
+    pub pruned: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupFailed {
+    pub error: String,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    #[serde(with = "crate::timestamps::unix_secs")]
+    created_at: u64,
+    app_version: &'static str,
+    profile: &'a str,
+    entries: Vec<&'static str>,
+}
+
+fn add_entry(
+    zip: &mut ZipWriter<File>,
+    options: SimpleFileOptions,
+    name: &str,
+    bytes: &[u8],
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("failed adding {name} to backup: {e}"))?;
+    zip.write_all(bytes)
+        .map_err(|e| format!("failed writing {name} to backup: {e}"))
+}
+
+/// A consistent copy of the history database, taken while the backend may
+/// be writing to it.
+fn snapshot_history(data_dir: &Path, scratch: &Path) -> Result<Option<Vec<u8>>, String> {
+    let path = history_db_path(data_dir);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let _ = fs::remove_file(scratch);
+    let conn =
+        rusqlite::Connection::open_with_flags(&path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("failed opening history database: {e}"))?;
+    conn.execute("VACUUM INTO ?1", [scratch.to_string_lossy()])
+        .map_err(|e| format!("failed copying history database: {e}"))?;
+    drop(conn);
+    let bytes = fs::read(scratch).map_err(|e| format!("failed reading history copy: {e}"));
+    let _ = fs::remove_file(scratch);
+    bytes.map(Some)
+}
+
+fn write_archive(
+    data_dir: &Path,
+    profile: &str,
+    dest: &Path,
+    created_at: u64,
+    task: &TaskToken,
+) -> Result<File, String> {
+    let file = File::create(dest).map_err(|e| format!("failed creating backup file: {e}"))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut entries = Vec::new();
+    // Copied as stored, so an encrypted config stays encrypted.
+    if let Ok(config) = fs::read(config_path(data_dir)) {
+        add_entry(&mut zip, options, "config.json", &config)?;
+        entries.push("config.json");
+    }
+    task.check("backup").map_err(|e| e.message)?;
+    if let Some(history) = snapshot_history(data_dir, &dest.with_extension("db.tmp"))? {
+        add_entry(&mut zip, options, "history.db", &history)?;
+        entries.push("history.db");
+    }
+    let manifest = Manifest {
+        created_at,
+        app_version: env!("CARGO_PKG_VERSION"),
+        profile,
+        entries,
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("failed serializing backup manifest: {e}"))?;
+    add_entry(&mut zip, options, "manifest.json", &manifest)?;
+    zip.finish()
+        .map_err(|e| format!("failed finalizing backup file: {e}"))
+}
+
+/// Space a backup needs next to it before compression: the archive, and the
+/// history copy staged beside it while the archive is written.
+fn estimated_size(data_dir: &Path) -> u64 {
+    let size = |path: PathBuf| fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    size(config_path(data_dir)) + 2 * size(history_db_path(data_dir))
+}
+
+/// Deletes this profile's backups beyond the newest `keep`.
+fn prune(dir: &Path, profile: &str, keep: u32) -> Vec<String> {
+    let prefix = profile_prefix(profile);
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<(u64, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            backup_time(&name, &prefix).map(|at| (at, entry.path()))
+        })
+        .collect();
+    backups.sort_by_key(|(at, _)| Reverse(*at));
+    backups
+        .into_iter()
+        .skip(keep.max(1) as usize)
+        .filter(|(_, path)| fs::remove_file(path).is_ok())
+        .map(|(_, path)| path.to_string_lossy().to_string())
+        .collect()
+}
+
+fn run_backup(
+    data_dir: &Path,
+    profile: &str,
+    config: &BackupsConfig,
+    dir: &Path,
+    task: &TaskToken,
+) -> Result<BackupCompleted, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("failed creating {}: {e}", dir.display()))?;
+    let created_at = unix_now();
+    let dest = dir.join(format!("{}{created_at}.zip", profile_prefix(profile)));
+    safe_write::ensure_space(dir, estimated_size(data_dir))
+        .map_err(|e| format!("failed saving backup: {e}"))?;
+    let partial = paths::temp_sibling(&dest);
+    let written =
+        write_archive(data_dir, profile, &partial, created_at, task).and_then(|mut file| {
+            task.check("backup").map_err(|e| e.message)?;
+            let expected = file
+                .stream_position()
+                .map_err(|e| format!("failed saving backup: {e}"))?;
+            Ok((file, expected))
+        });
+    let (file, expected) = match written {
+        Ok(written) => written,
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+    };
+    safe_write::commit(file, &partial, &dest, expected)
+        .map_err(|e| format!("failed saving backup: {e}"))?;
+    let _ = fs::write(marker_path(data_dir), created_at.to_string());
+    let size = fs::metadata(&dest).map(|meta| meta.len()).unwrap_or(0);
+    Ok(BackupCompleted {
+        path: paths::display_form(&dest),
+        size,
+        created_at,
+        pruned: prune(dir, profile, config.keep_count),
+    })
+}
+
+fn emit_result(app: &AppHandle, data_dir: &Path, result: &Result<BackupCompleted, String>) {
+    match result {
+        Ok(done) => {
+            desktop_log::info(data_dir, &format!("backup written to {}", done.path));
+            let _ = app.emit("backup-completed", done);
+        }
+        Err(error) => {
+            desktop_log::warn(data_dir, &format!("backup failed: {error}"));
+            let _ = app.emit(
+                "backup-failed",
+                BackupFailed {
+                    error: error.clone(),
+                },
+            );
+        }
+    }
+}
+
+fn identity(app: &AppHandle) -> Option<(String, PathBuf)> {
+    let state = app.try_state::<AppState>()?;
+    let runtime = state.runtime.lock().ok()?;
+    Some((runtime.profile.clone(), runtime.data_dir.clone()))
+}
+
+fn check_due(app: &AppHandle) {
+    if shutdown::in_progress() {
+        return;
+    }
+    let Some((profile, data_dir)) = identity(app) else {
+        return;
+    };
+    let Ok(config) = read_local_config(&data_dir).map(|config| config.backups) else {
+        return;
+    };
+    if !config.enabled || !is_due(&data_dir, &config, unix_now()) {
+        return;
+    }
+    let dir = match destination(&data_dir, &config) {
+        Destination::Ready(dir) => dir,
+        Destination::Unavailable(dir) => {
+            desktop_log::info(
+                &data_dir,
+                &format!("backup skipped: {} is not available", dir.display()),
+            );
+            return;
+        }
+    };
+    // A manual backup or a restore is running; try again next check.
+    let Some(_guard) = try_exclusive() else {
+        return;
+    };
+    let state = app.state::<AppState>();
+    let Ok(task) = state
+        .tasks
+        .register(TaskKind::Backup, "Scheduled backup", None)
+    else {
+        return;
+    };
+    let result = run_backup(&data_dir, &profile, &config, &dir, task.token());
+    emit_result(app, &data_dir, &result);
+}
+
+/// Starts the scheduler; called from `setup` once the app state exists.
+pub fn start(app: AppHandle) {
+    thread::spawn(move || loop {
+        check_due(&app);
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[tauri::command]
+pub fn create_backup(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BackupCompleted, CommandError> {
+    let (profile, data_dir) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        (runtime.profile.clone(), runtime.data_dir.clone())
+    };
+    let config = read_local_config(&data_dir)?.backups;
+    let dir = match destination(&data_dir, &config) {
+        Destination::Ready(dir) => dir,
+        Destination::Unavailable(dir) => {
+            return Err(CommandError::not_found(format!(
+                "backup destination {} is not available",
+                dir.display()
+            )));
+        }
+    };
+    let _guard = try_exclusive()
+        .ok_or_else(|| CommandError::conflict("a backup or restore is already running"))?;
+    let task = state.tasks.register(TaskKind::Backup, "Backup", None)?;
+    let result = run_backup(&data_dir, &profile, &config, &dir, task.token());
+    emit_result(&app, &data_dir, &result);
+    Ok(result?)
+}
+
+fn update(
+    state: &State<'_, AppState>,
+    mutate: impl FnOnce(&mut BackupsConfig),
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    mutate(&mut config.backups);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[tauri::command]
+pub fn set_backups_enabled(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    update(&state, |config| config.enabled = enabled)
+}
+
+pub fn validate_interval(interval_days: u32) -> Result<(), CommandError> {
+    if !(1..=MAX_INTERVAL_DAYS).contains(&interval_days) {
+        return Err(CommandError::invalid_input(format!(
+            "backup interval must be between 1 and {MAX_INTERVAL_DAYS} days"
+        )));
+    }
+    Ok(())
+}
+
+pub fn validate_keep_count(keep_count: u32) -> Result<(), CommandError> {
+    if !(1..=MAX_KEEP_COUNT).contains(&keep_count) {
+        return Err(CommandError::invalid_input(format!(
+            "backups kept must be between 1 and {MAX_KEEP_COUNT}"
+        )));
+    }
+    Ok(())
+}
+
+/// An existing folder, canonicalized; `None` stays `None`.
+pub fn validate_destination(destination: Option<String>) -> Result<Option<String>, CommandError> {
+    match destination {
+        Some(dir) => Ok(Some(
+            paths::canonical_dir(dir.trim()).map_err(CommandError::invalid_input)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+pub fn set_backup_schedule(
+    state: State<'_, AppState>,
+    interval_days: u32,
+    keep_count: u32,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    validate_interval(interval_days)?;
+    validate_keep_count(keep_count)?;
+    update(&state, |config| {
+        config.interval_days = interval_days;
+        config.keep_count = keep_count;
+    })
+}
+
+/// `None` goes back to `<data_dir>/backups`.
+#[tauri::command]
+pub fn set_backup_destination(
+    state: State<'_, AppState>,
+    destination: Option<String>,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let destination = validate_destination(destination)?;
+    update(&state, |config| config.destination = destination)
+}
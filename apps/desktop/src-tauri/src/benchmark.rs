@@ -0,0 +1,150 @@
+//! Round-trip latency to the backend, to answer "is it LiteClaw or my
+//! machine?". Each iteration opens a fresh connection so connect time is
+//! part of what gets measured.
+
+use serde::Serialize;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{backend_http, AppState};
+
+pub const MAX_ITERATIONS: u32 = 50;
+/// Iterations used when the benchmark is run for a diagnostics bundle.
+pub const DIAGNOSTICS_ITERATIONS: u32 = 10;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+/// Median total time (ms) at or below which latency is `good` / `ok`. This is
+/// loopback, so anything beyond a few tens of milliseconds is the machine or
+/// a busy backend.
+const GOOD_MS: f64 = 20.0;
+const OK_MS: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyClass {
+    Good,
+    Ok,
+    Slow,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyStats {
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub endpoint: &'static str,
+    pub iterations: u32,
+    pub connect: LatencyStats,
+    pub total: LatencyStats,
+    pub classification: LatencyClass,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Nearest-rank percentile over sorted samples.
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn stats(mut samples: Vec<f64>) -> LatencyStats {
+    samples.sort_by(f64::total_cmp);
+    LatencyStats {
+        min_ms: samples[0],
+        median_ms: percentile(&samples, 50.0),
+        p95_ms: percentile(&samples, 95.0),
+        max_ms: samples[samples.len() - 1],
+    }
+}
+
+fn classify(total: &LatencyStats) -> LatencyClass {
+    if total.median_ms <= GOOD_MS {
+        LatencyClass::Good
+    } else if total.median_ms <= OK_MS {
+        LatencyClass::Ok
+    } else {
+        LatencyClass::Slow
+    }
+}
+
+/// Runs `iterations` sequential health requests. Fails on the first request
+/// that does not succeed, so an unreachable backend costs one timeout.
+pub fn run_benchmark(
+    base_url: &str,
+    token: &str,
+    iterations: u32,
+) -> Result<BenchmarkReport, String> {
+    let iterations = iterations.clamp(1, MAX_ITERATIONS);
+    let parsed = url::Url::parse(base_url).map_err(|e| format!("invalid backend url: {e}"))?;
+    let addr = parsed
+        .socket_addrs(|| None)
+        .ok()
+        .and_then(|addrs| addrs.into_iter().next())
+        .ok_or_else(|| format!("cannot resolve backend address {base_url}"))?;
+    let health_url = format!("{base_url}/v1/health");
+
+    let mut connect = Vec::new();
+    let mut total = Vec::new();
+    for _ in 0..iterations {
+        let started = Instant::now();
+        TcpStream::connect_timeout(&addr, REQUEST_TIMEOUT)
+            .map_err(|e| format!("connecting to backend failed: {e}"))?;
+        connect.push(millis(started.elapsed()));
+
+        // A new agent per iteration: no pooled connection to hide connect cost.
+        let agent = backend_http::builder().timeout(REQUEST_TIMEOUT).build();
+        let started = Instant::now();
+        agent
+            .get(&health_url)
+            .set("Authorization", &format!("Bearer {token}"))
+            .call()
+            .map_err(|e| format!("health request failed: {e}"))?
+            .into_string()
+            .map_err(|e| format!("failed reading health response: {e}"))?;
+        total.push(millis(started.elapsed()));
+    }
+
+    let total = stats(total);
+    Ok(BenchmarkReport {
+        endpoint: "/v1/health",
+        iterations,
+        connect: stats(connect),
+        classification: classify(&total),
+        total,
+    })
+}
+
+#[tauri::command]
+pub async fn benchmark_backend(
+    state: State<'_, AppState>,
+    iterations: u32,
+) -> Result<BenchmarkReport, CommandError> {
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(CommandError::invalid_input(format!(
+            "iterations must be between 1 and {MAX_ITERATIONS}"
+        )));
+    }
+    let (base_url, token) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        if !runtime.backend_ready {
+            return Err(CommandError::new(
+                ErrorCode::BackendUnavailable,
+                "backend is not ready",
+            ));
+        }
+        (runtime.base_url.clone(), runtime.token.clone())
+    };
+    run_benchmark(&base_url, &token, iterations)
+        .map_err(|err| CommandError::new(ErrorCode::BackendUnavailable, err))
+}
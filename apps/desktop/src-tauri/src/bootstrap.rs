@@ -0,0 +1,142 @@
+//! The `bootstrap` event: what a window needs for its first frame, pushed to
+//! that window once its page has loaded so it does not start by pulling
+//! `get_api_config` and `get_local_config` (both still work) and drawing
+//! whatever transitional state they catch.
+//!
+//! A spawn holds the runtime lock until the backend answers, and page loads
+//! must not wait on it. While the lock is taken the window gets the last
+//! `ApiConfig` any command built, corrected by the start gate: a start in
+//! progress is `spawning`, never the readiness of the backend it replaces.
+//! A seamless restart keeps the running backend serving, so it stays `ready`.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::integrations::IntegrationStatus;
+use crate::startup_phase::{self, StartupPhase};
+use crate::{
+    api_config, local_config_view, spawn_guard, ApiConfig, AppState, BackendRuntime, BackendState,
+    LocalConfig,
+};
+
+/// The last `ApiConfig` built, and the data dir it was built from.
+static LAST: Mutex<Option<(PathBuf, ApiConfig)>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lifecycle {
+    Spawning,
+    Ready,
+    Degraded,
+    Stopped,
+    StoppedByUser,
+    Errored,
+}
+
+impl From<BackendState> for Lifecycle {
+    fn from(state: BackendState) -> Self {
+        match state {
+            BackendState::Ready => Lifecycle::Ready,
+            BackendState::Degraded => Lifecycle::Degraded,
+            BackendState::Starting => Lifecycle::Spawning,
+            BackendState::Stopped => Lifecycle::Stopped,
+            BackendState::StoppedByUser => Lifecycle::StoppedByUser,
+            BackendState::Errored => Lifecycle::Errored,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LifecycleEvent {
+    pub state: Lifecycle,
+    pub startup_phase: Option<StartupPhase>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Bootstrap {
+    pub api_config: ApiConfig,
+    pub local_config: LocalConfig,
+    pub integrations: Option<IntegrationStatus>,
+    pub lifecycle: LifecycleEvent,
+}
+
+/// Called by `api_config`, so the cache always holds what a command last
+/// returned.
+pub fn remember(data_dir: &Path, api: &ApiConfig) {
+    if let Ok(mut last) = LAST.lock() {
+        *last = Some((data_dir.to_path_buf(), api.clone()));
+    }
+}
+
+/// The `ApiConfig` to push and where config lives: built from the runtime
+/// when it is free, otherwise the cached one. `spawning` is whether a start
+/// the window can see is running.
+fn current(runtime: &Mutex<BackendRuntime>, spawning: bool) -> Option<(PathBuf, ApiConfig)> {
+    if let Ok(runtime) = runtime.try_lock() {
+        return Some((runtime.data_dir.clone(), api_config(&runtime)));
+    }
+    let (data_dir, mut api) = LAST.lock().ok()?.clone()?;
+    if spawning {
+        api.status.backend_ready = false;
+        api.status.backend_state = BackendState::Starting;
+        api.status.startup_phase = startup_phase::latest();
+    }
+    Some((data_dir, api))
+}
+
+fn build(state: &AppState) -> Option<Bootstrap> {
+    let spawning = spawn_guard::is_starting() && !startup_phase::is_quiet();
+    let (data_dir, api_config) = current(&state.runtime, spawning)?;
+    let local_config = local_config_view(&data_dir).ok()?;
+    Some(Bootstrap {
+        lifecycle: LifecycleEvent {
+            state: api_config.status.backend_state.into(),
+            startup_phase: api_config.status.startup_phase.clone(),
+        },
+        integrations: state.integrations.lock().ok().map(|status| status.clone()),
+        local_config,
+        api_config,
+    })
+}
+
+/// Sends `bootstrap` to the window `label` alone. Nothing is sent when the
+/// state cannot be read yet; the window then falls back to the commands.
+pub fn send(app: &AppHandle, label: &str) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    if let Some(bootstrap) = build(&state) {
+        let _ = app.emit_to(label, "bootstrap", bootstrap);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_window_created_mid_spawn_sees_spawning() {
+        let data_dir = std::env::temp_dir().join("liteclaw-bootstrap");
+        let mut ready = BackendRuntime::new(data_dir, "default".to_string());
+        ready.backend_ready = true;
+        let runtime = Mutex::new(ready);
+
+        let (_, before) = current(&runtime, false).unwrap();
+        assert_eq!(
+            Lifecycle::from(before.status.backend_state),
+            Lifecycle::Ready
+        );
+
+        // The spawn holds the lock for as long as the new backend takes.
+        let spawn = runtime.lock().unwrap();
+        let (_, during) = current(&runtime, true).unwrap();
+        assert_eq!(
+            Lifecycle::from(during.status.backend_state),
+            Lifecycle::Spawning
+        );
+        assert!(!during.status.backend_ready);
+        drop(spawn);
+    }
+}
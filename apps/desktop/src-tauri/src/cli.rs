@@ -0,0 +1,418 @@
+//! `liteclaw config ...`, for provisioning scripts that set LiteClaw up
+//! without opening a window:
+//!
+//! ```text
+//! liteclaw config show [--json]
+//! liteclaw config set <key> <value> [--json]
+//! liteclaw config add-folder <path> [--allow-root] [--no-follow-symlinks] [--json]
+//! ```
+//!
+//! `main` dispatches these before the Tauri builder runs. They go through
+//! the same code as the settings page: `set` turns `shell.enabled false`
+//! into the merge patch `{"shell": {"enabled": false}}` for
+//! `config_validation::apply`, and `add-folder` is `add_allowed_folder`.
+//! A value is read as JSON when it parses and as a string otherwise, so
+//! `null` resets a setting to its default. Any other first arguments,
+//! `config` with an unknown subcommand among them, start the app as
+//! before, so existing launchers keep working.
+//!
+//! When a LiteClaw on this machine owns the data dir, the change is written
+//! beside it and the executable is launched again with `--config-changed`:
+//! the single-instance plugin forwards that to the running window, which
+//! re-reads the config and reloads the backend. A LiteClaw owning the data
+//! dir from another machine cannot be told, so nothing is written then.
+//!
+//! Exit codes: 0 done, 1 failed, 2 bad usage, 3 refused (validation, a
+//! folder that cannot be allowed), 4 the data dir is owned elsewhere.
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{
+    add_folder, config_diff, config_validation, data_dir_lock, desktop_log, layout,
+    local_config_view, open_data_dir, read_local_config, AppState, BackendRuntime,
+};
+
+pub const CONFIG_CHANGED_FLAG: &str = "--config-changed";
+/// How long a forwarded launch may take to exit before it is taken for a
+/// new window, the running one having quit meanwhile.
+const NOTICE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const EXIT_OK: i32 = 0;
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_REFUSED: i32 = 3;
+const EXIT_LOCKED: i32 = 4;
+
+const USAGE: &str = "usage:
+  liteclaw config show [--json]
+  liteclaw config set <key> <value> [--json]
+  liteclaw config add-folder <path> [--allow-root] [--no-follow-symlinks] [--json]
+
+<key> is a dotted setting such as shell.enabled; <value> is JSON, or
+taken as a string when it is not. null resets a setting to its default.";
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    Show,
+    Set {
+        key: String,
+        value: String,
+    },
+    AddFolder {
+        path: String,
+        allow_root: bool,
+        follow_symlinks: bool,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+struct Invocation {
+    action: Action,
+    json: bool,
+}
+
+/// What `args` asks for: `None` when it is no `config` command, so the app
+/// starts; `Some(Err)` when it is one used wrongly.
+fn parse(args: &[String]) -> Option<Result<Invocation, String>> {
+    if args.get(1).map(String::as_str) != Some("config") {
+        return None;
+    }
+    let subcommand = args.get(2)?.as_str();
+    if !matches!(subcommand, "show" | "set" | "add-folder") {
+        return None;
+    }
+    let mut json = false;
+    let mut allow_root = false;
+    let mut follow_symlinks = true;
+    let mut positional = Vec::new();
+    for arg in &args[3..] {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--allow-root" if subcommand == "add-folder" => allow_root = true,
+            "--no-follow-symlinks" if subcommand == "add-folder" => follow_symlinks = false,
+            flag if flag.starts_with("--") => return Some(Err(format!("unknown option {flag}"))),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let action = match (subcommand, positional.as_slice()) {
+        ("show", []) => Action::Show,
+        ("set", [key, value]) => Action::Set {
+            key: key.clone(),
+            value: value.clone(),
+        },
+        ("add-folder", [path]) => Action::AddFolder {
+            path: path.clone(),
+            allow_root,
+            follow_symlinks,
+        },
+        _ => return Some(Err(format!("wrong arguments for `config {subcommand}`"))),
+    };
+    Some(Ok(Invocation { action, json }))
+}
+
+/// The merge patch that sets the dotted `key` to `value`.
+fn patch_for(key: &str, value: &str) -> Result<Value, String> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.trim().is_empty()) {
+        return Err(format!("`{key}` is not a setting name"));
+    }
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok(parts.iter().rev().fold(value, |inner, part| {
+        let mut object = Map::new();
+        object.insert(part.to_string(), inner);
+        Value::Object(object)
+    }))
+}
+
+/// Release builds are GUI programs on Windows and start without a console;
+/// the one of the shell that started them is borrowed for the output.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: no arguments but a constant; fails harmlessly with a console.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn exit_code(err: &CommandError) -> i32 {
+    match err.code {
+        ErrorCode::Internal
+        | ErrorCode::StorageUnavailable
+        | ErrorCode::DiskFull
+        | ErrorCode::ConfigKeyMissing => EXIT_FAILED,
+        ErrorCode::DataDirLocked => EXIT_LOCKED,
+        _ => EXIT_REFUSED,
+    }
+}
+
+/// A JSON value as `set` would take it back.
+fn display(value: &Value) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn print_json(value: &impl Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    );
+}
+
+/// One line per change in a serialized `ConfigDiff`.
+fn print_diff(diff: &Value) {
+    let changes = diff["changes"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    if changes.is_empty() {
+        println!("Nothing changed.");
+    }
+    for change in changes {
+        let shown = |value: &Value| match value {
+            _ if change["redacted"] == json!(true) => "[redacted]".to_string(),
+            Value::Null => "(unset)".to_string(),
+            value => display(value),
+        };
+        println!(
+            "{}: {} -> {}",
+            change["path"].as_str().unwrap_or_default(),
+            shown(&change["old"]),
+            shown(&change["new"])
+        );
+    }
+}
+
+/// Launches the executable with `CONFIG_CHANGED_FLAG` for the running
+/// window to pick up. True once the launch was forwarded.
+fn notify_running() -> bool {
+    let Ok(exe) = std::env::current_exe() else {
+        return false;
+    };
+    let Ok(mut child) = Command::new(exe)
+        .arg(CONFIG_CHANGED_FLAG)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return false;
+    };
+    let deadline = Instant::now() + NOTICE_TIMEOUT;
+    while Instant::now() < deadline {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
+/// Refuses changes under a LiteClaw that cannot be told about them.
+fn ensure_reachable(owner: Option<&data_dir_lock::Owner>) -> Result<(), CommandError> {
+    match owner.filter(|owner| !data_dir_lock::is_local(owner)) {
+        Some(owner) => Err(CommandError::new(
+            ErrorCode::DataDirLocked,
+            format!("the data folder is in use by {}", owner.describe()),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn has_changes(result: &Value) -> bool {
+    result["diff"]["changes"]
+        .as_array()
+        .is_some_and(|changes| !changes.is_empty())
+}
+
+fn execute(invocation: &Invocation) -> Result<Value, CommandError> {
+    let app_data_dir = layout::app_data_dir()
+        .ok_or_else(|| "cannot tell where LiteClaw keeps its data".to_string())?;
+    let (profile, data_dir) = open_data_dir(&app_data_dir)?;
+    let owner = data_dir_lock::live_owner(&data_dir);
+    let serialized = match &invocation.action {
+        Action::Show => return to_value(local_config_view(&data_dir)?),
+        Action::Set { key, value } => {
+            ensure_reachable(owner.as_ref())?;
+            let patch = patch_for(key, value).map_err(CommandError::invalid_input)?;
+            to_value(config_validation::apply(&data_dir, patch)?)
+        }
+        Action::AddFolder {
+            path,
+            allow_root,
+            follow_symlinks,
+        } => {
+            ensure_reachable(owner.as_ref())?;
+            let runtime = BackendRuntime::new(data_dir.clone(), profile);
+            to_value(add_folder(&runtime, path, *follow_symlinks, *allow_root)?)
+        }
+    };
+    let mut result = serialized?;
+    let notified = owner.is_some() && has_changes(&result) && notify_running();
+    if let Some(fields) = result.as_object_mut() {
+        fields.insert("running".to_string(), json!(owner.is_some()));
+        fields.insert("notified".to_string(), json!(notified));
+    }
+    Ok(result)
+}
+
+fn to_value(result: impl Serialize) -> Result<Value, CommandError> {
+    Ok(serde_json::to_value(result).map_err(|e| format!("failed serializing result: {e}"))?)
+}
+
+fn report(invocation: &Invocation, result: &Value) {
+    if invocation.json {
+        print_json(result);
+        return;
+    }
+    if let Action::Show = invocation.action {
+        for (path, value) in config_diff::settings(result) {
+            let value = value.as_ref().map_or("[redacted]".to_string(), display);
+            println!("{path} = {value}");
+        }
+        return;
+    }
+    print_diff(&result["diff"]);
+    if let Action::AddFolder { .. } = invocation.action {
+        match result["location_kind"].as_str() {
+            Some("read_only_mount") => eprintln!("warning: the folder is on a read-only mount"),
+            Some("fuse") => {
+                eprintln!("warning: the folder is on a FUSE filesystem; it may be slow")
+            }
+            _ => {}
+        }
+    }
+    if result["running"] == json!(true) && has_changes(result) {
+        if result["notified"] == json!(true) {
+            eprintln!("The running LiteClaw reloaded the config.");
+        } else {
+            eprintln!("LiteClaw is running but could not be told; restart it to apply this.");
+        }
+    }
+}
+
+/// Runs a `config` command line and returns its exit code, or `None` when
+/// `args` is not one and the app should start.
+pub fn run(args: &[String]) -> Option<i32> {
+    let parsed = parse(args)?;
+    attach_console();
+    let invocation = match parsed {
+        Ok(invocation) => invocation,
+        Err(err) => {
+            eprintln!("liteclaw: {err}\n\n{USAGE}");
+            return Some(EXIT_USAGE);
+        }
+    };
+    match execute(&invocation) {
+        Ok(result) => {
+            report(&invocation, &result);
+            Some(EXIT_OK)
+        }
+        Err(err) => {
+            if invocation.json {
+                print_json(&json!({ "error": err }));
+            } else {
+                eprintln!("liteclaw: {err}");
+            }
+            Some(exit_code(&err))
+        }
+    }
+}
+
+pub fn is_config_notice(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == CONFIG_CHANGED_FLAG)
+}
+
+/// Takes up a config another process wrote, as `update_local_config` takes
+/// up its own.
+pub fn serve_config_notice(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let state = app.state::<AppState>();
+        let Ok(runtime) = state.runtime.lock() else {
+            return;
+        };
+        let data_dir = runtime.data_dir.clone();
+        let applied = read_local_config(&data_dir)
+            .and_then(|config| config_validation::take_effect(&app, runtime, &config));
+        if let Err(err) = applied {
+            desktop_log::warn(&data_dir, &format!("config from the command line: {err}"));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn config_commands_are_parsed_and_anything_else_starts_the_app() {
+        assert_eq!(
+            parse(&args("liteclaw config set shell.enabled false --json")),
+            Some(Ok(Invocation {
+                action: Action::Set {
+                    key: "shell.enabled".to_string(),
+                    value: "false".to_string(),
+                },
+                json: true,
+            }))
+        );
+        assert_eq!(
+            parse(&args("liteclaw config add-folder /srv/docs --allow-root")),
+            Some(Ok(Invocation {
+                action: Action::AddFolder {
+                    path: "/srv/docs".to_string(),
+                    allow_root: true,
+                    follow_symlinks: true,
+                },
+                json: false,
+            }))
+        );
+        assert!(matches!(
+            parse(&args("liteclaw config set shell.enabled")),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            parse(&args("liteclaw config show --allow-root")),
+            Some(Err(_))
+        ));
+        for line in [
+            "liteclaw",
+            "liteclaw --safe-mode",
+            "liteclaw config",
+            "liteclaw config edit",
+        ] {
+            assert_eq!(parse(&args(line)), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn dotted_keys_become_nested_patches() {
+        assert_eq!(
+            patch_for("shell.enabled", "false").unwrap(),
+            json!({ "shell": { "enabled": false } })
+        );
+        assert_eq!(
+            patch_for("proxy.http_proxy", "http://proxy:3128").unwrap(),
+            json!({ "proxy": { "http_proxy": "http://proxy:3128" } })
+        );
+        assert_eq!(
+            patch_for("locale", "null").unwrap(),
+            json!({ "locale": null })
+        );
+        assert!(patch_for("shell..enabled", "1").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! One-shot clipboard capture for "ask about my clipboard". Contents are never
+//! logged or audited; images are staged as PNG attachments.
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+
+use crate::attachments;
+use crate::config_diff::ConfigChange;
+use crate::error::{CommandError, ErrorCode};
+use crate::{commit_config, read_local_config, AppState, LocalConfig};
+
+pub const DEFAULT_MAX_TEXT_CHARS: usize = 20_000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClipboardCapture {
+    Text {
+        content: String,
+        truncated: bool,
+    },
+    Image {
+        id: String,
+        staged_path: String,
+        width: usize,
+        height: usize,
+    },
+    Empty,
+}
+
+/// Truncates on a char boundary, returning whether anything was dropped.
+pub fn truncate_chars(text: &str, max_chars: usize) -> (String, bool) {
+    match text.char_indices().nth(max_chars) {
+        Some((cut, _)) => (text[..cut].to_string(), true),
+        None => (text.to_string(), false),
+    }
+}
+
+fn capture(data_dir: &Path, config: &LocalConfig) -> Result<ClipboardCapture, CommandError> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| format!("clipboard unavailable: {e}"))?;
+    match clipboard.get_text() {
+        Ok(text) if !text.trim().is_empty() => {
+            let (content, truncated) = truncate_chars(&text, config.clipboard_max_chars);
+            return Ok(ClipboardCapture::Text { content, truncated });
+        }
+        Ok(_) | Err(arboard::Error::ContentNotAvailable) => {}
+        Err(err) => return Err(format!("failed reading clipboard text: {err}").into()),
+    }
+    match clipboard.get_image() {
+        Ok(image) => {
+            let bytes =
+                attachments::encode_png(image.width as u32, image.height as u32, &image.bytes)?;
+            let staged = attachments::stage_bytes(data_dir, "clipboard.png", &bytes)?;
+            Ok(ClipboardCapture::Image {
+                id: staged.id,
+                staged_path: staged.staged_path,
+                width: image.width,
+                height: image.height,
+            })
+        }
+        Err(arboard::Error::ContentNotAvailable) => Ok(ClipboardCapture::Empty),
+        Err(err) => Err(format!("failed reading clipboard image: {err}").into()),
+    }
+}
+
+#[tauri::command]
+pub fn capture_clipboard(state: State<'_, AppState>) -> Result<ClipboardCapture, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let config = read_local_config(&runtime.data_dir)?;
+    if !config.allow_clipboard_capture {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            "clipboard capture is disabled in settings",
+        ));
+    }
+    capture(&runtime.data_dir, &config)
+}
+
+pub fn validate_max_chars(max_text_chars: usize) -> Result<(), CommandError> {
+    if max_text_chars == 0 {
+        return Err(CommandError::invalid_input(
+            "max_text_chars must be greater than zero",
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_clipboard_capture(
+    state: State<'_, AppState>,
+    enabled: bool,
+    max_text_chars: Option<usize>,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    if let Some(limit) = max_text_chars {
+        validate_max_chars(limit)?;
+    }
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.allow_clipboard_capture = enabled;
+    if let Some(limit) = max_text_chars {
+        config.clipboard_max_chars = limit;
+    }
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
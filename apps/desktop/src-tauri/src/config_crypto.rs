@@ -0,0 +1,206 @@
+//! Opt-in encryption of config.json at rest (`encrypt_config`). The file is
+//! then an envelope, `{"encrypted": true, "version", "nonce", "ciphertext"}`,
+//! sealed with XChaCha20-Poly1305 under a key kept in the OS keychain. A
+//! plain config never has a top-level `encrypted` key, so the two forms are
+//! told apart by that key alone. The backend cannot open the envelope; while
+//! encryption is on, the desktop sends it the config with every reload.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::confirmation::{self, Confirmable, Summary};
+use crate::error::{CommandError, ErrorCode};
+use crate::file_ops::{decode_hex, encode_hex};
+use crate::{
+    audit, backend_reload_config, commit_config, config_path, read_local_config, unix_now,
+    write_config_atomic, AppState, LocalConfig,
+};
+
+pub const KEYRING_SERVICE: &str = "LiteClaw";
+const KEYRING_ACCOUNT: &str = "config-encryption-key";
+const ENVELOPE_VERSION: u32 = 1;
+const NONCE_LEN: usize = 24;
+const MALFORMED_KEY: &str = "the OS keychain holds a malformed config key";
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    encrypted: bool,
+    version: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// True for an encrypted envelope, false for a plain config (or garbage).
+pub fn is_envelope(content: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(content)
+        .ok()
+        .and_then(|value| value.get("encrypted").and_then(serde_json::Value::as_bool))
+        == Some(true)
+}
+
+fn entry() -> Result<keyring::Entry, CommandError> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_ACCOUNT)
+        .map_err(|e| format!("OS keychain unavailable: {e}").into())
+}
+
+fn stored_key() -> Result<Option<Key>, CommandError> {
+    match entry()?.get_password() {
+        Ok(hex) => decode_hex(&hex)
+            .filter(|bytes| bytes.len() == 32)
+            .map(|bytes| Some(*Key::from_slice(&bytes)))
+            .ok_or_else(|| CommandError::from(MALFORMED_KEY.to_string())),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => {
+            Err(format!("failed reading the config key from the OS keychain: {err}").into())
+        }
+    }
+}
+
+fn key_for_writing() -> Result<Key, CommandError> {
+    if let Some(key) = stored_key()? {
+        return Ok(key);
+    }
+    let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+    entry()?
+        .set_password(&encode_hex(&key))
+        .map_err(|e| format!("failed storing the config key in the OS keychain: {e}"))?;
+    Ok(key)
+}
+
+fn key_missing(message: &str) -> CommandError {
+    CommandError::new(
+        ErrorCode::ConfigKeyMissing,
+        format!("{message}; reset the config with `reset_encrypted_config` to continue"),
+    )
+}
+
+/// The plain config JSON in `content`, decrypting it if it is an envelope.
+pub fn decode(content: String) -> Result<String, CommandError> {
+    if !is_envelope(&content) {
+        return Ok(content);
+    }
+    let envelope: Envelope =
+        serde_json::from_str(&content).map_err(|e| format!("invalid encrypted config: {e}"))?;
+    if envelope.version != ENVELOPE_VERSION {
+        return Err(format!(
+            "encrypted config version {} is not supported",
+            envelope.version
+        )
+        .into());
+    }
+    let key = stored_key()?.ok_or_else(|| {
+        key_missing("config.json is encrypted but its key is missing from the OS keychain")
+    })?;
+    let nonce = decode_hex(&envelope.nonce)
+        .filter(|nonce| nonce.len() == NONCE_LEN)
+        .ok_or_else(|| "invalid encrypted config nonce".to_string())?;
+    let ciphertext = decode_hex(&envelope.ciphertext)
+        .ok_or_else(|| "invalid encrypted config ciphertext".to_string())?;
+    let plain = XChaCha20Poly1305::new(&key)
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| key_missing("the key in the OS keychain does not open config.json"))?;
+    String::from_utf8(plain).map_err(|e| format!("decrypted config is not UTF-8: {e}").into())
+}
+
+/// The bytes to store for `plain`: an envelope when `encrypt` is set.
+pub fn encode(plain: Vec<u8>, encrypt: bool) -> Result<Vec<u8>, CommandError> {
+    if !encrypt {
+        return Ok(plain);
+    }
+    let key = key_for_writing()?;
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, plain.as_slice())
+        .map_err(|_| "failed encrypting config".to_string())?;
+    let envelope = Envelope {
+        encrypted: true,
+        version: ENVELOPE_VERSION,
+        nonce: encode_hex(&nonce),
+        ciphertext: encode_hex(&ciphertext),
+    };
+    serde_json::to_vec_pretty(&envelope)
+        .map_err(|e| format!("failed serializing encrypted config: {e}").into())
+}
+
+#[tauri::command]
+pub fn set_config_encryption(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.encrypt_config = enabled;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    // The backend stops reading the file once it is encrypted.
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigReset {
+    /// Where the unreadable envelope was moved.
+    pub backup_path: String,
+    pub config: LocalConfig,
+}
+
+/// Recovery for `config_key_missing`: moves the envelope aside and starts
+/// over with a default config. Needs confirming; see `confirmation`.
+#[tauri::command]
+pub fn reset_encrypted_config(
+    state: State<'_, AppState>,
+    confirm_token: Option<String>,
+) -> Result<Confirmable<ConfigReset>, CommandError> {
+    confirmation::guarded(
+        &state,
+        "reset_encrypted_config",
+        "",
+        confirm_token,
+        |data_dir| {
+            let path = config_path(data_dir);
+            let content =
+                fs::read_to_string(&path).map_err(|e| format!("failed reading config: {e}"))?;
+            if !is_envelope(&content) {
+                return Err(CommandError::conflict("config.json is not encrypted"));
+            }
+            Ok(Summary::new(
+                "Moves the encrypted config.json aside and starts over with default settings.",
+            )
+            .path(&path))
+        },
+        || reset_encrypted(&state),
+    )
+}
+
+fn reset_encrypted(state: &State<'_, AppState>) -> Result<ConfigReset, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let path = config_path(&runtime.data_dir);
+    let content = fs::read_to_string(&path).map_err(|e| format!("failed reading config: {e}"))?;
+    if !is_envelope(&content) {
+        return Err(CommandError::conflict("config.json is not encrypted"));
+    }
+    let backup = path.with_file_name(format!("config.{}.encrypted.json", unix_now()));
+    fs::rename(&path, &backup).map_err(|e| format!("failed moving encrypted config aside: {e}"))?;
+    let config = LocalConfig::default();
+    write_config_atomic(&runtime.data_dir, &config)?;
+    let backup_path = backup.to_string_lossy().to_string();
+    let _ = audit::record(
+        &runtime.data_dir,
+        "encrypted_config_reset",
+        serde_json::json!({ "backup_path": backup_path }),
+    );
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigReset {
+        backup_path,
+        config,
+    })
+}
@@ -0,0 +1,709 @@
+//! Whole-config edits from the settings page. `update_local_config` applies
+//! a JSON merge patch (RFC 7396: objects merge, anything else replaces,
+//! `null` resets to the default) to the current config and writes it only
+//! when `prepare` found nothing wrong; `validate_config_patch` runs the same
+//! `prepare` and stops there, so the page can show per-field errors while
+//! the user types without writing or reloading anything.
+//!
+//! `prepare` checks each changed setting with the validator its dedicated
+//! command uses and keeps what that validator normalizes (trimmed patterns,
+//! canonical folder paths). Settings left as they are are not re-checked,
+//! so a folder on an unplugged drive does not block unrelated edits.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::MutexGuard;
+use tauri::{AppHandle, State};
+
+use crate::config_diff::{self, ConfigChange, ConfigDiff};
+use crate::error::{CommandError, ErrorCode};
+use crate::proxy::ProxyMode;
+use crate::{
+    attachments, backend_env, backend_reload_config, backups, clipboard, commit_config,
+    data_dir_lock, folders, ignore_rules, locale, loopback, native_messaging, proxy,
+    read_local_config, request_limiter, response_limit, shell_policy, slow_commands, status_server,
+    wsl, AppState, BackendRuntime, LocalConfig,
+};
+
+/// Settings with a flow of their own that a patch must not bypass.
+const MANAGED: &[(&str, &str)] = &[
+    ("encrypt_config", "set_config_encryption"),
+    ("model_selection", "set_model_settings"),
+    ("onboarding", "complete_onboarding"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    /// Dotted path as in `config_diff`: `proxy.http_proxy`,
+    /// `allowed_folders[/home/me/src].alias`.
+    pub field: String,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatchValidation {
+    pub valid: bool,
+    pub errors: Vec<FieldError>,
+    /// The config the patch would produce; settings that failed to parse
+    /// keep their current value.
+    pub config: LocalConfig,
+    pub diff: ConfigDiff,
+}
+
+/// Recursive merge; `null` removes the key, which deserializing then fills
+/// with the default.
+fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn push(&mut self, field: impl Into<String>, err: CommandError) {
+        self.0.push(FieldError {
+            field: field.into(),
+            code: err.code,
+            message: err.message,
+        });
+    }
+
+    /// The validator's value, or `None` with the error recorded.
+    fn check<T>(&mut self, field: &str, result: Result<T, CommandError>) -> Option<T> {
+        result.map_err(|err| self.push(field, err)).ok()
+    }
+}
+
+fn to_map(config: &LocalConfig) -> Result<Map<String, Value>, CommandError> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err("config did not serialize to an object".to_string().into()),
+        Err(err) => Err(format!("failed serializing config: {err}").into()),
+    }
+}
+
+/// Merges `patch` into `current` one top-level setting at a time, so a
+/// setting of the wrong shape is reported by name and the rest still apply.
+fn merge_settings(
+    current: &LocalConfig,
+    patch: Value,
+    errors: &mut Errors,
+) -> Result<LocalConfig, CommandError> {
+    let Value::Object(patch) = patch else {
+        return Err(CommandError::invalid_input(
+            "config patch must be a JSON object",
+        ));
+    };
+    let known = to_map(&LocalConfig::default())?;
+    let mut merged = to_map(current)?;
+    for (key, value) in patch {
+        if !known.contains_key(&key) {
+            errors.push(key, CommandError::invalid_input("unknown setting"));
+            continue;
+        }
+        if let Some((_, command)) = MANAGED.iter().find(|(managed, _)| *managed == key) {
+            errors.push(
+                key,
+                CommandError::new(
+                    ErrorCode::NotAllowed,
+                    format!("change this with `{command}`"),
+                ),
+            );
+            continue;
+        }
+        let mut candidate = merged.clone();
+        let mut setting = candidate.remove(&key).unwrap_or(Value::Null);
+        merge(&mut setting, &value);
+        if !setting.is_null() {
+            candidate.insert(key.clone(), setting);
+        }
+        match serde_json::from_value::<LocalConfig>(Value::Object(candidate.clone())) {
+            Ok(_) => merged = candidate,
+            Err(err) => errors.push(key, CommandError::invalid_input(err.to_string())),
+        }
+    }
+    serde_json::from_value(Value::Object(merged))
+        .map_err(|e| format!("invalid config json: {e}").into())
+}
+
+/// Top-level settings whose value differs between the two configs.
+fn changed_settings(previous: &LocalConfig, config: &LocalConfig) -> BTreeSet<String> {
+    let (Ok(previous), Ok(config)) = (to_map(previous), to_map(config)) else {
+        return BTreeSet::new();
+    };
+    config
+        .into_iter()
+        .filter(|(key, value)| previous.get(key) != Some(value))
+        .map(|(key, _)| key)
+        .collect()
+}
+
+fn check_folders(previous: &LocalConfig, config: &mut LocalConfig, errors: &mut Errors) {
+    let known: Vec<String> = previous
+        .allowed_folders
+        .iter()
+        .map(|folder| folder.path.clone())
+        .collect();
+    for index in 0..config.allowed_folders.len() {
+        if previous
+            .allowed_folders
+            .contains(&config.allowed_folders[index])
+        {
+            continue;
+        }
+        let folder = &mut config.allowed_folders[index];
+        let field = |name: &str| format!("allowed_folders[{}].{name}", folder.path);
+        let (path, alias, note, patterns) = (
+            field("path"),
+            field("alias"),
+            field("note"),
+            field("ignore_patterns"),
+        );
+        if !known.contains(&folder.path) {
+            let resolved =
+                folders::resolve_folder_input(&folder.path, folder.follow_symlinks, false);
+            if let Some((stored, _)) = errors.check(&path, resolved) {
+                folder.path = stored;
+            }
+        }
+        if let Some(cleaned) = errors.check(&alias, folders::clean_alias(folder.alias.clone())) {
+            folder.alias = cleaned;
+        }
+        if let Some(cleaned) = errors.check(&note, folders::clean_note(folder.note.clone())) {
+            folder.note = cleaned;
+        }
+        let validated = ignore_rules::validate_patterns(&folder.ignore_patterns);
+        if let Some(cleaned) = errors.check(&patterns, validated) {
+            folder.ignore_patterns = cleaned;
+        }
+    }
+    let paths: Vec<String> = config
+        .allowed_folders
+        .iter()
+        .map(|folder| folder.path.clone())
+        .collect();
+    for (index, folder) in config.allowed_folders.iter().enumerate() {
+        let field = |name: &str| format!("allowed_folders[{}].{name}", folder.path);
+        if paths[..index].contains(&folder.path) {
+            errors.push(
+                field("path"),
+                CommandError::invalid_input(format!("listed more than once: {}", folder.path)),
+            );
+        } else if !known.contains(&folder.path) {
+            errors.check(
+                &field("path"),
+                folders::ensure_not_nested(&paths, &folder.path),
+            );
+        }
+        if let Some(alias) = &folder.alias {
+            errors.check(
+                &field("alias"),
+                folders::ensure_alias_free(config, alias, index),
+            );
+        }
+    }
+}
+
+/// Applies `patch` to `current` and checks the result; see the module docs.
+/// Fails outright only for a patch that is not an object or a data dir this
+/// run may not write.
+fn prepare(current: &LocalConfig, patch: Value) -> Result<PatchValidation, CommandError> {
+    data_dir_lock::ensure_writable()?;
+    let mut errors = Errors(Vec::new());
+    let mut config = merge_settings(current, patch, &mut errors)?;
+    let changed = changed_settings(current, &config);
+    let changed = |setting: &str| changed.contains(setting);
+
+    if changed("allowed_folders") {
+        check_folders(current, &mut config, &mut errors);
+    }
+    if changed("shell") {
+        let shell = &mut config.shell;
+        let auto = "shell.auto_approve_patterns";
+        let deny = "shell.always_deny_patterns";
+        let patterns = std::mem::take(&mut shell.auto_approve_patterns);
+        let validated = shell_policy::validate_patterns(patterns.clone(), "auto_approve_patterns");
+        shell.auto_approve_patterns = errors.check(auto, validated).unwrap_or(patterns);
+        let patterns = std::mem::take(&mut shell.always_deny_patterns);
+        let validated = shell_policy::validate_patterns(patterns.clone(), "always_deny_patterns");
+        shell.always_deny_patterns = errors.check(deny, validated).unwrap_or(patterns);
+    }
+    if changed("attachments") {
+        let limit = attachments::validate_size_limit(config.attachments.max_size_mb);
+        errors.check("attachments.max_size_mb", limit);
+    }
+    if changed("clipboard_max_chars") {
+        let limit = clipboard::validate_max_chars(config.clipboard_max_chars);
+        errors.check("clipboard_max_chars", limit);
+    }
+    if changed("proxy") {
+        let settings = &mut config.proxy;
+        for (field, value) in [
+            ("proxy.http_proxy", &mut settings.http_proxy),
+            ("proxy.https_proxy", &mut settings.https_proxy),
+        ] {
+            if let Some(url) = value.as_deref() {
+                if let Some(url) = errors.check(field, proxy_url(url)) {
+                    *value = url;
+                }
+            }
+        }
+        if let Some(list) = settings.no_proxy.as_deref() {
+            if let Some(list) = errors.check("proxy.no_proxy", proxy::validate_no_proxy(list)) {
+                settings.no_proxy = Some(list).filter(|list| !list.is_empty());
+            }
+        }
+        if settings.mode == ProxyMode::Manual {
+            let manual = proxy::validate_manual(&settings.http_proxy, &settings.https_proxy);
+            errors.check("proxy.mode", manual);
+        }
+    }
+    if changed("env_passthrough") {
+        let keys = std::mem::take(&mut config.env_passthrough);
+        let validated = backend_env::validate_passthrough(keys.clone());
+        config.env_passthrough = errors.check("env_passthrough", validated).unwrap_or(keys);
+    }
+    if changed("env_extra") {
+        errors.check("env_extra", backend_env::validate_extra(&config.env_extra));
+    }
+    if changed("loopback_host") {
+        if let Some(host) = &config.loopback_host {
+            errors.check("loopback_host", loopback::validate_host(host));
+        }
+    }
+    if changed("backend_host") {
+        errors.check(
+            "backend_host",
+            wsl::validate_backend_host(config.backend_host),
+        );
+    }
+    if changed("wsl_distro") {
+        if let Some(distro) = &config.wsl_distro {
+            errors.check("wsl_distro", wsl::validate_distro(distro));
+        }
+    }
+    if changed("locale") {
+        if let Some(tag) = config.locale.take() {
+            let normalized = errors.check("locale", locale::normalize(&tag));
+            config.locale = Some(normalized.unwrap_or(tag));
+        }
+    }
+    if changed("native_messaging") {
+        let messaging = &mut config.native_messaging;
+        for (field, ids, firefox) in [
+            (
+                "native_messaging.chrome_extension_ids",
+                &mut messaging.chrome_extension_ids,
+                false,
+            ),
+            (
+                "native_messaging.firefox_extension_ids",
+                &mut messaging.firefox_extension_ids,
+                true,
+            ),
+        ] {
+            let validated = native_messaging::validate_extension_ids(ids.clone(), firefox);
+            if let Some(valid) = errors.check(field, validated) {
+                *ids = valid;
+            }
+        }
+    }
+    if changed("performance") {
+        let limits = request_limiter::validate(&config.performance.concurrency);
+        errors.check("performance.concurrency", limits);
+    }
+    if changed("status_server") {
+        let port = status_server::validate_port(config.status_server.port);
+        errors.check("status_server.port", port);
+    }
+    if changed("backups") {
+        let backups_config = &mut config.backups;
+        let interval = backups::validate_interval(backups_config.interval_days);
+        errors.check("backups.interval_days", interval);
+        let keep = backups::validate_keep_count(backups_config.keep_count);
+        errors.check("backups.keep_count", keep);
+        let destination = backups::validate_destination(backups_config.destination.clone());
+        if let Some(destination) = errors.check("backups.destination", destination) {
+            backups_config.destination = destination;
+        }
+    }
+
+    let errors = errors.0;
+    Ok(PatchValidation {
+        valid: errors.is_empty(),
+        diff: config_diff::diff(current, &config),
+        errors,
+        config,
+    })
+}
+
+/// An empty string clears the URL, as in `set_manual_proxy`.
+fn proxy_url(url: &str) -> Result<Option<String>, CommandError> {
+    if url.trim().is_empty() {
+        return Ok(None);
+    }
+    proxy::validate_proxy_url(url).map(Some)
+}
+
+/// One error for a rejected patch, naming every failing setting.
+fn rejection(errors: &[FieldError]) -> CommandError {
+    let code = errors
+        .first()
+        .map_or(ErrorCode::InvalidInput, |err| err.code);
+    let message = errors
+        .iter()
+        .map(|err| format!("{}: {}", err.field, err.message))
+        .collect::<Vec<_>>()
+        .join("; ");
+    CommandError::new(code, message)
+}
+
+/// `update_local_config` without the reload, for the data dir alone; the
+/// `config set` command line uses it as it is.
+pub fn apply(data_dir: &Path, patch: Value) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let current = read_local_config(data_dir)?;
+    let checked = prepare(&current, patch)?;
+    if !checked.valid {
+        return Err(rejection(&checked.errors));
+    }
+    let diff = commit_config(data_dir, &checked.config)?;
+    Ok(ConfigChange::new(checked.config, diff))
+}
+
+/// Applies a merge patch to the whole config. Nothing is written unless
+/// every changed setting passes; the error then names each failure, as
+/// `validate_config_patch` lists them.
+#[tauri::command]
+pub fn update_local_config(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    patch: Value,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let change = apply(&runtime.data_dir, patch)?;
+    take_effect(&app, runtime, &change.result)?;
+    Ok(change)
+}
+
+/// Hands a written `config` to the backend and to the settings this
+/// process applies itself. Releases `runtime` before the status server
+/// restarts.
+pub fn take_effect(
+    app: &AppHandle,
+    runtime: MutexGuard<'_, BackendRuntime>,
+    config: &LocalConfig,
+) -> Result<(), CommandError> {
+    backend_reload_config(&runtime, config)?;
+    slow_commands::configure(config.slow_command_ms);
+    response_limit::configure(config.max_response_bytes);
+    request_limiter::configure(&config.performance.concurrency);
+    let data_dir = runtime.data_dir.clone();
+    drop(runtime);
+    status_server::apply(app, &data_dir, &config.status_server);
+    Ok(())
+}
+
+/// What `update_local_config` would do with `patch`, without doing it.
+#[tauri::command]
+pub fn validate_config_patch(
+    state: State<'_, AppState>,
+    patch: Value,
+) -> Result<PatchValidation, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    prepare(&read_local_config(&runtime.data_dir)?, patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn validate_and_update_agree_on_every_failure() {
+        let root = std::env::temp_dir().join(format!("liteclaw-patch-{}", uuid::Uuid::new_v4()));
+        let data_dir = root.join("data");
+        let project = root.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(&data_dir).unwrap();
+        let project = paths_string(&project);
+        let nested = format!("{project}/src");
+        let mut config = read_local_config(&data_dir).unwrap();
+        config.allowed_folders = vec![folders::AllowedFolder::new(project.clone())];
+        commit_config(&data_dir, &config).unwrap();
+        let on_disk = || serde_json::to_value(read_local_config(&data_dir).unwrap()).unwrap();
+        let before = on_disk();
+
+        let failures = [
+            (json!({ "no_such_setting": true }), "no_such_setting"),
+            (json!({ "encrypt_config": true }), "encrypt_config"),
+            (json!({ "history_enabled": "yes" }), "history_enabled"),
+            (
+                json!({ "attachments": { "max_size_mb": 0 } }),
+                "attachments.max_size_mb",
+            ),
+            (
+                json!({ "status_server": { "port": 80 } }),
+                "status_server.port",
+            ),
+            (json!({ "proxy": { "mode": "manual" } }), "proxy.mode"),
+            (
+                json!({ "proxy": { "http_proxy": "ftp://x" } }),
+                "proxy.http_proxy",
+            ),
+            (
+                json!({ "env_extra": { "LITECLAW_PORT": "1" } }),
+                "env_extra",
+            ),
+            (json!({ "loopback_host": "0.0.0.0" }), "loopback_host"),
+            (
+                json!({ "backups": { "keep_count": 0 } }),
+                "backups.keep_count",
+            ),
+            (
+                json!({ "shell": { "always_deny_patterns": ["*"] } }),
+                "shell.always_deny_patterns",
+            ),
+            (
+                json!({ "allowed_folders": [{ "path": format!("{project}/missing") }] }),
+                "allowed_folders[",
+            ),
+            (
+                json!({ "allowed_folders": [{ "path": project }, { "path": nested }] }),
+                "allowed_folders[",
+            ),
+            (
+                json!({ "allowed_folders": [{ "path": project, "ignore_patterns": ["   "] }] }),
+                "ignore_patterns",
+            ),
+        ];
+        for (patch, field) in failures {
+            let checked = prepare(&read_local_config(&data_dir).unwrap(), patch.clone()).unwrap();
+            assert!(!checked.valid, "{patch} passed validation");
+            assert!(
+                checked.errors.iter().any(|err| err.field.contains(field)),
+                "{patch}: {:?}",
+                checked.errors
+            );
+            let err = apply(&data_dir, patch.clone()).unwrap_err();
+            assert_eq!(err.message, rejection(&checked.errors).message, "{patch}");
+            assert_eq!(on_disk(), before, "{patch} was written");
+        }
+
+        let patch = json!({ "clipboard_max_chars": 500, "shell": { "enabled": true } });
+        let checked = prepare(&read_local_config(&data_dir).unwrap(), patch.clone()).unwrap();
+        assert!(checked.valid, "{:?}", checked.errors);
+        let written = apply(&data_dir, patch).unwrap();
+        assert_eq!(
+            serde_json::to_value(&written.result).unwrap(),
+            serde_json::to_value(&checked.config).unwrap()
+        );
+        assert_eq!(on_disk()["clipboard_max_chars"], 500);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    /// Runs `patch` through the dry run and the write path and checks both
+    /// refuse it for `field` with `code`, leaving the config on disk as is.
+    fn assert_both_reject(data_dir: &Path, patch: Value, field: &str, code: ErrorCode) {
+        let before = serde_json::to_value(read_local_config(data_dir).unwrap()).unwrap();
+        let checked = prepare(&read_local_config(data_dir).unwrap(), patch.clone()).unwrap();
+        assert!(!checked.valid, "{patch} passed validation");
+        let error = checked.errors.iter().find(|err| err.field == field);
+        assert_eq!(
+            error.map(|err| err.code),
+            Some(code),
+            "{patch}: {:?}",
+            checked.errors
+        );
+        let err = apply(data_dir, patch.clone()).unwrap_err();
+        assert_eq!(err.code, rejection(&checked.errors).code, "{patch}");
+        assert!(err.message.contains(field), "{patch}: {}", err.message);
+        let after = serde_json::to_value(read_local_config(data_dir).unwrap()).unwrap();
+        assert_eq!(after, before, "{patch} was written");
+    }
+
+    fn empty_data_dir() -> std::path::PathBuf {
+        let data_dir =
+            std::env::temp_dir().join(format!("liteclaw-patch-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&data_dir).unwrap();
+        data_dir
+    }
+
+    #[test]
+    fn managed_and_mistyped_settings_are_refused_by_both_paths() {
+        let data_dir = empty_data_dir();
+        let cases = [
+            (
+                json!({ "encrypt_config": true }),
+                "encrypt_config",
+                ErrorCode::NotAllowed,
+            ),
+            (
+                json!({ "model_selection": {} }),
+                "model_selection",
+                ErrorCode::NotAllowed,
+            ),
+            (
+                json!({ "onboarding": { "completed": true } }),
+                "onboarding",
+                ErrorCode::NotAllowed,
+            ),
+            (
+                json!({ "history_enabled": "yes" }),
+                "history_enabled",
+                ErrorCode::InvalidInput,
+            ),
+            (
+                json!({ "clipboard_max_chars": -1 }),
+                "clipboard_max_chars",
+                ErrorCode::InvalidInput,
+            ),
+            (
+                json!({ "status_server": { "port": "8080" } }),
+                "status_server",
+                ErrorCode::InvalidInput,
+            ),
+            (
+                json!({ "env_extra": ["A=1"] }),
+                "env_extra",
+                ErrorCode::InvalidInput,
+            ),
+        ];
+        for (patch, field, code) in cases {
+            assert_both_reject(&data_dir, patch, field, code);
+        }
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn out_of_range_values_are_refused_by_both_paths() {
+        let data_dir = empty_data_dir();
+        let cases = [
+            (
+                json!({ "attachments": { "max_size_mb": 0 } }),
+                "attachments.max_size_mb",
+            ),
+            (
+                json!({ "attachments": { "max_size_mb": 1025 } }),
+                "attachments.max_size_mb",
+            ),
+            (json!({ "clipboard_max_chars": 0 }), "clipboard_max_chars"),
+            (
+                json!({ "status_server": { "port": 1023 } }),
+                "status_server.port",
+            ),
+            (
+                json!({ "backups": { "interval_days": 0 } }),
+                "backups.interval_days",
+            ),
+            (
+                json!({ "backups": { "interval_days": 366 } }),
+                "backups.interval_days",
+            ),
+            (
+                json!({ "backups": { "keep_count": 101 } }),
+                "backups.keep_count",
+            ),
+            (
+                json!({ "performance": { "concurrency": { "interactive": 0 } } }),
+                "performance.concurrency",
+            ),
+            (
+                json!({ "performance": { "concurrency": { "max_queue": 1001 } } }),
+                "performance.concurrency",
+            ),
+        ];
+        for (patch, field) in cases {
+            assert_both_reject(&data_dir, patch, field, ErrorCode::InvalidInput);
+        }
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn unknown_keys_are_refused_by_both_paths_and_block_the_rest() {
+        let data_dir = empty_data_dir();
+        let cases = [
+            (json!({ "no_such_setting": true }), "no_such_setting"),
+            (json!({ "Clipboard_Max_Chars": 500 }), "Clipboard_Max_Chars"),
+            (json!({ "clipboard_max_chars": 500, "extra": 1 }), "extra"),
+        ];
+        for (patch, field) in cases {
+            assert_both_reject(&data_dir, patch, field, ErrorCode::InvalidInput);
+        }
+
+        // Every failure is named, not just the first.
+        let patch = json!({ "first": 1, "attachments": { "max_size_mb": 0 }, "second": 2 });
+        let checked = prepare(&read_local_config(&data_dir).unwrap(), patch.clone()).unwrap();
+        let fields: Vec<&str> = checked
+            .errors
+            .iter()
+            .map(|err| err.field.as_str())
+            .collect();
+        assert_eq!(fields, ["first", "second", "attachments.max_size_mb"]);
+        assert_eq!(
+            apply(&data_dir, patch).unwrap_err().message,
+            rejection(&checked.errors).message
+        );
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn accepted_patches_write_what_the_dry_run_showed() {
+        let data_dir = empty_data_dir();
+        let patches = [
+            json!({ "clipboard_max_chars": 500 }),
+            json!({ "attachments": { "max_size_mb": 1024 } }),
+            json!({ "status_server": { "port": 1024 } }),
+            json!({ "backups": { "interval_days": 365, "keep_count": 1 } }),
+            json!({ "shell": { "auto_approve_patterns": ["  git status  "] } }),
+            json!({ "clipboard_max_chars": null }),
+        ];
+        for patch in patches {
+            let current = read_local_config(&data_dir).unwrap();
+            let checked = prepare(&current, patch.clone()).unwrap();
+            assert!(checked.valid, "{patch}: {:?}", checked.errors);
+            let written = apply(&data_dir, patch.clone()).unwrap();
+            let shown = serde_json::to_value(&checked.config).unwrap();
+            assert_eq!(
+                serde_json::to_value(&written.result).unwrap(),
+                shown,
+                "{patch}"
+            );
+            assert_eq!(
+                serde_json::to_value(&written.diff).unwrap(),
+                serde_json::to_value(&checked.diff).unwrap(),
+                "{patch}"
+            );
+            let on_disk = serde_json::to_value(read_local_config(&data_dir).unwrap()).unwrap();
+            assert_eq!(on_disk, shown, "{patch}");
+        }
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    fn paths_string(path: &Path) -> String {
+        path.canonicalize().unwrap().to_string_lossy().into_owned()
+    }
+}
@@ -0,0 +1,201 @@
+//! Two-step confirmation for commands that destroy data, so a frontend bug
+//! cannot wipe anything with a single IPC call. Called without a token, a
+//! guarded command changes nothing and returns `requires_confirmation` with
+//! a `confirm_token` and a summary of exactly what it would do. Called again
+//! within `CONFIRM_WINDOW` with that token, it does it. Tokens are single use
+//! and bound to the command, its target and the profile they were issued in,
+//! so a token for one profile's history cannot clear another's. Both steps
+//! are audited. Guarding a command is one `guarded` call around its body.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{audit, AppState};
+
+pub const CONFIRM_WINDOW: Duration = Duration::from_secs(60);
+/// Unconfirmed requests kept at once; the oldest is dropped past this.
+const MAX_PENDING: usize = 16;
+
+/// What a confirmed call would do, for the confirmation dialog.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Summary {
+    pub description: String,
+    pub counts: BTreeMap<&'static str, u64>,
+    pub paths: Vec<String>,
+}
+
+impl Summary {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn count(mut self, name: &'static str, value: u64) -> Self {
+        self.counts.insert(name, value);
+        self
+    }
+
+    pub fn path(mut self, path: &Path) -> Self {
+        self.paths.push(path.to_string_lossy().into_owned());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfirmationRequired {
+    /// Always true; lets the frontend tell this apart from a result.
+    pub requires_confirmation: bool,
+    pub confirm_token: String,
+    pub action: &'static str,
+    pub summary: Summary,
+    pub expires_in_secs: u64,
+}
+
+/// A guarded command's response: the request for confirmation, or the
+/// command's own result once confirmed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Confirmable<T> {
+    Pending(ConfirmationRequired),
+    Done(T),
+}
+
+struct Pending {
+    token: String,
+    action: &'static str,
+    subject: String,
+    data_dir: PathBuf,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+pub struct Confirmations {
+    pending: Mutex<Vec<Pending>>,
+}
+
+impl Confirmations {
+    fn issue(
+        &self,
+        action: &'static str,
+        subject: &str,
+        data_dir: &Path,
+    ) -> Result<String, String> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|_| "confirmation lock poisoned".to_string())?;
+        let now = Instant::now();
+        pending.retain(|entry| entry.expires_at > now);
+        let excess = (pending.len() + 1).saturating_sub(MAX_PENDING);
+        pending.drain(..excess);
+        let token = Uuid::new_v4().to_string();
+        pending.push(Pending {
+            token: token.clone(),
+            action,
+            subject: subject.to_string(),
+            data_dir: data_dir.to_path_buf(),
+            expires_at: now + CONFIRM_WINDOW,
+        });
+        Ok(token)
+    }
+
+    /// Spends `token` whatever the outcome, so a rejected token cannot be
+    /// retried against another target.
+    fn redeem(
+        &self,
+        token: &str,
+        action: &'static str,
+        subject: &str,
+        data_dir: &Path,
+    ) -> Result<(), CommandError> {
+        let mut pending = self
+            .pending
+            .lock()
+            .map_err(|_| "confirmation lock poisoned".to_string())?;
+        let now = Instant::now();
+        let found = pending
+            .iter()
+            .position(|entry| entry.token == token)
+            .map(|index| pending.remove(index));
+        pending.retain(|entry| entry.expires_at > now);
+        let invalid = |message: &str| {
+            Err(CommandError::new(
+                ErrorCode::ConfirmationInvalid,
+                format!("{message}; ask for confirmation again"),
+            ))
+        };
+        match found {
+            None => invalid("unknown or already used confirm_token"),
+            Some(entry) if entry.expires_at <= now => invalid("confirm_token has expired"),
+            Some(entry)
+                if entry.action != action
+                    || entry.subject != subject
+                    || entry.data_dir != data_dir =>
+            {
+                invalid("confirm_token was issued for a different request")
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+/// Runs `perform` only when `confirm_token` was issued by an earlier call
+/// for the same `action` and `subject` (whatever identifies the target,
+/// such as a profile name). Without a token it returns the request for
+/// confirmation built by `summary`, which gets the active data dir.
+pub fn guarded<T>(
+    state: &State<'_, AppState>,
+    action: &'static str,
+    subject: &str,
+    confirm_token: Option<String>,
+    summary: impl FnOnce(&Path) -> Result<Summary, CommandError>,
+    perform: impl FnOnce() -> Result<T, CommandError>,
+) -> Result<Confirmable<T>, CommandError> {
+    let data_dir = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?
+        .data_dir
+        .clone();
+    let Some(token) = confirm_token else {
+        let summary = summary(&data_dir)?;
+        let confirm_token = state.confirmations.issue(action, subject, &data_dir)?;
+        let _ = audit::record(
+            &data_dir,
+            "confirmation_requested",
+            serde_json::json!({ "action": action, "subject": subject, "summary": summary }),
+        );
+        return Ok(Confirmable::Pending(ConfirmationRequired {
+            requires_confirmation: true,
+            confirm_token,
+            action,
+            summary,
+            expires_in_secs: CONFIRM_WINDOW.as_secs(),
+        }));
+    };
+    if let Err(err) = state
+        .confirmations
+        .redeem(&token, action, subject, &data_dir)
+    {
+        let _ = audit::record(
+            &data_dir,
+            "confirmation_rejected",
+            serde_json::json!({ "action": action, "subject": subject, "reason": err.message }),
+        );
+        return Err(err);
+    }
+    let _ = audit::record(
+        &data_dir,
+        "confirmation_accepted",
+        serde_json::json!({ "action": action, "subject": subject }),
+    );
+    perform().map(Confirmable::Done)
+}
@@ -0,0 +1,347 @@
+//! Native export of a conversation fetched from the backend to markdown, HTML
+//! or JSON. Attachments are copied next to the export for the document
+//! formats so the file stays readable on its own.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::tasks::{TaskKind, TaskToken};
+use crate::{backend_http, session_file, AppState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+    /// Versioned JSON session file that can be opened with the app.
+    Liteclaw,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttachmentRef {
+    pub path: String,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Message {
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub attachments: Vec<AttachmentRef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportResult {
+    pub path: String,
+    pub bytes_written: usize,
+    pub attachments_copied: usize,
+}
+
+fn backend_unavailable(message: impl Into<String>) -> CommandError {
+    CommandError::new(ErrorCode::BackendUnavailable, message)
+}
+
+fn valid_conversation_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn fetch_conversation(
+    base_url: &str,
+    token: &str,
+    conversation_id: &str,
+) -> Result<serde_json::Value, CommandError> {
+    let endpoint = format!("/v1/conversations/{conversation_id}");
+    let url = format!("{base_url}{endpoint}");
+    let request = backend_http::agent()
+        .get(&url)
+        .set("Authorization", &format!("Bearer {token}"));
+    let sent = backend_http::send_idempotent(&endpoint, request, None);
+    backend_http::rejected(&sent.result)?;
+    let note = sent.attempts_note();
+    match sent.result {
+        Ok(resp) => {
+            let body = resp
+                .into_string()
+                .map_err(|e| format!("failed reading conversation: {e}"))?;
+            serde_json::from_str(&body)
+                .map_err(|e| format!("invalid conversation payload: {e}").into())
+        }
+        Err(ureq::Error::Status(404, _)) => Err(CommandError::not_found(format!(
+            "conversation not found: {conversation_id}"
+        ))),
+        Err(ureq::Error::Status(code, _)) => {
+            Err(format!("fetching conversation failed: HTTP {code}{note}").into())
+        }
+        Err(ureq::Error::Transport(err)) => Err(backend_unavailable(format!(
+            "backend is unreachable{note}: {err}"
+        ))),
+    }
+}
+
+fn title_of(conversation: &Conversation) -> String {
+    conversation
+        .title
+        .clone()
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| format!("Conversation {}", conversation.id))
+}
+
+fn role_label(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => "Unknown".to_string(),
+    }
+}
+
+/// Copies referenced attachments into `files_dir` and rewrites their paths in
+/// message content to links relative to the export file.
+fn copy_attachments(
+    conversation: &mut Conversation,
+    files_dir: &Path,
+    link_prefix: &str,
+    task: &TaskToken,
+) -> Result<usize, CommandError> {
+    let total = conversation
+        .messages
+        .iter()
+        .map(|message| message.attachments.len() as u64)
+        .sum();
+    let mut seen = 0;
+    let mut copied = 0;
+    for message in &mut conversation.messages {
+        for attachment in &mut message.attachments {
+            task.check("export")?;
+            task.report(seen, Some(total));
+            seen += 1;
+            let source = Path::new(&attachment.path);
+            let Some(file_name) = source.file_name() else {
+                continue;
+            };
+            if !source.is_file() {
+                continue;
+            }
+            fs::create_dir_all(files_dir)
+                .map_err(|e| format!("failed creating attachments folder: {e}"))?;
+            let stored = format!("{copied}-{}", file_name.to_string_lossy());
+            fs::copy(source, files_dir.join(&stored))
+                .map_err(|e| format!("failed copying attachment {}: {e}", attachment.path))?;
+            let link = format!("{link_prefix}/{stored}");
+            message.content = message.content.replace(&attachment.path, &link);
+            if attachment.name.is_none() {
+                attachment.name = Some(file_name.to_string_lossy().to_string());
+            }
+            attachment.path = link;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+pub fn render_markdown(conversation: &Conversation) -> String {
+    let mut out = format!("# {}\n", title_of(conversation));
+    for message in &conversation.messages {
+        out.push_str(&format!("\n## {}\n\n", role_label(&message.role)));
+        // Content is already markdown; emitting it verbatim keeps fenced code
+        // blocks intact.
+        out.push_str(message.content.trim_end());
+        out.push('\n');
+        if !message.attachments.is_empty() {
+            out.push('\n');
+            for attachment in &message.attachments {
+                let name = attachment.name.as_deref().unwrap_or(&attachment.path);
+                out.push_str(&format!("- [{name}]({})\n", attachment.path));
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_content_html(content: &str) -> String {
+    let mut out = String::new();
+    let mut in_fence = false;
+    let mut paragraph: Vec<String> = Vec::new();
+    let flush = |paragraph: &mut Vec<String>, out: &mut String| {
+        if !paragraph.is_empty() {
+            out.push_str(&format!("<p>{}</p>\n", paragraph.join("<br>")));
+            paragraph.clear();
+        }
+    };
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            if in_fence {
+                out.push_str("</code></pre>\n");
+            } else {
+                flush(&mut paragraph, &mut out);
+                out.push_str("<pre><code>");
+            }
+            in_fence = !in_fence;
+        } else if in_fence {
+            out.push_str(&escape_html(line));
+            out.push('\n');
+        } else if line.trim().is_empty() {
+            flush(&mut paragraph, &mut out);
+        } else {
+            paragraph.push(escape_html(line));
+        }
+    }
+    if in_fence {
+        out.push_str("</code></pre>\n");
+    }
+    flush(&mut paragraph, &mut out);
+    out
+}
+
+pub fn render_html(conversation: &Conversation) -> String {
+    let title = escape_html(&title_of(conversation));
+    let mut body = String::new();
+    for message in &conversation.messages {
+        body.push_str(&format!(
+            "<section class=\"message {}\">\n<h2>{}</h2>\n",
+            escape_html(&message.role),
+            escape_html(&role_label(&message.role))
+        ));
+        body.push_str(&render_content_html(&message.content));
+        if !message.attachments.is_empty() {
+            body.push_str("<ul class=\"attachments\">\n");
+            for attachment in &message.attachments {
+                let name = attachment.name.as_deref().unwrap_or(&attachment.path);
+                body.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a></li>\n",
+                    escape_html(&attachment.path),
+                    escape_html(name)
+                ));
+            }
+            body.push_str("</ul>\n");
+        }
+        body.push_str("</section>\n");
+    }
+    format!(
+        "<!doctype html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>body{{font-family:system-ui,sans-serif;max-width:48rem;margin:2rem auto;padding:0 1rem}}\
+         pre{{background:#f4f4f4;padding:.75rem;overflow-x:auto}}</style>\n</head>\n<body>\n\
+         <h1>{title}</h1>\n{body}</body>\n</html>\n"
+    )
+}
+
+fn write_atomic(dest: &Path, bytes: &[u8]) -> Result<(), CommandError> {
+    let parent = dest
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = dest
+        .file_name()
+        .ok_or_else(|| CommandError::invalid_input("destination has no file name"))?
+        .to_string_lossy()
+        .to_string();
+    let temp = parent.join(format!(".{file_name}.{}.tmp", Uuid::new_v4()));
+    fs::write(&temp, bytes).map_err(|e| format!("failed writing export: {e}"))?;
+    if let Err(err) = fs::rename(&temp, dest) {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("failed replacing {}: {err}", dest.display()).into());
+    }
+    Ok(())
+}
+
+pub fn export_to(
+    raw: serde_json::Value,
+    dest: &Path,
+    format: ExportFormat,
+    task: &TaskToken,
+) -> Result<ExportResult, CommandError> {
+    let (bytes, attachments_copied) = match format {
+        ExportFormat::Json => {
+            let bytes = serde_json::to_vec_pretty(&raw)
+                .map_err(|e| format!("failed serializing conversation: {e}"))?;
+            (bytes, 0)
+        }
+        ExportFormat::Liteclaw => {
+            let bytes = serde_json::to_vec_pretty(&session_file::wrap_conversation(raw))
+                .map_err(|e| format!("failed serializing session: {e}"))?;
+            (bytes, 0)
+        }
+        ExportFormat::Markdown | ExportFormat::Html => {
+            let mut conversation: Conversation = serde_json::from_value(raw)
+                .map_err(|e| format!("unexpected conversation shape: {e}"))?;
+            let stem = dest
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| "conversation".to_string());
+            let folder_name = format!("{stem}_files");
+            let files_dir: PathBuf = dest.with_file_name(&folder_name);
+            let copied = copy_attachments(&mut conversation, &files_dir, &folder_name, task)?;
+            let rendered = if format == ExportFormat::Markdown {
+                render_markdown(&conversation)
+            } else {
+                render_html(&conversation)
+            };
+            (rendered.into_bytes(), copied)
+        }
+    };
+    task.check("export")?;
+    write_atomic(dest, &bytes)?;
+    Ok(ExportResult {
+        path: dest.to_string_lossy().to_string(),
+        bytes_written: bytes.len(),
+        attachments_copied,
+    })
+}
+
+#[tauri::command]
+pub fn export_conversation(
+    state: State<'_, AppState>,
+    conversation_id: String,
+    dest_path: String,
+    format: ExportFormat,
+) -> Result<ExportResult, CommandError> {
+    if !valid_conversation_id(&conversation_id) {
+        return Err(CommandError::invalid_input(format!(
+            "invalid conversation id: {conversation_id}"
+        )));
+    }
+    let (base_url, token) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        if !runtime.backend_ready {
+            return Err(backend_unavailable("backend is not running"));
+        }
+        (runtime.base_url.clone(), runtime.token.clone())
+    };
+    let task = state.tasks.register(
+        TaskKind::Export,
+        format!("Exporting conversation {conversation_id}"),
+        None,
+    )?;
+    let raw = fetch_conversation(&base_url, &token, &conversation_id)?;
+    export_to(raw, Path::new(&dest_path), format, task.token())
+}
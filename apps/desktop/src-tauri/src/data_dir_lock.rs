@@ -0,0 +1,384 @@
+//! One writer per data dir. A profile on a synced drive can be open on two
+//! machines at once, and the second would overwrite the first's config and
+//! history. At startup and on every profile switch the data dir's
+//! `owner.json` (hostname, pid, heartbeat) is claimed, with an OS advisory
+//! lock on `owner.lock` where the platform has one. Advisory locks do not
+//! cross a sync service, so an owner on another host counts as live while
+//! its heartbeat, refreshed every `REFRESH_EVERY`, is younger than
+//! `STALE_AFTER`. An owner on this host is live while its process is.
+//!
+//! Losing the claim puts the app in read-only mode: no backend is spawned,
+//! config writes fail with `data_dir_locked` naming the owner, and the
+//! window gets `read-only-mode`. `force_takeover` breaks the claim, after
+//! confirming, for an owner that is gone for good.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::backend_status;
+use crate::confirmation::{self, Confirmable, Summary};
+use crate::error::{CommandError, ErrorCode};
+use crate::{
+    audit, desktop_log, safe_write, start_subsystems, timestamps, unix_now, ApiConfig, AppState,
+};
+
+const OWNER_FILE: &str = "owner.json";
+const LOCK_FILE: &str = "owner.lock";
+const REFRESH_EVERY: Duration = Duration::from_secs(30);
+const STALE_AFTER: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Owner {
+    pub hostname: String,
+    pub pid: u32,
+    /// Tells two runs with a reused pid apart.
+    pub instance: String,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub heartbeat_at: u64,
+}
+
+impl Owner {
+    pub fn describe(&self) -> String {
+        format!(
+            "LiteClaw on {} (pid {}, last seen {})",
+            self.hostname,
+            self.pid,
+            timestamps::from_unix_secs(self.heartbeat_at)
+        )
+    }
+}
+
+struct Held {
+    data_dir: PathBuf,
+    owner: Owner,
+    /// Keeps the advisory lock for as long as it is open.
+    _lock: Option<File>,
+}
+
+enum Claim {
+    Held(Held),
+    Locked(Owner),
+}
+
+static CLAIM: Mutex<Option<Claim>> = Mutex::new(None);
+static REFRESHER: Once = Once::new();
+
+#[derive(Debug, Clone, Serialize)]
+struct ReadOnlyMode {
+    owner: Owner,
+    message: String,
+}
+
+fn owner_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(OWNER_FILE)
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    let ok = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } == 0;
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    match ok {
+        true if len > 0 => String::from_utf8_lossy(&buf[..len]).into_owned(),
+        _ => "unknown".to_string(),
+    }
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// `None` where it cannot be told; the heartbeat decides then.
+#[cfg(unix)]
+pub fn process_alive(pid: u32) -> Option<bool> {
+    let alive = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0
+        || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+    Some(alive)
+}
+
+#[cfg(not(unix))]
+pub fn process_alive(_pid: u32) -> Option<bool> {
+    None
+}
+
+fn read_owner(data_dir: &Path) -> Option<Owner> {
+    let bytes = fs::read(owner_path(data_dir)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+fn write_owner(data_dir: &Path, owner: &Owner) -> Result<(), String> {
+    let bytes =
+        serde_json::to_vec_pretty(owner).map_err(|e| format!("failed serializing owner: {e}"))?;
+    safe_write::replace(&owner_path(data_dir), &bytes)
+        .map_err(|e| format!("failed writing {OWNER_FILE}: {e}"))
+}
+
+/// Whether `owner` is another run that may still be writing, as seen from
+/// `hostname` at `now`. With `locked_here` this run holds the OS lock, which
+/// a live owner on the same host would have kept.
+fn is_live(owner: &Owner, hostname: &str, now: u64, locked_here: bool) -> bool {
+    if owner.hostname == hostname {
+        if locked_here || owner.pid == std::process::id() {
+            return false;
+        }
+        if let Some(alive) = process_alive(owner.pid) {
+            return alive;
+        }
+    }
+    now.saturating_sub(owner.heartbeat_at) < STALE_AFTER.as_secs()
+}
+
+/// The advisory lock, `Ok(None)` where the platform or file system has
+/// none, `Err` when another process on this host holds it.
+fn os_lock(data_dir: &Path) -> Result<Option<File>, ()> {
+    let Ok(file) = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(data_dir.join(LOCK_FILE))
+    else {
+        return Ok(None);
+    };
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Err(()),
+        Err(TryLockError::Error(_)) => Ok(None),
+    }
+}
+
+fn new_owner() -> Owner {
+    Owner {
+        hostname: hostname(),
+        pid: std::process::id(),
+        instance: Uuid::new_v4().to_string(),
+        heartbeat_at: unix_now(),
+    }
+}
+
+/// Claims `data_dir`, giving up any other claim first. `force` ignores a
+/// live owner recorded in `owner.json` but not a held OS lock. Returns the
+/// owner that keeps this run read-only, if any.
+fn claim(data_dir: &Path, force: bool) -> Result<Option<Owner>, String> {
+    let mut slot = CLAIM
+        .lock()
+        .map_err(|_| "data dir lock poisoned".to_string())?;
+    if let Some(Claim::Held(held)) = slot.take() {
+        let _ = fs::remove_file(owner_path(&held.data_dir));
+    }
+    let recorded = read_owner(data_dir);
+    let lock = match os_lock(data_dir) {
+        Ok(lock) => lock,
+        Err(()) => {
+            // Held by a run that has not written `owner.json` yet.
+            let owner = recorded.unwrap_or_else(|| Owner {
+                pid: 0,
+                ..new_owner()
+            });
+            *slot = Some(Claim::Locked(owner.clone()));
+            return Ok(Some(owner));
+        }
+    };
+    let locked_here = lock.is_some();
+    if let Some(owner) =
+        recorded.filter(|owner| !force && is_live(owner, &hostname(), unix_now(), locked_here))
+    {
+        *slot = Some(Claim::Locked(owner.clone()));
+        return Ok(Some(owner));
+    }
+    let owner = new_owner();
+    write_owner(data_dir, &owner)?;
+    *slot = Some(Claim::Held(Held {
+        data_dir: data_dir.to_path_buf(),
+        owner,
+        _lock: lock,
+    }));
+    REFRESHER.call_once(|| {
+        thread::spawn(refresh_loop);
+    });
+    Ok(None)
+}
+
+fn refresh_loop() {
+    loop {
+        thread::sleep(REFRESH_EVERY);
+        let Ok(mut slot) = CLAIM.lock() else {
+            return;
+        };
+        if let Some(Claim::Held(held)) = slot.as_mut() {
+            held.owner.heartbeat_at = unix_now();
+            if let Err(err) = write_owner(&held.data_dir, &held.owner) {
+                desktop_log::warn(&held.data_dir, &format!("data dir heartbeat: {err}"));
+            }
+        }
+    }
+}
+
+/// Claims `data_dir` at startup or after a profile switch. A claim that
+/// cannot be written is logged and treated as held; only a live owner makes
+/// the run read-only.
+pub fn acquire(data_dir: &Path) -> Option<Owner> {
+    match claim(data_dir, false) {
+        Ok(owner) => owner,
+        Err(err) => {
+            desktop_log::warn(data_dir, &format!("data dir lock: {err}"));
+            None
+        }
+    }
+}
+
+pub fn locked_by() -> Option<Owner> {
+    match CLAIM.lock().ok()?.as_ref()? {
+        Claim::Locked(owner) => Some(owner.clone()),
+        Claim::Held(_) => None,
+    }
+}
+
+/// The run that owns `data_dir` now, without claiming it, for a one-off
+/// writer such as the `config` command line that leaves the claim to the
+/// app.
+pub fn live_owner(data_dir: &Path) -> Option<Owner> {
+    let recorded = read_owner(data_dir);
+    let locked_here = match os_lock(data_dir) {
+        Ok(lock) => lock.is_some(),
+        Err(()) => {
+            return Some(recorded.unwrap_or_else(|| Owner {
+                pid: 0,
+                ..new_owner()
+            }))
+        }
+    };
+    recorded.filter(|owner| is_live(owner, &hostname(), unix_now(), locked_here))
+}
+
+/// Whether `owner` runs on this machine, where its pid means something.
+pub fn is_local(owner: &Owner) -> bool {
+    owner.hostname == hostname()
+}
+
+pub fn is_read_only() -> bool {
+    locked_by().is_some()
+}
+
+/// Fails with `data_dir_locked` while another run owns the data dir.
+pub fn ensure_writable() -> Result<(), CommandError> {
+    match locked_by() {
+        Some(owner) => Err(CommandError::new(
+            ErrorCode::DataDirLocked,
+            format!("the data folder is in use by {}", owner.describe()),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Gives up the claim on exit so the next run need not wait out the
+/// heartbeat.
+pub fn release() {
+    if let Ok(mut slot) = CLAIM.lock() {
+        if let Some(Claim::Held(held)) = slot.take() {
+            let _ = fs::remove_file(owner_path(&held.data_dir));
+        }
+    }
+}
+
+/// Emits `read-only-mode` when this run does not own the data dir.
+pub fn announce(app: &AppHandle) {
+    if let Some(owner) = locked_by() {
+        let message = format!(
+            "{} is using this data folder, so LiteClaw opened it read-only. \
+             Close it there, or take over if that machine is gone.",
+            owner.describe()
+        );
+        let _ = app.emit("read-only-mode", ReadOnlyMode { owner, message });
+    }
+}
+
+/// Breaks another run's claim on the data dir and starts normally. Needs
+/// confirming; see `confirmation`. Refused while a process on this machine
+/// still holds the OS lock.
+#[tauri::command]
+pub fn force_takeover(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    confirm_token: Option<String>,
+) -> Result<Confirmable<ApiConfig>, CommandError> {
+    let Some(owner) = locked_by() else {
+        return Err(CommandError::conflict(
+            "this run already owns the data folder",
+        ));
+    };
+    confirmation::guarded(
+        &state,
+        "force_takeover",
+        &owner.instance,
+        confirm_token,
+        |data_dir| {
+            Ok(Summary::new(format!(
+                "Takes the data folder over from {}. If it is still running, both will \
+                 write to the same config and history.",
+                owner.describe()
+            ))
+            .path(data_dir))
+        },
+        || {
+            let mut runtime = state
+                .runtime
+                .lock()
+                .map_err(|_| "runtime lock poisoned".to_string())?;
+            if let Some(holder) = claim(&runtime.data_dir, true)? {
+                return Err(CommandError::new(
+                    ErrorCode::DataDirLocked,
+                    format!("{} is still running on this machine", holder.describe()),
+                ));
+            }
+            let _ = audit::record(
+                &runtime.data_dir,
+                "data_dir_taken_over",
+                serde_json::json!({ "previous_owner": owner }),
+            );
+            if !runtime.safe_mode {
+                start_subsystems(&mut runtime)?;
+            }
+            Ok(backend_status::publish(&app, &runtime))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owners_elsewhere_stay_live_until_their_heartbeat_ages() {
+        let now = 1_714_564_800;
+        let elsewhere = Owner {
+            hostname: "other-machine".to_string(),
+            pid: 1,
+            instance: "a".to_string(),
+            heartbeat_at: now - 30,
+        };
+        assert!(is_live(&elsewhere, "this-machine", now, true));
+        let later = now + STALE_AFTER.as_secs();
+        assert!(!is_live(&elsewhere, "this-machine", later, true));
+
+        // On this host the OS lock, then the process, settle it.
+        let here = Owner {
+            hostname: "this-machine".to_string(),
+            pid: std::process::id(),
+            ..elsewhere
+        };
+        assert!(!is_live(&here, "this-machine", now, false));
+        let parent = Owner {
+            pid: std::os::unix::process::parent_id(),
+            ..here
+        };
+        assert!(!is_live(&parent, "this-machine", now, true));
+        assert!(is_live(&parent, "this-machine", now, false));
+    }
+}
@@ -0,0 +1,294 @@
+//! `liteclaw://` deep links from the browser extension. URLs are parsed
+//! against a strict allowlist; anything that changes config waits for the user
+//! to confirm in the UI. Rejections are audited.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use url::Url;
+use uuid::Uuid;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{add_folder, audit, AppState, BackendRuntime, FolderAddition};
+
+pub const SCHEME: &str = "liteclaw";
+const MAX_URL_LEN: usize = 4096;
+const MAX_ASK_CHARS: usize = 2000;
+const MAX_PENDING_CONFIRMATIONS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLinkAction {
+    Ask { text: String },
+    AddFolder { path: String },
+    Pair { code: String },
+}
+
+impl DeepLinkAction {
+    fn name(&self) -> &'static str {
+        match self {
+            DeepLinkAction::Ask { .. } => "ask",
+            DeepLinkAction::AddFolder { .. } => "add_folder",
+            DeepLinkAction::Pair { .. } => "pair",
+        }
+    }
+
+    fn needs_confirmation(&self) -> bool {
+        matches!(self, DeepLinkAction::AddFolder { .. })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingDeepLink {
+    pub id: String,
+    #[serde(flatten)]
+    pub action: DeepLinkAction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingDeepLinks {
+    pub actions: Vec<DeepLinkAction>,
+    pub confirmations: Vec<PendingDeepLink>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct DeepLinkRejected {
+    reason: String,
+}
+
+/// Returns the single value of `key`, rejecting any parameter outside
+/// `allowed` and repeated keys.
+fn single_param(url: &Url, key: &str, allowed: &[&str]) -> Result<String, String> {
+    let mut value = None;
+    for (name, candidate) in url.query_pairs() {
+        if !allowed.contains(&name.as_ref()) {
+            return Err(format!("unexpected parameter: {name}"));
+        }
+        if name == key {
+            if value.is_some() {
+                return Err(format!("parameter repeated: {key}"));
+            }
+            value = Some(candidate.into_owned());
+        }
+    }
+    value
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| format!("missing parameter: {key}"))
+}
+
+pub fn parse_deep_link(raw: &str) -> Result<DeepLinkAction, String> {
+    if raw.len() > MAX_URL_LEN {
+        return Err(format!("link is longer than {MAX_URL_LEN} bytes"));
+    }
+    let url = Url::parse(raw).map_err(|e| format!("malformed link: {e}"))?;
+    if url.scheme() != SCHEME {
+        return Err(format!("unexpected scheme: {}", url.scheme()));
+    }
+    // `liteclaw://ask?...` puts the action in the host position.
+    let action = url.host_str().unwrap_or_default();
+    if !matches!(url.path(), "" | "/") {
+        return Err("unexpected path in link".to_string());
+    }
+    match action {
+        "ask" => {
+            let text = single_param(&url, "text", &["text"])?;
+            if text.chars().count() > MAX_ASK_CHARS {
+                return Err(format!("ask text exceeds {MAX_ASK_CHARS} characters"));
+            }
+            Ok(DeepLinkAction::Ask { text })
+        }
+        "add-folder" => {
+            let path = single_param(&url, "path", &["path"])?;
+            if !std::path::Path::new(&path).is_absolute() {
+                return Err("folder path must be absolute".to_string());
+            }
+            Ok(DeepLinkAction::AddFolder { path })
+        }
+        "pair" => {
+            let code = single_param(&url, "code", &["code"])?;
+            let valid =
+                (6..=12).contains(&code.len()) && code.chars().all(|c| c.is_ascii_alphanumeric());
+            if !valid {
+                return Err("pairing code must be 6-12 letters or digits".to_string());
+            }
+            Ok(DeepLinkAction::Pair { code })
+        }
+        other => Err(format!("unknown action: {other}")),
+    }
+}
+
+fn audit_rejection(runtime: &BackendRuntime, raw: &str, reason: &str) {
+    // Only the length is recorded; the payload may be arbitrary text.
+    let _ = audit::record(
+        &runtime.data_dir,
+        "deeplink_rejected",
+        serde_json::json!({ "reason": reason, "length": raw.len() }),
+    );
+}
+
+fn request_confirmation(runtime: &mut BackendRuntime, action: DeepLinkAction) -> PendingDeepLink {
+    if runtime.pending_deeplinks.len() >= MAX_PENDING_CONFIRMATIONS {
+        runtime.pending_deeplinks.remove(0);
+    }
+    let pending = PendingDeepLink {
+        id: Uuid::new_v4().to_string(),
+        action,
+    };
+    runtime.pending_deeplinks.push(pending.clone());
+    pending
+}
+
+fn emit_action(app: &AppHandle, runtime: &mut BackendRuntime, action: DeepLinkAction) {
+    let _ = audit::record(
+        &runtime.data_dir,
+        "deeplink_received",
+        serde_json::json!({ "action": action.name() }),
+    );
+    if action.needs_confirmation() {
+        let pending = request_confirmation(runtime, action);
+        let _ = app.emit("deeplink-confirm-requested", &pending);
+        return;
+    }
+    let event = match &action {
+        DeepLinkAction::Ask { .. } => "deeplink-ask",
+        DeepLinkAction::Pair { .. } => "deeplink-pair",
+        DeepLinkAction::AddFolder { .. } => unreachable!("add-folder always needs confirmation"),
+    };
+    let _ = app.emit(event, &action);
+}
+
+pub fn handle_url(app: &AppHandle, runtime: &mut BackendRuntime, raw: &str) {
+    match parse_deep_link(raw) {
+        Ok(action) => emit_action(app, runtime, action),
+        Err(reason) => {
+            audit_rejection(runtime, raw, &reason);
+            let _ = app.emit("deeplink-rejected", DeepLinkRejected { reason });
+        }
+    }
+}
+
+/// Queues a link that launched the app; the frontend collects it with
+/// `take_pending_deeplinks` once it is listening.
+pub fn queue_launch_url(runtime: &mut BackendRuntime, raw: &str) {
+    match parse_deep_link(raw) {
+        Ok(action) => {
+            let _ = audit::record(
+                &runtime.data_dir,
+                "deeplink_received",
+                serde_json::json!({ "action": action.name() }),
+            );
+            if action.needs_confirmation() {
+                request_confirmation(runtime, action);
+            } else {
+                runtime.launch_deeplinks.push(action);
+            }
+        }
+        Err(reason) => audit_rejection(runtime, raw, &reason),
+    }
+}
+
+pub fn handle_urls(app: &AppHandle, urls: &[String]) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let Ok(mut runtime) = state.runtime.lock() else {
+        return;
+    };
+    for url in urls {
+        handle_url(app, &mut runtime, url);
+    }
+}
+
+/// Applies or discards a link that was waiting for confirmation.
+#[tauri::command]
+pub fn resolve_deeplink(
+    state: State<'_, AppState>,
+    id: String,
+    approved: bool,
+) -> Result<Option<ConfigChange<FolderAddition>>, CommandError> {
+    let mut runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let index = runtime
+        .pending_deeplinks
+        .iter()
+        .position(|pending| pending.id == id)
+        .ok_or_else(|| CommandError::not_found(format!("no pending deep link {id}")))?;
+    let pending = runtime.pending_deeplinks.remove(index);
+    let _ = audit::record(
+        &runtime.data_dir,
+        "deeplink_resolved",
+        serde_json::json!({ "action": pending.action.name(), "approved": approved }),
+    );
+    if !approved {
+        return Ok(None);
+    }
+    match pending.action {
+        DeepLinkAction::AddFolder { path } => add_folder(&runtime, &path, true, false).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Hands over links that arrived before the frontend was listening, along
+/// with every confirmation still awaiting an answer.
+#[tauri::command]
+pub fn take_pending_deeplinks(
+    state: State<'_, AppState>,
+) -> Result<PendingDeepLinks, CommandError> {
+    let mut runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(PendingDeepLinks {
+        actions: runtime.launch_deeplinks.drain(..).collect(),
+        confirmations: runtime.pending_deeplinks.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_allowlisted_links_parse() {
+        assert_eq!(
+            parse_deep_link("liteclaw://ask?text=What%20changed%3F"),
+            Ok(DeepLinkAction::Ask {
+                text: "What changed?".to_string()
+            })
+        );
+        assert_eq!(
+            parse_deep_link("liteclaw://pair/?code=AB12cd"),
+            Ok(DeepLinkAction::Pair {
+                code: "AB12cd".to_string()
+            })
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            parse_deep_link("liteclaw://add-folder?path=%2Fsrv%2Fdocs"),
+            Ok(DeepLinkAction::AddFolder {
+                path: "/srv/docs".to_string()
+            })
+        );
+        let long_ask = format!("liteclaw://ask?text={}", "a".repeat(MAX_ASK_CHARS + 1));
+        let long_url = format!("liteclaw://ask?text={}", "a".repeat(MAX_URL_LEN));
+        for raw in [
+            "https://ask?text=hi",
+            "liteclaw://delete?path=%2F",
+            "liteclaw://ask/more?text=hi",
+            "liteclaw://ask",
+            "liteclaw://ask?text=%20%20",
+            "liteclaw://ask?text=hi&text=again",
+            "liteclaw://ask?text=hi&open=1",
+            "liteclaw://add-folder?path=relative%2Fdir",
+            "liteclaw://pair?code=12345",
+            "liteclaw://pair?code=abc-123",
+            "not a url",
+            long_ask.as_str(),
+            long_url.as_str(),
+        ] {
+            assert!(parse_deep_link(raw).is_err(), "{raw}");
+        }
+    }
+}
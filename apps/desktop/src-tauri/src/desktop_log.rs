@@ -0,0 +1,37 @@
+//! Plain-text log for the desktop shell itself (`logs/desktop.log`), separate
+//! from the backend's log so shell issues are visible when the backend never
+//! started.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{storage, timestamps};
+
+pub fn desktop_log_path(data_dir: &Path) -> PathBuf {
+    crate::layout::cache_dir(data_dir)
+        .join("logs")
+        .join("desktop.log")
+}
+
+/// Skipped while the data dir is unavailable; see `storage`.
+fn write(data_dir: &Path, level: &str, message: &str) {
+    if storage::is_unavailable() {
+        return;
+    }
+    let path = desktop_log_path(data_dir);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{} [{level}] {message}", timestamps::now());
+    }
+}
+
+pub fn info(data_dir: &Path, message: &str) {
+    write(data_dir, "info", message);
+}
+
+pub fn warn(data_dir: &Path, message: &str) {
+    write(data_dir, "warn", message);
+}
@@ -0,0 +1,125 @@
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::benchmark::{self, DIAGNOSTICS_ITERATIONS};
+use crate::startup::StartupReport;
+use crate::{
+    audit, backend_state, config_path, supervisor, unix_now, BackendRuntime, BackendState,
+};
+
+const LOG_TAIL_LINES: usize = 2000;
+const AUDIT_ENTRIES: usize = 500;
+
+#[derive(Serialize)]
+struct DiagnosticsSummary {
+    app_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    generated_at: u64,
+    safe_mode: bool,
+    backend_state: BackendState,
+    backend_ready: bool,
+    last_error: Option<String>,
+    profile: String,
+    data_dir: String,
+    startup: StartupReport,
+}
+
+fn summary(runtime: &BackendRuntime, startup: &StartupReport) -> DiagnosticsSummary {
+    DiagnosticsSummary {
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        generated_at: unix_now(),
+        safe_mode: runtime.safe_mode,
+        backend_state: backend_state(runtime),
+        backend_ready: runtime.backend_ready,
+        last_error: runtime
+            .last_error
+            .as_ref()
+            .map(|err| err.detail().to_string()),
+        profile: runtime.profile.clone(),
+        data_dir: runtime.data_dir.to_string_lossy().to_string(),
+        startup: startup.clone(),
+    }
+}
+
+fn tail_lines(path: &Path, lines: usize) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut collected: Vec<&str> = content.lines().rev().take(lines).collect();
+    collected.reverse();
+    Some(collected.join("\n"))
+}
+
+/// Writes a zip bundle with a state summary, the latest self-check, the local
+/// config, the tail of the backend log, recent audit entries, crash reports
+/// and, when the backend answers, a short latency benchmark. Sections that
+/// cannot be read are skipped, not fatal. Audit entries and crash reports
+/// are re-serialized, so old and new versions share RFC 3339 timestamps.
+pub fn export_diagnostics(
+    runtime: &BackendRuntime,
+    startup: &StartupReport,
+    dest: &Path,
+) -> Result<PathBuf, String> {
+    let file = File::create(dest).map_err(|e| format!("failed creating diagnostics file: {e}"))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let summary_json = serde_json::to_vec_pretty(&summary(runtime, startup))
+        .map_err(|e| format!("failed serializing diagnostics summary: {e}"))?;
+    add_entry(&mut zip, options, "summary.json", &summary_json)?;
+
+    if let Some(report) = &runtime.self_check {
+        let report_json = serde_json::to_vec_pretty(report)
+            .map_err(|e| format!("failed serializing self-check report: {e}"))?;
+        add_entry(&mut zip, options, "self_check.json", &report_json)?;
+    }
+    if let Ok(config) = fs::read(config_path(&runtime.data_dir)) {
+        add_entry(&mut zip, options, "config.json", &config)?;
+    }
+    if let Some(logs) = tail_lines(Path::new(&runtime.log_path), LOG_TAIL_LINES) {
+        add_entry(&mut zip, options, "logs/backend.log", logs.as_bytes())?;
+    }
+    let audit = audit::read_recent(&runtime.data_dir, AUDIT_ENTRIES);
+    if !audit.is_empty() {
+        let audit_json = serde_json::to_vec_pretty(&audit)
+            .map_err(|e| format!("failed serializing audit entries: {e}"))?;
+        add_entry(&mut zip, options, "audit.json", &audit_json)?;
+    }
+    let crashes = supervisor::read_crash_reports(&runtime.data_dir);
+    if !crashes.is_empty() {
+        let crashes_json = serde_json::to_vec_pretty(&crashes)
+            .map_err(|e| format!("failed serializing crash reports: {e}"))?;
+        add_entry(&mut zip, options, "crash_reports.json", &crashes_json)?;
+    }
+    if runtime.backend_ready {
+        let bench =
+            benchmark::run_benchmark(&runtime.base_url, &runtime.token, DIAGNOSTICS_ITERATIONS);
+        if let Ok(report) = bench {
+            let report_json = serde_json::to_vec_pretty(&report)
+                .map_err(|e| format!("failed serializing benchmark: {e}"))?;
+            add_entry(&mut zip, options, "benchmark.json", &report_json)?;
+        }
+    }
+
+    zip.finish()
+        .map_err(|e| format!("failed finalizing diagnostics file: {e}"))?;
+    Ok(dest.to_path_buf())
+}
+
+fn add_entry(
+    zip: &mut ZipWriter<File>,
+    options: SimpleFileOptions,
+    name: &str,
+    bytes: &[u8],
+) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("failed adding {name} to diagnostics: {e}"))?;
+    zip.write_all(bytes)
+        .map_err(|e| format!("failed writing {name} to diagnostics: {e}"))
+}
@@ -0,0 +1,244 @@
+//! `discovery.json` in the data dir tells other local tools (editor plugins)
+//! where the running backend listens. It is rewritten every time a backend
+//! becomes ready and removed when it stops. It never contains the auth
+//! token.
+//!
+//! A session only hands out an advertisement it can vouch for: the pid must
+//! be its own running backend and the URL must accept its token (see
+//! `unvouched`). A file left by a crashed session (where another program
+//! may hold the port by now) is removed at startup, before a new backend
+//! advertises itself. `get_discovery_info` re-checks every time and
+//! withdraws an advertisement that no longer holds.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::backend_transport::{BackendTransport, UreqTransport};
+use crate::error::CommandError;
+use crate::{check_token, data_dir_lock, desktop_log, timestamps, AppState, TokenCheck};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscoveryInfo {
+    pub base_url: String,
+    pub pid: u32,
+    pub app_version: String,
+    pub started_at: String,
+}
+
+impl DiscoveryInfo {
+    pub fn new(base_url: &str, pid: u32) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            pid,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: timestamps::now(),
+        }
+    }
+}
+
+pub fn discovery_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("discovery.json")
+}
+
+/// Replaces the file atomically; the temp file is created owner-only so the
+/// contents are never readable by other users, even briefly.
+pub fn write(data_dir: &Path, info: &DiscoveryInfo) -> Result<(), String> {
+    let path = discovery_path(data_dir);
+    let temp = path.with_extension("tmp");
+    let bytes = serde_json::to_vec_pretty(info)
+        .map_err(|e| format!("failed serializing discovery info: {e}"))?;
+    let _ = fs::remove_file(&temp);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&temp)
+        .map_err(|e| format!("failed creating discovery file: {e}"))?;
+    file.write_all(&bytes)
+        .map_err(|e| format!("failed writing discovery file: {e}"))?;
+    drop(file);
+    fs::rename(&temp, &path).map_err(|e| format!("failed replacing discovery file: {e}"))
+}
+
+pub fn clear(data_dir: &Path) {
+    let _ = fs::remove_file(discovery_path(data_dir));
+}
+
+fn read(data_dir: &Path) -> Option<DiscoveryInfo> {
+    let bytes = fs::read(discovery_path(data_dir)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Why the session whose backend runs as `child_pid` with `token` cannot
+/// vouch for `info`, or `None` when it can.
+pub fn unvouched(
+    transport: &dyn BackendTransport,
+    info: &DiscoveryInfo,
+    child_pid: Option<u32>,
+    token: &str,
+) -> Option<String> {
+    if data_dir_lock::process_alive(info.pid) == Some(false) {
+        return Some(format!("pid {} has exited", info.pid));
+    }
+    if child_pid != Some(info.pid) {
+        return Some(format!("pid {} is not this session's backend", info.pid));
+    }
+    match check_token(transport, &info.base_url, token) {
+        TokenCheck::Accepted => None,
+        TokenCheck::Rejected(code) => Some(format!(
+            "{} answered HTTP {code} to this session's token",
+            info.base_url
+        )),
+        TokenCheck::Inconclusive => Some(format!(
+            "{} did not confirm this session's token",
+            info.base_url
+        )),
+    }
+}
+
+/// Removes a file left by an earlier session, which nothing running now
+/// can vouch for.
+pub fn remove_stale(data_dir: &Path) {
+    if !discovery_path(data_dir).exists() {
+        return;
+    }
+    let reason = match read(data_dir) {
+        Some(info) => unvouched(&UreqTransport, &info, None, ""),
+        None => Some("unreadable".to_string()),
+    };
+    let reason = reason.unwrap_or_default();
+    desktop_log::info(
+        data_dir,
+        &format!("removed discovery.json left by an earlier session: {reason}"),
+    );
+    clear(data_dir);
+}
+
+/// The running backend's discovery info, or `None` while none is ready or
+/// it no longer checks out. The token check runs without the runtime lock.
+#[tauri::command]
+pub fn get_discovery_info(
+    state: State<'_, AppState>,
+) -> Result<Option<DiscoveryInfo>, CommandError> {
+    let (info, child_pid, token, data_dir) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        let Some(info) = runtime.discovery.clone().filter(|_| runtime.backend_ready) else {
+            return Ok(None);
+        };
+        let child_pid = runtime.backend_child.as_ref().map(|child| child.id());
+        (
+            info,
+            child_pid,
+            runtime.token.clone(),
+            runtime.data_dir.clone(),
+        )
+    };
+    let Some(reason) = unvouched(&UreqTransport, &info, child_pid, &token) else {
+        // Put back a file something else removed or replaced.
+        if read(&data_dir).as_ref() != Some(&info) {
+            write(&data_dir, &info)?;
+        }
+        return Ok(Some(info));
+    };
+    let mut runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    // A backend started meanwhile has advertised itself; leave it be.
+    if runtime.discovery.as_ref() == Some(&info) {
+        runtime.discovery = None;
+        clear(&data_dir);
+        desktop_log::warn(&data_dir, &format!("withdrew discovery.json: {reason}"));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend_transport::fake::{FakeTransport, Reply};
+    use crate::backend_transport::AUTH_VERIFY;
+    use uuid::Uuid;
+
+    fn temp_data_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("liteclaw-discovery-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// The file is readable by any local process running as the user, so it
+    /// carries only where to connect. Clients get a token through a separate,
+    /// explicit flow (named client tokens); never add it to this struct.
+    #[test]
+    fn serialized_fields_are_exactly_the_public_ones() {
+        let info = DiscoveryInfo::new("http://127.0.0.1:8765", 4242);
+        let value = serde_json::to_value(&info).unwrap();
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["app_version", "base_url", "pid", "started_at"]);
+        assert!(!value.to_string().to_lowercase().contains("token"));
+    }
+
+    #[test]
+    fn token_in_a_stale_file_is_not_carried_over() {
+        let stale = r#"{"base_url":"http://127.0.0.1:1","pid":1,"app_version":"0.0.1",
+            "started_at":"2020-01-01T00:00:00.000Z","token":"leaked"}"#;
+        let info: DiscoveryInfo = serde_json::from_str(stale).unwrap();
+        assert!(!serde_json::to_string(&info).unwrap().contains("leaked"));
+    }
+
+    #[test]
+    fn only_this_sessions_backend_accepting_its_token_is_vouched_for() {
+        let pid = std::process::id();
+        let info = DiscoveryInfo::new("http://127.0.0.1:8767", pid);
+        let verify = format!("{}{AUTH_VERIFY}", info.base_url);
+        let fake = FakeTransport::new();
+        fake.script(&verify, [Reply::Status(200, "{}"), Reply::Status(401, "")]);
+
+        assert_eq!(unvouched(&fake, &info, Some(pid), "token"), None);
+        let squatted = unvouched(&fake, &info, Some(pid), "token").unwrap();
+        assert!(squatted.contains("HTTP 401"), "{squatted}");
+        assert!(unvouched(&fake, &info, None, "token").is_some());
+        assert_eq!(fake.seen(&verify).len(), 2);
+    }
+
+    #[test]
+    fn write_overwrites_a_stale_file_and_clear_removes_it() {
+        let dir = temp_data_dir();
+        fs::write(discovery_path(&dir), "not json from a crashed session").unwrap();
+        let info = DiscoveryInfo::new("http://127.0.0.1:8766", 7);
+        write(&dir, &info).unwrap();
+
+        let read: DiscoveryInfo =
+            serde_json::from_str(&fs::read_to_string(discovery_path(&dir)).unwrap()).unwrap();
+        assert_eq!(read, info);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(discovery_path(&dir))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        clear(&dir);
+        assert!(!discovery_path(&dir).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,227 @@
+//! Saving backend-generated files to a user-chosen location. Only URLs on the
+//! backend's own origin are fetched so this never becomes a generic
+//! downloader.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_dialog::DialogExt;
+use url::Url;
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::tasks::{TaskHandle, TaskKind};
+use crate::{backend_http, AppState};
+
+const PROGRESS_STEP: u64 = 256 * 1024;
+const CHUNK_SIZE: usize = 64 * 1024;
+pub const SHA256_HEADER: &str = "X-Content-SHA256";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadResult {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct DownloadProgress {
+    id: String,
+    bytes_received: u64,
+    total_bytes: Option<u64>,
+}
+
+/// Resolves a backend-relative path or absolute URL, requiring the result to
+/// share the backend's scheme, host and port.
+pub fn resolve_backend_url(base_url: &str, path_or_url: &str) -> Result<Url, CommandError> {
+    let base = Url::parse(base_url).map_err(|e| format!("invalid backend url: {e}"))?;
+    let target = if path_or_url.starts_with('/') && !path_or_url.starts_with("//") {
+        base.join(path_or_url)
+    } else {
+        Url::parse(path_or_url)
+    }
+    .map_err(|e| CommandError::invalid_input(format!("invalid download url: {e}")))?;
+    if target.origin() != base.origin() {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            "only files served by the LiteClaw backend can be downloaded",
+        ));
+    }
+    Ok(target)
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '/' | '\\' | ':'))
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.').to_string();
+    if cleaned.is_empty() {
+        "download".to_string()
+    } else {
+        cleaned
+    }
+}
+
+fn integrity_error(message: impl Into<String>) -> CommandError {
+    CommandError::new(ErrorCode::IntegrityMismatch, message)
+}
+
+/// Copies the body into `temp`, failing if the length or checksum the
+/// backend advertised doesn't match what arrived, or once cancelled.
+fn copy_verified(
+    app: &AppHandle,
+    task: &TaskHandle<'_>,
+    response: ureq::Response,
+    temp: &Path,
+) -> Result<(u64, String), CommandError> {
+    let expected_len = response
+        .header("Content-Length")
+        .and_then(|value| value.parse::<u64>().ok());
+    let expected_hash = response
+        .header(SHA256_HEADER)
+        .map(|value| value.trim().to_ascii_lowercase());
+    let progress = |received: u64| {
+        let _ = app.emit(
+            "download-progress",
+            DownloadProgress {
+                id: task.id().to_string(),
+                bytes_received: received,
+                total_bytes: expected_len,
+            },
+        );
+    };
+
+    let mut out = File::create(temp).map_err(|e| format!("failed creating file: {e}"))?;
+    let mut reader = response.into_reader();
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut received = 0u64;
+    let mut last_reported = 0u64;
+    loop {
+        task.token().check("download")?;
+        let read = reader
+            .read(&mut buf)
+            .map_err(|e| format!("download interrupted: {e}"))?;
+        if read == 0 {
+            break;
+        }
+        out.write_all(&buf[..read])
+            .map_err(|e| format!("failed writing download: {e}"))?;
+        hasher.update(&buf[..read]);
+        received += read as u64;
+        task.token().report(received, expected_len);
+        if received - last_reported >= PROGRESS_STEP {
+            last_reported = received;
+            progress(received);
+        }
+    }
+    out.sync_all()
+        .map_err(|e| format!("failed flushing download: {e}"))?;
+
+    let sha256 = format!("{:x}", hasher.finalize());
+    if let Some(expected) = expected_len.filter(|len| *len != received) {
+        return Err(integrity_error(format!(
+            "download incomplete: expected {expected} bytes, got {received}"
+        )));
+    }
+    if expected_hash.is_some_and(|hash| hash != sha256) {
+        return Err(integrity_error("download checksum does not match"));
+    }
+    progress(received);
+    Ok((received, sha256))
+}
+
+fn stream_to(
+    app: &AppHandle,
+    task: &TaskHandle<'_>,
+    response: ureq::Response,
+    dest: &Path,
+) -> Result<DownloadResult, CommandError> {
+    let parent = dest
+        .parent()
+        .ok_or_else(|| CommandError::invalid_input("destination has no parent folder"))?;
+    let file_name = dest
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = parent.join(format!(".{file_name}.{}.part", Uuid::new_v4()));
+    let (bytes, sha256) = match copy_verified(app, task, response, &temp) {
+        Ok(done) => done,
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            return Err(err);
+        }
+    };
+    if let Err(err) = fs::rename(&temp, dest) {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("failed saving {}: {err}", dest.display()).into());
+    }
+    Ok(DownloadResult {
+        path: dest.to_string_lossy().to_string(),
+        bytes,
+        sha256,
+    })
+}
+
+// Async because the save dialog blocks until the user answers.
+#[tauri::command]
+pub async fn download_backend_file(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path_or_url: String,
+    suggested_name: String,
+    download_id: Option<String>,
+) -> Result<DownloadResult, CommandError> {
+    let (base_url, token) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        if !runtime.backend_ready {
+            return Err(CommandError::new(
+                ErrorCode::BackendUnavailable,
+                "backend is not running",
+            ));
+        }
+        (runtime.base_url.clone(), runtime.token.clone())
+    };
+    let url = resolve_backend_url(&base_url, &path_or_url)?;
+
+    let dest: PathBuf = app
+        .dialog()
+        .file()
+        .set_file_name(sanitize_file_name(&suggested_name))
+        .blocking_save_file()
+        .ok_or_else(|| CommandError::new(ErrorCode::Cancelled, "save was cancelled"))?
+        .into_path()
+        .map_err(|e| format!("unsupported save location: {e}"))?;
+
+    let request = backend_http::agent()
+        .get(url.as_str())
+        .set("Authorization", &format!("Bearer {token}"));
+    let sent = backend_http::send_idempotent(url.path(), request, None);
+    backend_http::rejected(&sent.result)?;
+    let note = sent.attempts_note();
+    let response = sent.result.map_err(|err| match err {
+        ureq::Error::Status(404, _) => CommandError::not_found("file not found on backend"),
+        ureq::Error::Status(code, _) => format!("download failed: HTTP {code}{note}").into(),
+        ureq::Error::Transport(err) => CommandError::new(
+            ErrorCode::BackendUnavailable,
+            format!("download failed{note}: {err}"),
+        ),
+    })?;
+    let file_name = dest
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let task = state.tasks.register(
+        TaskKind::Download,
+        format!("Downloading {file_name}"),
+        download_id,
+    )?;
+    stream_to(&app, &task, response, &dest)
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Stable, machine-readable error identifiers. The frontend switches on these;
+/// `message` is for humans and may change freely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Internal,
+    InvalidInput,
+    NotFound,
+    Conflict,
+    NotAllowed,
+    DanglingSymlink,
+    SymlinkCycle,
+    ReadOnlyFolder,
+    TrashUnavailable,
+    Unsupported,
+    BackendUnavailable,
+    /// An OS privacy setting, or the calling window's command allowlist,
+    /// refused the operation.
+    PermissionDenied,
+    Cancelled,
+    IntegrityMismatch,
+    StorageUnavailable,
+    /// config.json is encrypted and the OS keychain has no key that opens it.
+    ConfigKeyMissing,
+    /// The backend and desktop share no API version, or the backend rejected
+    /// a payload shaped for the negotiated one.
+    BackendIncompatible,
+    /// A destructive command's `confirm_token` is unknown, spent, expired or
+    /// was issued for another request.
+    ConfirmationInvalid,
+    /// Not enough free space to write safely; nothing was overwritten.
+    DiskFull,
+    /// The backend is executing a task and will not switch models under it.
+    GenerationInProgress,
+    /// Another LiteClaw owns the data dir; this run is read-only.
+    DataDirLocked,
+    /// A conversation's working folder is no longer an allowed folder.
+    FolderMissing,
+    /// Too many requests were already waiting for the backend; nothing was
+    /// sent. See `request_limiter`.
+    BackendBusy,
+    /// The result was over `max_response_bytes` and the command streams it
+    /// elsewhere; the message names where. See `response_limit`.
+    ResponseTooLarge,
+    /// A folder to allow exists but is a file or device.
+    NotADirectory,
+    /// A folder to allow is on `/proc`, `/sys`, `/dev` or the like; see
+    /// `folder_location`.
+    PseudoFilesystem,
+    /// A folder to allow is a filesystem or drive root and `allow_root`
+    /// was not passed.
+    FilesystemRoot,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Conflict, message)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// Existing helpers report plain strings; treat those as internal failures so
+// they compose with `?` in commands that return typed errors.
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+// Lets helpers that still report plain strings call typed-error functions.
+impl From<CommandError> for String {
+    fn from(err: CommandError) -> Self {
+        err.message
+    }
+}
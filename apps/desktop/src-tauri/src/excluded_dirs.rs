@@ -0,0 +1,148 @@
+//! The app's own directories never count as part of an allowed folder, even
+//! when the user allows a folder above them such as their home directory.
+//! Otherwise the agent could read `config.json`, the audit log or another
+//! profile's history through that folder, and every walk would index the
+//! attachments being staged next to it. Excluded are the data, config and
+//! cache roots, which hold every profile, plus the active profile's dirs for
+//! a data dir kept outside them. `folders::is_within_allowed`, the writes
+//! and deletes in `file_ops`, the walks in `ignore_rules`, folder validation
+//! and the backend reload payload all use this list; staged attachments stay
+//! readable through their own check.
+
+use std::path::{Path, PathBuf};
+
+use crate::attachments::attachments_dir;
+use crate::folders::AllowedFolder;
+use crate::{layout, paths};
+
+/// The outermost app-owned dirs, canonical where they exist so they compare
+/// with resolved folder paths.
+pub fn excluded_dirs(data_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = layout::roots()
+        .into_iter()
+        .chain([
+            data_dir.to_path_buf(),
+            layout::config_dir(data_dir),
+            layout::cache_dir(data_dir),
+            attachments_dir(data_dir),
+        ])
+        .map(|dir| match paths::canonical_dir(&dir.to_string_lossy()) {
+            Ok(canonical) => PathBuf::from(canonical),
+            Err(_) => dir,
+        })
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    let nested: Vec<bool> = dirs
+        .iter()
+        .map(|dir| {
+            dirs.iter()
+                .any(|other| other != dir && dir.starts_with(other))
+        })
+        .collect();
+    dirs.into_iter()
+        .zip(nested)
+        .filter(|(_, nested)| !nested)
+        .map(|(dir, _)| dir)
+        .collect()
+}
+
+pub fn is_excluded(excluded: &[PathBuf], path: &Path) -> bool {
+    excluded.iter().any(|dir| path.starts_with(dir))
+}
+
+/// The excluded dirs that lie inside `folder`, or contain it.
+pub fn within_folder(excluded: &[PathBuf], folder: &AllowedFolder) -> Vec<PathBuf> {
+    let roots = [PathBuf::from(&folder.path), folder.effective_path()];
+    excluded
+        .iter()
+        .filter(|dir| {
+            roots
+                .iter()
+                .any(|root| dir.starts_with(root) || root.starts_with(dir))
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folders::{self, validate_config_folders, FolderStatus};
+    use crate::ignore_rules;
+    use crate::LocalConfig;
+    use std::fs;
+    use uuid::Uuid;
+
+    /// An allowed root with the data dir four levels below it.
+    struct Tree {
+        root: PathBuf,
+        data_dir: PathBuf,
+        config: LocalConfig,
+    }
+
+    impl Tree {
+        fn new() -> Self {
+            let base = std::env::temp_dir().join(format!("liteclaw-excluded-{}", Uuid::new_v4()));
+            let root = PathBuf::from(paths::canonical_dir(&mk(&base).to_string_lossy()).unwrap());
+            let data_dir = mk(&root.join("home/.local/share/liteclaw"));
+            mk(&attachments_dir(&data_dir));
+            fs::write(data_dir.join("config.json"), "{}").unwrap();
+            fs::write(mk(&root.join("docs")).join("notes.txt"), "notes").unwrap();
+            let config = LocalConfig {
+                allowed_folders: vec![AllowedFolder::new(root.to_string_lossy().into_owned())],
+                ..LocalConfig::default()
+            };
+            Self {
+                root,
+                data_dir,
+                config,
+            }
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn mk(dir: &Path) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        dir.to_path_buf()
+    }
+
+    #[test]
+    fn nested_data_dir_is_not_granted_by_an_allowed_root() {
+        let tree = Tree::new();
+        let allowed =
+            |path: PathBuf| folders::is_within_allowed(&tree.data_dir, &tree.config, &path);
+        assert!(allowed(tree.root.join("docs/notes.txt")));
+        assert!(allowed(tree.root.join("home/.local/share/other.txt")));
+        assert!(!allowed(tree.data_dir.join("config.json")));
+        assert!(!allowed(attachments_dir(&tree.data_dir).join("photo.png")));
+        assert!(!allowed(tree.data_dir.join("profiles/work/history.db")));
+    }
+
+    #[test]
+    fn walks_skip_a_nested_data_dir() {
+        let tree = Tree::new();
+        let ignore =
+            ignore_rules::for_path(&tree.data_dir, &tree.config, &tree.root).expect("folder rules");
+        assert!(ignore.is_ignored(&tree.data_dir, true));
+        assert!(ignore.is_ignored(&tree.data_dir.join("config.json"), false));
+        assert!(!ignore.is_ignored(&tree.root.join("docs/notes.txt"), false));
+    }
+
+    #[test]
+    fn validation_reports_the_excluded_data_dir() {
+        let tree = Tree::new();
+        let report = validate_config_folders(&tree.data_dir, &tree.config);
+        let folder = &report.folders[0];
+        assert_eq!(folder.status, FolderStatus::Ok);
+        assert_eq!(
+            folder.excluded,
+            vec![tree.data_dir.to_string_lossy().into_owned()]
+        );
+    }
+}
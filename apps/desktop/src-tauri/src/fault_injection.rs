@@ -0,0 +1,196 @@
+//! Simulated failures for exercising the UI's error states in QA.
+//! `inject_failure` arms a kind for a while, and the choke point that kind
+//! names checks for it:
+//!
+//! - `backend_unreachable`: every backend request fails to connect
+//!   (`backend_http::builder`).
+//! - `slow_backend`: every backend request waits `latency_ms` first (same).
+//! - `config_write_fail`: writing `config.json` fails with an IO error
+//!   (`write_config_file`).
+//! - `health_degraded`: hang detection reports the backend hung
+//!   (`backend_http::hang_evidence`).
+//!
+//! The commands refuse to run outside developer mode (see `backend_debug`),
+//! so in a release build without `LITECLAW_DEVELOPER_MODE=1` nothing can be
+//! armed and each check is one atomic load. Injections expire on their own;
+//! arming and clearing are audited.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::{audit, backend_debug, desktop_log, AppState};
+
+const MAX_DURATION_SECS: u64 = 3600;
+const DEFAULT_LATENCY_MS: u64 = 2000;
+const MAX_LATENCY_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    BackendUnreachable,
+    ConfigWriteFail,
+    SlowBackend,
+    HealthDegraded,
+}
+
+struct Injection {
+    kind: FailureKind,
+    expires_at: Instant,
+    latency: Duration,
+}
+
+/// Set while anything is armed, so checks skip the lock otherwise.
+static ARMED: AtomicBool = AtomicBool::new(false);
+static INJECTIONS: Mutex<Vec<Injection>> = Mutex::new(Vec::new());
+
+/// Runs `read` over the injections still in force, dropping expired ones.
+fn with_active<T>(read: impl FnOnce(&[Injection]) -> T) -> Option<T> {
+    if !ARMED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let mut injections = INJECTIONS.lock().ok()?;
+    let now = Instant::now();
+    injections.retain(|injection| injection.expires_at > now);
+    ARMED.store(!injections.is_empty(), Ordering::Relaxed);
+    Some(read(&injections))
+}
+
+fn find(kind: FailureKind) -> Option<Duration> {
+    with_active(|injections| {
+        injections
+            .iter()
+            .find(|injection| injection.kind == kind)
+            .map(|injection| injection.latency)
+    })
+    .flatten()
+}
+
+pub fn is_active(kind: FailureKind) -> bool {
+    find(kind).is_some()
+}
+
+/// For `config_write_fail`.
+pub fn config_write_error() -> Option<io::Error> {
+    is_active(FailureKind::ConfigWriteFail).then(|| io::Error::other("injected failure"))
+}
+
+/// Applies `slow_backend` and `backend_unreachable` to one request.
+pub fn before_request() -> Result<(), io::Error> {
+    if let Some(latency) = find(FailureKind::SlowBackend) {
+        thread::sleep(latency);
+    }
+    if is_active(FailureKind::BackendUnreachable) {
+        return Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            "injected failure: backend unreachable",
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveInjection {
+    pub kind: FailureKind,
+    pub remaining_secs: u64,
+    /// `slow_backend` only.
+    pub latency_ms: Option<u64>,
+}
+
+fn data_dir(state: &State<'_, AppState>) -> Result<PathBuf, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(runtime.data_dir.clone())
+}
+
+/// Arms `kind` for `duration_secs`, replacing an injection of the same kind.
+#[tauri::command]
+pub fn inject_failure(
+    state: State<'_, AppState>,
+    kind: FailureKind,
+    duration_secs: u64,
+    latency_ms: Option<u64>,
+) -> Result<Vec<ActiveInjection>, CommandError> {
+    backend_debug::require_developer_mode()?;
+    if duration_secs == 0 || duration_secs > MAX_DURATION_SECS {
+        return Err(CommandError::invalid_input(format!(
+            "duration_secs must be 1-{MAX_DURATION_SECS}"
+        )));
+    }
+    let latency_ms = match kind {
+        FailureKind::SlowBackend => {
+            Some(latency_ms.unwrap_or(DEFAULT_LATENCY_MS).min(MAX_LATENCY_MS))
+        }
+        _ => None,
+    };
+    let data_dir = data_dir(&state)?;
+    {
+        let mut injections = INJECTIONS
+            .lock()
+            .map_err(|_| "injection lock poisoned".to_string())?;
+        injections.retain(|injection| injection.kind != kind);
+        injections.push(Injection {
+            kind,
+            expires_at: Instant::now() + Duration::from_secs(duration_secs),
+            latency: Duration::from_millis(latency_ms.unwrap_or(0)),
+        });
+        ARMED.store(true, Ordering::Relaxed);
+    }
+    let _ = audit::record(
+        &data_dir,
+        "failure_injected",
+        serde_json::json!({
+            "kind": kind,
+            "duration_secs": duration_secs,
+            "latency_ms": latency_ms,
+        }),
+    );
+    desktop_log::warn(
+        &data_dir,
+        &format!("failure injection armed: {kind:?} for {duration_secs}s"),
+    );
+    list_active_injections()
+}
+
+#[tauri::command]
+pub fn list_active_injections() -> Result<Vec<ActiveInjection>, CommandError> {
+    backend_debug::require_developer_mode()?;
+    let now = Instant::now();
+    let active = with_active(|injections| {
+        injections
+            .iter()
+            .map(|injection| ActiveInjection {
+                kind: injection.kind,
+                remaining_secs: injection.expires_at.duration_since(now).as_secs(),
+                latency_ms: (injection.kind == FailureKind::SlowBackend)
+                    .then_some(injection.latency.as_millis() as u64),
+            })
+            .collect()
+    });
+    Ok(active.unwrap_or_default())
+}
+
+/// Returns how many injections were still in force.
+#[tauri::command]
+pub fn clear_injections(state: State<'_, AppState>) -> Result<usize, CommandError> {
+    backend_debug::require_developer_mode()?;
+    let cleared = with_active(|injections| injections.len()).unwrap_or(0);
+    if let Ok(mut injections) = INJECTIONS.lock() {
+        injections.clear();
+    }
+    ARMED.store(false, Ordering::Relaxed);
+    let _ = audit::record(
+        &data_dir(&state)?,
+        "failure_injections_cleared",
+        serde_json::json!({ "cleared": cleared }),
+    );
+    Ok(cleared)
+}
@@ -0,0 +1,654 @@
+//! File mutations performed on behalf of the assistant. Everything here is
+//! confined to allowed folders in `read_write` mode and is audited.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::folders::{AllowedFolder, FolderMode};
+use crate::{
+    audit, excluded_dirs, folder_listing, read_local_config, temporary_folders, AppState,
+    BackendRuntime, LocalConfig,
+};
+
+#[cfg(windows)]
+const TRASH_NAME: &str = "Recycle Bin";
+#[cfg(not(windows))]
+const TRASH_NAME: &str = "Trash";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleteResult {
+    pub path: String,
+    pub was_directory: bool,
+    pub destination: &'static str,
+}
+
+/// Absolute path with parent components resolved but the final component
+/// left alone, so operating on a symlink affects the link, not its target.
+pub fn absolute_target(path: &str) -> Result<PathBuf, CommandError> {
+    let raw = Path::new(path);
+    if !raw.is_absolute() {
+        return Err(CommandError::invalid_input(format!(
+            "path must be absolute: {path}"
+        )));
+    }
+    let (Some(parent), Some(name)) = (raw.parent(), raw.file_name()) else {
+        return Err(CommandError::invalid_input(format!(
+            "path has no file name: {path}"
+        )));
+    };
+    let parent = parent
+        .canonicalize()
+        .map_err(|e| CommandError::not_found(format!("parent folder not found for {path}: {e}")))?;
+    Ok(parent.join(name))
+}
+
+/// Returns the most specific allowed folder containing `path`, requiring it to
+/// be writable and refusing the folder root itself. The app's own dirs are
+/// refused even inside a folder, as in `folders::is_within_allowed`, and a
+/// temporary grant that has lapsed no longer counts.
+pub fn writable_folder_for<'a>(
+    data_dir: &Path,
+    config: &'a LocalConfig,
+    path: &Path,
+) -> Result<&'a AllowedFolder, CommandError> {
+    if excluded_dirs::is_excluded(&excluded_dirs::excluded_dirs(data_dir), path) {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            format!("path belongs to LiteClaw itself: {}", path.display()),
+        ));
+    }
+    let lapsed = temporary_folders::lapsed(data_dir);
+    let folder = config
+        .allowed_folders
+        .iter()
+        .filter(|folder| !lapsed.contains(&folder.path))
+        .filter(|folder| path.starts_with(folder.effective_path()))
+        .max_by_key(|folder| folder.effective_path().components().count())
+        .ok_or_else(|| {
+            CommandError::new(
+                ErrorCode::NotAllowed,
+                format!("path is not inside an allowed folder: {}", path.display()),
+            )
+        })?;
+    if folder.mode != FolderMode::ReadWrite {
+        return Err(CommandError::new(
+            ErrorCode::ReadOnlyFolder,
+            format!("folder is read-only: {}", folder.path),
+        ));
+    }
+    if path == folder.effective_path() {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            "refusing to modify an allowed folder root",
+        ));
+    }
+    Ok(folder)
+}
+
+/// Moves `path` to the OS trash for the `delete_file` command. The backend
+/// has no delete of its own and no route to this one, so every delete
+/// reaches it through the desktop UI.
+pub fn delete_to_trash(
+    runtime: &BackendRuntime,
+    path: &str,
+    recursive: bool,
+) -> Result<DeleteResult, CommandError> {
+    let target = absolute_target(path)?;
+    let metadata = fs::symlink_metadata(&target)
+        .map_err(|e| CommandError::not_found(format!("cannot delete {path}: {e}")))?;
+    let config = read_local_config(&runtime.data_dir)?;
+    writable_folder_for(&runtime.data_dir, &config, &target)?;
+
+    let was_directory = metadata.is_dir();
+    if was_directory && !recursive {
+        return Err(CommandError::invalid_input(
+            "path is a directory; pass recursive: true to delete it",
+        ));
+    }
+
+    // The trash crate never falls back to unlinking, so a failure here means
+    // nothing was removed.
+    trash::delete(&target).map_err(|e| {
+        CommandError::new(
+            ErrorCode::TrashUnavailable,
+            format!("could not move {path} to the {TRASH_NAME}: {e}"),
+        )
+    })?;
+    folder_listing::invalidate(&target);
+
+    let target_display = target.to_string_lossy().to_string();
+    let _ = audit::record(
+        &runtime.data_dir,
+        "file_deleted",
+        serde_json::json!({
+            "path": target_display,
+            "directory": was_directory,
+            "destination": TRASH_NAME,
+        }),
+    );
+    Ok(DeleteResult {
+        path: target_display,
+        was_directory,
+        destination: TRASH_NAME,
+    })
+}
+
+#[tauri::command]
+pub fn delete_file(
+    state: State<'_, AppState>,
+    path: String,
+    recursive: Option<bool>,
+) -> Result<DeleteResult, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    delete_to_trash(&runtime, &path, recursive.unwrap_or(false))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WriteResult {
+    pub path: String,
+    pub sha256: String,
+    pub bytes_written: usize,
+    pub created: bool,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    // An odd length leaves a one-digit slice, which `get` rejects.
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Like `absolute_target`, but tolerates missing parent folders when the
+/// caller intends to create them: the deepest existing ancestor is resolved
+/// and the remaining components are appended verbatim.
+fn resolve_for_write(path: &str, create_dirs: bool) -> Result<PathBuf, CommandError> {
+    if !create_dirs {
+        return absolute_target(path);
+    }
+    let raw = Path::new(path);
+    if !raw.is_absolute() {
+        return Err(CommandError::invalid_input(format!(
+            "path must be absolute: {path}"
+        )));
+    }
+    if raw.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(CommandError::invalid_input(format!(
+            "path must not contain '..': {path}"
+        )));
+    }
+    let mut existing = raw.parent();
+    let mut missing = Vec::new();
+    while let Some(candidate) = existing {
+        if candidate.exists() {
+            break;
+        }
+        if let Some(name) = candidate.file_name() {
+            missing.push(name.to_os_string());
+        }
+        existing = candidate.parent();
+    }
+    let base = existing
+        .ok_or_else(|| {
+            CommandError::invalid_input(format!("path has no existing ancestor: {path}"))
+        })?
+        .canonicalize()
+        .map_err(|e| format!("failed to canonicalize parent of {path}: {e}"))?;
+    let mut resolved = base;
+    for name in missing.iter().rev() {
+        resolved.push(name);
+    }
+    let file_name = raw
+        .file_name()
+        .ok_or_else(|| CommandError::invalid_input(format!("path has no file name: {path}")))?;
+    resolved.push(file_name);
+    Ok(resolved)
+}
+
+pub fn write_text_file(
+    runtime: &BackendRuntime,
+    path: &str,
+    contents: &str,
+    create_dirs: bool,
+    expected_hash: Option<&str>,
+    preserve_crlf: bool,
+) -> Result<WriteResult, CommandError> {
+    let target = resolve_for_write(path, create_dirs)?;
+    let config = read_local_config(&runtime.data_dir)?;
+    writable_folder_for(&runtime.data_dir, &config, &target)?;
+
+    let existing = match fs::read(&target) {
+        Ok(bytes) => Some(bytes),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(format!("failed reading {path}: {err}").into()),
+    };
+    if let Some(expected) = expected_hash {
+        let current = existing.as_deref().map(sha256_hex);
+        if current.as_deref() != Some(&expected.to_ascii_lowercase()) {
+            return Err(CommandError::conflict(format!(
+                "{path} changed since it was read"
+            )));
+        }
+    }
+
+    let uses_crlf = existing
+        .as_deref()
+        .is_some_and(|bytes| bytes.windows(2).any(|pair| pair == b"\r\n"));
+    let bytes = if preserve_crlf && uses_crlf {
+        contents
+            .replace("\r\n", "\n")
+            .replace('\n', "\r\n")
+            .into_bytes()
+    } else {
+        contents.as_bytes().to_vec()
+    };
+
+    let parent = target
+        .parent()
+        .ok_or_else(|| CommandError::invalid_input(format!("path has no parent: {path}")))?;
+    if create_dirs {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("failed creating folders for {path}: {e}"))?;
+    }
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = parent.join(format!(".{file_name}.{}.tmp", Uuid::new_v4()));
+    fs::write(&temp, &bytes).map_err(|e| format!("failed writing temp file for {path}: {e}"))?;
+    if let Ok(metadata) = fs::metadata(&target) {
+        let _ = fs::set_permissions(&temp, metadata.permissions());
+    }
+    if let Err(err) = fs::rename(&temp, &target) {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("failed replacing {path}: {err}").into());
+    }
+    folder_listing::invalidate(&target);
+
+    let sha256 = sha256_hex(&bytes);
+    let target_display = target.to_string_lossy().to_string();
+    let _ = audit::record(
+        &runtime.data_dir,
+        "file_written",
+        serde_json::json!({
+            "path": target_display,
+            "bytes": bytes.len(),
+            "created": existing.is_none(),
+        }),
+    );
+    Ok(WriteResult {
+        path: target_display,
+        sha256,
+        bytes_written: bytes.len(),
+        created: existing.is_none(),
+    })
+}
+
+#[tauri::command]
+pub fn write_file(
+    state: State<'_, AppState>,
+    path: String,
+    contents: String,
+    create_dirs: Option<bool>,
+    expected_hash: Option<String>,
+    preserve_crlf: Option<bool>,
+) -> Result<WriteResult, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    write_text_file(
+        &runtime,
+        &path,
+        &contents,
+        create_dirs.unwrap_or(false),
+        expected_hash.as_deref(),
+        preserve_crlf.unwrap_or(false),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_config;
+
+    /// A data dir allowing `writable` (read-write) with a read-only `locked`
+    /// folder inside it, next to a folder that is not allowed at all.
+    struct Fixture {
+        root: PathBuf,
+        runtime: BackendRuntime,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let root = std::env::temp_dir().join(format!("liteclaw-file-ops-{}", Uuid::new_v4()));
+            for dir in ["data", "writable/locked", "writable/sub", "outside"] {
+                fs::create_dir_all(root.join(dir)).unwrap();
+            }
+            let root = root.canonicalize().unwrap();
+            for file in ["writable/a.txt", "writable/locked/b.txt", "outside/c.txt"] {
+                fs::write(root.join(file), "keep").unwrap();
+            }
+            let runtime = BackendRuntime::new(root.join("data"), "default".to_string());
+            let mut config = read_local_config(&runtime.data_dir).unwrap();
+            let mut writable = AllowedFolder::new(root.join("writable").to_string_lossy().into());
+            writable.mode = FolderMode::ReadWrite;
+            let locked = AllowedFolder::new(root.join("writable/locked").to_string_lossy().into());
+            config.allowed_folders = vec![writable, locked];
+            commit_config(&runtime.data_dir, &config).unwrap();
+            Self { root, runtime }
+        }
+
+        fn path(&self, relative: &str) -> String {
+            self.root.join(relative).to_string_lossy().into_owned()
+        }
+
+        fn delete_fails(&self, relative: &str, recursive: bool) -> ErrorCode {
+            delete_to_trash(&self.runtime, &self.path(relative), recursive)
+                .unwrap_err()
+                .code
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn deletes_outside_allowed_folders_are_refused() {
+        let fixture = Fixture::new();
+        for path in ["outside/c.txt", "writable/../outside/c.txt", "data"] {
+            assert_eq!(
+                fixture.delete_fails(path, true),
+                ErrorCode::NotAllowed,
+                "{path}"
+            );
+        }
+        #[cfg(unix)]
+        {
+            let link = fixture.root.join("writable/escape");
+            std::os::unix::fs::symlink(fixture.root.join("outside"), &link).unwrap();
+            assert_eq!(
+                fixture.delete_fails("writable/escape/c.txt", false),
+                ErrorCode::NotAllowed
+            );
+        }
+        let relative = delete_to_trash(&fixture.runtime, "writable/a.txt", false).unwrap_err();
+        assert_eq!(relative.code, ErrorCode::InvalidInput);
+        assert!(fixture.root.join("outside/c.txt").exists());
+    }
+
+    #[test]
+    fn read_only_folders_and_folder_roots_are_not_deleted() {
+        let fixture = Fixture::new();
+        // The most specific folder decides, so read-only inside read-write
+        // stays read-only.
+        assert_eq!(
+            fixture.delete_fails("writable/locked/b.txt", false),
+            ErrorCode::ReadOnlyFolder
+        );
+        assert_eq!(
+            fixture.delete_fails("writable/locked", true),
+            ErrorCode::ReadOnlyFolder
+        );
+        assert_eq!(
+            fixture.delete_fails("writable", true),
+            ErrorCode::NotAllowed
+        );
+        assert_eq!(
+            fixture.delete_fails("writable/sub", false),
+            ErrorCode::InvalidInput
+        );
+        for kept in ["writable/locked/b.txt", "writable/sub"] {
+            assert!(fixture.root.join(kept).exists(), "{kept}");
+        }
+
+        let config = read_local_config(&fixture.runtime.data_dir).unwrap();
+        let folder = writable_folder_for(
+            &fixture.runtime.data_dir,
+            &config,
+            &fixture.root.join("writable/sub/new"),
+        )
+        .unwrap();
+        assert_eq!(folder.path, fixture.path("writable"));
+    }
+
+    #[test]
+    fn a_stale_expected_hash_is_a_conflict() {
+        let fixture = Fixture::new();
+        let path = fixture.path("writable/a.txt");
+        let write = |expected: &str| {
+            write_text_file(&fixture.runtime, &path, "new", false, Some(expected), false)
+        };
+        let stale = write(&sha256_hex(b"older")).unwrap_err();
+        assert_eq!(stale.code, ErrorCode::Conflict);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "keep");
+
+        let written = write(&sha256_hex(b"keep").to_ascii_uppercase()).unwrap();
+        assert_eq!(written.sha256, sha256_hex(b"new"));
+        assert!(!written.created);
+        // A file that does not exist has no hash to match.
+        let missing = fixture.path("writable/missing.txt");
+        let err = write_text_file(
+            &fixture.runtime,
+            &missing,
+            "x",
+            false,
+            Some(&written.sha256),
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err.code, ErrorCode::Conflict);
+        assert!(!Path::new(&missing).exists());
+    }
+
+    #[test]
+    fn crlf_files_keep_their_line_endings_when_asked() {
+        let fixture = Fixture::new();
+        let path = fixture.path("writable/a.txt");
+        let write = |contents: &str, preserve_crlf: bool| {
+            write_text_file(
+                &fixture.runtime,
+                &path,
+                contents,
+                false,
+                None,
+                preserve_crlf,
+            )
+            .unwrap();
+            fs::read(&path).unwrap()
+        };
+        fs::write(&path, "one\r\ntwo\r\n").unwrap();
+        assert_eq!(write("a\nb\r\nc", true), b"a\r\nb\r\nc");
+        assert_eq!(write("a\nb\n", false), b"a\nb\n");
+        // The file is LF now, so there is nothing left to preserve.
+        assert_eq!(write("x\ny", true), b"x\ny");
+        let created = fixture.path("writable/new.txt");
+        write_text_file(&fixture.runtime, &created, "x\ny", false, None, true).unwrap();
+        assert_eq!(fs::read(&created).unwrap(), b"x\ny");
+    }
+
+    #[test]
+    fn writes_stay_inside_writable_folders() {
+        let fixture = Fixture::new();
+        let refused = |relative: &str, create_dirs: bool| {
+            write_text_file(
+                &fixture.runtime,
+                &fixture.path(relative),
+                "x",
+                create_dirs,
+                None,
+                false,
+            )
+            .unwrap_err()
+            .code
+        };
+        assert_eq!(refused("outside/c.txt", false), ErrorCode::NotAllowed);
+        assert_eq!(
+            refused("writable/../outside/c.txt", false),
+            ErrorCode::NotAllowed
+        );
+        assert_eq!(
+            refused("writable/../outside/new/d.txt", true),
+            ErrorCode::InvalidInput
+        );
+        assert_eq!(refused("outside/new/d.txt", true), ErrorCode::NotAllowed);
+        assert_eq!(
+            refused("writable/locked/b.txt", false),
+            ErrorCode::ReadOnlyFolder
+        );
+        assert_eq!(
+            refused("writable/missing/d.txt", false),
+            ErrorCode::NotFound
+        );
+        #[cfg(unix)]
+        {
+            let link = fixture.root.join("writable/escape");
+            std::os::unix::fs::symlink(fixture.root.join("outside"), &link).unwrap();
+            assert_eq!(
+                refused("writable/escape/c.txt", false),
+                ErrorCode::NotAllowed
+            );
+            assert_eq!(
+                refused("writable/escape/new/d.txt", true),
+                ErrorCode::NotAllowed
+            );
+        }
+        assert!(!fixture.root.join("outside/new").exists());
+        assert_eq!(
+            fs::read_to_string(fixture.root.join("outside/c.txt")).unwrap(),
+            "keep"
+        );
+
+        let nested = write_text_file(
+            &fixture.runtime,
+            &fixture.path("writable/new/deeper/d.txt"),
+            "x",
+            true,
+            None,
+            false,
+        )
+        .unwrap();
+        assert!(nested.created);
+        assert_eq!(nested.path, fixture.path("writable/new/deeper/d.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_replace_the_file_in_one_step() {
+        use std::os::unix::fs::PermissionsExt;
+        let fixture = Fixture::new();
+        let path = fixture.root.join("writable/a.txt");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        // A reader holding the old file keeps seeing it whole: the new
+        // contents arrive under a new inode by rename, not by truncation.
+        let mut reader = fs::File::open(&path).unwrap();
+        let written = write_text_file(
+            &fixture.runtime,
+            &fixture.path("writable/a.txt"),
+            "replaced",
+            false,
+            None,
+            false,
+        )
+        .unwrap();
+        let mut old = String::new();
+        std::io::Read::read_to_string(&mut reader, &mut old).unwrap();
+        assert_eq!(old, "keep");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "replaced");
+        assert_eq!(written.sha256, sha256_hex(b"replaced"));
+        assert_eq!(written.bytes_written, 8);
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        let leftovers: Vec<_> = fs::read_dir(fixture.root.join("writable"))
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+    }
+
+    #[test]
+    fn the_app_data_inside_a_writable_folder_stays_out_of_reach() {
+        let fixture = Fixture::new();
+        let data_dir = fixture.root.join("writable/app-data");
+        fs::create_dir_all(&data_dir).unwrap();
+        let runtime = BackendRuntime::new(data_dir.clone(), "default".to_string());
+        let config = read_local_config(&fixture.runtime.data_dir).unwrap();
+        commit_config(&data_dir, &config).unwrap();
+        let config_json = data_dir.join("config.json").to_string_lossy().into_owned();
+        let before = fs::read(&config_json).unwrap();
+
+        let write = write_text_file(&runtime, &config_json, "{}", false, None, false);
+        assert_eq!(write.unwrap_err().code, ErrorCode::NotAllowed);
+        let delete = delete_to_trash(&runtime, &config_json, false);
+        assert_eq!(delete.unwrap_err().code, ErrorCode::NotAllowed);
+        let nested = data_dir.join("new/file.txt").to_string_lossy().into_owned();
+        let created = write_text_file(&runtime, &nested, "x", true, None, false);
+        assert_eq!(created.unwrap_err().code, ErrorCode::NotAllowed);
+        assert_eq!(fs::read(&config_json).unwrap(), before);
+        assert!(!data_dir.join("new").exists());
+        // The rest of the folder is as writable as before.
+        write_text_file(
+            &runtime,
+            &fixture.path("writable/a.txt"),
+            "x",
+            false,
+            None,
+            false,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn a_lapsed_temporary_grant_no_longer_allows_writes() {
+        let fixture = Fixture::new();
+        let grants = serde_json::json!([{
+            "path": fixture.path("writable"),
+            "granted_at": crate::timestamps::from_unix_secs(1),
+            "expires_at": crate::timestamps::from_unix_secs(2),
+        }]);
+        fs::write(
+            fixture.runtime.data_dir.join("temporary_folders.json"),
+            grants.to_string(),
+        )
+        .unwrap();
+        let err = write_text_file(
+            &fixture.runtime,
+            &fixture.path("writable/a.txt"),
+            "x",
+            false,
+            None,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotAllowed);
+        assert_eq!(
+            fixture.delete_fails("writable/a.txt", false),
+            ErrorCode::NotAllowed
+        );
+        assert_eq!(
+            fs::read_to_string(fixture.root.join("writable/a.txt")).unwrap(),
+            "keep"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use tauri::State;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::ignore_rules::FolderIgnore;
+use crate::{folders, ignore_rules, normalize_folder, read_local_config, AppState};
+
+const SAMPLE_BYTES: usize = 512;
+// Directory entries inspected while looking for a regular file to sample.
+const SAMPLE_SCAN_LIMIT: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderAccessReport {
+    pub path: String,
+    pub readable: bool,
+    pub listable: bool,
+    /// `None` when the folder has no regular file near the top to sample.
+    pub sample_file_readable: Option<bool>,
+    pub error: Option<String>,
+    pub os_error_code: Option<i32>,
+    /// Set when the failure looks like a macOS privacy (TCC) denial.
+    pub privacy_settings_hint: bool,
+}
+
+impl FolderAccessReport {
+    fn record_error(&mut self, path: &Path, err: &io::Error) {
+        self.error = Some(err.to_string());
+        self.os_error_code = err.raw_os_error();
+        self.privacy_settings_hint = looks_like_privacy_denial(path, err);
+    }
+}
+
+/// Entries matched by `ignore` are not picked as the sample file, so the
+/// probe reads something the backend would.
+pub fn probe_folder_access(path: &Path, ignore: Option<&FolderIgnore>) -> FolderAccessReport {
+    let mut report = FolderAccessReport {
+        path: path.to_string_lossy().to_string(),
+        readable: false,
+        listable: false,
+        sample_file_readable: None,
+        error: None,
+        os_error_code: None,
+        privacy_settings_hint: false,
+    };
+
+    let mut entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            report.record_error(path, &err);
+            return report;
+        }
+    };
+    report.readable = true;
+
+    let mut sample = None;
+    for (index, entry) in entries.by_ref().take(SAMPLE_SCAN_LIMIT).enumerate() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                report.record_error(path, &err);
+                return report;
+            }
+        };
+        if index == 0 {
+            report.listable = true;
+        }
+        let is_file = entry.file_type().map(|t| t.is_file()).unwrap_or(false);
+        if is_file && !ignore.is_some_and(|rules| rules.is_ignored(&entry.path(), false)) {
+            sample = Some(entry.path());
+            break;
+        }
+    }
+    // An empty directory is still listable.
+    if sample.is_none() && report.error.is_none() {
+        report.listable = true;
+    }
+
+    if let Some(file_path) = sample {
+        let result = File::open(&file_path).and_then(|mut file| {
+            let mut buf = [0u8; SAMPLE_BYTES];
+            file.read(&mut buf).map(|_| ())
+        });
+        match result {
+            Ok(()) => report.sample_file_readable = Some(true),
+            Err(err) => {
+                report.sample_file_readable = Some(false);
+                report.record_error(&file_path, &err);
+            }
+        }
+    }
+    report
+}
+
+#[cfg(target_os = "macos")]
+pub fn looks_like_privacy_denial(path: &Path, err: &io::Error) -> bool {
+    // TCC denials surface as EPERM rather than EACCES, on otherwise accessible paths.
+    if err.raw_os_error() != Some(libc::EPERM) {
+        return false;
+    }
+    protected_roots().iter().any(|root| path.starts_with(root))
+}
+
+/// Home subfolders that macOS guards behind per-app privacy consent.
+#[cfg(target_os = "macos")]
+pub fn protected_roots() -> Vec<std::path::PathBuf> {
+    let Some(home) = std::env::var_os("HOME").map(std::path::PathBuf::from) else {
+        return Vec::new();
+    };
+    [
+        "Desktop",
+        "Documents",
+        "Downloads",
+        "Pictures",
+        "Movies",
+        "Music",
+        "Library/Mobile Documents",
+    ]
+    .iter()
+    .map(|sub| home.join(sub))
+    .collect()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn looks_like_privacy_denial(_path: &Path, _err: &io::Error) -> bool {
+    false
+}
+
+#[tauri::command]
+pub fn check_folder_access(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<FolderAccessReport, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let normalized = normalize_folder(&path).unwrap_or(path);
+    let config = read_local_config(&runtime.data_dir)?;
+    if !folders::is_within_allowed(&runtime.data_dir, &config, Path::new(&normalized)) {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            format!("path is not inside an allowed folder: {normalized}"),
+        ));
+    }
+    let ignore = ignore_rules::for_path(&runtime.data_dir, &config, Path::new(&normalized));
+    Ok(probe_folder_access(Path::new(&normalized), ignore.as_ref()))
+}
@@ -0,0 +1,154 @@
+//! Recognizing an allowed folder after it was renamed or moved. Each entry
+//! stores a small fingerprint of its folder: the inode (Unix), the creation
+//! time where the filesystem keeps one, and the first few names inside it.
+//! A rename within a filesystem keeps the first two; a move elsewhere
+//! usually keeps the names. When a folder goes missing,
+//! `validate_allowed_folders` fingerprints the directories next to where it
+//! was and offers the one that matches as `possible_new_location`.
+//!
+//! Fingerprints are taken when a folder is added and refreshed whenever
+//! validation finds the folder in place, so the names follow slow changes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Names kept in a fingerprint, the first in sorted order.
+const SAMPLE_NAMES: usize = 16;
+/// Entries read at most to pick those names.
+const MAX_SCANNED: usize = 2000;
+/// Siblings fingerprinted at most when looking for a moved folder.
+const MAX_SIBLINGS: usize = 200;
+/// Share of stored names a candidate must also have to count as a match.
+const NAME_OVERLAP: f64 = 0.8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderFingerprint {
+    #[serde(default)]
+    pub inode: Option<u64>,
+    /// Unix seconds.
+    #[serde(default)]
+    pub created: Option<u64>,
+    #[serde(default)]
+    pub names: Vec<String>,
+}
+
+#[cfg(unix)]
+fn inode(meta: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.ino())
+}
+
+#[cfg(not(unix))]
+fn inode(_meta: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// `None` when `path` is not a readable directory.
+pub fn take(path: &Path) -> Option<FolderFingerprint> {
+    let meta = fs::metadata(path).ok()?;
+    if !meta.is_dir() {
+        return None;
+    }
+    let names: BTreeSet<String> = fs::read_dir(path)
+        .ok()?
+        .take(MAX_SCANNED)
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    Some(FolderFingerprint {
+        inode: inode(&meta),
+        created: meta
+            .created()
+            .ok()
+            .and_then(|created| created.duration_since(UNIX_EPOCH).ok())
+            .map(|since| since.as_secs()),
+        names: names.into_iter().take(SAMPLE_NAMES).collect(),
+    })
+}
+
+/// How strongly `candidate` looks like the folder `stored` was taken of:
+/// two points for the same inode, one each for the same creation time
+/// and for mostly the same names. Two or more is a likely match.
+fn score(stored: &FolderFingerprint, candidate: &FolderFingerprint) -> u32 {
+    let same = |a: Option<u64>, b: Option<u64>| a.is_some() && a == b;
+    let mut score = 0;
+    if same(stored.inode, candidate.inode) {
+        score += 2;
+    }
+    if same(stored.created, candidate.created) {
+        score += 1;
+    }
+    if !stored.names.is_empty() {
+        let shared = stored
+            .names
+            .iter()
+            .filter(|name| candidate.names.contains(name))
+            .count();
+        if shared as f64 / stored.names.len() as f64 >= NAME_OVERLAP {
+            score += 1;
+        }
+    }
+    score
+}
+
+/// The directory beside `missing` that `stored` most likely describes now,
+/// skipping those `taken` says are already allowed. Two equally good
+/// candidates are no answer.
+pub fn find_relocation(
+    missing: &Path,
+    stored: &FolderFingerprint,
+    taken: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    let parent = missing.parent()?;
+    let mut best: Option<(u32, PathBuf)> = None;
+    let mut tied = false;
+    let siblings = fs::read_dir(parent).ok()?.filter_map(Result::ok);
+    for entry in siblings.take(MAX_SIBLINGS) {
+        let path = entry.path();
+        if path == missing || taken(&path) {
+            continue;
+        }
+        let Some(candidate) = take(&path) else {
+            continue;
+        };
+        let score = score(stored, &candidate);
+        match &best {
+            _ if score < 2 => {}
+            Some((best_score, _)) if score < *best_score => {}
+            Some((best_score, _)) if score == *best_score => tied = true,
+            _ => {
+                best = Some((score, path));
+                tied = false;
+            }
+        }
+    }
+    best.filter(|_| !tied).map(|(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn a_renamed_folder_is_found_beside_its_old_path() {
+        let root = std::env::temp_dir().join(format!("liteclaw-relocate-{}", Uuid::new_v4()));
+        let old = root.join("acme");
+        for name in ["Cargo.toml", "README.md", "src"] {
+            fs::create_dir_all(&old).unwrap();
+            fs::write(old.join(name), name).unwrap();
+        }
+        fs::create_dir_all(root.join("other")).unwrap();
+        fs::write(root.join("other").join("notes.txt"), "x").unwrap();
+        let stored = take(&old).unwrap();
+
+        let new = root.join("acme-v2");
+        fs::rename(&old, &new).unwrap();
+        assert_eq!(find_relocation(&old, &stored, |_| false), Some(new.clone()));
+        assert_eq!(find_relocation(&old, &stored, |path| path == new), None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
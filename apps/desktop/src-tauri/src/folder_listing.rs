@@ -0,0 +1,299 @@
+//! Directory listings for the file browser. Expanding and collapsing a tree
+//! node relists the same directory over and over, so listings are cached by
+//! canonical path with the directory's mtime as validator: a directory whose
+//! mtime is unchanged has the same names, and its cached entries are
+//! returned without statting each one again. Adding, removing or renaming
+//! an entry bumps the mtime; rewriting a file in place does not, so the
+//! writes in `file_ops` invalidate the parent's listing themselves. Changes
+//! made outside the app to an existing file show their new size once the
+//! directory itself changes or the entry is evicted.
+//!
+//! The cache is capped in listings and in approximate bytes and evicts the
+//! least recently used. Listings are cached unfiltered, since ignore rules
+//! can change without the directory changing; `list_folder` filters them.
+//! Hits and misses are counted in `metrics`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::State;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::response_limit::{self, Fitted};
+use crate::{folders, ignore_rules, metrics, normalize_folder, read_local_config, AppState};
+
+const MAX_LISTINGS: usize = 256;
+const MAX_BYTES: usize = 8 * 1024 * 1024;
+/// Per-entry overhead on top of the name, for the byte cap.
+const ENTRY_OVERHEAD: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FolderEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    /// Files only.
+    pub size: Option<u64>,
+    #[serde(serialize_with = "crate::timestamps::serialize_unix_secs_opt")]
+    pub modified: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderListing {
+    pub path: String,
+    /// Directories first, then by name.
+    pub entries: Vec<FolderEntry>,
+    /// Entries hidden by the folder's ignore rules.
+    pub ignored: usize,
+    pub cached: bool,
+}
+
+struct Cached {
+    mtime: SystemTime,
+    entries: Arc<Vec<FolderEntry>>,
+    bytes: usize,
+    last_used: u64,
+}
+
+#[derive(Default)]
+pub struct ListingCache {
+    listings: HashMap<PathBuf, Cached>,
+    bytes: usize,
+    clock: u64,
+}
+
+static CACHE: Mutex<Option<ListingCache>> = Mutex::new(None);
+
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+fn read_entries(dir: &Path) -> io::Result<Vec<FolderEntry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Ok(link) = entry.metadata() else {
+            continue;
+        };
+        let is_symlink = link.file_type().is_symlink();
+        // Symlinks are shown as what they point to, when that resolves.
+        let metadata = if is_symlink {
+            fs::metadata(entry.path()).unwrap_or(link)
+        } else {
+            link
+        };
+        entries.push(FolderEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: metadata.is_dir(),
+            is_symlink,
+            size: metadata.is_file().then_some(metadata.len()),
+            modified: metadata.modified().ok().and_then(unix_secs),
+        });
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+fn listing_bytes(entries: &[FolderEntry]) -> usize {
+    entries
+        .iter()
+        .map(|entry| entry.name.len() + ENTRY_OVERHEAD)
+        .sum()
+}
+
+impl ListingCache {
+    /// The entries of `dir` and whether they came from the cache.
+    pub fn list(&mut self, dir: &Path) -> io::Result<(Arc<Vec<FolderEntry>>, bool)> {
+        let mtime = fs::metadata(dir)?.modified()?;
+        self.clock += 1;
+        if let Some(cached) = self.listings.get_mut(dir) {
+            if cached.mtime == mtime {
+                cached.last_used = self.clock;
+                return Ok((cached.entries.clone(), true));
+            }
+        }
+        self.remove(dir);
+        let entries = Arc::new(read_entries(dir)?);
+        let bytes = listing_bytes(&entries);
+        if bytes <= MAX_BYTES {
+            self.bytes += bytes;
+            self.listings.insert(
+                dir.to_path_buf(),
+                Cached {
+                    mtime,
+                    entries: entries.clone(),
+                    bytes,
+                    last_used: self.clock,
+                },
+            );
+            self.evict();
+        }
+        Ok((entries, false))
+    }
+
+    fn remove(&mut self, dir: &Path) {
+        if let Some(cached) = self.listings.remove(dir) {
+            self.bytes -= cached.bytes;
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.listings.len() > MAX_LISTINGS || self.bytes > MAX_BYTES {
+            let Some(oldest) = self
+                .listings
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(dir, _)| dir.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+
+    /// Drops the listings that show `path`: its parent's, and its own and
+    /// everything below it when it is a directory.
+    pub fn invalidate(&mut self, path: &Path) {
+        let stale: Vec<PathBuf> = self
+            .listings
+            .keys()
+            .filter(|dir| dir.starts_with(path) || path.parent() == Some(dir.as_path()))
+            .cloned()
+            .collect();
+        for dir in stale {
+            self.remove(&dir);
+        }
+    }
+}
+
+fn cached_list(dir: &Path) -> Result<(Arc<Vec<FolderEntry>>, bool), CommandError> {
+    let mut cache = CACHE
+        .lock()
+        .map_err(|_| "listing cache lock poisoned".to_string())?;
+    let (entries, hit) = cache
+        .get_or_insert_with(ListingCache::default)
+        .list(dir)
+        .map_err(|e| CommandError::not_found(format!("cannot list {}: {e}", dir.display())))?;
+    metrics::increment(if hit {
+        &metrics::METRICS.folder_listing_hits
+    } else {
+        &metrics::METRICS.folder_listing_misses
+    });
+    Ok((entries, hit))
+}
+
+/// For changes the app makes itself; see the module comment.
+pub fn invalidate(path: &Path) {
+    if let Ok(mut cache) = CACHE.lock() {
+        if let Some(cache) = cache.as_mut() {
+            cache.invalidate(path);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_folder(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<Fitted<FolderListing>, CommandError> {
+    let (data_dir, config) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        let config = read_local_config(&runtime.data_dir)?;
+        (runtime.data_dir.clone(), config)
+    };
+    let dir = PathBuf::from(normalize_folder(&path).map_err(CommandError::not_found)?);
+    if !folders::is_within_allowed(&data_dir, &config, &dir) {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            format!("path is not inside an allowed folder: {}", dir.display()),
+        ));
+    }
+    let (entries, cached) = cached_list(&dir)?;
+    let ignore = ignore_rules::for_path(&data_dir, &config, &dir);
+    let visible: Vec<FolderEntry> = entries
+        .iter()
+        .filter(|entry| {
+            !ignore
+                .as_ref()
+                .is_some_and(|rules| rules.is_ignored(&dir.join(&entry.name), entry.is_dir))
+        })
+        .cloned()
+        .collect();
+    let listing = FolderListing {
+        path: dir.to_string_lossy().into_owned(),
+        ignored: entries.len() - visible.len(),
+        entries: visible,
+        cached,
+    };
+    response_limit::fit(&data_dir, listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn temp_dir() -> TempDir {
+        let dir = std::env::temp_dir().join(format!("liteclaw-listing-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir.canonicalize().unwrap())
+    }
+
+    fn names(entries: &[FolderEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn changing_a_directory_invalidates_its_listing() {
+        let dir = temp_dir();
+        fs::write(dir.0.join("a.txt"), "a").unwrap();
+        let mut cache = ListingCache::default();
+
+        let (first, hit) = cache.list(&dir.0).unwrap();
+        assert!(!hit);
+        assert_eq!(names(&first), ["a.txt"]);
+        let (_, hit) = cache.list(&dir.0).unwrap();
+        assert!(hit);
+
+        fs::create_dir(dir.0.join("sub")).unwrap();
+        fs::write(dir.0.join("b.txt"), "b").unwrap();
+        let (second, hit) = cache.list(&dir.0).unwrap();
+        assert!(!hit);
+        assert_eq!(names(&second), ["sub", "a.txt", "b.txt"]);
+
+        fs::remove_file(dir.0.join("a.txt")).unwrap();
+        let (third, _) = cache.list(&dir.0).unwrap();
+        assert_eq!(names(&third), ["sub", "b.txt"]);
+    }
+
+    #[test]
+    fn invalidating_a_file_drops_its_parent_listing() {
+        let dir = temp_dir();
+        let file = dir.0.join("a.txt");
+        fs::write(&file, "a").unwrap();
+        let mut cache = ListingCache::default();
+        cache.list(&dir.0).unwrap();
+
+        // Rewriting in place leaves the directory's mtime alone.
+        fs::write(&file, "abc").unwrap();
+        cache.invalidate(&file);
+        let (entries, hit) = cache.list(&dir.0).unwrap();
+        assert!(!hit);
+        assert_eq!(entries[0].size, Some(3));
+    }
+}
@@ -0,0 +1,244 @@
+//! What kind of place a folder about to be allowed lives on. Some places
+//! break the backend outright: `/proc` and `/sys` hold endless or shifting
+//! entries that indexing hangs on, `/dev` holds endpoints rather than files,
+//! and a filesystem or drive root hands over everything. Those are refused,
+//! each with its own error code so the UI can say why: `pseudo_filesystem`,
+//! `filesystem_root` (unless the caller passes `allow_root`), and
+//! `not_found` or `not_a_directory` for input that is no folder at all.
+//!
+//! Others work but deserve a warning, reported as `location_kind`: a
+//! read-only mount (a mounted ISO, say) can be read but never written, and
+//! FUSE filesystems (MTP phones, sshfs, cloud drives) can be slow enough to
+//! look hung.
+//!
+//! Pseudo-filesystems are recognized by `statfs` magic on Linux and by type
+//! name on macOS; elsewhere, and as a backstop, by path prefix.
+
+use serde::Serialize;
+use std::path::Path;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::paths;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocationKind {
+    Local,
+    /// Mounted read-only; `read_write` mode cannot take effect.
+    ReadOnlyMount,
+    /// A FUSE filesystem, possibly backed by a device or the network.
+    Fuse,
+}
+
+/// What the filesystem under a path says about itself.
+#[derive(Debug, Default)]
+struct Probe {
+    /// Name of the pseudo-filesystem the path is on.
+    pseudo: Option<&'static str>,
+    read_only: bool,
+    fuse: bool,
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::Probe;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    const FUSE_MAGIC: u32 = 0x6573_5546;
+    /// From `linux/magic.h`. `/dev` is devtmpfs, which shares tmpfs's magic,
+    /// so it is left to the prefix check.
+    const PSEUDO: &[(u32, &str)] = &[
+        (0x9fa0, "proc"),
+        (0x6265_6572, "sysfs"),
+        (0x1cd1, "devpts"),
+        (0x0027_e0eb, "cgroup"),
+        (0x6367_7270, "cgroup2"),
+        (0x6462_6720, "debugfs"),
+        (0x7472_6163, "tracefs"),
+        (0x7363_6673, "securityfs"),
+        (0xcafe_4a11, "bpf"),
+        (0x6165_676c, "pstore"),
+        (0x6265_6570, "configfs"),
+        (0xde5e_81e4, "efivarfs"),
+        (0x6573_5543, "fusectl"),
+        (0x1980_0202, "mqueue"),
+        (0x4249_4e4d, "binfmt_misc"),
+        (0xf97c_ff8c, "selinuxfs"),
+    ];
+
+    pub fn probe(path: &Path) -> Probe {
+        let mut probe = Probe::default();
+        let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+            return probe;
+        };
+        // SAFETY: each struct is filled by its call and only read after the
+        // call succeeded.
+        unsafe {
+            let mut fs: libc::statfs = std::mem::zeroed();
+            if libc::statfs(c_path.as_ptr(), &mut fs) == 0 {
+                let magic = fs.f_type as u32;
+                probe.fuse = magic == FUSE_MAGIC;
+                probe.pseudo = PSEUDO
+                    .iter()
+                    .find(|(known, _)| *known == magic)
+                    .map(|(_, name)| *name);
+            }
+            let mut vfs: libc::statvfs = std::mem::zeroed();
+            if libc::statvfs(c_path.as_ptr(), &mut vfs) == 0 {
+                probe.read_only = vfs.f_flag & libc::ST_RDONLY != 0;
+            }
+        }
+        probe
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::Probe;
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub fn probe(path: &Path) -> Probe {
+        let mut probe = Probe::default();
+        let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+            return probe;
+        };
+        // SAFETY: the struct is filled by the call and only read after the
+        // call succeeded; `f_fstypename` is NUL-terminated.
+        unsafe {
+            let mut fs: libc::statfs = std::mem::zeroed();
+            if libc::statfs(c_path.as_ptr(), &mut fs) != 0 {
+                return probe;
+            }
+            let name = CStr::from_ptr(fs.f_fstypename.as_ptr()).to_string_lossy();
+            probe.pseudo = (name == "devfs").then_some("devfs");
+            // macfuse, osxfuse, fusefs, ...
+            probe.fuse = name.contains("fuse");
+            probe.read_only = fs.f_flags & libc::MNT_RDONLY as u32 != 0;
+        }
+        probe
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod platform {
+    use super::Probe;
+    use std::path::Path;
+
+    pub fn probe(_path: &Path) -> Probe {
+        Probe::default()
+    }
+}
+
+#[cfg(unix)]
+fn under_pseudo_prefix(path: &Path) -> bool {
+    ["/proc", "/sys", "/dev"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+/// The device namespace and the object manager root.
+#[cfg(windows)]
+fn under_pseudo_prefix(path: &Path) -> bool {
+    let path = path.to_string_lossy().to_ascii_lowercase();
+    [r"\\.\", r"\\?\globalroot\"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+fn kind_of(probe: &Probe) -> LocationKind {
+    if probe.fuse {
+        LocationKind::Fuse
+    } else if probe.read_only {
+        LocationKind::ReadOnlyMount
+    } else {
+        LocationKind::Local
+    }
+}
+
+/// Where the folder at `path` lives, without judging it.
+pub fn kind(path: &Path) -> LocationKind {
+    kind_of(&platform::probe(path))
+}
+
+/// Refuses a canonical folder path that cannot be allowed, or says what
+/// kind of place it is.
+pub fn check(path: &Path, allow_root: bool) -> Result<LocationKind, CommandError> {
+    let probe = platform::probe(path);
+    let pseudo = probe
+        .pseudo
+        .or_else(|| under_pseudo_prefix(path).then_some("a device or kernel filesystem"));
+    if let Some(name) = pseudo {
+        return Err(CommandError::new(
+            ErrorCode::PseudoFilesystem,
+            format!(
+                "{} is on {name}, which holds no files to work with",
+                path.display()
+            ),
+        ));
+    }
+    if !allow_root && path.parent().is_none() {
+        return Err(CommandError::new(
+            ErrorCode::FilesystemRoot,
+            format!(
+                "{} is the root of a filesystem; allowing it grants everything on it",
+                path.display()
+            ),
+        ));
+    }
+    Ok(kind_of(&probe))
+}
+
+/// The error for input that names no existing folder.
+pub fn not_a_folder(path: &str) -> CommandError {
+    if paths::for_io(Path::new(path)).exists() {
+        CommandError::new(ErrorCode::NotADirectory, format!("not a folder: {path}"))
+    } else {
+        CommandError::not_found(format!("folder does not exist: {path}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folders::resolve_folder_input;
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn roots_and_paths_that_are_no_folder_have_their_own_codes() {
+        let temp = std::env::temp_dir().canonicalize().unwrap();
+        let root = temp.ancestors().last().unwrap();
+        assert_eq!(
+            check(root, false).unwrap_err().code,
+            ErrorCode::FilesystemRoot
+        );
+        assert!(check(root, true).is_ok());
+        assert!(check(&temp, false).is_ok());
+
+        let missing = temp.join(format!("liteclaw-missing-{}", Uuid::new_v4()));
+        let missing = missing.to_string_lossy();
+        let err = resolve_folder_input(&missing, true, false).unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotFound);
+
+        let file = temp.join(format!("liteclaw-file-{}", Uuid::new_v4()));
+        fs::write(&file, "x").unwrap();
+        let err = resolve_folder_input(&file.to_string_lossy(), true, false).unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotADirectory);
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kernel_filesystems_are_refused() {
+        for path in ["/proc", "/sys", "/dev"] {
+            if Path::new(path).is_dir() {
+                let err = resolve_folder_input(path, true, false).unwrap_err();
+                assert_eq!(err.code, ErrorCode::PseudoFilesystem, "{path}");
+            }
+        }
+    }
+}
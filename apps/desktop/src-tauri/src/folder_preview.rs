@@ -0,0 +1,406 @@
+//! What adding a folder would take on, before it is added. A bounded walk
+//! of the folder, with the ignore rules it would get, counts its files and
+//! bytes, the largest top-level subdirectories and a rough file-type split,
+//! and names the built-in exclusions that keep parts of it out. Nothing is
+//! written and the backend is not told.
+//!
+//! The walk stops at `PREVIEW_BUDGET`; the counts are then what it saw so
+//! far and `estimate_incomplete` is set. It runs as a task, so `cancel_task`
+//! with the `preview_id` stops it early. `add_allowed_folder` runs a much
+//! smaller walk to warn about very large folders unless told the folder was
+//! previewed. Symlinks inside the folder are counted but not followed.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::excluded_dirs;
+use crate::folder_location::{self, LocationKind};
+use crate::folders::{self, AllowedFolder, SymlinkInfo};
+use crate::ignore_rules::{FolderIgnore, DEFAULT_IGNORE_PATTERNS};
+use crate::tasks::{TaskKind, TaskToken};
+use crate::{read_local_config, AppState};
+
+const TOP_DIRS: usize = 10;
+/// Entries between cancellation checks.
+const CHECK_EVERY: u64 = 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    pub time: Duration,
+    pub entries: u64,
+}
+
+pub const PREVIEW_BUDGET: Budget = Budget {
+    time: Duration::from_secs(10),
+    entries: 200_000,
+};
+
+/// What `add_allowed_folder` looks at before it warns.
+const ADD_CHECK_BUDGET: Budget = Budget {
+    time: Duration::from_secs(1),
+    entries: 20_000,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TypeCount {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DirSize {
+    pub name: String,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+/// A default ignore pattern and the directories it kept out of the walk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExclusionHit {
+    pub pattern: String,
+    pub dirs: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WalkSummary {
+    pub file_count: u64,
+    pub dir_count: u64,
+    pub total_bytes: u64,
+    /// Largest first; sizes cover everything below each directory.
+    pub largest_dirs: Vec<DirSize>,
+    pub file_types: BTreeMap<&'static str, TypeCount>,
+    pub exclusions: Vec<ExclusionHit>,
+    /// Entries hidden by patterns other than the defaults, such as the
+    /// folder's `.gitignore`.
+    pub other_ignored: u64,
+    pub estimate_incomplete: bool,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderPreview {
+    /// As `add_allowed_folder` would store it.
+    pub path: String,
+    pub symlink: Option<SymlinkInfo>,
+    pub already_allowed: bool,
+    /// App-owned dirs inside the folder, which it would not grant.
+    pub excluded_app_dirs: Vec<String>,
+    /// See `folder_location`.
+    pub location_kind: LocationKind,
+    #[serde(flatten)]
+    pub summary: WalkSummary,
+}
+
+struct Builtin {
+    pattern: &'static str,
+    matcher: Gitignore,
+    dirs: u64,
+}
+
+fn builtins(root: &Path, folder: &AllowedFolder) -> Vec<Builtin> {
+    DEFAULT_IGNORE_PATTERNS
+        .iter()
+        .filter(|pattern| folder.ignore_patterns.iter().any(|p| p == *pattern))
+        .filter_map(|pattern| {
+            let mut builder = GitignoreBuilder::new(root);
+            builder.add_line(None, pattern).ok()?;
+            Some(Builtin {
+                pattern,
+                matcher: builder.build().ok()?,
+                dirs: 0,
+            })
+        })
+        .collect()
+}
+
+fn file_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "rs" | "py" | "js" | "mjs" | "ts" | "tsx" | "jsx" | "go" | "java" | "kt" | "c" | "h"
+        | "cc" | "cpp" | "hpp" | "cs" | "rb" | "php" | "swift" | "sh" | "html" | "css" | "vue" => {
+            "code"
+        }
+        "md" | "txt" | "pdf" | "doc" | "docx" | "odt" | "rtf" | "xls" | "xlsx" | "ppt" | "pptx"
+        | "epub" => "documents",
+        "json" | "csv" | "tsv" | "yaml" | "yml" | "toml" | "xml" | "sqlite" | "db" | "parquet" => {
+            "data"
+        }
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp" | "tif" | "tiff" | "heic" => {
+            "images"
+        }
+        "mp3" | "wav" | "flac" | "ogg" | "m4a" | "aac" => "audio",
+        "mp4" | "mov" | "mkv" | "avi" | "webm" => "video",
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" | "dmg" | "iso" => "archives",
+        _ => "other",
+    }
+}
+
+/// Walks `root` within `budget`, skipping what `ignore` hides. Cancelled
+/// only through `token`; running out of budget is not an error.
+pub fn walk(
+    root: &Path,
+    folder: &AllowedFolder,
+    ignore: &FolderIgnore,
+    budget: Budget,
+    token: Option<&TaskToken>,
+) -> Result<WalkSummary, CommandError> {
+    let started = Instant::now();
+    let mut builtins = builtins(root, folder);
+    let mut summary = WalkSummary::default();
+    let mut top: HashMap<OsString, DirSize> = HashMap::new();
+    // Each pending directory with the top-level directory it is under.
+    let mut pending: Vec<(PathBuf, Option<OsString>)> = vec![(root.to_path_buf(), None)];
+    let mut seen = 0u64;
+    'walk: while let Some((dir, under)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            seen += 1;
+            if seen.is_multiple_of(CHECK_EVERY) {
+                if let Some(token) = token {
+                    token.check("folder preview")?;
+                    token.report(seen, None);
+                }
+            }
+            if seen > budget.entries || started.elapsed() >= budget.time {
+                summary.estimate_incomplete = true;
+                break 'walk;
+            }
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let is_dir = metadata.is_dir();
+            if ignore.is_ignored(&path, is_dir) {
+                let builtin = builtins
+                    .iter_mut()
+                    .find(|builtin| builtin.matcher.matched(&path, is_dir).is_ignore());
+                match builtin {
+                    Some(builtin) if is_dir => builtin.dirs += 1,
+                    Some(_) => {}
+                    None if excluded_dirs::is_excluded(ignore.excluded(), &path) => {}
+                    None => summary.other_ignored += 1,
+                }
+                continue;
+            }
+            let under = under.clone().or_else(|| is_dir.then(|| entry.file_name()));
+            if is_dir {
+                summary.dir_count += 1;
+                pending.push((path, under));
+                continue;
+            }
+            let bytes = if metadata.is_file() {
+                metadata.len()
+            } else {
+                0
+            };
+            summary.file_count += 1;
+            summary.total_bytes += bytes;
+            let bucket = summary.file_types.entry(file_type(&path)).or_default();
+            bucket.files += 1;
+            bucket.bytes += bytes;
+            if let Some(name) = under {
+                let dir = top.entry(name.clone()).or_insert_with(|| DirSize {
+                    name: name.to_string_lossy().into_owned(),
+                    bytes: 0,
+                    files: 0,
+                });
+                dir.bytes += bytes;
+                dir.files += 1;
+            }
+        }
+    }
+    let mut largest: Vec<DirSize> = top.into_values().collect();
+    largest.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    largest.truncate(TOP_DIRS);
+    summary.largest_dirs = largest;
+    summary.exclusions = builtins
+        .into_iter()
+        .filter(|builtin| builtin.dirs > 0)
+        .map(|builtin| ExclusionHit {
+            pattern: builtin.pattern.to_string(),
+            dirs: builtin.dirs,
+        })
+        .collect();
+    summary.elapsed_ms = started.elapsed().as_millis() as u64;
+    Ok(summary)
+}
+
+/// The folder `path` would become, with the rules it would get.
+fn candidate(
+    data_dir: &Path,
+    respect_gitignore: bool,
+    stored: &str,
+    follow_symlinks: bool,
+) -> (AllowedFolder, FolderIgnore, Vec<PathBuf>) {
+    let mut folder = AllowedFolder::new(stored.to_string());
+    folder.follow_symlinks = follow_symlinks;
+    let excluded = excluded_dirs::within_folder(&excluded_dirs::excluded_dirs(data_dir), &folder);
+    let ignore = FolderIgnore::new(&folder, respect_gitignore).excluding(excluded.clone());
+    (folder, ignore, excluded)
+}
+
+/// A warning for `add_allowed_folder` when `stored` is too large to look
+/// through quickly.
+pub fn size_warning(
+    data_dir: &Path,
+    respect_gitignore: bool,
+    stored: &str,
+    follow_symlinks: bool,
+) -> Option<String> {
+    let (folder, ignore, _) = candidate(data_dir, respect_gitignore, stored, follow_symlinks);
+    let summary = walk(
+        &folder.effective_path(),
+        &folder,
+        &ignore,
+        ADD_CHECK_BUDGET,
+        None,
+    )
+    .ok()?;
+    summary.estimate_incomplete.then(|| {
+        format!(
+            "{stored} holds more than {} files and folders; indexing it may take a while",
+            ADD_CHECK_BUDGET.entries
+        )
+    })
+}
+
+#[tauri::command]
+pub fn preview_folder_addition(
+    state: State<'_, AppState>,
+    path: String,
+    follow_symlinks: Option<bool>,
+    preview_id: Option<String>,
+    allow_root: Option<bool>,
+) -> Result<FolderPreview, CommandError> {
+    let follow_symlinks = follow_symlinks.unwrap_or(true);
+    let (data_dir, config) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        let config = read_local_config(&runtime.data_dir)?;
+        (runtime.data_dir.clone(), config)
+    };
+    let (stored, symlink) =
+        folders::resolve_folder_input(&path, follow_symlinks, allow_root.unwrap_or(false))?;
+    let (folder, ignore, excluded) = candidate(
+        &data_dir,
+        config.respect_gitignore,
+        &stored,
+        follow_symlinks,
+    );
+    let task = state.tasks.register(
+        TaskKind::Preview,
+        format!("Previewing {stored}"),
+        preview_id,
+    )?;
+    let summary = walk(
+        &folder.effective_path(),
+        &folder,
+        &ignore,
+        PREVIEW_BUDGET,
+        Some(task.token()),
+    )?;
+    Ok(FolderPreview {
+        already_allowed: folders::find_folder(&config, &stored).is_some(),
+        path: stored,
+        symlink,
+        excluded_app_dirs: excluded
+            .iter()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .collect(),
+        location_kind: folder_location::kind(&folder.effective_path()),
+        summary,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn temp_dir() -> TempDir {
+        let dir = std::env::temp_dir().join(format!("liteclaw-preview-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir.canonicalize().unwrap())
+    }
+
+    fn write(path: &Path, bytes: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![b'x'; bytes]).unwrap();
+    }
+
+    fn preview(root: &Path, budget: Budget) -> WalkSummary {
+        let folder = AllowedFolder::new(root.to_string_lossy().into_owned());
+        let ignore = FolderIgnore::new(&folder, false);
+        walk(root, &folder, &ignore, budget, None).unwrap()
+    }
+
+    #[test]
+    fn default_exclusions_are_reported_and_not_counted() {
+        let dir = temp_dir();
+        write(&dir.0.join("src/main.rs"), 10);
+        write(&dir.0.join("src/lib.rs"), 20);
+        write(&dir.0.join("docs/guide.md"), 300);
+        write(&dir.0.join("photo.png"), 5);
+        write(&dir.0.join("node_modules/left-pad/index.js"), 1000);
+        write(&dir.0.join("web/node_modules/react/index.js"), 1000);
+
+        let summary = preview(&dir.0, PREVIEW_BUDGET);
+        assert!(!summary.estimate_incomplete);
+        assert_eq!(summary.file_count, 4);
+        assert_eq!(summary.total_bytes, 335);
+        let largest: Vec<(&str, u64)> = summary
+            .largest_dirs
+            .iter()
+            .map(|dir| (dir.name.as_str(), dir.bytes))
+            .collect();
+        assert_eq!(largest, [("docs", 300), ("src", 30)]);
+        assert_eq!(summary.file_types["code"].files, 2);
+        assert_eq!(summary.file_types["images"].bytes, 5);
+        assert_eq!(
+            summary.exclusions,
+            [ExclusionHit {
+                pattern: "node_modules/".to_string(),
+                dirs: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn running_out_of_budget_marks_the_estimate_incomplete() {
+        let dir = temp_dir();
+        for index in 0..20 {
+            write(&dir.0.join(format!("file-{index}.txt")), 1);
+        }
+        let summary = preview(
+            &dir.0,
+            Budget {
+                time: PREVIEW_BUDGET.time,
+                entries: 5,
+            },
+        );
+        assert!(summary.estimate_incomplete);
+        assert_eq!(summary.file_count, 5);
+    }
+}
@@ -0,0 +1,1051 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::config_diff::{ConfigChange, ConfigDiff};
+use crate::error::{CommandError, ErrorCode};
+use crate::folder_fingerprint::{self, FolderFingerprint};
+use crate::folder_location::{self, LocationKind};
+use crate::macos_privacy::{self, PrivacyStatus};
+use crate::temporary_folders::TemporaryGrant;
+use crate::{
+    audit, backend_reload_config, commit_config, normalize_folder, read_local_config, telemetry,
+    unix_now, AppState, LocalConfig,
+};
+use crate::{excluded_dirs, ignore_rules, paths, safe_write, temporary_folders, ui_state};
+
+const MAX_ALIAS_CHARS: usize = 64;
+const MAX_NOTE_CHARS: usize = 500;
+const MAX_REMOVED_ENTRIES: usize = 10;
+const REMOVED_RETENTION_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AllowedFolder {
+    pub path: String,
+    #[serde(default)]
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// When false, `path` is the symlink itself and is re-resolved on use.
+    #[serde(default = "default_follow_symlinks")]
+    pub follow_symlinks: bool,
+    #[serde(default)]
+    pub mode: FolderMode,
+    /// Gitignore-syntax patterns hiding paths inside this folder; see
+    /// `ignore_rules`.
+    #[serde(default = "ignore_rules::default_patterns")]
+    pub ignore_patterns: Vec<String>,
+    /// What the folder looked like, for finding it after a rename; see
+    /// `folder_fingerprint`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<FolderFingerprint>,
+    /// Filled in by `get_local_config` for grants from
+    /// `add_temporary_folder`; the expiry itself lives in `temporary_folders`.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub temporary: Option<TemporaryGrant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderMode {
+    #[default]
+    ReadOnly,
+    ReadWrite,
+}
+
+/// How `allowed_folders` is ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderSortOrder {
+    /// By `natural_cmp`, re-sorted whenever a folder is added.
+    #[default]
+    Natural,
+    /// Insertion order; new folders append and `move_allowed_folder` reorders.
+    Added,
+}
+
+/// Filesystems where `Foo` and `foo` are the same folder.
+const CASE_INSENSITIVE_FS: bool = cfg!(any(windows, target_os = "macos"));
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// Case-insensitive comparison with digit runs compared as numbers, so
+/// `alpha` sorts before `Zeta` and `project2` before `project10`.
+fn natural_key_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_digits(&mut a), take_digits(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()))
+            }
+            (Some(x), Some(y)) => {
+                a.next();
+                b.next();
+                x.to_lowercase().cmp(y.to_lowercase())
+            }
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// The order folders are shown and stored in. Paths that tie under
+/// `natural_key_cmp` are only the same folder on a case-insensitive
+/// filesystem; elsewhere the raw strings break the tie. `find_folder` uses
+/// the same comparison, so sorting and duplicate detection always agree.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    natural_key_cmp(a, b).then_with(|| {
+        if CASE_INSENSITIVE_FS {
+            Ordering::Equal
+        } else {
+            a.cmp(b)
+        }
+    })
+}
+
+/// Applies `config.sort_order`; a no-op for insertion order.
+pub fn sort_folders(config: &mut LocalConfig) {
+    if config.sort_order == FolderSortOrder::Natural {
+        config
+            .allowed_folders
+            .sort_by(|a, b| natural_cmp(&a.path, &b.path));
+    }
+}
+
+fn default_follow_symlinks() -> bool {
+    true
+}
+
+impl AllowedFolder {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            alias: None,
+            note: None,
+            follow_symlinks: true,
+            mode: FolderMode::ReadOnly,
+            ignore_patterns: ignore_rules::default_patterns(),
+            fingerprint: None,
+            temporary: None,
+        }
+    }
+
+    /// `new` with a fingerprint of the folder as it is now.
+    pub fn fingerprinted(path: String) -> Self {
+        Self {
+            fingerprint: folder_fingerprint::take(Path::new(&path)),
+            ..Self::new(path)
+        }
+    }
+
+    /// The directory this entry currently grants, following a kept symlink to
+    /// wherever it points right now.
+    pub fn effective_path(&self) -> PathBuf {
+        let path = PathBuf::from(&self.path);
+        if self.follow_symlinks {
+            return path;
+        }
+        path.canonicalize().unwrap_or(path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkBehavior {
+    Followed,
+    KeptLink,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SymlinkInfo {
+    pub link_path: String,
+    pub target: String,
+    pub behavior: SymlinkBehavior,
+}
+
+/// Absolute form of `path` with every component but the last resolved, so a
+/// symlink in the final position stays a symlink.
+fn absolute_link_path(path: &Path) -> Result<PathBuf, String> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map_err(|e| format!("failed reading current dir: {e}"))?
+            .join(path)
+    };
+    match (absolute.parent(), absolute.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .map_err(|e| format!("failed to canonicalize {}: {e}", parent.display())),
+        _ => Ok(absolute),
+    }
+}
+
+fn is_symlink_loop(err: &std::io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        err.raw_os_error() == Some(libc::ELOOP)
+    }
+    #[cfg(windows)]
+    {
+        // ERROR_CANT_RESOLVE_FILENAME
+        err.raw_os_error() == Some(1921)
+    }
+}
+
+/// Validates user input for a new allowed folder and returns the path to
+/// store plus, for symlinked input, which policy was applied. Places
+/// `folder_location` refuses are refused here; roots only with
+/// `allow_root`.
+pub fn resolve_folder_input(
+    path: &str,
+    follow_symlinks: bool,
+    allow_root: bool,
+) -> Result<(String, Option<SymlinkInfo>), CommandError> {
+    let raw = Path::new(path);
+    let is_link = fs::symlink_metadata(raw)
+        .map(|meta| meta.file_type().is_symlink())
+        .unwrap_or(false);
+    if !is_link {
+        if !paths::for_io(raw).is_dir() {
+            return Err(folder_location::not_a_folder(path));
+        }
+        let stored = normalize_folder(path).map_err(CommandError::invalid_input)?;
+        folder_location::check(Path::new(&stored), allow_root)?;
+        return Ok((stored, None));
+    }
+
+    let target = match raw.canonicalize() {
+        Ok(target) => target,
+        Err(err) if is_symlink_loop(&err) => {
+            return Err(CommandError::new(
+                ErrorCode::SymlinkCycle,
+                format!("symlink cycle detected at {path}"),
+            ))
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(CommandError::new(
+                ErrorCode::DanglingSymlink,
+                format!("symlink target does not exist: {path}"),
+            ))
+        }
+        Err(err) => return Err(format!("failed to resolve symlink {path}: {err}").into()),
+    };
+    if !target.is_dir() {
+        return Err(CommandError::new(
+            ErrorCode::NotADirectory,
+            format!("symlink does not point to a folder: {path}"),
+        ));
+    }
+    folder_location::check(&target, allow_root)?;
+    let target = target.to_string_lossy().to_string();
+    let link_path = absolute_link_path(raw)?.to_string_lossy().to_string();
+    let (stored, behavior) = if follow_symlinks {
+        (target.clone(), SymlinkBehavior::Followed)
+    } else {
+        (link_path.clone(), SymlinkBehavior::KeptLink)
+    };
+    Ok((
+        stored,
+        Some(SymlinkInfo {
+            link_path,
+            target,
+            behavior,
+        }),
+    ))
+}
+
+/// Finds the entry a user-supplied path refers to, whether the entry stores
+/// the canonical target or a kept symlink.
+pub fn find_folder_by_input(config: &LocalConfig, input: &str) -> Option<usize> {
+    let mut keys = vec![input.to_string()];
+    if let Ok(link) = absolute_link_path(Path::new(input)) {
+        keys.push(link.to_string_lossy().to_string());
+    }
+    if let Ok(normalized) = normalize_folder(input) {
+        keys.push(normalized);
+    }
+    keys.iter().find_map(|key| find_folder(config, key))
+}
+
+/// Accepts both the current object entries and the plain path strings older
+/// configs were written with.
+pub fn deserialize_allowed_folders<'de, D>(deserializer: D) -> Result<Vec<AllowedFolder>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        Path(String),
+        Folder(AllowedFolder),
+    }
+
+    let entries = Vec::<Entry>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            Entry::Path(path) => AllowedFolder::new(path),
+            Entry::Folder(folder) => folder,
+        })
+        .collect())
+}
+
+pub fn find_folder(config: &LocalConfig, path: &str) -> Option<usize> {
+    config
+        .allowed_folders
+        .iter()
+        .position(|entry| natural_cmp(&entry.path, path).is_eq())
+}
+
+/// Whether `path` is granted by an allowed folder; the app's own dirs never
+/// are, see `excluded_dirs`.
+pub fn is_within_allowed(data_dir: &Path, config: &LocalConfig, path: &Path) -> bool {
+    if excluded_dirs::is_excluded(&excluded_dirs::excluded_dirs(data_dir), path) {
+        return false;
+    }
+    config
+        .allowed_folders
+        .iter()
+        .any(|folder| path.starts_with(&folder.path) || path.starts_with(folder.effective_path()))
+}
+
+fn clean_text(
+    value: Option<String>,
+    max_chars: usize,
+    field: &str,
+) -> Result<Option<String>, CommandError> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    if trimmed.chars().count() > max_chars {
+        return Err(CommandError::invalid_input(format!(
+            "{field} must be at most {max_chars} characters"
+        )));
+    }
+    Ok(Some(trimmed.to_string()))
+}
+
+pub fn clean_alias(alias: Option<String>) -> Result<Option<String>, CommandError> {
+    clean_text(alias, MAX_ALIAS_CHARS, "alias")
+}
+
+pub fn clean_note(note: Option<String>) -> Result<Option<String>, CommandError> {
+    clean_text(note, MAX_NOTE_CHARS, "note")
+}
+
+/// Fails when a folder other than the one at `index` already has `alias`,
+/// ignoring case.
+pub fn ensure_alias_free(
+    config: &LocalConfig,
+    alias: &str,
+    index: usize,
+) -> Result<(), CommandError> {
+    let wanted = alias.to_lowercase();
+    let taken = config.allowed_folders.iter().enumerate().any(|(i, entry)| {
+        i != index
+            && entry
+                .alias
+                .as_ref()
+                .is_some_and(|existing| existing.to_lowercase() == wanted)
+    });
+    if taken {
+        return Err(CommandError::conflict(format!(
+            "alias \"{alias}\" is already used by another folder"
+        )));
+    }
+    Ok(())
+}
+
+pub fn update_folder<F>(
+    state: &State<'_, AppState>,
+    path: String,
+    apply: F,
+) -> Result<ConfigChange<LocalConfig>, CommandError>
+where
+    F: FnOnce(&mut LocalConfig, usize) -> Result<(), CommandError>,
+{
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    let index = find_folder_by_input(&config, &path)
+        .ok_or_else(|| CommandError::not_found(format!("not an allowed folder: {path}")))?;
+    apply(&mut config, index)?;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[tauri::command]
+pub fn set_folder_alias(
+    state: State<'_, AppState>,
+    path: String,
+    alias: Option<String>,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let alias = clean_alias(alias)?;
+    update_folder(&state, path, |config, index| {
+        if let Some(alias) = &alias {
+            ensure_alias_free(config, alias, index)?;
+        }
+        config.allowed_folders[index].alias = alias;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn set_folder_note(
+    state: State<'_, AppState>,
+    path: String,
+    note: Option<String>,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let note = clean_note(note)?;
+    update_folder(&state, path, |config, index| {
+        config.allowed_folders[index].note = note;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn set_folder_mode(
+    state: State<'_, AppState>,
+    path: String,
+    mode: FolderMode,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime_data_dir = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?
+        .data_dir
+        .clone();
+    let change = update_folder(&state, path.clone(), |config, index| {
+        config.allowed_folders[index].mode = mode;
+        Ok(())
+    })?;
+    let _ = audit::record(
+        &runtime_data_dir,
+        "folder_mode_changed",
+        serde_json::json!({ "path": path, "mode": mode }),
+    );
+    Ok(change)
+}
+
+#[tauri::command]
+pub fn set_folder_sort_order(
+    state: State<'_, AppState>,
+    order: FolderSortOrder,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    // Switching to insertion order keeps the current arrangement.
+    config.sort_order = order;
+    sort_folders(&mut config);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[tauri::command]
+pub fn move_allowed_folder(
+    state: State<'_, AppState>,
+    from_index: usize,
+    to_index: usize,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    if config.sort_order != FolderSortOrder::Added {
+        return Err(CommandError::conflict(
+            "folders are sorted automatically; set sort_order to `added` to reorder them",
+        ));
+    }
+    let len = config.allowed_folders.len();
+    if from_index >= len || to_index >= len {
+        return Err(CommandError::invalid_input(format!(
+            "folder index out of range: there are {len} allowed folders"
+        )));
+    }
+    let folder = config.allowed_folders.remove(from_index);
+    config.allowed_folders.insert(to_index, folder);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemovedFolder {
+    pub folder: AllowedFolder,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub removed_at: u64,
+    pub undo_token: String,
+}
+
+fn removed_folders_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("removed_folders.json")
+}
+
+fn load_removed_folders(data_dir: &Path) -> Vec<RemovedFolder> {
+    let Ok(content) = fs::read_to_string(removed_folders_path(data_dir)) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<RemovedFolder> = serde_json::from_str(&content).unwrap_or_default();
+    let cutoff = unix_now().saturating_sub(REMOVED_RETENTION_SECS);
+    entries.retain(|entry| entry.removed_at >= cutoff);
+    entries
+}
+
+fn save_removed_folders(data_dir: &Path, entries: &[RemovedFolder]) -> Result<(), String> {
+    let bytes = serde_json::to_vec_pretty(entries)
+        .map_err(|e| format!("failed serializing removed folders: {e}"))?;
+    safe_write::replace(&removed_folders_path(data_dir), &bytes)
+        .map_err(|e| format!("failed writing removed folders: {e}"))
+}
+
+/// Remembers a removed folder with all of its metadata and returns the token
+/// the UI can hand back to `restore_removed_folder`.
+pub fn record_removal(data_dir: &Path, folder: AllowedFolder) -> Result<String, String> {
+    let mut entries = load_removed_folders(data_dir);
+    entries.retain(|entry| entry.folder.path != folder.path);
+    let undo_token = Uuid::new_v4().to_string();
+    entries.insert(
+        0,
+        RemovedFolder {
+            folder,
+            removed_at: unix_now(),
+            undo_token: undo_token.clone(),
+        },
+    );
+    entries.truncate(MAX_REMOVED_ENTRIES);
+    save_removed_folders(data_dir, &entries)?;
+    Ok(undo_token)
+}
+
+#[tauri::command]
+pub fn list_recently_removed_folders(
+    state: State<'_, AppState>,
+) -> Result<Vec<RemovedFolder>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(load_removed_folders(&runtime.data_dir))
+}
+
+/// Puts a recently removed folder back with its metadata. The folder is
+/// validated as `add_allowed_folder` would, since it may have moved or been
+/// replaced meanwhile, and its alias must still be free.
+#[tauri::command]
+pub fn restore_removed_folder(
+    state: State<'_, AppState>,
+    path: Option<String>,
+    undo_token: Option<String>,
+    allow_root: Option<bool>,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut entries = load_removed_folders(&runtime.data_dir);
+    let position = match (&undo_token, &path) {
+        (Some(token), _) => entries.iter().position(|entry| &entry.undo_token == token),
+        (None, Some(path)) => entries.iter().position(|entry| &entry.folder.path == path),
+        (None, None) => {
+            return Err(CommandError::invalid_input(
+                "either path or undo_token is required",
+            ))
+        }
+    };
+    let index =
+        position.ok_or_else(|| CommandError::not_found("no recently removed folder matches"))?;
+    let mut folder = entries[index].folder.clone();
+    folder.path = resolve_folder_input(
+        &folder.path,
+        folder.follow_symlinks,
+        allow_root.unwrap_or(false),
+    )?
+    .0;
+
+    let mut config = read_local_config(&runtime.data_dir)?;
+    if find_folder(&config, &folder.path).is_some() {
+        return Err(CommandError::conflict(format!(
+            "folder is already allowed: {}",
+            folder.path
+        )));
+    }
+    if let Some(alias) = &folder.alias {
+        ensure_alias_free(&config, alias, config.allowed_folders.len())?;
+    }
+    config.allowed_folders.push(folder);
+    sort_folders(&mut config);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    entries.remove(index);
+    save_removed_folders(&runtime.data_dir, &entries)?;
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderChangeKind {
+    Add,
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderChangeStatus {
+    Added,
+    Removed,
+    /// Already in the requested state, or left alone because an atomic
+    /// batch was not applied.
+    Unchanged,
+    /// Failed validation; `error` says why.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderChangeOutcome {
+    /// The path as given.
+    pub path: String,
+    pub change: FolderChangeKind,
+    pub status: FolderChangeStatus,
+    pub symlink: Option<SymlinkInfo>,
+    pub undo_token: Option<String>,
+    pub error: Option<CommandError>,
+    /// Bookkeeping that failed after the change was written, such as
+    /// recording the removal for undo; the change itself stands.
+    pub warnings: Vec<String>,
+    /// For a removal, conversations whose working folder this was.
+    pub unbound_conversations: Vec<String>,
+}
+
+impl FolderChangeOutcome {
+    fn new(path: &str, change: FolderChangeKind, status: FolderChangeStatus) -> Self {
+        Self {
+            path: path.to_string(),
+            change,
+            status,
+            symlink: None,
+            undo_token: None,
+            error: None,
+            warnings: Vec::new(),
+            unbound_conversations: Vec::new(),
+        }
+    }
+
+    fn skip(&mut self, error: CommandError) {
+        self.status = FolderChangeStatus::Skipped;
+        self.error = Some(error);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderBatch {
+    pub config: LocalConfig,
+    /// False when an atomic batch had a failing path and nothing was written.
+    pub applied: bool,
+    /// In input order: removals first, then additions.
+    pub outcomes: Vec<FolderChangeOutcome>,
+}
+
+fn contains(parent: &str, child: &str) -> bool {
+    Path::new(child).starts_with(parent)
+}
+
+/// Fails when `path` sits inside one of `allowed` other than itself.
+pub fn ensure_not_nested<'a>(
+    allowed: impl IntoIterator<Item = &'a String>,
+    path: &str,
+) -> Result<(), CommandError> {
+    let parent = allowed
+        .into_iter()
+        .find(|parent| !natural_cmp(parent, path).is_eq() && contains(parent, path));
+    match parent {
+        Some(parent) => Err(CommandError::invalid_input(format!(
+            "already inside allowed folder {parent}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Adds and removes allowed folders with one config write and one backend
+/// reload. Every path is validated before anything changes: inputs that do
+/// not resolve, repeat, sit inside a folder that stays allowed, or would
+/// keep a folder being removed reachable are skipped and reported, or with
+/// `atomic` leave the whole batch unapplied.
+#[tauri::command]
+pub fn apply_folder_changes(
+    state: State<'_, AppState>,
+    add: Vec<String>,
+    remove: Vec<String>,
+    atomic: Option<bool>,
+) -> Result<ConfigChange<FolderBatch>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    let mut outcomes = Vec::new();
+
+    // (outcome index, folder index) for each removal that validated.
+    let mut removals: Vec<(usize, usize)> = Vec::new();
+    for input in &remove {
+        let mut outcome =
+            FolderChangeOutcome::new(input, FolderChangeKind::Remove, FolderChangeStatus::Removed);
+        match find_folder_by_input(&config, input) {
+            None => outcome.skip(CommandError::not_found(format!(
+                "not an allowed folder: {input}"
+            ))),
+            Some(index) if removals.iter().any(|(_, seen)| *seen == index) => outcome.skip(
+                CommandError::invalid_input(format!("listed more than once: {input}")),
+            ),
+            Some(index) => removals.push((outcomes.len(), index)),
+        }
+        outcomes.push(outcome);
+    }
+    let removed_paths: Vec<String> = removals
+        .iter()
+        .map(|(_, index)| config.allowed_folders[*index].path.clone())
+        .collect();
+
+    // (outcome index, stored path) for each new folder that resolved.
+    let mut additions: Vec<(usize, String)> = Vec::new();
+    let mut conflicted: Vec<usize> = Vec::new();
+    for input in &add {
+        let mut outcome =
+            FolderChangeOutcome::new(input, FolderChangeKind::Add, FolderChangeStatus::Added);
+        match resolve_folder_input(input, true, false) {
+            Err(err) => outcome.skip(err),
+            Ok((stored, symlink)) => {
+                outcome.symlink = symlink;
+                if let Some(position) = removed_paths
+                    .iter()
+                    .position(|path| natural_cmp(path, &stored).is_eq())
+                {
+                    outcome.skip(CommandError::conflict(format!(
+                        "both added and removed: {input}"
+                    )));
+                    conflicted.push(position);
+                } else if additions
+                    .iter()
+                    .any(|(_, path)| natural_cmp(path, &stored).is_eq())
+                {
+                    outcome.skip(CommandError::invalid_input(format!(
+                        "listed more than once: {input}"
+                    )));
+                } else if find_folder(&config, &stored).is_some() {
+                    outcome.status = FolderChangeStatus::Unchanged;
+                } else {
+                    additions.push((outcomes.len(), stored));
+                }
+            }
+        }
+        outcomes.push(outcome);
+    }
+    for position in &conflicted {
+        let (outcome, _) = removals[*position];
+        let path = &outcomes[outcome].path;
+        let error = CommandError::conflict(format!("both added and removed: {path}"));
+        outcomes[outcome].skip(error);
+    }
+    let mut removals: Vec<(usize, usize)> = removals
+        .into_iter()
+        .enumerate()
+        .filter(|(position, _)| !conflicted.contains(position))
+        .map(|(_, removal)| removal)
+        .collect();
+    let removed_paths: Vec<String> = removals
+        .iter()
+        .map(|(_, index)| config.allowed_folders[*index].path.clone())
+        .collect();
+
+    // Nesting is judged against the folders the batch would leave allowed.
+    let removing: Vec<usize> = removals.iter().map(|(_, index)| *index).collect();
+    let kept: Vec<String> = config
+        .allowed_folders
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !removing.contains(index))
+        .map(|(_, folder)| folder.path.clone())
+        .collect();
+    let candidates: Vec<String> = additions.iter().map(|(_, path)| path.clone()).collect();
+    additions.retain(|(outcome, stored)| {
+        if let Err(err) = ensure_not_nested(kept.iter().chain(&candidates), stored) {
+            outcomes[*outcome].skip(err);
+            return false;
+        }
+        if let Some(removed) = removed_paths
+            .iter()
+            .find(|removed| contains(stored, removed))
+        {
+            outcomes[*outcome].skip(CommandError::conflict(format!(
+                "would keep {removed} reachable while it is being removed"
+            )));
+            return false;
+        }
+        true
+    });
+
+    let failed = outcomes
+        .iter()
+        .any(|outcome| outcome.status == FolderChangeStatus::Skipped);
+    if atomic.unwrap_or(false) && failed {
+        for outcome in &mut outcomes {
+            if outcome.status != FolderChangeStatus::Skipped {
+                outcome.status = FolderChangeStatus::Unchanged;
+            }
+        }
+        let batch = FolderBatch {
+            config,
+            applied: false,
+            outcomes,
+        };
+        return Ok(ConfigChange::new(batch, ConfigDiff::default()));
+    }
+    if removals.is_empty() && additions.is_empty() {
+        let batch = FolderBatch {
+            config,
+            applied: true,
+            outcomes,
+        };
+        return Ok(ConfigChange::new(batch, ConfigDiff::default()));
+    }
+
+    removals.sort_by_key(|(_, index)| std::cmp::Reverse(*index));
+    let mut removed = Vec::new();
+    for (outcome, index) in removals {
+        removed.push((outcome, config.allowed_folders.remove(index)));
+    }
+    for (_, stored) in &additions {
+        config
+            .allowed_folders
+            .push(AllowedFolder::fingerprinted(stored.clone()));
+    }
+    sort_folders(&mut config);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    // The config is written; from here a failure is reported on its outcome
+    // and the backend is still reloaded.
+    for (outcome, folder) in removed {
+        let outcome = &mut outcomes[outcome];
+        if let Err(err) = temporary_folders::forget(&runtime.data_dir, &folder.path) {
+            outcome.warnings.push(err);
+        }
+        outcome.unbound_conversations = ui_state::bound_to(&runtime.data_dir, &folder.path);
+        match record_removal(&runtime.data_dir, folder) {
+            Ok(token) => outcome.undo_token = Some(token),
+            Err(err) => outcome.warnings.push(err),
+        }
+    }
+    if !additions.is_empty() {
+        telemetry::track_event(
+            &runtime.data_dir,
+            telemetry::folder_added(true, config.allowed_folders.len()),
+        );
+    }
+    backend_reload_config(&runtime, &config)?;
+    let batch = FolderBatch {
+        config,
+        applied: true,
+        outcomes,
+    };
+    Ok(ConfigChange::new(batch, diff))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderStatus {
+    Ok,
+    Missing,
+    NotADirectory,
+    Unreadable,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderValidation {
+    pub path: String,
+    pub alias: Option<String>,
+    pub status: FolderStatus,
+    pub detail: Option<String>,
+    /// App-owned dirs inside the folder that it does not grant.
+    pub excluded: Vec<String>,
+    /// For a missing folder, a directory beside it that looks like the same
+    /// folder renamed; `relocate_allowed_folder` moves the entry there.
+    pub possible_new_location: Option<String>,
+    /// Where a folder that is in place lives; see `folder_location`.
+    pub location_kind: Option<LocationKind>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderValidationReport {
+    pub folders: Vec<FolderValidation>,
+    pub privacy: PrivacyStatus,
+}
+
+fn validate_folder(
+    config: &LocalConfig,
+    folder: &AllowedFolder,
+    excluded: &[PathBuf],
+) -> FolderValidation {
+    let path = Path::new(&folder.path);
+    let (status, detail) = if !path.exists() {
+        (FolderStatus::Missing, None)
+    } else if !path.is_dir() {
+        (FolderStatus::NotADirectory, None)
+    } else {
+        match fs::read_dir(path) {
+            Ok(_) => (FolderStatus::Ok, None),
+            Err(err) => (FolderStatus::Unreadable, Some(err.to_string())),
+        }
+    };
+    let possible_new_location = match (&status, &folder.fingerprint) {
+        (FolderStatus::Missing, Some(fingerprint)) => {
+            let allowed =
+                |candidate: &Path| find_folder(config, &candidate.to_string_lossy()).is_some();
+            folder_fingerprint::find_relocation(path, fingerprint, allowed)
+                .map(|found| found.to_string_lossy().into_owned())
+        }
+        _ => None,
+    };
+    let location_kind = (status == FolderStatus::Ok).then(|| folder_location::kind(path));
+    FolderValidation {
+        path: folder.path.clone(),
+        alias: folder.alias.clone(),
+        status,
+        detail,
+        excluded: excluded_dirs::within_folder(excluded, folder)
+            .iter()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .collect(),
+        possible_new_location,
+        location_kind,
+    }
+}
+
+pub fn validate_config_folders(data_dir: &Path, config: &LocalConfig) -> FolderValidationReport {
+    let excluded = excluded_dirs::excluded_dirs(data_dir);
+    FolderValidationReport {
+        folders: config
+            .allowed_folders
+            .iter()
+            .map(|folder| validate_folder(config, folder, &excluded))
+            .collect(),
+        privacy: macos_privacy::probe(config),
+    }
+}
+
+#[tauri::command]
+pub fn validate_allowed_folders(
+    state: State<'_, AppState>,
+) -> Result<FolderValidationReport, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    let report = validate_config_folders(&runtime.data_dir, &config);
+    // Best effort: a read-only data dir keeps the fingerprints it has.
+    if refresh_fingerprints(&mut config, &report)
+        && commit_config(&runtime.data_dir, &config).is_ok()
+    {
+        let _ = backend_reload_config(&runtime, &config);
+    }
+    Ok(report)
+}
+
+/// Re-takes the fingerprint of every folder `report` found in place. True
+/// when any changed.
+fn refresh_fingerprints(config: &mut LocalConfig, report: &FolderValidationReport) -> bool {
+    let mut changed = false;
+    for (folder, validation) in config.allowed_folders.iter_mut().zip(&report.folders) {
+        if validation.status != FolderStatus::Ok {
+            continue;
+        }
+        let fingerprint = folder_fingerprint::take(Path::new(&folder.path));
+        if fingerprint.is_some() && fingerprint != folder.fingerprint {
+            folder.fingerprint = fingerprint;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Points the entry for `old_path` at `new_path`, keeping its alias, note,
+/// mode and ignore patterns, along with its expiry if temporary and the
+/// conversations bound to it.
+#[tauri::command]
+pub fn relocate_allowed_folder(
+    state: State<'_, AppState>,
+    old_path: String,
+    new_path: String,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    let index = find_folder_by_input(&config, &old_path)
+        .ok_or_else(|| CommandError::not_found(format!("not an allowed folder: {old_path}")))?;
+    let follow_symlinks = config.allowed_folders[index].follow_symlinks;
+    let (stored, _) = resolve_folder_input(&new_path, follow_symlinks, false)?;
+    match find_folder(&config, &stored) {
+        Some(existing) if existing == index => {
+            return Ok(ConfigChange::new(config, ConfigDiff::default()))
+        }
+        Some(_) => {
+            return Err(CommandError::conflict(format!(
+                "folder is already allowed: {stored}"
+            )))
+        }
+        None => {}
+    }
+    let others: Vec<String> = config
+        .allowed_folders
+        .iter()
+        .enumerate()
+        .filter(|(other, _)| *other != index)
+        .map(|(_, folder)| folder.path.clone())
+        .collect();
+    ensure_not_nested(&others, &stored)?;
+
+    let old = std::mem::replace(&mut config.allowed_folders[index].path, stored.clone());
+    config.allowed_folders[index].fingerprint = folder_fingerprint::take(Path::new(&stored));
+    sort_folders(&mut config);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    temporary_folders::relocate(&runtime.data_dir, &old, &stored)?;
+    ui_state::rebind(&runtime.data_dir, &old, &stored)?;
+    let _ = audit::record(
+        &runtime.data_dir,
+        "folder_relocated",
+        serde_json::json!({ "old_path": old, "new_path": stored }),
+    );
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
@@ -0,0 +1,452 @@
+//! Periodic health probe of a running backend. Besides liveness, each beat
+//! compares the config generation the backend applied with the one on disk
+//! and re-sends the reload when they stay apart, and checks whether real
+//! requests are timing out while health keeps passing (a hung backend).
+//! A backend that stops answering health for `LOST_AFTER_FAILED_BEATS`
+//! beats in a row is marked not ready, and ready again once it answers;
+//! one that exits is the supervisor's. `check_now` runs the same probe out of band after network changes and
+//! clock jumps (see `system_events`).
+
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::backend_error::{BackendError, BackendErrorKind};
+use crate::backend_http::{self, RequestRecord};
+use crate::backend_status;
+use crate::backend_transport::{BackendTransport, UreqTransport};
+use crate::{
+    action_queue, backend_reload_config, config_generation, desktop_log, read_local_config,
+    shutdown, spawn_backend, spawn_guard, supervisor, AppState, BackendRuntime, LocalConfig,
+};
+
+pub const INTERVAL: Duration = Duration::from_secs(5);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Out-of-band checks retry since routing may still be settling.
+const CHECK_NOW_ATTEMPTS: u32 = 3;
+const CHECK_NOW_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Divergence is tolerated for one beat since a reload may be in flight.
+const OUT_OF_SYNC_BEATS: u32 = 2;
+/// A single refused or slow probe is a blip; this many in a row is not.
+const LOST_AFTER_FAILED_BEATS: u32 = 3;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HealthInfo {
+    #[serde(default)]
+    pub config_generation: Option<u64>,
+    /// Launch to ready inside the backend, including interpreter start.
+    #[serde(default)]
+    pub init_ms: Option<u64>,
+    /// Newest and oldest API versions the backend accepts; see `api_version`.
+    #[serde(default)]
+    pub api_version: Option<u32>,
+    #[serde(default)]
+    pub min_api_version: Option<u32>,
+    /// Requests the backend is handling, health checks aside.
+    #[serde(default)]
+    pub in_flight: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ConfigOutOfSync {
+    config_generation: u64,
+    backend_config_generation: Option<u64>,
+}
+
+pub fn probe_health(
+    transport: &dyn BackendTransport,
+    base_url: &str,
+    token: &str,
+) -> Result<HealthInfo, String> {
+    let response = transport
+        .health(base_url, token, Some(PROBE_TIMEOUT))
+        .map_err(|e| format!("backend health check failed: {e}"))?;
+    let body = response
+        .into_string()
+        .map_err(|e| format!("failed reading health response: {e}"))?;
+    Ok(serde_json::from_str(&body).unwrap_or_default())
+}
+
+/// `probe_health` up to `CHECK_NOW_ATTEMPTS` times, `retry_delay` apart.
+fn probe_until_healthy(
+    transport: &dyn BackendTransport,
+    base_url: &str,
+    token: &str,
+    retry_delay: Duration,
+) -> Result<HealthInfo, String> {
+    let mut result = probe_health(transport, base_url, token);
+    for _ in 1..CHECK_NOW_ATTEMPTS {
+        if result.is_ok() {
+            break;
+        }
+        thread::sleep(retry_delay);
+        result = probe_health(transport, base_url, token);
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncCheck {
+    InSync,
+    /// Apart for fewer than `OUT_OF_SYNC_BEATS` beats; a reload may be in
+    /// flight.
+    Waiting,
+    /// Apart for `OUT_OF_SYNC_BEATS` beats: tell the window and re-send.
+    Diverged,
+    /// Still apart after that: re-send quietly.
+    StillDiverged,
+}
+
+/// Counts the beats on which the backend's applied generation (`applied`)
+/// stayed apart from `current`. Only beats that reached the backend count.
+fn track_sync(runtime: &mut BackendRuntime, applied: Option<u64>, current: u64) -> SyncCheck {
+    runtime.backend_config_generation = applied;
+    if applied == Some(current) {
+        runtime.out_of_sync_beats = 0;
+        return SyncCheck::InSync;
+    }
+    runtime.out_of_sync_beats += 1;
+    match runtime.out_of_sync_beats {
+        beats if beats < OUT_OF_SYNC_BEATS => SyncCheck::Waiting,
+        OUT_OF_SYNC_BEATS => SyncCheck::Diverged,
+        _ => SyncCheck::StillDiverged,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Liveness {
+    Healthy,
+    /// Failed, but fewer than `LOST_AFTER_FAILED_BEATS` times in a row, or
+    /// already reported lost.
+    Failing,
+    /// Just reached `LOST_AFTER_FAILED_BEATS` failures: now not ready.
+    Lost,
+    /// Answered again after being marked not ready.
+    Recovered,
+}
+
+/// Counts beats whose probe failed and flips `backend_ready` when the count
+/// reaches `LOST_AFTER_FAILED_BEATS` or the backend answers again.
+fn track_health(runtime: &mut BackendRuntime, answered: Result<(), &str>) -> Liveness {
+    let Err(err) = answered else {
+        runtime.failed_health_beats = 0;
+        if runtime.backend_ready {
+            return Liveness::Healthy;
+        }
+        runtime.backend_ready = true;
+        runtime.last_error = None;
+        return Liveness::Recovered;
+    };
+    runtime.failed_health_beats += 1;
+    if runtime.failed_health_beats != LOST_AFTER_FAILED_BEATS {
+        return Liveness::Failing;
+    }
+    runtime.backend_ready = false;
+    let detail = format!("{LOST_AFTER_FAILED_BEATS} health checks in a row failed: {err}");
+    runtime.last_error = Some(
+        BackendError::new(BackendErrorKind::HealthTimeout, detail)
+            .with_summary("The backend stopped answering its health check."),
+    );
+    Liveness::Lost
+}
+
+/// Where to probe, or `None` when the backend must be left alone. A backend
+/// marked not ready is still probed so it can be marked ready again.
+fn probe_target(runtime: &BackendRuntime) -> Option<(String, String)> {
+    // Never probe (or later, restart) a backend the user stopped.
+    if runtime.stopped_by_user || runtime.safe_mode || runtime.backend_child.is_none() {
+        return None;
+    }
+    Some((runtime.base_url.clone(), runtime.token.clone()))
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct BackendHung {
+    timed_out: Vec<RequestRecord>,
+    auto_restart: bool,
+}
+
+fn describe_evidence(evidence: &[RequestRecord]) -> String {
+    evidence
+        .iter()
+        .map(|record| {
+            format!(
+                "{} after {}ms at {}",
+                record.endpoint, record.elapsed_ms, record.at
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Marks the backend degraded once enough requests in a row have timed out,
+/// and restarts it if configured to. Returns true when it was restarted.
+fn check_hang(app: &AppHandle, runtime: &mut BackendRuntime, config: &LocalConfig) -> bool {
+    let hang = &config.hang_detection;
+    let Some(evidence) = backend_http::hang_evidence(hang.consecutive_timeouts) else {
+        if runtime.backend_degraded {
+            backend_status::set_status(app, runtime, |runtime| runtime.backend_degraded = false);
+        }
+        return false;
+    };
+    if runtime.backend_degraded {
+        // Already reported; the restart offer stands.
+        return false;
+    }
+    backend_status::set_status(app, runtime, |runtime| runtime.backend_degraded = true);
+    desktop_log::warn(
+        &runtime.data_dir,
+        &format!(
+            "backend degraded: {} requests in a row timed out while health passes: {}",
+            evidence.len(),
+            describe_evidence(&evidence)
+        ),
+    );
+    let _ = app.emit(
+        "backend-hung",
+        BackendHung {
+            timed_out: evidence,
+            auto_restart: hang.auto_restart_on_hang,
+        },
+    );
+    if !hang.auto_restart_on_hang {
+        return false;
+    }
+    desktop_log::warn(
+        &runtime.data_dir,
+        "restarting hung backend (auto_restart_on_hang)",
+    );
+    let Ok(starting) = spawn_guard::begin() else {
+        // A user-initiated start is already waiting for the lock.
+        return true;
+    };
+    if let Err(err) = spawn_backend(runtime, &starting) {
+        desktop_log::warn(
+            &runtime.data_dir,
+            &format!("restart of hung backend failed: {err}"),
+        );
+    }
+    backend_status::publish(app, runtime);
+    true
+}
+
+fn beat(app: &AppHandle) {
+    if shutdown::in_progress() {
+        return;
+    }
+    let state = app.state::<AppState>();
+    let (base_url, token) = {
+        let Ok(mut runtime) = state.runtime.lock() else {
+            return;
+        };
+        if supervisor::check_exit(app, &mut runtime) {
+            return;
+        }
+        let Some(target) = probe_target(&runtime) else {
+            return;
+        };
+        target
+    };
+    let result = probe_health(&UreqTransport, &base_url, &token);
+    let Ok(mut runtime) = state.runtime.lock() else {
+        return;
+    };
+    if runtime.base_url != base_url {
+        // The backend was restarted while we were probing.
+        return;
+    }
+    match track_health(
+        &mut runtime,
+        result.as_ref().map(|_| ()).map_err(String::as_str),
+    ) {
+        Liveness::Healthy => {}
+        Liveness::Failing => return,
+        Liveness::Lost => {
+            let message = format!(
+                "backend marked not ready: {LOST_AFTER_FAILED_BEATS} health checks in a row failed"
+            );
+            desktop_log::warn(&runtime.data_dir, &message);
+            backend_status::publish(app, &runtime);
+            return;
+        }
+        Liveness::Recovered => {
+            desktop_log::info(&runtime.data_dir, "backend answers its health check again");
+            backend_status::publish(app, &runtime);
+        }
+    }
+    let Ok(info) = result else {
+        return;
+    };
+    action_queue::flush(app, &runtime);
+    let Ok(config) = read_local_config(&runtime.data_dir) else {
+        return;
+    };
+    if check_hang(app, &mut runtime, &config) {
+        return;
+    }
+    match track_sync(&mut runtime, info.config_generation, config_generation()) {
+        SyncCheck::InSync | SyncCheck::Waiting => return,
+        SyncCheck::Diverged => {
+            let _ = app.emit(
+                "config-out-of-sync",
+                ConfigOutOfSync {
+                    config_generation: config_generation(),
+                    backend_config_generation: runtime.backend_config_generation,
+                },
+            );
+        }
+        SyncCheck::StillDiverged => {}
+    }
+    let _ = backend_reload_config(&runtime, &config);
+}
+
+/// Probes immediately, marks the backend ready again if it answers and emits
+/// the lifecycle event so the UI can drop stale errors. Timeouts recorded
+/// before the trigger are discarded; they say nothing about the new network.
+pub fn check_now(app: &AppHandle, reason: &str) {
+    let state = app.state::<AppState>();
+    let (base_url, token) = {
+        let Ok(runtime) = state.runtime.lock() else {
+            return;
+        };
+        let Some(target) = probe_target(&runtime) else {
+            return;
+        };
+        target
+    };
+    let result = probe_until_healthy(&UreqTransport, &base_url, &token, CHECK_NOW_RETRY_DELAY);
+    let Ok(mut runtime) = state.runtime.lock() else {
+        return;
+    };
+    if runtime.base_url != base_url {
+        return;
+    }
+    match result {
+        Ok(info) => {
+            backend_http::reset();
+            runtime.backend_ready = true;
+            runtime.failed_health_beats = 0;
+            runtime.backend_degraded = false;
+            runtime.last_error = None;
+            runtime.backend_config_generation = info.config_generation;
+            action_queue::flush(app, &runtime);
+            desktop_log::info(
+                &runtime.data_dir,
+                &format!("backend healthy after {reason}"),
+            );
+        }
+        Err(err) => {
+            desktop_log::warn(&runtime.data_dir, &format!("after {reason}: {err}"));
+        }
+    }
+    backend_status::publish(app, &runtime);
+}
+
+pub fn start(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(INTERVAL);
+        beat(&app);
+        backend_status::publish_current(&app);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend_transport::fake::{FakeTransport, Reply};
+    use crate::backend_transport::HEALTH;
+    use std::path::PathBuf;
+
+    const BASE_URL: &str = "http://127.0.0.1:4100";
+
+    #[test]
+    fn flapping_health_drives_the_watchdog() {
+        let fake = FakeTransport::new();
+        let health = format!("{BASE_URL}{HEALTH}");
+        let stale = Reply::Status(200, r#"{"config_generation": 1}"#);
+        fake.script(
+            &health,
+            [
+                stale.clone(),
+                Reply::Refused,
+                stale.clone(),
+                Reply::TimedOut,
+                stale,
+                Reply::Status(200, r#"{"config_generation": 2}"#),
+            ],
+        );
+        let mut runtime = BackendRuntime::new(PathBuf::from("data"), "default".to_string());
+        let beats: Vec<Option<SyncCheck>> = (0..6)
+            .map(|_| {
+                let info = probe_health(&fake, BASE_URL, "token").ok()?;
+                Some(track_sync(&mut runtime, info.config_generation, 2))
+            })
+            .collect();
+        assert_eq!(
+            beats,
+            [
+                Some(SyncCheck::Waiting),
+                None,
+                Some(SyncCheck::Diverged),
+                None,
+                Some(SyncCheck::StillDiverged),
+                Some(SyncCheck::InSync),
+            ]
+        );
+        assert_eq!(runtime.out_of_sync_beats, 0);
+
+        // Failed probes in a row take readiness away; one answer restores it.
+        let ok = Reply::Status(200, r#"{"config_generation": 2}"#);
+        let fake = FakeTransport::new();
+        fake.script(
+            &health,
+            [
+                ok.clone(),
+                Reply::Refused,
+                ok.clone(),
+                Reply::Refused,
+                Reply::TimedOut,
+                Reply::Refused,
+                Reply::Refused,
+                ok,
+            ],
+        );
+        runtime.backend_ready = true;
+        let beats: Vec<(Liveness, bool, bool)> = (0..8)
+            .map(|_| {
+                let result = probe_health(&fake, BASE_URL, "token");
+                let answered = result.as_ref().map(|_| ()).map_err(String::as_str);
+                let liveness = track_health(&mut runtime, answered);
+                (
+                    liveness,
+                    runtime.backend_ready,
+                    runtime.last_error.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            beats,
+            [
+                (Liveness::Healthy, true, false),
+                (Liveness::Failing, true, false),
+                (Liveness::Healthy, true, false),
+                (Liveness::Failing, true, false),
+                (Liveness::Failing, true, false),
+                (Liveness::Lost, false, true),
+                (Liveness::Failing, false, true),
+                (Liveness::Recovered, true, false),
+            ]
+        );
+        assert_eq!(runtime.failed_health_beats, 0);
+
+        // An out-of-band check rides out a blip but not an outage.
+        let fake = FakeTransport::new();
+        fake.script(&health, [Reply::Refused, Reply::Status(200, "{}")]);
+        assert!(probe_until_healthy(&fake, BASE_URL, "token", Duration::ZERO).is_ok());
+        assert_eq!(fake.seen(&health).len(), 2);
+        let down = FakeTransport::new();
+        assert!(probe_until_healthy(&down, BASE_URL, "token", Duration::ZERO).is_err());
+        assert_eq!(down.seen(&health).len(), CHECK_NOW_ATTEMPTS as usize);
+    }
+}
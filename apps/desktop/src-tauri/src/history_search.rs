@@ -0,0 +1,294 @@
+//! Quick search over past conversations for the quick-ask window. Goes
+//! through the backend when it is up and otherwise reads the backend's
+//! history database directly, read-only and without taking locks.
+
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::{backend_http, ui_state, AppState};
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+/// Characters of context kept around the first match in a snippet.
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_AFTER: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSource {
+    Backend,
+    Local,
+}
+
+/// A run of snippet text; `highlight` marks the parts matching the query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryHit {
+    pub conversation_id: String,
+    pub title: String,
+    pub snippet: Vec<SnippetPart>,
+    pub timestamp: Option<String>,
+    pub source: SearchSource,
+    /// From `ui_state`, not the backend.
+    pub pinned: bool,
+    pub label: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BackendHit {
+    conversation_id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    snippet: String,
+    timestamp: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BackendResults {
+    results: Vec<BackendHit>,
+}
+
+pub fn history_db_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("history.db")
+}
+
+fn lowercase_chars(text: &str) -> Vec<char> {
+    // Per-char, so indices line up with `text.chars()`.
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+fn find_matches(haystack: &[char], needle: &[char]) -> Vec<usize> {
+    let mut found = Vec::new();
+    let mut index = 0;
+    while !needle.is_empty() && index + needle.len() <= haystack.len() {
+        if haystack[index..index + needle.len()] == *needle {
+            found.push(index);
+            index += needle.len();
+        } else {
+            index += 1;
+        }
+    }
+    found
+}
+
+/// Cuts a window around the first match and splits it into highlighted and
+/// plain parts. Without a match the start of the text is used.
+pub fn highlight_snippet(text: &str, query: &str) -> Vec<SnippetPart> {
+    let chars: Vec<char> = text.chars().collect();
+    let needle = lowercase_chars(query.trim());
+    let matches = find_matches(&lowercase_chars(text), &needle);
+    let first = matches.first().copied().unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_BEFORE);
+    let end = (first + needle.len() + SNIPPET_AFTER).min(chars.len());
+
+    let mut parts = Vec::new();
+    let mut push = |range: &[char], highlight: bool| {
+        if !range.is_empty() {
+            parts.push(SnippetPart {
+                text: range.iter().collect(),
+                highlight,
+            });
+        }
+    };
+    if start > 0 {
+        push(&['…'], false);
+    }
+    let mut cursor = start;
+    for &at in matches
+        .iter()
+        .filter(|&&at| at >= start && at + needle.len() <= end)
+    {
+        push(&chars[cursor..at], false);
+        push(&chars[at..at + needle.len()], true);
+        cursor = at + needle.len();
+    }
+    push(&chars[cursor..end], false);
+    if end < chars.len() {
+        push(&['…'], false);
+    }
+    parts
+}
+
+fn search_backend(
+    base_url: &str,
+    token: &str,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<HistoryHit>, String> {
+    let endpoint = "/v1/history/search";
+    let url = url::Url::parse_with_params(
+        &format!("{base_url}{endpoint}"),
+        &[("q", query), ("limit", &limit.to_string())],
+    )
+    .map_err(|e| format!("invalid search url: {e}"))?;
+    let request = backend_http::agent()
+        .get(url.as_str())
+        .set("Authorization", &format!("Bearer {token}"));
+    let sent = backend_http::send_idempotent(endpoint, request, None);
+    let note = sent.attempts_note();
+    let body = sent
+        .result
+        .map_err(|e| format!("history search failed{note}: {e}"))?
+        .into_string()
+        .map_err(|e| format!("failed reading search results: {e}"))?;
+    let parsed: BackendResults =
+        serde_json::from_str(&body).map_err(|e| format!("invalid search results: {e}"))?;
+    Ok(parsed
+        .results
+        .into_iter()
+        .map(|hit| HistoryHit {
+            snippet: highlight_snippet(&hit.snippet, query),
+            conversation_id: hit.conversation_id,
+            title: hit.title,
+            timestamp: hit.timestamp,
+            source: SearchSource::Backend,
+            pinned: false,
+            label: None,
+        })
+        .collect())
+}
+
+/// `immutable=1` tells SQLite the file will not change underneath it, so it
+/// takes no locks at all and never contends with the backend's writer.
+fn open_readonly(path: &Path) -> Result<Connection, String> {
+    let mut uri = url::Url::from_file_path(path)
+        .map_err(|_| format!("invalid history path: {}", path.display()))?;
+    uri.set_query(Some("immutable=1"));
+    Connection::open_with_flags(
+        uri.as_str(),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )
+    .map_err(|e| format!("failed opening history database: {e}"))
+}
+
+fn value_text(value: Value) -> Option<String> {
+    match value {
+        Value::Text(text) => Some(text),
+        Value::Integer(number) => Some(number.to_string()),
+        Value::Real(number) => Some(number.to_string()),
+        Value::Null | Value::Blob(_) => None,
+    }
+}
+
+fn like_pattern(query: &str) -> String {
+    let escaped = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Expects the backend's `conversations(id, title, updated_at)` and
+/// `messages(conversation_id, content, created_at)` tables.
+pub fn search_local(data_dir: &Path, query: &str, limit: usize) -> Result<Vec<HistoryHit>, String> {
+    let path = history_db_path(data_dir);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let conn = open_readonly(&path)?;
+    let query = query.trim();
+    let sql = if query.is_empty() {
+        "SELECT c.id, c.title, c.updated_at,
+                (SELECT m.content FROM messages m WHERE m.conversation_id = c.id
+                 ORDER BY m.created_at DESC LIMIT 1)
+         FROM conversations c
+         ORDER BY c.updated_at DESC LIMIT ?2"
+    } else {
+        "SELECT c.id, c.title, c.updated_at,
+                (SELECT m.content FROM messages m WHERE m.conversation_id = c.id
+                 AND m.content LIKE ?1 ESCAPE '\\' ORDER BY m.created_at DESC LIMIT 1) AS hit
+         FROM conversations c
+         WHERE c.title LIKE ?1 ESCAPE '\\' OR hit IS NOT NULL
+         ORDER BY c.updated_at DESC LIMIT ?2"
+    };
+    let mut statement = conn
+        .prepare(sql)
+        .map_err(|e| format!("history query failed: {e}"))?;
+    let rows = statement
+        .query_map((like_pattern(query), limit as i64), |row| {
+            Ok((
+                row.get::<_, Value>(0)?,
+                row.get::<_, Value>(1)?,
+                row.get::<_, Value>(2)?,
+                row.get::<_, Value>(3)?,
+            ))
+        })
+        .map_err(|e| format!("history query failed: {e}"))?;
+    let mut hits = Vec::new();
+    for row in rows {
+        let (id, title, updated_at, content) =
+            row.map_err(|e| format!("failed reading history row: {e}"))?;
+        let Some(conversation_id) = value_text(id) else {
+            continue;
+        };
+        let title = value_text(title).unwrap_or_default();
+        let content = value_text(content).unwrap_or_else(|| title.clone());
+        hits.push(HistoryHit {
+            conversation_id,
+            snippet: highlight_snippet(&content, query),
+            title,
+            timestamp: value_text(updated_at),
+            source: SearchSource::Local,
+            pinned: false,
+            label: None,
+        });
+    }
+    Ok(hits)
+}
+
+#[tauri::command]
+pub async fn search_history(
+    state: State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<HistoryHit>, CommandError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let (ready, base_url, token, data_dir) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        (
+            runtime.backend_ready,
+            runtime.base_url.clone(),
+            runtime.token.clone(),
+            runtime.data_dir.clone(),
+        )
+    };
+    let from_backend = if ready {
+        search_backend(&base_url, &token, &query, limit).ok()
+    } else {
+        None
+    };
+    let mut hits = match from_backend {
+        Some(hits) => hits,
+        None => search_local(&data_dir, &query, limit)?,
+    };
+    mark_pinned(&data_dir, &mut hits);
+    Ok(hits)
+}
+
+fn mark_pinned(data_dir: &Path, hits: &mut [HistoryHit]) {
+    let metadata = ui_state::lookup(data_dir);
+    for hit in hits {
+        if let Some(meta) = metadata
+            .iter()
+            .find(|meta| meta.conversation_id == hit.conversation_id)
+        {
+            hit.pinned = meta.pinned;
+            hit.label = meta.label.clone();
+        }
+    }
+}
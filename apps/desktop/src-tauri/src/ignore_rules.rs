@@ -0,0 +1,185 @@
+//! What inside an allowed folder is treated as invisible. Each folder carries
+//! gitignore-syntax `ignore_patterns`, seeded with `DEFAULT_IGNORE_PATTERNS`
+//! when the folder is added; with `respect_gitignore` set, the folder's own
+//! top-level `.gitignore` is applied as well. The backend gets the same
+//! patterns on reload so both sides agree on what is visible.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::excluded_dirs;
+use crate::folders::{self, AllowedFolder};
+use crate::{backend_reload_config, commit_config, read_local_config, AppState, LocalConfig};
+
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &["node_modules/", ".git/", "target/", "__pycache__/"];
+const MAX_PATTERNS_PER_FOLDER: usize = 200;
+const MAX_PATTERN_CHARS: usize = 256;
+
+pub fn default_patterns() -> Vec<String> {
+    DEFAULT_IGNORE_PATTERNS
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect()
+}
+
+/// Trims `pattern` and checks that it compiles as a gitignore line.
+pub fn validate_pattern(pattern: &str) -> Result<String, CommandError> {
+    let trimmed = pattern.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Err(CommandError::invalid_input(
+            "ignore pattern must not be empty or a comment",
+        ));
+    }
+    if trimmed.chars().count() > MAX_PATTERN_CHARS {
+        return Err(CommandError::invalid_input(format!(
+            "ignore pattern must be at most {MAX_PATTERN_CHARS} characters"
+        )));
+    }
+    GitignoreBuilder::new("")
+        .add_line(None, trimmed)
+        .map_err(|e| {
+            CommandError::invalid_input(format!("invalid ignore pattern `{trimmed}`: {e}"))
+        })?;
+    Ok(trimmed.to_string())
+}
+
+/// A folder's whole pattern list, as `validate_pattern` leaves each entry;
+/// repeats are dropped.
+pub fn validate_patterns(patterns: &[String]) -> Result<Vec<String>, CommandError> {
+    let mut valid: Vec<String> = Vec::with_capacity(patterns.len());
+    for pattern in patterns {
+        let pattern = validate_pattern(pattern)?;
+        if !valid.contains(&pattern) {
+            valid.push(pattern);
+        }
+    }
+    if valid.len() > MAX_PATTERNS_PER_FOLDER {
+        return Err(too_many_patterns());
+    }
+    Ok(valid)
+}
+
+fn too_many_patterns() -> CommandError {
+    CommandError::invalid_input(format!(
+        "a folder can have at most {MAX_PATTERNS_PER_FOLDER} ignore patterns"
+    ))
+}
+
+/// Compiled ignore rules for one allowed folder.
+pub struct FolderIgnore {
+    matcher: Gitignore,
+    /// App-owned dirs inside the folder, hidden whatever the patterns say.
+    excluded: Vec<PathBuf>,
+}
+
+impl FolderIgnore {
+    pub fn new(folder: &AllowedFolder, respect_gitignore: bool) -> Self {
+        let root = folder.effective_path();
+        let mut builder = GitignoreBuilder::new(&root);
+        if respect_gitignore {
+            // A broken line in the user's .gitignore only drops that line.
+            let _ = builder.add(root.join(".gitignore"));
+        }
+        for pattern in &folder.ignore_patterns {
+            let _ = builder.add_line(None, pattern);
+        }
+        Self {
+            matcher: builder.build().unwrap_or_else(|_| Gitignore::empty()),
+            excluded: Vec::new(),
+        }
+    }
+
+    pub fn excluding(mut self, excluded: Vec<PathBuf>) -> Self {
+        self.excluded = excluded;
+        self
+    }
+
+    pub fn excluded(&self) -> &[PathBuf] {
+        &self.excluded
+    }
+
+    /// True when `path`, or a directory above it inside the folder, matches.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if excluded_dirs::is_excluded(&self.excluded, path) {
+            return true;
+        }
+        if !path.starts_with(self.matcher.path()) {
+            return false;
+        }
+        self.matcher
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+}
+
+/// Rules for the allowed folder containing `path`, if any.
+pub fn for_path(data_dir: &Path, config: &LocalConfig, path: &Path) -> Option<FolderIgnore> {
+    let excluded = excluded_dirs::excluded_dirs(data_dir);
+    config
+        .allowed_folders
+        .iter()
+        .find(|folder| path.starts_with(folder.effective_path()))
+        .map(|folder| {
+            FolderIgnore::new(folder, config.respect_gitignore)
+                .excluding(excluded_dirs::within_folder(&excluded, folder))
+        })
+}
+
+#[tauri::command]
+pub fn add_folder_ignore_pattern(
+    state: State<'_, AppState>,
+    path: String,
+    pattern: String,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let pattern = validate_pattern(&pattern)?;
+    folders::update_folder(&state, path, |config, index| {
+        let patterns = &mut config.allowed_folders[index].ignore_patterns;
+        if patterns.contains(&pattern) {
+            return Ok(());
+        }
+        if patterns.len() >= MAX_PATTERNS_PER_FOLDER {
+            return Err(too_many_patterns());
+        }
+        patterns.push(pattern);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn remove_folder_ignore_pattern(
+    state: State<'_, AppState>,
+    path: String,
+    pattern: String,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let pattern = pattern.trim().to_string();
+    folders::update_folder(&state, path, |config, index| {
+        let patterns = &mut config.allowed_folders[index].ignore_patterns;
+        let before = patterns.len();
+        patterns.retain(|existing| existing != &pattern);
+        if patterns.len() == before {
+            return Err(CommandError::not_found(format!(
+                "folder has no ignore pattern `{pattern}`"
+            )));
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn set_respect_gitignore(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.respect_gitignore = enabled;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
@@ -0,0 +1,179 @@
+//! Which optional OS integrations work on this machine. Minimal Linux
+//! setups may lack a tray host, a session bus for notifications or a
+//! display for native dialogs; the app runs without each of them. Setup
+//! records what it found in `IntegrationStatus` (shown by `get_app_info`),
+//! the frontend falls back to typed paths and in-window banners, and one
+//! `integrations-limited` event lists whatever is missing.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::AppState;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Integration {
+    pub available: bool,
+    /// Why it is unavailable.
+    pub reason: Option<String>,
+    /// What the app does instead.
+    pub fallback: Option<&'static str>,
+}
+
+impl Integration {
+    fn available() -> Self {
+        Self {
+            available: true,
+            reason: None,
+            fallback: None,
+        }
+    }
+
+    fn missing(reason: impl Into<String>, fallback: &'static str) -> Self {
+        Self {
+            available: false,
+            reason: Some(reason.into()),
+            fallback: Some(fallback),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrationStatus {
+    pub tray: Integration,
+    /// Native file and folder pickers.
+    pub dialog: Integration,
+    pub notifications: Integration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingIntegration {
+    pub name: &'static str,
+    pub reason: String,
+    pub fallback: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrationsLimited {
+    pub missing: Vec<MissingIntegration>,
+}
+
+const TRAY_FALLBACK: &str = "the window stays the only way to reach LiteClaw";
+const DIALOG_FALLBACK: &str = "paths are typed in instead of picked";
+const NOTIFICATION_FALLBACK: &str = "alerts are shown as banners in the window";
+
+#[cfg(target_os = "linux")]
+fn session_bus_reachable() -> bool {
+    if std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some() {
+        return true;
+    }
+    // systemd puts the bus here without always exporting the variable.
+    let uid = unsafe { libc::getuid() };
+    std::path::Path::new(&format!("/run/user/{uid}/bus")).exists()
+}
+
+#[cfg(target_os = "linux")]
+fn detect_dialog() -> Integration {
+    let has_display =
+        std::env::var_os("WAYLAND_DISPLAY").is_some() || std::env::var_os("DISPLAY").is_some();
+    if !has_display {
+        return Integration::missing("no X11 or Wayland display", DIALOG_FALLBACK);
+    }
+    // Sandboxed builds can only open dialogs through xdg-desktop-portal.
+    let sandboxed = std::env::var_os("FLATPAK_ID").is_some() || std::env::var_os("SNAP").is_some();
+    if sandboxed && !session_bus_reachable() {
+        return Integration::missing(
+            "no D-Bus session bus for xdg-desktop-portal",
+            DIALOG_FALLBACK,
+        );
+    }
+    Integration::available()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_dialog() -> Integration {
+    Integration::available()
+}
+
+#[cfg(target_os = "linux")]
+fn detect_notifications() -> Integration {
+    if session_bus_reachable() {
+        Integration::available()
+    } else {
+        Integration::missing("no D-Bus session bus", NOTIFICATION_FALLBACK)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_notifications() -> Integration {
+    Integration::available()
+}
+
+/// Probes what can be probed before the window exists; the tray is filled
+/// in by `record_tray` once creation has been tried.
+pub fn detect() -> IntegrationStatus {
+    IntegrationStatus {
+        tray: Integration::available(),
+        dialog: detect_dialog(),
+        notifications: detect_notifications(),
+    }
+}
+
+pub fn record_tray(status: &mut IntegrationStatus, result: Result<(), String>) {
+    status.tray = match result {
+        Ok(()) => Integration::available(),
+        Err(reason) => Integration::missing(reason, TRAY_FALLBACK),
+    };
+}
+
+/// Emits `integrations-limited` once, at startup, if anything is missing.
+pub fn announce(app: &AppHandle, status: &IntegrationStatus) {
+    let missing: Vec<MissingIntegration> = [
+        ("tray", &status.tray),
+        ("dialog", &status.dialog),
+        ("notifications", &status.notifications),
+    ]
+    .into_iter()
+    .filter(|(_, integration)| !integration.available)
+    .map(|(name, integration)| MissingIntegration {
+        name,
+        reason: integration.reason.clone().unwrap_or_default(),
+        fallback: integration.fallback.unwrap_or_default(),
+    })
+    .collect();
+    if !missing.is_empty() {
+        let _ = app.emit("integrations-limited", IntegrationsLimited { missing });
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Banner<'a> {
+    title: &'a str,
+    body: &'a str,
+}
+
+/// An OS notification, or an `app-banner` event for the window when
+/// notifications are unavailable or fail.
+pub fn notify(app: &AppHandle, title: &str, body: &str) {
+    let available = app
+        .try_state::<AppState>()
+        .and_then(|state| {
+            state
+                .integrations
+                .lock()
+                .ok()
+                .map(|status| status.notifications.available)
+        })
+        .unwrap_or(true);
+    let shown = available
+        && app
+            .notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+            .is_ok();
+    if !shown {
+        let _ = app.emit("app-banner", Banner { title, body });
+    }
+}
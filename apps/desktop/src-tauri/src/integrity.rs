@@ -0,0 +1,123 @@
+//! Refuses to spawn a backend whose files differ from the ones this build
+//! shipped. `build.rs` embeds the SHA-256 of each backend file; before every
+//! spawn the resolved copy is hashed and compared. The source tree a dev
+//! build runs from is never checked, since editing it is the point.
+//! An installed backend update is instead checked against its own signed
+//! archive (see `backend_update`). `allow_modified_backend` in config lets
+//! developers run a patched bundle; the mismatch is then logged and audited
+//! instead of blocking the start.
+
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::file_ops::sha256_hex;
+use crate::{audit, backend_update, desktop_log, LocalConfig};
+
+include!(concat!(env!("OUT_DIR"), "/backend_manifest.rs"));
+
+pub const FAILED_CODE: &str = "backend_integrity_failed";
+
+/// Set while the last spawn was refused; surfaced in `ApiConfig`.
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+pub fn has_failed() -> bool {
+    FAILED.load(Ordering::SeqCst)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Mismatch {
+    pub file: String,
+    pub expected: String,
+    /// `None` when the file is missing or unreadable.
+    pub actual: Option<String>,
+}
+
+fn verify_against<S: AsRef<str>>(dir: &Path, manifest: &[(S, S)]) -> Vec<Mismatch> {
+    manifest
+        .iter()
+        .filter_map(|(file, expected)| {
+            let (file, expected) = (file.as_ref(), expected.as_ref());
+            let actual = fs::read(dir.join(file))
+                .ok()
+                .map(|bytes| sha256_hex(&bytes));
+            (actual.as_deref() != Some(expected)).then(|| Mismatch {
+                file: file.to_string(),
+                expected: expected.to_string(),
+                actual,
+            })
+        })
+        .collect()
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn describe(mismatches: &[Mismatch]) -> String {
+    mismatches
+        .iter()
+        .map(|mismatch| match &mismatch.actual {
+            Some(_) => format!("{} was modified", mismatch.file),
+            None => format!("{} is missing", mismatch.file),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Checks `backend_dir` before a spawn. `dev_dir` is the source tree, which
+/// is exempt.
+pub fn check(
+    backend_dir: &Path,
+    dev_dir: &Path,
+    config: &LocalConfig,
+    data_dir: &Path,
+) -> Result<(), String> {
+    FAILED.store(false, Ordering::SeqCst);
+    if same_dir(backend_dir, dev_dir) {
+        return Ok(());
+    }
+    let (summary, mismatches) = match backend_update::installed_manifest(backend_dir) {
+        Some(Err(err)) => (err, Vec::new()),
+        Some(Ok(manifest)) => {
+            let mismatches = verify_against(backend_dir, &manifest);
+            (describe(&mismatches), mismatches)
+        }
+        None => {
+            let mismatches = verify_against(backend_dir, BACKEND_MANIFEST);
+            (describe(&mismatches), mismatches)
+        }
+    };
+    if summary.is_empty() {
+        return Ok(());
+    }
+    if !config.allow_modified_backend {
+        FAILED.store(true, Ordering::SeqCst);
+        return Err(format!(
+            "{FAILED_CODE}: refusing to start a backend that differs from this build \
+             ({summary} in {}); reinstall LiteClaw",
+            backend_dir.display()
+        ));
+    }
+    desktop_log::warn(
+        data_dir,
+        &format!(
+            "running a MODIFIED backend from {} ({summary}) because \
+             allow_modified_backend is set",
+            backend_dir.display()
+        ),
+    );
+    let _ = audit::record(
+        data_dir,
+        "backend_integrity_overridden",
+        serde_json::json!({
+            "backend_dir": backend_dir.to_string_lossy(),
+            "mismatches": mismatches,
+        }),
+    );
+    Ok(())
+}
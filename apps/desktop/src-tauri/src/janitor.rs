@@ -0,0 +1,276 @@
+//! Startup cleanup of files crashes leave behind in the data and cache
+//! dirs. Every location the janitor may delete from is listed in `TARGETS`; anything not
+//! matched there is left alone.
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::{attachments, desktop_log, layout, response_limit, AppState, BACKEND_CWD_DIR};
+
+/// Interrupted writes are only swept once they are clearly abandoned.
+const TEMP_MIN_AGE: Duration = Duration::from_secs(10 * 60);
+
+enum Policy {
+    /// Delete matching entries older than the given age.
+    OlderThan(Duration),
+    /// Keep the newest N matching entries and delete the rest.
+    KeepNewest(usize),
+}
+
+/// Which of a profile's dirs a target's `dir` is relative to.
+enum Base {
+    Data,
+    Config,
+    Cache,
+}
+
+struct CleanupTarget {
+    name: &'static str,
+    base: Base,
+    /// Directory relative to `base`.
+    dir: &'static str,
+    matches: fn(&str) -> bool,
+    /// Whether matching entries may be directories (removed recursively).
+    directories: bool,
+    policy: Policy,
+}
+
+fn is_temp_file(name: &str) -> bool {
+    name.ends_with(".tmp") || name.ends_with(".part")
+}
+
+fn is_corrupt_backup(name: &str) -> bool {
+    name.ends_with(".corrupt")
+}
+
+fn is_attachment_slot(name: &str) -> bool {
+    uuid::Uuid::parse_str(name).is_ok()
+}
+
+fn is_crash_report(name: &str) -> bool {
+    name.starts_with("crash-") && name.ends_with(".json")
+}
+
+fn is_any(_name: &str) -> bool {
+    true
+}
+
+/// `backend.log.1`, `desktop.log.2`, ...
+fn is_rotated_log(name: &str) -> bool {
+    name.rsplit_once(".log.")
+        .is_some_and(|(_, suffix)| !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit()))
+}
+
+const TARGETS: &[CleanupTarget] = &[
+    CleanupTarget {
+        name: "temp_files",
+        base: Base::Data,
+        dir: "",
+        matches: is_temp_file,
+        directories: false,
+        policy: Policy::OlderThan(TEMP_MIN_AGE),
+    },
+    CleanupTarget {
+        name: "corrupt_backups",
+        base: Base::Data,
+        dir: "",
+        matches: is_corrupt_backup,
+        directories: false,
+        policy: Policy::KeepNewest(3),
+    },
+    // Only differs from the data dir on Linux; elsewhere this finds nothing
+    // the first target left.
+    CleanupTarget {
+        name: "config_temp_files",
+        base: Base::Config,
+        dir: "",
+        matches: is_temp_file,
+        directories: false,
+        policy: Policy::OlderThan(TEMP_MIN_AGE),
+    },
+    CleanupTarget {
+        name: "staged_attachments",
+        base: Base::Data,
+        dir: "attachments",
+        matches: is_attachment_slot,
+        directories: true,
+        policy: Policy::OlderThan(attachments::RETENTION),
+    },
+    CleanupTarget {
+        name: "crash_reports",
+        base: Base::Data,
+        dir: "crash_reports",
+        matches: is_crash_report,
+        directories: false,
+        policy: Policy::KeepNewest(20),
+    },
+    // The backend's cwd holds nothing of value; the age limit spares files
+    // of a backend started alongside the janitor.
+    CleanupTarget {
+        name: "backend_cwd_strays",
+        base: Base::Cache,
+        dir: BACKEND_CWD_DIR,
+        matches: is_any,
+        directories: true,
+        policy: Policy::OlderThan(TEMP_MIN_AGE),
+    },
+    CleanupTarget {
+        name: "spilled_results",
+        base: Base::Data,
+        dir: response_limit::SPILL_DIR,
+        matches: response_limit::is_spill_file,
+        directories: false,
+        policy: Policy::OlderThan(response_limit::SPILL_RETENTION),
+    },
+    CleanupTarget {
+        name: "rotated_logs",
+        base: Base::Cache,
+        dir: "logs",
+        matches: is_rotated_log,
+        directories: false,
+        policy: Policy::KeepNewest(5),
+    },
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetSummary {
+    pub name: &'static str,
+    pub files_removed: usize,
+    pub bytes_removed: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupSummary {
+    pub files_removed: usize,
+    pub bytes_removed: u64,
+    pub targets: Vec<TargetSummary>,
+}
+
+pub fn entry_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn target_dir(data_dir: &Path, target: &CleanupTarget) -> PathBuf {
+    let base = match target.base {
+        Base::Data => data_dir.to_path_buf(),
+        Base::Config => layout::config_dir(data_dir),
+        Base::Cache => layout::cache_dir(data_dir),
+    };
+    base.join(target.dir)
+}
+
+fn candidates(data_dir: &Path, target: &CleanupTarget) -> Vec<(PathBuf, SystemTime)> {
+    let Ok(entries) = fs::read_dir(target_dir(data_dir, target)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !(target.matches)(&name) {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            let kind_ok = if target.directories {
+                metadata.is_dir()
+            } else {
+                metadata.is_file()
+            };
+            if !kind_ok {
+                return None;
+            }
+            let modified = metadata.modified().ok()?;
+            Some((entry.path(), modified))
+        })
+        .collect()
+}
+
+fn clean_target(data_dir: &Path, target: &CleanupTarget) -> TargetSummary {
+    let mut entries = candidates(data_dir, target);
+    let doomed: Vec<PathBuf> = match target.policy {
+        Policy::OlderThan(age) => {
+            let cutoff = SystemTime::now()
+                .checked_sub(age)
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            entries
+                .into_iter()
+                .filter(|(_, modified)| *modified < cutoff)
+                .map(|(path, _)| path)
+                .collect()
+        }
+        Policy::KeepNewest(keep) => {
+            entries.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+            entries
+                .into_iter()
+                .skip(keep)
+                .map(|(path, _)| path)
+                .collect()
+        }
+    };
+    let mut summary = TargetSummary {
+        name: target.name,
+        files_removed: 0,
+        bytes_removed: 0,
+    };
+    for path in doomed {
+        let bytes = entry_size(&path);
+        let removed = if target.directories {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        if removed.is_ok() {
+            summary.files_removed += 1;
+            summary.bytes_removed += bytes;
+        }
+    }
+    summary
+}
+
+pub fn run_cleanup(data_dir: &Path) -> CleanupSummary {
+    let targets: Vec<TargetSummary> = TARGETS
+        .iter()
+        .map(|target| clean_target(data_dir, target))
+        .collect();
+    let summary = CleanupSummary {
+        files_removed: targets.iter().map(|t| t.files_removed).sum(),
+        bytes_removed: targets.iter().map(|t| t.bytes_removed).sum(),
+        targets,
+    };
+    desktop_log::info(
+        data_dir,
+        &format!(
+            "cleanup removed {} entries ({} bytes)",
+            summary.files_removed, summary.bytes_removed
+        ),
+    );
+    summary
+}
+
+#[tauri::command]
+pub fn run_cleanup_now(state: State<'_, AppState>) -> Result<CleanupSummary, CommandError> {
+    let data_dir = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?
+        .data_dir
+        .clone();
+    Ok(run_cleanup(&data_dir))
+}
@@ -0,0 +1,407 @@
+//! Where each kind of file lives. On Linux the XDG base directories are
+//! followed: `config.json` under `$XDG_CONFIG_HOME/liteclaw`, durable data
+//! (history, audit log, profiles) under `$XDG_DATA_HOME/liteclaw`, and logs
+//! plus anything regenerable under `$XDG_CACHE_HOME/liteclaw`, so backup tools
+//! can skip the cache. Windows and macOS keep the single app-data dir with a
+//! `cache/` subfolder. A profile's config and cache dirs mirror its path
+//! below the data root, so every path is still derived from the data dir.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const APP_DIR: &str = "liteclaw";
+/// `identifier` in tauri.conf.json, which names the app data dir.
+const IDENTIFIER: &str = "dev.liteclaw.desktop";
+const CACHE_SUBDIR: &str = "cache";
+/// Written to the data root once files have been moved to this layout.
+const MIGRATED_MARKER: &str = ".layout-v2";
+/// Entries below a data dir that belong in its cache dir.
+const CACHE_ENTRIES: &[&str] = &["logs/backend.log", "logs/desktop.log", "backend-cwd"];
+
+struct Roots {
+    data: PathBuf,
+    config: PathBuf,
+    cache: PathBuf,
+    /// Linux: separate XDG roots. Elsewhere config sits in the data dir and
+    /// the cache in its `cache/` subfolder.
+    split: bool,
+}
+
+static ROOTS: OnceLock<Roots> = OnceLock::new();
+
+/// `$<var>` if set to an absolute path (the spec says to ignore relative
+/// ones), else `$HOME/<fallback>`.
+#[cfg(target_os = "linux")]
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    let from_env = std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute());
+    from_env
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|home| !home.is_empty())
+                .map(|home| PathBuf::from(home).join(fallback))
+        })
+        .map(|base| base.join(APP_DIR))
+}
+
+#[cfg(target_os = "linux")]
+fn resolve_roots(app_data_dir: &Path) -> Roots {
+    let xdg = (
+        xdg_dir("XDG_DATA_HOME", ".local/share"),
+        xdg_dir("XDG_CONFIG_HOME", ".config"),
+        xdg_dir("XDG_CACHE_HOME", ".cache"),
+    );
+    match xdg {
+        (Some(data), Some(config), Some(cache)) => Roots {
+            data,
+            config,
+            cache,
+            split: true,
+        },
+        _ => single_dir_roots(app_data_dir),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resolve_roots(app_data_dir: &Path) -> Roots {
+    single_dir_roots(app_data_dir)
+}
+
+fn single_dir_roots(app_data_dir: &Path) -> Roots {
+    Roots {
+        data: app_data_dir.to_path_buf(),
+        config: app_data_dir.to_path_buf(),
+        cache: app_data_dir.join(CACHE_SUBDIR),
+        split: false,
+    }
+}
+
+/// The app data dir Tauri resolves for `IDENTIFIER`, for runs without an
+/// app to ask: the `config` command line and the native-messaging host.
+#[cfg(target_os = "linux")]
+pub fn app_data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+        })?;
+    Some(base.join(IDENTIFIER))
+}
+
+#[cfg(target_os = "macos")]
+pub fn app_data_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from)?;
+    Some(home.join("Library/Application Support").join(IDENTIFIER))
+}
+
+#[cfg(windows)]
+pub fn app_data_dir() -> Option<PathBuf> {
+    let roaming = std::env::var_os("APPDATA").map(PathBuf::from)?;
+    Some(roaming.join(IDENTIFIER))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn app_data_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Where `profiles.json` and the default profile live; `app_data_dir` until
+/// `init` has run.
+pub fn data_root(app_data_dir: &Path) -> PathBuf {
+    root().unwrap_or_else(|| app_data_dir.to_path_buf())
+}
+
+/// The data root, for app-wide state outside any profile; `None` before
+/// `init`.
+pub fn root() -> Option<PathBuf> {
+    ROOTS.get().map(|roots| roots.data.clone())
+}
+
+/// The data, config and cache roots, which between them hold every
+/// profile's files; empty before `init`.
+pub fn roots() -> Vec<PathBuf> {
+    ROOTS
+        .get()
+        .map(|roots| {
+            vec![
+                roots.data.clone(),
+                roots.config.clone(),
+                roots.cache.clone(),
+            ]
+        })
+        .unwrap_or_default()
+}
+
+/// Where `config.json` for the profile at `data_dir` lives.
+pub fn config_dir(data_dir: &Path) -> PathBuf {
+    match ROOTS.get() {
+        Some(roots) if roots.split => {
+            mirror(roots, data_dir, &roots.config).unwrap_or_else(|| data_dir.to_path_buf())
+        }
+        _ => data_dir.to_path_buf(),
+    }
+}
+
+/// Logs, the backend's cwd and other files that may be deleted at any time.
+pub fn cache_dir(data_dir: &Path) -> PathBuf {
+    match ROOTS.get() {
+        Some(roots) if roots.split => {
+            mirror(roots, data_dir, &roots.cache).unwrap_or_else(|| data_dir.join(CACHE_SUBDIR))
+        }
+        _ => data_dir.join(CACHE_SUBDIR),
+    }
+}
+
+/// `data_dir`'s place below `target_root`; `None` for a dir outside the data
+/// root, which then keeps the single-dir layout.
+fn mirror(roots: &Roots, data_dir: &Path, target_root: &Path) -> Option<PathBuf> {
+    data_dir
+        .strip_prefix(&roots.data)
+        .ok()
+        .map(|relative| target_root.join(relative))
+}
+
+/// Renames, falling back to copy-and-delete for files across filesystems
+/// (`$XDG_CACHE_HOME` is often a separate tmpfs).
+fn move_entry(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(_) if from.is_file() => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        // Only `backend-cwd` is a directory here, and it holds nothing of
+        // value.
+        Err(_) if from.is_dir() => fs::remove_dir_all(from),
+        Err(err) => Err(err),
+    }
+}
+
+/// `backend.log`, `backend.log.1`, ... for a listed log file.
+fn with_rotations(dir: &Path, entry: &str) -> Vec<OsString> {
+    let path = Path::new(entry);
+    let Some(name) = path.file_name() else {
+        return Vec::new();
+    };
+    let parent = dir.join(path.parent().unwrap_or(Path::new("")));
+    let prefix = format!("{}.", name.to_string_lossy());
+    let mut names = vec![name.to_os_string()];
+    if let Ok(entries) = fs::read_dir(parent) {
+        names.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.file_name())
+                .filter(|file| file.to_string_lossy().starts_with(&prefix)),
+        );
+    }
+    names
+}
+
+/// What the layout migration did, for the log and `migrations`.
+#[derive(Debug, Default)]
+pub struct LayoutMigration {
+    /// Where the whole data tree moved from, when it did (Linux only).
+    pub moved_tree: Option<PathBuf>,
+    /// Each file or dir moved out of a data dir, as `(from, to)`.
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    /// A file that could not be moved stays where it was.
+    pub problems: Vec<String>,
+}
+
+impl LayoutMigration {
+    /// For the migration history; `None` when nothing moved.
+    pub fn describe(&self) -> Option<String> {
+        let configs = self
+            .moved
+            .iter()
+            .filter(|(from, _)| from.file_name().is_some_and(|name| name == "config.json"))
+            .count();
+        let cached = self.moved.len() - configs;
+        let mut parts = Vec::new();
+        if let (Some(from), Some(root)) = (&self.moved_tree, root()) {
+            parts.push(format!(
+                "the data folder moved from {} to {}",
+                from.display(),
+                root.display()
+            ));
+        }
+        match configs {
+            0 => {}
+            1 => parts.push("config.json moved to the config folder".to_string()),
+            n => parts.push(format!(
+                "{n} profiles' config.json moved to the config folder"
+            )),
+        }
+        if cached > 0 {
+            parts.push(format!(
+                "{cached} log and working files moved to the cache folder"
+            ));
+        }
+        (!parts.is_empty()).then(|| {
+            format!(
+                "Files moved to the new folder layout: {}.",
+                parts.join("; ")
+            )
+        })
+    }
+}
+
+/// Moves one profile's config and cache files out of its data dir.
+fn migrate_profile(data_dir: &Path, roots: &Roots) -> LayoutMigration {
+    let mut migration = LayoutMigration::default();
+    let config_from = data_dir.join("config.json");
+    let config_to = mirror(roots, data_dir, &roots.config).map(|dir| dir.join("config.json"));
+    if let Some(config_to) = config_to.filter(|to| roots.split && !to.exists()) {
+        if config_from.is_file() {
+            match move_entry(&config_from, &config_to) {
+                Ok(()) => migration.moved.push((config_from, config_to)),
+                Err(err) => migration.problems.push(format!("config.json: {err}")),
+            }
+        }
+    }
+    let cache = match mirror(roots, data_dir, &roots.cache) {
+        Some(cache) if roots.split => cache,
+        _ => data_dir.join(CACHE_SUBDIR),
+    };
+    for entry in CACHE_ENTRIES {
+        let relative_dir = Path::new(entry).parent().unwrap_or(Path::new(""));
+        for name in with_rotations(data_dir, entry) {
+            let from = data_dir.join(relative_dir).join(&name);
+            let to = cache.join(relative_dir).join(&name);
+            if from.exists() && !to.exists() {
+                match move_entry(&from, &to) {
+                    Ok(()) => migration.moved.push((from, to)),
+                    Err(err) => migration
+                        .problems
+                        .push(format!("{}: {err}", from.display())),
+                }
+            }
+        }
+    }
+    migration
+}
+
+/// Resolves the roots and, once, moves files from the old single-dir layout.
+/// Returns the data root (where `profiles.json` lives) and what the
+/// migration did. `profile_dirs` lists the data dirs to migrate, given the
+/// data root; it is only called when a migration is due.
+pub fn init(
+    app_data_dir: &Path,
+    profile_dirs: impl FnOnce(&Path) -> Vec<PathBuf>,
+) -> Result<(PathBuf, LayoutMigration), String> {
+    let roots = ROOTS.get_or_init(|| resolve_roots(app_data_dir));
+    let marker = roots.data.join(MIGRATED_MARKER);
+    if marker.exists() {
+        return Ok((roots.data.clone(), LayoutMigration::default()));
+    }
+    let mut migration = LayoutMigration::default();
+    // Linux only: the whole old tree moves to the XDG data root first.
+    let data_root_empty = fs::read_dir(&roots.data).map_or(true, |mut e| e.next().is_none());
+    if roots.data != app_data_dir && app_data_dir.is_dir() && data_root_empty {
+        let _ = fs::remove_dir(&roots.data);
+        if let Some(parent) = roots.data.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("failed creating data root: {e}"))?;
+        }
+        fs::rename(app_data_dir, &roots.data).map_err(|e| {
+            format!(
+                "failed moving {} to {}: {e}",
+                app_data_dir.display(),
+                roots.data.display()
+            )
+        })?;
+        migration.moved_tree = Some(app_data_dir.to_path_buf());
+    }
+    fs::create_dir_all(&roots.data).map_err(|e| format!("failed creating data root: {e}"))?;
+    for data_dir in profile_dirs(&roots.data) {
+        let profile = migrate_profile(&data_dir, roots);
+        migration.moved.extend(profile.moved);
+        migration.problems.extend(profile.problems);
+    }
+    fs::write(&marker, b"").map_err(|e| format!("failed writing layout marker: {e}"))?;
+    Ok((roots.data.clone(), migration))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("liteclaw-layout-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn split_roots_mirror_profile_paths() {
+        let base = temp_dir();
+        let roots = Roots {
+            data: base.join("data"),
+            config: base.join("config"),
+            cache: base.join("cache"),
+            split: true,
+        };
+        let work = roots.data.join("profiles").join("work");
+        assert_eq!(
+            mirror(&roots, &work, &roots.cache),
+            Some(base.join("cache").join("profiles").join("work"))
+        );
+        assert_eq!(
+            mirror(&roots, &roots.data, &roots.config),
+            Some(roots.config.clone())
+        );
+        assert_eq!(mirror(&roots, &base.join("elsewhere"), &roots.cache), None);
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn migration_moves_config_and_logs_but_keeps_durable_data() {
+        let base = temp_dir();
+        let roots = Roots {
+            data: base.join("data"),
+            config: base.join("config"),
+            cache: base.join("cache"),
+            split: true,
+        };
+        let data_dir = roots.data.clone();
+        fs::create_dir_all(data_dir.join("logs")).unwrap();
+        fs::write(data_dir.join("config.json"), "{}").unwrap();
+        fs::write(data_dir.join("logs/backend.log"), "b").unwrap();
+        fs::write(data_dir.join("logs/backend.log.1"), "b1").unwrap();
+        fs::write(data_dir.join("logs/audit.log"), "a").unwrap();
+
+        let migration = migrate_profile(&data_dir, &roots);
+        assert!(migration.problems.is_empty());
+        assert_eq!(migration.moved.len(), 3);
+
+        assert!(roots.config.join("config.json").is_file());
+        assert!(roots.cache.join("logs/backend.log").is_file());
+        assert!(roots.cache.join("logs/backend.log.1").is_file());
+        assert!(!data_dir.join("config.json").exists());
+        assert!(data_dir.join("logs/audit.log").is_file());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn single_dir_layout_only_moves_logs_into_cache() {
+        let base = temp_dir();
+        let roots = single_dir_roots(&base);
+        fs::create_dir_all(base.join("logs")).unwrap();
+        fs::write(base.join("config.json"), "{}").unwrap();
+        fs::write(base.join("logs/desktop.log"), "d").unwrap();
+
+        assert!(migrate_profile(&base, &roots).problems.is_empty());
+
+        assert!(base.join("config.json").is_file());
+        assert!(base.join("cache/logs/desktop.log").is_file());
+        let _ = fs::remove_dir_all(&base);
+    }
+}
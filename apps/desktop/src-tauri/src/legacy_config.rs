@@ -0,0 +1,202 @@
+//! One-time upgrade from the layout of the earliest builds, which kept a
+//! camelCase `config.json` (`folders`, `shellEnabled`, ...) at the data-dir
+//! root and wrote logs next to it. Runs in `setup` before
+//! `ensure_config_exists`. A legacy file is translated through
+//! `LegacyConfig` and written as the current config, unless a current-format
+//! config already exists, in which case that one wins. Either way the
+//! legacy file is archived as `config.legacy-<unix>.json`, stray `*.log`
+//! files move to the logs dir, and the result is audited and added to the
+//! migration history. Once that has
+//! happened nothing legacy is left, so later runs do nothing.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::folders::AllowedFolder;
+use crate::migrations::{self, MigrationKind};
+use crate::{
+    audit, config_path, layout, paths, unix_now, write_config_atomic, LocalConfig, ShellConfig,
+};
+
+/// Keys only the legacy format used.
+const LEGACY_KEYS: &[&str] = &["folders", "shellEnabled"];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyConfig {
+    #[serde(default)]
+    version: Option<Value>,
+    #[serde(default)]
+    folders: Vec<String>,
+    #[serde(default)]
+    shell_enabled: bool,
+    #[serde(default)]
+    history_enabled: Option<bool>,
+    #[serde(default)]
+    auto_start_backend: Option<bool>,
+}
+
+impl LegacyConfig {
+    fn version(&self) -> String {
+        match &self.version {
+            Some(Value::String(version)) => version.clone(),
+            Some(Value::Number(version)) => version.to_string(),
+            _ => "unversioned".to_string(),
+        }
+    }
+
+    fn into_current(self) -> LocalConfig {
+        let defaults = LocalConfig::default();
+        LocalConfig {
+            allowed_folders: self
+                .folders
+                .into_iter()
+                .filter(|path| !path.trim().is_empty())
+                .map(AllowedFolder::new)
+                .collect(),
+            shell: ShellConfig {
+                enabled: self.shell_enabled,
+                ..ShellConfig::default()
+            },
+            history_enabled: self.history_enabled.unwrap_or(defaults.history_enabled),
+            auto_start_backend: self
+                .auto_start_backend
+                .unwrap_or(defaults.auto_start_backend),
+            ..defaults
+        }
+    }
+}
+
+fn is_legacy(value: &Value) -> bool {
+    let Some(object) = value.as_object() else {
+        return false;
+    };
+    !object.contains_key("allowed_folders")
+        && LEGACY_KEYS.iter().any(|key| object.contains_key(*key))
+}
+
+/// The legacy config at `path`, if the file there is one.
+fn read_legacy(path: &Path) -> Option<LegacyConfig> {
+    let content = fs::read_to_string(path).ok()?;
+    let value: Value = serde_json::from_str(&content).ok()?;
+    if !is_legacy(&value) {
+        return None;
+    }
+    serde_json::from_value(value).ok()
+}
+
+fn archive(path: &Path) -> Result<PathBuf, String> {
+    let archived = path.with_file_name(format!("config.legacy-{}.json", unix_now()));
+    fs::rename(path, &archived)
+        .map_err(|e| format!("failed archiving legacy config {}: {e}", path.display()))?;
+    Ok(archived)
+}
+
+/// Moves `*.log` files from the data-dir root into the logs dir they belong
+/// in now, never over an existing log.
+fn move_stray_logs(data_dir: &Path) -> Vec<String> {
+    let cache_logs = layout::cache_dir(data_dir).join("logs");
+    let Ok(entries) = fs::read_dir(data_dir) else {
+        return Vec::new();
+    };
+    let mut moved = Vec::new();
+    for entry in entries.flatten() {
+        let from = entry.path();
+        if !from.is_file() || from.extension().is_none_or(|ext| ext != "log") {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // The audit log is durable; every other log may be regenerated.
+        let logs_dir = if name == "audit.log" {
+            data_dir.join("logs")
+        } else {
+            cache_logs.clone()
+        };
+        let mut to = logs_dir.join(&name);
+        if to.exists() {
+            to = logs_dir.join(format!("legacy-{name}"));
+        }
+        if to.exists() || fs::create_dir_all(&logs_dir).is_err() {
+            continue;
+        }
+        if fs::rename(&from, &to).is_ok() {
+            moved.push(name);
+        }
+    }
+    moved
+}
+
+fn describe_logs(moved_logs: &[String]) -> Option<String> {
+    match moved_logs.len() {
+        0 => None,
+        1 => Some(format!("{} moved into the logs folder.", moved_logs[0])),
+        n => Some(format!("{n} log files moved into the logs folder.")),
+    }
+}
+
+fn record_history(description: String, moved_logs: &[String]) {
+    let description = match describe_logs(moved_logs) {
+        Some(logs) => format!("{description} {logs}"),
+        None => description,
+    };
+    let _ = migrations::record(MigrationKind::LegacyConfig, description);
+}
+
+pub fn migrate(data_dir: &Path) -> Result<(), String> {
+    let legacy_path = paths::for_io(&data_dir.join("config.json"));
+    let current_path = config_path(data_dir);
+    let moved_logs = move_stray_logs(data_dir);
+    let Some(legacy) = read_legacy(&legacy_path) else {
+        if !moved_logs.is_empty() {
+            let _ = audit::record(
+                data_dir,
+                "legacy_migration",
+                serde_json::json!({ "legacy_version": null, "moved_logs": moved_logs }),
+            );
+            if let Some(description) = describe_logs(&moved_logs) {
+                let _ = migrations::record(MigrationKind::LegacyConfig, description);
+            }
+        }
+        return Ok(());
+    };
+    let legacy_version = legacy.version();
+    // Only distinct from `current_path` where config lives outside the
+    // data dir; with the same path the file is necessarily legacy.
+    let current_exists = current_path != legacy_path && current_path.exists();
+    let archived = archive(&legacy_path)?;
+    if !current_exists {
+        if let Err(err) = write_config_atomic(data_dir, &legacy.into_current()) {
+            let _ = fs::rename(&archived, &legacy_path);
+            return Err(err.into());
+        }
+    }
+    let _ = audit::record(
+        data_dir,
+        "legacy_migration",
+        serde_json::json!({
+            "legacy_version": legacy_version,
+            "config_migrated": !current_exists,
+            "archived": archived.to_string_lossy(),
+            "moved_logs": moved_logs,
+        }),
+    );
+    let archived_name = archived
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let description = if current_exists {
+        format!(
+            "An old-format config.json ({legacy_version}) was archived as {archived_name}; \
+             your current config was kept."
+        )
+    } else {
+        format!(
+            "Your config from an earlier LiteClaw ({legacy_version}) was converted to the \
+             current format; the original is kept as {archived_name}."
+        )
+    };
+    record_history(description, &moved_logs);
+    Ok(())
+}
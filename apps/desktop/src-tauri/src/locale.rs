@@ -0,0 +1,183 @@
+//! The language the assistant answers in. `locale` in config holds a
+//! BCP-47-style tag (`en`, `pt-BR`, `zh-Hant-TW`); the first run fills it
+//! from the OS, and `set_locale` changes it. The backend gets it as
+//! `LITECLAW_LOCALE` at spawn and as `locale` in every reload, and
+//! `get_app_info` reports it. Anything that localizes messages on this side
+//! reads the same stored tag, so both halves agree on the language.
+//!
+//! Tags are checked for shape, not against the subtag registry: a language
+//! of two or three letters, then an optional script, region and variants.
+//! `_` is accepted for `-`, so POSIX names like `en_US` pass, and case is
+//! normalized.
+
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{backend_reload_config, commit_config, read_local_config, AppState, LocalConfig};
+
+const MAX_TAG_LEN: usize = 35;
+
+fn title_case(part: &str) -> String {
+    let lower = part.to_ascii_lowercase();
+    let (first, rest) = lower.split_at(1);
+    format!("{}{rest}", first.to_ascii_uppercase())
+}
+
+/// `tag` in canonical case, or an error when it is not shaped like one.
+pub fn normalize(tag: &str) -> Result<String, CommandError> {
+    let invalid = || {
+        CommandError::invalid_input(format!(
+            "`{tag}` is not a language tag such as `en` or `pt-BR`"
+        ))
+    };
+    let tag = tag.trim();
+    if tag.is_empty() || tag.len() > MAX_TAG_LEN {
+        return Err(invalid());
+    }
+    let mut parts = tag.split(['-', '_']);
+    let language = parts
+        .next()
+        .filter(|part| (2..=3).contains(&part.len()))
+        .filter(|part| part.bytes().all(|byte| byte.is_ascii_alphabetic()))
+        .ok_or_else(invalid)?;
+    let mut normalized = language.to_ascii_lowercase();
+    // Script, then region, then variants; each may be left out.
+    let mut seen_script = false;
+    let mut seen_region = false;
+    for part in parts {
+        let alpha = part.bytes().all(|byte| byte.is_ascii_alphabetic());
+        let digits = part.bytes().all(|byte| byte.is_ascii_digit());
+        let alnum = part.bytes().all(|byte| byte.is_ascii_alphanumeric());
+        let variant = alnum
+            && ((5..=8).contains(&part.len())
+                || (part.len() == 4 && part.as_bytes()[0].is_ascii_digit()));
+        let subtag = if !seen_script && !seen_region && part.len() == 4 && alpha {
+            seen_script = true;
+            title_case(part)
+        } else if !seen_region && ((part.len() == 2 && alpha) || (part.len() == 3 && digits)) {
+            seen_script = true;
+            seen_region = true;
+            part.to_ascii_uppercase()
+        } else if variant {
+            seen_script = true;
+            seen_region = true;
+            part.to_ascii_lowercase()
+        } else {
+            return Err(invalid());
+        };
+        normalized.push('-');
+        normalized.push_str(&subtag);
+    }
+    Ok(normalized)
+}
+
+/// The tag in a POSIX locale name such as `de_DE.UTF-8@euro`; `None` for
+/// `C`, `POSIX` and the like.
+fn from_posix(name: &str) -> Option<String> {
+    let name = name.split(['.', '@']).next()?;
+    normalize(name).ok()
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn os_locale() -> Option<String> {
+    // `LANGUAGE` is a preference list; its first entry wins.
+    let language = std::env::var("LANGUAGE")
+        .ok()
+        .and_then(|list| list.split(':').next().map(str::to_string));
+    language
+        .into_iter()
+        .chain(
+            ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|name| std::env::var(name).ok()),
+        )
+        .find_map(|name| from_posix(&name))
+}
+
+#[cfg(target_os = "macos")]
+fn os_locale() -> Option<String> {
+    // Apps started from Finder get no `LANG`; the user defaults have it.
+    let output = std::process::Command::new("defaults")
+        .args(["read", "-g", "AppleLocale"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success());
+    output
+        .and_then(|output| from_posix(String::from_utf8_lossy(&output.stdout).trim()))
+        .or_else(|| {
+            std::env::var("LANG")
+                .ok()
+                .and_then(|lang| from_posix(&lang))
+        })
+}
+
+#[cfg(windows)]
+fn os_locale() -> Option<String> {
+    use windows_sys::Win32::Globalization::GetUserDefaultLocaleName;
+    // LOCALE_NAME_MAX_LENGTH
+    let mut buf = [0u16; 85];
+    // SAFETY: the length passed is the buffer's.
+    let len = unsafe { GetUserDefaultLocaleName(buf.as_mut_ptr(), buf.len() as i32) };
+    if len <= 1 {
+        return None;
+    }
+    normalize(&String::from_utf16_lossy(&buf[..len as usize - 1])).ok()
+}
+
+/// The OS's display language, for a first run's `locale`.
+pub fn detect() -> Option<String> {
+    os_locale()
+}
+
+/// Sets the answer language; `None` leaves it to the backend.
+#[tauri::command]
+pub fn set_locale(
+    state: State<'_, AppState>,
+    tag: Option<String>,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let tag = tag.as_deref().map(normalize).transpose()?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.locale = tag;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_normalized_and_malformed_ones_refused() {
+        for (tag, expected) in [
+            ("en", "en"),
+            ("pt_br", "pt-BR"),
+            ("ZH-hant-tw", "zh-Hant-TW"),
+            ("es-419", "es-419"),
+            ("de-CH-1996", "de-CH-1996"),
+            ("sl-rozaj-biske", "sl-rozaj-biske"),
+        ] {
+            assert_eq!(normalize(tag).unwrap(), expected, "{tag}");
+        }
+        for tag in [
+            "",
+            "e",
+            "english",
+            "en-",
+            "en--US",
+            "en-US-Latn",
+            "en US",
+            "en-US.UTF-8",
+        ] {
+            assert!(normalize(tag).is_err(), "{tag}");
+        }
+        assert_eq!(from_posix("de_DE.UTF-8@euro").as_deref(), Some("de-DE"));
+        assert_eq!(from_posix("C.UTF-8"), None);
+        assert_eq!(from_posix("POSIX"), None);
+    }
+}
@@ -0,0 +1,335 @@
+//! Exporting a whole log, where `read_backend_logs` and the diagnostics zip
+//! only carry its tail. The chosen log and its rotated predecessors
+//! (`backend.log.2`, `backend.log.1`, then `backend.log`) are gzipped into
+//! one file, read `CHUNK_SIZE` at a time so a log of hundreds of MB never
+//! sits in memory. The export is a task: it reports progress as
+//! `log-export-progress` and stops at the next chunk once cancelled.
+//!
+//! By default the backend's auth token is replaced with `[redacted]` on the
+//! way through. The bytes that could start a token split across two chunks
+//! are held back until the next chunk arrives, so no occurrence slips
+//! past.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::tasks::{TaskKind, TaskToken};
+use crate::{desktop_log, safe_write, storage, AppState};
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// Rotated files looked for beyond the live one.
+const MAX_ROTATED: usize = 20;
+const REDACTED: &[u8] = b"[redacted]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Backend,
+    Desktop,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogExport {
+    pub path: String,
+    /// Oldest first, as they appear in the export.
+    pub files: Vec<String>,
+    pub original_bytes: u64,
+    pub compressed_bytes: u64,
+    /// Occurrences of the auth token that were replaced.
+    pub redactions: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct LogExportProgress {
+    id: String,
+    bytes_read: u64,
+    total_bytes: u64,
+}
+
+/// `log` and its rotated copies that exist, oldest first.
+fn sources(log: &Path) -> Vec<PathBuf> {
+    let name = log
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut files: Vec<PathBuf> = (1..=MAX_ROTATED)
+        .map(|n| log.with_file_name(format!("{name}.{n}")))
+        .take_while(|path| path.is_file())
+        .collect();
+    files.reverse();
+    if log.is_file() {
+        files.push(log.to_path_buf());
+    }
+    files
+}
+
+/// Replaces `secret` in a stream fed to it in pieces.
+struct Redactor {
+    secret: Vec<u8>,
+    /// The tail of what was fed that might begin an occurrence.
+    held: Vec<u8>,
+    count: u64,
+}
+
+impl Redactor {
+    fn new(secret: &str) -> Self {
+        Self {
+            secret: secret.as_bytes().to_vec(),
+            held: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// Appends to `out` everything in `chunk` that can no longer be part of
+    /// an occurrence, redacted.
+    fn feed(&mut self, chunk: &[u8], out: &mut Vec<u8>) {
+        if self.secret.is_empty() {
+            out.extend_from_slice(chunk);
+            return;
+        }
+        let mut data = std::mem::take(&mut self.held);
+        data.extend_from_slice(chunk);
+        // An occurrence starting past here would run beyond `data`.
+        let settled = data.len().saturating_sub(self.secret.len() - 1);
+        let mut start = 0;
+        while start < settled {
+            let found = data[start..]
+                .windows(self.secret.len())
+                .position(|window| window == self.secret.as_slice())
+                .map(|offset| start + offset)
+                .filter(|at| *at < settled);
+            let Some(at) = found else {
+                out.extend_from_slice(&data[start..settled]);
+                start = settled;
+                break;
+            };
+            out.extend_from_slice(&data[start..at]);
+            out.extend_from_slice(REDACTED);
+            self.count += 1;
+            start = at + self.secret.len();
+        }
+        self.held = data[start..].to_vec();
+    }
+
+    fn finish(&mut self, out: &mut Vec<u8>) {
+        out.append(&mut self.held);
+    }
+}
+
+fn write_failed(err: std::io::Error) -> CommandError {
+    if safe_write::is_disk_full(&err) {
+        return CommandError::new(ErrorCode::DiskFull, format!("log export failed: {err}"));
+    }
+    format!("failed writing log export: {err}").into()
+}
+
+/// Gzips `files` in order into `temp`, returning the count of redactions.
+/// Each file is read only up to the length it had at the start.
+fn compress(
+    files: &[(PathBuf, u64)],
+    temp: &Path,
+    secret: &str,
+    token: &TaskToken,
+    progress: &dyn Fn(u64),
+) -> Result<u64, CommandError> {
+    let total: u64 = files.iter().map(|(_, len)| len).sum();
+    let out = File::create(temp).map_err(write_failed)?;
+    let mut encoder = GzEncoder::new(out, Compression::default());
+    let mut redactor = Redactor::new(secret);
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut redacted = Vec::with_capacity(CHUNK_SIZE + REDACTED.len());
+    let mut read_total = 0u64;
+    for (path, len) in files {
+        let file =
+            File::open(path).map_err(|e| format!("failed opening {}: {e}", path.display()))?;
+        let mut reader = file.take(*len);
+        loop {
+            token.check("log export")?;
+            let read = reader
+                .read(&mut buf)
+                .map_err(|e| format!("failed reading {}: {e}", path.display()))?;
+            if read == 0 {
+                break;
+            }
+            redacted.clear();
+            redactor.feed(&buf[..read], &mut redacted);
+            encoder.write_all(&redacted).map_err(write_failed)?;
+            read_total += read as u64;
+            token.report(read_total, Some(total));
+            progress(read_total);
+        }
+    }
+    redacted.clear();
+    redactor.finish(&mut redacted);
+    encoder.write_all(&redacted).map_err(write_failed)?;
+    let out = encoder.finish().map_err(write_failed)?;
+    out.sync_all().map_err(write_failed)?;
+    Ok(redactor.count)
+}
+
+fn export_to(
+    log: &Path,
+    dest: &Path,
+    secret: &str,
+    token: &TaskToken,
+    progress: &dyn Fn(u64, u64),
+) -> Result<LogExport, CommandError> {
+    let files: Vec<(PathBuf, u64)> = sources(log)
+        .into_iter()
+        .filter_map(|path| Some((path.clone(), fs::metadata(&path).ok()?.len())))
+        .collect();
+    if files.is_empty() {
+        return Err(CommandError::not_found(format!(
+            "no log to export at {}",
+            log.display()
+        )));
+    }
+    let parent = dest
+        .parent()
+        .filter(|parent| parent.is_dir())
+        .ok_or_else(|| CommandError::invalid_input("destination folder does not exist"))?;
+    let original_bytes: u64 = files.iter().map(|(_, len)| len).sum();
+    // Logs shrink a lot under gzip, but nothing guarantees they will.
+    safe_write::ensure_space(parent, original_bytes).map_err(write_failed)?;
+
+    let file_name = dest
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = parent.join(format!(".{file_name}.{}.part", Uuid::new_v4()));
+    let redactions = match compress(&files, &temp, secret, token, &|read| {
+        progress(read, original_bytes)
+    }) {
+        Ok(redactions) => redactions,
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            return Err(err);
+        }
+    };
+    if let Err(err) = fs::rename(&temp, dest) {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("failed saving {}: {err}", dest.display()).into());
+    }
+    let compressed_bytes = fs::metadata(dest).map(|meta| meta.len()).unwrap_or(0);
+    Ok(LogExport {
+        path: dest.to_string_lossy().to_string(),
+        files: files
+            .iter()
+            .map(|(path, _)| path.to_string_lossy().into_owned())
+            .collect(),
+        original_bytes,
+        compressed_bytes,
+        redactions,
+    })
+}
+
+// Async so a long export does not hold up the main thread.
+#[tauri::command]
+pub async fn export_full_log(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    stream: LogStream,
+    dest_path: String,
+    redact_token: Option<bool>,
+    export_id: Option<String>,
+) -> Result<LogExport, CommandError> {
+    storage::ensure_available()?;
+    let (log, secret) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        let log = match stream {
+            LogStream::Backend => PathBuf::from(&runtime.log_path),
+            LogStream::Desktop => desktop_log::desktop_log_path(&runtime.data_dir),
+        };
+        let secret = if redact_token.unwrap_or(true) {
+            runtime.token.clone()
+        } else {
+            String::new()
+        };
+        (log, secret)
+    };
+    let name = log
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let task = state
+        .tasks
+        .register(TaskKind::Export, format!("Exporting {name}"), export_id)?;
+    let progress = |bytes_read: u64, total_bytes: u64| {
+        let _ = app.emit(
+            "log-export-progress",
+            LogExportProgress {
+                id: task.id().to_string(),
+                bytes_read,
+                total_bytes,
+            },
+        );
+    };
+    export_to(
+        &log,
+        Path::new(&dest_path),
+        &secret,
+        task.token(),
+        &progress,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+
+    fn redact_in_pieces(input: &[u8], secret: &str, piece: usize) -> (Vec<u8>, u64) {
+        let mut redactor = Redactor::new(secret);
+        let mut out = Vec::new();
+        for chunk in input.chunks(piece) {
+            redactor.feed(chunk, &mut out);
+        }
+        redactor.finish(&mut out);
+        (out, redactor.count)
+    }
+
+    #[test]
+    fn a_token_split_across_chunks_is_still_redacted() {
+        let input = b"Bearer abc123 then abc12 then abc123abc123 end abc";
+        let expected = b"Bearer [redacted] then abc12 then [redacted][redacted] end abc".to_vec();
+        for piece in 1..input.len() {
+            assert_eq!(
+                redact_in_pieces(input, "abc123", piece),
+                (expected.clone(), 3)
+            );
+        }
+        assert_eq!(redact_in_pieces(input, "", 4).0, input.to_vec());
+    }
+
+    #[test]
+    fn rotated_logs_are_exported_oldest_first() {
+        let dir = std::env::temp_dir().join(format!("liteclaw-log-export-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("backend.log");
+        fs::write(dir.join("backend.log.2"), "two token\n").unwrap();
+        fs::write(dir.join("backend.log.1"), "one\n").unwrap();
+        fs::write(&log, "live token\n").unwrap();
+        let dest = dir.join("backend.log.gz");
+
+        let export = export_to(&log, &dest, "token", &TaskToken::default(), &|_, _| {}).unwrap();
+        assert_eq!(export.files.len(), 3);
+        assert_eq!(export.original_bytes, 25);
+        assert_eq!(export.redactions, 2);
+        let mut text = String::new();
+        GzDecoder::new(File::open(&dest).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "two [redacted]\none\nlive [redacted]\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
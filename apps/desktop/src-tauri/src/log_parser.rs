@@ -0,0 +1,193 @@
+//! Structured view of the desktop's two log formats:
+//!
+//! - `backend.log`: `2024-05-01T12:00:00.123Z [stderr] <backend text>`, where
+//!   the text is either a JSON log record or Python's `LEVEL:name:message`
+//!   (unprefixed when the debug console flag is on).
+//! - `desktop.log`: `<unix seconds> [warn] <message>`.
+//!
+//! Python tracebacks span many lines; they are grouped into one entry.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::backend_output::TRACEBACK_HEADER;
+use crate::timestamps;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl Level {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "debug" | "trace" => Some(Level::Debug),
+            "info" => Some(Level::Info),
+            "warn" | "warning" => Some(Level::Warning),
+            "error" => Some(Level::Error),
+            "critical" | "fatal" => Some(Level::Critical),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSource {
+    Backend,
+    Desktop,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub source: LogSource,
+    /// ISO 8601 when the line carried a timestamp.
+    pub timestamp: Option<String>,
+    /// `None` for lines that carry no level of their own.
+    pub level: Option<Level>,
+    pub message: String,
+    /// Traceback lines, when this entry is a grouped traceback.
+    pub lines: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonRecord {
+    #[serde(alias = "levelname", alias = "severity")]
+    level: Option<String>,
+    #[serde(alias = "msg")]
+    message: Option<String>,
+}
+
+/// Level and message from the backend's own text, whatever format it used.
+fn parse_backend_text(text: &str) -> (Option<Level>, String) {
+    if text.starts_with('{') {
+        if let Ok(record) = serde_json::from_str::<JsonRecord>(text) {
+            let level = record.level.as_deref().and_then(Level::parse);
+            return (level, record.message.unwrap_or_else(|| text.to_string()));
+        }
+    }
+    // `logging.basicConfig` style (`ERROR:uvicorn.error:...`) and uvicorn's
+    // padded `ERROR:    ...`.
+    if let Some((head, rest)) = text.split_once(':') {
+        if let Some(level) = Level::parse(head) {
+            let rest = rest.trim_start();
+            let message = match rest.split_once(':') {
+                Some((logger, message)) if !logger.contains(' ') && !logger.is_empty() => {
+                    message.trim_start()
+                }
+                _ => rest,
+            };
+            return (Some(level), message.to_string());
+        }
+    }
+    (None, text.to_string())
+}
+
+/// Splits `<timestamp> [<label>] <rest>`.
+fn split_prefix(line: &str) -> Option<(&str, &str, &str)> {
+    let (timestamp, rest) = line.split_once(' ')?;
+    let rest = rest.strip_prefix('[')?;
+    let (label, rest) = rest.split_once(']')?;
+    Some((timestamp, label, rest.strip_prefix(' ').unwrap_or(rest)))
+}
+
+/// Desktop log lines carry RFC 3339; versions before that wrote unix
+/// seconds.
+fn desktop_timestamp(text: &str) -> Option<String> {
+    match text.parse::<u64>() {
+        Ok(secs) => Some(timestamps::from_unix_secs(secs)),
+        Err(_) => timestamps::parse(text).map(timestamps::rfc3339),
+    }
+}
+
+pub fn parse_desktop_line(line: &str) -> Option<LogEntry> {
+    let (timestamp, level, message) = split_prefix(line)?;
+    Some(LogEntry {
+        source: LogSource::Desktop,
+        timestamp: desktop_timestamp(timestamp),
+        level: Level::parse(level),
+        message: message.to_string(),
+        lines: Vec::new(),
+    })
+}
+
+/// One backend line, without traceback grouping.
+pub fn parse_backend_line(line: &str) -> LogEntry {
+    let (timestamp, text) = match split_prefix(line) {
+        Some((timestamp, "stdout" | "stderr", text)) => (Some(timestamp.to_string()), text),
+        _ => (None, line),
+    };
+    let (level, message) = parse_backend_text(text);
+    LogEntry {
+        source: LogSource::Backend,
+        timestamp,
+        level,
+        message,
+        lines: Vec::new(),
+    }
+}
+
+/// Parses a backend log, folding each traceback into a single `Error` entry
+/// whose message is the final exception line.
+pub fn parse_backend_log(content: &str) -> Vec<LogEntry> {
+    let mut entries = Vec::new();
+    let mut traceback: Option<LogEntry> = None;
+    for line in content.lines() {
+        let entry = parse_backend_line(line);
+        if let Some(mut open) = traceback.take() {
+            let continues = entry.message.starts_with(char::is_whitespace);
+            open.lines.push(entry.message.clone());
+            if continues || entry.message.is_empty() {
+                traceback = Some(open);
+                continue;
+            }
+            open.message = entry.message.trim().to_string();
+            entries.push(open);
+            continue;
+        }
+        if entry.message.starts_with(TRACEBACK_HEADER) {
+            traceback = Some(LogEntry {
+                level: Some(Level::Error),
+                lines: vec![entry.message.clone()],
+                ..entry
+            });
+            continue;
+        }
+        entries.push(entry);
+    }
+    // A traceback cut off by the end of the file still counts.
+    if let Some(open) = traceback {
+        entries.push(open);
+    }
+    entries
+}
+
+pub fn parse_desktop_log(content: &str) -> Vec<LogEntry> {
+    content.lines().filter_map(parse_desktop_line).collect()
+}
+
+/// The last `max_bytes` of a file, starting at a line boundary.
+pub fn read_tail(path: &Path, max_bytes: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    if start > 0 {
+        // Drop the partial first line.
+        let cut = bytes
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(bytes.len(), |index| index + 1);
+        bytes.drain(..cut);
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
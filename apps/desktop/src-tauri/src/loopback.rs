@@ -0,0 +1,145 @@
+//! Which loopback address the backend is reached on. By default health polls
+//! try both `127.0.0.1` and `[::1]` and `base_url` records whichever answered,
+//! since some uvicorn setups bind IPv6 only. `loopback_host` in config pins a
+//! single host instead, e.g. `localhost` for a backend inside WSL reached via
+//! port forwarding; it is also passed to the backend as `LITECLAW_HOST`.
+
+use serde::Serialize;
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::ops::Range;
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{commit_config, read_local_config, AppState, LocalConfig};
+
+const DEFAULT_HOSTS: [&str; 2] = ["127.0.0.1", "::1"];
+const PORT_RANGE: Range<u16> = 8765..8865;
+
+/// Hosts to probe, in order.
+pub fn candidates(config: &LocalConfig) -> Vec<String> {
+    match &config.loopback_host {
+        Some(host) => vec![host.clone()],
+        None => DEFAULT_HOSTS.iter().map(|host| host.to_string()).collect(),
+    }
+}
+
+/// `http://host:` with IPv6 literals bracketed.
+fn origin_prefix(host: &str) -> String {
+    if host.contains(':') {
+        format!("http://[{host}]:")
+    } else {
+        format!("http://{host}:")
+    }
+}
+
+pub fn base_url(host: &str, port: u16) -> String {
+    format!("{}{port}", origin_prefix(host))
+}
+
+fn addresses(host: &str, port: u16) -> Vec<SocketAddr> {
+    (host, port)
+        .to_socket_addrs()
+        .map(Iterator::collect)
+        .unwrap_or_default()
+}
+
+/// Free on every address the candidates resolve to. An address family the
+/// machine does not have (no IPv6 loopback) is skipped rather than failing
+/// every port.
+fn port_is_free(hosts: &[String], port: u16) -> bool {
+    let mut bound_any = false;
+    for addr in hosts.iter().flat_map(|host| addresses(host, port)) {
+        match TcpListener::bind(addr) {
+            Ok(_) => bound_any = true,
+            Err(err) if err.kind() == io::ErrorKind::AddrNotAvailable => {}
+            Err(_) => return false,
+        }
+    }
+    bound_any
+}
+
+/// The first free port in the range, passing over `avoid`.
+pub fn find_open_port(hosts: &[String], avoid: &[u16]) -> Result<u16, String> {
+    PORT_RANGE
+        .clone()
+        .find(|port| !avoid.contains(port) && port_is_free(hosts, *port))
+        .ok_or_else(|| {
+            format!(
+                "no open port found in {}-{} on {}",
+                PORT_RANGE.start,
+                PORT_RANGE.end - 1,
+                hosts.join(", ")
+            )
+        })
+}
+
+/// A bare host name or IP; no scheme, port or path.
+pub fn validate_host(host: &str) -> Result<(), CommandError> {
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    let valid = !literal.is_empty()
+        && literal.len() <= 253
+        && (literal.parse::<std::net::IpAddr>().is_ok()
+            || literal
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.'));
+    if !valid {
+        return Err(CommandError::invalid_input(format!(
+            "invalid loopback host `{host}`: give a host name or IP address without scheme or port"
+        )));
+    }
+    // The backend binds this host; a wildcard would expose it to the network.
+    if literal
+        .parse::<std::net::IpAddr>()
+        .is_ok_and(|ip| ip.is_unspecified())
+    {
+        return Err(CommandError::invalid_input(format!(
+            "`{host}` listens on every interface; use a loopback or forwarded address"
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoopbackUpdate {
+    pub config: LocalConfig,
+    /// The running backend is still reached on the previous host.
+    pub restart_pending: bool,
+}
+
+/// `None` or an empty string goes back to probing both loopback addresses.
+#[tauri::command]
+pub fn set_loopback_host(
+    state: State<'_, AppState>,
+    host: Option<String>,
+) -> Result<ConfigChange<LoopbackUpdate>, CommandError> {
+    let host = host
+        .map(|host| {
+            host.trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string()
+        })
+        .filter(|host| !host.is_empty());
+    if let Some(host) = &host {
+        validate_host(host)?;
+    }
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.loopback_host = host;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    let reachable_now = candidates(&config)
+        .iter()
+        .any(|host| runtime.base_url.starts_with(&origin_prefix(host)));
+    Ok(ConfigChange::new(
+        LoopbackUpdate {
+            restart_pending: runtime.backend_child.is_some() && !reachable_now,
+            config,
+        },
+        diff,
+    ))
+}
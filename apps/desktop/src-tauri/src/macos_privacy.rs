@@ -0,0 +1,163 @@
+//! Detection of macOS privacy (TCC) blocks on allowed folders. Other platforms
+//! report `not_applicable` so callers never need to special-case them.
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::{read_local_config, AppState, LocalConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub enum PrivacyState {
+    NotApplicable,
+    Ok,
+    LikelyBlocked,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtectedLocation {
+    pub path: String,
+    pub accessible: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivacyStatus {
+    pub state: PrivacyState,
+    pub blocked_folders: Vec<String>,
+    pub protected_locations: Vec<ProtectedLocation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenPrivacySettingsResult {
+    pub state: PrivacyState,
+    pub opened: bool,
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use super::{PrivacyState, PrivacyStatus, ProtectedLocation};
+    use crate::error::CommandError;
+    use crate::folder_access::protected_roots;
+    use crate::LocalConfig;
+
+    fn is_tcc_denial(err: &io::Error) -> bool {
+        err.raw_os_error() == Some(libc::EPERM)
+    }
+
+    fn accessible(path: &Path) -> bool {
+        let result = fs::metadata(path).and_then(|_| fs::read_dir(path).map(|_| ()));
+        !matches!(result, Err(ref err) if is_tcc_denial(err))
+    }
+
+    pub fn probe(config: &LocalConfig) -> PrivacyStatus {
+        let roots = protected_roots();
+        let mut relevant: Vec<PathBuf> = Vec::new();
+        let mut blocked_folders = Vec::new();
+        for folder in &config.allowed_folders {
+            let folder_path = Path::new(&folder.path);
+            let mut folder_blocked = !accessible(folder_path);
+            for root in &roots {
+                if folder_path.starts_with(root) || root.starts_with(folder_path) {
+                    if !relevant.contains(root) {
+                        relevant.push(root.clone());
+                    }
+                    // A folder spanning a protected root (e.g. all of $HOME)
+                    // silently loses that subtree.
+                    if root.starts_with(folder_path) && !accessible(root) {
+                        folder_blocked = true;
+                    }
+                }
+            }
+            if folder_blocked {
+                blocked_folders.push(folder.path.clone());
+            }
+        }
+        let protected_locations: Vec<ProtectedLocation> = relevant
+            .iter()
+            .map(|root| ProtectedLocation {
+                path: root.to_string_lossy().to_string(),
+                accessible: accessible(root),
+            })
+            .collect();
+        let state = if blocked_folders.is_empty() {
+            PrivacyState::Ok
+        } else {
+            PrivacyState::LikelyBlocked
+        };
+        PrivacyStatus {
+            state,
+            blocked_folders,
+            protected_locations,
+        }
+    }
+
+    pub fn open_pane(pane: &str) -> Result<(), CommandError> {
+        let anchor = match pane {
+            "full_disk_access" => "Privacy_AllFiles",
+            "files_and_folders" => "Privacy_FilesAndFolders",
+            "screen_recording" => "Privacy_ScreenCapture",
+            other => {
+                return Err(CommandError::invalid_input(format!(
+                    "unknown privacy settings pane: {other}"
+                )))
+            }
+        };
+        let url = format!("x-apple.systempreferences:com.apple.preference.security?{anchor}");
+        Command::new("open")
+            .arg(url)
+            .status()
+            .map_err(|e| format!("failed opening System Settings: {e}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn probe(config: &LocalConfig) -> PrivacyStatus {
+    platform::probe(config)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn probe(_config: &LocalConfig) -> PrivacyStatus {
+    PrivacyStatus {
+        state: PrivacyState::NotApplicable,
+        blocked_folders: Vec::new(),
+        protected_locations: Vec::new(),
+    }
+}
+
+#[tauri::command]
+pub fn get_macos_privacy_status(state: State<'_, AppState>) -> Result<PrivacyStatus, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let config = read_local_config(&runtime.data_dir)?;
+    Ok(probe(&config))
+}
+
+#[tauri::command]
+pub fn open_privacy_settings(pane: String) -> Result<OpenPrivacySettingsResult, CommandError> {
+    #[cfg(target_os = "macos")]
+    {
+        platform::open_pane(&pane)?;
+        Ok(OpenPrivacySettingsResult {
+            state: PrivacyState::Ok,
+            opened: true,
+        })
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = pane;
+        Ok(OpenPrivacySettingsResult {
+            state: PrivacyState::NotApplicable,
+            opened: false,
+        })
+    }
+}
@@ -1,18 +1,166 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File, OpenOptions};
-use std::net::TcpListener;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, Instant};
-use tauri::{Manager, State};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::webview::PageLoadEvent;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_deep_link::DeepLinkExt;
 use uuid::Uuid;
 
+mod action_queue;
+mod api_version;
+mod app_info;
+mod attachments;
+mod audit;
+mod backend_debug;
+mod backend_env;
+mod backend_error;
+mod backend_http;
+mod backend_output;
+mod backend_status;
+mod backend_transport;
+mod backend_update;
+mod backups;
+mod benchmark;
+mod bootstrap;
+mod cli;
+mod clipboard;
+mod config_crypto;
+mod config_diff;
+mod config_validation;
+mod confirmation;
+mod conversation_export;
+mod data_dir_lock;
+mod deeplink;
+mod desktop_log;
+mod diagnostics;
+mod discovery;
+mod download;
+mod error;
+mod excluded_dirs;
+mod fault_injection;
+mod file_ops;
+mod folder_access;
+mod folder_fingerprint;
+mod folder_listing;
+mod folder_location;
+mod folder_preview;
+mod folders;
+mod heartbeat;
+mod history_search;
+mod ignore_rules;
+mod integrations;
+mod integrity;
+mod janitor;
+mod layout;
+mod legacy_config;
+mod locale;
+mod log_export;
+mod log_parser;
+mod loopback;
+mod macos_privacy;
+mod metrics;
+mod migrations;
+mod model_settings;
+mod native_messaging;
+mod onboarding;
+mod paths;
+mod performance;
+mod permissions;
+mod profiles;
+mod proxy;
+mod recent_errors;
+mod reload_limiter;
+mod request_limiter;
+mod response_limit;
+mod safe_write;
+mod screenshot;
+mod seamless_restart;
+mod self_check;
+mod session_file;
+mod shell_policy;
+mod shutdown;
+mod slow_commands;
+mod spawn_guard;
+mod startup;
+mod startup_phase;
+mod status_server;
+mod status_summary;
+mod storage;
+mod supervisor;
+mod system_events;
+mod tasks;
+mod telemetry;
+mod temporary_folders;
+mod timestamps;
+mod traceback_hint;
+mod tray;
+mod ui_state;
+mod unread;
+mod upload;
+mod wsl;
+
+use action_queue::{ActionQueue, QueuedAction};
+use api_version::ApiVersion;
+use attachments::AttachmentsConfig;
+use backend_env::EnvSettings;
+use backend_error::{BackendError, BackendErrorKind};
+use backend_http::HangDetectionConfig;
+use backend_output::{LineFormat, LogSink, StartupSignal, Stream};
+use backend_status::{BackendStatus, ConnectionInfo};
+use backend_transport::{BackendTransport, UreqTransport};
+use backups::BackupsConfig;
+use config_diff::{ConfigChange, ConfigDiff};
+use confirmation::{Confirmable, Summary};
+use deeplink::{DeepLinkAction, PendingDeepLink};
+use discovery::DiscoveryInfo;
+use error::{CommandError, ErrorCode};
+use folder_location::LocationKind;
+use folders::{AllowedFolder, FolderSortOrder, SymlinkInfo};
+use heartbeat::HealthInfo;
+use integrations::IntegrationStatus;
+use migrations::MigrationKind;
+use model_settings::ModelSelection;
+use native_messaging::NativeMessagingConfig;
+use onboarding::OnboardingConfig;
+use performance::{AppliedPerformance, PerformanceConfig};
+use proxy::{ProxyConfig, ResolvedProxy};
+use reload_limiter::{Admission, ReloadLimiter};
+use self_check::SelfCheckReport;
+use session_file::{OpenedSession, SessionOpenError};
+use spawn_guard::SpawnGuard;
+use startup::{SpawnTimings, StartupReport};
+use status_server::StatusServerConfig;
+use supervisor::{CrashLoopConfig, CrashTracker};
+use telemetry::TelemetryConfig;
+use wsl::{BackendHost, WslTarget};
+
+const PYTHON_BIN: &str = "python";
+
+/// Bumped on every successful config write. The backend echoes the last
+/// generation it applied so a stale backend can be detected.
+static CONFIG_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn config_generation() -> u64 {
+    CONFIG_GENERATION.load(Ordering::SeqCst)
+}
+
 struct AppState {
     runtime: Mutex<BackendRuntime>,
+    tasks: tasks::TaskRegistry,
+    startup: Mutex<StartupReport>,
+    integrations: Mutex<IntegrationStatus>,
+    /// Tokens for the second step of destructive commands.
+    confirmations: confirmation::Confirmations,
 }
 
 struct BackendRuntime {
@@ -20,225 +168,1059 @@ struct BackendRuntime {
     base_url: String,
     log_path: String,
     backend_ready: bool,
-    last_error: Option<String>,
+    /// Full detail; `ApiConfig` only gets its kind and summary.
+    last_error: Option<BackendError>,
     data_dir: PathBuf,
+    /// Name of the profile `data_dir` belongs to.
+    profile: String,
     backend_child: Option<Child>,
+    stopped_by_user: bool,
+    safe_mode: bool,
+    self_check: Option<SelfCheckReport>,
+    pending_sessions: Vec<OpenedSession>,
+    session_errors: Vec<SessionOpenError>,
+    launch_deeplinks: Vec<DeepLinkAction>,
+    pending_deeplinks: Vec<PendingDeepLink>,
+    /// Backend calls waiting for the backend; see `action_queue`.
+    pending_actions: ActionQueue,
+    /// Step durations of the last successful `spawn_backend`.
+    spawn_timings: Option<SpawnTimings>,
+    reload_limiter: Arc<ReloadLimiter>,
+    backend_config_generation: Option<u64>,
+    out_of_sync_beats: u32,
+    /// Heartbeat probes in a row that got no answer; see `heartbeat`.
+    failed_health_beats: u32,
+    /// Health passes but real requests keep timing out.
+    backend_degraded: bool,
+    /// Proxy variables the running backend was started with.
+    backend_proxy: Option<ResolvedProxy>,
+    crash_tracker: CrashTracker,
+    /// Environment the running backend was started with.
+    backend_env: Option<EnvSettings>,
+    /// Priority settings the running backend got.
+    backend_performance: Option<AppliedPerformance>,
+    /// What `discovery.json` currently advertises.
+    discovery: Option<DiscoveryInfo>,
+    /// Set while the running backend is inside WSL; its paths are translated.
+    backend_wsl: Option<WslTarget>,
+    /// Negotiated with the running backend at spawn.
+    backend_api: ApiVersion,
+    /// The last start was refused because the backend shares no API version.
+    backend_incompatible: bool,
+}
+
+impl BackendRuntime {
+    fn new(data_dir: PathBuf, profile: String) -> Self {
+        Self {
+            token: String::new(),
+            base_url: String::new(),
+            log_path: backend_log_path(&data_dir).to_string_lossy().to_string(),
+            backend_ready: false,
+            last_error: None,
+            data_dir,
+            profile,
+            backend_child: None,
+            stopped_by_user: false,
+            safe_mode: false,
+            self_check: None,
+            pending_sessions: Vec::new(),
+            session_errors: Vec::new(),
+            launch_deeplinks: Vec::new(),
+            pending_deeplinks: Vec::new(),
+            pending_actions: ActionQueue::default(),
+            spawn_timings: None,
+            reload_limiter: Arc::new(ReloadLimiter::new()),
+            backend_config_generation: None,
+            out_of_sync_beats: 0,
+            failed_health_beats: 0,
+            backend_degraded: false,
+            backend_proxy: None,
+            crash_tracker: CrashTracker::default(),
+            backend_env: None,
+            backend_performance: None,
+            discovery: None,
+            backend_wsl: None,
+            backend_api: ApiVersion::default(),
+            backend_incompatible: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum BackendState {
+    Ready,
+    Degraded,
+    /// A spawn holds the start gate and is waiting for health.
+    Starting,
+    Stopped,
+    StoppedByUser,
+    Errored,
 }
 
 #[derive(Serialize)]
+struct FolderAddition {
+    config: LocalConfig,
+    symlink: Option<SymlinkInfo>,
+    /// Set when the folder looks large enough to be slow to index; see
+    /// `folder_preview`.
+    size_warning: Option<String>,
+    /// A read-only mount or FUSE filesystem is worth a warning; see
+    /// `folder_location`.
+    location_kind: LocationKind,
+}
+
+#[derive(Serialize)]
+struct FolderRemoval {
+    config: LocalConfig,
+    undo_token: Option<String>,
+    /// Conversations whose working folder this was; see `ui_state`.
+    unbound_conversations: Vec<String>,
+}
+
+/// `ConnectionInfo` and `BackendStatus` flattened together, as
+/// `get_api_config` and `backend-state-changed` have always sent them.
+#[derive(Clone, Serialize)]
 struct ApiConfig {
-    base_url: String,
-    token: String,
-    backend_ready: bool,
-    last_error: Option<String>,
-    log_path: String,
+    #[serde(flatten)]
+    connection: ConnectionInfo,
+    #[serde(flatten)]
+    status: BackendStatus,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 struct ShellConfig {
     enabled: bool,
+    /// Command-line patterns that run without asking; see `shell_policy`.
+    auto_approve_patterns: Vec<String>,
+    /// Command-line patterns that never run, whatever else matches.
+    always_deny_patterns: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 struct LocalConfig {
-    allowed_folders: Vec<String>,
+    #[serde(deserialize_with = "folders::deserialize_allowed_folders")]
+    allowed_folders: Vec<AllowedFolder>,
     shell: ShellConfig,
     history_enabled: bool,
+    auto_start_backend: bool,
+    attachments: AttachmentsConfig,
+    allow_clipboard_capture: bool,
+    clipboard_max_chars: usize,
+    allow_screen_capture: bool,
+    /// Writes backend output to the log verbatim, without timestamps.
+    debug_console: bool,
+    hang_detection: HangDetectionConfig,
+    proxy: ProxyConfig,
+    telemetry: TelemetryConfig,
+    crash_loop: CrashLoopConfig,
+    onboarding: OnboardingConfig,
+    /// Extra variable names inherited by the backend; see `backend_env`.
+    env_passthrough: Vec<String>,
+    env_extra: BTreeMap<String, String>,
+    performance: PerformanceConfig,
+    /// Pins the host the backend is reached on; `None` probes both loopbacks.
+    loopback_host: Option<String>,
+    backend_host: BackendHost,
+    /// WSL distro for `backend_host: wsl`; `None` uses the default distro.
+    wsl_distro: Option<String>,
+    /// Runs a bundled backend that fails the integrity check, with a warning.
+    /// For developers patching an installed copy.
+    allow_modified_backend: bool,
+    /// Release manifest polled by `check_backend_update`; `None` disables it.
+    backend_update_url: Option<String>,
+    /// Also hides what each allowed folder's top-level `.gitignore` lists.
+    respect_gitignore: bool,
+    /// Order of `allowed_folders`.
+    sort_order: FolderSortOrder,
+    /// Stores config.json encrypted; see `config_crypto`.
+    encrypt_config: bool,
+    /// Browser extension bridge; see `native_messaging`.
+    native_messaging: NativeMessagingConfig,
+    /// Loopback `/status` and `/metrics` for monitoring; see `status_server`.
+    status_server: StatusServerConfig,
+    /// Scheduled zip snapshots of config and history; see `backups`.
+    backups: BackupsConfig,
+    /// Restarts by starting a replacement before stopping the running
+    /// backend, at the cost of running two for a while; see `seamless_restart`.
+    seamless_restart: bool,
+    /// Commands slower than this are logged; 0 turns it off. See
+    /// `slow_commands`.
+    slow_command_ms: u64,
+    /// Command results whose JSON is larger are spilled to a file; 0 turns
+    /// it off. See `response_limit`.
+    max_response_bytes: u64,
+    /// Language tag the assistant answers in; see `locale`.
+    locale: Option<String>,
+    /// Last model choice the backend reported; see `model_settings`.
+    model_selection: Option<ModelSelection>,
 }
 
 impl Default for LocalConfig {
     fn default() -> Self {
         Self {
             allowed_folders: Vec::new(),
-            shell: ShellConfig { enabled: false },
+            shell: ShellConfig::default(),
             history_enabled: true,
+            auto_start_backend: true,
+            attachments: AttachmentsConfig::default(),
+            allow_clipboard_capture: false,
+            clipboard_max_chars: clipboard::DEFAULT_MAX_TEXT_CHARS,
+            allow_screen_capture: false,
+            debug_console: false,
+            hang_detection: HangDetectionConfig::default(),
+            proxy: ProxyConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            crash_loop: CrashLoopConfig::default(),
+            onboarding: OnboardingConfig::default(),
+            env_passthrough: Vec::new(),
+            env_extra: BTreeMap::new(),
+            performance: PerformanceConfig::default(),
+            loopback_host: None,
+            backend_host: BackendHost::Native,
+            wsl_distro: None,
+            allow_modified_backend: false,
+            backend_update_url: None,
+            respect_gitignore: false,
+            sort_order: FolderSortOrder::Natural,
+            encrypt_config: false,
+            native_messaging: NativeMessagingConfig::default(),
+            status_server: StatusServerConfig::default(),
+            backups: BackupsConfig::default(),
+            seamless_restart: false,
+            slow_command_ms: slow_commands::DEFAULT_THRESHOLD_MS,
+            max_response_bytes: response_limit::DEFAULT_MAX_BYTES,
+            locale: None,
+            model_selection: None,
         }
     }
 }
 
+fn backend_state(runtime: &BackendRuntime) -> BackendState {
+    if runtime.backend_ready && runtime.backend_degraded {
+        BackendState::Degraded
+    } else if runtime.backend_ready {
+        BackendState::Ready
+    } else if spawn_guard::is_starting() {
+        BackendState::Starting
+    } else if runtime.stopped_by_user {
+        BackendState::StoppedByUser
+    } else if runtime.last_error.is_some() {
+        BackendState::Errored
+    } else {
+        BackendState::Stopped
+    }
+}
+
+fn config_in_sync(runtime: &BackendRuntime) -> bool {
+    runtime.backend_config_generation == Some(config_generation())
+}
+
+fn api_config(runtime: &BackendRuntime) -> ApiConfig {
+    let api = ApiConfig {
+        connection: backend_status::connection_info(runtime),
+        status: backend_status::backend_status(runtime),
+    };
+    bootstrap::remember(&runtime.data_dir, &api);
+    api
+}
+
+/// Deprecated in favour of `get_connection_info` and `get_backend_status`;
+/// kept for one release.
 #[tauri::command]
 fn get_api_config(state: State<'_, AppState>) -> ApiConfig {
     let runtime = state.runtime.lock().expect("runtime lock poisoned");
-    ApiConfig {
-        base_url: runtime.base_url.clone(),
-        token: runtime.token.clone(),
-        backend_ready: runtime.backend_ready,
-        last_error: runtime.last_error.clone(),
-        log_path: runtime.log_path.clone(),
-    }
+    api_config(&runtime)
+}
+
+fn unix_now_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
 }
 
-fn config_path(data_dir: &PathBuf) -> PathBuf {
-    data_dir.join("config.json")
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
-fn write_config_atomic(data_dir: &PathBuf, config: &LocalConfig) -> Result<(), String> {
-    fs::create_dir_all(data_dir).map_err(|e| format!("failed creating data dir: {e}"))?;
+/// The active profile's data dir, for background threads that outlive a
+/// profile switch. `None` before `setup` has managed the state.
+fn current_data_dir(app: &AppHandle) -> Option<PathBuf> {
+    let state = app.try_state::<AppState>()?;
+    let runtime = state.runtime.lock().ok()?;
+    Some(runtime.data_dir.clone())
+}
+
+fn config_path(data_dir: &Path) -> PathBuf {
+    paths::for_io(&layout::config_dir(data_dir).join("config.json"))
+}
+
+/// Writes `config.json` via a temp file. Errors carry the step that failed so
+/// callers can tell an outage apart from a bad write.
+fn write_config_file(
+    data_dir: &Path,
+    config: &LocalConfig,
+) -> Result<(), (&'static str, io::Error)> {
+    // Recreating a vanished data dir would hide the outage and scatter files
+    // onto whatever is now at that path.
+    if !data_dir.is_dir() && storage::has_cached_config() {
+        return Err(("data dir is gone", io::ErrorKind::NotFound.into()));
+    }
+    fs::create_dir_all(data_dir).map_err(|e| ("failed creating data dir", e))?;
+    if let Some(err) = fault_injection::config_write_error() {
+        return Err(("failed writing temp config", err));
+    }
     let path = config_path(data_dir);
-    let temp = path.with_extension("tmp");
-    let bytes =
-        serde_json::to_vec_pretty(config).map_err(|e| format!("failed serializing config: {e}"))?;
-    fs::write(&temp, bytes).map_err(|e| format!("failed writing temp config: {e}"))?;
-    if path.exists() {
-        fs::remove_file(&path).map_err(|e| format!("failed removing old config: {e}"))?;
-    }
-    fs::rename(&temp, &path).map_err(|e| format!("failed replacing config: {e}"))?;
+    if let Some(config_dir) = path.parent() {
+        fs::create_dir_all(config_dir).map_err(|e| ("failed creating config dir", e))?;
+    }
+    let bytes = serde_json::to_vec_pretty(config)
+        .map_err(|e| ("failed serializing config", io::Error::other(e)))?;
+    let bytes = config_crypto::encode(bytes, config.encrypt_config)
+        .map_err(|e| ("failed encrypting config", io::Error::other(e.message)))?;
+    safe_write::replace(&path, &bytes).map_err(|e| ("failed writing config", e))
+}
+
+fn write_config_atomic(data_dir: &Path, config: &LocalConfig) -> Result<(), CommandError> {
+    data_dir_lock::ensure_writable()?;
+    if storage::is_unavailable() {
+        storage::buffer_config(config)?;
+    } else {
+        match write_config_file(data_dir, config) {
+            Ok(()) => storage::remember_config(config),
+            Err((_, err)) if storage::is_outage(data_dir, &err) => {
+                storage::enter_outage();
+                storage::buffer_config(config)?;
+            }
+            Err((step, err)) if safe_write::is_disk_full(&err) => {
+                return Err(CommandError::new(ErrorCode::DiskFull, format!("{step}: {err}")));
+            }
+            Err((step, err)) => return Err(format!("{step}: {err}").into()),
+        }
+    }
+    CONFIG_GENERATION.fetch_add(1, Ordering::SeqCst);
     Ok(())
 }
 
-fn ensure_config_exists(data_dir: &PathBuf) -> Result<(), String> {
+/// Writes a config changed by a command, audits what changed and returns the
+/// diff for the command's result.
+fn commit_config(data_dir: &Path, config: &LocalConfig) -> Result<ConfigDiff, CommandError> {
+    let previous = read_local_config(data_dir).unwrap_or_default();
+    write_config_atomic(data_dir, config)?;
+    let diff = config_diff::diff(&previous, config);
+    if !diff.is_empty() {
+        let _ = audit::record(data_dir, "config_changed", serde_json::json!(diff));
+    }
+    Ok(diff)
+}
+
+fn ensure_config_exists(data_dir: &Path) -> Result<(), CommandError> {
     let path = config_path(data_dir);
-    if path.exists() {
+    // A read-only run leaves even a missing config to the owner.
+    if path.exists() || data_dir_lock::is_read_only() {
         return Ok(());
     }
-    write_config_atomic(data_dir, &LocalConfig::default())
+    let config = LocalConfig {
+        locale: locale::detect(),
+        ..LocalConfig::default()
+    };
+    write_config_atomic(data_dir, &config)
 }
 
-fn read_local_config(data_dir: &PathBuf) -> Result<LocalConfig, String> {
+/// Serves the in-memory config while the data dir is unavailable.
+fn read_local_config(data_dir: &Path) -> Result<LocalConfig, CommandError> {
+    if storage::is_unavailable() {
+        return storage::cached_config();
+    }
+    if !data_dir.is_dir() && storage::has_cached_config() {
+        storage::enter_outage();
+        return storage::cached_config();
+    }
     ensure_config_exists(data_dir)?;
-    let path = config_path(data_dir);
-    let content = fs::read_to_string(path).map_err(|e| format!("failed reading config: {e}"))?;
-    serde_json::from_str::<LocalConfig>(&content).map_err(|e| format!("invalid config json: {e}"))
+    let content = match fs::read_to_string(config_path(data_dir)) {
+        Ok(content) => content,
+        Err(err) if storage::is_outage(data_dir, &err) => {
+            storage::enter_outage();
+            return storage::cached_config();
+        }
+        Err(err) => return Err(format!("failed reading config: {err}").into()),
+    };
+    let content = config_crypto::decode(content)?;
+    let config = serde_json::from_str::<LocalConfig>(&content)
+        .map_err(|e| format!("invalid config json: {e}"))?;
+    storage::remember_config(&config);
+    Ok(config)
 }
 
 fn normalize_folder(path: &str) -> Result<String, String> {
-    let raw = PathBuf::from(path);
-    if !raw.exists() || !raw.is_dir() {
-        return Err(format!("not a folder: {path}"));
+    paths::canonical_dir(path)
+}
+
+/// Folder paths are given as the backend sees them, so a WSL backend gets
+/// `/mnt/c/...` rather than `C:\...`.
+fn reload_payload(
+    data_dir: &Path,
+    config: &LocalConfig,
+    wsl: Option<&WslTarget>,
+) -> serde_json::Value {
+    // The timer revokes lapsed grants; until it does they are left out here.
+    let lapsed = temporary_folders::lapsed(data_dir);
+    let folders: Vec<serde_json::Value> = config
+        .allowed_folders
+        .iter()
+        .filter(|folder| !lapsed.contains(&folder.path))
+        .map(|folder| {
+            let resolved = folder.effective_path().to_string_lossy().into_owned();
+            serde_json::json!({
+                "path": wsl::backend_path(&folder.path, wsl),
+                "resolved_path": wsl::backend_path(&resolved, wsl),
+                "alias": folder.alias,
+                "mode": folder.mode,
+                "ignore_patterns": folder.ignore_patterns,
+            })
+        })
+        .collect();
+    // The backend only knows its own profile's dirs; these cover the rest.
+    let excluded: Vec<String> = excluded_dirs::excluded_dirs(data_dir)
+        .iter()
+        .map(|dir| wsl::backend_path(&dir.to_string_lossy(), wsl))
+        .collect();
+    let mut payload = serde_json::json!({
+        "allowed_folders": folders,
+        "excluded_paths": excluded,
+        "respect_gitignore": config.respect_gitignore,
+        "locale": config.locale,
+        "config_generation": config_generation(),
+    });
+    // The backend cannot read an encrypted config.json, so it gets the config
+    // itself, with folder paths as it sees them.
+    if config.encrypt_config {
+        let mut full = serde_json::to_value(config).unwrap_or_default();
+        full["allowed_folders"] = payload["allowed_folders"].clone();
+        payload["config"] = full;
     }
-    let canonical = raw
-        .canonicalize()
-        .map_err(|e| format!("failed to canonicalize folder {path}: {e}"))?;
-    Ok(canonical.to_string_lossy().to_string())
+    payload
 }
 
-fn backend_reload_config(runtime: &BackendRuntime) -> Result<(), String> {
-    if !runtime.backend_ready {
-        return Err("backend is not ready".to_string());
-    }
-    let url = format!("{}/v1/config/reload", runtime.base_url);
-    let response = ureq::post(&url)
-        .set("Authorization", &format!("Bearer {}", runtime.token))
-        .set("Content-Type", "application/json")
-        .send_string("{}");
-    match response {
+#[allow(clippy::result_large_err)]
+fn post_reload(
+    transport: &dyn BackendTransport,
+    base_url: &str,
+    token: &str,
+    data_dir: &Path,
+    config: &LocalConfig,
+    wsl: Option<&WslTarget>,
+    api: ApiVersion,
+) -> Result<(), CommandError> {
+    metrics::increment(&metrics::METRICS.backend_reloads);
+    let payload = api_version::reload_body(api, reload_payload(data_dir, config, wsl)).to_string();
+    let sent = backend_http::retry_idempotent(backend_transport::RELOAD, |key| {
+        transport.reload(base_url, token, &payload, key)
+    });
+    backend_http::rejected(&sent.result)?;
+    let note = sent.attempts_note();
+    match sent.result {
         Ok(resp) if resp.status() == 200 => Ok(()),
-        Ok(resp) => Err(format!("backend config reload failed: HTTP {}", resp.status())),
-        Err(err) => Err(format!("backend config reload failed: {err}")),
+        Ok(resp) => {
+            Err(format!("backend config reload failed: HTTP {}{note}", resp.status()).into())
+        }
+        Err(ureq::Error::Status(422, _)) => Err(api_version::incompatible(format!(
+            "backend rejected the API v{} config reload",
+            api.number()
+        ))),
+        Err(err) => Err(format!("backend config reload failed{note}: {err}").into()),
+    }
+}
+
+/// Rate-limited reload. Calls over budget return immediately and are folded
+/// into a single trailing reload that re-reads config from disk. While the
+/// backend is not ready the reload is queued and sent once it is, so every
+/// config change reaches a backend that is still starting.
+fn backend_reload_config(
+    runtime: &BackendRuntime,
+    config: &LocalConfig,
+) -> Result<(), CommandError> {
+    reload_config_via(&UreqTransport, runtime, config)
+}
+
+fn reload_config_via(
+    transport: &'static dyn BackendTransport,
+    runtime: &BackendRuntime,
+    config: &LocalConfig,
+) -> Result<(), CommandError> {
+    if !runtime.backend_ready {
+        runtime.pending_actions.enqueue(QueuedAction::ReloadConfig)?;
+        return Ok(());
+    }
+    match runtime.reload_limiter.admit() {
+        Admission::Now => post_reload(
+            transport,
+            &runtime.base_url,
+            &runtime.token,
+            &runtime.data_dir,
+            config,
+            runtime.backend_wsl.as_ref(),
+            runtime.backend_api,
+        ),
+        Admission::Coalesced => {
+            metrics::increment(&metrics::METRICS.backend_reloads_coalesced);
+            Ok(())
+        }
+        Admission::Trailing(delay) => {
+            metrics::increment(&metrics::METRICS.backend_reloads_coalesced);
+            let limiter = runtime.reload_limiter.clone();
+            let (base_url, token) = (runtime.base_url.clone(), runtime.token.clone());
+            let data_dir = runtime.data_dir.clone();
+            let wsl = runtime.backend_wsl.clone();
+            let api = runtime.backend_api;
+            thread::spawn(move || {
+                thread::sleep(delay);
+                limiter.take_trailing();
+                if let Ok(latest) = read_local_config(&data_dir) {
+                    let wsl = wsl.as_ref();
+                    let _ = post_reload(transport, &base_url, &token, &data_dir, &latest, wsl, api);
+                }
+            });
+            Ok(())
+        }
     }
 }
 
+/// Config as windows are shown it.
+fn local_config_view(data_dir: &Path) -> Result<LocalConfig, CommandError> {
+    let mut config = read_local_config(data_dir)?;
+    // Configs saved before natural sorting are still in byte order on disk.
+    folders::sort_folders(&mut config);
+    temporary_folders::mark(data_dir, &mut config);
+    Ok(config)
+}
+
 #[tauri::command]
-fn get_local_config(state: State<'_, AppState>) -> Result<LocalConfig, String> {
+fn get_local_config(state: State<'_, AppState>) -> Result<LocalConfig, CommandError> {
     let runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
-    read_local_config(&runtime.data_dir)
+    local_config_view(&runtime.data_dir)
+}
+
+/// Adding a folder that was granted temporarily makes it permanent.
+fn add_folder(
+    runtime: &BackendRuntime,
+    path: &str,
+    follow_symlinks: bool,
+    allow_root: bool,
+) -> Result<ConfigChange<FolderAddition>, CommandError> {
+    let (stored, symlink) = folders::resolve_folder_input(path, follow_symlinks, allow_root)?;
+    add_resolved_folder(runtime, stored, symlink, follow_symlinks)
+}
+
+/// `add_folder` for input `resolve_folder_input` already accepted.
+fn add_resolved_folder(
+    runtime: &BackendRuntime,
+    stored: String,
+    symlink: Option<SymlinkInfo>,
+    follow_symlinks: bool,
+) -> Result<ConfigChange<FolderAddition>, CommandError> {
+    temporary_folders::forget(&runtime.data_dir, &stored)?;
+    insert_folder(runtime, stored, symlink, follow_symlinks)
+}
+
+/// Adds an already resolved folder unless it is allowed already.
+fn insert_folder(
+    runtime: &BackendRuntime,
+    stored: String,
+    symlink: Option<SymlinkInfo>,
+    follow_symlinks: bool,
+) -> Result<ConfigChange<FolderAddition>, CommandError> {
+    let location_kind = folder_location::kind(Path::new(&stored));
+    let mut config = read_local_config(&runtime.data_dir)?;
+    let mut diff = ConfigDiff::default();
+    if folders::find_folder(&config, &stored).is_none() {
+        let mut folder = AllowedFolder::fingerprinted(stored);
+        folder.follow_symlinks = follow_symlinks;
+        config.allowed_folders.push(folder);
+        folders::sort_folders(&mut config);
+        diff = commit_config(&runtime.data_dir, &config)?;
+        telemetry::track_event(
+            &runtime.data_dir,
+            telemetry::folder_added(follow_symlinks, config.allowed_folders.len()),
+        );
+        backend_reload_config(runtime, &config)?;
+    }
+    let addition = FolderAddition {
+        config,
+        symlink,
+        size_warning: None,
+        location_kind,
+    };
+    Ok(ConfigChange::new(addition, diff))
 }
 
 #[tauri::command]
-fn add_allowed_folder(state: State<'_, AppState>, path: String) -> Result<LocalConfig, String> {
+fn add_allowed_folder(
+    state: State<'_, AppState>,
+    path: String,
+    follow_symlinks: Option<bool>,
+    previewed: Option<bool>,
+    allow_root: Option<bool>,
+) -> Result<ConfigChange<FolderAddition>, CommandError> {
     let runtime = state
         .runtime
         .lock()
         .map_err(|_| "runtime lock poisoned".to_string())?;
-    let normalized = normalize_folder(&path)?;
-    let mut config = read_local_config(&runtime.data_dir)?;
-    if !config.allowed_folders.iter().any(|entry| entry == &normalized) {
-        config.allowed_folders.push(normalized);
-        config.allowed_folders.sort();
-        write_config_atomic(&runtime.data_dir, &config)?;
-        backend_reload_config(&runtime)?;
+    let follow_symlinks = follow_symlinks.unwrap_or(true);
+    let allow_root = allow_root.unwrap_or(false);
+    // Resolved once, so the size check looks at the folder that was added.
+    let (stored, symlink) = folders::resolve_folder_input(&path, follow_symlinks, allow_root)?;
+    let mut change = add_resolved_folder(&runtime, stored.clone(), symlink, follow_symlinks)?;
+    // A folder just previewed was already shown with its size.
+    if !previewed.unwrap_or(false) && !change.diff.is_empty() {
+        let data_dir = runtime.data_dir.clone();
+        drop(runtime);
+        let respect_gitignore = change.result.config.respect_gitignore;
+        change.result.size_warning =
+            folder_preview::size_warning(&data_dir, respect_gitignore, &stored, follow_symlinks);
     }
-    Ok(config)
+    Ok(change)
 }
 
 #[tauri::command]
 fn remove_allowed_folder(
     state: State<'_, AppState>,
     path: String,
-) -> Result<LocalConfig, String> {
+) -> Result<ConfigChange<FolderRemoval>, CommandError> {
     let runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
-    let normalized = normalize_folder(&path).unwrap_or(path);
     let mut config = read_local_config(&runtime.data_dir)?;
-    config.allowed_folders.retain(|entry| entry != &normalized);
-    write_config_atomic(&runtime.data_dir, &config)?;
-    backend_reload_config(&runtime)?;
-    Ok(config)
+    let removed = folders::find_folder_by_input(&config, &path)
+        .map(|index| config.allowed_folders.remove(index));
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    if let Some(folder) = &removed {
+        temporary_folders::forget(&runtime.data_dir, &folder.path)?;
+    }
+    let unbound_conversations = match &removed {
+        Some(folder) => ui_state::bound_to(&runtime.data_dir, &folder.path),
+        None => Vec::new(),
+    };
+    let undo_token = match removed {
+        Some(folder) => Some(folders::record_removal(&runtime.data_dir, folder)?),
+        None => None,
+    };
+    backend_reload_config(&runtime, &config)?;
+    let removal = FolderRemoval {
+        config,
+        undo_token,
+        unbound_conversations,
+    };
+    Ok(ConfigChange::new(removal, diff))
 }
 
 #[tauri::command]
-fn set_shell_enabled(state: State<'_, AppState>, enabled: bool) -> Result<LocalConfig, String> {
+fn set_shell_enabled(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
     let runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
     let mut config = read_local_config(&runtime.data_dir)?;
     config.shell.enabled = enabled;
-    write_config_atomic(&runtime.data_dir, &config)?;
-    backend_reload_config(&runtime)?;
-    Ok(config)
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    telemetry::track_event(&runtime.data_dir, telemetry::shell_toggled(enabled));
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigChange::new(config, diff))
 }
 
 #[tauri::command]
-fn retry_backend(state: State<'_, AppState>) -> Result<ApiConfig, String> {
+fn set_auto_start_backend(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.auto_start_backend = enabled;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+/// Keeps onboarding progress unless `clear_onboarding` is set, which replays
+/// the first-run wizard. Needs confirming; see `confirmation`.
+#[tauri::command]
+fn reset_local_config(
+    state: State<'_, AppState>,
+    clear_onboarding: Option<bool>,
+    confirm_token: Option<String>,
+) -> Result<Confirmable<ConfigChange<LocalConfig>>, CommandError> {
+    let clear_onboarding = clear_onboarding.unwrap_or(false);
+    let subject = if clear_onboarding { "clear_onboarding" } else { "keep_onboarding" };
+    confirmation::guarded(
+        &state,
+        "reset_local_config",
+        subject,
+        confirm_token,
+        |data_dir| {
+            let config = read_local_config(data_dir)?;
+            let onboarding = if clear_onboarding { "cleared" } else { "kept" };
+            Ok(Summary::new(format!(
+                "Replaces every setting with its default; onboarding progress is {onboarding}."
+            ))
+            .count("allowed_folders", config.allowed_folders.len() as u64)
+            .count("env_extra", config.env_extra.len() as u64)
+            .path(&config_path(data_dir)))
+        },
+        || {
+            let runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
+            let mut config = LocalConfig::default();
+            if !clear_onboarding {
+                config.onboarding = read_local_config(&runtime.data_dir)?.onboarding;
+            }
+            let diff = commit_config(&runtime.data_dir, &config)?;
+            backend_reload_config(&runtime, &config)?;
+            Ok(ConfigChange::new(config, diff))
+        },
+    )
+}
+
+fn ensure_not_safe_mode(runtime: &BackendRuntime) -> Result<(), String> {
+    if runtime.safe_mode {
+        return Err("safe mode is active; leave safe mode to start the backend".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn retry_backend(app: AppHandle, state: State<'_, AppState>) -> Result<ApiConfig, String> {
+    // Taken before the lock so repeated clicks return instead of queueing.
+    let starting = spawn_guard::begin()?;
     let mut runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
-    spawn_backend(&mut runtime)?;
-    Ok(ApiConfig {
-        base_url: runtime.base_url.clone(),
-        token: runtime.token.clone(),
-        backend_ready: runtime.backend_ready,
-        last_error: runtime.last_error.clone(),
-        log_path: runtime.log_path.clone(),
-    })
+    ensure_not_safe_mode(&runtime)?;
+    runtime.stopped_by_user = false;
+    runtime.crash_tracker.reset();
+    spawn_backend(&mut runtime, &starting)?;
+    session_file::deliver_pending(&app, &mut runtime);
+    action_queue::flush(&app, &runtime);
+    Ok(backend_status::publish(&app, &runtime))
+}
+
+#[tauri::command]
+fn start_backend(app: AppHandle, state: State<'_, AppState>) -> Result<ApiConfig, String> {
+    let starting = spawn_guard::begin()?;
+    let mut runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
+    ensure_not_safe_mode(&runtime)?;
+    runtime.stopped_by_user = false;
+    if runtime.backend_ready && runtime.backend_child.is_some() {
+        return Ok(api_config(&runtime));
+    }
+    spawn_backend(&mut runtime, &starting)?;
+    session_file::deliver_pending(&app, &mut runtime);
+    action_queue::flush(&app, &runtime);
+    Ok(backend_status::publish(&app, &runtime))
 }
 
 #[tauri::command]
-fn read_backend_logs(state: State<'_, AppState>, lines: usize) -> Result<String, String> {
+fn stop_backend_command(app: AppHandle, state: State<'_, AppState>) -> Result<ApiConfig, String> {
+    let mut runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(backend_status::set_status(&app, &mut runtime, |runtime| {
+        stop_backend(runtime);
+        runtime.backend_ready = false;
+        runtime.last_error = None;
+        runtime.stopped_by_user = true;
+    }))
+}
+
+#[tauri::command]
+fn leave_safe_mode(app: AppHandle, state: State<'_, AppState>) -> Result<ApiConfig, String> {
+    let mut runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
+    if !runtime.safe_mode {
+        return Ok(api_config(&runtime));
+    }
+    runtime.safe_mode = false;
+    let started = start_subsystems(&mut runtime);
+    session_file::deliver_pending(&app, &mut runtime);
+    action_queue::flush(&app, &runtime);
+    let config = backend_status::publish(&app, &runtime);
+    let data_dir = runtime.data_dir.clone();
+    drop(runtime);
+    start_background(&app, &data_dir);
+    started.map(|()| config)
+}
+
+#[tauri::command]
+fn export_diagnostics(
+    state: State<'_, AppState>,
+    dest_path: String,
+) -> Result<String, CommandError> {
+    storage::ensure_available()?;
+    let startup = state
+        .startup
+        .lock()
+        .map_err(|_| "startup report lock poisoned".to_string())?
+        .clone();
     let runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
-    let path = PathBuf::from(&runtime.log_path);
-    let content = fs::read_to_string(path).map_err(|e| format!("failed reading logs: {e}"))?;
-    let mut collected: Vec<&str> = content.lines().rev().take(lines.max(1)).collect();
-    collected.reverse();
-    Ok(collected.join("\n"))
+    let written = diagnostics::export_diagnostics(&runtime, &startup, Path::new(&dest_path))?;
+    Ok(written.to_string_lossy().to_string())
 }
 
-fn backend_script_path() -> PathBuf {
-    let here = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    here.join("..").join("..").join("backend").join("main.py")
+#[tauri::command]
+fn run_self_check(app: AppHandle, state: State<'_, AppState>) -> Result<SelfCheckReport, String> {
+    let mut runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
+    let report = self_check::run_self_check(&runtime.data_dir);
+    let _ = app.emit("app-self-check", &report);
+    runtime.self_check = Some(report.clone());
+    Ok(report)
 }
 
-fn find_open_port() -> Result<u16, String> {
-    for port in 8765..8865 {
-        if TcpListener::bind(("127.0.0.1", port)).is_ok() {
-            return Ok(port);
+/// Upper bound on `read_backend_logs(lines)`.
+const MAX_LOG_LINES: usize = 10_000;
+/// Pause before retrying a read that lost a race with log rotation.
+const LOG_ROTATION_RETRY: Duration = Duration::from_millis(100);
+
+#[derive(Serialize)]
+struct BackendLogs {
+    content: String,
+    /// False before the first backend spawn of a fresh install.
+    exists: bool,
+    lines_requested: usize,
+    lines_returned: usize,
+    /// `lines` was above `MAX_LOG_LINES` and was capped.
+    clamped: bool,
+    total_size_bytes: u64,
+}
+
+fn read_log_bytes(path: &Path) -> io::Result<Vec<u8>> {
+    match fs::read(path) {
+        // The file may be mid-rename by rotation; look once more.
+        Err(err) if err.kind() != io::ErrorKind::PermissionDenied => {
+            thread::sleep(LOG_ROTATION_RETRY);
+            fs::read(path).map_err(|_| err)
         }
+        result => result,
     }
-    Err("no open port found in 8765-8864".to_string())
 }
 
-fn backend_log_file(data_dir: &PathBuf) -> Result<File, String> {
-    let logs_dir = data_dir.join("logs");
-    fs::create_dir_all(&logs_dir).map_err(|e| format!("failed creating logs dir: {e}"))?;
-    let path = logs_dir.join("backend.log");
-    OpenOptions::new()
+#[tauri::command]
+fn read_backend_logs(
+    state: State<'_, AppState>,
+    lines: usize,
+) -> Result<BackendLogs, CommandError> {
+    storage::ensure_available()?;
+    let log_path = {
+        let runtime = state.runtime.lock().map_err(|_| "runtime lock poisoned".to_string())?;
+        PathBuf::from(&runtime.log_path)
+    };
+    let wanted = lines.clamp(1, MAX_LOG_LINES);
+    let mut logs = BackendLogs {
+        content: String::new(),
+        exists: false,
+        lines_requested: lines,
+        lines_returned: 0,
+        clamped: lines > MAX_LOG_LINES,
+        total_size_bytes: 0,
+    };
+    let bytes = match read_log_bytes(&log_path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(logs),
+        Err(err) => return Err(format!("failed reading logs: {err}").into()),
+    };
+    let content = String::from_utf8_lossy(&bytes);
+    let mut collected: Vec<&str> = content.lines().rev().take(wanted).collect();
+    collected.reverse();
+    logs.exists = true;
+    logs.total_size_bytes = bytes.len() as u64;
+    logs.lines_returned = collected.len();
+    logs.content = collected.join("\n");
+    response_limit::refuse_oversized("read_backend_logs", &logs, "export_full_log")?;
+    Ok(logs)
+}
+
+/// Cache-dir subdirectory the backend runs in, so relative paths it writes
+/// land somewhere the janitor can sweep.
+const BACKEND_CWD_DIR: &str = "backend-cwd";
+
+fn backend_cwd(data_dir: &Path) -> PathBuf {
+    layout::cache_dir(data_dir).join(BACKEND_CWD_DIR)
+}
+
+/// The source tree's backend, which dev builds run directly.
+fn dev_backend_dir() -> PathBuf {
+    let here = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    here.join("..").join("..").join("backend")
+}
+
+/// `bundle.resources` as each platform's package lays them out: next to the
+/// executable on Windows, `Contents/Resources` on macOS, `lib/LiteClaw` on
+/// Linux.
+fn bundled_backend_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    [
+        exe_dir.join("backend"),
+        exe_dir.join("..").join("Resources").join("backend"),
+        exe_dir.join("..").join("lib").join("LiteClaw").join("backend"),
+    ]
+    .into_iter()
+    .find(|dir| dir.join("main.py").is_file())
+}
+
+/// An installed backend update wins over the bundled backend.
+fn backend_dir() -> PathBuf {
+    backend_update::active_dir()
+        .or_else(bundled_backend_dir)
+        .unwrap_or_else(dev_backend_dir)
+}
+
+fn backend_log_path(data_dir: &Path) -> PathBuf {
+    layout::cache_dir(data_dir).join("logs").join("backend.log")
+}
+
+fn backend_log_file(data_dir: &Path) -> Result<LogSink, String> {
+    let path = backend_log_path(data_dir);
+    if let Some(logs_dir) = path.parent() {
+        fs::create_dir_all(logs_dir).map_err(|e| format!("failed creating logs dir: {e}"))?;
+    }
+    let file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(path)
-        .map_err(|e| format!("failed opening backend log file: {e}"))
+        .open(&path)
+        .map_err(|e| format!("failed opening backend log file: {e}"))?;
+    Ok(LogSink::new(path, file))
 }
 
-fn poll_backend_health(base_url: &str, token: &str, timeout: Duration) -> Result<(), String> {
-    let deadline = Instant::now() + timeout;
-    let health_url = format!("{base_url}/v1/health");
-    while Instant::now() < deadline {
-        let response = ureq::get(&health_url)
-            .set("Authorization", &format!("Bearer {token}"))
-            .call();
-        if let Ok(resp) = response {
-            if resp.status() == 200 {
-                return Ok(());
+/// How long to wait for the readiness sentinel before assuming an older
+/// backend that never prints it.
+const SENTINEL_GRACE: Duration = Duration::from_secs(2);
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a backend that reports startup phases may go quiet between them
+/// before we stop waiting for the sentinel.
+const PHASE_STALL: Duration = Duration::from_secs(30);
+/// Ports given up on because another process answered there, before the
+/// spawn fails for good.
+const MAX_SQUATTED_PORTS: usize = 3;
+
+enum TokenCheck {
+    Accepted,
+    Rejected(u16),
+    Inconclusive,
+}
+
+/// Whether the backend at `base_url` accepts our token. A stray backend left
+/// on the port can pass health (older builds did not guard it) but not this;
+/// backends predating `/v1/auth/verify` are asked for `/v1/version`.
+fn check_token(transport: &dyn BackendTransport, base_url: &str, token: &str) -> TokenCheck {
+    for endpoint in [backend_transport::AUTH_VERIFY, backend_transport::VERSION] {
+        match transport.verify_token(base_url, token, endpoint) {
+            Ok(resp) if resp.status() == 200 => return TokenCheck::Accepted,
+            Err(ureq::Error::Status(404, _)) => continue,
+            Err(ureq::Error::Status(code @ (401 | 403), _)) => return TokenCheck::Rejected(code),
+            _ => return TokenCheck::Inconclusive,
+        }
+    }
+    TokenCheck::Inconclusive
+}
+
+fn startup_failure(child: &mut Child, signal: Option<StartupSignal>) -> Option<BackendError> {
+    if let Some(StartupSignal::Fatal(reason)) = signal {
+        let detail = format!("backend crashed during startup: {reason}");
+        return Some(BackendError::new(BackendErrorKind::StartupCrashed, detail));
+    }
+    let status = child.try_wait().ok().flatten()?;
+    let detail = format!("backend exited during startup: {status}");
+    let error = BackendError::new(BackendErrorKind::StartupExited, detail);
+    Some(match status.code() {
+        Some(code) => {
+            error.with_summary(format!("The backend exited while starting (code {code})."))
+        }
+        None => error,
+    })
+}
+
+/// Waits for the sentinel (or the grace period for older backends, which
+/// each phase line extends), then
+/// polls health on each candidate host and returns the base URL that
+/// answered and accepted our token. A traceback or early exit fails fast
+/// with the reason instead of waiting out the timeout, and a process that
+/// passes health but rejects the token is reported as `PortSquatted`.
+fn wait_for_backend(
+    child: &mut Child,
+    hosts: &[String],
+    token: &str,
+    port: u16,
+    signals: &Receiver<StartupSignal>,
+) -> Result<(String, HealthInfo), BackendError> {
+    let mut grace_deadline = Instant::now() + SENTINEL_GRACE;
+    while Instant::now() < grace_deadline {
+        let signal = match signals.recv_timeout(Duration::from_millis(100)) {
+            Ok(StartupSignal::Ready { port: Some(reported) }) if reported != port => {
+                let detail = format!("backend reported port {reported}, expected {port}");
+                return Err(BackendError::new(BackendErrorKind::PortMismatch, detail));
+            }
+            Ok(StartupSignal::Ready { .. }) | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(StartupSignal::Progress) => {
+                grace_deadline = Instant::now() + PHASE_STALL;
+                None
             }
+            Ok(signal) => Some(signal),
+            Err(RecvTimeoutError::Timeout) => None,
+        };
+        if let Some(reason) = startup_failure(child, signal) {
+            return Err(reason);
+        }
+    }
+
+    let deadline = Instant::now() + HEALTH_TIMEOUT;
+    let base_urls: Vec<String> = hosts.iter().map(|host| loopback::base_url(host, port)).collect();
+    while Instant::now() < deadline {
+        if let Some(reason) = startup_failure(child, signals.try_recv().ok()) {
+            return Err(reason);
+        }
+        if let Some(found) = find_backend(&UreqTransport, &base_urls, token, port) {
+            return found;
         }
         thread::sleep(Duration::from_millis(250));
     }
-    Err("backend health check timed out".to_string())
+    Err(BackendError::new(
+        BackendErrorKind::HealthTimeout,
+        "backend health check timed out",
+    ))
+}
+
+/// One pass of `wait_for_backend` over `base_urls`: the first that passes
+/// health and accepts our token, `PortSquatted` for one that passes health
+/// but rejects the token, or `None` to poll again.
+fn find_backend(
+    transport: &dyn BackendTransport,
+    base_urls: &[String],
+    token: &str,
+    port: u16,
+) -> Option<Result<(String, HealthInfo), BackendError>> {
+    for base_url in base_urls {
+        let response = transport.health(base_url, token, None);
+        let Some(resp) = response.ok().filter(|resp| resp.status() == 200) else {
+            continue;
+        };
+        match check_token(transport, base_url, token) {
+            TokenCheck::Accepted => {}
+            TokenCheck::Rejected(code) => {
+                let detail = format!(
+                    "{base_url} passed health but answered HTTP {code} to our token; \
+                     another process holds port {port}"
+                );
+                let error = BackendError::new(BackendErrorKind::PortSquatted, detail);
+                return Some(Err(error.with_summary(format!(
+                    "Another program is answering on port {port}."
+                ))));
+            }
+            TokenCheck::Inconclusive => continue,
+        }
+        let info = resp
+            .into_string()
+            .ok()
+            .and_then(|body| serde_json::from_str::<HealthInfo>(&body).ok())
+            .unwrap_or_default();
+        return Some(Ok((base_url.clone(), info)));
+    }
+    None
 }
 
 fn stop_backend(runtime: &mut BackendRuntime) {
@@ -247,96 +1229,637 @@ fn stop_backend(runtime: &mut BackendRuntime) {
         let _ = child.wait();
     }
     runtime.backend_child = None;
+    runtime.discovery = None;
+    discovery::clear(&runtime.data_dir);
+}
+
+/// The new child stays local until it passes health and is promoted into
+/// `runtime`; on failure it is killed here, so no path leaves an orphan.
+/// Records a failure in `last_error` and desktop.log, returning only its
+/// summary. A squatted port is logged and the spawn retried on the next
+/// free one; when that succeeds `last_error` still names the squatted ports.
+fn spawn_backend(runtime: &mut BackendRuntime, starting: &SpawnGuard) -> Result<(), String> {
+    data_dir_lock::ensure_writable()?;
+    launch_with_retries(runtime, |runtime, squatted| {
+        launch_backend(runtime, starting, squatted)
+    })
+}
+
+/// The retries and failure reporting of `spawn_backend` around `launch`.
+fn launch_with_retries<T>(
+    runtime: &mut BackendRuntime,
+    mut launch: impl FnMut(&mut BackendRuntime, &mut Vec<u16>) -> Result<T, BackendError>,
+) -> Result<T, String> {
+    let started = SystemTime::now();
+    let mut squatted = Vec::new();
+    let result = loop {
+        match launch(runtime, &mut squatted) {
+            Err(err)
+                if err.kind == BackendErrorKind::PortSquatted
+                    && squatted.len() < MAX_SQUATTED_PORTS =>
+            {
+                let message = format!("retrying backend on another port: {}", err.detail());
+                desktop_log::warn(&runtime.data_dir, &message);
+            }
+            result => break result,
+        }
+    };
+    let err = match result {
+        Ok(launched) => {
+            if !squatted.is_empty() {
+                let ports: Vec<String> = squatted.iter().map(u16::to_string).collect();
+                let ports = ports.join(", ");
+                let detail = format!("another process answered on port {ports}");
+                let error = BackendError::new(BackendErrorKind::PortSquatted, detail);
+                runtime.last_error = Some(error.with_summary(format!(
+                    "Another program was answering on port {ports}; \
+                     the backend moved to a free port."
+                )));
+            }
+            return Ok(launched);
+        }
+        Err(err) => err,
+    };
+    let message = format!("backend start failed: {}", err.detail());
+    desktop_log::warn(&runtime.data_dir, &message);
+    let err = match err.kind {
+        BackendErrorKind::StartupCrashed
+        | BackendErrorKind::StartupExited
+        | BackendErrorKind::HealthTimeout => {
+            let log_path = backend_log_path(&runtime.data_dir);
+            err.with_hint(traceback_hint::from_log(&log_path, Some(started)))
+        }
+        _ => err,
+    };
+    let summary = err.summary().to_string();
+    runtime.last_error = Some(err);
+    Err(summary)
 }
 
-fn spawn_backend(runtime: &mut BackendRuntime) -> Result<(), String> {
+/// Pushes the port onto `squatted` when another process turns out to hold it.
+fn launch_backend(
+    runtime: &mut BackendRuntime,
+    starting: &SpawnGuard,
+    squatted: &mut Vec<u16>,
+) -> Result<(), BackendError> {
     stop_backend(runtime);
+    backend_http::reset();
+    runtime.backend_ready = false;
+    runtime.backend_degraded = false;
+    runtime.backend_incompatible = false;
+    runtime.last_error = None;
+    match launch_process(&runtime.data_dir, starting, squatted) {
+        Ok(launched) => {
+            promote(runtime, launched);
+            Ok(())
+        }
+        Err(err) => {
+            runtime.backend_incompatible = err.kind == BackendErrorKind::Incompatible;
+            Err(err)
+        }
+    }
+}
 
-    let port = find_open_port()?;
+/// A backend that passed health and the API check but is not in `runtime`.
+struct Launched {
+    child: Child,
+    token: String,
+    base_url: String,
+    config: LocalConfig,
+    proxy: ResolvedProxy,
+    env: EnvSettings,
+    performance: AppliedPerformance,
+    wsl: Option<WslTarget>,
+    api: ApiVersion,
+    generation: u64,
+    timings: SpawnTimings,
+}
+
+/// Starts a backend for `data_dir` next to whatever is running.
+fn launch_process(
+    data_dir: &Path,
+    starting: &SpawnGuard,
+    squatted: &mut Vec<u16>,
+) -> Result<Launched, BackendError> {
+    starting.assert_held();
+    let discovery_started = Instant::now();
+    let config = read_local_config(data_dir).unwrap_or_default();
+    let hosts = loopback::candidates(&config);
+    let port = loopback::find_open_port(&hosts, squatted)?;
     let token = Uuid::new_v4().to_string();
-    let base_url = format!("http://127.0.0.1:{port}");
-    let script_path = backend_script_path();
-    let log_file = backend_log_file(&runtime.data_dir)?;
-    let stderr_file = log_file
-        .try_clone()
-        .map_err(|e| format!("failed cloning log file handle: {e}"))?;
-
-    let child = Command::new("python")
-        .arg(script_path.to_string_lossy().to_string())
+    let backend_dir = backend_dir();
+    if let Err(err) = integrity::check(&backend_dir, &dev_backend_dir(), &config, data_dir) {
+        return Err(BackendError::new(BackendErrorKind::IntegrityFailed, err));
+    }
+    let script_path = backend_dir.join("main.py");
+    storage::ensure_available()?;
+    let log_file = Arc::new(backend_log_file(data_dir)?);
+    let line_format = if config.debug_console {
+        LineFormat::Raw
+    } else {
+        LineFormat::Prefixed
+    };
+    backend_http::configure(&config.hang_detection);
+    request_limiter::configure(&config.performance.concurrency);
+
+    let generation = config_generation();
+    let resolved_proxy = proxy::resolve(&config.proxy);
+    let env = EnvSettings::from_config(&config);
+    let cwd = backend_cwd(data_dir);
+    fs::create_dir_all(&cwd).map_err(|e| format!("failed creating backend cwd: {e}"))?;
+    let wsl_target = match config.backend_host {
+        BackendHost::Native => None,
+        BackendHost::Wsl => Some(wsl::target(&config)?),
+    };
+    let script_arg = match &wsl_target {
+        None => script_path.to_string_lossy().to_string(),
+        Some(target) => wsl::to_wsl_path(&script_path.to_string_lossy(), &target.automount_root)
+            .ok_or_else(|| format!("cannot reach {} from WSL", script_path.display()))?,
+    };
+    let mut command = match &wsl_target {
+        None => Command::new(PYTHON_BIN),
+        Some(target) => wsl::python_command(target),
+    };
+    backend_env::apply_to_command(&mut command, &env);
+    proxy::apply_to_command(&mut command, &resolved_proxy);
+    if let Ok(launch_cwd) = std::env::current_dir() {
+        command.env("LITECLAW_LAUNCH_CWD", launch_cwd);
+    }
+    if let Some(host) = &config.loopback_host {
+        command.env("LITECLAW_HOST", host);
+    }
+    if let Some(locale) = &config.locale {
+        command.env("LITECLAW_LOCALE", locale);
+    }
+    let mut performance = performance::prepare_command(&mut command, &config.performance);
+    command
+        .current_dir(&cwd)
+        .arg(script_arg)
         .env("LITECLAW_AUTH_TOKEN", token.clone())
-        .env("LITECLAW_DATA_DIR", runtime.data_dir.to_string_lossy().to_string())
+        .env("LITECLAW_DATA_DIR", data_dir.to_string_lossy().to_string())
+        .env("LITECLAW_CONFIG_DIR", layout::config_dir(data_dir))
+        .env("LITECLAW_CACHE_DIR", layout::cache_dir(data_dir))
         .env("LITECLAW_PORT", port.to_string())
-        .stdout(Stdio::from(log_file))
-        .stderr(Stdio::from(stderr_file))
+        .env("LITECLAW_CONFIG_GENERATION", generation.to_string())
+        .env("LITECLAW_SPAWNED_AT_MS", unix_now_millis().to_string());
+    if wsl_target.is_some() {
+        wsl::forward_env(&mut command);
+    }
+    startup_phase::clear();
+    let spawn_started = Instant::now();
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()
         .map_err(|e| format!("failed to spawn backend: {e}"))?;
+    let health_started = Instant::now();
+    performance::apply_to_child(&child, &mut performance);
+    for warning in &performance.warnings {
+        desktop_log::warn(data_dir, warning);
+    }
+    let (signal_tx, signal_rx) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        backend_output::spawn_tee(
+            stdout,
+            Stream::Stdout,
+            line_format,
+            log_file.clone(),
+            signal_tx.clone(),
+        );
+    }
+    if let Some(stderr) = child.stderr.take() {
+        backend_output::spawn_tee(stderr, Stream::Stderr, line_format, log_file, signal_tx);
+    }
 
-    runtime.token = token;
-    runtime.base_url = base_url;
-    runtime.backend_child = Some(child);
-    runtime.backend_ready = false;
-    runtime.last_error = None;
-
-    match poll_backend_health(&runtime.base_url, &runtime.token, Duration::from_secs(5)) {
-        Ok(_) => {
-            runtime.backend_ready = true;
-            Ok(())
+    let (base_url, health) = match wait_for_backend(&mut child, &hosts, &token, port, &signal_rx) {
+        Ok(ready) => ready,
+        Err(err) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            if err.kind == BackendErrorKind::PortSquatted {
+                squatted.push(port);
+            } else {
+                telemetry::track_event(data_dir, telemetry::backend_crashed("startup"));
+            }
+            return Err(err);
         }
+    };
+    let api = match api_version::negotiate(health.api_version, health.min_api_version) {
+        Ok(api) => api,
         Err(err) => {
-            runtime.backend_ready = false;
-            runtime.last_error = Some(err.clone());
-            stop_backend(runtime);
-            Err(err)
+            let _ = child.kill();
+            let _ = child.wait();
+            let detail = err.message.clone();
+            let error = BackendError::new(BackendErrorKind::Incompatible, detail);
+            return Err(error.with_summary(err.message));
+        }
+    };
+
+    Ok(Launched {
+        child,
+        token,
+        base_url,
+        config,
+        proxy: resolved_proxy,
+        env,
+        performance,
+        wsl: wsl_target,
+        api,
+        generation,
+        timings: SpawnTimings {
+            python_discovery: spawn_started.duration_since(discovery_started),
+            spawn: health_started.duration_since(spawn_started),
+            health_wait: health_started.elapsed(),
+            backend_init_ms: health.init_ms,
+        },
+    })
+}
+
+/// Makes `launched` the running backend, returning the child it replaces.
+fn promote(runtime: &mut BackendRuntime, launched: Launched) -> Option<Child> {
+    let info = DiscoveryInfo::new(&launched.base_url, launched.child.id());
+    let config = launched.config;
+    runtime.token = launched.token;
+    runtime.base_url = launched.base_url;
+    let replaced = runtime.backend_child.replace(launched.child);
+    runtime.backend_proxy = Some(launched.proxy);
+    runtime.backend_env = Some(launched.env);
+    runtime.backend_performance = Some(launched.performance);
+    runtime.backend_wsl = launched.wsl;
+    runtime.backend_api = launched.api;
+    runtime.backend_ready = true;
+    runtime.backend_degraded = false;
+    runtime.backend_incompatible = false;
+    startup_phase::clear();
+    // A fresh backend reads config.json itself at startup.
+    runtime.backend_config_generation = Some(launched.generation);
+    runtime.out_of_sync_beats = 0;
+    runtime.failed_health_beats = 0;
+    runtime.crash_tracker.mark_ready();
+    if let Err(err) = discovery::write(&runtime.data_dir, &info) {
+        desktop_log::warn(&runtime.data_dir, &err);
+    }
+    runtime.discovery = Some(info);
+    if config.encrypt_config {
+        let wsl = runtime.backend_wsl.as_ref();
+        let api = runtime.backend_api;
+        let (base_url, token) = (&runtime.base_url, &runtime.token);
+        let data_dir = &runtime.data_dir;
+        let pushed = post_reload(&UreqTransport, base_url, token, data_dir, &config, wsl, api);
+        if let Err(err) = pushed {
+            let message = format!("initial config push failed: {}", err.message);
+            desktop_log::warn(data_dir, &message);
+        }
+    }
+    runtime.spawn_timings = Some(launched.timings);
+    replaced
+}
+
+/// Cleanup, telemetry, the status server and the timers, none of which run
+/// in safe mode; `leave_safe_mode` starts them then. Must not be called with
+/// the runtime lock held, as for `status_server::apply`.
+fn start_background(app: &AppHandle, data_dir: &Path) {
+    if !data_dir_lock::is_read_only() {
+        let janitor_root = data_dir.to_path_buf();
+        thread::spawn(move || {
+            janitor::run_cleanup(&janitor_root);
+        });
+    }
+    telemetry::start(app.clone());
+    let config = read_local_config(data_dir).unwrap_or_default();
+    status_server::apply(app, data_dir, &config.status_server);
+    heartbeat::start(app.clone());
+    backups::start(app.clone());
+    temporary_folders::start(app.clone());
+    system_events::start(app.clone());
+}
+
+/// Callers hold the runtime lock; a start already waiting on it will bring
+/// the backend up instead.
+fn start_subsystems(runtime: &mut BackendRuntime) -> Result<(), String> {
+    let config = read_local_config(&runtime.data_dir)?;
+    if config.auto_start_backend {
+        let Ok(starting) = spawn_guard::begin() else {
+            return Ok(());
+        };
+        // A failure is already recorded in `last_error`.
+        let _ = spawn_backend(runtime, &starting);
+    } else {
+        runtime.stopped_by_user = true;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn shift_held_at_launch() -> bool {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_SHIFT};
+    // The high bit is set while the key is down.
+    unsafe { (GetAsyncKeyState(VK_SHIFT as i32) as u16 & 0x8000) != 0 }
+}
+
+#[cfg(not(windows))]
+fn shift_held_at_launch() -> bool {
+    false
+}
+
+fn safe_mode_requested() -> bool {
+    std::env::args().any(|arg| arg == "--safe-mode") || shift_held_at_launch()
+}
+
+/// Resolves the layout and the active profile, migrating the old layout
+/// once. Returns the profile's name and data dir.
+fn open_data_dir(app_data_dir: &Path) -> Result<(String, PathBuf), String> {
+    let (app_root, layout_migration) = layout::init(app_data_dir, profiles::data_dirs)?;
+    let (profile, data_dir) = profiles::load_active(&app_root)?;
+    for problem in &layout_migration.problems {
+        desktop_log::warn(&data_dir, &format!("layout migration: {problem}"));
+    }
+    if let Some(description) = layout_migration.describe() {
+        if let Err(err) = migrations::record(MigrationKind::DataLayout, description) {
+            desktop_log::warn(&data_dir, &format!("migration history: {err}"));
         }
     }
+    Ok((profile, data_dir))
 }
 
 fn main() {
+    startup::mark_process_start();
+    if std::env::args().any(|arg| arg == native_messaging::HOST_FLAG) {
+        std::process::exit(native_messaging::run_host());
+    }
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+    let safe_mode = safe_mode_requested();
     tauri::Builder::default()
+        // Must be registered first so a second launch exits before doing any
+        // other setup.
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            // A `config` command line changed the config; nothing to show.
+            if cli::is_config_notice(&argv) {
+                cli::serve_config_notice(app);
+                return;
+            }
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+            session_file::open_session_paths(app, &session_file::session_paths_in_args(&argv));
+            native_messaging::serve_args(app, &argv);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_dialog::init())
-        .setup(|app| {
-            let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        .plugin(tauri_plugin_notification::init())
+        .on_page_load(|webview, payload| {
+            if payload.event() != PageLoadEvent::Finished {
+                return;
+            }
+            bootstrap::send(webview.app_handle(), webview.label());
+            if webview.label() != "main" {
+                return;
+            }
+            if let Some(state) = webview.try_state::<AppState>() {
+                if let Ok(mut startup) = state.startup.lock() {
+                    startup.window_shown();
+                }
+            }
+            native_messaging::serve_queued(webview.app_handle());
+            migrations::emit_applied(webview.app_handle());
+            data_dir_lock::announce(webview.app_handle());
+            let handle = webview.app_handle().clone();
+            thread::spawn(move || {
+                self_check::announce(&handle);
+                onboarding::announce(&handle);
+            });
+        })
+        .setup(move |app| {
+            let mut startup = StartupReport::begin();
+            startup.phase("tauri_setup");
+            startup_phase::attach(app.handle().clone());
+            let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+            let (profile, data_dir) = open_data_dir(&app_data_dir)?;
+            startup.record_last_run(&data_dir);
+            let report = self_check::run_self_check(&data_dir);
             fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
-            let log_path = data_dir.join("logs").join("backend.log");
-
             let mut runtime = BackendRuntime {
-                token: String::new(),
-                base_url: String::new(),
-                log_path: log_path.to_string_lossy().to_string(),
-                backend_ready: false,
-                last_error: None,
-                data_dir,
-                backend_child: None,
+                safe_mode,
+                self_check: Some(report),
+                ..BackendRuntime::new(data_dir, profile)
             };
-            ensure_config_exists(&runtime.data_dir)?;
-            if let Err(err) = spawn_backend(&mut runtime) {
-                runtime.last_error = Some(err);
+            let read_only = data_dir_lock::acquire(&runtime.data_dir).is_some();
+            // Whatever a previous (possibly crashed) session left is stale,
+            // unless the run that owns the data dir is still advertising.
+            if !read_only {
+                discovery::remove_stale(&runtime.data_dir);
+            }
+            if read_only {
+                desktop_log::warn(&runtime.data_dir, "data dir owned elsewhere; read-only");
+            } else if let Err(err) = legacy_config::migrate(&runtime.data_dir) {
+                desktop_log::warn(&runtime.data_dir, &format!("legacy migration: {err}"));
             }
+            ensure_config_exists(&runtime.data_dir).map_err(String::from)?;
+            startup.phase("config_load");
+            temporary_folders::revoke_at_startup(app.handle(), &runtime.data_dir);
+            storage::start(app.handle().clone());
+            startup.mark();
+            if !runtime.safe_mode && !read_only {
+                start_subsystems(&mut runtime)?;
+            }
+            if let Some(timings) = &runtime.spawn_timings {
+                startup.backend_started(timings);
+            }
+            let args: Vec<String> = std::env::args().collect();
+            session_file::queue_launch_sessions(&mut runtime, &args);
+            native_messaging::queue_launch_requests(&args);
+            #[cfg(any(windows, target_os = "linux"))]
+            {
+                // Registers the scheme for unbundled runs (dev builds, AppImage).
+                let _ = app.deep_link().register_all();
+            }
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                for url in urls {
+                    deeplink::queue_launch_url(&mut runtime, url.as_str());
+                }
+            }
+            let identity = (runtime.profile.clone(), runtime.data_dir.clone());
+            let local_config = read_local_config(&runtime.data_dir).unwrap_or_default();
+            slow_commands::configure(local_config.slow_command_ms);
+            response_limit::configure(local_config.max_response_bytes);
             app.manage(AppState {
                 runtime: Mutex::new(runtime),
+                tasks: tasks::TaskRegistry::default(),
+                startup: Mutex::new(startup),
+                integrations: Mutex::new(integrations::detect()),
+                confirmations: confirmation::Confirmations::default(),
             });
+            // Registered once `AppState` is managed; the handler reads it.
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                let urls: Vec<String> = event.urls().iter().map(|url| url.to_string()).collect();
+                deeplink::handle_urls(&handle, &urls);
+            });
+            let title = profiles::window_title(&identity.0);
+            let tray = tray::create(app.handle(), &identity.1, &title);
+            if let Ok(mut status) = app.state::<AppState>().integrations.lock() {
+                integrations::record_tray(&mut status, tray);
+                integrations::announce(app.handle(), &status);
+            }
+            profiles::show_identity(app.handle(), &identity.0, &identity.1);
+            if !safe_mode {
+                start_background(app.handle(), &identity.1);
+            }
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(permissions::guard(tauri::generate_handler![
             get_api_config,
+            backend_status::get_connection_info,
+            backend_status::get_backend_status,
+            app_info::get_app_info,
             get_local_config,
+            config_validation::update_local_config,
+            config_validation::validate_config_patch,
             add_allowed_folder,
             remove_allowed_folder,
+            temporary_folders::add_temporary_folder,
+            temporary_folders::extend_temporary_folder,
+            folders::apply_folder_changes,
+            folders::set_folder_alias,
+            folders::set_folder_note,
+            folders::set_folder_mode,
+            folders::set_folder_sort_order,
+            config_crypto::set_config_encryption,
+            config_crypto::reset_encrypted_config,
+            folders::move_allowed_folder,
+            ignore_rules::add_folder_ignore_pattern,
+            ignore_rules::remove_folder_ignore_pattern,
+            ignore_rules::set_respect_gitignore,
+            native_messaging::set_native_messaging,
+            native_messaging::install_native_messaging_host,
+            status_server::get_status_server,
+            status_server::set_status_server_enabled,
+            status_server::set_status_server_port,
+            backups::create_backup,
+            backups::set_backups_enabled,
+            backups::set_backup_schedule,
+            backups::set_backup_destination,
+            backend_debug::backend_debug_request,
+            backend_debug::get_backend_routes,
+            backend_error::get_last_error_details,
+            folders::list_recently_removed_folders,
+            folders::restore_removed_folder,
+            folder_access::check_folder_access,
+            folder_listing::list_folder,
+            folder_preview::preview_folder_addition,
+            file_ops::delete_file,
+            file_ops::write_file,
+            locale::set_locale,
+            log_export::export_full_log,
+            response_limit::read_spilled_result,
+            response_limit::discard_spilled_result,
+            folders::validate_allowed_folders,
+            folders::relocate_allowed_folder,
+            macos_privacy::get_macos_privacy_status,
+            macos_privacy::open_privacy_settings,
+            attachments::stage_attachment,
+            attachments::list_staged_attachments,
+            attachments::remove_staged_attachment,
+            attachments::set_attachment_size_limit,
+            clipboard::capture_clipboard,
+            clipboard::set_clipboard_capture,
+            screenshot::capture_screenshot,
+            screenshot::set_screen_capture_enabled,
+            conversation_export::export_conversation,
+            session_file::take_pending_session_files,
+            deeplink::resolve_deeplink,
+            deeplink::take_pending_deeplinks,
+            upload::upload_file_to_backend,
+            upload::cancel_upload,
+            tasks::list_tasks,
+            tasks::cancel_task,
+            download::download_backend_file,
+            janitor::run_cleanup_now,
+            metrics::get_metrics,
+            model_settings::get_model_settings,
+            model_settings::set_model_settings,
+            model_settings::set_provider_api_key,
+            slow_commands::set_slow_command_threshold,
+            telemetry::set_telemetry_enabled,
+            history_search::search_history,
+            ui_state::pin_conversation,
+            ui_state::unpin_conversation,
+            ui_state::list_pinned_conversations,
+            ui_state::set_conversation_label,
+            ui_state::set_conversation_folder,
+            ui_state::get_conversation_folder,
+            unread::set_unread_count,
+            unread::clear_unread,
+            fault_injection::inject_failure,
+            fault_injection::list_active_injections,
+            fault_injection::clear_injections,
+            proxy::set_proxy_mode,
+            proxy::set_manual_proxy,
+            proxy::get_resolved_proxy,
+            benchmark::benchmark_backend,
+            recent_errors::get_recent_errors,
+            onboarding::get_onboarding_state,
+            onboarding::mark_onboarding_step,
+            onboarding::complete_onboarding,
+            discovery::get_discovery_info,
+            backend_env::set_env_passthrough,
+            backend_env::set_env_extra,
+            performance::set_backend_performance,
+            performance::get_backend_stats,
+            loopback::set_loopback_host,
+            startup::get_startup_report,
+            action_queue::get_pending_actions,
+            status_summary::get_status_summary,
+            action_queue::cancel_pending_action,
+            backend_update::check_backend_update,
+            backend_update::install_backend_update,
+            wsl::set_backend_host,
+            wsl::set_wsl_distro,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::delete_profile,
+            profiles::switch_profile,
             set_shell_enabled,
+            shell_policy::set_shell_policy,
+            shell_policy::test_shell_policy,
+            shell_policy::request_shell_approval,
+            set_auto_start_backend,
+            reset_local_config,
             retry_backend,
+            seamless_restart::restart_backend,
+            seamless_restart::set_seamless_restart,
+            start_backend,
+            stop_backend_command,
+            leave_safe_mode,
+            data_dir_lock::force_takeover,
+            export_diagnostics,
+            run_self_check,
+            migrations::get_migration_history,
             read_backend_logs
-        ])
+        ]))
         .build(tauri::generate_context!())
         .expect("failed to build LiteClaw desktop app")
         .run(|app, event| match event {
-            tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit => {
-                let state = app.state::<AppState>();
-                match state.runtime.lock() {
-                    Ok(mut runtime) => {
-                        stop_backend(&mut runtime);
-                    }
-                    Err(_) => {}
-                };
+            tauri::RunEvent::ExitRequested { code, api, .. }
+                if shutdown::on_exit_requested(app, code) =>
+            {
+                api.prevent_exit()
+            }
+            tauri::RunEvent::Exit => shutdown::on_exit(app),
+            tauri::RunEvent::WindowEvent {
+                label,
+                event: tauri::WindowEvent::Focused(true),
+                ..
+            } if label == "main" => unread::clear(app),
+            #[cfg(target_os = "macos")]
+            tauri::RunEvent::Opened { urls } => {
+                let paths: Vec<PathBuf> =
+                    urls.iter().filter_map(|url| url.to_file_path().ok()).collect();
+                session_file::open_session_paths(app, &paths);
             }
             _ => {}
         });
@@ -0,0 +1,74 @@
+//! Process-wide counters for behaviour that is otherwise invisible, exposed
+//! to the frontend via `get_metrics` and to scrapers via `status_server`.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{request_limiter, response_limit};
+
+pub struct Metrics {
+    pub backend_reloads: AtomicU64,
+    pub backend_reloads_coalesced: AtomicU64,
+    /// Automatic restarts after the backend exited on its own.
+    pub backend_restarts: AtomicU64,
+    /// `list_folder` answered from the listing cache, or not.
+    pub folder_listing_hits: AtomicU64,
+    pub folder_listing_misses: AtomicU64,
+    /// Commands over the `slow_commands` threshold.
+    pub slow_commands: AtomicU64,
+    /// Results over `response_limit`'s cap, written to a file instead.
+    pub responses_spilled: AtomicU64,
+    /// Results over the cap refused in favour of a streaming command.
+    pub responses_refused: AtomicU64,
+}
+
+pub static METRICS: Metrics = Metrics {
+    backend_reloads: AtomicU64::new(0),
+    backend_reloads_coalesced: AtomicU64::new(0),
+    backend_restarts: AtomicU64::new(0),
+    folder_listing_hits: AtomicU64::new(0),
+    folder_listing_misses: AtomicU64::new(0),
+    slow_commands: AtomicU64::new(0),
+    responses_spilled: AtomicU64::new(0),
+    responses_refused: AtomicU64::new(0),
+};
+
+pub fn increment(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    pub backend_reloads: u64,
+    pub backend_reloads_coalesced: u64,
+    pub backend_restarts: u64,
+    pub folder_listing_hits: u64,
+    pub folder_listing_misses: u64,
+    pub slow_commands: u64,
+    pub responses_spilled: u64,
+    pub responses_refused: u64,
+    /// `max_response_bytes` in effect; 0 when results are not capped.
+    pub max_response_bytes: u64,
+    /// Requests in flight and waiting per `request_limiter` pool.
+    pub backend_pools: request_limiter::Occupancy,
+}
+
+pub fn snapshot() -> MetricsSnapshot {
+    MetricsSnapshot {
+        backend_reloads: METRICS.backend_reloads.load(Ordering::Relaxed),
+        backend_reloads_coalesced: METRICS.backend_reloads_coalesced.load(Ordering::Relaxed),
+        backend_restarts: METRICS.backend_restarts.load(Ordering::Relaxed),
+        folder_listing_hits: METRICS.folder_listing_hits.load(Ordering::Relaxed),
+        folder_listing_misses: METRICS.folder_listing_misses.load(Ordering::Relaxed),
+        slow_commands: METRICS.slow_commands.load(Ordering::Relaxed),
+        responses_spilled: METRICS.responses_spilled.load(Ordering::Relaxed),
+        responses_refused: METRICS.responses_refused.load(Ordering::Relaxed),
+        max_response_bytes: response_limit::limit(),
+        backend_pools: request_limiter::occupancy(),
+    }
+}
+
+#[tauri::command]
+pub fn get_metrics() -> MetricsSnapshot {
+    snapshot()
+}
@@ -0,0 +1,132 @@
+//! What automatic migrations changed, for a "what changed" notice after an
+//! update. Each migration that actually changes something (the folder
+//! layout move, legacy config conversion) appends one JSON line to
+//! `migrations.log` in the data root, which spans profiles like the layout
+//! move does. Entries carry an id assigned when written, so the frontend can
+//! remember which ones it has shown. `get_migration_history` lists them
+//! newest first; entries written this session are emitted as
+//! `migrations-applied` once the main window has loaded.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use crate::{layout, unix_now};
+
+const FILE_NAME: &str = "migrations.log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationKind {
+    DataLayout,
+    LegacyConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Migration {
+    pub id: String,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub ts: u64,
+    pub kind: MigrationKind,
+    pub description: String,
+}
+
+/// Entries written this session, for `migrations-applied`.
+static APPLIED: Mutex<Vec<Migration>> = Mutex::new(Vec::new());
+
+fn log_path(root: &Path) -> PathBuf {
+    root.join(FILE_NAME)
+}
+
+fn append(root: &Path, migration: &Migration) -> Result<(), String> {
+    let mut line = serde_json::to_string(migration)
+        .map_err(|e| format!("failed serializing migration: {e}"))?;
+    line.push('\n');
+    fs::create_dir_all(root).map_err(|e| format!("failed creating data root: {e}"))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path(root))
+        .map_err(|e| format!("failed opening {FILE_NAME}: {e}"))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("failed writing {FILE_NAME}: {e}"))
+}
+
+/// Newest first; lines that do not parse are skipped.
+fn read_history(root: &Path) -> Vec<Migration> {
+    let Ok(content) = fs::read_to_string(log_path(root)) else {
+        return Vec::new();
+    };
+    let mut history: Vec<Migration> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    history.reverse();
+    history
+}
+
+/// Records a migration that just ran. Needs `layout::init` to have run.
+pub fn record(kind: MigrationKind, description: impl Into<String>) -> Result<(), String> {
+    let root = layout::root().ok_or_else(|| "data root is not resolved yet".to_string())?;
+    let migration = Migration {
+        id: Uuid::new_v4().to_string(),
+        ts: unix_now(),
+        kind,
+        description: description.into(),
+    };
+    append(&root, &migration)?;
+    if let Ok(mut applied) = APPLIED.lock() {
+        applied.push(migration);
+    }
+    Ok(())
+}
+
+/// Emits this session's migrations, if there were any.
+pub fn emit_applied(app: &AppHandle) {
+    let applied = APPLIED
+        .lock()
+        .map(|applied| applied.clone())
+        .unwrap_or_default();
+    if !applied.is_empty() {
+        let _ = app.emit("migrations-applied", applied);
+    }
+}
+
+#[tauri::command]
+pub fn get_migration_history() -> Vec<Migration> {
+    layout::root()
+        .map(|root| read_history(&root))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_is_newest_first_and_skips_damaged_lines() {
+        let root = std::env::temp_dir().join(format!("liteclaw-migrations-{}", Uuid::new_v4()));
+        let entry = |ts, description: &str| Migration {
+            id: Uuid::new_v4().to_string(),
+            ts,
+            kind: MigrationKind::LegacyConfig,
+            description: description.to_string(),
+        };
+        append(&root, &entry(1, "first")).unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(log_path(&root))
+            .unwrap();
+        file.write_all(b"{\"id\": \"trunc\n").unwrap();
+        append(&root, &entry(2, "second")).unwrap();
+
+        let history = read_history(&root);
+        let descriptions: Vec<&str> = history.iter().map(|m| m.description.as_str()).collect();
+        assert_eq!(descriptions, ["second", "first"]);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,271 @@
+//! Model and provider choice. The backend owns it, in its models registry,
+//! and the desktop proxies `/v1/models` and `/v1/config/model`. The last
+//! known selection is cached as `model_selection` in config, so the settings
+//! page can show it before the backend answers and while it is down.
+//!
+//! Providers other than `local` need an API key in the OS keychain, stored
+//! with `set_provider_api_key`. A selection whose provider has none is still
+//! saved but comes back with a `missing_api_key` warning. The backend
+//! refuses to switch while a task is executing, which surfaces here as
+//! `generation_in_progress`.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::config_crypto::KEYRING_SERVICE;
+use crate::error::{CommandError, ErrorCode};
+use crate::{backend_http, backend_reload_config, commit_config, read_local_config, AppState};
+
+/// Runs installed models; needs no key.
+const LOCAL_PROVIDER: &str = "local";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelSelection {
+    pub provider: String,
+    pub model_id: String,
+    #[serde(default)]
+    pub params: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledModel {
+    pub model_id: String,
+    pub display_name: String,
+    pub local_path: Option<String>,
+    pub status: String,
+}
+
+/// `/v1/models` as the backend returns it.
+#[derive(Debug, Clone, Deserialize)]
+struct ModelsState {
+    #[serde(default)]
+    installed_models: Vec<InstalledModel>,
+    default_model_id: Option<String>,
+    selection: Option<ModelSelection>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelWarning {
+    /// The provider needs an API key and the keychain has none for it.
+    MissingApiKey,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelSettings {
+    pub selection: Option<ModelSelection>,
+    /// `None` when the backend did not answer; `selection` is then the
+    /// cached one.
+    pub installed_models: Option<Vec<InstalledModel>>,
+    pub default_model_id: Option<String>,
+    pub from_cache: bool,
+    pub warnings: Vec<ModelWarning>,
+}
+
+fn validate_provider(provider: &str) -> Result<(), CommandError> {
+    let valid = !provider.is_empty()
+        && provider.len() <= 64
+        && provider
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(CommandError::invalid_input(
+            "provider must be 1-64 letters, digits, '-' or '_'",
+        ));
+    }
+    Ok(())
+}
+
+fn key_entry(provider: &str) -> Result<keyring::Entry, CommandError> {
+    keyring::Entry::new(KEYRING_SERVICE, &format!("provider-api-key:{provider}"))
+        .map_err(|e| format!("OS keychain unavailable: {e}").into())
+}
+
+fn has_api_key(provider: &str) -> Result<bool, CommandError> {
+    match key_entry(provider)?.get_password() {
+        Ok(key) => Ok(!key.is_empty()),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(err) => Err(format!("failed reading the {provider} API key: {err}").into()),
+    }
+}
+
+fn warnings(selection: Option<&ModelSelection>) -> Result<Vec<ModelWarning>, CommandError> {
+    let Some(selection) = selection else {
+        return Ok(Vec::new());
+    };
+    if selection.provider == LOCAL_PROVIDER || has_api_key(&selection.provider)? {
+        return Ok(Vec::new());
+    }
+    Ok(vec![ModelWarning::MissingApiKey])
+}
+
+struct Target {
+    base_url: String,
+    token: String,
+    data_dir: PathBuf,
+    ready: bool,
+}
+
+fn target(state: &State<'_, AppState>) -> Result<Target, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(Target {
+        base_url: runtime.base_url.clone(),
+        token: runtime.token.clone(),
+        data_dir: runtime.data_dir.clone(),
+        ready: runtime.backend_ready,
+    })
+}
+
+fn fetch_models(target: &Target) -> Result<ModelsState, String> {
+    let endpoint = "/v1/models";
+    let request = backend_http::agent()
+        .get(&format!("{}{endpoint}", target.base_url))
+        .set("Authorization", &format!("Bearer {}", target.token));
+    let sent = backend_http::send_idempotent(endpoint, request, None);
+    let note = sent.attempts_note();
+    let response = sent
+        .result
+        .map_err(|e| format!("{endpoint} failed{note}: {e}"))?;
+    let body = response
+        .into_string()
+        .map_err(|e| format!("failed reading {endpoint}: {e}"))?;
+    serde_json::from_str(&body).map_err(|e| format!("unexpected {endpoint} response: {e}"))
+}
+
+fn post_selection(target: &Target, selection: &ModelSelection) -> Result<(), CommandError> {
+    let endpoint = "/v1/config/model";
+    let body = serde_json::to_string(selection)
+        .map_err(|e| format!("failed serializing model selection: {e}"))?;
+    let request = backend_http::agent()
+        .post(&format!("{}{endpoint}", target.base_url))
+        .set("Authorization", &format!("Bearer {}", target.token))
+        .set("Content-Type", "application/json");
+    let sent = backend_http::send_idempotent(endpoint, request, Some(&body));
+    backend_http::rejected(&sent.result)?;
+    let note = sent.attempts_note();
+    match sent.result {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(409, _)) => Err(CommandError::new(
+            ErrorCode::GenerationInProgress,
+            "the model cannot be changed while a task is running",
+        )),
+        Err(ureq::Error::Status(404, _)) => Err(CommandError::not_found(format!(
+            "model {} is not installed",
+            selection.model_id
+        ))),
+        Err(ureq::Error::Status(code, _)) => {
+            Err(format!("{endpoint} failed: HTTP {code}{note}").into())
+        }
+        Err(err) => Err(CommandError::new(
+            ErrorCode::BackendUnavailable,
+            format!("{endpoint} failed{note}: {err}"),
+        )),
+    }
+}
+
+/// Remembers `selection` as the last known one, if it changed.
+fn cache(target: &Target, selection: &Option<ModelSelection>) -> Result<(), CommandError> {
+    let mut config = read_local_config(&target.data_dir)?;
+    if config.model_selection == *selection {
+        return Ok(());
+    }
+    config.model_selection = selection.clone();
+    commit_config(&target.data_dir, &config)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_model_settings(state: State<'_, AppState>) -> Result<ModelSettings, CommandError> {
+    let target = target(&state)?;
+    let cached = read_local_config(&target.data_dir)?.model_selection;
+    let models = if target.ready {
+        fetch_models(&target).ok()
+    } else {
+        None
+    };
+    let settings = match models {
+        Some(models) => {
+            cache(&target, &models.selection)?;
+            ModelSettings {
+                warnings: warnings(models.selection.as_ref())?,
+                selection: models.selection,
+                installed_models: Some(models.installed_models),
+                default_model_id: models.default_model_id,
+                from_cache: false,
+            }
+        }
+        None => ModelSettings {
+            warnings: warnings(cached.as_ref())?,
+            selection: cached,
+            installed_models: None,
+            default_model_id: None,
+            from_cache: true,
+        },
+    };
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn set_model_settings(
+    state: State<'_, AppState>,
+    provider: String,
+    model: String,
+    params: Option<serde_json::Map<String, serde_json::Value>>,
+) -> Result<ModelSettings, CommandError> {
+    validate_provider(&provider)?;
+    if model.trim().is_empty() {
+        return Err(CommandError::invalid_input("model must not be empty"));
+    }
+    let selection = ModelSelection {
+        provider,
+        model_id: model,
+        params: params.unwrap_or_default(),
+    };
+    let target = target(&state)?;
+    if !target.ready {
+        return Err(CommandError::new(
+            ErrorCode::BackendUnavailable,
+            "the backend must be running to change the model",
+        ));
+    }
+    post_selection(&target, &selection)?;
+    cache(&target, &Some(selection.clone()))?;
+    {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        let config = read_local_config(&runtime.data_dir)?;
+        backend_reload_config(&runtime, &config)?;
+    }
+    let models = fetch_models(&target).ok();
+    Ok(ModelSettings {
+        warnings: warnings(Some(&selection))?,
+        selection: Some(selection),
+        installed_models: models
+            .as_ref()
+            .map(|models| models.installed_models.clone()),
+        default_model_id: models.and_then(|models| models.default_model_id),
+        from_cache: false,
+    })
+}
+
+/// Stores or, with `None`, removes `provider`'s API key in the keychain.
+#[tauri::command]
+pub fn set_provider_api_key(provider: String, api_key: Option<String>) -> Result<(), CommandError> {
+    validate_provider(&provider)?;
+    let entry = key_entry(&provider)?;
+    match api_key.filter(|key| !key.trim().is_empty()) {
+        Some(key) => entry
+            .set_password(key.trim())
+            .map_err(|e| format!("failed storing the {provider} API key: {e}").into()),
+        None => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(format!("failed removing the {provider} API key: {err}").into()),
+        },
+    }
+}
@@ -0,0 +1,775 @@
+//! Browser extension bridge. Browsers start a native-messaging host with a
+//! manifest pointing at a launcher script, which re-runs this executable
+//! with `--native-messaging`; it then speaks the browser's stdio protocol
+//! (a native-endian `u32` length followed by that many bytes of JSON).
+//! Each accepted message is written to an exchange directory in the app
+//! data dir, private to the user (0700, owned by them, checked before every
+//! use and refused otherwise), and
+//! handed to the desktop by launching the executable again with
+//! `--native-message-request=<id>`: the single-instance plugin forwards that
+//! to the running window, or the launch becomes the window. The desktop
+//! answers by writing `<id>.response.json` next to the request.
+//!
+//! Only the actions in `Action` exist, every field is bounded, and nothing
+//! is acted on unless `native_messaging.enabled` is set in config.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use uuid::Uuid;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{
+    attachments, audit, backend_state, commit_config, layout, read_local_config, unix_now,
+    AppState, LocalConfig,
+};
+
+pub const HOST_FLAG: &str = "--native-messaging";
+const REQUEST_FLAG: &str = "--native-message-request=";
+/// Manifest `name`; browsers allow only lowercase letters, digits, `_`, `.`.
+const HOST_NAME: &str = "dev.liteclaw.desktop";
+/// Below the app data dir; `native-messaging/` in a data dir holds the
+/// launcher script.
+const EXCHANGE_DIR: &str = "native-messaging-exchange";
+
+/// Chrome caps messages to a host at 4 GiB; nothing here needs more than this.
+const MAX_MESSAGE_BYTES: u32 = 256 * 1024;
+const MAX_TEXT_CHARS: usize = 100_000;
+const MAX_URL_CHARS: usize = 2048;
+const MAX_TITLE_CHARS: usize = 512;
+const MAX_ID_CHARS: usize = 64;
+const MAX_EXTENSION_IDS: usize = 16;
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+const REPLY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NativeMessagingConfig {
+    pub enabled: bool,
+    /// Chrome, Chromium and Edge extension ids allowed to connect.
+    pub chrome_extension_ids: Vec<String>,
+    /// Firefox add-on ids (`name@example.org` or `{uuid}`) allowed to connect.
+    pub firefox_extension_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Action {
+    Ask,
+    AddSelectionAsAttachment,
+    GetStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    /// Echoed back so the extension can match replies to messages.
+    #[serde(default)]
+    id: Option<String>,
+    action: Action,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+fn check_len(field: &str, value: &Option<String>, max: usize) -> Result<(), String> {
+    match value {
+        Some(value) if value.chars().count() > max => {
+            Err(format!("`{field}` must be at most {max} characters"))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Request {
+    fn validate(&self) -> Result<(), String> {
+        check_len("id", &self.id, MAX_ID_CHARS)?;
+        check_len("text", &self.text, MAX_TEXT_CHARS)?;
+        check_len("url", &self.url, MAX_URL_CHARS)?;
+        check_len("title", &self.title, MAX_TITLE_CHARS)?;
+        if let Some(url) = &self.url {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err("`url` must be an http(s) URL".to_string());
+            }
+        }
+        let has_text = self
+            .text
+            .as_deref()
+            .is_some_and(|text| !text.trim().is_empty());
+        match self.action {
+            Action::Ask | Action::AddSelectionAsAttachment if !has_text => {
+                Err("`text` is required for this action".to_string())
+            }
+            Action::GetStatus if self.text.is_some() => {
+                Err("`get-status` takes no `text`".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn parse_request(bytes: &[u8]) -> Result<Request, String> {
+    let request: Request =
+        serde_json::from_slice(bytes).map_err(|e| format!("invalid message: {e}"))?;
+    request.validate()?;
+    Ok(request)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    id: Option<String>,
+    ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Response {
+    fn ok(id: Option<String>, result: Value) -> Self {
+        Self {
+            id,
+            ok: true,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    fn error(id: Option<String>, error: impl Into<String>) -> Self {
+        Self {
+            id,
+            ok: false,
+            result: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+fn request_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.request.json"))
+}
+
+fn response_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.response.json"))
+}
+
+/// Creates `dir` for this user alone, or fails when what is there could be
+/// read or written by anyone else.
+#[cfg(unix)]
+fn ensure_private_dir(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(err) => return Err(format!("failed creating {}: {err}", dir.display())),
+    }
+    let meta =
+        fs::symlink_metadata(dir).map_err(|e| format!("failed checking {}: {e}", dir.display()))?;
+    // SAFETY: no arguments; cannot fail.
+    let uid = unsafe { libc::geteuid() };
+    if !meta.is_dir() || meta.uid() != uid {
+        return Err(format!(
+            "{} is not a folder owned by this user",
+            dir.display()
+        ));
+    }
+    if meta.mode() & 0o077 != 0 {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+            .map_err(|e| format!("failed making {} private: {e}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// `%APPDATA%` is already private to the user.
+#[cfg(not(unix))]
+fn ensure_private_dir(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("failed creating {}: {e}", dir.display()))
+}
+
+/// The exchange directory, checked for both sides before each use.
+fn exchange_dir() -> Result<PathBuf, String> {
+    let root = layout::app_data_dir()
+        .ok_or_else(|| "cannot tell where LiteClaw keeps its data".to_string())?;
+    fs::create_dir_all(&root).map_err(|e| format!("failed creating {}: {e}", root.display()))?;
+    let dir = root.join(EXCHANGE_DIR);
+    ensure_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Writes `bytes` so the other side never reads a partial file.
+fn write_exchange_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, bytes).map_err(|e| format!("failed writing {}: {e}", temp.display()))?;
+    fs::rename(&temp, path).map_err(|e| format!("failed writing {}: {e}", path.display()))
+}
+
+// --- host side: runs in the process the browser starts, without Tauri ---
+
+/// One message from the browser; `None` once it closes the pipe.
+fn read_message(reader: &mut impl Read) -> Result<Option<Vec<u8>>, String> {
+    let mut header = [0u8; 4];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(format!("failed reading message: {err}")),
+    }
+    let len = u32::from_ne_bytes(header);
+    if len > MAX_MESSAGE_BYTES {
+        return Err(format!(
+            "message of {len} bytes exceeds the {MAX_MESSAGE_BYTES}-byte limit"
+        ));
+    }
+    let mut body = vec![0u8; len as usize];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| format!("failed reading message: {e}"))?;
+    Ok(Some(body))
+}
+
+fn write_message(writer: &mut impl Write, response: &Response) -> io::Result<()> {
+    let body = serde_json::to_vec(response)?;
+    writer.write_all(&(body.len() as u32).to_ne_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()
+}
+
+/// Hands `request` to the desktop and waits for its answer.
+fn forward(request: &Request) -> Result<Response, String> {
+    let dir = exchange_dir()?;
+    let id = Uuid::new_v4().to_string();
+    let body = serde_json::to_vec(request).map_err(|e| format!("failed encoding request: {e}"))?;
+    write_exchange_file(&request_path(&dir, &id), &body)?;
+    let exe = std::env::current_exe().map_err(|e| format!("cannot locate LiteClaw: {e}"))?;
+    // Exits at once when forwarded to a running instance; otherwise it is
+    // the instance, and outlives this host.
+    Command::new(exe)
+        .arg(format!("{REQUEST_FLAG}{id}"))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed starting LiteClaw: {e}"))?;
+
+    let deadline = Instant::now() + REPLY_TIMEOUT;
+    while Instant::now() < deadline {
+        if let Ok(bytes) = fs::read(response_path(&dir, &id)) {
+            let _ = fs::remove_file(response_path(&dir, &id));
+            return serde_json::from_slice(&bytes).map_err(|e| format!("invalid reply: {e}"));
+        }
+        thread::sleep(REPLY_POLL_INTERVAL);
+    }
+    let _ = fs::remove_file(request_path(&dir, &id));
+    Err("LiteClaw did not answer in time".to_string())
+}
+
+/// The `--native-messaging` entry point; returns the process exit code.
+pub fn run_host() -> i32 {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    loop {
+        let message = match read_message(&mut stdin) {
+            Ok(Some(message)) => message,
+            Ok(None) => return 0,
+            Err(err) => {
+                // The stream cannot be resynchronized after a bad header.
+                let _ = write_message(&mut stdout, &Response::error(None, err));
+                return 1;
+            }
+        };
+        let response = match parse_request(&message) {
+            Ok(request) => {
+                forward(&request).unwrap_or_else(|err| Response::error(request.id.clone(), err))
+            }
+            Err(err) => Response::error(None, err),
+        };
+        if write_message(&mut stdout, &response).is_err() {
+            return 1;
+        }
+    }
+}
+
+// --- desktop side ---
+
+/// Requests found in a first launch, served once the window has loaded.
+static LAUNCH_REQUESTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn request_ids_in_args(args: &[String]) -> Vec<String> {
+    args.iter()
+        .filter_map(|arg| arg.strip_prefix(REQUEST_FLAG))
+        .filter(|id| Uuid::parse_str(id).is_ok())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Called from `setup` with the launch arguments.
+pub fn queue_launch_requests(args: &[String]) {
+    if let Ok(mut queued) = LAUNCH_REQUESTS.lock() {
+        queued.extend(request_ids_in_args(args));
+    }
+}
+
+/// Serves requests queued at launch; called when the main window loads.
+pub fn serve_queued(app: &AppHandle) {
+    let ids = match LAUNCH_REQUESTS.lock() {
+        Ok(mut queued) => std::mem::take(&mut *queued),
+        Err(_) => return,
+    };
+    serve_ids(app, ids);
+}
+
+/// Serves requests forwarded by the single-instance plugin.
+pub fn serve_args(app: &AppHandle, args: &[String]) {
+    serve_ids(app, request_ids_in_args(args));
+}
+
+fn serve_ids(app: &AppHandle, ids: Vec<String>) {
+    for id in ids {
+        let app = app.clone();
+        thread::spawn(move || serve(&app, &id));
+    }
+}
+
+fn serve(app: &AppHandle, id: &str) {
+    // A request in a folder someone else could write to is not served.
+    let Ok(dir) = exchange_dir() else {
+        return;
+    };
+    let path = request_path(&dir, id);
+    let Ok(bytes) = fs::read(&path) else {
+        return;
+    };
+    let _ = fs::remove_file(&path);
+    let response = match parse_request(&bytes) {
+        Ok(request) => {
+            let request_id = request.id.clone();
+            dispatch(app, request).unwrap_or_else(|err| Response::error(request_id, err))
+        }
+        Err(err) => Response::error(None, err),
+    };
+    if let Ok(body) = serde_json::to_vec(&response) {
+        let _ = write_exchange_file(&response_path(&dir, id), &body);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct NativeAsk {
+    text: String,
+    url: Option<String>,
+    title: Option<String>,
+}
+
+fn focus_main(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn selection_body(request: &Request) -> String {
+    let text = request.text.clone().unwrap_or_default();
+    match (&request.title, &request.url) {
+        (Some(title), Some(url)) => format!("{text}\n\n-- {title} <{url}>\n"),
+        (None, Some(url)) => format!("{text}\n\n-- <{url}>\n"),
+        _ => text,
+    }
+}
+
+fn dispatch(app: &AppHandle, request: Request) -> Result<Response, String> {
+    let state = app.state::<AppState>();
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let config = read_local_config(&runtime.data_dir).map_err(|e| e.message)?;
+    if !config.native_messaging.enabled {
+        return Err("the browser integration is turned off in LiteClaw settings".to_string());
+    }
+    let action = serde_json::to_value(request.action).unwrap_or_default();
+    let _ = audit::record(
+        &runtime.data_dir,
+        "native_message_received",
+        json!({ "action": action, "url": request.url }),
+    );
+    let result = match request.action {
+        Action::GetStatus => json!({
+            "app_version": env!("CARGO_PKG_VERSION"),
+            "backend_state": backend_state(&runtime),
+            "profile": runtime.profile,
+        }),
+        Action::Ask => {
+            let ask = NativeAsk {
+                text: request.text.clone().unwrap_or_default(),
+                url: request.url.clone(),
+                title: request.title.clone(),
+            };
+            let _ = app.emit("native-messaging-ask", &ask);
+            focus_main(app);
+            json!({ "delivered": true })
+        }
+        Action::AddSelectionAsAttachment => {
+            let file_name = format!("selection-{}.txt", unix_now());
+            let staged = attachments::stage_bytes(
+                &runtime.data_dir,
+                &file_name,
+                selection_body(&request).as_bytes(),
+            )?;
+            let _ = app.emit("native-messaging-attachment", &staged);
+            serde_json::to_value(&staged).unwrap_or_default()
+        }
+    };
+    Ok(Response::ok(request.id, result))
+}
+
+fn validate_extension_id(id: &str, firefox: bool) -> Result<String, CommandError> {
+    let id = id.trim();
+    let valid = if firefox {
+        !id.is_empty()
+            && id.len() <= 255
+            && (id.contains('@') || (id.starts_with('{') && id.ends_with('}')))
+            && !id.contains(char::is_whitespace)
+    } else {
+        id.len() == 32 && id.bytes().all(|b| (b'a'..=b'p').contains(&b))
+    };
+    if !valid {
+        return Err(CommandError::invalid_input(format!(
+            "`{id}` is not a valid {} extension id",
+            if firefox { "Firefox" } else { "Chrome" }
+        )));
+    }
+    Ok(id.to_string())
+}
+
+pub fn validate_extension_ids(
+    ids: Vec<String>,
+    firefox: bool,
+) -> Result<Vec<String>, CommandError> {
+    if ids.len() > MAX_EXTENSION_IDS {
+        return Err(CommandError::invalid_input(format!(
+            "at most {MAX_EXTENSION_IDS} extension ids are allowed"
+        )));
+    }
+    let mut valid = Vec::with_capacity(ids.len());
+    for id in ids {
+        let id = validate_extension_id(&id, firefox)?;
+        if !valid.contains(&id) {
+            valid.push(id);
+        }
+    }
+    Ok(valid)
+}
+
+/// Turns the integration on or off; id lists left `None` are kept.
+#[tauri::command]
+pub fn set_native_messaging(
+    state: State<'_, AppState>,
+    enabled: bool,
+    chrome_extension_ids: Option<Vec<String>>,
+    firefox_extension_ids: Option<Vec<String>>,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.native_messaging.enabled = enabled;
+    if let Some(ids) = chrome_extension_ids {
+        config.native_messaging.chrome_extension_ids = validate_extension_ids(ids, false)?;
+    }
+    if let Some(ids) = firefox_extension_ids {
+        config.native_messaging.firefox_extension_ids = validate_extension_ids(ids, true)?;
+    }
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Browser {
+    Chrome,
+    Chromium,
+    Edge,
+    Firefox,
+}
+
+impl Browser {
+    fn is_firefox(self) -> bool {
+        matches!(self, Browser::Firefox)
+    }
+
+    #[cfg(windows)]
+    fn registry_key(self) -> String {
+        let vendor = match self {
+            Browser::Chrome => r"Google\Chrome",
+            Browser::Chromium => "Chromium",
+            Browser::Edge => r"Microsoft\Edge",
+            Browser::Firefox => "Mozilla",
+        };
+        format!(r"HKCU\Software\{vendor}\NativeMessagingHosts\{HOST_NAME}")
+    }
+}
+
+/// Directory the browser reads per-user host manifests from.
+#[cfg(not(windows))]
+fn manifest_dir(browser: Browser, _data_dir: &Path) -> Result<PathBuf, CommandError> {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| CommandError::from("HOME is not set".to_string()))?;
+    #[cfg(target_os = "macos")]
+    let dir = {
+        let support = home.join("Library/Application Support");
+        match browser {
+            Browser::Chrome => support.join("Google/Chrome/NativeMessagingHosts"),
+            Browser::Chromium => support.join("Chromium/NativeMessagingHosts"),
+            Browser::Edge => support.join("Microsoft Edge/NativeMessagingHosts"),
+            Browser::Firefox => support.join("Mozilla/NativeMessagingHosts"),
+        }
+    };
+    #[cfg(not(target_os = "macos"))]
+    let dir = {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| home.join(".config"));
+        match browser {
+            Browser::Chrome => config.join("google-chrome/NativeMessagingHosts"),
+            Browser::Chromium => config.join("chromium/NativeMessagingHosts"),
+            Browser::Edge => config.join("microsoft-edge/NativeMessagingHosts"),
+            Browser::Firefox => home.join(".mozilla/native-messaging-hosts"),
+        }
+    };
+    Ok(dir)
+}
+
+/// Windows reads the manifest from wherever the registry points.
+#[cfg(windows)]
+fn manifest_dir(_browser: Browser, data_dir: &Path) -> Result<PathBuf, CommandError> {
+    Ok(data_dir.join("native-messaging"))
+}
+
+/// Browsers cannot pass arguments to a host, so the manifest points at a
+/// script that adds `--native-messaging`.
+fn write_launcher(data_dir: &Path) -> Result<PathBuf, CommandError> {
+    let exe = std::env::current_exe().map_err(|e| format!("cannot locate LiteClaw: {e}"))?;
+    let dir = data_dir.join("native-messaging");
+    fs::create_dir_all(&dir).map_err(|e| format!("failed creating {}: {e}", dir.display()))?;
+    #[cfg(windows)]
+    let (path, script) = (
+        dir.join("liteclaw-native-host.bat"),
+        format!("@echo off\r\n\"{}\" {HOST_FLAG} %*\r\n", exe.display()),
+    );
+    #[cfg(not(windows))]
+    let (path, script) = (
+        dir.join("liteclaw-native-host"),
+        format!(
+            "#!/bin/sh\nexec '{}' {HOST_FLAG} \"$@\"\n",
+            exe.display().to_string().replace('\'', r"'\''")
+        ),
+    );
+    fs::write(&path, script).map_err(|e| format!("failed writing {}: {e}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("failed making {} executable: {e}", path.display()))?;
+    }
+    Ok(path)
+}
+
+fn manifest(browser: Browser, launcher: &Path, config: &NativeMessagingConfig) -> Value {
+    let mut manifest = json!({
+        "name": HOST_NAME,
+        "description": "LiteClaw desktop",
+        "path": launcher.to_string_lossy(),
+        "type": "stdio",
+    });
+    if browser.is_firefox() {
+        manifest["allowed_extensions"] = json!(config.firefox_extension_ids);
+    } else {
+        let origins: Vec<String> = config
+            .chrome_extension_ids
+            .iter()
+            .map(|id| format!("chrome-extension://{id}/"))
+            .collect();
+        manifest["allowed_origins"] = json!(origins);
+    }
+    manifest
+}
+
+#[cfg(windows)]
+fn register(browser: Browser, manifest_path: &Path) -> Result<(), CommandError> {
+    let status = Command::new("reg")
+        .args(["add", &browser.registry_key(), "/ve", "/t", "REG_SZ", "/d"])
+        .arg(manifest_path)
+        .arg("/f")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| format!("failed running reg.exe: {e}"))?;
+    if !status.success() {
+        return Err(format!("reg.exe failed registering the host ({status})").into());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledHost {
+    pub browser: Browser,
+    pub manifest_path: String,
+    pub launcher_path: String,
+}
+
+#[tauri::command]
+pub fn install_native_messaging_host(
+    state: State<'_, AppState>,
+    browser: Browser,
+) -> Result<InstalledHost, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let config = read_local_config(&runtime.data_dir)?.native_messaging;
+    let ids = if browser.is_firefox() {
+        &config.firefox_extension_ids
+    } else {
+        &config.chrome_extension_ids
+    };
+    if ids.is_empty() {
+        return Err(CommandError::invalid_input(
+            "add the extension's id with `set_native_messaging` before installing the host",
+        ));
+    }
+    let launcher = write_launcher(&runtime.data_dir)?;
+    let dir = manifest_dir(browser, &runtime.data_dir)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("failed creating {}: {e}", dir.display()))?;
+    let manifest_path = dir.join(format!("{HOST_NAME}.json"));
+    let body = serde_json::to_vec_pretty(&manifest(browser, &launcher, &config))
+        .map_err(|e| format!("failed encoding manifest: {e}"))?;
+    fs::write(&manifest_path, body)
+        .map_err(|e| format!("failed writing {}: {e}", manifest_path.display()))?;
+    #[cfg(windows)]
+    register(browser, &manifest_path)?;
+
+    let installed = InstalledHost {
+        browser,
+        manifest_path: manifest_path.to_string_lossy().to_string(),
+        launcher_path: launcher.to_string_lossy().to_string(),
+    };
+    let _ = audit::record(
+        &runtime.data_dir,
+        "native_messaging_host_installed",
+        json!({ "browser": browser, "manifest_path": installed.manifest_path }),
+    );
+    Ok(installed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framed(body: &[u8]) -> Vec<u8> {
+        let mut bytes = (body.len() as u32).to_ne_bytes().to_vec();
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn messages_are_framed_by_a_native_endian_length() {
+        let mut stream = framed(br#"{"action":"get-status"}"#);
+        stream.extend(framed(b"{}"));
+        let mut reader = stream.as_slice();
+        assert_eq!(
+            read_message(&mut reader).unwrap().unwrap(),
+            br#"{"action":"get-status"}"#
+        );
+        assert_eq!(read_message(&mut reader).unwrap().unwrap(), b"{}");
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+
+        let oversized = (MAX_MESSAGE_BYTES + 1).to_ne_bytes();
+        assert!(read_message(&mut oversized.as_slice()).is_err());
+        let truncated = &framed(b"{\"action\"")[..6];
+        assert!(read_message(&mut &truncated[..]).is_err());
+
+        let mut out = Vec::new();
+        write_message(&mut out, &Response::error(None, "nope")).unwrap();
+        let body = read_message(&mut out.as_slice()).unwrap().unwrap();
+        assert!(!serde_json::from_slice::<Response>(&body).unwrap().ok);
+    }
+
+    #[test]
+    fn malformed_requests_are_rejected() {
+        assert!(
+            parse_request(br#"{"action":"ask","text":"hi","url":"https://a.example"}"#).is_ok()
+        );
+        let long_title = format!(
+            r#"{{"action":"ask","text":"hi","title":"{}"}}"#,
+            "t".repeat(MAX_TITLE_CHARS + 1)
+        );
+        for message in [
+            "not json",
+            r#"{"action":"delete-everything"}"#,
+            r#"{"action":"ask","text":"hi","extra":1}"#,
+            r#"{"action":"ask"}"#,
+            r#"{"action":"add-selection-as-attachment","text":"   "}"#,
+            r#"{"action":"get-status","text":"hi"}"#,
+            r#"{"action":"ask","text":"hi","url":"file:///etc/passwd"}"#,
+            long_title.as_str(),
+        ] {
+            assert!(parse_request(message.as_bytes()).is_err(), "{message}");
+        }
+    }
+
+    #[test]
+    fn extension_ids_are_checked_per_browser() {
+        let chrome = "abcdefghijklmnopabcdefghijklmnop";
+        assert_eq!(
+            validate_extension_id(&format!(" {chrome} "), false).unwrap(),
+            chrome
+        );
+        for id in [
+            "",
+            "abcdefghijklmnopabcdefghijklmnoq",
+            "ABCDEFGHIJKLMNOPABCDEFGHIJKLMNOP",
+        ] {
+            assert!(validate_extension_id(id, false).is_err(), "{id}");
+        }
+        assert!(validate_extension_id("clipper@example.org", true).is_ok());
+        assert!(validate_extension_id("{3f1e5c1a-0000-4000-8000-000000000000}", true).is_ok());
+        for id in ["", "clipper", "clip per@example.org", chrome] {
+            assert!(validate_extension_id(id, true).is_err(), "{id}");
+        }
+        let ids = vec![chrome.to_string(); MAX_EXTENSION_IDS + 1];
+        assert!(validate_extension_ids(ids, false).is_err());
+        let ids = vec![chrome.to_string(), chrome.to_string()];
+        assert_eq!(validate_extension_ids(ids, false).unwrap().len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_shared_exchange_dir_is_made_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("liteclaw-exchange-{}", Uuid::new_v4()));
+        ensure_private_dir(&dir).unwrap();
+        let mode = |dir: &Path| fs::metadata(dir).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), 0o700);
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        ensure_private_dir(&dir).unwrap();
+        assert_eq!(mode(&dir), 0o700);
+
+        let link = dir.with_extension("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert!(ensure_private_dir(&link).is_err());
+        fs::remove_file(&link).unwrap();
+        fs::remove_dir(&dir).unwrap();
+    }
+}
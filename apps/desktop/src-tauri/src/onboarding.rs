@@ -0,0 +1,98 @@
+//! First-run wizard progress, kept in `LocalConfig` so it survives frontend
+//! reloads. Each load of the main window emits `onboarding-required` until
+//! the wizard completes.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::config_diff::{ConfigChange, ConfigDiff};
+use crate::error::CommandError;
+use crate::{commit_config, current_data_dir, read_local_config, timestamps, AppState};
+
+/// Step identifiers the frontend may report, in wizard order.
+pub const STEPS: &[&str] = &["folders", "shell", "api_key"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OnboardingConfig {
+    pub completed: bool,
+    pub completed_steps: Vec<String>,
+    pub completed_at: Option<String>,
+}
+
+/// Emits `onboarding-required` while the wizard is unfinished. Called from
+/// the main window's page load, off the main thread, as a start holds the
+/// runtime lock.
+pub fn announce(app: &AppHandle) {
+    let Some(data_dir) = current_data_dir(app) else {
+        return;
+    };
+    let Ok(config) = read_local_config(&data_dir) else {
+        return;
+    };
+    if !config.onboarding.completed {
+        let _ = app.emit("onboarding-required", config.onboarding);
+    }
+}
+
+fn validate_step(step: &str) -> Result<(), CommandError> {
+    if STEPS.contains(&step) {
+        return Ok(());
+    }
+    Err(CommandError::invalid_input(format!(
+        "unknown onboarding step `{step}`; expected one of {}",
+        STEPS.join(", ")
+    )))
+}
+
+#[tauri::command]
+pub fn get_onboarding_state(state: State<'_, AppState>) -> Result<OnboardingConfig, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(read_local_config(&runtime.data_dir)?.onboarding)
+}
+
+#[tauri::command]
+pub fn mark_onboarding_step(
+    state: State<'_, AppState>,
+    step: String,
+) -> Result<ConfigChange<OnboardingConfig>, CommandError> {
+    validate_step(&step)?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    let mut diff = ConfigDiff::default();
+    let onboarding = &mut config.onboarding;
+    if !onboarding.completed_steps.contains(&step) {
+        onboarding.completed_steps.push(step);
+        // Stored in wizard order regardless of the order steps were done in.
+        onboarding
+            .completed_steps
+            .sort_by_key(|done| STEPS.iter().position(|known| known == done));
+        diff = commit_config(&runtime.data_dir, &config)?;
+    }
+    Ok(ConfigChange::new(config.onboarding, diff))
+}
+
+/// Steps that were skipped stay out of `completed_steps`.
+#[tauri::command]
+pub fn complete_onboarding(
+    state: State<'_, AppState>,
+) -> Result<ConfigChange<OnboardingConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    let mut diff = ConfigDiff::default();
+    if !config.onboarding.completed {
+        config.onboarding.completed = true;
+        config.onboarding.completed_at = Some(timestamps::now());
+        diff = commit_config(&runtime.data_dir, &config)?;
+    }
+    Ok(ConfigChange::new(config.onboarding, diff))
+}
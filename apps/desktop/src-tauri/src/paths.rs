@@ -0,0 +1,225 @@
+//! Path strings that survive Windows. `canonicalize` there returns verbatim
+//! `\\?\C:\...` paths, which lift the 260-character `MAX_PATH` limit and the
+//! ban on device names (`con`, `aux`, ...) but read badly and break tools
+//! that expect plain paths. Paths shown or stored are plain whenever a plain
+//! form would work, and stay verbatim when it would not; the backend is
+//! given the same string, so it can reopen what the desktop resolved.
+//! The helpers work on strings and leave non-Windows paths untouched, since
+//! those never start with `\\?\` or a drive letter.
+
+use std::path::{Path, PathBuf};
+
+/// Longest plain path Windows accepts everywhere: `CreateDirectoryW` stops
+/// 12 characters short of `MAX_PATH` (260) to leave room for an 8.3 name.
+const PLAIN_PATH_LIMIT: usize = 248;
+const VERBATIM_PREFIX: &str = r"\\?\";
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+const DEVICE_NAMES: &[&str] = &["CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$"];
+const NUMBERED_DEVICES: &[&str] = &["COM", "LPT"];
+
+/// True for names Windows maps to a device, with or without an extension
+/// (`aux`, `Con.txt`, `lpt1 `).
+pub fn is_reserved_name(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or_default();
+    let stem = stem.trim_end_matches(' ');
+    if DEVICE_NAMES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(stem))
+    {
+        return true;
+    }
+    NUMBERED_DEVICES.iter().any(|prefix| {
+        stem.get(..3)
+            .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+            && matches!(
+                &stem[3..],
+                "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" | "¹" | "²" | "³"
+            )
+    })
+}
+
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['\\', '/']).filter(|part| !part.is_empty())
+}
+
+/// Whether `plain` (no `\\?\`) only works through its verbatim form.
+fn needs_verbatim(plain: &str) -> bool {
+    plain.encode_utf16().count() >= PLAIN_PATH_LIMIT || components(plain).any(is_reserved_name)
+}
+
+/// `\\?\C:\x` to `C:\x` and `\\?\UNC\srv\share` to `\\srv\share`; `None` for
+/// anything else, including volume GUID paths that have no plain form.
+fn strip_verbatim(path: &str) -> Option<String> {
+    if let Some(rest) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+        return Some(format!(r"\\{rest}"));
+    }
+    let rest = path.strip_prefix(VERBATIM_PREFIX)?;
+    let bytes = rest.as_bytes();
+    let is_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    is_drive.then(|| rest.to_string())
+}
+
+/// The verbatim form of an absolute drive or UNC path. Verbatim paths skip
+/// all normalization, so separators are converted here.
+fn to_verbatim(path: &str) -> Option<String> {
+    if path.starts_with(VERBATIM_PREFIX) {
+        return Some(path.to_string());
+    }
+    let path = path.replace('/', "\\");
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!("{VERBATIM_UNC_PREFIX}{share}"));
+    }
+    let bytes = path.as_bytes();
+    let is_drive_absolute =
+        bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\';
+    is_drive_absolute.then(|| format!("{VERBATIM_PREFIX}{path}"))
+}
+
+/// The string to show, store and hand to the backend for `path`: plain when
+/// that works, verbatim when the path is too long or names a device.
+pub fn display_form(path: &Path) -> String {
+    let raw = path.to_string_lossy();
+    match strip_verbatim(&raw) {
+        Some(plain) if !needs_verbatim(&plain) => plain,
+        _ => raw.into_owned(),
+    }
+}
+
+/// `path` in the form file APIs accept: verbatim when the plain form would
+/// fail, unchanged otherwise.
+pub fn for_io(path: &Path) -> PathBuf {
+    let raw = path.to_string_lossy();
+    if !needs_verbatim(&raw) {
+        return path.to_path_buf();
+    }
+    to_verbatim(&raw).map_or_else(|| path.to_path_buf(), PathBuf::from)
+}
+
+/// Sibling temp file for an atomic replace of `path`. The leading dot keeps
+/// it from turning into a device name when the target is `aux.json`.
+pub fn temp_sibling(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.tmp"))
+}
+
+/// Canonical form of an existing directory, as `display_form` renders it.
+pub fn canonical_dir(path: &str) -> Result<String, String> {
+    let raw = for_io(Path::new(path));
+    if !raw.is_dir() {
+        return Err(format!("not a folder: {path}"));
+    }
+    let canonical = raw
+        .canonicalize()
+        .map_err(|e| format!("failed to canonicalize folder {path}: {e}"))?;
+    Ok(display_form(&canonical))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_names_are_reserved_with_any_extension() {
+        for name in ["con", "AUX", "Nul.txt", "com1", "LPT9.log", "prn ", "com²"] {
+            assert!(is_reserved_name(name), "{name}");
+        }
+        for name in ["console", "auxiliary", "com0", "com10", "lpt", "a.con", ""] {
+            assert!(!is_reserved_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn verbatim_prefix_is_dropped_only_when_the_plain_path_works() {
+        let short = Path::new(r"\\?\C:\Users\Ada\src");
+        assert_eq!(display_form(short), r"C:\Users\Ada\src");
+        assert_eq!(
+            display_form(Path::new(r"\\?\UNC\nas\share\docs")),
+            r"\\nas\share\docs"
+        );
+
+        let long = format!(r"\\?\C:\{}", ["segment"; 40].join(r"\"));
+        assert_eq!(display_form(Path::new(&long)), long);
+        let device = Path::new(r"\\?\C:\work\aux\notes");
+        assert_eq!(display_form(device), r"\\?\C:\work\aux\notes");
+        assert_eq!(
+            display_form(Path::new(r"\\?\Volume{1234}\x")),
+            r"\\?\Volume{1234}\x"
+        );
+    }
+
+    #[test]
+    fn io_form_goes_verbatim_for_long_and_device_paths() {
+        assert_eq!(for_io(Path::new(r"C:\short")), PathBuf::from(r"C:\short"));
+        assert_eq!(
+            for_io(Path::new("C:/work/con/config.json")),
+            PathBuf::from(r"\\?\C:\work\con\config.json")
+        );
+        let tail = ["segment"; 40].join(r"\");
+        assert_eq!(
+            for_io(Path::new(&format!(r"\\srv\share\{tail}"))),
+            PathBuf::from(format!(r"\\?\UNC\srv\share\{tail}"))
+        );
+        assert_eq!(
+            temp_sibling(Path::new("dir/aux.json")),
+            PathBuf::from("dir/.aux.json.tmp")
+        );
+    }
+
+    #[cfg(windows)]
+    mod windows {
+        use super::super::*;
+        use std::fs;
+
+        fn scratch(name: &str) -> PathBuf {
+            let dir =
+                std::env::temp_dir().join(format!("liteclaw-paths-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(for_io(&dir));
+            dir
+        }
+
+        #[test]
+        fn folders_past_max_path_canonicalize_to_a_usable_string() {
+            let root = scratch("long");
+            let deep = root.join(["a-fairly-long-directory-name"; 12].join("\\"));
+            assert!(deep.to_string_lossy().len() > 260);
+            fs::create_dir_all(for_io(&deep)).unwrap();
+
+            let stored = canonical_dir(&deep.to_string_lossy()).unwrap();
+            assert!(stored.starts_with(VERBATIM_PREFIX), "{stored}");
+            fs::write(Path::new(&stored).join("file.txt"), b"ok").unwrap();
+            assert_eq!(
+                fs::read(Path::new(&stored).join("file.txt")).unwrap(),
+                b"ok"
+            );
+            fs::remove_dir_all(for_io(&root)).unwrap();
+        }
+
+        #[test]
+        fn reserved_name_folders_stay_verbatim() {
+            let root = scratch("reserved");
+            let device = root.join("aux");
+            fs::create_dir_all(for_io(&device)).unwrap();
+
+            let stored = canonical_dir(&device.to_string_lossy()).unwrap();
+            assert!(stored.starts_with(VERBATIM_PREFIX), "{stored}");
+            let config = for_io(&device.join("config.json"));
+            fs::write(temp_sibling(&config), b"{}").unwrap();
+            fs::rename(temp_sibling(&config), &config).unwrap();
+            assert!(config.is_file());
+            fs::remove_dir_all(for_io(&root)).unwrap();
+        }
+
+        #[test]
+        fn short_folders_are_stored_plain() {
+            let root = scratch("short");
+            fs::create_dir_all(&root).unwrap();
+            let stored = canonical_dir(&root.to_string_lossy()).unwrap();
+            assert!(!stored.starts_with(VERBATIM_PREFIX), "{stored}");
+            fs::remove_dir_all(&root).unwrap();
+        }
+    }
+}
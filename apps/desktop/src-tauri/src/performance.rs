@@ -0,0 +1,187 @@
+//! Scheduling priority and a thread hint for the backend, so heavy indexing
+//! yields to whatever the user is doing. Nothing here can fail a spawn: a
+//! value that cannot be applied becomes a warning in `get_backend_stats`.
+//! `concurrency` limits the desktop's side instead; see `request_limiter`.
+
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::process::{Child, Command};
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::request_limiter::ConcurrencyConfig;
+use crate::{backend_state, commit_config, read_local_config, AppState, BackendState, LocalConfig};
+
+pub const NICE_RANGE: RangeInclusive<i32> = -20..=19;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerformanceConfig {
+    /// Unix nice value; higher is lower priority. 0 leaves it alone.
+    pub backend_nice: i32,
+    /// Windows `BELOW_NORMAL_PRIORITY_CLASS`.
+    pub below_normal_priority: bool,
+    /// Passed as `LITECLAW_MAX_THREADS`; caps the backend's worker threads.
+    pub max_threads_hint: Option<u32>,
+    /// Requests the desktop sends at once; applies without a restart.
+    pub concurrency: ConcurrencyConfig,
+}
+
+impl PerformanceConfig {
+    /// Whether a backend started with `self` needs a restart to match
+    /// `wanted`; only the process settings count.
+    pub fn spawn_differs(&self, wanted: &PerformanceConfig) -> bool {
+        self.backend_nice != wanted.backend_nice
+            || self.below_normal_priority != wanted.below_normal_priority
+            || self.max_threads_hint != wanted.max_threads_hint
+    }
+}
+
+/// What the running backend actually got.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AppliedPerformance {
+    pub requested: PerformanceConfig,
+    pub nice: Option<i32>,
+    pub below_normal_priority: bool,
+    pub max_threads_hint: Option<u32>,
+    pub warnings: Vec<String>,
+}
+
+/// Problems with a config that are reported, not rejected.
+fn config_warnings(config: &PerformanceConfig) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if !NICE_RANGE.contains(&config.backend_nice) {
+        warnings.push(format!(
+            "backend_nice {} is outside {}..={} and will be clamped",
+            config.backend_nice,
+            NICE_RANGE.start(),
+            NICE_RANGE.end()
+        ));
+    }
+    if config.backend_nice < 0 {
+        warnings.push("negative backend_nice usually needs elevated privileges".to_string());
+    }
+    if cfg!(not(unix)) && config.backend_nice != 0 {
+        warnings.push("backend_nice only applies on macOS and Linux".to_string());
+    }
+    if cfg!(not(windows)) && config.below_normal_priority {
+        warnings.push("below_normal_priority only applies on Windows".to_string());
+    }
+    if config.max_threads_hint == Some(0) {
+        warnings.push("max_threads_hint of 0 is ignored".to_string());
+    }
+    warnings
+}
+
+/// Applied before spawn: the thread hint and, on Windows, the priority class.
+pub fn prepare_command(command: &mut Command, config: &PerformanceConfig) -> AppliedPerformance {
+    let mut applied = AppliedPerformance {
+        requested: config.clone(),
+        warnings: config_warnings(config),
+        ..AppliedPerformance::default()
+    };
+    applied.max_threads_hint = config.max_threads_hint.filter(|threads| *threads > 0);
+    if let Some(threads) = applied.max_threads_hint {
+        command.env("LITECLAW_MAX_THREADS", threads.to_string());
+    }
+    #[cfg(windows)]
+    if config.below_normal_priority {
+        use std::os::windows::process::CommandExt;
+        const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+        command.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+        applied.below_normal_priority = true;
+    }
+    applied
+}
+
+/// Applied right after spawn. The nice value is set from the parent rather
+/// than in `pre_exec` so a refusal (EPERM for negative values) can be
+/// reported; the interpreter has not started any threads yet at this point.
+pub fn apply_to_child(child: &Child, applied: &mut AppliedPerformance) {
+    #[cfg(unix)]
+    {
+        let nice = applied
+            .requested
+            .backend_nice
+            .clamp(*NICE_RANGE.start(), *NICE_RANGE.end());
+        if nice != 0 {
+            // SAFETY: plain syscall on our own child's pid.
+            let result =
+                unsafe { libc::setpriority(libc::PRIO_PROCESS, child.id() as libc::id_t, nice) };
+            if result == 0 {
+                applied.nice = Some(nice);
+            } else {
+                applied.warnings.push(format!(
+                    "could not set backend nice to {nice}: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (child, applied);
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceUpdate {
+    pub config: LocalConfig,
+    pub warnings: Vec<String>,
+    /// The running backend still has the previous settings.
+    pub restart_pending: bool,
+}
+
+#[tauri::command]
+pub fn set_backend_performance(
+    state: State<'_, AppState>,
+    backend_nice: i32,
+    below_normal_priority: bool,
+    max_threads_hint: Option<u32>,
+) -> Result<ConfigChange<PerformanceUpdate>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.performance = PerformanceConfig {
+        backend_nice,
+        below_normal_priority,
+        max_threads_hint,
+        concurrency: config.performance.concurrency,
+    };
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    let restart_pending = runtime.backend_child.is_some()
+        && runtime
+            .backend_performance
+            .as_ref()
+            .is_none_or(|applied| applied.requested.spawn_differs(&config.performance));
+    Ok(ConfigChange::new(
+        PerformanceUpdate {
+            warnings: config_warnings(&config.performance),
+            config,
+            restart_pending,
+        },
+        diff,
+    ))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendStats {
+    pub backend_state: BackendState,
+    pub pid: Option<u32>,
+    /// `None` until a backend has been spawned this session.
+    pub performance: Option<AppliedPerformance>,
+}
+
+#[tauri::command]
+pub fn get_backend_stats(state: State<'_, AppState>) -> Result<BackendStats, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(BackendStats {
+        backend_state: backend_state(&runtime),
+        pid: runtime.backend_child.as_ref().map(Child::id),
+        performance: runtime.backend_performance.clone(),
+    })
+}
@@ -0,0 +1,73 @@
+//! Which window may invoke which command. Every call from a webview passes
+//! through `guard` before reaching its handler: the main window may call
+//! anything, any other window only `SECONDARY_COMMANDS` plus what its label
+//! is given in `WINDOW_COMMANDS`. A window that may render remote content
+//! therefore cannot reach commands that change config or touch files, and
+//! a new command is main-only until it is listed here. Refusals return
+//! `permission_denied` and are written to the audit log. Allowed calls are
+//! timed for `slow_commands`.
+
+use std::time::Instant;
+use tauri::ipc::Invoke;
+use tauri::Manager;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{audit, slow_commands, AppState};
+
+const MAIN_WINDOW: &str = "main";
+
+/// Read-only commands every window may call.
+const SECONDARY_COMMANDS: &[&str] = &["get_app_info", "get_metrics", "get_startup_report"];
+
+/// Extra commands for specific secondary windows, by label.
+const WINDOW_COMMANDS: &[(&str, &[&str])] = &[("logs", &["read_backend_logs"])];
+
+fn is_allowed(label: &str, command: &str) -> bool {
+    if label == MAIN_WINDOW || SECONDARY_COMMANDS.contains(&command) {
+        return true;
+    }
+    WINDOW_COMMANDS
+        .iter()
+        .any(|(window, commands)| *window == label && commands.contains(&command))
+}
+
+fn audit_refusal(invoke: &Invoke, label: &str, command: &str) {
+    let webview = invoke.message.webview_ref();
+    let Some(state) = webview.try_state::<AppState>() else {
+        return;
+    };
+    let Ok(runtime) = state.runtime.lock() else {
+        return;
+    };
+    let _ = audit::record(
+        &runtime.data_dir,
+        "command_denied",
+        serde_json::json!({ "window": label, "command": command }),
+    );
+}
+
+/// Wraps the `generate_handler!` dispatcher with the allowlist check and
+/// the slow-command timing.
+pub fn guard<F>(handler: F) -> impl Fn(Invoke) -> bool + Send + Sync + 'static
+where
+    F: Fn(Invoke) -> bool + Send + Sync + 'static,
+{
+    move |invoke: Invoke| {
+        let label = invoke.message.webview_ref().label().to_string();
+        let command = invoke.message.command().to_string();
+        if is_allowed(&label, &command) {
+            let app = invoke.message.webview_ref().app_handle().clone();
+            let args = slow_commands::summarize(invoke.message.payload());
+            let started = Instant::now();
+            let handled = handler(invoke);
+            slow_commands::observe(&app, &command, args, started.elapsed());
+            return handled;
+        }
+        audit_refusal(&invoke, &label, &command);
+        invoke.resolver.reject(CommandError::new(
+            ErrorCode::PermissionDenied,
+            format!("the `{label}` window may not call `{command}`"),
+        ));
+        true
+    }
+}
@@ -0,0 +1,506 @@
+//! Named profiles, each with its own data dir, so work and personal setups
+//! never share folders, history or keys. `profiles.json` at the data
+//! root lists them and remembers the active one. The flat data dir from
+//! before profiles existed stays where it is and becomes `default`; new
+//! profiles live under `profiles/<name>`.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::confirmation::{self, Confirmable, Summary};
+use crate::error::{CommandError, ErrorCode};
+use crate::{
+    api_config, audit, backend_status, data_dir_lock, discovery, ensure_config_exists, janitor,
+    layout, paths, read_local_config, self_check, start_subsystems, status_server, stop_backend,
+    storage, timestamps, tray, ApiConfig, AppState, BackendRuntime,
+};
+
+pub const DEFAULT_PROFILE: &str = "default";
+const MAX_NAME_LEN: usize = 32;
+
+/// Mirrors `BackendRuntime.profile` for writers that only get a data dir.
+static ACTIVE: Mutex<String> = Mutex::new(String::new());
+
+/// The active profile's name, or empty before `setup` has resolved it.
+pub fn active() -> String {
+    ACTIVE.lock().map(|name| name.clone()).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// Relative to the data root; `.` for the migrated default profile.
+    pub dir: String,
+    pub created_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileRegistry {
+    pub active: String,
+    pub profiles: Vec<Profile>,
+}
+
+impl Profile {
+    /// The pre-profile data dir, kept in place.
+    fn migrated_default() -> Self {
+        Self {
+            name: DEFAULT_PROFILE.to_string(),
+            dir: ".".to_string(),
+            created_at: None,
+        }
+    }
+}
+
+impl ProfileRegistry {
+    fn migrated() -> Self {
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+            profiles: vec![Profile::migrated_default()],
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub data_dir: String,
+    pub created_at: Option<String>,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<ProfileInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ProfileChanged {
+    profile: String,
+    data_dir: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ProfileSwitch {
+    from: String,
+    to: String,
+}
+
+fn registry_path(root: &Path) -> PathBuf {
+    root.join("profiles.json")
+}
+
+/// Rejects anything that is not a plain relative path below the root.
+fn profile_dir(root: &Path, profile: &Profile) -> Result<PathBuf, String> {
+    let relative = Path::new(&profile.dir);
+    let contained = relative
+        .components()
+        .all(|part| matches!(part, Component::Normal(_) | Component::CurDir));
+    if !contained {
+        return Err(format!(
+            "profile `{}` has an invalid folder `{}`",
+            profile.name, profile.dir
+        ));
+    }
+    Ok(root.join(relative))
+}
+
+fn validate_name(name: &str) -> Result<(), CommandError> {
+    let valid = (1..=MAX_NAME_LEN).contains(&name.len())
+        && name
+            .bytes()
+            .next()
+            .is_some_and(|first| first.is_ascii_lowercase() || first.is_ascii_digit())
+        && name.bytes().all(|byte| {
+            byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'-' || byte == b'_'
+        });
+    if !valid {
+        return Err(CommandError::invalid_input(format!(
+            "invalid profile name `{name}`: use up to {MAX_NAME_LEN} of a-z, 0-9, - and _, \
+             starting with a letter or digit"
+        )));
+    }
+    if paths::is_reserved_name(name) {
+        return Err(CommandError::invalid_input(format!(
+            "`{name}` cannot be used as a profile name"
+        )));
+    }
+    Ok(())
+}
+
+/// Reads `profiles.json`, writing the single-profile registry on first run.
+fn load_registry(root: &Path) -> Result<ProfileRegistry, String> {
+    let path = registry_path(root);
+    if !path.exists() {
+        let registry = ProfileRegistry::migrated();
+        write_registry(root, &registry)?;
+        return Ok(registry);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("failed reading profiles: {e}"))?;
+    serde_json::from_str(&content).map_err(|e| format!("invalid profiles json: {e}"))
+}
+
+fn write_registry(root: &Path, registry: &ProfileRegistry) -> Result<(), String> {
+    fs::create_dir_all(root).map_err(|e| format!("failed creating app data dir: {e}"))?;
+    let path = registry_path(root);
+    let temp = path.with_extension("tmp");
+    let bytes = serde_json::to_vec_pretty(registry)
+        .map_err(|e| format!("failed serializing profiles: {e}"))?;
+    fs::write(&temp, bytes).map_err(|e| format!("failed writing profiles: {e}"))?;
+    fs::rename(&temp, &path).map_err(|e| format!("failed replacing profiles: {e}"))
+}
+
+/// Every listed profile's data dir, for the one-time layout migration. An
+/// unreadable registry still gives the root, which is the default profile.
+pub fn data_dirs(root: &Path) -> Vec<PathBuf> {
+    let Ok(registry) = load_registry(root) else {
+        return vec![root.to_path_buf()];
+    };
+    registry
+        .profiles
+        .iter()
+        .filter_map(|profile| profile_dir(root, profile).ok())
+        .collect()
+}
+
+/// The active profile's name and data dir, for `setup`. A registry naming a
+/// profile that no longer exists falls back to `default`.
+pub fn load_active(root: &Path) -> Result<(String, PathBuf), String> {
+    let mut registry = load_registry(root)?;
+    if let Some(profile) = registry.find(&registry.active) {
+        return Ok((profile.name.clone(), profile_dir(root, profile)?));
+    }
+    registry.active = DEFAULT_PROFILE.to_string();
+    let profile = match registry.find(DEFAULT_PROFILE) {
+        Some(profile) => profile.clone(),
+        None => {
+            registry.profiles.insert(0, Profile::migrated_default());
+            Profile::migrated_default()
+        }
+    };
+    write_registry(root, &registry)?;
+    Ok((profile.name.clone(), profile_dir(root, &profile)?))
+}
+
+pub fn window_title(profile: &str) -> String {
+    format!("LiteClaw — {profile}")
+}
+
+/// Labels the window and tray with `profile` and emits `profile-changed`.
+/// Runs from Rust so the title is right before the frontend has loaded.
+pub fn show_identity(app: &AppHandle, profile: &str, data_dir: &Path) {
+    if let Ok(mut active) = ACTIVE.lock() {
+        *active = profile.to_string();
+    }
+    let title = window_title(profile);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_title(&title);
+    }
+    tray::set_label(app, data_dir, &title);
+    let _ = app.emit(
+        "profile-changed",
+        ProfileChanged {
+            profile: profile.to_string(),
+            data_dir: data_dir.to_string_lossy().into_owned(),
+        },
+    );
+}
+
+fn app_root(app: &AppHandle) -> Result<PathBuf, CommandError> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(layout::data_root(&app_data_dir))
+}
+
+fn profile_list(root: &Path, registry: &ProfileRegistry) -> ProfileList {
+    let profiles = registry
+        .profiles
+        .iter()
+        .map(|profile| ProfileInfo {
+            name: profile.name.clone(),
+            data_dir: profile_dir(root, profile)
+                .map(|dir| dir.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            created_at: profile.created_at.clone(),
+            active: profile.name == registry.active,
+        })
+        .collect();
+    ProfileList {
+        active: registry.active.clone(),
+        profiles,
+    }
+}
+
+#[tauri::command]
+pub fn list_profiles(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ProfileList, CommandError> {
+    // Held so registry reads never interleave with a write.
+    let _runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let root = app_root(&app)?;
+    Ok(profile_list(&root, &load_registry(&root)?))
+}
+
+/// Creates an empty profile. Its config is written on first switch.
+#[tauri::command]
+pub fn create_profile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<ProfileList, CommandError> {
+    let name = name.trim().to_string();
+    validate_name(&name)?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let root = app_root(&app)?;
+    let mut registry = load_registry(&root)?;
+    if registry.find(&name).is_some() {
+        return Err(CommandError::conflict(format!(
+            "profile `{name}` already exists"
+        )));
+    }
+    let profile = Profile {
+        dir: format!("profiles/{name}"),
+        name: name.clone(),
+        created_at: Some(timestamps::now()),
+    };
+    let dir = profile_dir(&root, &profile)?;
+    // Leftovers from a profile whose folder could not be deleted must not be
+    // silently adopted by a new one.
+    if fs::read_dir(&dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(CommandError::conflict(format!(
+            "a folder for profile `{name}` already exists at {}",
+            dir.display()
+        )));
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("failed creating profile folder: {e}"))?;
+    registry.profiles.push(profile);
+    write_registry(&root, &registry)?;
+    let _ = audit::record(
+        &runtime.data_dir,
+        "profile_created",
+        serde_json::json!({ "name": name }),
+    );
+    Ok(profile_list(&root, &registry))
+}
+
+/// The registry and the data dir of the profile `name`, if it may be deleted.
+fn deletable(root: &Path, name: &str) -> Result<(ProfileRegistry, PathBuf), CommandError> {
+    let registry = load_registry(root)?;
+    let Some(profile) = registry.find(name).cloned() else {
+        return Err(CommandError::not_found(format!(
+            "no profile named `{name}`"
+        )));
+    };
+    if name == registry.active {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            format!("profile `{name}` is active; switch to another profile first"),
+        ));
+    }
+    if name == DEFAULT_PROFILE {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            "the default profile cannot be deleted",
+        ));
+    }
+    let dir = profile_dir(root, &profile)?;
+    Ok((registry, dir))
+}
+
+/// Config and cache first: they mirror the data dir and are only findable
+/// through it. On Windows and macOS both sit inside it anyway.
+fn profile_dirs(dir: &Path) -> [PathBuf; 3] {
+    [
+        layout::config_dir(dir),
+        layout::cache_dir(dir),
+        dir.to_path_buf(),
+    ]
+}
+
+/// Deletes a profile and everything in its data dir. `confirm_name` must
+/// repeat the name; the active profile and `default` are never deleted.
+/// Needs confirming as well; see `confirmation`.
+#[tauri::command]
+pub fn delete_profile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    confirm_name: String,
+    confirm_token: Option<String>,
+) -> Result<Confirmable<ProfileList>, CommandError> {
+    if confirm_name != name {
+        return Err(CommandError::invalid_input(
+            "confirmation does not match the profile name",
+        ));
+    }
+    let root = app_root(&app)?;
+    confirmation::guarded(
+        &state,
+        "delete_profile",
+        &name,
+        confirm_token,
+        |_| {
+            let (_, dir) = deletable(&root, &name)?;
+            let mut summary = Summary::new(format!(
+                "Deletes profile `{name}` with its history, settings and attachments."
+            ))
+            .count("bytes", janitor::entry_size(&dir));
+            for dir in profile_dirs(&dir) {
+                if dir.exists() {
+                    summary = summary.path(&dir);
+                }
+            }
+            Ok(summary)
+        },
+        || remove_profile(&state, &root, &name),
+    )
+}
+
+fn remove_profile(
+    state: &State<'_, AppState>,
+    root: &Path,
+    name: &str,
+) -> Result<ProfileList, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let (mut registry, dir) = deletable(root, name)?;
+    // Unlisted first, so a half-deleted folder is never offered as a profile.
+    registry.profiles.retain(|profile| profile.name != name);
+    write_registry(root, &registry)?;
+    let _ = audit::record(
+        &runtime.data_dir,
+        "profile_deleted",
+        serde_json::json!({ "name": name }),
+    );
+    for dir in profile_dirs(&dir) {
+        match fs::remove_dir_all(&dir) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(format!(
+                    "profile `{name}` was removed but its folder {} could not be deleted: {err}",
+                    dir.display()
+                )
+                .into())
+            }
+        }
+    }
+    Ok(profile_list(root, &registry))
+}
+
+/// Points the runtime at another profile's data dir. The caller has already
+/// stopped the backend.
+fn swap_data_dir(runtime: &mut BackendRuntime, name: &str, data_dir: PathBuf) {
+    // The cached config and queued calls belong to the previous profile.
+    storage::forget_config();
+    runtime.pending_actions.clear();
+    runtime.profile = name.to_string();
+    runtime.log_path = crate::backend_log_path(&data_dir)
+        .to_string_lossy()
+        .into_owned();
+    runtime.data_dir = data_dir;
+    runtime.backend_ready = false;
+    runtime.backend_degraded = false;
+    runtime.stopped_by_user = false;
+    runtime.last_error = None;
+    runtime.backend_config_generation = None;
+    runtime.out_of_sync_beats = 0;
+    runtime.failed_health_beats = 0;
+    runtime.backend_proxy = None;
+    runtime.backend_env = None;
+    runtime.backend_performance = None;
+    runtime.crash_tracker.reset();
+}
+
+/// Stops the backend, moves to `name`'s data dir and starts a fresh backend
+/// there if that profile auto-starts. Emits `profile-switching`,
+/// the backend status once stopped, `profile-changed`, the backend status
+/// and connection once started, then `profile-switched`.
+#[tauri::command]
+pub fn switch_profile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<ApiConfig, CommandError> {
+    // Buffered config changes would be flushed into the wrong profile.
+    storage::ensure_available()?;
+    let mut runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let root = app_root(&app)?;
+    let mut registry = load_registry(&root)?;
+    let Some(profile) = registry.find(&name).cloned() else {
+        return Err(CommandError::not_found(format!(
+            "no profile named `{name}`"
+        )));
+    };
+    if runtime.profile == name {
+        return Ok(api_config(&runtime));
+    }
+    let data_dir = profile_dir(&root, &profile)?;
+    fs::create_dir_all(&data_dir).map_err(|e| format!("failed creating profile folder: {e}"))?;
+    let switch = ProfileSwitch {
+        from: runtime.profile.clone(),
+        to: name.clone(),
+    };
+    let _ = app.emit("profile-switching", &switch);
+
+    backend_status::set_status(&app, &mut runtime, |runtime| {
+        stop_backend(runtime);
+        runtime.backend_ready = false;
+    });
+    let _ = audit::record(
+        &runtime.data_dir,
+        "profile_switched",
+        serde_json::json!(switch),
+    );
+
+    registry.active = name.clone();
+    write_registry(&root, &registry)?;
+    swap_data_dir(&mut runtime, &name, data_dir);
+    let read_only = data_dir_lock::acquire(&runtime.data_dir).is_some();
+    show_identity(&app, &runtime.profile, &runtime.data_dir);
+    discovery::clear(&runtime.data_dir);
+    ensure_config_exists(&runtime.data_dir)?;
+    let report = self_check::run_self_check(&runtime.data_dir);
+    let _ = app.emit("app-self-check", &report);
+    runtime.self_check = Some(report);
+    let janitor_root = runtime.data_dir.clone();
+    if !read_only {
+        thread::spawn(move || {
+            janitor::run_cleanup(&janitor_root);
+        });
+    }
+    if !runtime.safe_mode && !read_only {
+        start_subsystems(&mut runtime)?;
+    }
+    data_dir_lock::announce(&app);
+
+    let config = backend_status::publish(&app, &runtime);
+    let _ = app.emit("profile-switched", &switch);
+    let local = read_local_config(&runtime.data_dir)?;
+    if !local.onboarding.completed {
+        let _ = app.emit("onboarding-required", local.onboarding);
+    }
+    let data_dir = runtime.data_dir.clone();
+    drop(runtime);
+    status_server::apply(&app, &data_dir, &local.status_server);
+    Ok(config)
+}
@@ -0,0 +1,430 @@
+//! Outbound HTTP proxy settings. The backend gets them as the standard
+//! `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY` variables at spawn, so a change only
+//! takes effect after a backend restart. Loopback is always exempt: the
+//! desktop and backend talk over loopback. The desktop's own outbound calls
+//! (telemetry uploads) use `outbound_agent`.
+
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{commit_config, read_local_config, AppState, BackendRuntime, LocalConfig};
+
+const LOOPBACK_NO_PROXY: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyMode {
+    System,
+    Manual,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    pub mode: ProxyMode,
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        // `system` keeps the old behaviour of inheriting the desktop's env.
+        Self {
+            mode: ProxyMode::System,
+            http_proxy: None,
+            https_proxy: None,
+            no_proxy: None,
+        }
+    }
+}
+
+/// Concrete values after applying the mode.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ResolvedProxy {
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyUpdate {
+    pub config: LocalConfig,
+    pub resolved: ResolvedProxy,
+    /// The running backend still uses the previous values.
+    pub restart_pending: bool,
+}
+
+pub fn validate_proxy_url(raw: &str) -> Result<String, CommandError> {
+    let trimmed = raw.trim();
+    let parsed = url::Url::parse(trimmed)
+        .map_err(|e| CommandError::invalid_input(format!("invalid proxy url {trimmed}: {e}")))?;
+    if !PROXY_SCHEMES.contains(&parsed.scheme()) {
+        return Err(CommandError::invalid_input(format!(
+            "unsupported proxy scheme {}; use one of {}",
+            parsed.scheme(),
+            PROXY_SCHEMES.join(", ")
+        )));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(CommandError::invalid_input(format!(
+            "proxy url has no host: {trimmed}"
+        )));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Normalizes a comma-separated host list (`example.com,.corp,10.0.0.0/8`).
+pub fn validate_no_proxy(raw: &str) -> Result<String, CommandError> {
+    let entries: Vec<&str> = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    if let Some(bad) = entries
+        .iter()
+        .find(|entry| entry.contains(char::is_whitespace) || entry.contains("://"))
+    {
+        return Err(CommandError::invalid_input(format!(
+            "invalid no_proxy entry: {bad}"
+        )));
+    }
+    Ok(entries.join(","))
+}
+
+pub fn validate_manual(
+    http_proxy: &Option<String>,
+    https_proxy: &Option<String>,
+) -> Result<(), CommandError> {
+    if http_proxy.is_none() && https_proxy.is_none() {
+        return Err(CommandError::invalid_input(
+            "manual mode needs an http or https proxy",
+        ));
+    }
+    Ok(())
+}
+
+fn with_loopback(no_proxy: Option<String>) -> Option<String> {
+    let mut entries: Vec<String> = no_proxy
+        .iter()
+        .flat_map(|list| list.split(','))
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    for host in LOOPBACK_NO_PROXY {
+        if !entries.iter().any(|entry| entry == host) {
+            entries.push(host.to_string());
+        }
+    }
+    Some(entries.join(","))
+}
+
+fn env_var(names: [&str; 2]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// `HTTP_PROXY` and friends from the desktop's own environment.
+fn proxy_from_env() -> Option<ResolvedProxy> {
+    let resolved = ResolvedProxy {
+        http_proxy: env_var(["HTTP_PROXY", "http_proxy"]),
+        https_proxy: env_var(["HTTPS_PROXY", "https_proxy"]),
+        no_proxy: env_var(["NO_PROXY", "no_proxy"]),
+    };
+    (resolved.http_proxy.is_some() || resolved.https_proxy.is_some()).then_some(resolved)
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{command_output, ResolvedProxy};
+
+    fn value<'a>(output: &'a str, key: &str) -> Option<&'a str> {
+        output.lines().find_map(|line| {
+            let (name, value) = line.split_once(" : ")?;
+            (name.trim() == key).then(|| value.trim())
+        })
+    }
+
+    fn endpoint(output: &str, prefix: &str) -> Option<String> {
+        if value(output, &format!("{prefix}Enable")) != Some("1") {
+            return None;
+        }
+        let host = value(output, &format!("{prefix}Proxy"))?;
+        let port = value(output, &format!("{prefix}Port")).unwrap_or("80");
+        Some(format!("http://{host}:{port}"))
+    }
+
+    /// `scutil --proxy` prints the active network service's settings as a
+    /// plist-ish dictionary; `ExceptionsList` is an indexed array.
+    pub fn system_proxy() -> Option<ResolvedProxy> {
+        let output = command_output("scutil", &["--proxy"])?;
+        let exceptions: Vec<&str> = output
+            .lines()
+            .skip_while(|line| !line.contains("ExceptionsList"))
+            .skip(1)
+            .take_while(|line| !line.trim_start().starts_with('}'))
+            .filter_map(|line| line.split_once(" : ").map(|(_, host)| host.trim()))
+            .collect();
+        let resolved = ResolvedProxy {
+            http_proxy: endpoint(&output, "HTTP"),
+            https_proxy: endpoint(&output, "HTTPS"),
+            no_proxy: (!exceptions.is_empty()).then(|| exceptions.join(",")),
+        };
+        (resolved.http_proxy.is_some() || resolved.https_proxy.is_some()).then_some(resolved)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{command_output, ResolvedProxy};
+
+    const INTERNET_SETTINGS: &str =
+        r"HKCU\Software\Microsoft\Windows\CurrentVersion\Internet Settings";
+
+    fn reg_value(name: &str) -> Option<String> {
+        let output = command_output("reg", &["query", INTERNET_SETTINGS, "/v", name])?;
+        // `    ProxyServer    REG_SZ    host:8080`
+        output.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            (parts.next()? == name).then_some(())?;
+            parts.next()?;
+            Some(parts.collect::<Vec<_>>().join(" "))
+        })
+    }
+
+    fn with_scheme(server: &str) -> String {
+        if server.contains("://") {
+            server.to_string()
+        } else {
+            format!("http://{server}")
+        }
+    }
+
+    /// WinINet settings. `ProxyServer` is either `host:port` for every
+    /// protocol or `http=host:port;https=host:port`.
+    pub fn system_proxy() -> Option<ResolvedProxy> {
+        let enabled = reg_value("ProxyEnable")?;
+        if enabled != "0x1" {
+            return None;
+        }
+        let server = reg_value("ProxyServer")?;
+        let (http_proxy, https_proxy) = if server.contains('=') {
+            let lookup = |scheme: &str| {
+                server.split(';').find_map(|part| {
+                    let (name, value) = part.split_once('=')?;
+                    (name.trim().eq_ignore_ascii_case(scheme)).then(|| with_scheme(value.trim()))
+                })
+            };
+            (lookup("http"), lookup("https"))
+        } else {
+            (Some(with_scheme(&server)), Some(with_scheme(&server)))
+        };
+        let no_proxy = reg_value("ProxyOverride").map(|list| {
+            list.split(';')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty() && *entry != "<local>")
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+        Some(ResolvedProxy {
+            http_proxy,
+            https_proxy,
+            no_proxy,
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+    use super::{command_output, ResolvedProxy};
+
+    fn gsetting(schema: &str, key: &str) -> Option<String> {
+        let raw = command_output("gsettings", &["get", schema, key])?;
+        Some(raw.trim().trim_matches('\'').to_string())
+    }
+
+    fn gnome_endpoint(scheme: &str) -> Option<String> {
+        let schema = format!("org.gnome.system.proxy.{scheme}");
+        let host = gsetting(&schema, "host").filter(|host| !host.is_empty())?;
+        let port = gsetting(&schema, "port").unwrap_or_else(|| "8080".to_string());
+        Some(format!("http://{host}:{port}"))
+    }
+
+    /// Desktop environments export the proxy into the session env; GNOME's
+    /// own setting is the fallback when the app was launched without it.
+    pub fn system_proxy() -> Option<ResolvedProxy> {
+        if gsetting("org.gnome.system.proxy", "mode").as_deref() != Some("manual") {
+            return None;
+        }
+        // `['localhost', '127.0.0.0/8']`
+        let ignore = gsetting("org.gnome.system.proxy", "ignore-hosts").map(|list| {
+            list.trim_matches(|c| c == '[' || c == ']')
+                .split(',')
+                .map(|entry| entry.trim().trim_matches('\''))
+                .filter(|entry| !entry.is_empty())
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+        Some(ResolvedProxy {
+            http_proxy: gnome_endpoint("http"),
+            https_proxy: gnome_endpoint("https"),
+            no_proxy: ignore,
+        })
+    }
+}
+
+/// Applies the mode. Loopback is always appended to `no_proxy`.
+pub fn resolve(config: &ProxyConfig) -> ResolvedProxy {
+    let base = match config.mode {
+        ProxyMode::None => return ResolvedProxy::default(),
+        ProxyMode::Manual => ResolvedProxy {
+            http_proxy: config.http_proxy.clone(),
+            https_proxy: config.https_proxy.clone(),
+            no_proxy: config.no_proxy.clone(),
+        },
+        ProxyMode::System => proxy_from_env()
+            .or_else(platform::system_proxy)
+            .unwrap_or_default(),
+    };
+    ResolvedProxy {
+        no_proxy: with_loopback(base.no_proxy),
+        ..base
+    }
+}
+
+/// Replaces any inherited proxy variables with the resolved ones. Both cases
+/// are set since Python's `urllib` prefers lowercase and `httpx` reads either.
+pub fn apply_to_command(command: &mut Command, resolved: &ResolvedProxy) {
+    let vars = [
+        ("HTTP_PROXY", &resolved.http_proxy),
+        ("HTTPS_PROXY", &resolved.https_proxy),
+        ("NO_PROXY", &resolved.no_proxy),
+    ];
+    for (name, value) in vars {
+        let lower = name.to_ascii_lowercase();
+        command.env_remove(name).env_remove(&lower);
+        if let Some(value) = value {
+            command.env(name, value).env(&lower, value);
+        }
+    }
+}
+
+fn bypasses(no_proxy: Option<&str>, host: &str) -> bool {
+    no_proxy
+        .into_iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            let suffix = entry.trim_start_matches('*');
+            entry == "*"
+                || host.eq_ignore_ascii_case(suffix.trim_start_matches('.'))
+                || (suffix.starts_with('.') && host.ends_with(suffix))
+        })
+}
+
+/// Agent for requests leaving the machine, honouring the proxy settings.
+pub fn outbound_agent(resolved: &ResolvedProxy, target: &url::Url) -> ureq::Agent {
+    let host = target.host_str().unwrap_or_default();
+    let proxy = match target.scheme() {
+        "https" => resolved.https_proxy.as_ref(),
+        _ => resolved.http_proxy.as_ref(),
+    }
+    .filter(|_| !bypasses(resolved.no_proxy.as_deref(), host))
+    .and_then(|url| ureq::Proxy::new(url).ok());
+    let builder = ureq::AgentBuilder::new().timeout(std::time::Duration::from_secs(30));
+    match proxy {
+        Some(proxy) => builder.proxy(proxy).build(),
+        None => builder.build(),
+    }
+}
+
+fn update_proxy(
+    runtime: &BackendRuntime,
+    change: impl FnOnce(&mut ProxyConfig),
+) -> Result<ConfigChange<ProxyUpdate>, CommandError> {
+    let mut config = read_local_config(&runtime.data_dir)?;
+    change(&mut config.proxy);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    let resolved = resolve(&config.proxy);
+    let restart_pending =
+        runtime.backend_child.is_some() && runtime.backend_proxy.as_ref() != Some(&resolved);
+    Ok(ConfigChange::new(
+        ProxyUpdate {
+            config,
+            resolved,
+            restart_pending,
+        },
+        diff,
+    ))
+}
+
+#[tauri::command]
+pub fn set_proxy_mode(
+    state: State<'_, AppState>,
+    mode: ProxyMode,
+) -> Result<ConfigChange<ProxyUpdate>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    update_proxy(&runtime, |proxy| proxy.mode = mode)
+}
+
+/// Stores manual proxy values and switches to `manual` mode. Empty strings
+/// clear a value.
+#[tauri::command]
+pub fn set_manual_proxy(
+    state: State<'_, AppState>,
+    http_proxy: Option<String>,
+    https_proxy: Option<String>,
+    no_proxy: Option<String>,
+) -> Result<ConfigChange<ProxyUpdate>, CommandError> {
+    let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    let http_proxy = non_empty(http_proxy)
+        .map(|url| validate_proxy_url(&url))
+        .transpose()?;
+    let https_proxy = non_empty(https_proxy)
+        .map(|url| validate_proxy_url(&url))
+        .transpose()?;
+    let no_proxy = non_empty(no_proxy)
+        .map(|list| validate_no_proxy(&list))
+        .transpose()?;
+    validate_manual(&http_proxy, &https_proxy)?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    update_proxy(&runtime, |proxy| {
+        *proxy = ProxyConfig {
+            mode: ProxyMode::Manual,
+            http_proxy,
+            https_proxy,
+            no_proxy,
+        };
+    })
+}
+
+#[tauri::command]
+pub fn get_resolved_proxy(state: State<'_, AppState>) -> Result<ResolvedProxy, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(resolve(&read_local_config(&runtime.data_dir)?.proxy))
+}
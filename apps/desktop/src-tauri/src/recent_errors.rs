@@ -0,0 +1,93 @@
+//! "What went wrong recently": warnings and errors from both logs, grouped
+//! and de-duplicated for the status panel.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::State;
+
+use crate::desktop_log::desktop_log_path;
+use crate::error::CommandError;
+use crate::log_parser::{self, Level, LogEntry, LogSource};
+use crate::AppState;
+
+/// Bounds the read so the command stays cheap on large logs.
+const TAIL_BYTES: u64 = 2 * 1024 * 1024;
+const DEFAULT_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentError {
+    pub source: LogSource,
+    pub timestamp: Option<String>,
+    pub level: Level,
+    pub message: String,
+    pub lines: Vec<String>,
+    /// How many times this message appeared in the scanned window.
+    pub count: usize,
+}
+
+fn is_problem(entry: &LogEntry) -> bool {
+    entry.level.is_some_and(|level| level >= Level::Warning)
+}
+
+/// Newest-first, with identical messages from the same source collapsed into
+/// their most recent occurrence.
+pub fn summarize(backend: Vec<LogEntry>, desktop: Vec<LogEntry>, limit: usize) -> Vec<RecentError> {
+    // Entries are in file order; timestamps decide the order across files.
+    let mut problems: Vec<LogEntry> = backend
+        .into_iter()
+        .chain(desktop)
+        .filter(is_problem)
+        .collect();
+    problems.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    let mut summary: Vec<RecentError> = Vec::new();
+    let mut seen: HashMap<(LogSource, String), usize> = HashMap::new();
+    for entry in problems.into_iter().rev() {
+        let key = (entry.source, entry.message.clone());
+        if let Some(&index) = seen.get(&key) {
+            summary[index].count += 1;
+            continue;
+        }
+        seen.insert(key, summary.len());
+        summary.push(RecentError {
+            source: entry.source,
+            timestamp: entry.timestamp,
+            level: entry.level.unwrap_or(Level::Warning),
+            message: entry.message,
+            lines: entry.lines,
+            count: 1,
+        });
+    }
+    summary.truncate(limit);
+    summary
+}
+
+pub fn recent_errors(log_path: &Path, data_dir: &Path, limit: usize) -> Vec<RecentError> {
+    let backend = log_parser::read_tail(log_path, TAIL_BYTES)
+        .map(|content| log_parser::parse_backend_log(&content))
+        .unwrap_or_default();
+    let desktop = log_parser::read_tail(&desktop_log_path(data_dir), TAIL_BYTES)
+        .map(|content| log_parser::parse_desktop_log(&content))
+        .unwrap_or_default();
+    summarize(backend, desktop, limit)
+}
+
+#[tauri::command]
+pub fn get_recent_errors(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<RecentError>, CommandError> {
+    let (log_path, data_dir) = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        (runtime.log_path.clone(), runtime.data_dir.clone())
+    };
+    Ok(recent_errors(
+        Path::new(&log_path),
+        &data_dir,
+        limit.unwrap_or(DEFAULT_LIMIT).max(1),
+    ))
+}
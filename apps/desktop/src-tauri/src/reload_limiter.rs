@@ -0,0 +1,81 @@
+//! Token bucket in front of backend config reloads. Bursts beyond the budget
+//! collapse into one trailing reload of whatever config is on disk by then,
+//! so no change is lost and a quiet bucket never delays a reload.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const BURST: f64 = 5.0;
+pub const WINDOW: Duration = Duration::from_secs(10);
+
+pub enum Admission {
+    /// Send the reload right away.
+    Now,
+    /// A trailing reload is already scheduled and will pick this change up.
+    Coalesced,
+    /// The caller should schedule the trailing reload after this delay.
+    Trailing(Duration),
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    trailing_scheduled: bool,
+}
+
+pub struct ReloadLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let per_second = BURST / WINDOW.as_secs_f64();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_second).min(BURST);
+        self.last_refill = now;
+    }
+}
+
+impl Default for ReloadLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReloadLimiter {
+    pub fn new() -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: BURST,
+                last_refill: Instant::now(),
+                trailing_scheduled: false,
+            }),
+        }
+    }
+
+    pub fn admit(&self) -> Admission {
+        let Ok(mut bucket) = self.bucket.lock() else {
+            return Admission::Now;
+        };
+        bucket.refill(Instant::now());
+        if bucket.trailing_scheduled {
+            return Admission::Coalesced;
+        }
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Admission::Now;
+        }
+        bucket.trailing_scheduled = true;
+        let per_second = BURST / WINDOW.as_secs_f64();
+        Admission::Trailing(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+    }
+
+    /// Called by the trailing reload right before it fires.
+    pub fn take_trailing(&self) {
+        if let Ok(mut bucket) = self.bucket.lock() {
+            bucket.refill(Instant::now());
+            bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+            bucket.trailing_scheduled = false;
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Caps how many requests the desktop has in flight to the backend, which
+//! serves one request at a time and times everything out when flooded.
+//! Requests are split by path into two pools so a burst of uploads or
+//! exports cannot starve chat and health: `interactive` for everything else
+//! and `bulk` for transfers and searches. A request over its pool's limit
+//! waits for a slot; once `max_queue` are already waiting it is turned away
+//! at once with `backend_busy`. Limits come from `performance.concurrency`.
+//!
+//! A slot is held until the backend's response headers arrive, which for
+//! this backend is when the work is done; reading the body does not count.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Condvar, Mutex};
+
+use crate::error::CommandError;
+
+/// Above this many waiting requests the queue itself is the problem.
+const MAX_QUEUE_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConcurrencyConfig {
+    /// Requests in flight at once for chat, health, config and the like.
+    pub interactive: usize,
+    /// Requests in flight at once for uploads, downloads, exports, imports
+    /// and searches.
+    pub bulk: usize,
+    /// Requests that may wait per pool before more fail with `backend_busy`.
+    pub max_queue: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            interactive: 4,
+            bulk: 2,
+            max_queue: 16,
+        }
+    }
+}
+
+pub fn validate(config: &ConcurrencyConfig) -> Result<(), CommandError> {
+    if config.interactive == 0 || config.bulk == 0 {
+        return Err(CommandError::invalid_input(
+            "each request pool needs room for at least one request",
+        ));
+    }
+    if config.max_queue > MAX_QUEUE_LIMIT {
+        return Err(CommandError::invalid_input(format!(
+            "max_queue must be at most {MAX_QUEUE_LIMIT}"
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestClass {
+    Interactive,
+    Bulk,
+}
+
+/// Path segments that make a request bulk.
+const BULK_SEGMENTS: &[&str] = &[
+    "upload", "uploads", "download", "export", "import", "search",
+];
+
+pub fn classify(path: &str) -> RequestClass {
+    let bulk = path
+        .split(['/', '?'])
+        .any(|segment| BULK_SEGMENTS.contains(&segment));
+    if bulk {
+        RequestClass::Bulk
+    } else {
+        RequestClass::Interactive
+    }
+}
+
+/// A request turned away because its pool's queue was full.
+#[derive(Debug)]
+pub struct Busy {
+    pub class: RequestClass,
+    pub queued: usize,
+}
+
+impl fmt::Display for Busy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = match self.class {
+            RequestClass::Interactive => "interactive",
+            RequestClass::Bulk => "bulk",
+        };
+        write!(
+            f,
+            "the backend is busy: {} {class} requests are already waiting; try again shortly",
+            self.queued
+        )
+    }
+}
+
+impl std::error::Error for Busy {}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PoolOccupancy {
+    pub active: usize,
+    pub queued: usize,
+    pub limit: usize,
+    pub max_queue: usize,
+    /// Requests turned away with `backend_busy` since startup.
+    pub rejected: u64,
+}
+
+struct Pool {
+    state: Mutex<PoolOccupancy>,
+    freed: Condvar,
+}
+
+impl Pool {
+    const fn new(limit: usize, max_queue: usize) -> Self {
+        Self {
+            state: Mutex::new(PoolOccupancy {
+                active: 0,
+                queued: 0,
+                limit,
+                max_queue,
+                rejected: 0,
+            }),
+            freed: Condvar::new(),
+        }
+    }
+
+    fn configure(&self, limit: usize, max_queue: usize) {
+        if let Ok(mut state) = self.state.lock() {
+            state.limit = limit.max(1);
+            state.max_queue = max_queue;
+        }
+        // A raised limit lets waiting requests through now.
+        self.freed.notify_all();
+    }
+
+    fn acquire(&'static self, class: RequestClass) -> Result<Permit, Busy> {
+        // A poisoned pool limits nothing rather than failing every request.
+        let Ok(mut state) = self.state.lock() else {
+            return Ok(Permit { pool: None });
+        };
+        if state.active >= state.limit {
+            if state.queued >= state.max_queue {
+                state.rejected += 1;
+                return Err(Busy {
+                    class,
+                    queued: state.queued,
+                });
+            }
+            state.queued += 1;
+            while state.active >= state.limit {
+                state = match self.freed.wait(state) {
+                    Ok(state) => state,
+                    Err(_) => return Ok(Permit { pool: None }),
+                };
+            }
+            state.queued -= 1;
+        }
+        state.active += 1;
+        Ok(Permit { pool: Some(self) })
+    }
+
+    fn occupancy(&self) -> PoolOccupancy {
+        self.state.lock().map(|state| *state).unwrap_or_default()
+    }
+}
+
+// Until `configure` runs, the `ConcurrencyConfig` defaults.
+static INTERACTIVE: Pool = Pool::new(4, 16);
+static BULK: Pool = Pool::new(2, 16);
+
+fn pool(class: RequestClass) -> &'static Pool {
+    match class {
+        RequestClass::Interactive => &INTERACTIVE,
+        RequestClass::Bulk => &BULK,
+    }
+}
+
+/// A slot in a pool, given back on drop.
+pub struct Permit {
+    pool: Option<&'static Pool>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let Some(pool) = self.pool else {
+            return;
+        };
+        if let Ok(mut state) = pool.state.lock() {
+            state.active = state.active.saturating_sub(1);
+        }
+        pool.freed.notify_one();
+    }
+}
+
+/// Applies `performance.concurrency` to subsequent requests.
+pub fn configure(config: &ConcurrencyConfig) {
+    INTERACTIVE.configure(config.interactive, config.max_queue);
+    BULK.configure(config.bulk, config.max_queue);
+}
+
+/// Waits for a slot for a request to `path`, or fails with `Busy`.
+pub fn acquire(path: &str) -> Result<Permit, Busy> {
+    let class = classify(path);
+    pool(class).acquire(class)
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Occupancy {
+    pub interactive: PoolOccupancy,
+    pub bulk: PoolOccupancy,
+}
+
+pub fn occupancy() -> Occupancy {
+    Occupancy {
+        interactive: INTERACTIVE.occupancy(),
+        bulk: BULK.occupancy(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn a_full_queue_fails_fast_and_a_freed_slot_lets_the_next_in() {
+        assert_eq!(classify("/v1/history/search?q=x"), RequestClass::Bulk);
+        assert_eq!(classify("/v1/health"), RequestClass::Interactive);
+
+        let pool: &'static Pool = Box::leak(Box::new(Pool::new(1, 1)));
+        let first = pool.acquire(RequestClass::Bulk).unwrap();
+        let (sent, waited) = mpsc::channel();
+        let waiter = thread::spawn(move || {
+            let permit = pool.acquire(RequestClass::Bulk);
+            sent.send(()).unwrap();
+            permit.is_ok()
+        });
+        while pool.occupancy().queued == 0 {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let busy = pool.acquire(RequestClass::Bulk).err().unwrap();
+        assert_eq!(busy.queued, 1);
+        assert_eq!(pool.occupancy().rejected, 1);
+        assert!(waited.try_recv().is_err());
+
+        drop(first);
+        assert!(waiter.join().unwrap());
+        assert_eq!(pool.occupancy().active, 0);
+    }
+}
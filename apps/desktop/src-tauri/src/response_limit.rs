@@ -0,0 +1,296 @@
+//! A cap on how much one command sends back over IPC, where a message of
+//! tens of MB freezes the webview while it is parsed. A result whose JSON
+//! is over `max_response_bytes` (4 MB by default, 0 for no cap) is written
+//! to `spilled/` in the data dir instead, and the command returns
+//! `{ spilled: true, path, size }`. `read_spilled_result` hands the JSON
+//! back `CHUNK_BYTES` at a time and removes the file after the last piece;
+//! `discard_spilled_result` drops one early, and the janitor sweeps those
+//! nobody came back for.
+//!
+//! Commands with a streaming variant refuse instead of spilling, with an
+//! error that names the variant: `read_backend_logs` points to
+//! `export_full_log`.
+//!
+//! Commands opt in by passing their result through `fit` or
+//! `refuse_oversized`; the dispatcher in `permissions::guard` never sees
+//! the serialized result.
+
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::State;
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{metrics, safe_write, storage, AppState};
+
+pub const DEFAULT_MAX_BYTES: u64 = 4 * 1024 * 1024;
+/// Data-dir subdirectory spilled results are written to.
+pub const SPILL_DIR: &str = "spilled";
+/// Spilled results older than this are the janitor's.
+pub const SPILL_RETENTION: Duration = Duration::from_secs(60 * 60);
+/// Bytes of JSON per `read_spilled_result` call.
+const CHUNK_BYTES: usize = 1024 * 1024;
+
+static MAX_BYTES: AtomicU64 = AtomicU64::new(DEFAULT_MAX_BYTES);
+
+/// Applies `max_response_bytes` from config.
+pub fn configure(max_bytes: u64) {
+    MAX_BYTES.store(max_bytes, Ordering::Relaxed);
+}
+
+pub fn limit() -> u64 {
+    MAX_BYTES.load(Ordering::Relaxed)
+}
+
+/// A result too large to send, waiting in `path`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Spilled {
+    /// Always true; tells the caller to read `path` instead.
+    pub spilled: bool,
+    pub path: String,
+    /// Bytes of JSON in `path`.
+    pub size: u64,
+}
+
+/// What a guarded command returns.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Fitted<T> {
+    Inline(T),
+    Spilled(Spilled),
+}
+
+/// Counts what serde writes without keeping it.
+struct Counter(u64);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn json_size<T: Serialize>(value: &T) -> Result<u64, CommandError> {
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, value)
+        .map_err(|e| format!("failed serializing result: {e}"))?;
+    Ok(counter.0)
+}
+
+fn over_limit<T: Serialize>(value: &T, limit: u64) -> Result<Option<u64>, CommandError> {
+    if limit == 0 {
+        return Ok(None);
+    }
+    let size = json_size(value)?;
+    Ok((size > limit).then_some(size))
+}
+
+pub fn spill_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(SPILL_DIR)
+}
+
+/// `<uuid>.json`, as `spill` names them.
+pub fn is_spill_file(name: &str) -> bool {
+    name.strip_suffix(".json")
+        .is_some_and(|stem| Uuid::parse_str(stem).is_ok())
+}
+
+fn spill<T: Serialize>(data_dir: &Path, value: &T, size: u64) -> Result<Spilled, CommandError> {
+    let dir = spill_dir(data_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("failed creating {}: {e}", dir.display()))?;
+    safe_write::ensure_space(&dir, size)
+        .map_err(|e| CommandError::new(ErrorCode::DiskFull, format!("cannot spill result: {e}")))?;
+    let path = dir.join(format!("{}.json", Uuid::new_v4()));
+    let written = File::create(&path).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        serde_json::to_writer(&mut out, value)?;
+        out.flush()
+    });
+    if let Err(err) = written {
+        let _ = fs::remove_file(&path);
+        return Err(format!("failed spilling result: {err}").into());
+    }
+    metrics::increment(&metrics::METRICS.responses_spilled);
+    Ok(Spilled {
+        spilled: true,
+        path: path.to_string_lossy().into_owned(),
+        size,
+    })
+}
+
+fn fit_within<T: Serialize>(
+    data_dir: &Path,
+    value: T,
+    limit: u64,
+) -> Result<Fitted<T>, CommandError> {
+    match over_limit(&value, limit)? {
+        Some(size) => spill(data_dir, &value, size).map(Fitted::Spilled),
+        None => Ok(Fitted::Inline(value)),
+    }
+}
+
+/// `value` as it is, or spilled to a file when it is over the limit.
+pub fn fit<T: Serialize>(data_dir: &Path, value: T) -> Result<Fitted<T>, CommandError> {
+    fit_within(data_dir, value, limit())
+}
+
+/// Fails with `response_too_large` when `value` is over the limit,
+/// pointing the caller at `streaming`, the command that streams the same
+/// data.
+pub fn refuse_oversized<T: Serialize>(
+    command: &str,
+    value: &T,
+    streaming: &str,
+) -> Result<(), CommandError> {
+    let limit = limit();
+    let Some(size) = over_limit(value, limit)? else {
+        return Ok(());
+    };
+    metrics::increment(&metrics::METRICS.responses_refused);
+    Err(CommandError::new(
+        ErrorCode::ResponseTooLarge,
+        format!(
+            "`{command}` would return {size} bytes, over the {limit} byte limit; \
+             use `{streaming}` instead"
+        ),
+    ))
+}
+
+/// The spilled file `path` names, refusing anything outside `spilled/`.
+fn spilled_file(data_dir: &Path, path: &str) -> Result<PathBuf, CommandError> {
+    let path = Path::new(path);
+    let dir = spill_dir(data_dir);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| is_spill_file(name))
+        .filter(|_| path.parent() == Some(dir.as_path()));
+    match name {
+        Some(name) => Ok(dir.join(name)),
+        None => Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            format!("not a spilled result: {}", path.display()),
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpilledChunk {
+    /// A piece of the JSON; concatenated in order, the pieces parse.
+    pub content: String,
+    pub offset: u64,
+    /// Where the next piece starts; `None` after the last, once the file
+    /// is gone.
+    pub next_offset: Option<u64>,
+    pub size: u64,
+}
+
+fn read_chunk(file: &Path, offset: u64) -> Result<SpilledChunk, CommandError> {
+    let mut handle = File::open(file)
+        .map_err(|e| CommandError::not_found(format!("spilled result is gone: {e}")))?;
+    let size = handle
+        .metadata()
+        .map_err(|e| format!("failed reading spilled result: {e}"))?
+        .len();
+    let mut bytes = Vec::with_capacity(CHUNK_BYTES);
+    handle
+        .seek(SeekFrom::Start(offset))
+        .and_then(|_| (&handle).take(CHUNK_BYTES as u64).read_to_end(&mut bytes))
+        .map_err(|e| format!("failed reading spilled result: {e}"))?;
+    // Stop short of a character the chunk boundary cuts through.
+    if let Err(err) = std::str::from_utf8(&bytes) {
+        if err.error_len().is_some() {
+            return Err(CommandError::invalid_input(
+                "offset does not start a piece of the spilled result",
+            ));
+        }
+        bytes.truncate(err.valid_up_to());
+    }
+    let next = offset + bytes.len() as u64;
+    let content = String::from_utf8(bytes).unwrap_or_default();
+    Ok(SpilledChunk {
+        content,
+        offset,
+        next_offset: (next < size).then_some(next),
+        size,
+    })
+}
+
+fn runtime_data_dir(state: &State<'_, AppState>) -> Result<PathBuf, CommandError> {
+    Ok(state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?
+        .data_dir
+        .clone())
+}
+
+#[tauri::command]
+pub fn read_spilled_result(
+    state: State<'_, AppState>,
+    path: String,
+    offset: Option<u64>,
+) -> Result<SpilledChunk, CommandError> {
+    storage::ensure_available()?;
+    let file = spilled_file(&runtime_data_dir(&state)?, &path)?;
+    let chunk = read_chunk(&file, offset.unwrap_or(0))?;
+    if chunk.next_offset.is_none() {
+        let _ = fs::remove_file(&file);
+    }
+    Ok(chunk)
+}
+
+#[tauri::command]
+pub fn discard_spilled_result(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<(), CommandError> {
+    let file = spilled_file(&runtime_data_dir(&state)?, &path)?;
+    match fs::remove_file(&file) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(format!("failed removing spilled result: {err}").into())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_oversized_result_spills_and_reads_back_in_whole_characters() {
+        let data_dir = std::env::temp_dir().join(format!("liteclaw-spill-{}", Uuid::new_v4()));
+        fs::create_dir_all(&data_dir).unwrap();
+        let small = vec!["é"; 4];
+        assert!(matches!(
+            fit_within(&data_dir, small, 64).unwrap(),
+            Fitted::Inline(_)
+        ));
+
+        let large = vec!["é".repeat(700_000)];
+        let Fitted::Spilled(spilled) = fit_within(&data_dir, large.clone(), 64).unwrap() else {
+            panic!("expected a spill");
+        };
+        let file = spilled_file(&data_dir, &spilled.path).unwrap();
+        let mut json = String::new();
+        let mut offset = Some(0);
+        while let Some(at) = offset {
+            let chunk = read_chunk(&file, at).unwrap();
+            assert_eq!(chunk.size, spilled.size);
+            json.push_str(&chunk.content);
+            offset = chunk.next_offset;
+        }
+        assert_eq!(serde_json::from_str::<Vec<String>>(&json).unwrap(), large);
+        assert!(spilled_file(&data_dir, "/etc/passwd").is_err());
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+//! Writes that cannot destroy what they replace when the disk fills up.
+//! A full disk can accept a short write without an error, and a temp file
+//! renamed over the original then takes the good copy with it. `replace`
+//! checks for free space first, writes a temp sibling, and renames it only
+//! once `sync_all` succeeded and the file holds every byte; otherwise the
+//! temp is removed and the original is left alone. `append` does the same
+//! for log lines and truncates a partial line away.
+//!
+//! Running out of space is reported as `io::ErrorKind::StorageFull`, which
+//! `is_disk_full` recognizes and `write_config_atomic` turns into
+//! `ErrorCode::DiskFull`. Config, the audit log, crash reports and backups
+//! go through here.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{paths, self_check};
+
+/// Kept free beyond the write itself, for directory entries and metadata.
+const HEADROOM: u64 = 64 * 1024;
+
+pub fn is_disk_full(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::StorageFull
+}
+
+fn disk_full(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::StorageFull, message)
+}
+
+/// Fails when the volume holding `dir` has less than `needed` bytes free.
+/// Volumes that do not report free space are given the benefit of the doubt.
+pub fn ensure_space(dir: &Path, needed: u64) -> io::Result<()> {
+    match self_check::available_space(dir) {
+        Some(available) if available < needed.saturating_add(HEADROOM) => Err(disk_full(format!(
+            "disk is full: {needed} bytes needed, {available} available"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// What the bytes go through; files in practice, a failing writer in tests.
+trait Target: Write {
+    fn sync(&mut self) -> io::Result<()>;
+    fn len(&self) -> io::Result<u64>;
+}
+
+impl Target for File {
+    fn sync(&mut self) -> io::Result<()> {
+        self.sync_all()
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        self.metadata().map(|meta| meta.len())
+    }
+}
+
+fn verify_len(target: &impl Target, expected: u64) -> io::Result<()> {
+    let written = target.len()?;
+    if written != expected {
+        return Err(disk_full(format!(
+            "short write: {written} of {expected} bytes reached the disk"
+        )));
+    }
+    Ok(())
+}
+
+fn write_synced(target: &mut impl Target, bytes: &[u8]) -> io::Result<()> {
+    target.write_all(bytes)?;
+    target.flush()?;
+    target.sync()?;
+    verify_len(target, bytes.len() as u64)
+}
+
+fn replace_via<T: Target>(
+    path: &Path,
+    bytes: &[u8],
+    open: impl FnOnce(&Path) -> io::Result<T>,
+) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    ensure_space(dir, bytes.len() as u64)?;
+    let temp = paths::temp_sibling(path);
+    let written = open(&temp).and_then(|mut target| write_synced(&mut target, bytes));
+    if let Err(err) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// Replaces `path` with `bytes`, or leaves it untouched.
+pub fn replace(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    replace_via(path, bytes, |temp| File::create(temp))
+}
+
+/// Appends `bytes` whole or not at all.
+pub fn append(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    ensure_space(dir, bytes.len() as u64)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let before = file.len()?;
+    let written = file
+        .write_all(bytes)
+        .and_then(|()| file.sync_data())
+        .and_then(|()| verify_len(&file, before + bytes.len() as u64));
+    if written.is_err() {
+        let _ = file.set_len(before);
+    }
+    written
+}
+
+/// For files streamed to `temp` (backups): syncs and checks the `expected`
+/// length before renaming `temp` to `path`. Removes `temp` on failure.
+pub fn commit(mut file: File, temp: &Path, path: &Path, expected: u64) -> io::Result<()> {
+    let synced = file.sync().and_then(|()| verify_len(&file, expected));
+    drop(file);
+    if let Err(err) = synced {
+        let _ = fs::remove_file(temp);
+        return Err(err);
+    }
+    fs::rename(temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(temp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    /// A disk with `room` bytes left: writes past it fail the way ENOSPC
+    /// does, or with `silent`, are dropped without an error.
+    struct FullDisk {
+        file: File,
+        room: usize,
+        silent: bool,
+    }
+
+    impl Write for FullDisk {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.room == 0 && !self.silent {
+                return Err(io::Error::from(io::ErrorKind::StorageFull));
+            }
+            let kept = buf.len().min(self.room);
+            self.file.write_all(&buf[..kept])?;
+            self.room -= kept;
+            Ok(if self.silent { buf.len() } else { kept })
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.file.flush()
+        }
+    }
+
+    impl Target for FullDisk {
+        fn sync(&mut self) -> io::Result<()> {
+            self.file.sync_all()
+        }
+
+        fn len(&self) -> io::Result<u64> {
+            Target::len(&self.file)
+        }
+    }
+
+    #[test]
+    fn a_full_disk_leaves_the_original_untouched() {
+        let dir = std::env::temp_dir().join(format!("liteclaw-safe-write-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        fs::write(&path, b"{\"good\": true}").unwrap();
+        let update = vec![b'x'; 4096];
+
+        for silent in [false, true] {
+            let err = replace_via(&path, &update, |temp| {
+                Ok(FullDisk {
+                    file: File::create(temp)?,
+                    room: 1000,
+                    silent,
+                })
+            })
+            .unwrap_err();
+            assert!(is_disk_full(&err), "{err}");
+            assert_eq!(fs::read(&path).unwrap(), b"{\"good\": true}");
+            assert!(!paths::temp_sibling(&path).exists());
+        }
+
+        replace(&path, &update).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), update);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
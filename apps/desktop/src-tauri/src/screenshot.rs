@@ -0,0 +1,219 @@
+//! Screen capture into the attachments staging dir. macOS shells out to
+//! `screencapture` (which supports interactive selection); other platforms use
+//! xcap and fall back to the full primary screen for `selection`.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::error::{CommandError, ErrorCode};
+use crate::{attachments, audit, commit_config, read_local_config, AppState, LocalConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    Full,
+    Window,
+    Selection,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenshotResult {
+    pub id: String,
+    pub staged_path: String,
+    pub width: u32,
+    pub height: u32,
+    /// The mode actually used, which differs from the requested one when the
+    /// platform had to fall back to a full-screen capture.
+    pub mode: CaptureMode,
+}
+
+fn unsupported(message: impl Into<String>) -> CommandError {
+    CommandError::new(ErrorCode::Unsupported, message)
+}
+
+pub fn png_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let reader = png::Decoder::new(std::io::Cursor::new(bytes))
+        .read_info()
+        .ok()?;
+    let info = reader.info();
+    Some((info.width, info.height))
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::fs;
+    use std::process::Command;
+
+    use super::CaptureMode;
+    use crate::error::{CommandError, ErrorCode};
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGPreflightScreenCaptureAccess() -> bool;
+    }
+
+    pub fn capture(mode: CaptureMode) -> Result<(Vec<u8>, CaptureMode), CommandError> {
+        // SAFETY: takes no arguments and only queries TCC state.
+        if !unsafe { CGPreflightScreenCaptureAccess() } {
+            return Err(CommandError::new(
+                ErrorCode::PermissionDenied,
+                "LiteClaw needs Screen Recording permission in System Settings",
+            ));
+        }
+        let temp = std::env::temp_dir().join(format!("liteclaw-{}.png", uuid::Uuid::new_v4()));
+        let mut command = Command::new("screencapture");
+        command.arg("-x");
+        match mode {
+            CaptureMode::Full => {}
+            CaptureMode::Window => {
+                command.args(["-i", "-W"]);
+            }
+            CaptureMode::Selection => {
+                command.args(["-i", "-s"]);
+            }
+        }
+        let status = command
+            .arg(&temp)
+            .status()
+            .map_err(|e| format!("failed running screencapture: {e}"))?;
+        // Interactive modes exit cleanly without writing a file when the user
+        // presses Escape.
+        let bytes = match fs::read(&temp) {
+            Ok(bytes) => bytes,
+            Err(_) if status.success() && mode != CaptureMode::Full => {
+                return Err(CommandError::new(
+                    ErrorCode::Cancelled,
+                    "screenshot was cancelled",
+                ))
+            }
+            Err(err) => return Err(format!("screencapture produced no image: {err}").into()),
+        };
+        let _ = fs::remove_file(&temp);
+        Ok((bytes, mode))
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod platform {
+    use super::{unsupported, CaptureMode};
+    use crate::attachments;
+    use crate::error::CommandError;
+
+    const OWN_WINDOW_TITLE: &str = "LiteClaw";
+
+    fn map_err(err: xcap::XCapError) -> CommandError {
+        match err {
+            xcap::XCapError::NotSupported => {
+                unsupported("screen capture is not supported on this system")
+            }
+            other => format!("screen capture failed: {other}").into(),
+        }
+    }
+
+    fn capture_window() -> Result<Option<xcap::image::RgbaImage>, CommandError> {
+        let windows = xcap::Window::all().map_err(map_err)?;
+        let candidates: Vec<&xcap::Window> = windows
+            .iter()
+            .filter(|window| !window.is_minimized().unwrap_or(true))
+            .filter(|window| window.title().map_or(true, |t| t != OWN_WINDOW_TITLE))
+            .collect();
+        let target = candidates
+            .iter()
+            .find(|window| window.is_focused().unwrap_or(false))
+            .or_else(|| candidates.first());
+        match target {
+            Some(window) => window.capture_image().map(Some).map_err(map_err),
+            None => Ok(None),
+        }
+    }
+
+    fn capture_full() -> Result<xcap::image::RgbaImage, CommandError> {
+        let monitors = xcap::Monitor::all().map_err(map_err)?;
+        let monitor = monitors
+            .iter()
+            .find(|monitor| monitor.is_primary().unwrap_or(false))
+            .or_else(|| monitors.first())
+            .ok_or_else(|| unsupported("no display found to capture"))?;
+        monitor.capture_image().map_err(map_err)
+    }
+
+    pub fn capture(mode: CaptureMode) -> Result<(Vec<u8>, CaptureMode), CommandError> {
+        #[cfg(target_os = "linux")]
+        {
+            let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+                || std::env::var("XDG_SESSION_TYPE").is_ok_and(|kind| kind == "wayland");
+            if wayland {
+                return Err(unsupported("screen capture is not supported on Wayland"));
+            }
+        }
+        let (image, used) = match mode {
+            CaptureMode::Window => match capture_window()? {
+                Some(image) => (image, CaptureMode::Window),
+                None => (capture_full()?, CaptureMode::Full),
+            },
+            CaptureMode::Full | CaptureMode::Selection => (capture_full()?, CaptureMode::Full),
+        };
+        let bytes = attachments::encode_png(image.width(), image.height(), image.as_raw())?;
+        Ok((bytes, used))
+    }
+}
+
+fn capture_into(data_dir: &Path, mode: CaptureMode) -> Result<ScreenshotResult, CommandError> {
+    let (bytes, used) = platform::capture(mode)?;
+    let (width, height) = png_dimensions(&bytes)
+        .ok_or_else(|| "screen capture produced an invalid PNG".to_string())?;
+    let staged = attachments::stage_bytes(data_dir, "screenshot.png", &bytes)?;
+    let _ = audit::record(
+        data_dir,
+        "screenshot_captured",
+        serde_json::json!({ "path": staged.staged_path, "mode": used }),
+    );
+    Ok(ScreenshotResult {
+        id: staged.id,
+        staged_path: staged.staged_path,
+        width,
+        height,
+        mode: used,
+    })
+}
+
+// Async so interactive selection doesn't block the main thread; the runtime
+// lock is released before capturing for the same reason.
+#[tauri::command]
+pub async fn capture_screenshot(
+    state: State<'_, AppState>,
+    mode: CaptureMode,
+) -> Result<ScreenshotResult, CommandError> {
+    let data_dir = {
+        let runtime = state
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock poisoned".to_string())?;
+        runtime.data_dir.clone()
+    };
+    let config = read_local_config(&data_dir)?;
+    if !config.allow_screen_capture {
+        return Err(CommandError::new(
+            ErrorCode::NotAllowed,
+            "screen capture is disabled in settings",
+        ));
+    }
+    capture_into(&data_dir, mode)
+}
+
+#[tauri::command]
+pub fn set_screen_capture_enabled(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.allow_screen_capture = enabled;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
@@ -0,0 +1,178 @@
+//! Restarts without the backend going away. Proxy, environment and
+//! performance settings only reach a backend at spawn, and a plain restart
+//! stops the running one first, so the window is offline until the new one
+//! passes health. With `seamless_restart` on, `restart_backend` instead
+//! starts a standby on a fresh port with the new settings while the running
+//! backend keeps serving, waits for it to pass health and the token check,
+//! and swaps it into `BackendRuntime` under the runtime lock: from then on
+//! `api_config` hands out the standby's URL and token. The replaced backend
+//! is stopped once it reports no requests in flight, or after
+//! `DRAIN_TIMEOUT`. A standby that fails to start is killed and the running
+//! backend stays in place; the failure is reported like any failed start.
+//!
+//! Off by default: until the old backend drains, two run side by side.
+
+use std::path::PathBuf;
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::heartbeat::HealthInfo;
+use crate::spawn_guard::SpawnGuard;
+use crate::{
+    action_queue, audit, backend_http, backend_status, commit_config, desktop_log,
+    ensure_not_safe_mode, launch_process, launch_with_retries, promote, read_local_config,
+    session_file, spawn_backend, spawn_guard, startup_phase, ApiConfig, AppState, BackendRuntime,
+    LocalConfig,
+};
+
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+const DRAIN_POLL: Duration = Duration::from_millis(250);
+
+/// The backend a standby replaced, stopped by `drain`.
+struct Retired {
+    child: Child,
+    base_url: String,
+    token: String,
+}
+
+/// Requests the backend is still handling; `None` once it stops answering.
+fn in_flight(agent: &ureq::Agent, base_url: &str, token: &str) -> Option<u64> {
+    let response = agent
+        .get(&format!("{base_url}/v1/health"))
+        .set("Authorization", &format!("Bearer {token}"))
+        .call()
+        .ok()?;
+    let body = response.into_string().ok()?;
+    let health: HealthInfo = serde_json::from_str(&body).ok()?;
+    // A backend that does not count its requests has nothing to wait for.
+    Some(health.in_flight.unwrap_or(0))
+}
+
+/// Waits up to `timeout` for the backend in `child` to go idle or exit.
+/// Returns the requests it was still handling when time ran out.
+pub fn wait_until_idle(child: &mut Child, base_url: &str, token: &str, timeout: Duration) -> u64 {
+    let agent = backend_http::builder()
+        .timeout(Duration::from_secs(2))
+        .build();
+    let deadline = Instant::now() + timeout;
+    let mut busy = 0;
+    while Instant::now() < deadline {
+        if matches!(child.try_wait(), Ok(Some(_))) {
+            return 0;
+        }
+        match in_flight(&agent, base_url, token) {
+            Some(0) | None => return 0,
+            Some(count) => busy = count,
+        }
+        thread::sleep(DRAIN_POLL);
+    }
+    busy
+}
+
+fn drain(data_dir: PathBuf, mut retired: Retired) {
+    thread::spawn(move || {
+        let busy = wait_until_idle(
+            &mut retired.child,
+            &retired.base_url,
+            &retired.token,
+            DRAIN_TIMEOUT,
+        );
+        if busy > 0 {
+            let message = format!(
+                "stopping the replaced backend with {busy} requests still in flight after {}s",
+                DRAIN_TIMEOUT.as_secs()
+            );
+            desktop_log::warn(&data_dir, &message);
+        }
+        let _ = retired.child.kill();
+        let _ = retired.child.wait();
+    });
+}
+
+/// Swaps in a standby for the running backend. On failure the running
+/// backend is untouched apart from `last_error`.
+fn restart(runtime: &mut BackendRuntime, starting: &SpawnGuard) -> Result<(), String> {
+    let base_url = runtime.base_url.clone();
+    let token = runtime.token.clone();
+    runtime.last_error = None;
+    let replaced = {
+        let _quiet = startup_phase::quiet();
+        launch_with_retries(runtime, |runtime, squatted| {
+            let launched = launch_process(&runtime.data_dir, starting, squatted)?;
+            Ok(promote(runtime, launched))
+        })
+    };
+    let replaced = match replaced {
+        Ok(replaced) => replaced,
+        Err(summary) => {
+            let _ = audit::record(
+                &runtime.data_dir,
+                "backend_restart_failed",
+                serde_json::json!({ "seamless": true }),
+            );
+            return Err(format!(
+                "the running backend was kept; its replacement failed to start: {summary}"
+            ));
+        }
+    };
+    // The hang history described the replaced backend.
+    backend_http::reset();
+    let _ = audit::record(
+        &runtime.data_dir,
+        "backend_restarted",
+        serde_json::json!({ "seamless": true }),
+    );
+    if let Some(child) = replaced {
+        let retired = Retired {
+            child,
+            base_url,
+            token,
+        };
+        drain(runtime.data_dir.clone(), retired);
+    }
+    Ok(())
+}
+
+/// Restarts the backend so settings read at spawn take effect: seamlessly
+/// when `seamless_restart` is on and a backend is up, otherwise by stopping
+/// it and starting it again.
+#[tauri::command]
+pub fn restart_backend(app: AppHandle, state: State<'_, AppState>) -> Result<ApiConfig, String> {
+    // Taken before the lock so repeated clicks return instead of queueing.
+    let starting = spawn_guard::begin()?;
+    let mut runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    ensure_not_safe_mode(&runtime)?;
+    let config = read_local_config(&runtime.data_dir)?;
+    let running = runtime.backend_ready && runtime.backend_child.is_some();
+    if config.seamless_restart && running {
+        restart(&mut runtime, &starting)?;
+    } else {
+        runtime.stopped_by_user = false;
+        spawn_backend(&mut runtime, &starting)?;
+    }
+    session_file::deliver_pending(&app, &mut runtime);
+    action_queue::flush(&app, &runtime);
+    Ok(backend_status::publish(&app, &runtime))
+}
+
+#[tauri::command]
+pub fn set_seamless_restart(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.seamless_restart = enabled;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
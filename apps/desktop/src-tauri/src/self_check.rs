@@ -0,0 +1,227 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::wsl::{self, BackendHost};
+use crate::{config_crypto, config_path, layout, loopback, AppState, LocalConfig, PYTHON_BIN};
+
+const MIN_FREE_DISK_BYTES: u64 = 200 * 1024 * 1024;
+// 2024-01-01T00:00:00Z; anything earlier means the clock is clearly wrong.
+const MIN_SANE_UNIX_SECS: u64 = 1_704_067_200;
+// 2100-01-01T00:00:00Z.
+const MAX_SANE_UNIX_SECS: u64 = 4_102_444_800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfCheckReport {
+    pub ok: bool,
+    pub checks: Vec<SelfCheck>,
+}
+
+fn check(name: &'static str, result: Result<String, String>) -> SelfCheck {
+    match result {
+        Ok(detail) => SelfCheck {
+            name,
+            status: CheckStatus::Pass,
+            detail,
+        },
+        Err(detail) => SelfCheck {
+            name,
+            status: CheckStatus::Fail,
+            detail,
+        },
+    }
+}
+
+fn check_data_dir_writable(data_dir: &Path) -> Result<String, String> {
+    fs::create_dir_all(data_dir).map_err(|e| format!("cannot create data dir: {e}"))?;
+    let probe = data_dir.join(".self-check-probe");
+    fs::write(&probe, b"probe").map_err(|e| format!("cannot write probe file: {e}"))?;
+    fs::remove_file(&probe).map_err(|e| format!("cannot remove probe file: {e}"))?;
+    Ok("data dir is writable".to_string())
+}
+
+fn check_free_disk(data_dir: &Path) -> Result<String, String> {
+    let available = available_space(data_dir)
+        .ok_or_else(|| "could not determine free disk space".to_string())?;
+    let mb = available / (1024 * 1024);
+    if available < MIN_FREE_DISK_BYTES {
+        return Err(format!(
+            "only {mb} MB free; at least {} MB recommended",
+            MIN_FREE_DISK_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(format!("{mb} MB free"))
+}
+
+fn check_config_parseable(data_dir: &Path) -> Result<String, String> {
+    let path = config_path(data_dir);
+    if !path.exists() {
+        return Ok("config not created yet; defaults will be written".to_string());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("cannot read config: {e}"))?;
+    let content = config_crypto::decode(content).map_err(|e| e.message)?;
+    serde_json::from_str::<LocalConfig>(&content)
+        .map(|_| "config parsed".to_string())
+        .map_err(|e| format!("invalid config json: {e}"))
+}
+
+fn check_logs_dir(data_dir: &Path) -> Result<String, String> {
+    fs::create_dir_all(layout::cache_dir(data_dir).join("logs"))
+        .map(|_| "logs dir available".to_string())
+        .map_err(|e| format!("cannot create logs dir: {e}"))
+}
+
+/// Checks the interpreter `spawn_backend` will use. When native Python is
+/// missing but WSL exists, the failure says so.
+fn check_python(config: &LocalConfig) -> Result<String, String> {
+    if config.backend_host == BackendHost::Wsl {
+        let target = wsl::target(config)?;
+        let output = wsl::python_command(&target)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| format!("`wsl.exe` not found: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "`python3 --version` in WSL exited with {}",
+                output.status
+            ));
+        }
+        return Ok(format!(
+            "{} (WSL)",
+            String::from_utf8_lossy(&output.stdout).trim()
+        ));
+    }
+    let output = Command::new(PYTHON_BIN)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            let hint = if wsl::is_available() {
+                "; WSL is available, set backend_host to \"wsl\" to use its python3"
+            } else {
+                ""
+            };
+            format!("`{PYTHON_BIN}` not found: {e}{hint}")
+        })?;
+    if !output.status.success() {
+        return Err(format!(
+            "`{PYTHON_BIN} --version` exited with {}",
+            output.status
+        ));
+    }
+    // Python 2 printed the version to stderr.
+    let mut version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if version.is_empty() {
+        version = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    }
+    Ok(version)
+}
+
+/// The config the next spawn will use; unreadable configs give defaults.
+fn lenient_config(data_dir: &Path) -> LocalConfig {
+    fs::read_to_string(config_path(data_dir))
+        .ok()
+        .and_then(|content| config_crypto::decode(content).ok())
+        .and_then(|content| serde_json::from_str::<LocalConfig>(&content).ok())
+        .unwrap_or_default()
+}
+
+fn check_ports(config: &LocalConfig) -> Result<String, String> {
+    loopback::find_open_port(&loopback::candidates(config), &[])
+        .map(|port| format!("port {port} available"))
+}
+
+fn check_clock() -> Result<String, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| "system clock is before 1970".to_string())?
+        .as_secs();
+    if !(MIN_SANE_UNIX_SECS..MAX_SANE_UNIX_SECS).contains(&now) {
+        return Err(format!("system clock looks wrong (unix time {now})"));
+    }
+    Ok(format!("unix time {now}"))
+}
+
+pub fn run_self_check(data_dir: &Path) -> SelfCheckReport {
+    let config = lenient_config(data_dir);
+    let checks = vec![
+        check("data_dir_writable", check_data_dir_writable(data_dir)),
+        check("free_disk_space", check_free_disk(data_dir)),
+        check("config_parseable", check_config_parseable(data_dir)),
+        check("logs_dir_creatable", check_logs_dir(data_dir)),
+        check("python_resolvable", check_python(&config)),
+        check("port_available", check_ports(&config)),
+        check("clock_sane", check_clock()),
+    ];
+    let ok = checks.iter().all(|c| c.status == CheckStatus::Pass);
+    SelfCheckReport { ok, checks }
+}
+
+/// Emits the report kept from the last run as `app-self-check`. `setup` runs
+/// the check before any page listens, so the main window's page load sends
+/// it from here; call it off the main thread, as a start holds the lock.
+pub fn announce(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let report = match state.runtime.lock() {
+        Ok(runtime) => runtime.self_check.clone(),
+        Err(_) => return,
+    };
+    if let Some(report) = report {
+        let _ = app.emit("app-self-check", report);
+    }
+}
+
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available: u64 = 0;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+    Some(available)
+}
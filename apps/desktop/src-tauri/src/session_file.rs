@@ -0,0 +1,182 @@
+//! `.liteclaw` session files: versioned JSON conversation exports that can be
+//! opened with the app. Files arriving before the backend is ready are queued
+//! and delivered once it is.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::CommandError;
+use crate::{unix_now, AppState, BackendRuntime};
+
+pub const SESSION_EXTENSION: &str = "liteclaw";
+pub const SESSION_MAGIC: &str = "liteclaw-session";
+/// 2: `exported_at` is RFC 3339 rather than unix seconds.
+pub const SESSION_VERSION: u32 = 2;
+const MAX_SESSION_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub format: String,
+    pub version: u32,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub exported_at: u64,
+    pub conversation: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenedSession {
+    pub path: String,
+    pub session: SessionFile,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionOpenError {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingSessionFiles {
+    pub sessions: Vec<OpenedSession>,
+    pub errors: Vec<SessionOpenError>,
+}
+
+pub fn wrap_conversation(conversation: serde_json::Value) -> SessionFile {
+    SessionFile {
+        format: SESSION_MAGIC.to_string(),
+        version: SESSION_VERSION,
+        exported_at: unix_now(),
+        conversation,
+    }
+}
+
+pub fn parse_session_file(path: &Path) -> Result<SessionFile, CommandError> {
+    let metadata = fs::metadata(path)
+        .map_err(|e| CommandError::not_found(format!("cannot open {}: {e}", path.display())))?;
+    if metadata.len() > MAX_SESSION_BYTES {
+        return Err(CommandError::invalid_input("session file is too large"));
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("failed reading session file: {e}"))?;
+    let session: SessionFile = serde_json::from_str(&content)
+        .map_err(|e| CommandError::invalid_input(format!("not a LiteClaw session file: {e}")))?;
+    if session.format != SESSION_MAGIC {
+        return Err(CommandError::invalid_input(
+            "not a LiteClaw session file: missing header",
+        ));
+    }
+    if session.version == 0 || session.version > SESSION_VERSION {
+        return Err(CommandError::invalid_input(format!(
+            "unsupported session file version {}",
+            session.version
+        )));
+    }
+    if !session
+        .conversation
+        .get("messages")
+        .is_some_and(|messages| messages.is_array())
+    {
+        return Err(CommandError::invalid_input(
+            "session file has no conversation messages",
+        ));
+    }
+    Ok(session)
+}
+
+/// Picks `.liteclaw` paths out of a launch or second-instance argv.
+pub fn session_paths_in_args(args: &[String]) -> Vec<PathBuf> {
+    args.iter()
+        .skip(1)
+        .map(PathBuf::from)
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(SESSION_EXTENSION))
+        })
+        .collect()
+}
+
+/// Delivers queued sessions now that the backend can import them.
+pub fn deliver_pending(app: &AppHandle, runtime: &mut BackendRuntime) {
+    if !runtime.backend_ready {
+        return;
+    }
+    for opened in runtime.pending_sessions.drain(..) {
+        let _ = app.emit("open-session-file", &opened);
+    }
+}
+
+fn load(path: &Path) -> Result<OpenedSession, SessionOpenError> {
+    let display = path.to_string_lossy().to_string();
+    match parse_session_file(path) {
+        Ok(session) => Ok(OpenedSession {
+            path: display,
+            session,
+        }),
+        Err(err) => Err(SessionOpenError {
+            path: display,
+            message: err.message,
+        }),
+    }
+}
+
+/// Queues files passed on the command line at launch; the frontend collects
+/// them (and any parse errors) with `take_pending_session_files` once loaded.
+pub fn queue_launch_sessions(runtime: &mut BackendRuntime, args: &[String]) {
+    for path in session_paths_in_args(args) {
+        match load(&path) {
+            Ok(opened) => runtime.pending_sessions.push(opened),
+            Err(err) => runtime.session_errors.push(err),
+        }
+    }
+}
+
+/// Parses `path` and either emits `open-session-file` or queues it until the
+/// backend is ready. Failures are reported via `open-session-file-error` so
+/// a double-clicked file never silently does nothing.
+pub fn open_session_path(app: &AppHandle, runtime: &mut BackendRuntime, path: &Path) {
+    match load(path) {
+        Ok(opened) => {
+            runtime.pending_sessions.push(opened);
+            deliver_pending(app, runtime);
+        }
+        Err(err) => {
+            let _ = app.emit("open-session-file-error", &err);
+        }
+    }
+}
+
+pub fn open_session_paths(app: &AppHandle, paths: &[PathBuf]) {
+    if paths.is_empty() {
+        return;
+    }
+    let state = app.state::<AppState>();
+    let Ok(mut runtime) = state.runtime.lock() else {
+        return;
+    };
+    for path in paths {
+        open_session_path(app, &mut runtime, path);
+    }
+}
+
+/// Returns files queued before the frontend was listening. Sessions stay
+/// queued while the backend is not ready; errors are always handed over.
+#[tauri::command]
+pub fn take_pending_session_files(
+    state: State<'_, AppState>,
+) -> Result<PendingSessionFiles, CommandError> {
+    let mut runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let sessions = if runtime.backend_ready {
+        runtime.pending_sessions.drain(..).collect()
+    } else {
+        Vec::new()
+    };
+    Ok(PendingSessionFiles {
+        sessions,
+        errors: runtime.session_errors.drain(..).collect(),
+    })
+}
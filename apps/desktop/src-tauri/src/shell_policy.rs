@@ -0,0 +1,403 @@
+//! Which shell commands run without asking. `ShellConfig` carries two
+//! pattern lists matched against the whole command line: a pattern with `*`
+//! or `?` is a glob over the full line, anything else a prefix ending on a
+//! word boundary (`git status` matches `git status -s`, not `git statuses`).
+//!
+//! `always_deny_patterns` win over everything, so a command matching both
+//! lists is denied. An auto-approved command that names a path outside the
+//! allowed folders still asks. Everything else asks: `request_shell_approval`
+//! emits `shell-approval-requested` for those and audits every decision with
+//! the rule that made it.
+
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{
+    audit, backend_reload_config, commit_config, folders, read_local_config, AppState, LocalConfig,
+};
+
+const MAX_PATTERNS: usize = 100;
+const MAX_PATTERN_CHARS: usize = 256;
+const MAX_COMMAND_CHARS: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bucket {
+    AutoApprove,
+    Ask,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternList {
+    AutoApprove,
+    AlwaysDeny,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MatchedRule {
+    pub list: PatternList,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Verdict {
+    pub bucket: Bucket,
+    /// The pattern that decided; `None` when nothing matched.
+    pub rule: Option<MatchedRule>,
+    /// Paths in the command outside every allowed folder. Non-empty only
+    /// when they kept an auto-approve pattern from applying.
+    pub outside_paths: Vec<String>,
+}
+
+/// Glob over the full line when `pattern` has wildcards, otherwise a prefix
+/// that ends where a word does.
+fn matches(pattern: &str, command_line: &str) -> bool {
+    if pattern.contains(['*', '?']) {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = command_line.chars().collect();
+        return glob(&pattern, &text);
+    }
+    match command_line.strip_prefix(pattern) {
+        Some(rest) => rest.is_empty() || rest.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was and how much text it has swallowed.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+            }
+            Some(c) if *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Collapses runs of whitespace so `git  status` and `git status` match alike.
+fn normalize(command_line: &str) -> String {
+    command_line
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn validate_patterns(patterns: Vec<String>, list: &str) -> Result<Vec<String>, CommandError> {
+    if patterns.len() > MAX_PATTERNS {
+        return Err(CommandError::invalid_input(format!(
+            "{list} may hold at most {MAX_PATTERNS} patterns"
+        )));
+    }
+    let mut cleaned: Vec<String> = Vec::with_capacity(patterns.len());
+    for pattern in patterns {
+        let pattern = normalize(&pattern);
+        if pattern.is_empty() || pattern.chars().all(|c| c == '*') {
+            return Err(CommandError::invalid_input(format!(
+                "{list} patterns must name a command"
+            )));
+        }
+        if pattern.chars().count() > MAX_PATTERN_CHARS {
+            return Err(CommandError::invalid_input(format!(
+                "{list} patterns must be at most {MAX_PATTERN_CHARS} characters"
+            )));
+        }
+        if pattern.chars().any(char::is_control) {
+            return Err(CommandError::invalid_input(format!(
+                "{list} pattern `{pattern}` contains control characters"
+            )));
+        }
+        if !cleaned.contains(&pattern) {
+            cleaned.push(pattern);
+        }
+    }
+    Ok(cleaned)
+}
+
+/// Pairs where a command matching the deny pattern also matches the
+/// auto-approve one, or the other way round. Approximated by matching each
+/// pattern's text against the other.
+fn conflict_warnings(auto_approve: &[String], always_deny: &[String]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for allow in auto_approve {
+        for deny in always_deny {
+            if allow == deny || matches(allow, deny) || matches(deny, allow) {
+                warnings.push(format!(
+                    "commands matching both `{allow}` and `{deny}` are denied"
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+/// Arguments that look like paths, resolved against `cwd` without touching
+/// the disk.
+fn path_arguments(command_line: &str, cwd: Option<&Path>) -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    command_line
+        .split_whitespace()
+        .skip(1)
+        .map(|word| word.trim_matches(['"', '\'']))
+        .map(|word| word.split_once('=').map_or(word, |(_, value)| value))
+        .filter(|word| {
+            word.starts_with(['/', '~', '.']) || word.contains(['/', '\\']) || word.contains(":\\")
+        })
+        .filter_map(|word| {
+            let path = match word.strip_prefix('~') {
+                Some(rest) => PathBuf::from(home.as_ref()?).join(rest.trim_start_matches('/')),
+                None => PathBuf::from(word),
+            };
+            // Without a cwd a relative path cannot be placed; it stays
+            // relative and so counts as outside.
+            let path = match cwd {
+                Some(cwd) if !path.is_absolute() => cwd.join(path),
+                _ => path,
+            };
+            Some(lexical(&path))
+        })
+        .collect()
+}
+
+fn lexical(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+pub fn evaluate(
+    data_dir: &Path,
+    config: &LocalConfig,
+    command_line: &str,
+    cwd: Option<&Path>,
+) -> Verdict {
+    let command_line = normalize(command_line);
+    let rule = |list, pattern: &String| MatchedRule {
+        list,
+        pattern: pattern.clone(),
+    };
+    if let Some(deny) = config
+        .shell
+        .always_deny_patterns
+        .iter()
+        .find(|pattern| matches(pattern, &command_line))
+    {
+        return Verdict {
+            bucket: Bucket::Deny,
+            rule: Some(rule(PatternList::AlwaysDeny, deny)),
+            outside_paths: Vec::new(),
+        };
+    }
+    let Some(allow) = config
+        .shell
+        .auto_approve_patterns
+        .iter()
+        .find(|pattern| matches(pattern, &command_line))
+    else {
+        return Verdict {
+            bucket: Bucket::Ask,
+            rule: None,
+            outside_paths: Vec::new(),
+        };
+    };
+    let outside_paths: Vec<String> = path_arguments(&command_line, cwd)
+        .into_iter()
+        .filter(|path| !folders::is_within_allowed(data_dir, config, path))
+        .map(|path| path.display().to_string())
+        .collect();
+    Verdict {
+        bucket: if outside_paths.is_empty() {
+            Bucket::AutoApprove
+        } else {
+            Bucket::Ask
+        },
+        rule: Some(rule(PatternList::AutoApprove, allow)),
+        outside_paths,
+    }
+}
+
+fn validate_command_line(command_line: &str) -> Result<(), CommandError> {
+    if command_line.trim().is_empty() {
+        return Err(CommandError::invalid_input(
+            "command line must not be empty",
+        ));
+    }
+    if command_line.chars().count() > MAX_COMMAND_CHARS {
+        return Err(CommandError::invalid_input(format!(
+            "command line must be at most {MAX_COMMAND_CHARS} characters"
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShellPolicyUpdate {
+    pub config: LocalConfig,
+    /// Patterns that overlap across the lists; the deny side wins.
+    pub warnings: Vec<String>,
+}
+
+#[tauri::command]
+pub fn set_shell_policy(
+    state: State<'_, AppState>,
+    auto_approve_patterns: Vec<String>,
+    always_deny_patterns: Vec<String>,
+) -> Result<ConfigChange<ShellPolicyUpdate>, CommandError> {
+    let auto_approve = validate_patterns(auto_approve_patterns, "auto_approve_patterns")?;
+    let always_deny = validate_patterns(always_deny_patterns, "always_deny_patterns")?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    let warnings = conflict_warnings(&auto_approve, &always_deny);
+    config.shell.auto_approve_patterns = auto_approve;
+    config.shell.always_deny_patterns = always_deny;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    backend_reload_config(&runtime, &config)?;
+    Ok(ConfigChange::new(
+        ShellPolicyUpdate { config, warnings },
+        diff,
+    ))
+}
+
+/// Which bucket `command_line` would fall into, for the settings page.
+/// Changes and records nothing.
+#[tauri::command]
+pub fn test_shell_policy(
+    state: State<'_, AppState>,
+    command_line: String,
+    cwd: Option<String>,
+) -> Result<Verdict, CommandError> {
+    validate_command_line(&command_line)?;
+    let data_dir = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?
+        .data_dir
+        .clone();
+    let config = read_local_config(&data_dir)?;
+    Ok(evaluate(
+        &data_dir,
+        &config,
+        &command_line,
+        cwd.as_deref().map(Path::new),
+    ))
+}
+
+/// Decides a shell step before an approval token is issued for it. `Ask`
+/// verdicts are also emitted as `shell-approval-requested`.
+#[tauri::command]
+pub fn request_shell_approval(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    command_line: String,
+    cwd: Option<String>,
+) -> Result<Verdict, CommandError> {
+    validate_command_line(&command_line)?;
+    let data_dir = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?
+        .data_dir
+        .clone();
+    let config = read_local_config(&data_dir)?;
+    let verdict = evaluate(
+        &data_dir,
+        &config,
+        &command_line,
+        cwd.as_deref().map(Path::new),
+    );
+    audit::record(
+        &data_dir,
+        "shell_policy_applied",
+        serde_json::json!({
+            "command": normalize(&command_line),
+            "bucket": verdict.bucket,
+            "rule": verdict.rule,
+            "outside_paths": verdict.outside_paths,
+        }),
+    )?;
+    if verdict.bucket == Bucket::Ask {
+        let _ = app.emit(
+            "shell-approval-requested",
+            serde_json::json!({ "command_line": command_line, "verdict": verdict }),
+        );
+    }
+    Ok(verdict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deny_wins_and_outside_paths_still_ask() {
+        let data_dir =
+            std::env::temp_dir().join(format!("liteclaw-shell-policy-{}", uuid::Uuid::new_v4()));
+        let mut config = LocalConfig {
+            allowed_folders: vec![folders::AllowedFolder::new("/work/project".to_string())],
+            ..LocalConfig::default()
+        };
+        config.shell.auto_approve_patterns = vec!["git status".into(), "ls*".into()];
+        config.shell.always_deny_patterns = vec!["rm".into(), "ls -R*".into()];
+        let cwd = Some(Path::new("/work/project"));
+        let bucket = |line: &str| evaluate(&data_dir, &config, line, cwd).bucket;
+
+        assert_eq!(bucket("git  status -s"), Bucket::AutoApprove);
+        assert_eq!(bucket("git statuses"), Bucket::Ask);
+        assert_eq!(bucket("rm -rf build"), Bucket::Deny);
+        assert_eq!(bucket("rmdir build"), Bucket::Ask);
+        assert_eq!(bucket("ls -R src"), Bucket::Deny);
+        assert_eq!(bucket("ls ./src"), Bucket::AutoApprove);
+
+        let outside = evaluate(&data_dir, &config, "ls ../../etc", cwd);
+        assert_eq!(outside.bucket, Bucket::Ask);
+        assert_eq!(
+            outside.outside_paths,
+            [Path::new("/etc").display().to_string()]
+        );
+        assert_eq!(
+            outside.rule.map(|rule| rule.list),
+            Some(PatternList::AutoApprove)
+        );
+
+        assert_eq!(
+            conflict_warnings(&config.shell.auto_approve_patterns, &["ls -la".into()]),
+            ["commands matching both `ls*` and `ls -la` are denied"]
+        );
+        assert!(validate_patterns(vec!["  *  ".into()], "auto_approve_patterns").is_err());
+    }
+}
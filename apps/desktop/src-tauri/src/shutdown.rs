@@ -0,0 +1,157 @@
+//! Orderly exit. Tray Quit, closing the last window and the OS ending the
+//! session all reach `RunEvent::ExitRequested` or, for a session end that
+//! skips it, `RunEvent::Exit`; both funnel into one sequence that runs at
+//! most once. `ExitRequested` is held with `prevent_exit` while the sequence
+//! runs on its own thread, which then exits for real. The sequence stops
+//! the status server, cancels registered tasks (uploads, downloads, exports,
+//! backups) and waits for them, saves config buffered during a storage
+//! outage, and stops the backend once it has no requests in flight. Each
+//! step is emitted as `shutdown-progress`. Whatever is still running after
+//! `HARD_CEILING` is cut short by exiting the process.
+//!
+//! The audit and desktop logs are written through on every entry, so there
+//! is nothing of theirs to flush. The timers (heartbeat, backups, temporary
+//! folders) check `in_progress` and stand down, so none of them restarts the
+//! backend or starts a backup halfway through.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    data_dir_lock, desktop_log, seamless_restart, status_server, stop_backend, storage, tasks,
+    AppState,
+};
+
+pub const HARD_CEILING: Duration = Duration::from_secs(10);
+/// How long the backend gets to finish what it is handling.
+const BACKEND_GRACE: Duration = Duration::from_secs(3);
+
+const IDLE: u8 = 0;
+const RUNNING: u8 = 1;
+const DONE: u8 = 2;
+
+static STATE: AtomicU8 = AtomicU8::new(IDLE);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Step {
+    StatusServer,
+    Tasks,
+    Config,
+    Backend,
+}
+
+const STEPS: [Step; 4] = [Step::StatusServer, Step::Tasks, Step::Config, Step::Backend];
+
+#[derive(Debug, Clone, Serialize)]
+struct ShutdownProgress {
+    step: Step,
+    index: usize,
+    total: usize,
+}
+
+/// True from the first exit request on.
+pub fn in_progress() -> bool {
+    STATE.load(Ordering::Acquire) != IDLE
+}
+
+fn run_step(state: &AppState, step: Step) {
+    match step {
+        Step::StatusServer => status_server::stop(),
+        Step::Tasks => {
+            let unfinished = state.tasks.shutdown(tasks::EXIT_GRACE);
+            if unfinished > 0 {
+                if let Ok(runtime) = state.runtime.lock() {
+                    let message = format!("exiting with {unfinished} tasks still running");
+                    desktop_log::warn(&runtime.data_dir, &message);
+                }
+            }
+        }
+        Step::Config => {
+            let Ok(runtime) = state.runtime.lock() else {
+                return;
+            };
+            if storage::flush_pending(&runtime.data_dir).is_none() {
+                let message = "exiting with config changes unsaved; the data folder is unavailable";
+                desktop_log::warn(&runtime.data_dir, message);
+            }
+        }
+        Step::Backend => {
+            let Ok(mut runtime) = state.runtime.lock() else {
+                return;
+            };
+            let (base_url, token) = (runtime.base_url.clone(), runtime.token.clone());
+            if let Some(child) = runtime.backend_child.as_mut() {
+                let busy =
+                    seamless_restart::wait_until_idle(child, &base_url, &token, BACKEND_GRACE);
+                if busy > 0 {
+                    let message = format!("stopping the backend with {busy} requests in flight");
+                    desktop_log::warn(&runtime.data_dir, &message);
+                }
+            }
+            stop_backend(&mut runtime);
+            runtime.backend_ready = false;
+        }
+    }
+}
+
+fn run(app: &AppHandle) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    for (index, step) in STEPS.into_iter().enumerate() {
+        let progress = ShutdownProgress {
+            step,
+            index,
+            total: STEPS.len(),
+        };
+        let _ = app.emit("shutdown-progress", progress);
+        run_step(&state, step);
+    }
+    data_dir_lock::release();
+}
+
+/// For `ExitRequested`: whether to hold the exit. The first request starts
+/// the sequence and is held; the one the sequence makes when done goes
+/// through.
+pub fn on_exit_requested(app: &AppHandle, code: Option<i32>) -> bool {
+    match STATE.compare_exchange(IDLE, RUNNING, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => {
+            let code = code.unwrap_or(0);
+            thread::spawn(move || {
+                thread::sleep(HARD_CEILING);
+                std::process::exit(code);
+            });
+            let app = app.clone();
+            thread::spawn(move || {
+                run(&app);
+                STATE.store(DONE, Ordering::Release);
+                app.exit(code);
+            });
+            true
+        }
+        Err(DONE) => false,
+        Err(_) => true,
+    }
+}
+
+/// For `Exit`, the last event before the process ends. Runs the sequence
+/// here when no request started it, or waits for the one that did.
+pub fn on_exit(app: &AppHandle) {
+    match STATE.compare_exchange(IDLE, RUNNING, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => {
+            run(app);
+            STATE.store(DONE, Ordering::Release);
+        }
+        Err(RUNNING) => {
+            let deadline = Instant::now() + HARD_CEILING;
+            while STATE.load(Ordering::Acquire) != DONE && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+        Err(_) => {}
+    }
+}
@@ -0,0 +1,146 @@
+//! Warnings for commands slow enough to notice. Every command already
+//! passes through `permissions::guard`, which times the dispatch and hands
+//! the result to `observe`. Sync commands run on the main thread, where a
+//! slow one freezes the window, and are timed to completion; async ones are
+//! only timed until they hand off to the runtime. A call over the threshold
+//! (`slow_command_ms`, 0 for never) is counted in `metrics`, logged with a
+//! summary of its arguments, and in debug builds emitted as `slow-command`.
+//!
+//! The summary names each argument but shows only numbers and booleans as
+//! they are: paths are cut to their file name and other strings to their
+//! length, since arguments can carry file contents or prompts.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use tauri::ipc::InvokeBody;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{
+    commit_config, current_data_dir, desktop_log, metrics, read_local_config, AppState, LocalConfig,
+};
+
+pub const DEFAULT_THRESHOLD_MS: u64 = 1000;
+/// Arguments named in a summary; the rest are counted.
+const MAX_SUMMARY_ARGS: usize = 8;
+
+static THRESHOLD_MS: AtomicU64 = AtomicU64::new(DEFAULT_THRESHOLD_MS);
+
+#[derive(Debug, Clone, Serialize)]
+struct SlowCommand {
+    command: String,
+    duration_ms: u64,
+    args: String,
+}
+
+/// Applies `slow_command_ms` from config.
+pub fn configure(threshold_ms: u64) {
+    THRESHOLD_MS.store(threshold_ms, Ordering::Relaxed);
+}
+
+fn summarize_value(value: &serde_json::Value) -> String {
+    use serde_json::Value;
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::String(text) if text.contains(['/', '\\']) => {
+            let name = Path::new(text)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            format!("…/{name}")
+        }
+        Value::String(text) => format!("<{} chars>", text.chars().count()),
+        Value::Array(items) => format!("[{} items]", items.len()),
+        Value::Object(fields) => format!("{{{} fields}}", fields.len()),
+    }
+}
+
+pub fn summarize(body: &InvokeBody) -> String {
+    let fields = match body {
+        InvokeBody::Json(serde_json::Value::Object(fields)) => fields,
+        InvokeBody::Json(value) => return summarize_value(value),
+        InvokeBody::Raw(bytes) => return format!("<{} bytes>", bytes.len()),
+    };
+    let mut parts: Vec<String> = fields
+        .iter()
+        .take(MAX_SUMMARY_ARGS)
+        .map(|(name, value)| format!("{name}={}", summarize_value(value)))
+        .collect();
+    if fields.len() > MAX_SUMMARY_ARGS {
+        parts.push(format!("+{} more", fields.len() - MAX_SUMMARY_ARGS));
+    }
+    parts.join(", ")
+}
+
+/// Called by `permissions::guard` after each dispatch.
+pub fn observe(app: &AppHandle, command: &str, args: String, elapsed: Duration) {
+    let threshold = THRESHOLD_MS.load(Ordering::Relaxed);
+    if threshold == 0 || elapsed < Duration::from_millis(threshold) {
+        return;
+    }
+    metrics::increment(&metrics::METRICS.slow_commands);
+    let slow = SlowCommand {
+        command: command.to_string(),
+        duration_ms: elapsed.as_millis() as u64,
+        args,
+    };
+    if cfg!(debug_assertions) {
+        let _ = app.emit("slow-command", &slow);
+    }
+    // Off the main thread: the runtime lock may be held by whatever is slow.
+    let app = app.clone();
+    thread::spawn(move || {
+        if let Some(data_dir) = current_data_dir(&app) {
+            let message = format!(
+                "slow command {}: {} ms ({})",
+                slow.command, slow.duration_ms, slow.args
+            );
+            desktop_log::warn(&data_dir, &message);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn set_slow_command_threshold(
+    state: State<'_, AppState>,
+    threshold_ms: u64,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.slow_command_ms = threshold_ms;
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    configure(threshold_ms);
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summaries_keep_only_file_names_and_lengths() {
+        let body = InvokeBody::Json(json!({
+            "path": "/home/sam/Projects/secret-plan/notes.md",
+            "content": "the whole file",
+            "ttl_minutes": 30,
+            "follow_symlinks": true,
+            "patterns": ["a", "b"],
+        }));
+        assert_eq!(
+            summarize(&body),
+            "content=<14 chars>, follow_symlinks=true, path=…/notes.md, \
+             patterns=[2 items], ttl_minutes=30"
+        );
+        assert_eq!(summarize(&InvokeBody::Raw(vec![0; 4])), "<4 bytes>");
+    }
+}
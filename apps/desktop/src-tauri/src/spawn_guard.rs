@@ -0,0 +1,132 @@
+//! At most one backend start at a time. A start takes the gate before the
+//! runtime lock, so a second `retry_backend` while one is mid-health-poll
+//! returns "start already in progress" instead of queueing behind the lock
+//! and then killing the backend that just came up. `spawn_backend` requires a
+//! `SpawnGuard` to be called at all.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const IN_PROGRESS: &str = "backend start already in progress";
+
+pub struct StartGate {
+    starting: AtomicBool,
+}
+
+static GATE: StartGate = StartGate::new();
+
+impl StartGate {
+    pub const fn new() -> Self {
+        Self {
+            starting: AtomicBool::new(false),
+        }
+    }
+
+    pub fn begin(&self) -> Result<SpawnGuard<'_>, String> {
+        self.starting
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| SpawnGuard { gate: self })
+            .map_err(|_| IN_PROGRESS.to_string())
+    }
+
+    pub fn is_starting(&self) -> bool {
+        self.starting.load(Ordering::Acquire)
+    }
+}
+
+/// Held for the whole start; dropping it opens the gate again.
+pub struct SpawnGuard<'a> {
+    gate: &'a StartGate,
+}
+
+impl SpawnGuard<'_> {
+    /// For code that must only run mid-start; the gate is never reopened
+    /// while a guard is alive, so this failing means the gate is broken.
+    pub fn assert_held(&self) {
+        debug_assert!(
+            self.gate.is_starting(),
+            "spawn guard alive with the gate open"
+        );
+    }
+}
+
+impl Drop for SpawnGuard<'_> {
+    fn drop(&mut self) {
+        self.gate.starting.store(false, Ordering::Release);
+    }
+}
+
+pub fn begin() -> Result<SpawnGuard<'static>, String> {
+    GATE.begin()
+}
+
+pub fn is_starting() -> bool {
+    GATE.is_starting()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{launch_with_retries, stop_backend, BackendRuntime};
+    use std::process::Command;
+    use std::sync::{Arc, Barrier, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    /// `retry_backend` with the real gate, lock and retry loop; only the
+    /// Python spawn and health poll are stood in for.
+    fn retry(runtime: &Mutex<BackendRuntime>, pids: &Mutex<Vec<u32>>) -> Result<(), String> {
+        let starting = begin()?;
+        let mut runtime = runtime.lock().unwrap();
+        launch_with_retries(&mut runtime, |runtime, _squatted| {
+            stop_backend(runtime);
+            starting.assert_held();
+            let child = Command::new("sleep").arg("30").spawn().unwrap();
+            pids.lock().unwrap().push(child.id());
+            thread::sleep(Duration::from_millis(50));
+            runtime.backend_child = Some(child);
+            Ok(())
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn concurrent_retries_leave_exactly_one_child() {
+        let data_dir =
+            std::env::temp_dir().join(format!("liteclaw-spawn-guard-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+        let runtime = Arc::new(Mutex::new(BackendRuntime::new(
+            data_dir.clone(),
+            "default".to_string(),
+        )));
+        let pids = Arc::new(Mutex::new(Vec::new()));
+        let barrier = Arc::new(Barrier::new(20));
+        let handles: Vec<_> = (0..20)
+            .map(|_| {
+                let (runtime, pids, barrier) = (runtime.clone(), pids.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    retry(&runtime, &pids)
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert!(results.iter().any(Result::is_ok));
+        assert!(results
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .all(|err| err == IN_PROGRESS));
+        assert!(!is_starting());
+        // Replaced children were killed and reaped, so only one pid is live.
+        let alive = pids
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|pid| unsafe { libc::kill(**pid as libc::pid_t, 0) } == 0)
+            .count();
+        assert_eq!(alive, 1);
+
+        stop_backend(&mut runtime.lock().unwrap());
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
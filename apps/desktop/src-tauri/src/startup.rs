@@ -0,0 +1,157 @@
+//! Where launch time goes. `setup` closes named phases as it passes them,
+//! `spawn_backend` times its own steps into `SpawnTimings`, and the window's
+//! first page load closes the report. Durations are monotonic, measured from
+//! the start of `main`. A `last-run` marker in the cache dir tells a warm
+//! start (OS caches likely still hold Python and its imports) from a cold one.
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::error::CommandError;
+use crate::{layout, unix_now, AppState};
+
+/// Runs within this long of the previous launch count as warm.
+const WARM_WITHIN: Duration = Duration::from_secs(60 * 60);
+const LAST_RUN_FILE: &str = "last-run";
+
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+
+/// Called first thing in `main`.
+pub fn mark_process_start() {
+    PROCESS_START.get_or_init(Instant::now);
+}
+
+fn process_start() -> Instant {
+    *PROCESS_START.get_or_init(Instant::now)
+}
+
+/// Time since the start of `main`.
+pub fn process_uptime() -> Duration {
+    process_start().elapsed()
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartKind {
+    Cold,
+    Warm,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupPhase {
+    pub name: &'static str,
+    pub duration_ms: u64,
+}
+
+/// How long the last `spawn_backend` spent in each step.
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnTimings {
+    /// Resolving the backend dir, integrity check and interpreter command.
+    pub python_discovery: Duration,
+    pub spawn: Duration,
+    pub health_wait: Duration,
+    /// `init_ms` from the backend's health payload, if it sends one.
+    pub backend_init_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupReport {
+    pub phases: Vec<StartupPhase>,
+    /// Start of `main` to the window's first page load; `None` until then.
+    pub total_ms: Option<u64>,
+    pub start_kind: StartKind,
+    pub seconds_since_last_run: Option<u64>,
+    pub backend_init_ms: Option<u64>,
+    #[serde(skip)]
+    last_mark: Instant,
+}
+
+impl StartupReport {
+    pub fn begin() -> Self {
+        Self {
+            phases: Vec::new(),
+            total_ms: None,
+            start_kind: StartKind::Cold,
+            seconds_since_last_run: None,
+            backend_init_ms: None,
+            last_mark: process_start(),
+        }
+    }
+
+    /// Records the time since the previous phase ended.
+    pub fn phase(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.push(name, now.duration_since(self.last_mark));
+        self.last_mark = now;
+    }
+
+    fn push(&mut self, name: &'static str, duration: Duration) {
+        self.phases.push(StartupPhase {
+            name,
+            duration_ms: millis(duration),
+        });
+    }
+
+    /// Adds the backend's phases, measured inside `spawn_backend`, and moves
+    /// the mark past them.
+    pub fn backend_started(&mut self, timings: &SpawnTimings) {
+        self.push("python_discovery", timings.python_discovery);
+        self.push("spawn", timings.spawn);
+        self.push("health_wait", timings.health_wait);
+        self.backend_init_ms = timings.backend_init_ms;
+        self.last_mark = Instant::now();
+    }
+
+    /// Skips over work not worth its own phase.
+    pub fn mark(&mut self) {
+        self.last_mark = Instant::now();
+    }
+
+    /// Classifies this start from the previous run's marker, then stamps it.
+    pub fn record_last_run(&mut self, data_dir: &Path) {
+        let marker = last_run_path(data_dir);
+        let previous = fs::read_to_string(&marker)
+            .ok()
+            .and_then(|content| content.trim().parse::<u64>().ok());
+        let now = unix_now();
+        self.seconds_since_last_run = previous.map(|then| now.saturating_sub(then));
+        self.start_kind = match self.seconds_since_last_run {
+            Some(seconds) if seconds < WARM_WITHIN.as_secs() => StartKind::Warm,
+            _ => StartKind::Cold,
+        };
+        if let Some(parent) = marker.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let _ = fs::write(&marker, now.to_string());
+    }
+
+    /// Closes the report at the first page load; later loads are ignored.
+    pub fn window_shown(&mut self) {
+        if self.total_ms.is_some() {
+            return;
+        }
+        self.phase("window_show");
+        self.total_ms = Some(millis(process_start().elapsed()));
+    }
+}
+
+fn last_run_path(data_dir: &Path) -> PathBuf {
+    layout::cache_dir(data_dir).join(LAST_RUN_FILE)
+}
+
+#[tauri::command]
+pub fn get_startup_report(state: State<'_, AppState>) -> Result<StartupReport, CommandError> {
+    let report = state
+        .startup
+        .lock()
+        .map_err(|_| "startup report lock poisoned".to_string())?;
+    Ok(report.clone())
+}
@@ -0,0 +1,121 @@
+//! What a starting backend says it is doing. Before `LITECLAW_READY` the
+//! backend may print `LITECLAW_PHASE {"phase": "loading-model", "pct": 40}`
+//! lines (optionally with a display `label`); `backend_output` hands each to
+//! `report`, which keeps the latest for `ApiConfig::startup_phase` and emits
+//! `backend-startup-progress`. The latest phase is cleared when a spawn
+//! starts and once the backend is ready, so after a failed start it names
+//! the phase the backend got stuck in. Backends that print no phases leave
+//! it empty and the window keeps its plain "starting" message. A standby
+//! started by `seamless_restart` reports nothing, since the running backend
+//! is still serving the window.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
+
+pub const PHASE_SENTINEL: &str = "LITECLAW_PHASE";
+const MAX_LABEL_CHARS: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StartupPhase {
+    pub phase: String,
+    /// For display; the phase name in words when the backend sent none.
+    pub label: String,
+    pub pct: Option<u8>,
+}
+
+#[derive(Deserialize)]
+struct PhasePayload {
+    phase: String,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    pct: Option<f64>,
+}
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+static LATEST: Mutex<Option<StartupPhase>> = Mutex::new(None);
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Drops phase reports until it goes out of scope; see `quiet`.
+pub struct Quiet;
+
+impl Drop for Quiet {
+    fn drop(&mut self) {
+        QUIET.store(false, Ordering::Release);
+    }
+}
+
+/// `loading-model` as "Loading model".
+fn phase_words(phase: &str) -> String {
+    let words = phase.replace(['-', '_'], " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+pub fn parse_phase_line(line: &str) -> Option<StartupPhase> {
+    let rest = line.trim().strip_prefix(PHASE_SENTINEL)?;
+    let payload: PhasePayload = serde_json::from_str(rest.trim()).ok()?;
+    let phase: String = payload.phase.trim().chars().take(MAX_LABEL_CHARS).collect();
+    if phase.is_empty() {
+        return None;
+    }
+    let label = payload
+        .label
+        .map(|label| {
+            label
+                .trim()
+                .chars()
+                .take(MAX_LABEL_CHARS)
+                .collect::<String>()
+        })
+        .filter(|label| !label.is_empty())
+        .unwrap_or_else(|| phase_words(&phase));
+    Some(StartupPhase {
+        label,
+        pct: payload.pct.map(|pct| pct.clamp(0.0, 100.0).round() as u8),
+        phase,
+    })
+}
+
+/// Lets `report` emit; called once from `setup`.
+pub fn attach(app: AppHandle) {
+    let _ = APP.set(app);
+}
+
+pub fn report(phase: StartupPhase) {
+    if QUIET.load(Ordering::Acquire) {
+        return;
+    }
+    if let Some(app) = APP.get() {
+        let _ = app.emit("backend-startup-progress", &phase);
+    }
+    if let Ok(mut latest) = LATEST.lock() {
+        *latest = Some(phase);
+    }
+}
+
+/// For a spawn that must not touch what the window shows.
+pub fn quiet() -> Quiet {
+    QUIET.store(true, Ordering::Release);
+    Quiet
+}
+
+/// Whether a quiet spawn is running.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Acquire)
+}
+
+pub fn clear() {
+    if let Ok(mut latest) = LATEST.lock() {
+        *latest = None;
+    }
+}
+
+pub fn latest() -> Option<StartupPhase> {
+    LATEST.lock().ok().and_then(|latest| latest.clone())
+}
@@ -0,0 +1,504 @@
+//! Opt-in HTTP listener for local monitoring agents, served by the desktop
+//! itself so it answers while the backend is down. It binds loopback only,
+//! on `status_server.port`, and serves two read-only routes: `GET /status`,
+//! the `ApiConfig` fields minus the token plus uptime and restart counts,
+//! and `GET /metrics`, the same numbers and the `metrics` counters in
+//! Prometheus text format. A request whose `Host` is not `127.0.0.1:<port>`
+//! or `localhost:<port>` is refused, so a web page that rebinds its own
+//! name to 127.0.0.1 cannot read either route. A port that cannot be bound
+//! is logged and shown by `get_status_server`; the rest of the app carries
+//! on.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt::{Display, Write as _};
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{
+    api_config, backend_status, commit_config, desktop_log, metrics, read_local_config, startup,
+    AppState, BackendRuntime, LocalConfig,
+};
+
+pub const DEFAULT_PORT: u16 = 47_831;
+/// How often the accept loop checks whether it should stop.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusServerConfig {
+    pub enabled: bool,
+    pub port: u16,
+}
+
+impl Default for StatusServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+struct Running {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl Running {
+    fn shut_down(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.thread.join();
+    }
+}
+
+struct Server {
+    running: Option<Running>,
+    /// Why the last enabled start did not bind.
+    error: Option<String>,
+}
+
+static SERVER: Mutex<Server> = Mutex::new(Server {
+    running: None,
+    error: None,
+});
+
+/// Starts, restarts or stops the listener to match `config`. Must not be
+/// called with the runtime lock held: stopping waits for the serving thread,
+/// which takes that lock to answer.
+pub fn apply(app: &AppHandle, data_dir: &Path, config: &StatusServerConfig) {
+    let Ok(mut server) = SERVER.lock() else {
+        return;
+    };
+    let wanted = config.enabled.then_some(config.port);
+    if wanted.is_some() && server.running.as_ref().map(|running| running.port) == wanted {
+        return;
+    }
+    if let Some(running) = server.running.take() {
+        running.shut_down();
+    }
+    server.error = None;
+    let Some(port) = wanted else {
+        return;
+    };
+    match start(app.clone(), port) {
+        Ok(running) => server.running = Some(running),
+        Err(err) => {
+            let message = format!("status server could not listen on 127.0.0.1:{port}: {err}");
+            desktop_log::warn(data_dir, &message);
+            server.error = Some(message);
+        }
+    }
+}
+
+/// Closes the listener on exit.
+pub fn stop() {
+    if let Ok(mut server) = SERVER.lock() {
+        if let Some(running) = server.running.take() {
+            running.shut_down();
+        }
+    }
+}
+
+fn start(app: AppHandle, port: u16) -> io::Result<Running> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    // Non-blocking so the loop can notice `stop` between connections.
+    listener.set_nonblocking(true)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    let thread = thread::spawn(move || {
+        while !flag.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let _ = respond(&app, stream, port);
+                }
+                Err(_) => thread::sleep(ACCEPT_POLL),
+            }
+        }
+    });
+    Ok(Running { port, stop, thread })
+}
+
+/// The request line and headers; the body, if any, is ignored.
+fn read_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while head.len() < MAX_REQUEST_HEAD && !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&chunk[..read]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+struct Reply {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn text(status: &'static str, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{body}\n"),
+        }
+    }
+}
+
+/// Whether the `Host` header names this listener. Browsers send the name
+/// the page used, so a rebound name never matches; a request without one is
+/// refused too.
+fn host_allowed(head: &str, port: u16) -> bool {
+    let host = head.lines().skip(1).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("host")
+            .then(|| value.trim())
+    });
+    host.is_some_and(|host| {
+        [format!("127.0.0.1:{port}"), format!("localhost:{port}")]
+            .iter()
+            .any(|allowed| host.eq_ignore_ascii_case(allowed))
+    })
+}
+
+fn respond(app: &AppHandle, mut stream: TcpStream, port: u16) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let head = read_head(&mut stream)?;
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default();
+    let reply = if host_allowed(&head, port) {
+        route(app, method, path)
+    } else {
+        Reply::text("403 Forbidden", "unexpected Host header")
+    };
+    let allow = if reply.status.starts_with("405") {
+        "Allow: GET\r\n"
+    } else {
+        ""
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\n{allow}Connection: close\r\n\r\n{}",
+        reply.status,
+        reply.content_type,
+        reply.body.len(),
+        reply.body
+    )?;
+    stream.flush()
+}
+
+fn route(app: &AppHandle, method: &str, path: &str) -> Reply {
+    if path != "/status" && path != "/metrics" {
+        return Reply::text("404 Not Found", "not found");
+    }
+    if method != "GET" {
+        return Reply::text("405 Method Not Allowed", "only GET is supported");
+    }
+    let Some(state) = app.try_state::<AppState>() else {
+        return Reply::text("503 Service Unavailable", "starting");
+    };
+    let Ok(runtime) = state.runtime.lock() else {
+        return Reply::text("500 Internal Server Error", "runtime lock poisoned");
+    };
+    if path == "/status" {
+        Reply {
+            status: "200 OK",
+            content_type: "application/json",
+            body: status_json(&runtime).to_string(),
+        }
+    } else {
+        Reply {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: prometheus_text(&runtime),
+        }
+    }
+}
+
+fn status_json(runtime: &BackendRuntime) -> Value {
+    let mut status = serde_json::to_value(api_config(runtime)).unwrap_or_default();
+    if let Some(fields) = status.as_object_mut() {
+        fields.remove("token");
+        fields.insert("app_version".into(), json!(env!("CARGO_PKG_VERSION")));
+        fields.insert(
+            "uptime_secs".into(),
+            json!(startup::process_uptime().as_secs()),
+        );
+        fields.insert(
+            "backend_uptime_secs".into(),
+            json!(runtime.crash_tracker.ready_for().map(|up| up.as_secs())),
+        );
+        fields.insert(
+            "backend_restarts".into(),
+            json!(metrics::snapshot().backend_restarts),
+        );
+    }
+    status
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl Display) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+fn prometheus_text(runtime: &BackendRuntime) -> String {
+    let status = backend_status::backend_status(runtime);
+    let counters = metrics::snapshot();
+    let mut out = String::new();
+    let _ = writeln!(out, "# HELP liteclaw_info Desktop build information.");
+    let _ = writeln!(out, "# TYPE liteclaw_info gauge");
+    let _ = writeln!(
+        out,
+        "liteclaw_info{{version=\"{}\"}} 1",
+        env!("CARGO_PKG_VERSION")
+    );
+    metric(
+        &mut out,
+        "liteclaw_uptime_seconds",
+        "gauge",
+        "Seconds since the desktop process started.",
+        startup::process_uptime().as_secs(),
+    );
+    metric(
+        &mut out,
+        "liteclaw_backend_ready",
+        "gauge",
+        "1 while the backend answers.",
+        u8::from(status.backend_ready),
+    );
+    metric(
+        &mut out,
+        "liteclaw_backend_uptime_seconds",
+        "gauge",
+        "Seconds since the backend became ready; 0 while it is not.",
+        runtime
+            .crash_tracker
+            .ready_for()
+            .map_or(0, |up| up.as_secs()),
+    );
+    metric(
+        &mut out,
+        "liteclaw_backend_crash_loop",
+        "gauge",
+        "1 once automatic restarts have been given up on.",
+        u8::from(status.crash_loop),
+    );
+    metric(
+        &mut out,
+        "liteclaw_config_generation",
+        "gauge",
+        "Generation of the config last written by the desktop.",
+        status.config_generation,
+    );
+    metric(
+        &mut out,
+        "liteclaw_config_in_sync",
+        "gauge",
+        "1 when the backend runs the latest config generation.",
+        u8::from(status.config_in_sync),
+    );
+    metric(
+        &mut out,
+        "liteclaw_backend_restarts_total",
+        "counter",
+        "Automatic restarts after the backend exited on its own.",
+        counters.backend_restarts,
+    );
+    metric(
+        &mut out,
+        "liteclaw_backend_reloads_total",
+        "counter",
+        "Config reloads sent to the backend.",
+        counters.backend_reloads,
+    );
+    metric(
+        &mut out,
+        "liteclaw_backend_reloads_coalesced_total",
+        "counter",
+        "Config reloads merged into a later one.",
+        counters.backend_reloads_coalesced,
+    );
+    metric(
+        &mut out,
+        "liteclaw_folder_listing_cache_hits_total",
+        "counter",
+        "Folder listings answered from the listing cache.",
+        counters.folder_listing_hits,
+    );
+    metric(
+        &mut out,
+        "liteclaw_folder_listing_cache_misses_total",
+        "counter",
+        "Folder listings read from disk.",
+        counters.folder_listing_misses,
+    );
+    metric(
+        &mut out,
+        "liteclaw_slow_commands_total",
+        "counter",
+        "Commands that took longer than the slow-command threshold.",
+        counters.slow_commands,
+    );
+    metric(
+        &mut out,
+        "liteclaw_responses_spilled_total",
+        "counter",
+        "Command results over the size limit written to a file instead.",
+        counters.responses_spilled,
+    );
+    metric(
+        &mut out,
+        "liteclaw_responses_refused_total",
+        "counter",
+        "Command results over the size limit refused for a streaming command.",
+        counters.responses_refused,
+    );
+    metric(
+        &mut out,
+        "liteclaw_max_response_bytes",
+        "gauge",
+        "Size limit on a command result; 0 when there is none.",
+        counters.max_response_bytes,
+    );
+    out
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusServerStatus {
+    pub enabled: bool,
+    pub port: u16,
+    pub listening: bool,
+    pub error: Option<String>,
+}
+
+fn status_of(config: &StatusServerConfig) -> StatusServerStatus {
+    let (listening, error) = match SERVER.lock() {
+        Ok(server) => (server.running.is_some(), server.error.clone()),
+        Err(_) => (false, Some("status server lock poisoned".to_string())),
+    };
+    StatusServerStatus {
+        enabled: config.enabled,
+        port: config.port,
+        listening,
+        error,
+    }
+}
+
+#[tauri::command]
+pub fn get_status_server(state: State<'_, AppState>) -> Result<StatusServerStatus, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let config = read_local_config(&runtime.data_dir)?;
+    Ok(status_of(&config.status_server))
+}
+
+fn update(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    mutate: impl FnOnce(&mut StatusServerConfig),
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    mutate(&mut config.status_server);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    let data_dir = runtime.data_dir.clone();
+    drop(runtime);
+    apply(app, &data_dir, &config.status_server);
+    Ok(ConfigChange::new(config, diff))
+}
+
+pub fn validate_port(port: u16) -> Result<(), CommandError> {
+    if port < 1024 {
+        return Err(CommandError::invalid_input(
+            "status server port must be between 1024 and 65535",
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_status_server_enabled(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    update(&app, &state, |config| config.enabled = enabled)
+}
+
+#[tauri::command]
+pub fn set_status_server_port(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    port: u16,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    validate_port(port)?;
+    update(&app, &state, |config| config.port = port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn only_loopback_hosts_on_our_port_are_served() {
+        let request = |host: &str| format!("GET /status HTTP/1.1\r\n{host}\r\nAccept: */*\r\n\r\n");
+        for host in [
+            "Host: 127.0.0.1:4000",
+            "host: LOCALHOST:4000",
+            "Host:localhost:4000 ",
+        ] {
+            assert!(host_allowed(&request(host), 4000), "{host}");
+        }
+        for host in [
+            "Host: attacker.example:4000",
+            "Host: 127.0.0.1:4001",
+            "Host: 127.0.0.1",
+            "Host: localhost.attacker.example:4000",
+            "X-Host: 127.0.0.1:4000",
+        ] {
+            assert!(!host_allowed(&request(host), 4000), "{host}");
+        }
+        assert!(!host_allowed("GET /status HTTP/1.0\r\n\r\n", 4000));
+    }
+
+    #[test]
+    fn status_never_carries_the_token() {
+        let mut runtime = BackendRuntime::new(PathBuf::from("data"), "default".to_string());
+        runtime.token = "secret-token".to_string();
+        runtime.backend_ready = true;
+        let status = status_json(&runtime);
+        assert!(status.get("token").is_none());
+        assert_eq!(status["backend_ready"], true);
+        assert_eq!(status["app_version"], env!("CARGO_PKG_VERSION"));
+        assert!(!status.to_string().contains("secret-token"));
+        assert!(!prometheus_text(&runtime).contains("secret-token"));
+    }
+}
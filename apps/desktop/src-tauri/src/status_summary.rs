@@ -0,0 +1,408 @@
+//! Everything the settings page reports on, in one read, for screen-reader
+//! users who would otherwise step through `get_api_config`, the folder
+//! validation, pending actions and storage state separately. Each source is
+//! read on its own thread and given its own timeout, so a source stuck
+//! behind the runtime lock (a slow backend start holds it) or a folder on a
+//! hung network drive shows as `unknown` instead of holding up the rest.
+//!
+//! Every section carries a short sentence meant to be read out as is; the
+//! overall `severity` is the worst of the sections that answered. Unknown
+//! sections do not raise it, since unknown is not known to be wrong.
+//!
+//! The backend does not report indexing progress, so the index section
+//! always reads as unknown.
+
+use serde::Serialize;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    backend_status, config_in_sync, current_data_dir, folders, read_local_config, storage,
+    AppState, BackendRuntime, BackendState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Backend,
+    ConfigSync,
+    Index,
+    Folders,
+    PendingActions,
+    Storage,
+}
+
+impl Source {
+    const ALL: [Source; 6] = [
+        Source::Backend,
+        Source::ConfigSync,
+        Source::Index,
+        Source::Folders,
+        Source::PendingActions,
+        Source::Storage,
+    ];
+
+    /// Folder checks touch the disk; the rest only need the runtime lock.
+    fn timeout(self) -> Duration {
+        match self {
+            Source::Folders => Duration::from_secs(2),
+            Source::Storage => Duration::from_millis(250),
+            _ => Duration::from_millis(500),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Source::Backend => "Backend",
+            Source::ConfigSync => "Settings sync",
+            Source::Index => "Indexing",
+            Source::Folders => "Folders",
+            Source::PendingActions => "Pending actions",
+            Source::Storage => "Storage",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionSeverity {
+    Ok,
+    Warning,
+    Error,
+    /// The source did not answer in time.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Section {
+    pub source: Source,
+    pub severity: SectionSeverity,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSummary {
+    pub severity: Severity,
+    pub summary: String,
+    /// One per `Source`, in `Source::ALL` order.
+    pub sections: Vec<Section>,
+}
+
+fn section(source: Source, severity: SectionSeverity, summary: impl Into<String>) -> Section {
+    Section {
+        source,
+        severity,
+        summary: summary.into(),
+    }
+}
+
+fn unknown(source: Source, summary: &str) -> Section {
+    section(source, SectionSeverity::Unknown, summary)
+}
+
+fn backend_section(runtime: &BackendRuntime) -> Section {
+    use SectionSeverity::*;
+    let api = backend_status::backend_status(runtime);
+    let (severity, summary) = match api.backend_state {
+        _ if api.safe_mode => (
+            Warning,
+            "Safe mode is on; the backend is not started.".into(),
+        ),
+        BackendState::Ready => (Ok, "Backend is running.".to_string()),
+        BackendState::Degraded => (Warning, "Backend is running but responding slowly.".into()),
+        BackendState::Starting => (Warning, "Backend is starting.".into()),
+        BackendState::StoppedByUser => (Ok, "Backend was stopped.".into()),
+        BackendState::Stopped => (Warning, "Backend is not running.".into()),
+        BackendState::Errored if api.crash_loop => (
+            Error,
+            "Backend keeps crashing and was not restarted.".into(),
+        ),
+        BackendState::Errored => {
+            let detail = api.last_error.unwrap_or_default();
+            (
+                Error,
+                format!("Backend failed to start. {detail}")
+                    .trim()
+                    .to_string(),
+            )
+        }
+    };
+    section(Source::Backend, severity, summary)
+}
+
+fn config_sync_section(runtime: &BackendRuntime) -> Section {
+    let source = Source::ConfigSync;
+    if !runtime.backend_ready {
+        return section(
+            source,
+            SectionSeverity::Ok,
+            "Settings are sent when the backend starts.",
+        );
+    }
+    if config_in_sync(runtime) {
+        section(
+            source,
+            SectionSeverity::Ok,
+            "Backend has the latest settings.",
+        )
+    } else {
+        section(
+            source,
+            SectionSeverity::Warning,
+            "Backend has not applied the latest settings yet.",
+        )
+    }
+}
+
+fn index_section() -> Section {
+    unknown(Source::Index, "Indexing progress is not reported.")
+}
+
+fn pending_actions_section(runtime: &BackendRuntime) -> Section {
+    let source = Source::PendingActions;
+    match runtime.pending_actions.list().len() {
+        0 => section(source, SectionSeverity::Ok, "No actions are waiting."),
+        1 => section(
+            source,
+            SectionSeverity::Warning,
+            "1 action is waiting for the backend.",
+        ),
+        count => section(
+            source,
+            SectionSeverity::Warning,
+            format!("{count} actions are waiting for the backend."),
+        ),
+    }
+}
+
+fn folders_section(app: &AppHandle) -> Section {
+    let source = Source::Folders;
+    let Some(data_dir) = current_data_dir(app) else {
+        return unknown(source, "Folders could not be checked.");
+    };
+    let Ok(config) = read_local_config(&data_dir) else {
+        return unknown(
+            source,
+            "Folders could not be checked; settings did not load.",
+        );
+    };
+    let report = folders::validate_config_folders(&data_dir, &config);
+    let problems = report
+        .folders
+        .iter()
+        .filter(|folder| folder.status != folders::FolderStatus::Ok)
+        .count();
+    let blocked = report.privacy.blocked_folders.len();
+    match (problems, blocked) {
+        (0, 0) if report.folders.is_empty() => {
+            section(source, SectionSeverity::Ok, "No folders are shared.")
+        }
+        (0, 0) => section(
+            source,
+            SectionSeverity::Ok,
+            format!("All {} shared folders are reachable.", report.folders.len()),
+        ),
+        (0, blocked) => section(
+            source,
+            SectionSeverity::Warning,
+            format!("{blocked} shared folders are blocked by system privacy settings."),
+        ),
+        (problems, _) => section(
+            source,
+            SectionSeverity::Warning,
+            format!("{problems} shared folders are missing or unreadable."),
+        ),
+    }
+}
+
+fn storage_section() -> Section {
+    let source = Source::Storage;
+    if !storage::is_unavailable() {
+        return section(source, SectionSeverity::Ok, "Data folder is available.");
+    }
+    let buffered = storage::buffered_mutations();
+    section(
+        source,
+        SectionSeverity::Error,
+        format!("Data folder is unavailable; {buffered} changes are waiting to be saved."),
+    )
+}
+
+fn with_runtime(app: &AppHandle, read: impl FnOnce(&BackendRuntime) -> Section) -> Option<Section> {
+    let state = app.try_state::<AppState>()?;
+    let runtime = state.runtime.lock().ok()?;
+    Some(read(&runtime))
+}
+
+fn read_source(app: &AppHandle, source: Source) -> Section {
+    let read = match source {
+        Source::Backend => with_runtime(app, backend_section),
+        Source::ConfigSync => with_runtime(app, config_sync_section),
+        Source::Index => Some(index_section()),
+        Source::PendingActions => with_runtime(app, pending_actions_section),
+        Source::Folders => Some(folders_section(app)),
+        Source::Storage => Some(storage_section()),
+    };
+    read.unwrap_or_else(|| unknown(source, "Status could not be read."))
+}
+
+/// Runs `read` for every source at once and waits for each up to its
+/// timeout. Threads that miss it are left to finish on their own.
+fn gather<F>(read: F) -> Vec<Section>
+where
+    F: Fn(Source) -> Section + Clone + Send + 'static,
+{
+    let started = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    for source in Source::ALL {
+        let (tx, read) = (tx.clone(), read.clone());
+        thread::spawn(move || {
+            let _ = tx.send(read(source));
+        });
+    }
+    drop(tx);
+    let mut answered: Vec<Section> = Vec::new();
+    loop {
+        let waiting = Source::ALL
+            .into_iter()
+            .filter(|source| !answered.iter().any(|section| section.source == *source));
+        let Some(deadline) = waiting.map(|source| started + source.timeout()).min() else {
+            break;
+        };
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(section) => answered.push(section),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let now = Instant::now();
+                for source in Source::ALL {
+                    let missing = !answered.iter().any(|section| section.source == source);
+                    if missing && started + source.timeout() <= now {
+                        answered.push(unknown(source, "Did not answer in time."));
+                    }
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    Source::ALL
+        .into_iter()
+        .map(|source| {
+            answered
+                .iter()
+                .find(|section| section.source == source)
+                .cloned()
+                .unwrap_or_else(|| unknown(source, "Did not answer."))
+        })
+        .collect()
+}
+
+fn summarize(sections: Vec<Section>) -> StatusSummary {
+    let level = |section: &Section| match section.severity {
+        SectionSeverity::Error => Some(Severity::Error),
+        SectionSeverity::Warning => Some(Severity::Warning),
+        SectionSeverity::Ok | SectionSeverity::Unknown => None,
+    };
+    let severity = sections
+        .iter()
+        .filter_map(level)
+        .max()
+        .unwrap_or(Severity::Ok);
+    let attention: Vec<&str> = sections
+        .iter()
+        .filter(|section| level(section).is_some())
+        .map(|section| section.source.label())
+        .collect();
+    let summary = if attention.is_empty() {
+        "Everything is working.".to_string()
+    } else {
+        format!("Needs attention: {}.", attention.join(", "))
+    };
+    StatusSummary {
+        severity,
+        summary,
+        sections,
+    }
+}
+
+#[tauri::command]
+pub async fn get_status_summary(app: AppHandle) -> StatusSummary {
+    summarize(gather(move |source| read_source(&app, source)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summary_serializes_flat_sections_in_source_order() {
+        let summary = summarize(vec![
+            section(Source::Backend, SectionSeverity::Ok, "Backend is running."),
+            section(Source::ConfigSync, SectionSeverity::Warning, "Stale."),
+            unknown(Source::Index, "Indexing progress is not reported."),
+            section(
+                Source::Folders,
+                SectionSeverity::Ok,
+                "No folders are shared.",
+            ),
+            section(Source::PendingActions, SectionSeverity::Ok, "None."),
+            section(Source::Storage, SectionSeverity::Error, "Gone."),
+        ]);
+        let value = serde_json::to_value(&summary).unwrap();
+        assert_eq!(value["severity"], "error");
+        assert_eq!(value["summary"], "Needs attention: Settings sync, Storage.");
+        assert_eq!(
+            value["sections"][2],
+            json!({
+                "source": "index",
+                "severity": "unknown",
+                "summary": "Indexing progress is not reported.",
+            })
+        );
+        let sources: Vec<&str> = value["sections"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|section| section["source"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                "backend",
+                "config_sync",
+                "index",
+                "folders",
+                "pending_actions",
+                "storage"
+            ]
+        );
+    }
+
+    #[test]
+    fn sources_that_time_out_are_unknown_and_do_not_raise_severity() {
+        let sections = gather(|source| {
+            if source == Source::Storage {
+                thread::sleep(Duration::from_secs(5));
+            }
+            section(source, SectionSeverity::Ok, "Fine.")
+        });
+        let summary = summarize(sections);
+        assert_eq!(summary.severity, Severity::Ok);
+        assert_eq!(summary.summary, "Everything is working.");
+        let storage = &summary.sections[5];
+        assert_eq!(storage.source, Source::Storage);
+        assert_eq!(storage.severity, SectionSeverity::Unknown);
+        assert!(summary.sections[..5]
+            .iter()
+            .all(|section| section.severity == SectionSeverity::Ok));
+    }
+}
@@ -0,0 +1,203 @@
+//! Survives the data dir disappearing mid-session (network home drives, VPN
+//! drops). IO errors that look like the directory is gone switch the app into
+//! a `storage_unavailable` state: config reads are served from memory, config
+//! writes are buffered (up to `MAX_BUFFERED_MUTATIONS`), log writes pause, and
+//! a probe thread flushes everything once the directory is back.
+
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{current_data_dir, desktop_log, write_config_file, LocalConfig};
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+pub const MAX_BUFFERED_MUTATIONS: usize = 64;
+
+struct Cache {
+    /// Last config read from or written to disk.
+    last_known: Option<LocalConfig>,
+    /// Latest config written during the outage, not yet on disk.
+    pending: Option<LocalConfig>,
+    buffered_mutations: usize,
+}
+
+static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+static CACHE: Mutex<Cache> = Mutex::new(Cache {
+    last_known: None,
+    pending: None,
+    buffered_mutations: 0,
+});
+
+#[derive(Debug, Clone, Serialize)]
+struct StorageRestored {
+    flushed_mutations: usize,
+}
+
+pub fn is_unavailable() -> bool {
+    UNAVAILABLE.load(Ordering::SeqCst)
+}
+
+pub fn enter_outage() {
+    UNAVAILABLE.store(true, Ordering::SeqCst);
+}
+
+pub fn unavailable_error() -> CommandError {
+    CommandError::new(
+        ErrorCode::StorageUnavailable,
+        "the data folder is unavailable; changes will be saved when it returns",
+    )
+}
+
+pub fn ensure_available() -> Result<(), CommandError> {
+    if is_unavailable() {
+        return Err(unavailable_error());
+    }
+    Ok(())
+}
+
+/// Errors that mean the storage itself went away, as opposed to a single
+/// file being missing or unreadable.
+pub fn is_outage_io(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    let codes = [libc::ENOTCONN, libc::EIO, libc::ESTALE, libc::EHOSTDOWN];
+    // ERROR_NOT_READY, ERROR_BAD_NETPATH, ERROR_DEV_NOT_EXIST,
+    // ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED.
+    #[cfg(windows)]
+    let codes = [21, 53, 55, 59, 64];
+    err.raw_os_error().is_some_and(|code| codes.contains(&code))
+}
+
+/// Like `is_outage_io`, and also treats "not found" as an outage when the
+/// data dir itself is gone.
+pub fn is_outage(data_dir: &Path, err: &io::Error) -> bool {
+    is_outage_io(err) || (err.kind() == io::ErrorKind::NotFound && !data_dir.is_dir())
+}
+
+/// Config writes held back by the current outage.
+pub fn buffered_mutations() -> usize {
+    CACHE
+        .lock()
+        .map(|cache| cache.buffered_mutations)
+        .unwrap_or(0)
+}
+
+pub fn remember_config(config: &LocalConfig) {
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.last_known = Some(config.clone());
+    }
+}
+
+/// Drops the remembered config when the data dir changes to another
+/// profile's.
+pub fn forget_config() {
+    if let Ok(mut cache) = CACHE.lock() {
+        cache.last_known = None;
+    }
+}
+
+/// Whether this session has seen a config on disk, i.e. a missing data dir
+/// means it vanished rather than was never created.
+pub fn has_cached_config() -> bool {
+    CACHE
+        .lock()
+        .is_ok_and(|cache| cache.last_known.is_some() || cache.pending.is_some())
+}
+
+pub fn cached_config() -> Result<LocalConfig, CommandError> {
+    let cache = CACHE
+        .lock()
+        .map_err(|_| "storage cache poisoned".to_string())?;
+    cache
+        .pending
+        .clone()
+        .or_else(|| cache.last_known.clone())
+        .ok_or_else(unavailable_error)
+}
+
+pub fn buffer_config(config: &LocalConfig) -> Result<(), CommandError> {
+    let mut cache = CACHE
+        .lock()
+        .map_err(|_| "storage cache poisoned".to_string())?;
+    if cache.buffered_mutations >= MAX_BUFFERED_MUTATIONS {
+        return Err(CommandError::new(
+            ErrorCode::StorageUnavailable,
+            "the data folder is unavailable and too many changes are waiting to be saved",
+        ));
+    }
+    cache.buffered_mutations += 1;
+    cache.pending = Some(config.clone());
+    Ok(())
+}
+
+fn storage_back(data_dir: &Path) -> bool {
+    let probe = data_dir.join(".storage-probe.tmp");
+    let writable = data_dir.is_dir() && fs::write(&probe, b"ok").is_ok();
+    let _ = fs::remove_file(&probe);
+    writable
+}
+
+/// Writes the buffered config and leaves the outage state. Returns the number
+/// of mutations flushed, or `None` if the directory is still unusable.
+fn try_restore(data_dir: &Path) -> Option<usize> {
+    if !storage_back(data_dir) {
+        return None;
+    }
+    let mut cache = CACHE.lock().ok()?;
+    if let Some(pending) = cache.pending.clone() {
+        write_config_file(data_dir, &pending).ok()?;
+        cache.last_known = Some(pending);
+    }
+    cache.pending = None;
+    let flushed = std::mem::take(&mut cache.buffered_mutations);
+    UNAVAILABLE.store(false, Ordering::SeqCst);
+    Some(flushed)
+}
+
+/// The last chance at exit to save config buffered during an outage.
+/// Returns the mutations saved, or `None` when the data dir is still gone
+/// and they are lost.
+pub fn flush_pending(data_dir: &Path) -> Option<usize> {
+    if !is_unavailable() {
+        return Some(0);
+    }
+    try_restore(data_dir)
+}
+
+/// Probes for the data dir while it is unavailable and reports transitions.
+/// Profile switches are refused during an outage, so the active data dir is
+/// the one that went away.
+pub fn start(app: AppHandle) {
+    thread::spawn(move || {
+        let mut reported = false;
+        loop {
+            thread::sleep(PROBE_INTERVAL);
+            if !is_unavailable() {
+                continue;
+            }
+            let Some(data_dir) = current_data_dir(&app) else {
+                continue;
+            };
+            if !reported {
+                reported = true;
+                let _ = app.emit("storage-unavailable", data_dir.to_string_lossy());
+            }
+            if let Some(flushed_mutations) = try_restore(&data_dir) {
+                reported = false;
+                desktop_log::info(
+                    &data_dir,
+                    &format!(
+                        "data dir is back; flushed {flushed_mutations} buffered config changes"
+                    ),
+                );
+                let _ = app.emit("storage-restored", StorageRestored { flushed_mutations });
+            }
+        }
+    });
+}
@@ -0,0 +1,272 @@
+//! Restarts a backend that exits on its own after becoming ready, and gives
+//! up once it keeps dying young: after `max_early_exits` consecutive exits
+//! within `early_exit_secs` of readiness the backend is left stopped, a crash
+//! report is written and the user is notified. `retry_backend` starts over.
+//!
+//! Crash reports since version 2 carry `version` and RFC 3339 timestamps;
+//! `read_crash_reports` also reads the unix seconds of version 1.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::backend_error::{BackendError, BackendErrorKind};
+use crate::{
+    backend_log_path, backend_status, desktop_log, discovery, integrations, metrics,
+    read_local_config, safe_write, spawn_backend, spawn_guard, telemetry, traceback_hint, unix_now,
+    BackendRuntime,
+};
+
+/// Exits kept for the crash report.
+const EXIT_HISTORY: usize = 10;
+const CRASH_REPORT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashLoopConfig {
+    /// Consecutive early exits before auto-restart stops.
+    pub max_early_exits: u32,
+    /// An exit this soon after readiness counts as early.
+    pub early_exit_secs: u64,
+}
+
+impl Default for CrashLoopConfig {
+    fn default() -> Self {
+        Self {
+            max_early_exits: 5,
+            early_exit_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExitRecord {
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub exited_at: u64,
+    pub uptime_ms: u64,
+    pub status: String,
+}
+
+#[derive(Debug, Default)]
+pub struct CrashTracker {
+    ready_at: Option<Instant>,
+    early_exits: u32,
+    exits: VecDeque<ExitRecord>,
+    pub crash_loop: bool,
+}
+
+impl CrashTracker {
+    pub fn mark_ready(&mut self) {
+        self.ready_at = Some(Instant::now());
+    }
+
+    /// How long the backend has been ready, if it is.
+    pub fn ready_for(&self) -> Option<Duration> {
+        self.ready_at.map(|at| at.elapsed())
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Records an exit and reports whether it was early.
+    fn record_exit(&mut self, status: String, config: &CrashLoopConfig) -> bool {
+        let uptime = self
+            .ready_at
+            .take()
+            .map(|at| at.elapsed())
+            .unwrap_or_default();
+        let early = uptime.as_secs() < config.early_exit_secs;
+        self.early_exits = if early { self.early_exits + 1 } else { 0 };
+        self.exits.push_back(ExitRecord {
+            exited_at: unix_now(),
+            uptime_ms: uptime.as_millis() as u64,
+            status,
+        });
+        while self.exits.len() > EXIT_HISTORY {
+            self.exits.pop_front();
+        }
+        early
+    }
+}
+
+#[derive(Serialize)]
+struct CrashReport<'a> {
+    version: u32,
+    kind: &'static str,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    created_at: u64,
+    app_version: &'static str,
+    profile: &'a str,
+    early_exits: u32,
+    early_exit_secs: u64,
+    exits: &'a VecDeque<ExitRecord>,
+    backend_log: &'a str,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+/// A crash report as read back, from any version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredCrashReport {
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+    pub kind: String,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub created_at: u64,
+    #[serde(default)]
+    pub app_version: String,
+    #[serde(default)]
+    pub profile: String,
+    #[serde(default)]
+    pub early_exits: u32,
+    #[serde(default)]
+    pub exits: Vec<ExitRecord>,
+}
+
+fn crash_reports_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("crash_reports")
+}
+
+/// Every readable report in the data dir, oldest first.
+pub fn read_crash_reports(data_dir: &Path) -> Vec<StoredCrashReport> {
+    let Ok(entries) = fs::read_dir(crash_reports_dir(data_dir)) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<StoredCrashReport> = entries
+        .flatten()
+        .filter_map(|entry| fs::read(entry.path()).ok())
+        .filter_map(|bytes| serde_json::from_slice(&bytes).ok())
+        .collect();
+    reports.sort_by_key(|report| report.created_at);
+    reports
+}
+
+fn write_crash_report(
+    runtime: &BackendRuntime,
+    config: &CrashLoopConfig,
+) -> Result<PathBuf, String> {
+    let dir = crash_reports_dir(&runtime.data_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("failed creating crash report dir: {e}"))?;
+    let report = CrashReport {
+        version: CRASH_REPORT_VERSION,
+        kind: "crash_loop",
+        created_at: unix_now(),
+        app_version: env!("CARGO_PKG_VERSION"),
+        profile: &runtime.profile,
+        early_exits: runtime.crash_tracker.early_exits,
+        early_exit_secs: config.early_exit_secs,
+        exits: &runtime.crash_tracker.exits,
+        backend_log: &runtime.log_path,
+    };
+    let bytes = serde_json::to_vec_pretty(&report)
+        .map_err(|e| format!("failed serializing crash report: {e}"))?;
+    // Two crash loops can land in the same second; neither report may
+    // replace the other.
+    let path = dir.join(format!("crash-{}-{}.json", unix_now(), Uuid::new_v4()));
+    safe_write::replace(&path, &bytes).map_err(|e| format!("failed writing crash report: {e}"))?;
+    Ok(path)
+}
+
+fn enter_crash_loop(app: &AppHandle, runtime: &mut BackendRuntime, config: &CrashLoopConfig) {
+    let count = runtime.crash_tracker.early_exits;
+    runtime.crash_tracker.crash_loop = true;
+    let message = format!(
+        "backend crashed {count} times within {}s of starting; automatic restarts stopped",
+        config.early_exit_secs
+    );
+    // The last run's traceback, if it printed one.
+    let last_run = runtime.crash_tracker.exits.back().map(|exit| {
+        UNIX_EPOCH + Duration::from_secs(exit.exited_at) - Duration::from_millis(exit.uptime_ms)
+    });
+    let hint = traceback_hint::from_log(&backend_log_path(&runtime.data_dir), last_run);
+    runtime.last_error = Some(
+        BackendError::new(BackendErrorKind::CrashLoop, message.clone())
+            .with_summary(message)
+            .with_hint(hint),
+    );
+    let report = write_crash_report(runtime, config);
+    desktop_log::warn(
+        &runtime.data_dir,
+        &match &report {
+            Ok(path) => format!("crash loop detected; report at {}", path.display()),
+            Err(err) => format!("crash loop detected; {err}"),
+        },
+    );
+    integrations::notify(
+        app,
+        "LiteClaw backend keeps crashing",
+        "Automatic restarts were stopped. Open LiteClaw and press Retry once the problem is fixed.",
+    );
+    backend_status::publish(app, runtime);
+}
+
+/// Called on every heartbeat. Returns true when the backend had exited and
+/// was handled here (restarted or given up on).
+pub fn check_exit(app: &AppHandle, runtime: &mut BackendRuntime) -> bool {
+    if !runtime.backend_ready || runtime.stopped_by_user || runtime.crash_tracker.crash_loop {
+        return false;
+    }
+    let Some(status) = runtime
+        .backend_child
+        .as_mut()
+        .and_then(|child| child.try_wait().ok().flatten())
+    else {
+        return false;
+    };
+    runtime.backend_ready = false;
+    runtime.backend_child = None;
+    runtime.discovery = None;
+    discovery::clear(&runtime.data_dir);
+    let config = read_local_config(&runtime.data_dir)
+        .map(|config| config.crash_loop)
+        .unwrap_or_default();
+    let early = runtime
+        .crash_tracker
+        .record_exit(status.to_string(), &config);
+    telemetry::track_event(
+        &runtime.data_dir,
+        telemetry::backend_crashed(if early { "early" } else { "running" }),
+    );
+    desktop_log::warn(
+        &runtime.data_dir,
+        &format!("backend exited unexpectedly: {status}"),
+    );
+
+    if runtime.crash_tracker.early_exits >= config.max_early_exits.max(1) {
+        enter_crash_loop(app, runtime, &config);
+        return true;
+    }
+    // A start already in progress (a user retry waiting for the lock) will
+    // bring the backend back up.
+    if let Ok(starting) = spawn_guard::begin() {
+        metrics::increment(&metrics::METRICS.backend_restarts);
+        // A failure is already recorded in `last_error`.
+        let _ = spawn_backend(runtime, &starting);
+    }
+    backend_status::publish(app, runtime);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_in_the_same_second_are_all_kept() {
+        let data_dir = std::env::temp_dir().join(format!("liteclaw-crash-{}", Uuid::new_v4()));
+        let runtime = BackendRuntime::new(data_dir.clone(), "default".to_string());
+        let config = CrashLoopConfig::default();
+        let first = write_crash_report(&runtime, &config).unwrap();
+        let second = write_crash_report(&runtime, &config).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(read_crash_reports(&data_dir).len(), 2);
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+}
@@ -0,0 +1,216 @@
+//! Network changes and suspend/clock jumps, both of which tend to make the
+//! first backend call after them fail. Each one triggers an out-of-band
+//! `heartbeat::check_now`. Network notifications come from netlink on Linux,
+//! `NotifyAddrChange` on Windows and interface polling on macOS; suspend is
+//! detected everywhere as the wall clock running away from the monotonic one.
+
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::AppHandle;
+
+use crate::heartbeat;
+
+const CLOCK_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+/// Wall-vs-monotonic divergence treated as a suspend or clock change.
+const CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(30);
+/// Changes arrive in bursts while an interface comes up; wait for quiet.
+const SETTLE_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    NetworkChanged,
+    ClockJump,
+}
+
+impl Trigger {
+    fn describe(self) -> &'static str {
+        match self {
+            Trigger::NetworkChanged => "network change",
+            Trigger::ClockJump => "clock jump or resume from suspend",
+        }
+    }
+}
+
+fn watch_clock(tx: Sender<Trigger>) {
+    let mut mono = Instant::now();
+    let mut wall = SystemTime::now();
+    loop {
+        thread::sleep(CLOCK_SAMPLE_INTERVAL);
+        let (now_mono, now_wall) = (Instant::now(), SystemTime::now());
+        let mono_elapsed = now_mono.duration_since(mono);
+        // A wall clock set backwards shows up as an error here.
+        let divergence = match now_wall.duration_since(wall) {
+            Ok(wall_elapsed) => wall_elapsed.abs_diff(mono_elapsed),
+            Err(back) => back.duration() + mono_elapsed,
+        };
+        (mono, wall) = (now_mono, now_wall);
+        if divergence >= CLOCK_JUMP_THRESHOLD && tx.send(Trigger::ClockJump).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::io;
+    use std::mem;
+    use std::sync::mpsc::Sender;
+
+    use super::Trigger;
+
+    /// Blocks on an rtnetlink socket subscribed to link, address and route
+    /// changes.
+    pub fn watch_network(tx: Sender<Trigger>) {
+        // SAFETY: a socket owned by this function, a zeroed sockaddr_nl and a
+        // stack buffer whose length is passed alongside it.
+        unsafe {
+            let fd = libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            );
+            if fd < 0 {
+                return;
+            }
+            let mut addr: libc::sockaddr_nl = mem::zeroed();
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            addr.nl_groups = (libc::RTMGRP_LINK
+                | libc::RTMGRP_IPV4_IFADDR
+                | libc::RTMGRP_IPV6_IFADDR
+                | libc::RTMGRP_IPV4_ROUTE) as u32;
+            let bound = libc::bind(
+                fd,
+                (&addr as *const libc::sockaddr_nl).cast(),
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            );
+            if bound == 0 {
+                let mut buf = [0u8; 8192];
+                loop {
+                    let read = libc::recv(fd, buf.as_mut_ptr().cast(), buf.len(), 0);
+                    let interrupted =
+                        io::Error::last_os_error().kind() == io::ErrorKind::Interrupted;
+                    if read < 0 && interrupted {
+                        continue;
+                    }
+                    if read <= 0 || tx.send(Trigger::NetworkChanged).is_err() {
+                        break;
+                    }
+                }
+            }
+            libc::close(fd);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ptr;
+    use std::sync::mpsc::Sender;
+    use windows_sys::Win32::NetworkManagement::IpHelper::NotifyAddrChange;
+
+    use super::Trigger;
+
+    pub fn watch_network(tx: Sender<Trigger>) {
+        // SAFETY: null handle and overlapped make this a plain blocking call
+        // that returns once any IPv4 address changes.
+        while unsafe { NotifyAddrChange(ptr::null_mut(), ptr::null()) } == 0 {
+            if tx.send(Trigger::NetworkChanged).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::CStr;
+    use std::mem;
+    use std::ptr;
+    use std::sync::mpsc::Sender;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::Trigger;
+
+    const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// Interface names with their raw IPv4/IPv6 addresses, sorted.
+    fn interface_fingerprint() -> Vec<(String, Vec<u8>)> {
+        let mut found = Vec::new();
+        // SAFETY: walks the list getifaddrs allocated and frees it once; each
+        // address is only read for the size its family declares.
+        unsafe {
+            let mut head: *mut libc::ifaddrs = ptr::null_mut();
+            if libc::getifaddrs(&mut head) != 0 {
+                return found;
+            }
+            let mut cursor = head;
+            while let Some(entry) = cursor.as_ref() {
+                cursor = entry.ifa_next;
+                if entry.ifa_addr.is_null() {
+                    continue;
+                }
+                let len = match i32::from((*entry.ifa_addr).sa_family) {
+                    libc::AF_INET => mem::size_of::<libc::sockaddr_in>(),
+                    libc::AF_INET6 => mem::size_of::<libc::sockaddr_in6>(),
+                    _ => continue,
+                };
+                let name = CStr::from_ptr(entry.ifa_name)
+                    .to_string_lossy()
+                    .into_owned();
+                let bytes = std::slice::from_raw_parts(entry.ifa_addr.cast::<u8>(), len);
+                found.push((name, bytes.to_vec()));
+            }
+            libc::freeifaddrs(head);
+        }
+        found.sort();
+        found
+    }
+
+    pub fn watch_network(tx: Sender<Trigger>) {
+        let mut last = interface_fingerprint();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let current = interface_fingerprint();
+            if current != last {
+                last = current;
+                if tx.send(Trigger::NetworkChanged).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use std::sync::mpsc::Sender;
+
+    use super::Trigger;
+
+    /// No notifications here; the clock watcher still catches suspend.
+    pub fn watch_network(_tx: Sender<Trigger>) {}
+}
+
+pub fn start(app: AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    let clock_tx = tx.clone();
+    thread::spawn(move || watch_clock(clock_tx));
+    thread::spawn(move || platform::watch_network(tx));
+    thread::spawn(move || {
+        while let Ok(first) = rx.recv() {
+            thread::sleep(SETTLE_DELAY);
+            // A clock jump usually comes with network churn on resume; report
+            // it as the more specific reason.
+            let trigger = rx.try_iter().fold(first, |seen, next| {
+                if next == Trigger::ClockJump {
+                    next
+                } else {
+                    seen
+                }
+            });
+            heartbeat::check_now(&app, trigger.describe());
+        }
+    });
+}
@@ -0,0 +1,251 @@
+//! Long-running desktop work (uploads, downloads, exports, backups, folder
+//! previews) in one
+//! registry on `AppState`. Each operation registers under an id for as long
+//! as its `TaskHandle` lives and polls the handle's `TaskToken` for
+//! cancellation between chunks of work, reporting progress through it where
+//! the total is known. `list_tasks` feeds the background activity panel,
+//! `cancel_task` flags one token, and exit flags them all and waits a
+//! bounded time for the tasks to wind down.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{unix_now, AppState};
+
+/// How long exit waits for cancelled tasks to finish.
+pub const EXIT_GRACE: Duration = Duration::from_secs(3);
+const MAX_DESCRIPTION_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    Upload,
+    Download,
+    Export,
+    Backup,
+    Preview,
+}
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    done: AtomicU64,
+    /// 0 while the total is unknown.
+    total: AtomicU64,
+}
+
+/// Shared between a task and the registry: cancellation one way, progress
+/// the other.
+#[derive(Debug, Clone, Default)]
+pub struct TaskToken(Arc<TokenState>);
+
+impl TaskToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// `Err(cancelled)` once cancellation was requested; for `?` in loops.
+    pub fn check(&self, what: &str) -> Result<(), CommandError> {
+        if self.is_cancelled() {
+            return Err(CommandError::new(
+                ErrorCode::Cancelled,
+                format!("{what} was cancelled"),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn report(&self, done: u64, total: Option<u64>) {
+        self.0.done.store(done, Ordering::Relaxed);
+        self.0.total.store(total.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Entry {
+    kind: TaskKind,
+    description: String,
+    started_at: u64,
+    token: TaskToken,
+}
+
+#[derive(Default)]
+pub struct TaskRegistry {
+    tasks: Mutex<HashMap<String, Entry>>,
+    /// Signalled whenever a task finishes.
+    finished: Condvar,
+}
+
+impl TaskRegistry {
+    /// Registers a task under `id`, or a fresh id when `None`.
+    pub fn register(
+        &self,
+        kind: TaskKind,
+        description: impl Into<String>,
+        id: Option<String>,
+    ) -> Result<TaskHandle<'_>, CommandError> {
+        let id = id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        let description: String = description
+            .into()
+            .chars()
+            .take(MAX_DESCRIPTION_CHARS)
+            .collect();
+        let token = TaskToken::default();
+        let mut tasks = self
+            .tasks
+            .lock()
+            .map_err(|_| "task registry poisoned".to_string())?;
+        if tasks.contains_key(&id) {
+            return Err(CommandError::conflict(format!(
+                "task {id} is already running"
+            )));
+        }
+        tasks.insert(
+            id.clone(),
+            Entry {
+                kind,
+                description,
+                started_at: unix_now(),
+                token: token.clone(),
+            },
+        );
+        Ok(TaskHandle {
+            registry: self,
+            id,
+            token,
+        })
+    }
+
+    /// Flags the task; false when no such task is running.
+    pub fn cancel(&self, id: &str) -> bool {
+        let Ok(tasks) = self.tasks.lock() else {
+            return false;
+        };
+        match tasks.get(id) {
+            Some(entry) => {
+                entry.token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancels everything and waits up to `grace` for it to finish; returns
+    /// how many tasks were still running when the wait ended.
+    pub fn shutdown(&self, grace: Duration) -> usize {
+        let Ok(mut tasks) = self.tasks.lock() else {
+            return 0;
+        };
+        for entry in tasks.values() {
+            entry.token.cancel();
+        }
+        let deadline = Instant::now() + grace;
+        while !tasks.is_empty() {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            tasks = match self.finished.wait_timeout(tasks, left) {
+                Ok((tasks, _)) => tasks,
+                Err(_) => return 0,
+            };
+        }
+        tasks.len()
+    }
+
+    fn finish(&self, id: &str) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.remove(id);
+        }
+        self.finished.notify_all();
+    }
+}
+
+/// Keeps a task listed until dropped.
+pub struct TaskHandle<'a> {
+    registry: &'a TaskRegistry,
+    id: String,
+    token: TaskToken,
+}
+
+impl TaskHandle<'_> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn token(&self) -> &TaskToken {
+        &self.token
+    }
+}
+
+impl Drop for TaskHandle<'_> {
+    fn drop(&mut self) {
+        self.registry.finish(&self.id);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskProgress {
+    pub done: u64,
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    pub id: String,
+    pub kind: TaskKind,
+    pub description: String,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub started_at: u64,
+    /// `None` for tasks that do not report progress.
+    pub progress: Option<TaskProgress>,
+    /// Cancellation was requested and the task has not stopped yet.
+    pub cancelling: bool,
+}
+
+#[tauri::command]
+pub fn list_tasks(state: State<'_, AppState>) -> Result<Vec<TaskInfo>, CommandError> {
+    let tasks = state
+        .tasks
+        .tasks
+        .lock()
+        .map_err(|_| "task registry poisoned".to_string())?;
+    let mut list: Vec<TaskInfo> = tasks
+        .iter()
+        .map(|(id, entry)| {
+            let token = &entry.token.0;
+            let done = token.done.load(Ordering::Relaxed);
+            let total = token.total.load(Ordering::Relaxed);
+            TaskInfo {
+                id: id.clone(),
+                kind: entry.kind,
+                description: entry.description.clone(),
+                started_at: entry.started_at,
+                progress: (done > 0 || total > 0).then_some(TaskProgress {
+                    done,
+                    total: (total > 0).then_some(total),
+                }),
+                cancelling: entry.token.is_cancelled(),
+            }
+        })
+        .collect();
+    list.sort_by(|a, b| {
+        a.started_at
+            .cmp(&b.started_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(list)
+}
+
+#[tauri::command]
+pub fn cancel_task(state: State<'_, AppState>, id: String) -> bool {
+    state.tasks.cancel(&id)
+}
@@ -0,0 +1,250 @@
+//! Opt-in, anonymous feature-usage events. Events are queued on disk in
+//! `telemetry/queue.jsonl` and uploaded in one batch at most once an hour.
+//! Nothing is recorded while telemetry is disabled.
+//!
+//! Property values are deliberately limited to booleans, numbers and static
+//! labels: there is no way to attach a path, token or any user-provided text.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{
+    commit_config, current_data_dir, proxy, read_local_config, unix_now, AppState, LocalConfig,
+};
+
+const UPLOAD_INTERVAL_SECS: u64 = 60 * 60;
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Upper bound on queued events while uploads are failing or unconfigured.
+const MAX_QUEUED: usize = 1000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    pub anonymous_id: Option<String>,
+    /// Where batches are POSTed. Nothing is uploaded while unset.
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PropValue {
+    Bool(bool),
+    Number(f64),
+    Label(&'static str),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryEvent {
+    pub name: &'static str,
+    pub ts: u64,
+    pub props: BTreeMap<&'static str, PropValue>,
+}
+
+impl TelemetryEvent {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            ts: unix_now(),
+            props: BTreeMap::new(),
+        }
+    }
+
+    pub fn with(mut self, key: &'static str, value: PropValue) -> Self {
+        self.props.insert(key, value);
+        self
+    }
+}
+
+pub fn folder_added(follow_symlinks: bool, folder_count: usize) -> TelemetryEvent {
+    TelemetryEvent::new("folder_added")
+        .with("follow_symlinks", PropValue::Bool(follow_symlinks))
+        .with("folder_count", PropValue::Number(folder_count as f64))
+}
+
+pub fn shell_toggled(enabled: bool) -> TelemetryEvent {
+    TelemetryEvent::new("shell_toggled").with("enabled", PropValue::Bool(enabled))
+}
+
+pub fn backend_crashed(phase: &'static str) -> TelemetryEvent {
+    TelemetryEvent::new("backend_crashed").with("phase", PropValue::Label(phase))
+}
+
+fn telemetry_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("telemetry")
+}
+
+fn queue_path(data_dir: &Path) -> PathBuf {
+    telemetry_dir(data_dir).join("queue.jsonl")
+}
+
+fn last_upload_path(data_dir: &Path) -> PathBuf {
+    telemetry_dir(data_dir).join("last_upload")
+}
+
+/// Queues an event if the user opted in; otherwise does nothing. Failures are
+/// swallowed since telemetry must never affect the feature being measured.
+pub fn track_event(data_dir: &Path, event: TelemetryEvent) {
+    let enabled = read_local_config(data_dir).is_ok_and(|config| config.telemetry.enabled);
+    if !enabled {
+        return;
+    }
+    let Ok(line) = serde_json::to_string(&event) else {
+        return;
+    };
+    let _ = fs::create_dir_all(telemetry_dir(data_dir));
+    if let Ok(mut file) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(queue_path(data_dir))
+    {
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+/// Body of an upload. Only the anonymous id and coarse platform info are
+/// added to the events themselves.
+pub fn upload_payload(config: &LocalConfig, events: &[serde_json::Value]) -> serde_json::Value {
+    serde_json::json!({
+        "anonymous_id": config.telemetry.anonymous_id,
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "events": events,
+    })
+}
+
+fn read_queue(data_dir: &Path) -> Vec<serde_json::Value> {
+    let content = fs::read_to_string(queue_path(data_dir)).unwrap_or_default();
+    let events: Vec<serde_json::Value> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = events.len().saturating_sub(MAX_QUEUED);
+    events.into_iter().skip(skip).collect()
+}
+
+fn clear_queue(data_dir: &Path) {
+    let _ = fs::remove_file(queue_path(data_dir));
+}
+
+fn upload_due(data_dir: &Path) -> bool {
+    let last = fs::read_to_string(last_upload_path(data_dir))
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+    unix_now().saturating_sub(last) >= UPLOAD_INTERVAL_SECS
+}
+
+/// Uploads the queue if telemetry is on, an endpoint is set and the last
+/// attempt was over an hour ago. A disabled config drops the queue.
+pub fn flush_if_due(data_dir: &Path) {
+    let Ok(config) = read_local_config(data_dir) else {
+        return;
+    };
+    if !config.telemetry.enabled {
+        clear_queue(data_dir);
+        return;
+    }
+    let Some(endpoint) = config.telemetry.endpoint.as_deref() else {
+        return;
+    };
+    let Ok(url) = url::Url::parse(endpoint) else {
+        return;
+    };
+    if !upload_due(data_dir) {
+        return;
+    }
+    let events = read_queue(data_dir);
+    if events.is_empty() {
+        return;
+    }
+    // Recorded before sending so a failing endpoint is retried hourly, not
+    // on every check.
+    let _ = fs::write(last_upload_path(data_dir), unix_now().to_string());
+    let agent = proxy::outbound_agent(&proxy::resolve(&config.proxy), &url);
+    let sent = agent
+        .post(url.as_str())
+        .set("Content-Type", "application/json")
+        .send_string(&upload_payload(&config, &events).to_string());
+    if sent.is_ok() {
+        clear_queue(data_dir);
+    }
+}
+
+/// Flushes whichever profile is active at each check.
+pub fn start(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(CHECK_INTERVAL);
+        if let Some(data_dir) = current_data_dir(&app) {
+            flush_if_due(&data_dir);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn set_telemetry_enabled(
+    state: State<'_, AppState>,
+    enabled: bool,
+) -> Result<ConfigChange<LocalConfig>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    config.telemetry.enabled = enabled;
+    if enabled {
+        if config.telemetry.anonymous_id.is_none() {
+            config.telemetry.anonymous_id = Some(Uuid::new_v4().to_string());
+        }
+    } else {
+        config.telemetry.anonymous_id = None;
+        clear_queue(&runtime.data_dir);
+    }
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    Ok(ConfigChange::new(config, diff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folders::AllowedFolder;
+
+    #[test]
+    fn payload_never_contains_configured_folder_paths() {
+        let folder = "/home/alice/Projects/secret-client";
+        let mut config = LocalConfig::default();
+        config
+            .allowed_folders
+            .push(AllowedFolder::new(folder.to_string()));
+        config.telemetry = TelemetryConfig {
+            enabled: true,
+            anonymous_id: Some(Uuid::new_v4().to_string()),
+            endpoint: Some("https://telemetry.example.invalid/v1/events".to_string()),
+        };
+
+        let events: Vec<serde_json::Value> = [
+            folder_added(true, config.allowed_folders.len()),
+            folder_added(false, 2),
+            shell_toggled(true),
+            backend_crashed("startup"),
+        ]
+        .iter()
+        .map(|event| serde_json::to_value(event).unwrap())
+        .collect();
+        let payload = upload_payload(&config, &events).to_string();
+
+        assert!(payload.contains("folder_added"));
+        assert!(!payload.contains(folder));
+        assert!(!payload.contains("secret-client"));
+        assert!(!payload.contains("/home/alice"));
+    }
+}
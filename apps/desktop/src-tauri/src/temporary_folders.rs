@@ -0,0 +1,306 @@
+//! Allowed folders granted for a limited time. A temporary folder sits in
+//! `allowed_folders` like any other, so every check treats it the same, and
+//! its expiry is kept in `temporary_folders.json`. That record is written
+//! before the folder is added to the config, so a crash in between cannot
+//! leave a grant without an expiry. A timer revokes lapsed grants (config
+//! rewritten, backend reloaded, `folder-expired` emitted); grants that lapsed
+//! while the app was closed are revoked in `setup` before the backend starts.
+//! Until a lapsed grant is revoked, reload payloads leave it out.
+//!
+//! Adding the same folder with `add_allowed_folder` makes it permanent, and
+//! removing it drops its record.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{
+    audit, backend_reload_config, commit_config, desktop_log, folders, insert_folder,
+    read_local_config, shutdown, unix_now, AppState, FolderAddition, LocalConfig,
+};
+
+const FILE_NAME: &str = "temporary_folders.json";
+const MAX_TTL_MINUTES: u64 = 7 * 24 * 60;
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Grant {
+    /// As stored in `allowed_folders`.
+    path: String,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    granted_at: u64,
+    #[serde(with = "crate::timestamps::unix_secs")]
+    expires_at: u64,
+}
+
+/// How `get_local_config` marks a temporary folder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemporaryGrant {
+    #[serde(with = "crate::timestamps::unix_secs")]
+    pub expires_at: u64,
+    pub remaining_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FolderExpired {
+    path: String,
+    alias: Option<String>,
+}
+
+fn grants_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(FILE_NAME)
+}
+
+fn load(data_dir: &Path) -> Vec<Grant> {
+    let Ok(content) = fs::read_to_string(grants_path(data_dir)) else {
+        return Vec::new();
+    };
+    serde_json::from_str(&content).unwrap_or_default()
+}
+
+fn save(data_dir: &Path, grants: &[Grant]) -> Result<(), String> {
+    let path = grants_path(data_dir);
+    let temp = path.with_extension("tmp");
+    let bytes = serde_json::to_vec_pretty(grants)
+        .map_err(|e| format!("failed serializing temporary folders: {e}"))?;
+    fs::write(&temp, bytes).map_err(|e| format!("failed writing temporary folders: {e}"))?;
+    fs::rename(&temp, &path).map_err(|e| format!("failed replacing temporary folders: {e}"))
+}
+
+fn same_folder(grant: &Grant, path: &str) -> bool {
+    folders::natural_cmp(&grant.path, path).is_eq()
+}
+
+fn ttl_secs(ttl_minutes: u64) -> Result<u64, CommandError> {
+    if ttl_minutes == 0 || ttl_minutes > MAX_TTL_MINUTES {
+        return Err(CommandError::invalid_input(format!(
+            "ttl_minutes must be 1-{MAX_TTL_MINUTES}"
+        )));
+    }
+    Ok(ttl_minutes * 60)
+}
+
+/// Drops the record for `path`, leaving the folder itself allowed.
+pub fn forget(data_dir: &Path, path: &str) -> Result<(), String> {
+    let mut grants = load(data_dir);
+    let before = grants.len();
+    grants.retain(|grant| !same_folder(grant, path));
+    if grants.len() == before {
+        return Ok(());
+    }
+    save(data_dir, &grants)
+}
+
+/// Moves the record for `from` to `to`, for a relocated folder.
+pub fn relocate(data_dir: &Path, from: &str, to: &str) -> Result<(), String> {
+    let mut grants = load(data_dir);
+    let mut moved = false;
+    for grant in grants.iter_mut().filter(|grant| same_folder(grant, from)) {
+        grant.path = to.to_string();
+        moved = true;
+    }
+    if !moved {
+        return Ok(());
+    }
+    save(data_dir, &grants)
+}
+
+/// Paths of grants past their expiry that have not been revoked yet.
+pub fn lapsed(data_dir: &Path) -> Vec<String> {
+    let now = unix_now();
+    load(data_dir)
+        .into_iter()
+        .filter(|grant| grant.expires_at <= now)
+        .map(|grant| grant.path)
+        .collect()
+}
+
+pub fn mark(data_dir: &Path, config: &mut LocalConfig) {
+    let grants = load(data_dir);
+    if grants.is_empty() {
+        return;
+    }
+    let now = unix_now();
+    for folder in &mut config.allowed_folders {
+        folder.temporary = grants
+            .iter()
+            .find(|grant| same_folder(grant, &folder.path))
+            .map(|grant| TemporaryGrant {
+                expires_at: grant.expires_at,
+                remaining_secs: grant.expires_at.saturating_sub(now),
+            });
+    }
+}
+
+/// Removes lapsed grants from the config and the record. Returns the folders
+/// taken out of `allowed_folders` and the config when it changed.
+fn revoke_lapsed(data_dir: &Path) -> Result<(Vec<FolderExpired>, Option<LocalConfig>), String> {
+    let now = unix_now();
+    let (lapsed, kept): (Vec<Grant>, Vec<Grant>) = load(data_dir)
+        .into_iter()
+        .partition(|grant| grant.expires_at <= now);
+    if lapsed.is_empty() {
+        return Ok((Vec::new(), None));
+    }
+    let mut config = read_local_config(data_dir)?;
+    let mut expired = Vec::new();
+    config.allowed_folders.retain(|folder| {
+        let lapsed = lapsed.iter().any(|grant| same_folder(grant, &folder.path));
+        if lapsed {
+            expired.push(FolderExpired {
+                path: folder.path.clone(),
+                alias: folder.alias.clone(),
+            });
+        }
+        !lapsed
+    });
+    let changed = !expired.is_empty();
+    // The config first: a crash before the record is saved revokes again.
+    if changed {
+        commit_config(data_dir, &config)?;
+    }
+    save(data_dir, &kept)?;
+    for folder in &expired {
+        let _ = audit::record(
+            data_dir,
+            "temporary_folder_expired",
+            serde_json::json!({ "path": folder.path }),
+        );
+    }
+    Ok((expired, changed.then_some(config)))
+}
+
+fn emit_expired(app: &AppHandle, expired: &[FolderExpired]) {
+    for folder in expired {
+        let _ = app.emit("folder-expired", folder);
+    }
+}
+
+/// For grants that lapsed while the app was closed; the backend has not
+/// started yet, so it reads the revoked config at spawn.
+pub fn revoke_at_startup(app: &AppHandle, data_dir: &Path) {
+    match revoke_lapsed(data_dir) {
+        Ok((expired, _)) => emit_expired(app, &expired),
+        Err(err) => desktop_log::warn(data_dir, &format!("temporary folders: {err}")),
+    }
+}
+
+fn check(app: &AppHandle) {
+    if shutdown::in_progress() {
+        return;
+    }
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let Ok(runtime) = state.runtime.lock() else {
+        return;
+    };
+    match revoke_lapsed(&runtime.data_dir) {
+        Ok((expired, Some(config))) => {
+            if let Err(err) = backend_reload_config(&runtime, &config) {
+                desktop_log::warn(
+                    &runtime.data_dir,
+                    &format!("reload after revoking temporary folders failed: {err}"),
+                );
+            }
+            drop(runtime);
+            emit_expired(app, &expired);
+        }
+        Ok((_, None)) => {}
+        Err(err) => desktop_log::warn(&runtime.data_dir, &format!("temporary folders: {err}")),
+    }
+}
+
+/// Starts the expiry timer; called from `setup` once the app state exists.
+pub fn start(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(CHECK_INTERVAL);
+        check(&app);
+    });
+}
+
+/// Grants `path` like `add_allowed_folder` until `ttl_minutes` from now.
+/// On a folder that is already temporary this restarts its expiry; one that
+/// is allowed permanently is refused rather than made temporary.
+#[tauri::command]
+pub fn add_temporary_folder(
+    state: State<'_, AppState>,
+    path: String,
+    ttl_minutes: u64,
+    follow_symlinks: Option<bool>,
+    allow_root: Option<bool>,
+) -> Result<ConfigChange<FolderAddition>, CommandError> {
+    let ttl = ttl_secs(ttl_minutes)?;
+    let follow_symlinks = follow_symlinks.unwrap_or(true);
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let (stored, symlink) =
+        folders::resolve_folder_input(&path, follow_symlinks, allow_root.unwrap_or(false))?;
+    let mut grants = load(&runtime.data_dir);
+    let config = read_local_config(&runtime.data_dir)?;
+    let granted = grants.iter().any(|grant| same_folder(grant, &stored));
+    if folders::find_folder(&config, &stored).is_some() && !granted {
+        return Err(CommandError::conflict(format!(
+            "{stored} is already allowed permanently"
+        )));
+    }
+    let now = unix_now();
+    grants.retain(|grant| !same_folder(grant, &stored));
+    grants.push(Grant {
+        path: stored.clone(),
+        granted_at: now,
+        expires_at: now + ttl,
+    });
+    save(&runtime.data_dir, &grants)?;
+    let _ = audit::record(
+        &runtime.data_dir,
+        "temporary_folder_granted",
+        serde_json::json!({ "path": stored, "ttl_minutes": ttl_minutes }),
+    );
+    let mut change = insert_folder(&runtime, stored, symlink, follow_symlinks)?;
+    mark(&runtime.data_dir, &mut change.result.config);
+    Ok(change)
+}
+
+/// Sets a temporary folder to expire `ttl_minutes` from now.
+#[tauri::command]
+pub fn extend_temporary_folder(
+    state: State<'_, AppState>,
+    path: String,
+    ttl_minutes: u64,
+) -> Result<TemporaryGrant, CommandError> {
+    let ttl = ttl_secs(ttl_minutes)?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let config = read_local_config(&runtime.data_dir)?;
+    let stored = folders::find_folder_by_input(&config, &path)
+        .map(|index| config.allowed_folders[index].path.clone())
+        .ok_or_else(|| CommandError::not_found(format!("not an allowed folder: {path}")))?;
+    let mut grants = load(&runtime.data_dir);
+    let now = unix_now();
+    let grant = grants
+        .iter_mut()
+        .find(|grant| same_folder(grant, &stored) && grant.expires_at > now)
+        .ok_or_else(|| CommandError::not_found(format!("{stored} is not a temporary folder")))?;
+    grant.expires_at = now + ttl;
+    let expires_at = grant.expires_at;
+    save(&runtime.data_dir, &grants)?;
+    let _ = audit::record(
+        &runtime.data_dir,
+        "temporary_folder_extended",
+        serde_json::json!({ "path": stored, "ttl_minutes": ttl_minutes }),
+    );
+    Ok(TemporaryGrant {
+        expires_at,
+        remaining_secs: ttl,
+    })
+}
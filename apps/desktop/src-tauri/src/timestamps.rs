@@ -0,0 +1,225 @@
+//! The one place timestamps get their serialized form. Every timestamp that
+//! crosses IPC or lands in a file the app writes is RFC 3339 in UTC with
+//! millisecond precision (`2024-05-01T12:00:00.000Z`), so they sort as
+//! strings and mean the same instant on either side of a DST change.
+//!
+//! Wall-clock values are held as `SystemTime` or unix seconds in memory;
+//! `Instant`s only measure durations and are never serialized. For u64
+//! fields, `#[serde(with = "timestamps::unix_secs")]` writes RFC 3339 and
+//! reads either that or the bare unix seconds earlier versions wrote, so
+//! files from before the switch still load.
+
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// RFC 3339 UTC with millisecond precision.
+pub fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+pub fn now() -> String {
+    rfc3339(SystemTime::now())
+}
+
+pub fn from_unix_secs(secs: u64) -> String {
+    rfc3339(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Days-from-civil, the inverse of the conversion in `rfc3339`.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn number(text: &str, range: std::ops::RangeInclusive<i64>) -> Option<i64> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok().filter(|value| range.contains(value))
+}
+
+/// Parses RFC 3339 with any offset (`Z`, `+02:00`, `-05:00`) and optional
+/// fractional seconds. Times before 1970 are `None`.
+pub fn parse(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.split_once(['T', 't', ' '])?;
+    let mut date_parts = date.splitn(3, '-');
+    let year = number(date_parts.next()?, 0..=9999)?;
+    let month = number(date_parts.next()?, 1..=12)?;
+    let day = number(date_parts.next()?, 1..=31)?;
+
+    let (clock, offset_secs) = match time.strip_suffix(['Z', 'z']) {
+        Some(clock) => (clock, 0),
+        None => {
+            let at = time.rfind(['+', '-'])?;
+            let (clock, offset) = time.split_at(at);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let offset = number(hours, 0..=23)? * 3600 + number(minutes, 0..=59)? * 60;
+            (clock, sign * offset)
+        }
+    };
+    let (clock, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    let mut clock_parts = clock.splitn(3, ':');
+    let hour = number(clock_parts.next()?, 0..=23)?;
+    let minute = number(clock_parts.next()?, 0..=59)?;
+    let second = number(clock_parts.next()?, 0..=60)?;
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        let digits: String = fraction
+            .chars()
+            .chain("000000000".chars())
+            .take(9)
+            .collect();
+        number(&digits, 0..=999_999_999)?
+    };
+
+    let local = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    let utc = u64::try_from(local - offset_secs).ok()?;
+    Some(UNIX_EPOCH + Duration::new(utc, nanos as u32))
+}
+
+pub fn parse_unix_secs(text: &str) -> Option<u64> {
+    let since_epoch = parse(text)?.duration_since(UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_secs())
+}
+
+/// What a timestamp field may hold in files written before and after the
+/// switch to RFC 3339.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Stored {
+    UnixSecs(u64),
+    Text(String),
+}
+
+impl Stored {
+    fn unix_secs<E: serde::de::Error>(self) -> Result<u64, E> {
+        match self {
+            Stored::UnixSecs(secs) => Ok(secs),
+            Stored::Text(text) => parse_unix_secs(&text)
+                .ok_or_else(|| E::custom(format!("invalid timestamp `{text}`"))),
+        }
+    }
+}
+
+/// Serde adapter for unix-seconds fields; see the module docs.
+pub mod unix_secs {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(secs: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&from_unix_secs(*secs))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        Stored::deserialize(deserializer)?.unix_secs()
+    }
+}
+
+/// `unix_secs` for optional fields that are only written, for
+/// `serialize_with`.
+pub fn serialize_unix_secs_opt<S: Serializer>(
+    secs: &Option<u64>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match secs {
+        Some(secs) => serializer.serialize_some(&from_unix_secs(*secs)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Entry {
+        #[serde(with = "unix_secs")]
+        at: u64,
+        #[serde(
+            default,
+            skip_deserializing,
+            serialize_with = "serialize_unix_secs_opt"
+        )]
+        seen: Option<u64>,
+    }
+
+    #[test]
+    fn timestamps_round_trip_and_old_files_still_read() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_714_564_800_123);
+        assert_eq!(rfc3339(time), "2024-05-01T12:00:00.123Z");
+        assert_eq!(parse(&rfc3339(time)), Some(time));
+
+        let entry = Entry {
+            at: 1_714_564_800,
+            seen: None,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"at":"2024-05-01T12:00:00.000Z","seen":null}"#);
+        assert_eq!(serde_json::from_str::<Entry>(&json).unwrap(), entry);
+
+        let old: Entry = serde_json::from_str(r#"{"at":1714564800}"#).unwrap();
+        assert_eq!(old.at, 1_714_564_800);
+        let seen = Entry {
+            seen: Some(1_714_564_801),
+            ..old
+        };
+        let json = serde_json::to_value(&seen).unwrap();
+        assert_eq!(json["seen"], "2024-05-01T12:00:01.000Z");
+        assert!(serde_json::from_str::<Entry>(r#"{"at":"yesterday"}"#).is_err());
+    }
+
+    #[test]
+    fn ordering_holds_across_a_dst_transition() {
+        // US Eastern falls back at 2024-11-03 02:00 EDT: local 01:30 happens
+        // twice, first at -04:00, then an hour later at -05:00.
+        let local = [
+            "2024-11-03T00:59:00-04:00",
+            "2024-11-03T01:30:00-04:00",
+            "2024-11-03T01:10:00-05:00",
+            "2024-11-03T01:30:00-05:00",
+            "2024-11-03T02:00:00-05:00",
+        ];
+        let utc: Vec<String> = local.iter().map(|t| rfc3339(parse(t).unwrap())).collect();
+        assert_eq!(utc[1], "2024-11-03T05:30:00.000Z");
+        assert_eq!(utc[2], "2024-11-03T06:10:00.000Z");
+        let mut sorted = utc.clone();
+        sorted.sort();
+        assert_eq!(sorted, utc);
+
+        // Spring forward: 01:59:59 EST is one second before 03:00:00 EDT.
+        let before = parse("2024-03-10T01:59:59-05:00").unwrap();
+        let after = parse("2024-03-10T03:00:00-04:00").unwrap();
+        assert_eq!(
+            after.duration_since(before).unwrap(),
+            Duration::from_secs(1)
+        );
+        assert!(rfc3339(before) < rfc3339(after));
+    }
+}
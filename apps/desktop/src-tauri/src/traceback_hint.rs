@@ -0,0 +1,276 @@
+//! What the backend's last traceback says went wrong. A failed start is
+//! recorded by what the desktop saw ("health check timed out"), while the
+//! cause is usually in the traceback the backend printed first. This pulls
+//! the final exception line of the most recent traceback in the tail of
+//! `backend.log` and matches it against known failures, so `ApiConfig` can
+//! carry a `failure_hint` such as "missing dependency fastapi".
+//!
+//! Known failures get fixed text plus at most a module name, in keeping
+//! with `backend_error`; their raw message can hold an API key or a path.
+//! Anything else surfaces the raw final line, cut to `MAX_RAW_CHARS`.
+
+use serde::Serialize;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::backend_output::TRACEBACK_HEADER;
+use crate::log_parser::{self, LogEntry};
+use crate::timestamps;
+
+/// Enough for a few tracebacks; only the newest is used.
+const TAIL_BYTES: u64 = 64 * 1024;
+const MAX_RAW_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HintKind {
+    MissingDependency,
+    PortInUse,
+    InvalidApiKey,
+    PermissionDenied,
+    OutOfMemory,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FailureHint {
+    pub kind: HintKind,
+    pub summary: String,
+    /// The exception's type as Python printed it, e.g. `openai.AuthenticationError`.
+    pub exception: String,
+}
+
+/// The final `Type: message` line of a traceback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinalLine {
+    pub exception: String,
+    pub message: String,
+    pub raw: String,
+}
+
+pub fn parse_final_line(line: &str) -> FinalLine {
+    let raw = line.trim().to_string();
+    let (head, message) = raw.split_once(':').unwrap_or((&raw, ""));
+    let is_type = !head.is_empty()
+        && head
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if !is_type {
+        return FinalLine {
+            exception: String::new(),
+            message: raw.clone(),
+            raw,
+        };
+    }
+    FinalLine {
+        exception: head.to_string(),
+        message: message.trim().to_string(),
+        raw: raw.clone(),
+    }
+}
+
+/// The final line of the newest traceback logged at or after `since`.
+/// Lines without a timestamp (debug console mode) always count.
+pub fn last_traceback(entries: &[LogEntry], since: Option<SystemTime>) -> Option<FinalLine> {
+    let since = since.map(timestamps::rfc3339);
+    entries
+        .iter()
+        .rev()
+        .filter(|entry| match (&since, &entry.timestamp) {
+            (Some(since), Some(timestamp)) => timestamp >= since,
+            _ => true,
+        })
+        .find(|entry| {
+            entry
+                .lines
+                .first()
+                .is_some_and(|line| line.starts_with(TRACEBACK_HEADER))
+                && entry.message != TRACEBACK_HEADER
+        })
+        .map(|entry| parse_final_line(&entry.message))
+}
+
+/// `'fastapi.routing'` from `No module named 'fastapi.routing'` as `fastapi`.
+fn missing_module(message: &str) -> Option<String> {
+    let rest = message.split("No module named ").nth(1)?;
+    let name = rest.trim().trim_matches(|c| c == '\'' || c == '"');
+    let top = name.split('.').next()?;
+    let valid = !top.is_empty()
+        && top.len() <= 64
+        && top.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| top.to_string())
+}
+
+fn type_name(exception: &str) -> &str {
+    exception.rsplit('.').next().unwrap_or(exception)
+}
+
+pub fn classify(line: &FinalLine) -> FailureHint {
+    let name = type_name(&line.exception);
+    let message = line.message.to_ascii_lowercase();
+    let hint = |kind, summary: String| FailureHint {
+        kind,
+        summary,
+        exception: line.exception.clone(),
+    };
+    if matches!(name, "ModuleNotFoundError" | "ImportError") {
+        if let Some(module) = missing_module(&line.message) {
+            return hint(
+                HintKind::MissingDependency,
+                format!("The backend is missing the Python package `{module}`."),
+            );
+        }
+    }
+    // Errno 98 on Linux, 48 on macOS, WinError 10048 on Windows.
+    let port_in_use = ["[errno 98]", "[errno 48]", "[winerror 10048]"]
+        .iter()
+        .any(|code| message.contains(code))
+        || message.contains("address already in use");
+    if port_in_use {
+        return hint(
+            HintKind::PortInUse,
+            "The backend's port is already in use by another program.".to_string(),
+        );
+    }
+    let auth_error = name == "AuthenticationError"
+        || message.contains("incorrect api key")
+        || message.contains("invalid api key")
+        || message.contains("invalid x-api-key");
+    if auth_error {
+        return hint(
+            HintKind::InvalidApiKey,
+            "The model provider rejected the API key.".to_string(),
+        );
+    }
+    if name == "PermissionError" {
+        return hint(
+            HintKind::PermissionDenied,
+            "The backend was denied access to a file or folder.".to_string(),
+        );
+    }
+    if name == "MemoryError" {
+        return hint(
+            HintKind::OutOfMemory,
+            "The backend ran out of memory.".to_string(),
+        );
+    }
+    hint(
+        HintKind::Unknown,
+        line.raw.chars().take(MAX_RAW_CHARS).collect(),
+    )
+}
+
+/// The hint for the newest traceback in `log_path` since `since`, if any.
+pub fn from_log(log_path: &Path, since: Option<SystemTime>) -> Option<FailureHint> {
+    let tail = log_parser::read_tail(log_path, TAIL_BYTES).ok()?;
+    let entries = log_parser::parse_backend_log(&tail);
+    last_traceback(&entries, since).map(|line| classify(&line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    /// Prefixes each line as `backend_output` writes it to backend.log.
+    fn logged(at: &str, text: &str) -> String {
+        text.lines()
+            .map(|line| format!("{at} [stderr] {line}\n"))
+            .collect()
+    }
+
+    fn hint_for(log: &str) -> Option<FailureHint> {
+        last_traceback(&log_parser::parse_backend_log(log), None).map(|line| classify(&line))
+    }
+
+    const MISSING_MODULE: &str = r#"Traceback (most recent call last):
+  File "/opt/liteclaw/backend/main.py", line 12, in <module>
+    from fastapi import FastAPI
+ModuleNotFoundError: No module named 'fastapi'"#;
+
+    const PORT_IN_USE: &str = r#"Traceback (most recent call last):
+  File "/usr/lib/python3.11/asyncio/base_events.py", line 1536, in create_server
+    sock.bind(sa)
+OSError: [Errno 98] Address already in use"#;
+
+    const WINDOWS_PORT_IN_USE: &str = r#"Traceback (most recent call last):
+  File "C:\Program Files\LiteClaw\backend\main.py", line 301, in <module>
+    uvicorn.run(app, host=host, port=port)
+  File "C:\Python311\Lib\asyncio\base_events.py", line 1536, in create_server
+    sock.bind(sa)
+OSError: [WinError 10048] Only one usage of each socket address (protocol/network address/port) is normally permitted"#;
+
+    const BAD_API_KEY: &str = r#"Traceback (most recent call last):
+  File "/opt/liteclaw/backend/agent.py", line 88, in run
+    response = client.chat.completions.create(**request)
+  File "/opt/liteclaw/venv/lib/python3.11/site-packages/openai/_base_client.py", line 1020, in _request
+    raise self._make_status_error_from_response(err.response) from None
+openai.AuthenticationError: Error code: 401 - {'error': {'message': 'Incorrect API key provided: sk-proj-AbCd****wxyz.', 'type': 'invalid_request_error', 'code': 'invalid_api_key'}}"#;
+
+    const CHAINED: &str = r#"Traceback (most recent call last):
+  File "/opt/liteclaw/backend/config.py", line 40, in load
+    return settings["model"]
+KeyError: 'model'
+
+During handling of the above exception, another exception occurred:
+
+Traceback (most recent call last):
+  File "/opt/liteclaw/backend/main.py", line 60, in lifespan
+    config = load()
+  File "/opt/liteclaw/backend/config.py", line 42, in load
+    raise RuntimeError("config has no model configured")
+RuntimeError: config has no model configured"#;
+
+    #[test]
+    fn missing_module_names_the_package() {
+        let hint = hint_for(&logged("2024-05-01T12:00:00.000Z", MISSING_MODULE)).unwrap();
+        assert_eq!(hint.kind, HintKind::MissingDependency);
+        assert_eq!(hint.exception, "ModuleNotFoundError");
+        assert!(hint.summary.contains("`fastapi`"));
+    }
+
+    #[test]
+    fn bind_errors_are_port_in_use_on_each_platform() {
+        for traceback in [PORT_IN_USE, WINDOWS_PORT_IN_USE] {
+            let hint = hint_for(&logged("2024-05-01T12:00:00.000Z", traceback)).unwrap();
+            assert_eq!(hint.kind, HintKind::PortInUse);
+            assert_eq!(hint.exception, "OSError");
+        }
+    }
+
+    #[test]
+    fn rejected_api_key_keeps_the_key_out_of_the_summary() {
+        let hint = hint_for(&logged("2024-05-01T12:00:00.000Z", BAD_API_KEY)).unwrap();
+        assert_eq!(hint.kind, HintKind::InvalidApiKey);
+        assert_eq!(hint.exception, "openai.AuthenticationError");
+        assert!(!hint.summary.contains("sk-proj"));
+    }
+
+    #[test]
+    fn chained_tracebacks_report_the_last_exception() {
+        let hint = hint_for(&logged("2024-05-01T12:00:00.000Z", CHAINED)).unwrap();
+        assert_eq!(hint.kind, HintKind::Unknown);
+        assert_eq!(hint.exception, "RuntimeError");
+        assert_eq!(hint.summary, "RuntimeError: config has no model configured");
+    }
+
+    #[test]
+    fn tracebacks_from_before_the_spawn_are_ignored() {
+        let log = logged("2024-05-01T11:00:00.000Z", MISSING_MODULE)
+            + &logged(
+                "2024-05-01T12:00:00.500Z",
+                "INFO:     Started server process [4242]",
+            );
+        let entries = log_parser::parse_backend_log(&log);
+        let spawned = UNIX_EPOCH + Duration::from_secs(1_714_564_800);
+        assert_eq!(timestamps::rfc3339(spawned), "2024-05-01T12:00:00.000Z");
+        assert!(last_traceback(&entries, Some(spawned)).is_none());
+        assert!(last_traceback(&entries, None).is_some());
+    }
+
+    #[test]
+    fn raw_backend_output_is_read_too() {
+        let hint = hint_for(PORT_IN_USE).unwrap();
+        assert_eq!(hint.kind, HintKind::PortInUse);
+    }
+}
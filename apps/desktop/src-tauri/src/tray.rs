@@ -0,0 +1,91 @@
+//! System tray icon. The tooltip and the disabled first menu entry name the
+//! active profile; `profiles::show_identity` keeps them current.
+
+use std::path::Path;
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Manager};
+
+use crate::desktop_log;
+
+const TRAY_ID: &str = "main";
+const MENU_HEADER: &str = "profile-header";
+const MENU_SHOW: &str = "show-window";
+const MENU_QUIT: &str = "quit";
+
+fn build_menu(app: &AppHandle, label: &str) -> tauri::Result<Menu> {
+    let header = MenuItem::with_id(app, MENU_HEADER, label, false, None::<&str>)?;
+    let separator = PredefinedMenuItem::separator(app)?;
+    let show = MenuItem::with_id(app, MENU_SHOW, "Show LiteClaw", true, None::<&str>)?;
+    let quit = MenuItem::with_id(app, MENU_QUIT, "Quit", true, None::<&str>)?;
+    Menu::with_items(app, &[&header, &separator, &show, &quit])
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        MENU_SHOW => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+        }
+        // Exits through `shutdown`, like closing the last window.
+        MENU_QUIT => app.exit(0),
+        _ => {}
+    }
+}
+
+/// Creates the tray icon labelled `label`. The app works without a tray, so
+/// failure is logged and returned for `integrations`, never fatal.
+pub fn create(app: &AppHandle, data_dir: &Path, label: &str) -> Result<(), String> {
+    let menu = match build_menu(app, label) {
+        Ok(menu) => menu,
+        Err(err) => {
+            let message = format!("failed building tray menu: {err}");
+            desktop_log::warn(data_dir, &message);
+            return Err(message);
+        }
+    };
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(label)
+        .menu(&menu)
+        .on_menu_event(on_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    if let Err(err) = builder.build(app) {
+        let message = format!("failed creating tray icon: {err}");
+        desktop_log::warn(data_dir, &message);
+        return Err(message);
+    }
+    Ok(())
+}
+
+/// Relabels the tooltip and menu header.
+pub fn set_label(app: &AppHandle, data_dir: &Path, label: &str) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let _ = tray.set_tooltip(Some(label));
+    match build_menu(app, label) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(err) => desktop_log::warn(data_dir, &format!("failed rebuilding tray menu: {err}")),
+    }
+}
+
+/// Swaps the icon, `None` restoring the default. Nothing happens without a
+/// tray. macOS and Windows flag unread answers on the dock and taskbar
+/// instead, see `unread`.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn set_icon(app: &AppHandle, icon: Option<Image<'static>>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let icon = icon.or_else(|| app.default_window_icon().cloned());
+    let _ = tray.set_icon(icon);
+}
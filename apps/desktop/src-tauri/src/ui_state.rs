@@ -0,0 +1,300 @@
+//! Desktop-owned UI metadata about conversations (pins, labels, working
+//! folders) in
+//! `ui_state.json` in the data dir, so it survives a backend reinstall. The
+//! backend owns the conversations themselves; ids here may outlive them.
+//! Entries are kept in least-recently-touched order and the oldest are
+//! dropped past `MAX_ENTRIES`. An unreadable file is moved aside as
+//! `ui_state.json.<unix>.corrupt` (the janitor keeps the newest few) and the
+//! store starts over empty.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::error::{CommandError, ErrorCode};
+use crate::{audit, desktop_log, folders, paths, read_local_config, unix_now, AppState};
+
+const FILE_NAME: &str = "ui_state.json";
+const MAX_ENTRIES: usize = 300;
+const MAX_ID_CHARS: usize = 128;
+const MAX_LABEL_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConversationMeta {
+    pub conversation_id: String,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub label: Option<String>,
+    /// Allowed folder the conversation's file operations are scoped to.
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// When the entry was last changed; the eviction order.
+    #[serde(default, with = "crate::timestamps::unix_secs")]
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UiState {
+    /// Oldest first.
+    #[serde(default)]
+    conversations: Vec<ConversationMeta>,
+}
+
+fn state_path(data_dir: &Path) -> PathBuf {
+    paths::for_io(&data_dir.join(FILE_NAME))
+}
+
+fn quarantine(data_dir: &Path, path: &Path, reason: &str) {
+    let moved = path.with_file_name(format!("{FILE_NAME}.{}.corrupt", unix_now()));
+    let message = format!("{FILE_NAME} is unreadable ({reason}); starting over");
+    desktop_log::warn(data_dir, &message);
+    if fs::rename(path, &moved).is_ok() {
+        let _ = audit::record(
+            data_dir,
+            "ui_state_reset",
+            serde_json::json!({ "backup_path": moved.to_string_lossy(), "reason": reason }),
+        );
+    }
+}
+
+fn load(data_dir: &Path) -> UiState {
+    let path = state_path(data_dir);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return UiState::default(),
+    };
+    match serde_json::from_str(&content) {
+        Ok(state) => state,
+        Err(err) => {
+            quarantine(data_dir, &path, &err.to_string());
+            UiState::default()
+        }
+    }
+}
+
+/// Writes via a temp file and rename, as `config.json` is.
+fn save(data_dir: &Path, state: &UiState) -> Result<(), CommandError> {
+    let path = state_path(data_dir);
+    let temp = paths::temp_sibling(&path);
+    let bytes = serde_json::to_vec_pretty(state)
+        .map_err(|e| format!("failed serializing {FILE_NAME}: {e}"))?;
+    fs::write(&temp, bytes).map_err(|e| format!("failed writing temp {FILE_NAME}: {e}"))?;
+    fs::rename(&temp, &path).map_err(|e| format!("failed replacing {FILE_NAME}: {e}").into())
+}
+
+fn validate_id(id: &str) -> Result<String, CommandError> {
+    let id = id.trim();
+    if id.is_empty() || id.chars().count() > MAX_ID_CHARS || id.chars().any(char::is_control) {
+        return Err(CommandError::invalid_input(format!(
+            "conversation id must be 1-{MAX_ID_CHARS} printable characters"
+        )));
+    }
+    Ok(id.to_string())
+}
+
+impl UiState {
+    /// Applies `change` to the entry for `id`, creating it if needed, and
+    /// moves it to the recent end. Entries left with no metadata are dropped.
+    fn update(&mut self, id: &str, change: impl FnOnce(&mut ConversationMeta)) -> ConversationMeta {
+        let mut entry = match self
+            .conversations
+            .iter()
+            .position(|meta| meta.conversation_id == id)
+        {
+            Some(index) => self.conversations.remove(index),
+            None => ConversationMeta {
+                conversation_id: id.to_string(),
+                pinned: false,
+                label: None,
+                folder: None,
+                updated_at: 0,
+            },
+        };
+        change(&mut entry);
+        entry.updated_at = unix_now();
+        if entry.pinned || entry.label.is_some() || entry.folder.is_some() {
+            self.conversations.push(entry.clone());
+        }
+        let excess = self.conversations.len().saturating_sub(MAX_ENTRIES);
+        self.conversations.drain(..excess);
+        entry
+    }
+}
+
+/// Read-modify-write under the runtime lock, which serializes these commands.
+/// Nothing is saved when `change` fails.
+fn modify<T>(
+    state: &State<'_, AppState>,
+    change: impl FnOnce(&Path, &mut UiState) -> Result<T, CommandError>,
+) -> Result<T, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut ui_state = load(&runtime.data_dir);
+    let result = change(&runtime.data_dir, &mut ui_state)?;
+    save(&runtime.data_dir, &ui_state)?;
+    Ok(result)
+}
+
+/// Every entry, for marking search results.
+pub fn lookup(data_dir: &Path) -> Vec<ConversationMeta> {
+    load(data_dir).conversations
+}
+
+/// Ids of the conversations bound to `folder`, for reporting what removing
+/// it unbinds. The bindings stay, so undoing the removal restores them.
+pub fn bound_to(data_dir: &Path, folder: &str) -> Vec<String> {
+    load(data_dir)
+        .conversations
+        .into_iter()
+        .filter(|meta| meta.folder.as_deref() == Some(folder))
+        .map(|meta| meta.conversation_id)
+        .collect()
+}
+
+/// Moves the conversations bound to `from` over to `to`, for a relocated
+/// folder.
+pub fn rebind(data_dir: &Path, from: &str, to: &str) -> Result<(), CommandError> {
+    let mut ui_state = load(data_dir);
+    let mut moved = false;
+    for meta in &mut ui_state.conversations {
+        if meta.folder.as_deref() == Some(from) {
+            meta.folder = Some(to.to_string());
+            moved = true;
+        }
+    }
+    if !moved {
+        return Ok(());
+    }
+    save(data_dir, &ui_state)
+}
+
+#[tauri::command]
+pub fn pin_conversation(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<ConversationMeta, CommandError> {
+    let id = validate_id(&id)?;
+    modify(&state, |_, ui_state| {
+        Ok(ui_state.update(&id, |meta| meta.pinned = true))
+    })
+}
+
+/// False when the conversation was not pinned.
+#[tauri::command]
+pub fn unpin_conversation(state: State<'_, AppState>, id: String) -> Result<bool, CommandError> {
+    let id = validate_id(&id)?;
+    modify(&state, |_, ui_state| {
+        let was_pinned = ui_state
+            .conversations
+            .iter()
+            .any(|meta| meta.conversation_id == id && meta.pinned);
+        if was_pinned {
+            ui_state.update(&id, |meta| meta.pinned = false);
+        }
+        Ok(was_pinned)
+    })
+}
+
+/// Most recently changed first.
+#[tauri::command]
+pub fn list_pinned_conversations(
+    state: State<'_, AppState>,
+) -> Result<Vec<ConversationMeta>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    Ok(load(&runtime.data_dir)
+        .conversations
+        .into_iter()
+        .rev()
+        .filter(|meta| meta.pinned)
+        .collect())
+}
+
+/// `None` or a blank label clears it.
+#[tauri::command]
+pub fn set_conversation_label(
+    state: State<'_, AppState>,
+    id: String,
+    label: Option<String>,
+) -> Result<ConversationMeta, CommandError> {
+    let id = validate_id(&id)?;
+    let label = label
+        .map(|label| label.trim().to_string())
+        .filter(|label| !label.is_empty());
+    if let Some(label) = &label {
+        if label.chars().count() > MAX_LABEL_CHARS || label.chars().any(char::is_control) {
+            return Err(CommandError::invalid_input(format!(
+                "label must be at most {MAX_LABEL_CHARS} printable characters"
+            )));
+        }
+    }
+    modify(&state, |_, ui_state| {
+        Ok(ui_state.update(&id, |meta| meta.label = label))
+    })
+}
+
+/// Binds the conversation to an allowed folder, stored as the config has
+/// it; `None` or a blank path unbinds it.
+#[tauri::command]
+pub fn set_conversation_folder(
+    state: State<'_, AppState>,
+    conversation_id: String,
+    path: Option<String>,
+) -> Result<ConversationMeta, CommandError> {
+    let id = validate_id(&conversation_id)?;
+    let path = path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty());
+    modify(&state, |data_dir, ui_state| {
+        let folder = match path {
+            Some(path) => {
+                let config = read_local_config(data_dir)?;
+                let Some(index) = folders::find_folder_by_input(&config, &path) else {
+                    return Err(CommandError::not_found(format!(
+                        "not an allowed folder: {path}"
+                    )));
+                };
+                Some(config.allowed_folders[index].path.clone())
+            }
+            None => None,
+        };
+        Ok(ui_state.update(&id, |meta| meta.folder = folder))
+    })
+}
+
+/// The conversation's folder, `None` when it has none. Fails with
+/// `folder_missing` when the folder has been removed from the allowed list
+/// since it was bound.
+#[tauri::command]
+pub fn get_conversation_folder(
+    state: State<'_, AppState>,
+    conversation_id: String,
+) -> Result<Option<String>, CommandError> {
+    let id = validate_id(&conversation_id)?;
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let Some(folder) = load(&runtime.data_dir)
+        .conversations
+        .into_iter()
+        .find(|meta| meta.conversation_id == id)
+        .and_then(|meta| meta.folder)
+    else {
+        return Ok(None);
+    };
+    let config = read_local_config(&runtime.data_dir)?;
+    if folders::find_folder(&config, &folder).is_none() {
+        return Err(CommandError::new(
+            ErrorCode::FolderMissing,
+            format!("{folder} is no longer an allowed folder"),
+        ));
+    }
+    Ok(Some(folder))
+}
@@ -0,0 +1,113 @@
+//! Unread assistant answers, flagged outside the window: the dock badge on
+//! macOS, a taskbar overlay dot on Windows and a dotted tray icon elsewhere.
+//! The count lives here rather than in the webview so it survives a reload
+//! (`get_app_info` reports it), and focusing the main window clears it from
+//! the run loop instead of relying on the frontend to call `clear_unread`.
+//! Every change is emitted as `unread-changed` for the in-app badge.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+#[cfg(not(target_os = "macos"))]
+use tauri::image::Image;
+use tauri::{AppHandle, Emitter, Manager};
+
+const MAIN_WINDOW: &str = "main";
+const MAX_COUNT: u32 = 999;
+/// Red dot, RGBA.
+#[cfg(not(target_os = "macos"))]
+const DOT_COLOR: [u8; 4] = [0xe5, 0x48, 0x4d, 0xff];
+
+static UNREAD: AtomicU32 = AtomicU32::new(0);
+
+pub fn count() -> u32 {
+    UNREAD.load(Ordering::SeqCst)
+}
+
+/// Paints a dot of `radius` centred at (`cx`, `cy`) into `rgba`.
+#[cfg(not(target_os = "macos"))]
+fn paint_dot(rgba: &mut [u8], width: u32, cx: f32, cy: f32, radius: f32) {
+    for (index, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+        let x = (index as u32 % width) as f32 + 0.5;
+        let y = (index as u32 / width) as f32 + 0.5;
+        if (x - cx).powi(2) + (y - cy).powi(2) <= radius.powi(2) {
+            pixel.copy_from_slice(&DOT_COLOR);
+        }
+    }
+}
+
+/// A bare dot, for the taskbar overlay.
+#[cfg(target_os = "windows")]
+fn dot_icon(size: u32) -> Image<'static> {
+    let mut rgba = vec![0; (size * size * 4) as usize];
+    let half = size as f32 / 2.0;
+    paint_dot(&mut rgba, size, half, half, half);
+    Image::new_owned(rgba, size, size)
+}
+
+/// `icon` with a dot in its top-right corner, for the tray.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn with_dot(icon: &Image<'_>) -> Image<'static> {
+    let (width, height) = (icon.width(), icon.height());
+    let mut rgba = icon.rgba().to_vec();
+    let radius = width.min(height) as f32 / 4.0;
+    paint_dot(&mut rgba, width, width as f32 - radius, radius, radius);
+    Image::new_owned(rgba, width, height)
+}
+
+#[cfg(target_os = "macos")]
+fn show(app: &AppHandle, count: u32) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.set_badge_count((count > 0).then_some(i64::from(count)));
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn show(app: &AppHandle, count: u32) {
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.set_overlay_icon((count > 0).then(|| dot_icon(16)));
+    }
+}
+
+/// Without a tray `tray::set_icon` does nothing; the in-app badge remains.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn show(app: &AppHandle, count: u32) {
+    let icon = match app.default_window_icon() {
+        Some(icon) if count > 0 => Some(with_dot(icon)),
+        _ => None,
+    };
+    crate::tray::set_icon(app, icon);
+}
+
+fn main_window_focused(app: &AppHandle) -> bool {
+    app.get_webview_window(MAIN_WINDOW).is_some_and(|window| {
+        window.is_focused().unwrap_or(false) && window.is_visible().unwrap_or(false)
+    })
+}
+
+fn update(app: &AppHandle, count: u32) -> u32 {
+    let previous = UNREAD.swap(count, Ordering::SeqCst);
+    if previous != count {
+        show(app, count);
+        let _ = app.emit("unread-changed", count);
+    }
+    count
+}
+
+/// For the run loop's focus event on the main window.
+pub fn clear(app: &AppHandle) {
+    update(app, 0);
+}
+
+/// Answers arriving while the main window has focus are already being read,
+/// so they leave the count at zero.
+#[tauri::command]
+pub fn set_unread_count(app: AppHandle, count: u32) -> u32 {
+    if main_window_focused(&app) {
+        return update(&app, 0);
+    }
+    update(&app, count.min(MAX_COUNT))
+}
+
+#[tauri::command]
+pub fn clear_unread(app: AppHandle) -> u32 {
+    update(&app, 0)
+}
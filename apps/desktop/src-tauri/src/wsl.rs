@@ -0,0 +1,350 @@
+//! Running the backend inside WSL on Windows (`backend_host: "wsl"`). The
+//! backend is started as `wsl.exe [-d <distro>] -- python3 <script>`, with
+//! Windows paths translated to their WSL mount (`C:\x` → `/mnt/c/x`, or the
+//! distro's own automount root) and `\\wsl$\<distro>\...` mapped back to the
+//! Linux path. WSL2 forwards localhost, so the port needs nothing special.
+//! Variables only reach the Linux side when listed in `WSLENV`.
+
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use tauri::State;
+
+use crate::config_diff::ConfigChange;
+use crate::error::CommandError;
+use crate::{commit_config, read_local_config, AppState, LocalConfig};
+
+pub const DEFAULT_AUTOMOUNT_ROOT: &str = "/mnt/";
+const WSL_EXE: &str = "wsl.exe";
+const WSL_PYTHON: &str = "python3";
+/// Set for `wsl.exe` itself; forwarding them would clobber the Linux values.
+const WINDOWS_ONLY_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "USERNAME",
+    "LOGNAME",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "SYSTEMROOT",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+    "WSLENV",
+];
+/// Forwarded with `/p` so WSL translates them as paths.
+const PATH_VARS: &[&str] = &[
+    "LITECLAW_DATA_DIR",
+    "LITECLAW_CONFIG_DIR",
+    "LITECLAW_CACHE_DIR",
+    "LITECLAW_LAUNCH_CWD",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendHost {
+    #[default]
+    Native,
+    Wsl,
+}
+
+/// Where a WSL backend runs, resolved at spawn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WslTarget {
+    /// `None` uses the default distro.
+    pub distro: Option<String>,
+    /// Where Windows drives are mounted, with a trailing slash.
+    pub automount_root: String,
+}
+
+/// Translates a Windows path for use inside WSL. Paths that are already
+/// POSIX pass through; network shares and relative paths have no WSL
+/// equivalent and give `None`.
+pub fn to_wsl_path(path: &str, automount_root: &str) -> Option<String> {
+    if path.starts_with('/') {
+        return Some(path.to_string());
+    }
+    let normalized = path.replace('\\', "/");
+    let normalized = normalized
+        .strip_prefix("//?/")
+        .or_else(|| normalized.strip_prefix("//./"))
+        .unwrap_or(&normalized);
+    for host in ["//wsl$/", "//wsl.localhost/"] {
+        if let Some(rest) = normalized.strip_prefix(host) {
+            // `<distro>/home/...`; the distro name is dropped.
+            let inside = rest.split_once('/').map_or("", |(_, inside)| inside);
+            return Some(format!("/{inside}"));
+        }
+    }
+    let bytes = normalized.as_bytes();
+    if bytes.len() < 2 || !bytes[0].is_ascii_alphabetic() || bytes[1] != b':' {
+        return None;
+    }
+    let drive = (bytes[0] as char).to_ascii_lowercase();
+    let rest = normalized[2..].trim_start_matches('/');
+    let root = automount_root.trim_end_matches('/');
+    Some(format!("{root}/{drive}/{rest}"))
+}
+
+fn wsl_command(distro: Option<&str>) -> Command {
+    let mut command = Command::new(WSL_EXE);
+    if let Some(distro) = distro {
+        command.args(["-d", distro]);
+    }
+    command.arg("--");
+    command
+}
+
+/// Asks the distro where `C:` is mounted, which `/etc/wsl.conf` can change.
+fn detect_automount_root(distro: Option<&str>) -> String {
+    let output = wsl_command(distro)
+        .args(["wslpath", "-u", "C:\\"])
+        .stdin(Stdio::null())
+        .output();
+    output
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            let mounted = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            mounted
+                .trim_end_matches('/')
+                .strip_suffix("/c")
+                .map(|root| format!("{root}/"))
+        })
+        .unwrap_or_else(|| DEFAULT_AUTOMOUNT_ROOT.to_string())
+}
+
+/// Whether `wsl.exe` can be run at all.
+pub fn is_available() -> bool {
+    cfg!(windows)
+        && Command::new(WSL_EXE)
+            .arg("--status")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+}
+
+pub fn target(config: &LocalConfig) -> Result<WslTarget, String> {
+    if !cfg!(windows) {
+        return Err("the WSL backend host is only available on Windows".to_string());
+    }
+    let distro = config.wsl_distro.clone();
+    Ok(WslTarget {
+        automount_root: detect_automount_root(distro.as_deref()),
+        distro,
+    })
+}
+
+/// `wsl.exe ... -- python3`; the caller adds the translated script path.
+pub fn python_command(target: &WslTarget) -> Command {
+    let mut command = wsl_command(target.distro.as_deref());
+    command.arg(WSL_PYTHON);
+    command
+}
+
+/// Lists every variable set on `command` (other than the Windows-only ones)
+/// in `WSLENV`. Call after all variables are set.
+pub fn forward_env(command: &mut Command) {
+    let names: Vec<String> = command
+        .get_envs()
+        .filter(|(_, value)| value.is_some())
+        .filter_map(|(key, _)| key.to_str().map(str::to_string))
+        .filter(|key| !WINDOWS_ONLY_VARS.contains(&key.to_ascii_uppercase().as_str()))
+        .map(|key| {
+            if PATH_VARS.contains(&key.as_str()) {
+                format!("{key}/p")
+            } else {
+                key
+            }
+        })
+        .collect();
+    command.env("WSLENV", names.join(":"));
+}
+
+/// A folder path as the backend will see it. Untranslatable paths (network
+/// shares) are passed unchanged; the backend reports them as unreachable.
+pub fn backend_path(path: &str, wsl: Option<&WslTarget>) -> String {
+    match wsl {
+        Some(target) => {
+            to_wsl_path(path, &target.automount_root).unwrap_or_else(|| path.to_string())
+        }
+        None => path.to_string(),
+    }
+}
+
+pub fn validate_distro(distro: &str) -> Result<(), CommandError> {
+    let valid = !distro.is_empty()
+        && distro
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'-' | b'_'));
+    if !valid {
+        return Err(CommandError::invalid_input(format!(
+            "invalid WSL distro name `{distro}`"
+        )));
+    }
+    Ok(())
+}
+
+pub fn validate_backend_host(host: BackendHost) -> Result<(), CommandError> {
+    if host == BackendHost::Wsl && !cfg!(windows) {
+        return Err(CommandError::invalid_input(
+            "the WSL backend host is only available on Windows",
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendHostUpdate {
+    pub config: LocalConfig,
+    /// The running backend was started with the previous host settings.
+    pub restart_pending: bool,
+}
+
+fn update_host(
+    state: State<'_, AppState>,
+    change: impl FnOnce(&mut LocalConfig),
+) -> Result<ConfigChange<BackendHostUpdate>, CommandError> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock poisoned".to_string())?;
+    let mut config = read_local_config(&runtime.data_dir)?;
+    change(&mut config);
+    let diff = commit_config(&runtime.data_dir, &config)?;
+    let running_distro = runtime.backend_wsl.as_ref().map(|target| &target.distro);
+    let wanted_distro = (config.backend_host == BackendHost::Wsl).then_some(&config.wsl_distro);
+    Ok(ConfigChange::new(
+        BackendHostUpdate {
+            restart_pending: runtime.backend_child.is_some() && running_distro != wanted_distro,
+            config,
+        },
+        diff,
+    ))
+}
+
+#[tauri::command]
+pub fn set_backend_host(
+    state: State<'_, AppState>,
+    host: BackendHost,
+) -> Result<ConfigChange<BackendHostUpdate>, CommandError> {
+    validate_backend_host(host)?;
+    update_host(state, |config| config.backend_host = host)
+}
+
+/// `None` or an empty name uses the default distro.
+#[tauri::command]
+pub fn set_wsl_distro(
+    state: State<'_, AppState>,
+    distro: Option<String>,
+) -> Result<ConfigChange<BackendHostUpdate>, CommandError> {
+    let distro = distro
+        .map(|distro| distro.trim().to_string())
+        .filter(|distro| !distro.is_empty());
+    if let Some(distro) = &distro {
+        validate_distro(distro)?;
+    }
+    update_host(state, |config| config.wsl_distro = distro)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(path: &str) -> Option<String> {
+        to_wsl_path(path, DEFAULT_AUTOMOUNT_ROOT)
+    }
+
+    #[test]
+    fn drive_paths_map_under_the_automount_root() {
+        assert_eq!(translate(r"C:\").as_deref(), Some("/mnt/c/"));
+        assert_eq!(
+            translate(r"C:\Users\Ada\My Projects\notes").as_deref(),
+            Some("/mnt/c/Users/Ada/My Projects/notes")
+        );
+        assert_eq!(
+            translate("D:/work/repo").as_deref(),
+            Some("/mnt/d/work/repo")
+        );
+        assert_eq!(
+            to_wsl_path(r"E:\data", "/").as_deref(),
+            Some("/e/data"),
+            "automount root `/` from wsl.conf"
+        );
+    }
+
+    #[test]
+    fn verbatim_prefix_is_stripped() {
+        assert_eq!(
+            translate(r"\\?\C:\Users\Ada").as_deref(),
+            Some("/mnt/c/Users/Ada")
+        );
+    }
+
+    #[test]
+    fn wsl_share_paths_map_back_to_linux_paths() {
+        assert_eq!(
+            translate(r"\\wsl$\Ubuntu\home\ada\src").as_deref(),
+            Some("/home/ada/src")
+        );
+        assert_eq!(
+            translate(r"\\wsl.localhost\Debian\srv").as_deref(),
+            Some("/srv")
+        );
+    }
+
+    #[test]
+    fn untranslatable_paths_give_none() {
+        assert_eq!(translate(r"\\fileserver\team\docs"), None);
+        assert_eq!(translate(r"relative\path"), None);
+        assert_eq!(translate(""), None);
+    }
+
+    #[test]
+    fn posix_paths_pass_through() {
+        assert_eq!(translate("/home/ada").as_deref(), Some("/home/ada"));
+    }
+
+    #[test]
+    fn backend_path_keeps_unmapped_folders_unchanged() {
+        let target = WslTarget {
+            distro: None,
+            automount_root: DEFAULT_AUTOMOUNT_ROOT.to_string(),
+        };
+        assert_eq!(backend_path(r"C:\src", Some(&target)), "/mnt/c/src");
+        assert_eq!(backend_path(r"\\nas\share", Some(&target)), r"\\nas\share");
+        assert_eq!(backend_path(r"C:\src", None), r"C:\src");
+    }
+
+    #[test]
+    fn forwarded_env_marks_paths_and_skips_windows_variables() {
+        let mut command = Command::new("wsl.exe");
+        command
+            .env_clear()
+            .env("PATH", r"C:\Windows")
+            .env("SYSTEMROOT", r"C:\Windows")
+            .env("LITECLAW_AUTH_TOKEN", "t")
+            .env("LITECLAW_DATA_DIR", r"C:\data")
+            .env("HTTPS_PROXY", "http://proxy:3128");
+        forward_env(&mut command);
+        let wslenv = command
+            .get_envs()
+            .find(|(key, _)| *key == "WSLENV")
+            .and_then(|(_, value)| value)
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        let mut names: Vec<&str> = wslenv.split(':').collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            ["HTTPS_PROXY", "LITECLAW_AUTH_TOKEN", "LITECLAW_DATA_DIR/p"]
+        );
+    }
+}
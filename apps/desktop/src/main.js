@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { open } from "@tauri-apps/plugin-dialog";
 
 const statusBadge = document.getElementById("status-badge");
@@ -21,11 +22,16 @@ const addFolderButton = document.getElementById("add-folder-btn");
 const shellEnabledCheckbox = document.getElementById("shell-enabled-checkbox");
 const allowedFoldersList = document.getElementById("allowed-folders-list");
 const noFoldersBanner = document.getElementById("no-folders-banner");
+const appBanner = document.getElementById("app-banner");
+const appBannerText = document.getElementById("app-banner-text");
+const appBannerDismissButton = document.getElementById("app-banner-dismiss-btn");
 const bannerAddFolderButton = document.getElementById("banner-add-folder-btn");
 const modelsList = document.getElementById("models-list");
 const modelsEmpty = document.getElementById("models-empty");
 const downloadModelButton = document.getElementById("download-model-btn");
 const registerModelButton = document.getElementById("register-model-btn");
+const activityList = document.getElementById("activity-list");
+const activityEmpty = document.getElementById("activity-empty");
 
 const tasksList = document.getElementById("tasks-list");
 const selectedTraceOutput = document.getElementById("selected-trace-output");
@@ -50,6 +56,8 @@ const doctorExportMdButton = document.getElementById("doctor-export-md-btn");
 const doctorOutput = document.getElementById("doctor-output");
 
 let apiConfig = null;
+// The conversation prompts belong to, once a session file names one.
+let conversationId = null;
 let localConfig = { allowed_folders: [], shell: { enabled: false } };
 let modelsState = { installed_models: [], default_model_id: null };
 let advancedMode = false;
@@ -57,6 +65,14 @@ let selectedTask = null;
 let selectedTaskTrace = null;
 let logsIntervalId = null;
 let latestDoctor = null;
+let dialogAvailable = true;
+
+function errorText(err) {
+  if (err && typeof err === "object" && "message" in err) {
+    return err.message;
+  }
+  return String(err);
+}
 
 function renderJson(el, data) {
   el.textContent = JSON.stringify(data, null, 2);
@@ -108,6 +124,31 @@ async function api(path, method = "GET", body = null) {
   return response.json();
 }
 
+// Turns the diff returned by config commands into "removed 2 folders, enabled shell".
+function describeConfigDiff(diff) {
+  const parts = [];
+  const folders = { added: 0, removed: 0 };
+  for (const change of diff?.changes ?? []) {
+    const folder = change.path.match(/^allowed_folders\[[^\]]*\]$/);
+    if (folder && change.kind in folders) {
+      folders[change.kind] += 1;
+    } else if (typeof change.new === "boolean") {
+      parts.push(`${change.new ? "enabled" : "disabled"} ${change.path.replace(/\.enabled$/, "")}`);
+    } else {
+      parts.push(`changed ${change.path}`);
+    }
+  }
+  for (const [kind, count] of Object.entries(folders)) {
+    if (count) parts.unshift(`${kind} ${count} folder${count === 1 ? "" : "s"}`);
+  }
+  return parts.join(", ");
+}
+
+function showConfigDiff(diff) {
+  const summary = describeConfigDiff(diff);
+  if (summary) traceOutput.textContent = `Settings updated: ${summary}`;
+}
+
 function setBackendReadyUI(ready, message = "") {
   runButton.disabled = !ready;
   if (ready) {
@@ -123,12 +164,19 @@ function setBackendReadyUI(ready, message = "") {
   }
 }
 
+// The backend's own traceback usually says more than how the start failed.
+function lastErrorText(config, fallback = "") {
+  const message = config.last_error || fallback;
+  const hint = config.failure_hint;
+  return hint ? `${message} ${hint.summary}`.trim() : message;
+}
+
 async function fetchBackendLogs() {
   try {
     const logs = await invoke("read_backend_logs", { lines: 200 });
-    backendLogsOutput.textContent = logs || "(no logs)";
+    backendLogsOutput.textContent = logs.exists ? logs.content || "(empty log)" : "(no logs yet)";
   } catch (err) {
-    backendLogsOutput.textContent = String(err);
+    backendLogsOutput.textContent = errorText(err);
   }
 }
 
@@ -145,15 +193,22 @@ function renderAllowedFolders() {
     const li = document.createElement("li");
     const span = document.createElement("span");
     span.className = "folder-path";
-    span.textContent = folder;
+    span.textContent = folder.alias
+      ? `${folder.alias} (${folder.path})`
+      : folder.path;
+    if (folder.note) span.title = folder.note;
     const removeButton = document.createElement("button");
     removeButton.textContent = "Remove";
     removeButton.addEventListener("click", async () => {
       try {
-        localConfig = await invoke("remove_allowed_folder", { path: folder });
+        const result = await invoke("remove_allowed_folder", {
+          path: folder.path,
+        });
+        localConfig = result.config;
         renderAllowedFolders();
+        showConfigDiff(result.diff);
       } catch (err) {
-        traceOutput.textContent = String(err);
+        traceOutput.textContent = errorText(err);
       }
     });
     li.appendChild(span);
@@ -168,6 +223,34 @@ async function refreshLocalConfig() {
   renderAllowedFolders();
 }
 
+function describeProgress(progress) {
+  if (!progress) return "";
+  if (!progress.total) return ` (${progress.done})`;
+  return ` (${Math.floor((progress.done / progress.total) * 100)}%)`;
+}
+
+async function refreshActivity() {
+  const tasks = await invoke("list_tasks");
+  activityEmpty.classList.toggle("hidden", tasks.length > 0);
+  activityList.innerHTML = "";
+  for (const task of tasks) {
+    const li = document.createElement("li");
+    const span = document.createElement("span");
+    span.className = "folder-path";
+    span.textContent = `${task.description}${describeProgress(task.progress)}`;
+    const cancelButton = document.createElement("button");
+    cancelButton.textContent = task.cancelling ? "Cancelling…" : "Cancel";
+    cancelButton.disabled = task.cancelling;
+    cancelButton.addEventListener("click", async () => {
+      await invoke("cancel_task", { id: task.id });
+      await refreshActivity();
+    });
+    li.appendChild(span);
+    li.appendChild(cancelButton);
+    activityList.appendChild(li);
+  }
+}
+
 function renderModels() {
   modelsList.innerHTML = "";
   const installed = modelsState.installed_models || [];
@@ -189,7 +272,7 @@ function renderModels() {
           });
           renderModels();
         } catch (err) {
-          traceOutput.textContent = String(err);
+          traceOutput.textContent = errorText(err);
         }
       });
       li.appendChild(button);
@@ -203,16 +286,57 @@ async function refreshModels() {
   renderModels();
 }
 
+function showBanner(text) {
+  appBannerText.textContent = text;
+  appBanner.classList.remove("hidden");
+}
+
+// Native picker when it works, a typed path when it does not.
+async function pickPath(directory) {
+  if (dialogAvailable) {
+    try {
+      return await open({ directory, multiple: false });
+    } catch (err) {
+      dialogAvailable = false;
+    }
+  }
+  const typed = window.prompt(directory ? "Folder path" : "File path", "");
+  return typed ? typed.trim() : null;
+}
+
+// Where an added folder lives, when that is worth knowing; see
+// `folder_location`.
+const LOCATION_WARNINGS = {
+  read_only_mount: "It is on a read-only mount, so nothing in it can be changed.",
+  fuse: "It is on a FUSE filesystem, which can be slow to read.",
+};
+
 async function addFolderFlow() {
   try {
-    const selected = await open({ directory: true, multiple: false });
+    const selected = await pickPath(true);
     if (!selected || typeof selected !== "string") return;
-    localConfig = await invoke("add_allowed_folder", { path: selected });
+    let result;
+    try {
+      result = await invoke("add_allowed_folder", { path: selected });
+    } catch (err) {
+      const confirmed =
+        err?.code === "filesystem_root" &&
+        window.confirm(`${err.message}. Allow the whole filesystem anyway?`);
+      if (!confirmed) throw err;
+      result = await invoke("add_allowed_folder", { path: selected, allowRoot: true });
+    }
+    localConfig = result.config;
     renderAllowedFolders();
-    traceOutput.textContent = "Folder added.";
+    traceOutput.textContent =
+      result.symlink?.behavior === "followed"
+        ? `Folder added (symlink resolved to ${result.symlink.target}).`
+        : "Folder added.";
+    if (result.size_warning) traceOutput.textContent += ` ${result.size_warning}.`;
+    const locationWarning = LOCATION_WARNINGS[result.location_kind];
+    if (locationWarning) traceOutput.textContent += ` ${locationWarning}`;
     noFoldersBanner.classList.add("hidden");
   } catch (err) {
-    traceOutput.textContent = String(err);
+    traceOutput.textContent = errorText(err);
   }
 }
 
@@ -277,14 +401,203 @@ async function runDoctor() {
   renderJson(doctorOutput, latestDoctor);
 }
 
+async function importSessionFile(opened) {
+  try {
+    await api("/v1/conversations/import", "POST", opened.session.conversation);
+    conversationId = opened.session.conversation.id ?? conversationId;
+    traceOutput.textContent = `Imported session from ${opened.path}`;
+  } catch (err) {
+    traceOutput.textContent = `Could not import ${opened.path}: ${errorText(err)}`;
+  }
+}
+
+function showSessionFileError(error) {
+  traceOutput.textContent = `Could not open ${error.path}: ${error.message}`;
+}
+
+listen("open-session-file", (event) => importSessionFile(event.payload));
+listen("open-session-file-error", (event) => showSessionFileError(event.payload));
+
+async function confirmDeepLink(pending) {
+  if (pending.action !== "add_folder") return;
+  const approved = window.confirm(
+    `A link asked LiteClaw to allow access to:\n${pending.path}\n\nAllow this folder?`,
+  );
+  try {
+    const result = await invoke("resolve_deeplink", { id: pending.id, approved });
+    if (result) {
+      localConfig = result.config;
+      renderAllowedFolders();
+    }
+  } catch (err) {
+    traceOutput.textContent = errorText(err);
+  }
+}
+
+function handleDeepLinkAction(action) {
+  if (action.action === "ask") promptInput.value = action.text;
+}
+
+listen("deeplink-confirm-requested", (event) => confirmDeepLink(event.payload));
+listen("deeplink-ask", (event) => handleDeepLinkAction(event.payload));
+listen("deeplink-rejected", (event) => {
+  traceOutput.textContent = `Ignored link: ${event.payload.reason}`;
+});
+listen("native-messaging-ask", (event) => {
+  promptInput.value = event.payload.text;
+  promptInput.focus();
+});
+listen("native-messaging-attachment", (event) => {
+  traceOutput.textContent = `Browser selection staged as ${event.payload.file_name}`;
+});
+
+async function offerHungRestart(hung) {
+  const endpoints = hung.timed_out.map((record) => record.endpoint).join(", ");
+  if (hung.auto_restart) {
+    traceOutput.textContent = `Backend stopped responding (${endpoints}); restarting it.`;
+    return;
+  }
+  const restart = window.confirm(
+    `The backend is running but requests keep timing out:\n${endpoints}\n\nRestart it now?`,
+  );
+  if (restart) retryButton.click();
+}
+
+listen("backend-hung", (event) => offerHungRestart(event.payload));
+listen("backend-startup-progress", (event) => {
+  setBackendReadyUI(false, startupPhaseText(event.payload));
+});
+listen("backend-status-changed", (event) => {
+  apiConfig = { ...apiConfig, ...event.payload };
+  if (apiConfig.crash_loop) setBackendReadyUI(false, lastErrorText(apiConfig));
+  else if (apiConfig.backend_ready) setBackendReadyUI(true);
+});
+listen("connection-info-changed", (event) => {
+  apiConfig = { ...apiConfig, ...event.payload };
+});
+listen("bootstrap", (event) => {
+  const { api_config, local_config, integrations, lifecycle } = event.payload;
+  apiConfig = api_config;
+  localConfig = local_config;
+  if (integrations) dialogAvailable = integrations.dialog.available;
+  if (lifecycle.state === "spawning") {
+    setBackendReadyUI(
+      false,
+      lifecycle.startup_phase ? startupPhaseText(lifecycle.startup_phase) : "Backend is starting...",
+    );
+  } else if (lifecycle.state === "ready") {
+    setBackendReadyUI(true);
+  }
+});
+function showMigrations(migrations) {
+  const seen = new Set(JSON.parse(localStorage.getItem("seenMigrations") || "[]"));
+  const unseen = migrations.filter((migration) => !seen.has(migration.id));
+  if (unseen.length === 0) return;
+  window.alert(`What changed in your setup:\n\n${unseen.map((m) => m.description).join("\n")}`);
+  for (const migration of unseen) seen.add(migration.id);
+  localStorage.setItem("seenMigrations", JSON.stringify([...seen]));
+}
+
+listen("migrations-applied", (event) => showMigrations(event.payload));
+listen("app-self-check", (event) => {
+  const failed = event.payload.checks.filter((check) => check.status === "fail");
+  if (failed.length === 0) return;
+  showBanner(`Startup checks failed: ${failed.map((c) => `${c.name} (${c.detail})`).join("; ")}`);
+});
+listen("shutdown-progress", (event) => {
+  const { index, total } = event.payload;
+  setBackendReadyUI(false, `Shutting down (${index + 1}/${total})…`);
+});
+// Debug builds only.
+listen("slow-command", (event) => {
+  const { command, duration_ms, args } = event.payload;
+  console.warn(`slow command ${command}: ${duration_ms} ms (${args})`);
+});
+listen("storage-unavailable", (event) => {
+  traceOutput.textContent = `Data folder ${event.payload} is unavailable; changes are kept until it returns.`;
+});
+listen("storage-restored", (event) => {
+  const flushed = event.payload.flushed_mutations;
+  traceOutput.textContent = `Data folder is back; saved ${flushed} pending change(s).`;
+});
+
+listen("profile-switching", (event) => {
+  setBackendReadyUI(false, `Switching to profile ${event.payload.to}...`);
+});
+listen("profile-changed", (event) => {
+  if (apiConfig) apiConfig.profile = event.payload.profile;
+});
+listen("profile-switched", (event) => {
+  traceOutput.textContent = `Switched from profile ${event.payload.from} to ${event.payload.to}.`;
+});
+listen("pending-action-completed", (event) => {
+  const { action, ok, error } = event.payload;
+  traceOutput.textContent = ok
+    ? `Queued ${action} completed.`
+    : `Queued ${action} failed: ${error}`;
+});
+listen("backend-update-progress", (event) => {
+  const { version, bytes_received, total_bytes } = event.payload;
+  const of = total_bytes ? ` of ${total_bytes}` : "";
+  traceOutput.textContent = `Downloading backend ${version}: ${bytes_received}${of} bytes`;
+});
+
+function showOnboarding(onboarding) {
+  if (onboarding.completed) return;
+  const remaining = ["folders", "shell", "api_key"].filter(
+    (step) => !onboarding.completed_steps.includes(step),
+  );
+  traceOutput.textContent = `Finish setting up LiteClaw: ${remaining.join(", ")}`;
+}
+
+listen("app-banner", (event) => {
+  showBanner(`${event.payload.title}: ${event.payload.body}`);
+});
+listen("integrations-limited", (event) => {
+  const missing = event.payload.missing;
+  if (missing.some((item) => item.name === "dialog")) dialogAvailable = false;
+  const lines = missing.map((item) => `${item.name} (${item.reason}): ${item.fallback}`);
+  showBanner(`Some desktop integrations are unavailable. ${lines.join("; ")}`);
+});
+listen("onboarding-required", (event) => showOnboarding(event.payload));
+listen("read-only-mode", async (event) => {
+  showBanner(event.payload.message);
+  try {
+    const pending = await invoke("force_takeover");
+    const question = `${event.payload.message}\n\n${pending.summary.description}\n\nTake over now?`;
+    if (!window.confirm(question)) return;
+    apiConfig = await invoke("force_takeover", { confirmToken: pending.confirm_token });
+    appBanner.classList.add("hidden");
+  } catch (err) {
+    showBanner(errorText(err));
+  }
+});
+
+function startupPhaseText({ label, pct }) {
+  return pct == null ? `${label}...` : `${label} (${pct}%)`;
+}
+
 async function init() {
   try {
-    apiConfig = await invoke("get_api_config");
+    apiConfig = {
+      ...(await invoke("get_connection_info")),
+      ...(await invoke("get_backend_status")),
+    };
     await refreshLocalConfig();
+    showOnboarding(await invoke("get_onboarding_state"));
+    const { integrations } = await invoke("get_app_info");
+    dialogAvailable = integrations.dialog.available;
+    const links = await invoke("take_pending_deeplinks");
+    links.actions.forEach(handleDeepLinkAction);
+    for (const pending of links.confirmations) await confirmDeepLink(pending);
     if (!apiConfig.backend_ready) {
       setBackendReadyUI(
         false,
-        apiConfig.last_error || "Backend failed to start.",
+        apiConfig.backend_state === "stopped_by_user"
+          ? "Backend is stopped. Press Retry to start it."
+          : apiConfig.backend_state === "starting" && apiConfig.startup_phase
+            ? startupPhaseText(apiConfig.startup_phase)
+            : lastErrorText(apiConfig, "Backend failed to start."),
       );
       return;
     }
@@ -294,9 +607,12 @@ async function init() {
     await refreshLogsTail();
     await runDoctor();
     setBackendReadyUI(true);
+    const pending = await invoke("take_pending_session_files");
+    pending.errors.forEach(showSessionFileError);
+    for (const opened of pending.sessions) await importSessionFile(opened);
   } catch (err) {
-    setBackendReadyUI(false, String(err));
-    traceOutput.textContent = String(err);
+    setBackendReadyUI(false, errorText(err));
+    traceOutput.textContent = errorText(err);
   }
 }
 
@@ -323,19 +639,32 @@ retryButton.addEventListener("click", async () => {
   try {
     apiConfig = await invoke("retry_backend");
     if (!apiConfig.backend_ready) {
-      setBackendReadyUI(false, apiConfig.last_error || "Retry failed.");
+      setBackendReadyUI(false, lastErrorText(apiConfig, "Retry failed."));
       return;
     }
     await api("/v1/health");
     setBackendReadyUI(true);
   } catch (err) {
-    setBackendReadyUI(false, String(err));
-    traceOutput.textContent = String(err);
+    setBackendReadyUI(false, errorText(err));
+    traceOutput.textContent = errorText(err);
   }
 });
 
 showLogsButton.addEventListener("click", fetchBackendLogs);
 
+// The current conversation's working folder, or null. A folder removed since
+// it was bound is reported and the prompt runs unscoped.
+async function conversationFolder() {
+  if (!conversationId) return null;
+  try {
+    return await invoke("get_conversation_folder", { conversationId });
+  } catch (err) {
+    if (err?.code !== "folder_missing") throw err;
+    traceOutput.textContent = `${errorText(err)}; running without a working folder.`;
+    return null;
+  }
+}
+
 runButton.addEventListener("click", async () => {
   traceOutput.textContent = "";
   backendLogsOutput.textContent = "";
@@ -345,6 +674,8 @@ runButton.addEventListener("click", async () => {
       prompt: promptInput.value,
       allowed_folders: localConfig.allowed_folders || [],
       dry_run: true,
+      conversation_id: conversationId,
+      working_folder: await conversationFolder(),
     });
     renderJson(planOutput, plan);
 
@@ -366,6 +697,24 @@ runButton.addEventListener("click", async () => {
     }
     noFoldersBanner.classList.add("hidden");
 
+    if (firstStep && firstStep.agent === "shell") {
+      const verdict = await invoke("request_shell_approval", {
+        commandLine: String(firstStep.inputs?.command ?? ""),
+        cwd: firstStep.inputs?.cwd ?? null,
+      });
+      if (verdict.bucket === "deny") {
+        traceOutput.textContent = `Blocked by shell policy: ${verdict.rule.pattern}`;
+        return;
+      }
+      if (
+        verdict.bucket === "ask" &&
+        !window.confirm(`Run this shell command?\n\n${firstStep.inputs?.command}`)
+      ) {
+        traceOutput.textContent = "Shell command not approved.";
+        return;
+      }
+    }
+
     const approval = await api("/v1/approvals/issue-token", "POST", {
       plan_id: plan.plan_id,
     });
@@ -377,7 +726,7 @@ runButton.addEventListener("click", async () => {
     renderJson(traceOutput, trace);
     await refreshTasks();
   } catch (err) {
-    traceOutput.textContent = String(err);
+    traceOutput.textContent = errorText(err);
   }
 });
 
@@ -391,13 +740,13 @@ downloadModelButton.addEventListener("click", async () => {
     });
     renderModels();
   } catch (err) {
-    traceOutput.textContent = String(err);
+    traceOutput.textContent = errorText(err);
   }
 });
 
 registerModelButton.addEventListener("click", async () => {
   try {
-    const selected = await open({ directory: false, multiple: false });
+    const selected = await pickPath(false);
     if (!selected || typeof selected !== "string") return;
     const id = window.prompt("Model ID", "local-gguf");
     if (!id) return;
@@ -408,20 +757,23 @@ registerModelButton.addEventListener("click", async () => {
     });
     renderModels();
   } catch (err) {
-    traceOutput.textContent = String(err);
+    traceOutput.textContent = errorText(err);
   }
 });
 
+appBannerDismissButton.addEventListener("click", () => appBanner.classList.add("hidden"));
 addFolderButton.addEventListener("click", addFolderFlow);
 bannerAddFolderButton.addEventListener("click", addFolderFlow);
 shellEnabledCheckbox.addEventListener("change", async () => {
   try {
-    localConfig = await invoke("set_shell_enabled", {
+    const { diff, ...config } = await invoke("set_shell_enabled", {
       enabled: shellEnabledCheckbox.checked,
     });
+    localConfig = config;
     shellEnabledCheckbox.checked = !!localConfig.shell?.enabled;
+    showConfigDiff(diff);
   } catch (err) {
-    traceOutput.textContent = String(err);
+    traceOutput.textContent = errorText(err);
     shellEnabledCheckbox.checked = !!localConfig.shell?.enabled;
   }
 });
@@ -460,7 +812,7 @@ logsAutoRefreshCheckbox.addEventListener("change", () => {
     if (logsIntervalId) clearInterval(logsIntervalId);
     logsIntervalId = setInterval(() => {
       refreshLogsTail().catch((err) => {
-        logsOutput.textContent = String(err);
+        logsOutput.textContent = errorText(err);
       });
     }, 2000);
   } else if (logsIntervalId) {
@@ -502,6 +854,10 @@ doctorExportMdButton.addEventListener("click", async () => {
   downloadTextFile(payload.file_name, payload.content, "text/markdown");
 });
 
+setInterval(() => {
+  refreshActivity().catch(() => {});
+}, 1000);
+
 setMode(false);
 setActiveAdvancedTab("tasks");
 init();